@@ -0,0 +1,12 @@
+//! Builds `libno_proto.so`/`libno_proto.a` with the C API from `no_proto::ffi`, the functions are declared in `include/no_proto.h`.
+//! 
+//! `no_proto` is `no_std`, this crate links the standard library to provide the allocator and panic handling a C library needs.
+
+pub use no_proto::ffi::*;
+
+// the linker drops the `no_proto::ffi` functions from the shared library unless something here uses them
+struct Exports(unsafe extern "C" fn(*const u8) -> *mut no_proto::NP_Factory);
+unsafe impl Sync for Exports {}
+
+#[used]
+static EXPORTS: Exports = Exports(np_factory_new);
@@ -0,0 +1,42 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use no_proto::NP_Factory;
+
+const SCHEMA: &str = r#"{
+    "type": "table",
+    "columns": [
+        ["string",  {"type": "string"}],
+        ["fixed",   {"type": "string", "size": 10}],
+        ["bytes",   {"type": "bytes"}],
+        ["int",     {"type": "i32"}],
+        ["float",   {"type": "f64"}],
+        ["dec",     {"type": "decimal", "exp": 2}],
+        ["bool",    {"type": "bool"}],
+        ["geo",     {"type": "geo8"}],
+        ["uuid",    {"type": "uuid"}],
+        ["ulid",    {"type": "ulid"}],
+        ["date",    {"type": "date"}],
+        ["option",  {"type": "option", "choices": ["red", "blue"]}],
+        ["list",    {"type": "list", "of": {"type": "string"}}],
+        ["map",     {"type": "map", "value": {"type": "u8"}}],
+        ["tuple",   {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}],
+        ["table",   {"type": "table", "columns": [["inner", {"type": "u16"}]]}]
+    ]
+}"#;
+
+
+fuzz_target!(|data: &[u8]| {
+    let factory = NP_Factory::new(SCHEMA).unwrap();
+
+    // generated buffers must always be valid
+    let buffer = factory.arbitrary_buffer(data).unwrap();
+    let json = buffer.json_encode(&[]).unwrap().stringify();
+
+    let mut checked = factory.open_checked_buffer(buffer.close()).unwrap();
+    assert_eq!(checked.json_encode(&[]).unwrap().stringify(), json);
+
+    // compaction must land on the predicted size
+    let size = checked.calc_bytes().unwrap();
+    checked.compact(None).unwrap();
+    assert_eq!(checked.calc_bytes().unwrap().current_buffer, size.after_compaction);
+});
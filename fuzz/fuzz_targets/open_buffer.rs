@@ -0,0 +1,46 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use no_proto::NP_Factory;
+
+const SCHEMA: &str = r#"{
+    "type": "table",
+    "columns": [
+        ["string",  {"type": "string"}],
+        ["fixed",   {"type": "string", "size": 10}],
+        ["bytes",   {"type": "bytes"}],
+        ["int",     {"type": "i32"}],
+        ["float",   {"type": "f64"}],
+        ["dec",     {"type": "decimal", "exp": 2}],
+        ["bool",    {"type": "bool"}],
+        ["geo",     {"type": "geo8"}],
+        ["uuid",    {"type": "uuid"}],
+        ["ulid",    {"type": "ulid"}],
+        ["date",    {"type": "date"}],
+        ["option",  {"type": "option", "choices": ["red", "blue"]}],
+        ["list",    {"type": "list", "of": {"type": "string"}}],
+        ["map",     {"type": "map", "value": {"type": "u8"}}],
+        ["tuple",   {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}],
+        ["table",   {"type": "table", "columns": [["inner", {"type": "u16"}]]}]
+    ]
+}"#;
+
+fuzz_target!(|data: &[u8]| {
+    let factory = NP_Factory::new(SCHEMA).unwrap();
+
+    // buffers that pass the checks must be safe to use
+    if let Ok(mut buffer) = factory.open_checked_buffer(data.to_vec()) {
+        let _ = buffer.json_encode(&[]);
+        let _ = buffer.get::<&str>(&["string"]);
+        let _ = buffer.get::<&str>(&["list", "3"]);
+        let _ = buffer.get::<u8>(&["map", "key"]);
+        let _ = buffer.calc_bytes();
+        let _ = buffer.set(&["list", "10"], "hello");
+        let _ = buffer.compact(None);
+    }
+
+    // and lenient buffers must always be safe to use
+    let (mut buffer, _report) = factory.open_lenient(data.to_vec());
+    let _ = buffer.json_encode(&[]);
+    let _ = buffer.calc_bytes();
+    let _ = buffer.compact(None);
+});
@@ -0,0 +1,51 @@
+/* C API for NoProto, build the library with the `ffi` feature enabled */
+
+#ifndef NO_PROTO_H
+#define NO_PROTO_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define NP_OK 0
+#define NP_UNSET 1
+#define NP_FAILED -1
+
+typedef struct np_factory np_factory;
+typedef struct np_buffer np_buffer;
+
+np_factory* np_factory_new(const char* schema);
+void np_factory_free(np_factory* factory);
+
+np_buffer* np_buffer_empty(const np_factory* factory);
+np_buffer* np_buffer_open(const np_factory* factory, const uint8_t* bytes, size_t len);
+void np_buffer_free(np_buffer* buffer);
+
+const uint8_t* np_buffer_bytes(const np_buffer* buffer, size_t* len);
+int32_t np_buffer_compact(np_buffer* buffer);
+int32_t np_buffer_del(np_buffer* buffer, const char* const* path, size_t path_len);
+
+int32_t np_buffer_set_json(np_buffer* buffer, const char* const* path, size_t path_len, const char* json);
+char* np_buffer_json(np_buffer* buffer, const char* const* path, size_t path_len);
+
+int32_t np_buffer_set_i64(np_buffer* buffer, const char* const* path, size_t path_len, int64_t value);
+int32_t np_buffer_set_f64(np_buffer* buffer, const char* const* path, size_t path_len, double value);
+int32_t np_buffer_set_bool(np_buffer* buffer, const char* const* path, size_t path_len, bool value);
+int32_t np_buffer_set_string(np_buffer* buffer, const char* const* path, size_t path_len, const char* value);
+
+int32_t np_buffer_get_i64(np_buffer* buffer, const char* const* path, size_t path_len, int64_t* out);
+int32_t np_buffer_get_f64(np_buffer* buffer, const char* const* path, size_t path_len, double* out);
+int32_t np_buffer_get_bool(np_buffer* buffer, const char* const* path, size_t path_len, bool* out);
+int32_t np_buffer_get_string(np_buffer* buffer, const char* const* path, size_t path_len, char** out);
+
+void np_string_free(char* text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
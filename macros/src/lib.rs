@@ -0,0 +1,82 @@
+//! Compile time schemas for NoProto
+//!
+//! `np_schema!` takes a JSON schema, validates it while your crate is being built and expands to the compiled schema bytes.  An invalid schema fails the build with the same message `NP_Factory::new` would return, and the factory is created from bytes at runtime so there's no JSON parsing on startup.
+//!
+//! ```
+//! use no_proto::NP_Factory;
+//! use no_proto_macros::np_schema;
+//!
+//! static USER_SCHEMA: &[u8] = np_schema!({
+//!     "type": "table",
+//!     "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8", "default": 18}]
+//!     ]
+//! });
+//!
+//! let factory = NP_Factory::new_compiled(USER_SCHEMA.to_vec());
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Billy")?;
+//!
+//! assert_eq!(new_buffer.get::<&str>(&["name"])?, Some("Billy"));
+//! assert_eq!(new_buffer.get::<u8>(&["age"])?, Some(18));
+//!
+//! # Ok::<(), no_proto::error::NP_Error>(())
+//! ```
+//!
+//! Schemas that don't parse are rejected by the compiler:
+//!
+//! ```compile_fail
+//! use no_proto_macros::np_schema;
+//!
+//! static BAD_SCHEMA: &[u8] = np_schema!({"type": "not a type"});
+//! ```
+#![warn(missing_docs)]
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+use no_proto::NP_Factory;
+
+/// Validate and compile a JSON schema into a `&'static [u8]` for `NP_Factory::new_compiled`
+///
+/// The schema is written inline as JSON, the macro fails to compile if the schema is invalid.
+#[proc_macro]
+pub fn np_schema(input: TokenStream) -> TokenStream {
+
+    let mut json = String::new();
+    write_json(input, &mut json);
+
+    let expanded = match NP_Factory::new(&json) {
+        Ok(factory) => {
+            let bytes: Vec<String> = factory.compile_schema().iter().map(|byte| format!("{}u8", byte)).collect();
+            format!("&[{}]", bytes.join(", "))
+        },
+        Err(e) => format!("compile_error!({:?})", format!("Invalid NoProto schema: {}", e))
+    };
+
+    expanded.parse().unwrap_or_else(|_| TokenStream::new())
+}
+
+// JSON tokenizes cleanly as rust tokens, write them back out without the spacing rust adds (so `-1` doesn't become `- 1`)
+fn write_json(input: TokenStream, json: &mut String) {
+    for token in input {
+        match token {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::None => ("", "")
+                };
+                json.push_str(open);
+                write_json(group.stream(), json);
+                json.push_str(close);
+            },
+            TokenTree::Punct(punct) => json.push(punct.as_char()),
+            TokenTree::Ident(ident) => json.push_str(&ident.to_string()),
+            TokenTree::Literal(literal) => json.push_str(&literal.to_string())
+        }
+    }
+}
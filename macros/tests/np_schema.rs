@@ -0,0 +1,44 @@
+use no_proto::NP_Factory;
+use no_proto::error::NP_Error;
+use no_proto_macros::np_schema;
+
+static SCHEMA: &[u8] = np_schema!({
+    "type": "table",
+    "columns": [
+        ["id",     {"type": "uuid"}],
+        ["offset", {"type": "i32", "default": -20}],
+        ["tags",   {"type": "list", "of": {"type": "string"}}],
+        ["kind",   {"type": "option", "choices": ["red", "blue"], "default": "blue"}],
+        ["active", {"type": "bool", "default": true}]
+    ]
+});
+
+#[test]
+fn np_schema_matches_factory() -> Result<(), NP_Error> {
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["id",     {"type": "uuid"}],
+            ["offset", {"type": "i32", "default": -20}],
+            ["tags",   {"type": "list", "of": {"type": "string"}}],
+            ["kind",   {"type": "option", "choices": ["red", "blue"], "default": "blue"}],
+            ["active", {"type": "bool", "default": true}]
+        ]
+    }"#)?;
+
+    assert_eq!(SCHEMA, &factory.compile_schema()[..]);
+
+    let compiled = NP_Factory::new_compiled(SCHEMA.to_vec());
+    assert_eq!(compiled.compile_schema(), factory.compile_schema());
+    assert_eq!(compiled.export_schema()?.stringify(), factory.export_schema()?.stringify());
+
+    for factory in [&compiled, &factory].iter() {
+        let mut buffer = factory.empty_buffer(None);
+        buffer.set(&["tags", "0"], "first")?;
+        assert_eq!(buffer.get::<&str>(&["tags", "0"])?, Some("first"));
+        assert_eq!(buffer.get::<i32>(&["offset"])?, Some(-20));
+        assert_eq!(buffer.get::<bool>(&["active"])?, Some(true));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,246 @@
+//! Python bindings for NoProto
+//! 
+//! Build with `maturin develop` (or `maturin build --release`) from this folder.  Values move between Python and the buffer directly, without going through JSON strings.
+//! 
+//! ```python
+//! import no_proto
+//! 
+//! factory = no_proto.Factory("""{
+//!     "type": "table",
+//!     "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }""")
+//! 
+//! buffer = factory.empty_buffer()
+//! buffer["name"] = "Jeb Kermin"
+//! buffer["tags"] = ["pilot"]
+//! 
+//! assert buffer["tags", 0] == "pilot"
+//! assert buffer.get() == {"name": "Jeb Kermin", "tags": ["pilot"]}
+//! 
+//! data = buffer.to_bytes()
+//! assert factory.open_buffer(data)["name"] == "Jeb Kermin"
+//! ```
+//! 
+//! Paths are a single key, or a tuple of keys for nested values.  List and tuple indexes can be numbers or strings.
+//! 
+//! Values use the same layout as `json_encode`, so `bytes` columns are read as lists of numbers and decimals as `{"num": .., "exp": ..}` dictionaries.
+//! 
+
+use std::sync::Arc;
+use pyo3::prelude::*;
+use pyo3::PyMappingProtocol;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::types::{PyBool, PyBytes, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple};
+use no_proto::NP_Factory;
+use no_proto::buffer::NP_Buffer;
+use no_proto::error::NP_Error;
+use no_proto::json_flex::{NP_JSON, JSMAP};
+
+fn py_error(error: NP_Error) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+fn py_to_json(value: &PyAny) -> PyResult<NP_JSON> {
+    if value.is_none() {
+        return Ok(NP_JSON::Null);
+    }
+    // bool is a subclass of int in Python, check it first
+    if let Ok(x) = value.downcast::<PyBool>() {
+        return Ok(if x.is_true() { NP_JSON::True } else { NP_JSON::False });
+    }
+    if let Ok(x) = value.downcast::<PyLong>() {
+        if let Ok(x) = x.extract::<i64>() {
+            return Ok(NP_JSON::Integer(x));
+        }
+        return Ok(NP_JSON::Unsigned(x.extract::<u64>()?));
+    }
+    if let Ok(x) = value.downcast::<PyFloat>() {
+        return Ok(NP_JSON::Float(x.value()));
+    }
+    if let Ok(x) = value.downcast::<PyString>() {
+        return Ok(NP_JSON::String(x.to_str()?.to_owned()));
+    }
+    if let Ok(x) = value.downcast::<PyBytes>() {
+        return Ok(NP_JSON::Array(x.as_bytes().iter().map(|byte| NP_JSON::Integer(*byte as i64)).collect()));
+    }
+    if let Ok(x) = value.downcast::<PyList>() {
+        return Ok(NP_JSON::Array(x.iter().map(py_to_json).collect::<PyResult<Vec<NP_JSON>>>()?));
+    }
+    if let Ok(x) = value.downcast::<PyTuple>() {
+        return Ok(NP_JSON::Array(x.iter().map(py_to_json).collect::<PyResult<Vec<NP_JSON>>>()?));
+    }
+    if let Ok(x) = value.downcast::<PyDict>() {
+        let mut object = JSMAP::new();
+        for (key, value) in x.iter() {
+            object.insert(key.extract::<String>()?, py_to_json(value)?);
+        }
+        return Ok(NP_JSON::Dictionary(object));
+    }
+    Err(PyTypeError::new_err("Value can't be stored in a buffer!"))
+}
+
+fn json_to_py(py: Python, value: &NP_JSON) -> PyResult<PyObject> {
+    Ok(match value {
+        NP_JSON::Null => py.None(),
+        NP_JSON::True => true.into_py(py),
+        NP_JSON::False => false.into_py(py),
+        NP_JSON::Integer(x) => x.into_py(py),
+        NP_JSON::Unsigned(x) => x.into_py(py),
+        NP_JSON::Float(x) => x.into_py(py),
+        NP_JSON::String(x) => x.into_py(py),
+        NP_JSON::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            list.into()
+        },
+        NP_JSON::Dictionary(object) => {
+            let dict = PyDict::new(py);
+            for (key, value) in object.values.iter() {
+                dict.set_item(key, json_to_py(py, value)?)?;
+            }
+            dict.into()
+        }
+    })
+}
+
+fn py_path(key: Option<&PyAny>) -> PyResult<Vec<String>> {
+    let path_key = |key: &PyAny| -> PyResult<String> {
+        if let Ok(x) = key.downcast::<PyString>() {
+            return Ok(x.to_str()?.to_owned());
+        }
+        if let Ok(x) = key.downcast::<PyLong>() {
+            return Ok(x.extract::<usize>()?.to_string());
+        }
+        Err(PyTypeError::new_err("Path keys must be strings or numbers!"))
+    };
+
+    match key {
+        None => Ok(Vec::new()),
+        Some(key) => {
+            if let Ok(x) = key.downcast::<PyTuple>() {
+                x.iter().map(path_key).collect()
+            } else {
+                Ok(vec![path_key(key)?])
+            }
+        }
+    }
+}
+
+/// Factory built from a JSON schema
+#[pyclass]
+pub struct Factory {
+    factory: Arc<NP_Factory>
+}
+
+#[pymethods]
+impl Factory {
+
+    /// Parse a JSON schema into a new factory
+    #[new]
+    fn new(schema: &str) -> PyResult<Self> {
+        Ok(Factory { factory: Arc::new(NP_Factory::new(schema).map_err(py_error)?) })
+    }
+
+    /// Create a new empty buffer
+    fn empty_buffer(&self) -> Buffer {
+        Buffer {
+            factory: Arc::clone(&self.factory),
+            bytes: self.factory.empty_buffer(None).close()
+        }
+    }
+
+    /// Open existing buffer bytes, the bytes are checked before they're used
+    fn open_buffer(&self, bytes: &[u8]) -> PyResult<Buffer> {
+        let bytes = self.factory.open_checked_buffer(bytes.to_vec()).map_err(py_error)?.close();
+        Ok(Buffer {
+            factory: Arc::clone(&self.factory),
+            bytes
+        })
+    }
+}
+
+/// Buffer holding NoProto bytes for a factory
+#[pyclass]
+pub struct Buffer {
+    factory: Arc<NP_Factory>,
+    bytes: Vec<u8>
+}
+
+impl Buffer {
+    fn with_buffer<T, F>(&mut self, callback: F) -> PyResult<T> where F: FnOnce(&mut NP_Buffer) -> Result<T, NP_Error> {
+        let mut buffer = self.factory.open_buffer(std::mem::take(&mut self.bytes));
+        let result = callback(&mut buffer);
+        self.bytes = buffer.close();
+        result.map_err(py_error)
+    }
+
+    fn get_path(&mut self, py: Python, key: Option<&PyAny>) -> PyResult<PyObject> {
+        let path = py_path(key)?;
+        let path: Vec<&str> = path.iter().map(|key| key.as_str()).collect();
+        let json = self.with_buffer(|buffer| buffer.json_encode(&path))?;
+        json_to_py(py, &json)
+    }
+
+    fn set_path(&mut self, key: Option<&PyAny>, value: &PyAny) -> PyResult<bool> {
+        let path = py_path(key)?;
+        let path: Vec<&str> = path.iter().map(|key| key.as_str()).collect();
+        let json = py_to_json(value)?;
+        self.with_buffer(|buffer| buffer.set_json_value(&path, &json))
+    }
+}
+
+#[pymethods]
+impl Buffer {
+
+    /// Get the value at `path`, or the whole buffer if there is no path
+    #[args(path = "None")]
+    fn get(&mut self, py: Python, path: Option<&PyAny>) -> PyResult<PyObject> {
+        self.get_path(py, path)
+    }
+
+    /// Set the value at `path`, returns false if the path doesn't exist
+    fn set(&mut self, path: Option<&PyAny>, value: &PyAny) -> PyResult<bool> {
+        self.set_path(path, value)
+    }
+
+    /// Remove unused space from the buffer
+    fn compact(&mut self) -> PyResult<()> {
+        self.with_buffer(|buffer| buffer.compact(None))
+    }
+
+    /// Copy of the buffer bytes
+    fn to_bytes(&self, py: Python) -> PyObject {
+        PyBytes::new(py, &self.bytes).into()
+    }
+}
+
+#[pyproto]
+impl PyMappingProtocol for Buffer {
+
+    fn __getitem__(&mut self, key: &PyAny) -> PyResult<PyObject> {
+        self.get_path(key.py(), Some(key))
+    }
+
+    fn __setitem__(&mut self, key: &PyAny, value: &PyAny) -> PyResult<()> {
+        self.set_path(Some(key), value).map(|_| ())
+    }
+
+    fn __delitem__(&mut self, key: &PyAny) -> PyResult<()> {
+        let path = py_path(Some(key))?;
+        let path: Vec<&str> = path.iter().map(|key| key.as_str()).collect();
+        self.with_buffer(|buffer| buffer.del(&path)).map(|_| ())
+    }
+}
+
+/// NoProto buffers for Python
+#[pymodule]
+fn no_proto(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<Factory>()?;
+    module.add_class::<Buffer>()?;
+    Ok(())
+}
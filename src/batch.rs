@@ -0,0 +1,426 @@
+//! Many buffers in one blob
+//!
+//! An `NP_Batch` packs buffers of the same factory into a single blob with an index, so files and messages with millions of records don't need another container format around them.  Buffers can be read back by index without reading the ones before them.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut batch = factory.empty_batch();
+//! for (name, age) in [("Jeb", 30u8), ("Bill", 42u8), ("Bob", 25u8)].iter() {
+//!     let mut user = factory.empty_buffer(None);
+//!     user.set(&["name"], *name)?;
+//!     user.set(&["age"], *age)?;
+//!     batch.append(&user)?;
+//! }
+//!
+//! let bytes: Vec<u8> = batch.close();
+//!
+//! let batch = factory.open_batch(bytes)?;
+//! assert_eq!(batch.len(), 3);
+//! assert_eq!(batch.get(1).unwrap().get::<&str>(&["name"])?, Some("Bill"));
+//!
+//! let ages: Vec<u8> = batch.iter().map(|user| user.get::<u8>(&["age"]).unwrap().unwrap()).collect();
+//! assert_eq!(ages, vec![30, 42, 25]);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! ## Compression
+//!
+//! Buffers are usually small and look a lot like each other, so compressing them one at a time barely helps.  `NP_Batch::compress` builds a dictionary from the bytes that show up in many of the buffers and compresses every buffer against it instead.  Buffers appended later are compressed with the same dictionary, and `get` and `iter` decompress buffers as they're read.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["email",   {"type": "string"}],
+//!         ["country", {"type": "string"}],
+//!         ["visits",  {"type": "u32"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut batch = factory.empty_batch();
+//! for i in 0..100u32 {
+//!     let email = format!("user{}@example.com", i);
+//!     let mut user = factory.empty_buffer(None);
+//!     user.set(&["email"], email.as_str())?;
+//!     user.set(&["country"], "United Kingdom")?;
+//!     user.set(&["visits"], i)?;
+//!     batch.append(&user)?;
+//! }
+//!
+//! let uncompressed = batch.read_len();
+//! batch.compress(1024)?;
+//! assert!(batch.read_len() < uncompressed / 2);
+//!
+//! let batch = factory.open_batch(batch.close())?;
+//! assert_eq!(batch.get(42).unwrap().get::<&str>(&["email"])?, Some("user42@example.com"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! ## Format
+//!
+//! Batches are laid out like this, every number is big endian:
+//!
+//! | Bytes  | Contents                                                      |
+//! |--------|---------------------------------------------------------------|
+//! | 0      | Batch format version, 1 or 2 if the batch is compressed       |
+//! | 1 - 8  | Fingerprint of the schema (u64), see `NP_Schema::fingerprint` |
+//! | 9 - 12 | Number of buffers (u32)                                       |
+//! | ...    | Compressed batches only: dictionary length (u32) and bytes    |
+//! | ...    | Address of each buffer in the data, 4 bytes each (u32)        |
+//! | ...    | Data, each buffer has a 4 byte length (u32) in front of it    |
+//!
+//! The lengths let the data be read in order without the index.
+//!
+
+use crate::buffer::NP_Buffer;
+use crate::dictionary;
+use crate::error::NP_Error;
+use crate::NP_Factory;
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+/// Format version written by `NP_Batch::close`
+pub const BATCH_VERSION: u8 = 1;
+
+/// Format version written by `NP_Batch::close` for compressed batches
+pub const COMPRESSED_BATCH_VERSION: u8 = 2;
+
+// version, schema fingerprint, count
+const HEADER_LEN: usize = 13;
+
+/// Buffers of one factory packed together, see the [batch](index.html) module.
+///
+/// Get one with `NP_Factory::empty_batch` or `NP_Factory::open_batch`.
+///
+#[derive(Debug)]
+pub struct NP_Batch<'batch> {
+    factory: &'batch NP_Factory,
+    // address of each length prefix in `data`
+    index: Vec<u32>,
+    data: Vec<u8>,
+    // set once the batch is compressed, every buffer in `data` is compressed with it
+    dictionary: Option<Vec<u8>>
+}
+
+fn read_u32(bytes: &[u8], address: usize) -> Option<usize> {
+    let mut number = [0u8; 4];
+    number.copy_from_slice(bytes.get(address..address.checked_add(4)?)?);
+    Some(u32::from_be_bytes(number) as usize)
+}
+
+impl<'batch> NP_Batch<'batch> {
+
+    #[doc(hidden)]
+    pub fn _new(factory: &'batch NP_Factory) -> Self {
+        NP_Batch { factory, index: Vec::new(), data: Vec::new(), dictionary: None }
+    }
+
+    /// Read a batch made by `close`, checking the index against the data.
+    pub(crate) fn open(factory: &'batch NP_Factory, bytes: Vec<u8>) -> Result<Self, NP_Error> {
+        if bytes.len() < HEADER_LEN || (bytes[0] != BATCH_VERSION && bytes[0] != COMPRESSED_BATCH_VERSION) {
+            return Err(NP_Error::new_static("Unknown batch format!"));
+        }
+
+        // batches from other schemas won't open
+        if bytes[1..9] != factory.schema.fingerprint.to_be_bytes() {
+            return Err(NP_Error::new_static("Batch was made with a different schema!"));
+        }
+
+        let count = read_u32(&bytes, 9).unwrap_or(0);
+
+        let mut index_start = HEADER_LEN;
+        let dictionary = if bytes[0] == COMPRESSED_BATCH_VERSION {
+            let len = read_u32(&bytes, HEADER_LEN).ok_or_else(|| NP_Error::new_static("Batch dictionary is truncated!"))?;
+            index_start += 4 + len;
+            Some(bytes.get((HEADER_LEN + 4)..index_start).ok_or_else(|| NP_Error::new_static("Batch dictionary is truncated!"))?.to_vec())
+        } else {
+            None
+        };
+
+        let data_start = count.checked_mul(4).and_then(|len| len.checked_add(index_start)).unwrap_or(usize::MAX);
+        if data_start > bytes.len() {
+            return Err(NP_Error::new_static("Batch index is truncated!"));
+        }
+
+        let data = &bytes[data_start..];
+        let mut index: Vec<u32> = Vec::with_capacity(count);
+        for i in 0..count {
+            let address = read_u32(&bytes, index_start + (i * 4)).unwrap_or(0);
+            match read_u32(data, address) {
+                Some(len) if address + 4 + len <= data.len() => index.push(address as u32),
+                _ => return Err(NP_Error::new_static("Batch index points outside the data!"))
+            }
+        }
+
+        let batch = NP_Batch { factory, index, data: data.to_vec(), dictionary };
+
+        // compressed buffers are only checked once, here
+        if let Some(dictionary) = &batch.dictionary {
+            for i in 0..batch.len() {
+                dictionary::decompress(dictionary, batch.stored_bytes(i).unwrap_or(&[]))?;
+            }
+        }
+
+        Ok(batch)
+    }
+
+    /// How many buffers are in the batch
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// If the batch has no buffers
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// The dictionary buffers are compressed with, if the batch is compressed
+    pub fn dictionary(&self) -> Option<&[u8]> {
+        self.dictionary.as_deref()
+    }
+
+    /// Bytes used by the dictionary, index and buffers, about what `close` will return
+    pub fn read_len(&self) -> usize {
+        HEADER_LEN + self.dictionary.as_ref().map(|dictionary| 4 + dictionary.len()).unwrap_or(0) + (self.index.len() * 4) + self.data.len()
+    }
+
+    // bytes of a buffer as they're stored, compressed or not
+    fn stored_bytes(&self, index: usize) -> Option<&[u8]> {
+        let address = *self.index.get(index)? as usize;
+        let len = read_u32(&self.data, address)?;
+        self.data.get((address + 4)..(address + 4 + len))
+    }
+
+    /// The bytes of the buffer at `index`, `None` if the index is past the end.  Buffers in compressed batches are decompressed.
+    pub fn get_bytes(&self, index: usize) -> Option<Cow<'_, [u8]>> {
+        let stored = self.stored_bytes(index)?;
+        match &self.dictionary {
+            Some(dictionary) => dictionary::decompress(dictionary, stored).ok().map(Cow::Owned),
+            None => Some(Cow::Borrowed(stored))
+        }
+    }
+
+    /// Open a copy of the buffer at `index`, `None` if the index is past the end
+    pub fn get(&self, index: usize) -> Option<NP_Buffer<'batch>> {
+        let bytes = self.get_bytes(index)?.into_owned();
+        Some(self.factory.open_buffer(bytes))
+    }
+
+    /// Iterate over copies of every buffer in the batch
+    pub fn iter(&self) -> NP_Batch_Iterator<'_, 'batch> {
+        NP_Batch_Iterator { batch: self, index: 0 }
+    }
+
+    /// Add a buffer to the end of the batch and get its index.
+    ///
+    /// The buffer must use the same schema as the batch.  Buffers are added as they are, compact them first to leave out any wasted bytes.
+    ///
+    pub fn append(&mut self, buffer: &NP_Buffer) -> Result<usize, NP_Error> {
+        if !buffer.has_schema(self.factory.schema.fingerprint) {
+            return Err(NP_Error::new_static("Can't add a buffer with a different schema to this batch!"));
+        }
+
+        match &self.dictionary {
+            Some(dictionary) => {
+                let compressed = dictionary::compress(dictionary, buffer.read_bytes());
+                self.push(&compressed)
+            },
+            None => self.push(buffer.read_bytes())
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) -> Result<usize, NP_Error> {
+        if self.index.len() >= u32::MAX as usize || self.data.len() + 4 + bytes.len() > u32::MAX as usize {
+            return Err(NP_Error::OutOfSpace);
+        }
+
+        self.index.push(self.data.len() as u32);
+        self.data.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+        self.data.extend_from_slice(bytes);
+        Ok(self.index.len() - 1)
+    }
+
+    /// Compress every buffer against a shared dictionary of up to `dictionary_size` bytes, built from the buffers in the batch.
+    ///
+    /// Buffers appended after this are compressed with the same dictionary.  Compressing a batch again builds a new dictionary from all of its buffers.  See the [batch](index.html) module for an example.
+    ///
+    pub fn compress(&mut self, dictionary_size: usize) -> Result<(), NP_Error> {
+        let buffers: Vec<Vec<u8>> = (0..self.len()).map(|i| self.get_bytes(i).map(|bytes| bytes.into_owned()).unwrap_or_default()).collect();
+        let samples: Vec<&[u8]> = buffers.iter().map(|bytes| bytes.as_slice()).collect();
+        let dictionary = dictionary::build(&samples, core::cmp::min(dictionary_size, u32::MAX as usize));
+
+        self.index = Vec::with_capacity(buffers.len());
+        self.data = Vec::new();
+        for bytes in buffers.iter() {
+            self.push(&dictionary::compress(&dictionary, bytes))?;
+        }
+        self.dictionary = Some(dictionary);
+        Ok(())
+    }
+
+    /// Get the bytes of the batch
+    pub fn close(self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.read_len());
+        bytes.push(if self.dictionary.is_some() { COMPRESSED_BATCH_VERSION } else { BATCH_VERSION });
+        bytes.extend_from_slice(&self.factory.schema.fingerprint.to_be_bytes());
+        bytes.extend_from_slice(&(self.index.len() as u32).to_be_bytes());
+        if let Some(dictionary) = &self.dictionary {
+            bytes.extend_from_slice(&(dictionary.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(dictionary);
+        }
+        for address in self.index.iter() {
+            bytes.extend_from_slice(&address.to_be_bytes());
+        }
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<'batch> NP_Batch<'batch> {
+
+    /// Run `f` on every buffer in the batch on the rayon thread pool, needs the `rayon` feature.
+    ///
+    /// Results are in the same order as the buffers.
+    ///
+    /// ```
+    /// # #[cfg(feature = "rayon")] {
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    ///
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["age",  {"type": "u8"}]
+    ///     ]
+    /// }"#)?;
+    ///
+    /// let mut batch = factory.empty_batch();
+    /// for (name, age) in [("Jeb", 30u8), ("Bill", 42u8), ("Bob", 25u8)].iter() {
+    ///     let mut user = factory.empty_buffer(None);
+    ///     user.set(&["name"], *name)?;
+    ///     user.set(&["age"], *age)?;
+    ///     batch.append(&user)?;
+    /// }
+    ///
+    /// let ages: Vec<u8> = batch.par_map(|user| user.get::<u8>(&["age"]).unwrap_or(None).unwrap_or(0));
+    /// assert_eq!(ages, vec![30, 42, 25]);
+    ///
+    /// // buffer indexes sorted by name
+    /// let by_name = batch.par_index(|user| user.get::<&str>(&["name"]).unwrap_or(None).map(|name| name.to_owned()));
+    /// assert_eq!(by_name, vec![("Bill".to_owned(), 1), ("Bob".to_owned(), 2), ("Jeb".to_owned(), 0)]);
+    ///
+    /// # }
+    /// # Ok::<(), no_proto::error::NP_Error>(())
+    /// ```
+    ///
+    pub fn par_map<R, F>(&self, f: F) -> Vec<R> where R: Send, F: Fn(NP_Buffer<'batch>) -> R + Sync + Send {
+        use rayon::prelude::*;
+        (0..self.len()).into_par_iter().filter_map(|i| self.get(i)).map(f).collect()
+    }
+
+    /// Get a key from every buffer on the rayon thread pool and sort them, needs the `rayon` feature.
+    ///
+    /// Returns `(key, buffer index)` pairs sorted by key, ready for a binary search.  Buffers `key` returns `None` for are left out.  See `par_map` for an example.
+    ///
+    pub fn par_index<K, F>(&self, key: F) -> Vec<(K, usize)> where K: Ord + Send, F: Fn(&NP_Buffer<'batch>) -> Option<K> + Sync + Send {
+        use rayon::prelude::*;
+        let mut index: Vec<(K, usize)> = (0..self.len()).into_par_iter().filter_map(|i| {
+            let buffer = self.get(i)?;
+            key(&buffer).map(|key| (key, i))
+        }).collect();
+        index.par_sort();
+        index
+    }
+}
+
+/// Iterator over the buffers of an `NP_Batch`, from `NP_Batch::iter`
+#[derive(Debug)]
+pub struct NP_Batch_Iterator<'iter, 'batch> {
+    batch: &'iter NP_Batch<'batch>,
+    index: usize
+}
+
+impl<'iter, 'batch> Iterator for NP_Batch_Iterator<'iter, 'batch> {
+    type Item = NP_Buffer<'batch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let buffer = self.batch.get(self.index)?;
+        self.index += 1;
+        Some(buffer)
+    }
+}
+
+#[test]
+fn batch_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+
+    let empty = factory.open_batch(factory.empty_batch().close())?;
+    assert!(empty.is_empty());
+    assert!(empty.get(0).is_none());
+
+    let mut batch = factory.empty_batch();
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(batch.append(&buffer)?, 0);
+    buffer.set(&["2"], "hello")?;
+    assert_eq!(batch.append(&buffer)?, 1);
+
+    let other = crate::NP_Factory::new(r#"{"type": "string"}"#)?;
+    assert!(batch.append(&other.empty_buffer(None)).is_err());
+
+    let bytes = batch.close();
+    let batch = factory.open_batch(bytes.clone())?;
+    assert_eq!(batch.len(), 2);
+    assert_eq!(batch.get_bytes(0).as_deref(), Some(&[0u8, 0, 0][..]));
+    assert!(batch.dictionary().is_none());
+    assert_eq!(batch.get(1).unwrap().get::<&str>(&["2"])?, Some("hello"));
+    assert_eq!(batch.iter().count(), 2);
+
+    // bad headers and indexes don't open
+    assert!(other.open_batch(bytes.clone()).is_err());
+    assert!(factory.open_batch(bytes[..(bytes.len() - 1)].to_vec()).is_err());
+    let mut bad_index = bytes.clone();
+    bad_index[20] = 200;
+    assert!(factory.open_batch(bad_index).is_err());
+    let mut bad_count = bytes.clone();
+    bad_count[9] = 255;
+    assert!(factory.open_batch(bad_count).is_err());
+
+    // compressed batches keep compressing appended buffers
+    let mut batch = factory.open_batch(bytes)?;
+    batch.compress(128)?;
+    buffer.set(&["0"], "world")?;
+    assert_eq!(batch.append(&buffer)?, 2);
+    let bytes = batch.close();
+    assert_eq!(bytes[0], COMPRESSED_BATCH_VERSION);
+
+    let batch = factory.open_batch(bytes.clone())?;
+    assert!(batch.dictionary().is_some());
+    let json: Vec<alloc::string::String> = batch.iter().map(|buffer| buffer.json_encode(&[]).unwrap().stringify()).collect();
+    assert_eq!(json, [r#"null"#, r#"[null,null,"hello"]"#, r#"["world",null,"hello"]"#].iter().map(|json| alloc::string::String::from(*json)).collect::<Vec<_>>());
+    assert_eq!(batch.get(1).unwrap().get::<&str>(&["2"])?, Some("hello"));
+
+    // broken compressed buffers don't open
+    let mut bad_data = bytes.clone();
+    let last = bytes.len() - batch.stored_bytes(2).unwrap().len();
+    bad_data[last..(last + 3)].copy_from_slice(&[0xFF, 0xFF, 0x0F]);
+    assert!(factory.open_batch(bad_data).is_err());
+
+    Ok(())
+}
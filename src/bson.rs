@@ -0,0 +1,661 @@
+//! BSON export and import, enabled with the `bson` feature
+//!
+//! `NP_Buffer::to_bson` writes a buffer as a BSON document and `NP_Factory::open_bson` reads one back, so buffers can be stored in and read from MongoDB collections.  The buffer's root must be a table or map.
+//!
+//! The schema decides how each value is written, so types BSON has a native form for keep it:
+//!
+//! | NoProto                              | BSON                                   |
+//! |--------------------------------------|----------------------------------------|
+//! | table, map                           | embedded document                      |
+//! | list, tuple, log, tensor             | array, empty list items are `null`     |
+//! | string, option, ulid, bigint         | string                                 |
+//! | bytes                                | binary (subtype 0)                     |
+//! | uuid                                 | binary (subtype 4)                     |
+//! | i8, i16, i32, u8, u16                | int32                                  |
+//! | i64, u32                             | int64                                  |
+//! | u64                                  | int64 with the same bits               |
+//! | float, double                        | double                                 |
+//! | decimal                              | decimal128                             |
+//! | date                                 | UTC datetime                           |
+//! | geo                                  | GeoJSON point                          |
+//! | bool                                 | boolean                                |
+//!
+//! `any` values use the closest BSON type for what they hold.  Unset values are left out of documents.
+//!
+//! MongoDB adds an `_id` to every document, it's skipped when reading unless the table has an `_id` column.  ObjectIds are read as 24 character hex strings.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::date::NP_Date;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["joined", {"type": "date"}],
+//!         ["visits", {"type": "u64"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb Kermin")?;
+//! new_buffer.set(&["joined"], NP_Date::new(1604965249484))?;
+//! new_buffer.set(&["visits"], u64::MAX)?;
+//!
+//! let bson: Vec<u8> = new_buffer.to_bson()?;
+//!
+//! let opened = factory.open_bson(&bson)?;
+//! assert_eq!(opened.get::<&str>(&["name"])?, Some("Jeb Kermin"));
+//! assert_eq!(opened.get::<NP_Date>(&["joined"])?, Some(NP_Date::new(1604965249484)));
+//! assert_eq!(opened.get::<u64>(&["visits"])?, Some(u64::MAX));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::json_flex::{JSMAP, NP_JSON};
+use crate::pointer::uuid::NP_UUID;
+use crate::schema::{NP_Parsed_Schema, NP_TypeKeys};
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+const DOUBLE: u8 = 0x01;
+const STRING: u8 = 0x02;
+const DOCUMENT: u8 = 0x03;
+const ARRAY: u8 = 0x04;
+const BINARY: u8 = 0x05;
+const OBJECT_ID: u8 = 0x07;
+const BOOLEAN: u8 = 0x08;
+const DATETIME: u8 = 0x09;
+const NULL: u8 = 0x0A;
+const INT32: u8 = 0x10;
+const INT64: u8 = 0x12;
+const DECIMAL128: u8 = 0x13;
+
+const BINARY_GENERIC: u8 = 0x00;
+const BINARY_UUID: u8 = 0x04;
+
+// decimal128 exponents are stored with this bias
+const DECIMAL_BIAS: i32 = 6176;
+
+/// Encode the JSON of a buffer (from `json_encode`) as a BSON document, using the schema at `address` to pick BSON types.
+pub(crate) fn encode(json: &NP_JSON, schema: &[NP_Parsed_Schema], address: usize) -> Result<Vec<u8>, NP_Error> {
+    match &schema[address] {
+        NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::Map { .. } => { },
+        _ => return Err(NP_Error::new_static("BSON documents need a table or map root!"))
+    }
+
+    let mut out = Vec::new();
+    match json {
+        NP_JSON::Dictionary(_) => write_document(&mut out, json, schema, address)?,
+        // unset root is an empty document
+        _ => out.extend_from_slice(&[5, 0, 0, 0, 0])
+    }
+    Ok(out)
+}
+
+/// Decode a BSON document into JSON that `set_json_value` accepts for the schema at `address`.
+pub(crate) fn decode(bytes: &[u8], schema: &[NP_Parsed_Schema], address: usize) -> Result<NP_JSON, NP_Error> {
+    match &schema[address] {
+        NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::Map { .. } => { },
+        _ => return Err(NP_Error::new_static("BSON documents need a table or map root!"))
+    }
+
+    let mut reader = Reader { bytes, pos: 0 };
+    let value = read_document(&mut reader, schema, address)?;
+    if reader.pos != bytes.len() {
+        return Err(NP_Error::new_static("Unexpected bytes after BSON document!"));
+    }
+    Ok(value)
+}
+
+fn write_key(out: &mut Vec<u8>, kind: u8, key: &str) -> Result<(), NP_Error> {
+    if key.as_bytes().contains(&0) {
+        return Err(NP_Error::new_static("BSON keys can't contain null characters!"));
+    }
+    out.push(kind);
+    out.extend_from_slice(key.as_bytes());
+    out.push(0);
+    Ok(())
+}
+
+// write the length of everything from `start` into the 4 bytes at `start`
+fn patch_length(out: &mut [u8], start: usize) -> Result<(), NP_Error> {
+    let length = out.len() - start;
+    if length > i32::MAX as usize {
+        return Err(NP_Error::new_static("BSON document is too large!"));
+    }
+    out[start..(start + 4)].copy_from_slice(&(length as i32).to_le_bytes());
+    Ok(())
+}
+
+fn write_document(out: &mut Vec<u8>, json: &NP_JSON, schema: &[NP_Parsed_Schema], address: usize) -> Result<(), NP_Error> {
+    let start = out.len();
+    out.extend_from_slice(&[0u8; 4]);
+
+    if let NP_JSON::Dictionary(object) = json {
+        for (key, value) in object.values.iter() {
+            // unset values are left out
+            if let NP_JSON::Null = value { continue; }
+
+            let value_addr = match &schema[address] {
+                NP_Parsed_Schema::Table { columns, .. } => match columns.iter().find(|column| column.1 == *key) {
+                    Some(column) => column.2,
+                    None => return Err(NP_Error::new(String::from("Column not found in table: ") + key))
+                },
+                NP_Parsed_Schema::Map { value, .. } => *value,
+                _ => return Err(NP_Error::new_static("BSON documents need a table or map!"))
+            };
+
+            write_element(out, key, value, schema, value_addr)?;
+        }
+    }
+
+    out.push(0);
+    patch_length(out, start)
+}
+
+fn write_array<F: Fn(usize) -> Option<usize>>(out: &mut Vec<u8>, items: &[NP_JSON], schema: &[NP_Parsed_Schema], item_addr: F) -> Result<(), NP_Error> {
+    let start = out.len();
+    out.extend_from_slice(&[0u8; 4]);
+
+    for (idx, item) in items.iter().enumerate() {
+        let key = format!("{}", idx);
+        match (item, item_addr(idx)) {
+            // keep every index so sparse lists read back the same
+            (NP_JSON::Null, _) => write_key(out, NULL, &key)?,
+            (_, Some(addr)) => write_element(out, &key, item, schema, addr)?,
+            (_, None) => return Err(NP_Error::new_static("Too many values for tuple!"))
+        }
+    }
+
+    out.push(0);
+    patch_length(out, start)
+}
+
+fn write_number(out: &mut Vec<u8>, key: &str, dtype: &NP_TypeKeys, json: &NP_JSON) -> Result<(), NP_Error> {
+    match (dtype, json) {
+        (NP_TypeKeys::Float, _) | (NP_TypeKeys::Double, _) => {
+            let value = match json {
+                NP_JSON::Float(x) => *x,
+                NP_JSON::Integer(x) => *x as f64,
+                NP_JSON::Unsigned(x) => *x as f64,
+                _ => return Err(mismatch(key))
+            };
+            write_key(out, DOUBLE, key)?;
+            out.extend_from_slice(&value.to_le_bytes());
+        },
+        (NP_TypeKeys::Int8, NP_JSON::Integer(x)) | (NP_TypeKeys::Int16, NP_JSON::Integer(x)) | (NP_TypeKeys::Int32, NP_JSON::Integer(x)) |
+        (NP_TypeKeys::Uint8, NP_JSON::Integer(x)) | (NP_TypeKeys::Uint16, NP_JSON::Integer(x)) => {
+            write_key(out, INT32, key)?;
+            out.extend_from_slice(&(*x as i32).to_le_bytes());
+        },
+        (_, NP_JSON::Integer(x)) => {
+            write_key(out, INT64, key)?;
+            out.extend_from_slice(&x.to_le_bytes());
+        },
+        // u64 values above i64::MAX keep their bits
+        (_, NP_JSON::Unsigned(x)) => {
+            write_key(out, INT64, key)?;
+            out.extend_from_slice(&x.to_le_bytes());
+        },
+        _ => return Err(mismatch(key))
+    }
+    Ok(())
+}
+
+fn write_binary(out: &mut Vec<u8>, key: &str, subtype: u8, bytes: &[u8]) -> Result<(), NP_Error> {
+    write_key(out, BINARY, key)?;
+    out.extend_from_slice(&(bytes.len() as i32).to_le_bytes());
+    out.push(subtype);
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn write_string(out: &mut Vec<u8>, key: &str, value: &str) -> Result<(), NP_Error> {
+    write_key(out, STRING, key)?;
+    out.extend_from_slice(&((value.len() + 1) as i32).to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+    out.push(0);
+    Ok(())
+}
+
+fn json_bytes(key: &str, items: &[NP_JSON]) -> Result<Vec<u8>, NP_Error> {
+    items.iter().map(|item| match item {
+        NP_JSON::Integer(x) if *x >= 0 && *x <= 255 => Ok(*x as u8),
+        _ => Err(mismatch(key))
+    }).collect()
+}
+
+fn mismatch(key: &str) -> NP_Error {
+    NP_Error::new(String::from("Value doesn't match schema type for BSON key ") + key)
+}
+
+fn write_element(out: &mut Vec<u8>, key: &str, json: &NP_JSON, schema: &[NP_Parsed_Schema], address: usize) -> Result<(), NP_Error> {
+    match (&schema[address], json) {
+        (NP_Parsed_Schema::None, _) => { },
+        (NP_Parsed_Schema::Any { .. }, NP_JSON::True) | (NP_Parsed_Schema::Boolean { .. }, NP_JSON::True) => {
+            write_key(out, BOOLEAN, key)?;
+            out.push(1);
+        },
+        (NP_Parsed_Schema::Any { .. }, NP_JSON::False) | (NP_Parsed_Schema::Boolean { .. }, NP_JSON::False) => {
+            write_key(out, BOOLEAN, key)?;
+            out.push(0);
+        },
+        (NP_Parsed_Schema::Any { .. }, NP_JSON::Float(_)) => write_number(out, key, &NP_TypeKeys::Double, json)?,
+        (NP_Parsed_Schema::Any { .. }, NP_JSON::Integer(_)) | (NP_Parsed_Schema::Any { .. }, NP_JSON::Unsigned(_)) => write_number(out, key, &NP_TypeKeys::Int64, json)?,
+        (NP_Parsed_Schema::Any { .. }, NP_JSON::String(x)) => write_string(out, key, x)?,
+        (NP_Parsed_Schema::Any { .. }, NP_JSON::Array(items)) | (NP_Parsed_Schema::Bytes { .. }, NP_JSON::Array(items)) => {
+            write_binary(out, key, BINARY_GENERIC, &json_bytes(key, items)?)?;
+        },
+        (NP_Parsed_Schema::UTF8String { .. }, NP_JSON::String(x)) | (NP_Parsed_Schema::Enum { .. }, NP_JSON::String(x)) |
+        (NP_Parsed_Schema::Ulid { .. }, NP_JSON::String(x)) | (NP_Parsed_Schema::BigInt { .. }, NP_JSON::String(x)) => write_string(out, key, x)?,
+        (NP_Parsed_Schema::Int8 { i, .. }, _) | (NP_Parsed_Schema::Int16 { i, .. }, _) | (NP_Parsed_Schema::Int32 { i, .. }, _) | (NP_Parsed_Schema::Int64 { i, .. }, _) |
+        (NP_Parsed_Schema::Uint8 { i, .. }, _) | (NP_Parsed_Schema::Uint16 { i, .. }, _) | (NP_Parsed_Schema::Uint32 { i, .. }, _) | (NP_Parsed_Schema::Uint64 { i, .. }, _) |
+        (NP_Parsed_Schema::Float { i, .. }, _) | (NP_Parsed_Schema::Double { i, .. }, _) => write_number(out, key, i, json)?,
+        (NP_Parsed_Schema::Decimal { .. }, _) => {
+            match (&json["num"], &json["exp"]) {
+                (NP_JSON::Integer(num), NP_JSON::Integer(exp)) => {
+                    write_key(out, DECIMAL128, key)?;
+                    out.extend_from_slice(&decimal128(*num, *exp).to_le_bytes());
+                },
+                _ => return Err(mismatch(key))
+            }
+        },
+        (NP_Parsed_Schema::Geo { .. }, NP_JSON::Dictionary(_)) => {
+            // GeoJSON puts longitude first
+            write_key(out, DOCUMENT, key)?;
+            let start = out.len();
+            out.extend_from_slice(&[0u8; 4]);
+            write_string(out, "type", "Point")?;
+            write_key(out, ARRAY, "coordinates")?;
+            let coordinates = out.len();
+            out.extend_from_slice(&[0u8; 4]);
+            write_number(out, "0", &NP_TypeKeys::Double, &json["lng"])?;
+            write_number(out, "1", &NP_TypeKeys::Double, &json["lat"])?;
+            out.push(0);
+            patch_length(out, coordinates)?;
+            out.push(0);
+            patch_length(out, start)?;
+        },
+        (NP_Parsed_Schema::Date { .. }, _) => {
+            let value = match json.into_u64() {
+                Some(x) => x,
+                None => return Err(mismatch(key))
+            };
+            write_key(out, DATETIME, key)?;
+            out.extend_from_slice(&value.to_le_bytes());
+        },
+        (NP_Parsed_Schema::Uuid { .. }, NP_JSON::String(x)) => {
+            match NP_UUID::from_string(x) {
+                Some(uuid) => write_binary(out, key, BINARY_UUID, &uuid.value)?,
+                None => return Err(mismatch(key))
+            }
+        },
+        (NP_Parsed_Schema::Tensor { dtype, .. }, NP_JSON::Array(items)) => {
+            write_key(out, ARRAY, key)?;
+            let start = out.len();
+            out.extend_from_slice(&[0u8; 4]);
+            for (idx, item) in items.iter().enumerate() {
+                write_number(out, &format!("{}", idx), dtype, item)?;
+            }
+            out.push(0);
+            patch_length(out, start)?;
+        },
+        (NP_Parsed_Schema::Table { .. }, NP_JSON::Dictionary(_)) | (NP_Parsed_Schema::Map { .. }, NP_JSON::Dictionary(_)) => {
+            write_key(out, DOCUMENT, key)?;
+            write_document(out, json, schema, address)?;
+        },
+        (NP_Parsed_Schema::List { of, .. }, NP_JSON::Array(items)) | (NP_Parsed_Schema::Log { of, .. }, NP_JSON::Array(items)) => {
+            write_key(out, ARRAY, key)?;
+            write_array(out, items, schema, |_| Some(*of))?;
+        },
+        (NP_Parsed_Schema::Tuple { values, .. }, NP_JSON::Array(items)) => {
+            write_key(out, ARRAY, key)?;
+            write_array(out, items, schema, |idx| values.get(idx).copied())?;
+        },
+        _ => return Err(mismatch(key))
+    }
+    Ok(())
+}
+
+// decimal128 with the coefficient in the low 113 bits, `num / 10^exp`
+fn decimal128(num: i64, exp: i64) -> u128 {
+    let sign: u128 = if num < 0 { 1 } else { 0 };
+    let biased = (DECIMAL_BIAS as i64 - exp) as u128;
+    (sign << 127) | ((biased & 0x3FFF) << 113) | (num.unsigned_abs() as u128)
+}
+
+struct Reader<'bytes> {
+    bytes: &'bytes [u8],
+    pos: usize
+}
+
+impl<'bytes> Reader<'bytes> {
+
+    fn take(&mut self, len: usize) -> Result<&'bytes [u8], NP_Error> {
+        match self.bytes.get(self.pos..(self.pos + len)) {
+            Some(x) => {
+                self.pos += len;
+                Ok(x)
+            },
+            None => Err(NP_Error::new_static("BSON document ended early!"))
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, NP_Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32, NP_Error> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(i32::from_le_bytes(bytes))
+    }
+
+    fn i64(&mut self) -> Result<i64, NP_Error> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(i64::from_le_bytes(bytes))
+    }
+
+    fn len(&mut self) -> Result<usize, NP_Error> {
+        match self.i32()? {
+            x if x >= 0 => Ok(x as usize),
+            _ => Err(NP_Error::new_static("Negative length in BSON document!"))
+        }
+    }
+
+    fn cstring(&mut self) -> Result<String, NP_Error> {
+        let rest = &self.bytes[self.pos..];
+        match rest.iter().position(|b| *b == 0) {
+            Some(end) => {
+                let value = String::from_utf8(rest[..end].to_vec())?;
+                self.pos += end + 1;
+                Ok(value)
+            },
+            None => Err(NP_Error::new_static("BSON document ended early!"))
+        }
+    }
+
+    fn string(&mut self) -> Result<String, NP_Error> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        match bytes.split_last() {
+            Some((0, value)) => Ok(String::from_utf8(value.to_vec())?),
+            _ => Err(NP_Error::new_static("BSON string isn't null terminated!"))
+        }
+    }
+}
+
+// read elements until the end of the document, calling `element` with the type and key of each
+fn read_elements<F: FnMut(&mut Reader, u8, String) -> Result<(), NP_Error>>(reader: &mut Reader, mut element: F) -> Result<(), NP_Error> {
+    let start = reader.pos;
+    let len = reader.len()?;
+    let end = start + len;
+    if len < 5 || end > reader.bytes.len() {
+        return Err(NP_Error::new_static("Invalid BSON document length!"));
+    }
+
+    loop {
+        let kind = reader.u8()?;
+        if kind == 0 { break; }
+        let key = reader.cstring()?;
+        element(reader, kind, key)?;
+        if reader.pos >= end {
+            return Err(NP_Error::new_static("Invalid BSON document length!"));
+        }
+    }
+
+    if reader.pos != end {
+        return Err(NP_Error::new_static("Invalid BSON document length!"));
+    }
+    Ok(())
+}
+
+fn read_document(reader: &mut Reader, schema: &[NP_Parsed_Schema], address: usize) -> Result<NP_JSON, NP_Error> {
+    let mut object = JSMAP::new();
+
+    read_elements(reader, |reader, kind, key| {
+        let value_addr = match &schema[address] {
+            NP_Parsed_Schema::Table { columns, .. } => match columns.iter().find(|column| column.1 == key) {
+                Some(column) => column.2,
+                // MongoDB adds an _id to every document
+                None if key == "_id" => {
+                    read_value(reader, kind, &key, schema, None)?;
+                    return Ok(());
+                },
+                None => return Err(NP_Error::new(String::from("Column not found in table: ") + &key))
+            },
+            NP_Parsed_Schema::Map { value, .. } => *value,
+            _ => return Err(mismatch(&key))
+        };
+        let value = read_value(reader, kind, &key, schema, Some(value_addr))?;
+        object.insert(key, value);
+        Ok(())
+    })?;
+
+    Ok(NP_JSON::Dictionary(object))
+}
+
+fn read_array(reader: &mut Reader, schema: &[NP_Parsed_Schema], address: usize) -> Result<NP_JSON, NP_Error> {
+    let mut items = Vec::new();
+
+    read_elements(reader, |reader, kind, key| {
+        let item_addr = match &schema[address] {
+            NP_Parsed_Schema::List { of, .. } | NP_Parsed_Schema::Log { of, .. } => Some(*of),
+            NP_Parsed_Schema::Tuple { values, .. } => values.get(items.len()).copied(),
+            // tensor items are numbers of the tensor's type
+            _ => None
+        };
+        let mut value = read_value(reader, kind, &key, schema, item_addr)?;
+        if let (NP_Parsed_Schema::Tensor { dtype: NP_TypeKeys::Uint64, .. }, NP_JSON::Integer(x)) = (&schema[address], &value) {
+            value = NP_JSON::from_u64(*x as u64);
+        }
+        items.push(value);
+        Ok(())
+    })?;
+
+    Ok(NP_JSON::Array(items))
+}
+
+fn read_value(reader: &mut Reader, kind: u8, key: &str, schema: &[NP_Parsed_Schema], address: Option<usize>) -> Result<NP_JSON, NP_Error> {
+    let parsed = address.map(|addr| &schema[addr]);
+
+    Ok(match kind {
+        DOUBLE => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(reader.take(8)?);
+            NP_JSON::Float(f64::from_le_bytes(bytes))
+        },
+        STRING => NP_JSON::String(reader.string()?),
+        DOCUMENT => match (parsed, address) {
+            (Some(NP_Parsed_Schema::Geo { .. }), _) => read_geo(reader, key)?,
+            (Some(NP_Parsed_Schema::Table { .. }), Some(addr)) | (Some(NP_Parsed_Schema::Map { .. }), Some(addr)) => read_document(reader, schema, addr)?,
+            (None, _) => { skip_document(reader)?; NP_JSON::Null },
+            _ => return Err(mismatch(key))
+        },
+        ARRAY => match (parsed, address) {
+            (Some(NP_Parsed_Schema::List { .. }), Some(addr)) | (Some(NP_Parsed_Schema::Log { .. }), Some(addr)) |
+            (Some(NP_Parsed_Schema::Tuple { .. }), Some(addr)) | (Some(NP_Parsed_Schema::Tensor { .. }), Some(addr)) => read_array(reader, schema, addr)?,
+            (None, _) => { skip_document(reader)?; NP_JSON::Null },
+            _ => return Err(mismatch(key))
+        },
+        BINARY => {
+            let len = reader.len()?;
+            let subtype = reader.u8()?;
+            let bytes = reader.take(len)?;
+            match parsed {
+                Some(NP_Parsed_Schema::Uuid { .. }) if bytes.len() == 16 && (subtype == BINARY_UUID || subtype == 0x03) => {
+                    let mut uuid = NP_UUID { value: [0u8; 16] };
+                    uuid.value.copy_from_slice(bytes);
+                    NP_JSON::String(uuid.to_string())
+                },
+                Some(NP_Parsed_Schema::Uuid { .. }) => return Err(mismatch(key)),
+                _ => NP_JSON::Array(bytes.iter().map(|b| NP_JSON::Integer(*b as i64)).collect())
+            }
+        },
+        OBJECT_ID => {
+            let bytes = reader.take(12)?;
+            NP_JSON::String(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+        },
+        BOOLEAN => match reader.u8()? {
+            0 => NP_JSON::False,
+            1 => NP_JSON::True,
+            _ => return Err(NP_Error::new_static("Invalid BSON boolean!"))
+        },
+        DATETIME | INT64 => {
+            let value = reader.i64()?;
+            match parsed {
+                // u64 and dates above i64::MAX were written with the same bits
+                Some(NP_Parsed_Schema::Uint64 { .. }) | Some(NP_Parsed_Schema::Date { .. }) => NP_JSON::from_u64(value as u64),
+                _ => NP_JSON::Integer(value)
+            }
+        },
+        NULL => NP_JSON::Null,
+        INT32 => NP_JSON::Integer(reader.i32()? as i64),
+        DECIMAL128 => {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(reader.take(16)?);
+            read_decimal128(u128::from_le_bytes(bytes), key)?
+        },
+        _ => return Err(NP_Error::new(format!("BSON type 0x{:02x} isn't supported, found at key {}", kind, key)))
+    })
+}
+
+fn skip_document(reader: &mut Reader) -> Result<(), NP_Error> {
+    let start = reader.pos;
+    let len = reader.len()?;
+    reader.pos = start;
+    reader.take(len)?;
+    Ok(())
+}
+
+fn read_geo(reader: &mut Reader, key: &str) -> Result<NP_JSON, NP_Error> {
+    let mut coordinates: Vec<f64> = Vec::new();
+
+    read_elements(reader, |reader, kind, item_key| {
+        match (kind, item_key.as_str()) {
+            (STRING, "type") => {
+                if reader.string()? != "Point" { return Err(mismatch(key)); }
+            },
+            (ARRAY, "coordinates") => {
+                read_elements(reader, |reader, kind, _| {
+                    match read_value(reader, kind, key, &[], None)? {
+                        NP_JSON::Float(x) => coordinates.push(x),
+                        NP_JSON::Integer(x) => coordinates.push(x as f64),
+                        _ => return Err(mismatch(key))
+                    }
+                    Ok(())
+                })?;
+            },
+            _ => return Err(mismatch(key))
+        }
+        Ok(())
+    })?;
+
+    // GeoJSON puts longitude first
+    match coordinates.as_slice() {
+        [lng, lat] => {
+            let mut object = JSMAP::new();
+            object.insert("lat".to_owned(), NP_JSON::Float(*lat));
+            object.insert("lng".to_owned(), NP_JSON::Float(*lng));
+            Ok(NP_JSON::Dictionary(object))
+        },
+        _ => Err(mismatch(key))
+    }
+}
+
+fn read_decimal128(value: u128, key: &str) -> Result<NP_JSON, NP_Error> {
+    // infinity, NaN and coefficients too large for the normal form
+    if (value >> 125) & 0b11 == 0b11 {
+        return Err(NP_Error::new(String::from("Decimal128 value can't be stored as a decimal at BSON key ") + key));
+    }
+
+    let negative = value >> 127 == 1;
+    let exponent = ((value >> 113) & 0x3FFF) as i32 - DECIMAL_BIAS;
+    let mut coefficient = value & ((1u128 << 113) - 1);
+
+    // positive exponents are multiplied into the number
+    for _ in 0..core::cmp::max(exponent, 0) {
+        coefficient = match coefficient.checked_mul(10) {
+            Some(x) => x,
+            None => return Err(mismatch(key))
+        };
+    }
+    let exp = core::cmp::max(-exponent, 0);
+
+    if coefficient > i64::MAX as u128 || exp > 255 {
+        return Err(NP_Error::new(String::from("Decimal128 value is too large for a decimal at BSON key ") + key));
+    }
+
+    let mut object = JSMAP::new();
+    object.insert("num".to_owned(), NP_JSON::Integer(if negative { -(coefficient as i64) } else { coefficient as i64 }));
+    object.insert("exp".to_owned(), NP_JSON::Integer(exp as i64));
+    Ok(NP_JSON::Dictionary(object))
+}
+
+#[test]
+fn bson_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["string", {"type": "string"}],
+        ["bytes",  {"type": "bytes"}],
+        ["i8",     {"type": "i8"}],
+        ["u32",    {"type": "u32"}],
+        ["u64",    {"type": "u64"}],
+        ["float",  {"type": "float"}],
+        ["dec",    {"type": "decimal", "exp": 2}],
+        ["bool",   {"type": "bool"}],
+        ["geo",    {"type": "geo8"}],
+        ["date",   {"type": "date"}],
+        ["option", {"type": "option", "choices": ["red", "blue"]}],
+        ["uuid",   {"type": "uuid"}],
+        ["ulid",   {"type": "ulid"}],
+        ["any",    {"type": "any"}],
+        ["list",   {"type": "list", "of": {"type": "string"}}],
+        ["map",    {"type": "map", "value": {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}}],
+        ["log",    {"type": "log", "of": {"type": "i32"}}],
+        ["tensor", {"type": "tensor", "dtype": "u64", "shape": [2]}],
+        ["bigint", {"type": "bigint"}]
+    ]}"#)?;
+
+    for seed in 0..50 {
+        let sample = factory.generate_sample(seed)?;
+        let opened = factory.open_bson(&sample.to_bson()?)?;
+        assert!(opened.semantic_eq(&sample));
+    }
+
+    // native BSON types
+    let mut new_buffer = factory.empty_buffer(None);
+    new_buffer.set(&["i8"], -2i8)?;
+    new_buffer.set(&["u64"], u64::MAX)?;
+    new_buffer.set(&["list", "1"], "b")?;
+    new_buffer.set_with_json(&["dec"], r#"{"num": -1250, "exp": 2}"#)?;
+    let bson = new_buffer.to_bson()?;
+    assert_eq!(&bson[4..12], &[INT32, b'i', b'8', 0, 0xFE, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(&bson[12..25], &[INT64, b'u', b'6', b'4', 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(read_decimal128(decimal128(-1250, 2), "dec")?.stringify(), r#"{"num":-1250,"exp":2}"#);
+    assert_eq!(read_decimal128(decimal128(5, -3), "dec")?.stringify(), r#"{"num":5000,"exp":0}"#);
+    assert_eq!(factory.open_bson(&bson)?.json_encode(&["list"])?.stringify(), r#"[null,"b"]"#);
+
+    // MongoDB ids are skipped, other unknown keys are errors
+    let mut document = alloc::vec![0u8; 4];
+    document.extend_from_slice(&[OBJECT_ID, b'_', b'i', b'd', 0]);
+    document.extend_from_slice(&[7u8; 12]);
+    document.extend_from_slice(&[BOOLEAN, b'b', b'o', b'o', b'l', 0, 1, 0]);
+    let len = document.len() as i32;
+    document[0..4].copy_from_slice(&len.to_le_bytes());
+    assert_eq!(factory.open_bson(&document)?.get::<bool>(&["bool"])?, Some(true));
+    document[6] = b'x';
+    assert!(factory.open_bson(&document).is_err());
+    assert!(factory.open_bson(&document[..10]).is_err());
+
+    let list = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u8"}}"#)?;
+    assert!(list.empty_buffer(None).to_bson().is_err());
+
+    Ok(())
+}
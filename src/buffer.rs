@@ -3,17 +3,38 @@
 use crate::utils::opt_err;
 use crate::collection::tuple::NP_Tuple;
 
-use crate::{pointer::{NP_Scalar}};
+use crate::{pointer::{NP_Scalar, numbers::{NP_Counter, NP_Overflow, overflow_of}, dec::{NP_Dec, NP_Rounding}, string, any::NP_Dyn}};
 use crate::{collection::map::NP_Map};
 use crate::{pointer::NP_Value};
 use crate::pointer::NP_Cursor;
 use crate::{schema::NP_Parsed_Schema, collection::table::NP_Table};
 use alloc::vec::Vec;
-use crate::{collection::{list::NP_List}};
+use alloc::borrow::Cow;
+use alloc::sync::Arc;
+use crate::{collection::{list::NP_List, log::NP_Log}};
 use crate::error::NP_Error;
+use crate::compare;
+use crate::schema::{NP_Schema, NP_TypeKeys};
+use core::cmp::Ordering;
+use core::ops::Deref;
 use crate::memory::{NP_Memory};
-use crate::{json_flex::NP_JSON};
-use crate::alloc::borrow::ToOwned;
+use crate::{json_flex::{NP_JSON, json_decode}};
+use alloc::string::{String, ToString};
+use crate::view::{NP_FieldMask, NP_Buffer_View};
+use crate::snapshot::NP_Snapshot;
+use crate::reader::{NP_Buffer_Reader, NP_Buffer_Writer};
+use crate::compactor::NP_Compactor;
+use crate::path::{NP_PathHandle, NP_Path_Step};
+use crate::raw::NP_Raw_Addr;
+use crate::mask::NP_Mask;
+use crate::tombstone::{self, NP_Tombstone};
+use crate::crdt;
+use crate::version::{self, NP_Version};
+use crate::fingerprint;
+use crate::hashmap::SEED;
+use crate::pointer::{NP_Vtable, VTABLE_SLOTS, NP_List_Bytes, NP_Log_Bytes, NP_Pointer_List_Item, NP_Pointer_Wide_List_Item, NP_Pointer_Map_Item, NP_Pointer_Log_Item, tensor};
+use crate::schema::{NP_SchemaNode, String_Collation};
+use core::mem::size_of;
 
 /// The address location of the root pointer.
 #[doc(hidden)]
@@ -30,7 +51,169 @@ pub struct NP_Buffer<'buffer> {
     memory: NP_Memory<'buffer>,
     cursor: NP_Cursor,
     sortable: bool,
-    backup_cursor: NP_Cursor
+    backup_cursor: NP_Cursor,
+    // tombstone hashes of the cursor and backup cursor paths
+    cursor_hash: u32,
+    backup_hash: u32,
+    // set by `track_deletes`
+    clock: Option<fn() -> u64>,
+    // set by `track_version`
+    hlc: Option<fn() -> u64>,
+    // set by `set_overflow`
+    overflow: Option<NP_Overflow>
+}
+
+// the JSON of the buffer, used by test tools like proptest to print failing cases
+impl<'buffer> core::fmt::Debug for NP_Buffer<'buffer> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.json_encode(&[]) {
+            Ok(json) => write!(f, "NP_Buffer({})", json.stringify()),
+            Err(_e) => write!(f, "NP_Buffer({} bytes)", self.memory.read_bytes().len())
+        }
+    }
+}
+
+// a frozen buffer without its memory, from `NP_Buffer::share`
+pub(crate) struct NP_Shared_Buffer<'buffer> {
+    bytes: Arc<Vec<u8>>,
+    schema: &'buffer NP_Schema,
+    options: NP_Buffer_Options,
+    cursor: NP_Cursor,
+    sortable: bool,
+    backup_cursor: NP_Cursor,
+    cursor_hash: u32,
+    backup_hash: u32,
+    clock: Option<fn() -> u64>,
+    hlc: Option<fn() -> u64>,
+    overflow: Option<NP_Overflow>
+}
+
+impl<'buffer> NP_Shared_Buffer<'buffer> {
+
+    // a reader sharing these bytes
+    pub(crate) fn reader(&self) -> NP_Buffer_Reader<'buffer> {
+        NP_Buffer_Reader::_new(NP_Buffer {
+            memory: NP_Memory::shared(Arc::clone(&self.bytes), self.schema, self.options),
+            cursor: self.cursor,
+            sortable: self.sortable,
+            backup_cursor: self.backup_cursor,
+            cursor_hash: self.cursor_hash,
+            backup_hash: self.backup_hash,
+            clock: self.clock,
+            hlc: self.hlc,
+            overflow: self.overflow
+        })
+    }
+}
+
+/// How a buffer grows when it runs out of capacity, used with `NP_Buffer_Options`.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum NP_Growth {
+    /// Double the capacity (the default), fewer and larger copies
+    Double,
+    /// Grow the capacity by this many bytes past what's needed, smaller copies and less unused capacity
+    Linear(usize)
+}
+
+/// Limits and growth behavior for a buffer, use with `NP_Factory::empty_buffer_with` or `NP_Factory::open_buffer_with`.
+/// 
+/// Options are kept through compaction.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct NP_Buffer_Options {
+    /// Largest size in bytes the buffer can grow to, writes past this fail with `NP_Error::TooLarge` or `NP_Error::OutOfSpace`.  Buffers can never be larger than the address space (just under 64KB).
+    pub max_size: usize,
+    /// How the buffer grows when it runs out of capacity
+    pub growth: NP_Growth,
+    /// How much work reading the buffer is allowed to do
+    pub limits: NP_Limits
+}
+
+impl Default for NP_Buffer_Options {
+    fn default() -> Self {
+        NP_Buffer_Options { max_size: u16::MAX as usize, growth: NP_Growth::Double, limits: NP_Limits::default() }
+    }
+}
+
+/// Limits for reading buffers from untrusted sources, part of `NP_Buffer_Options`.
+/// 
+/// Going past a limit returns `NP_Error::LimitExceeded` instead of using up the stack or memory.  Limits are checked as a path is selected or JSON is encoded, nothing is walked twice.
+/// 
+/// The defaults are high enough for any buffer written by this library but stop hostile ones from making gigabytes of JSON, `NP_Limits::unlimited()` turns every limit off.
+/// 
+/// ```
+/// use no_proto::error::NP_Error;
+/// use no_proto::NP_Factory;
+/// use no_proto::buffer::{NP_Buffer_Options, NP_Limits};
+/// 
+/// let factory: NP_Factory = NP_Factory::new(r#"{
+///    "type": "list",
+///    "of": {"type": "list", "of": {"type": "u8"}}
+/// }"#)?;
+/// 
+/// let mut new_buffer = factory.empty_buffer(None);
+/// new_buffer.set(&["0", "4"], 2u8)?;
+/// let bytes = new_buffer.close();
+/// 
+/// let buffer = factory.open_buffer_with(bytes.clone(), NP_Buffer_Options {
+///     limits: NP_Limits { max_depth: 1, ..Default::default() },
+///     ..Default::default()
+/// });
+/// assert!(matches!(buffer.json_encode(&[]), Err(NP_Error::LimitExceeded { .. })));
+/// assert!(matches!(buffer.get::<u8>(&["0", "4"]), Err(NP_Error::LimitExceeded { .. })));
+/// 
+/// let buffer = factory.open_buffer_with(bytes.clone(), NP_Buffer_Options {
+///     limits: NP_Limits { max_steps: 4, ..Default::default() },
+///     ..Default::default()
+/// });
+/// assert!(matches!(buffer.json_encode(&[]), Err(NP_Error::LimitExceeded { .. })));
+/// 
+/// let buffer = factory.open_buffer_with(bytes.clone(), NP_Buffer_Options {
+///     limits: NP_Limits { max_json_size: 16, ..Default::default() },
+///     ..Default::default()
+/// });
+/// assert!(matches!(buffer.json_encode(&[]), Err(NP_Error::LimitExceeded { .. })));
+/// 
+/// // buffers within every limit read normally
+/// let buffer = factory.open_buffer_with(bytes, NP_Buffer_Options {
+///     limits: NP_Limits { max_depth: 2, max_steps: 6, max_json_size: 32 },
+///     ..Default::default()
+/// });
+/// assert_eq!(buffer.json_encode(&[])?.stringify(), "[[null,null,null,null,2]]");
+/// assert_eq!(buffer.get::<u8>(&["0", "4"])?, Some(2));
+/// 
+/// # Ok::<(), NP_Error>(()) 
+/// ```
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct NP_Limits {
+    /// Most collections nested inside each other that a path or `json_encode` can go through
+    pub max_depth: usize,
+    /// Most collection items `json_encode` steps through, empty slots in lists that aren't wide count too
+    pub max_steps: usize,
+    /// Longest JSON text in bytes that `json_encode` and `json_write` produce
+    pub max_json_size: usize
+}
+
+impl NP_Limits {
+    /// No limits at all, the same as versions before 0.7.  Only use this for buffers from trusted sources.
+    pub const fn unlimited() -> Self {
+        NP_Limits { max_depth: usize::MAX, max_steps: usize::MAX, max_json_size: usize::MAX }
+    }
+}
+
+impl Default for NP_Limits {
+    /// 64 levels of nesting, a million collection items and 16MB of JSON
+    fn default() -> Self {
+        NP_Limits { max_depth: 64, max_steps: 1 << 20, max_json_size: 16 << 20 }
+    }
+}
+
+/// Whether `set_opts` makes the collections along a path that don't exist yet.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum NP_CreateIntermediate {
+    /// Make any missing collections along the path, like `set`
+    Yes,
+    /// Only set the value if every collection along the path already exists
+    No
 }
 
 /// When calling `maybe_compact` on a buffer, this struct is provided to help make a choice on wether to compact or not.
@@ -44,6 +227,77 @@ pub struct NP_Size_Data {
     pub wasted_bytes: usize
 }
 
+/// Memory usage of a buffer, returned from `memory_stats`
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct NP_Memory_Stats {
+    /// The size of the existing buffer
+    pub current_buffer: usize,
+    /// Capacity of the underlying `Vec<u8>`
+    pub capacity: usize,
+    /// How many times space was allocated in the buffer since it was created or opened
+    pub allocations: usize,
+    /// How many of those allocations made the underlying `Vec<u8>` grow (and likely copy)
+    pub growth_events: usize,
+    /// Bytes used by pointers and collection structure (the root pointer, table vtables, list and map items)
+    pub pointer_bytes: usize,
+    /// Bytes used by scalar values, values shared by several pointers are counted once
+    pub payload_bytes: usize,
+    /// Bytes not used by any value, compaction removes these
+    pub wasted_bytes: usize,
+    /// Bytes used by each type, collections only count their own structure.  Sorted by type key.
+    pub bytes_by_type: Vec<(NP_TypeKeys, usize)>
+}
+
+/// Bytes used by one schema node, returned from `size_report`
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct NP_Node_Size {
+    /// Path to the node in the schema, `*` stands for every item of a list, log or map
+    pub path: Vec<String>,
+    /// How many values are set at this node
+    pub values: usize,
+    /// Bytes used by those values after compaction, everything below them and their pointers included
+    pub bytes: usize
+}
+
+/// The state of a value returned from `get_state`
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum NP_ValueState<T> {
+    /// There's no value here and the schema doesn't have a default
+    Unset,
+    /// There's no value here, this is the default from the schema
+    Default(T),
+    /// This value is stored in the buffer
+    Set(T)
+}
+
+impl<T> NP_ValueState<T> {
+    /// The value or schema default, same as the result of `get`
+    pub fn value(self) -> Option<T> {
+        match self {
+            NP_ValueState::Unset => None,
+            NP_ValueState::Default(x) => Some(x),
+            NP_ValueState::Set(x) => Some(x)
+        }
+    }
+
+    /// If this value is stored in the buffer
+    pub fn is_set(&self) -> bool {
+        match self {
+            NP_ValueState::Set(_) => true,
+            _ => false
+        }
+    }
+}
+
+/// How `json_encode_redacted` hides sensitive table columns
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum NP_Redaction<'mask> {
+    /// Leave sensitive columns out of the JSON
+    Omit,
+    /// Replace sensitive values with this string, unset columns are still `null`
+    Mask(&'mask str)
+}
+
 impl<'buffer> NP_Buffer<'buffer> {
 
     #[doc(hidden)]
@@ -61,11 +315,18 @@ impl<'buffer> NP_Buffer<'buffer> {
             _ => {}
         };
 
+        np_event!(bytes = memory.read_bytes().len(), "open");
+
         NP_Buffer {
             cursor: NP_Cursor::new(ROOT_PTR_ADDR, 0, 0),
             memory: memory,
             sortable: is_sortable,
-            backup_cursor: NP_Cursor::new(ROOT_PTR_ADDR, 0, 0)
+            backup_cursor: NP_Cursor::new(ROOT_PTR_ADDR, 0, 0),
+            cursor_hash: SEED,
+            backup_hash: SEED,
+            clock: None,
+            hlc: None,
+            overflow: None
         }
     }
 
@@ -97,16 +358,173 @@ impl<'buffer> NP_Buffer<'buffer> {
     /// 
     pub fn json_encode(&self, path: &[&str]) -> Result<NP_JSON, NP_Error> {
 
-        let value_cursor = self.select(self.cursor.clone(), false, path)?;
+        let value_cursor = self.select(self.cursor.clone(), path)?;
+
+        if let Some(x) = value_cursor {
+            NP_Cursor::json_encode_limited(&x, &self.memory, path.len())
+        } else {
+            Ok(NP_JSON::Null)
+        }
+
+    }
+
+    /// Copy an object at the provided path and all it's children into JSON, hiding sensitive table columns.
+    /// 
+    /// Columns are sensitive when their schema has `"sensitive": true`.  Sensitive columns of every table below the path are left out or masked, use this to write logs and support dumps without personal data.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Redaction;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["email", {"type": "string", "sensitive": true}],
+    ///         ["phone", {"type": "string", "sensitive": true}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["name"], "Jeb Kermin")?;
+    /// new_buffer.set(&["email"], "jeb@ksc.com")?;
+    /// 
+    /// assert_eq!("{\"name\":\"Jeb Kermin\"}", new_buffer.json_encode_redacted(&[], NP_Redaction::Omit)?.stringify());
+    /// assert_eq!("{\"name\":\"Jeb Kermin\",\"email\":\"***\",\"phone\":null}", new_buffer.json_encode_redacted(&[], NP_Redaction::Mask("***"))?.stringify());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn json_encode_redacted(&self, path: &[&str], redaction: NP_Redaction) -> Result<NP_JSON, NP_Error> {
+
+        let value_cursor = self.select(self.cursor.clone(), path)?;
 
         if let Some(x) = value_cursor {
-            Ok(NP_Cursor::json_encode(&x, &self.memory))
+            let mut json = NP_Cursor::json_encode_limited(&x, &self.memory, path.len())?;
+            redact_json(&mut json, self.memory.schema, x.schema_addr, &redaction);
+            Ok(json)
         } else {
             Ok(NP_JSON::Null)
         }
 
     }
 
+    /// Copy the values covered by a mask into JSON, starting at the root of the buffer.
+    /// 
+    /// Table columns and map keys the mask doesn't cover are left out, list items it doesn't cover are `null`.  See the [mask](../mask/index.html) module for an example.
+    /// 
+    pub fn json_encode_masked(&self, mask: &NP_Mask) -> Result<NP_JSON, NP_Error> {
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        Ok(mask.prune(NP_Cursor::json_encode_limited(&root, &self.memory, 0)?, &[]).unwrap_or(NP_JSON::Null))
+    }
+
+    /// Write an object at the provided path and all it's children as JSON text, without building an `NP_JSON` copy first.
+    /// 
+    /// The text is the same as `json_encode(path)?.stringify()`, but collections and strings are streamed straight from the buffer.  Use `json_write` to stream into an `std::io::Write`.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["0"], "hello")?;
+    /// new_buffer.set(&["2"], "world")?;
+    /// 
+    /// let mut json = String::new();
+    /// new_buffer.json_write_fmt(&[], &mut json)?;
+    /// assert_eq!("[\"hello\",null,\"world\"]", json);
+    /// assert_eq!(new_buffer.json_encode(&[])?.stringify(), json);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn json_write_fmt<W: core::fmt::Write>(&self, path: &[&str], out: &mut W) -> Result<(), NP_Error> {
+
+        match self.select(self.cursor, path)? {
+            Some(x) => NP_Cursor::json_write_limited(&x, &self.memory, path.len(), out),
+            None => core::fmt::Write::write_str(out, "null").map_err(|_| NP_Error::new_static("Failed to write JSON!"))
+        }
+    }
+
+    /// Stream an object at the provided path and all it's children as JSON text into a writer, needs the `std` feature.
+    /// 
+    /// Writes happen a few bytes at a time, wrap files and sockets in a `std::io::BufWriter`.
+    /// 
+    /// ```
+    /// # #[cfg(feature = "std")] {
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "map",
+    ///    "value": {"type": "u32"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["visits"], 20u32)?;
+    /// 
+    /// let mut bytes: Vec<u8> = Vec::new();
+    /// new_buffer.json_write(&[], &mut bytes)?;
+    /// assert_eq!(bytes, b"{\"visits\":20}");
+    /// 
+    /// # }
+    /// # Ok::<(), no_proto::error::NP_Error>(()) 
+    /// ```
+    /// 
+    #[cfg(feature = "std")]
+    pub fn json_write<W: std::io::Write>(&self, path: &[&str], out: &mut W) -> Result<(), NP_Error> {
+
+        // keeps the io error so it can be returned instead of fmt's empty error
+        struct Adapter<'out, W: std::io::Write> {
+            out: &'out mut W,
+            error: Option<std::io::Error>
+        }
+
+        impl<'out, W: std::io::Write> core::fmt::Write for Adapter<'out, W> {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                self.out.write_all(s.as_bytes()).map_err(|e| {
+                    self.error = Some(e);
+                    core::fmt::Error
+                })
+            }
+        }
+
+        let mut adapter = Adapter { out, error: None };
+
+        match self.json_write_fmt(path, &mut adapter) {
+            Err(e) => match adapter.error {
+                Some(io_error) => Err(NP_Error::new(io_error.to_string())),
+                None => Err(e)
+            },
+            Ok(()) => Ok(())
+        }
+    }
+
+    /// Export the buffer as a BSON document, needs the `bson` feature.
+    /// 
+    /// The root of the schema must be a table or map, the export always starts at the root even if the cursor has moved.  See the [bson](../bson/index.html) module for how each type is written.
+    /// 
+    #[cfg(feature = "bson")]
+    pub fn to_bson(&self) -> Result<Vec<u8>, NP_Error> {
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        crate::bson::encode(&NP_Cursor::json_encode_limited(&root, &self.memory, 0)?, self.memory.schema, 0)
+    }
+
+    /// Encrypt the buffer with ChaCha20-Poly1305, needs the `seal` feature.
+    /// 
+    /// The sealed bytes keep a cleartext header with the schema fingerprint and the nonce, open them again with `NP_Factory::open_sealed`.  Never use the same nonce twice with one key.  See the [seal](../seal/index.html) module for the format and an example.
+    /// 
+    #[cfg(feature = "seal")]
+    pub fn seal(&self, key: &[u8; 32], nonce: &[u8; 12]) -> Result<Vec<u8>, NP_Error> {
+        crate::seal::seal(self.memory.fingerprint(), self.memory.read_bytes(), key, nonce)
+    }
+
     /// Moves the underlying bytes out of the buffer, consuming the buffer in the process.
     /// 
     /// ```
@@ -178,7 +596,7 @@ impl<'buffer> NP_Buffer<'buffer> {
         match &self.memory.schema[0] {
             NP_Parsed_Schema::Tuple { values, sortable, .. } => {
                 if *sortable == false {
-                    Err(NP_Error::new("Attempted to close_sortable() on buffer that isn't sortable!"))
+                    Err(NP_Error::new_static("Attempted to close_sortable() on buffer that isn't sortable!"))
                 } else {
                     let mut vtables = 1usize;
                     let mut length = values.len();
@@ -193,13 +611,18 @@ impl<'buffer> NP_Buffer<'buffer> {
                     Ok(closed_vec[root_offset..].to_vec())
                 }
             },
-            _ => Err(NP_Error::new("Attempted to close_sortable() on buffer that isn't sortable!"))
+            _ => Err(NP_Error::new_static("Attempted to close_sortable() on buffer that isn't sortable!"))
         }
     }
 
+    // same schema fingerprint, the schema compiles to the same bytes
+    pub(crate) fn has_schema(&self, fingerprint: u64) -> bool {
+        self.memory.fingerprint() == fingerprint
+    }
+
     /// Read the bytes of the buffer immutably.  No touching!
     /// 
-    pub fn read_bytes(&self) -> &Vec<u8> {
+    pub fn read_bytes(&self) -> &[u8] {
         self.memory.read_bytes()
     }
 
@@ -209,7 +632,7 @@ impl<'buffer> NP_Buffer<'buffer> {
     /// 
     pub fn move_cursor(&mut self, path: &[&str]) -> Result<bool, NP_Error> {
 
-        let value_cursor = self.select(self.cursor.clone(), true, path)?;
+        let value_cursor = self.select_make(self.cursor.clone(), path)?;
 
         let cursor = if let Some(x) = value_cursor {
             x
@@ -218,6 +641,7 @@ impl<'buffer> NP_Buffer<'buffer> {
         };
 
         self.cursor = cursor;
+        self.cursor_hash = tombstone::hash_path(self.cursor_hash, path);
 
         Ok(true)
     }
@@ -226,23 +650,119 @@ impl<'buffer> NP_Buffer<'buffer> {
     /// 
     pub fn backup_cursor(&mut self) {
         self.backup_cursor = self.cursor.clone();
+        self.backup_hash = self.cursor_hash;
     }
 
     /// Restore the backed up cursor location
     /// 
     pub fn restore_cursor(&mut self) {
         self.cursor = self.backup_cursor.clone();
+        self.cursor_hash = self.backup_hash;
     }
 
     /// Moves cursor position to root of buffer, the default.
     /// 
     pub fn cursor_to_root(&mut self) {
         self.cursor = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        self.cursor_hash = SEED;
+    }
+
+    /// Get a handle to this buffer that can only read and write the paths in `mask`.
+    /// 
+    /// The cursor is moved to the root, paths used with the view are always relative to the root.  See the [view](../view/index.html) module for an example.
+    /// 
+    pub fn view<'view>(&'view mut self, mask: &'view NP_FieldMask) -> NP_Buffer_View<'view, 'buffer> {
+        self.cursor_to_root();
+        NP_Buffer_View::_new(self, mask)
+    }
+
+    /// Get a read only snapshot of the buffer as it is now, the buffer can keep changing without changing the snapshot.
+    /// 
+    /// The snapshot shares the bytes written so far, the buffer copies them the next time it's used.  See the [snapshot](../snapshot/index.html) module for an example.
+    /// 
+    pub fn freeze(&mut self) -> NP_Snapshot<'buffer> {
+        NP_Snapshot::_new(self.frozen())
+    }
+
+    /// Turn the buffer into a reader that can be cloned and sent to other threads, see the [reader](../reader/index.html) module for an example.
+    pub fn into_reader(self) -> NP_Buffer_Reader<'buffer> {
+        NP_Buffer_Reader::_new(self.frozen())
+    }
+
+    /// Turn the buffer into a compactor that compacts it a few values at a time, see the [compactor](../compactor/index.html) module for an example.
+    /// 
+    /// The first argument, new_capacity, is the same as in `compact`.
+    /// 
+    pub fn into_compactor(self, new_capacity: Option<u32>) -> NP_Compactor<'buffer> {
+        NP_Compactor::_new(self, new_capacity)
+    }
+
+    /// Turn the buffer into a writer, see the [reader](../reader/index.html) module for an example.
+    pub fn into_writer(self) -> NP_Buffer_Writer<'buffer> {
+        NP_Buffer_Writer::_new(self)
+    }
+
+    // read only copy of the buffer sharing the bytes written so far
+    pub(crate) fn frozen(&self) -> NP_Buffer<'buffer> {
+        NP_Buffer { memory: self.memory.freeze(), ..*self }
+    }
+
+    // the bytes written so far and everything needed to read them, none of it changes so it can be shared between threads
+    pub(crate) fn share(&self) -> NP_Shared_Buffer<'buffer> {
+        NP_Shared_Buffer {
+            bytes: self.memory.share(),
+            schema: self.memory.factory_schema,
+            options: self.memory.options(),
+            cursor: self.cursor,
+            sortable: self.sortable,
+            backup_cursor: self.backup_cursor,
+            cursor_hash: self.cursor_hash,
+            backup_hash: self.backup_hash,
+            clock: self.clock,
+            hlc: self.hlc,
+            overflow: self.overflow
+        }
+    }
+
+    // writable again, the bytes are copied first if anything still shares them
+    pub(crate) fn thawed(self) -> NP_Buffer<'buffer> {
+        NP_Buffer { memory: self.memory.thaw(), ..self }
     }
 
     /// Used to set scalar values inside the buffer.
     /// 
-    /// The type that you cast the request to will be compared to the schema, if it doesn't match the schema the request will fail.
+    /// The type that you cast the request to will be compared to the schema.  Numbers, strings, bools and bytes that don't match the schema are converted when nothing is lost: integers into wider integers, floats or decimals, strings into dates (RFC 3339), uuids, ulids, options or bigints, anything into `any`.  A number that doesn't fit returns `NP_Error::Overflow` unless the [overflow policy](../pointer/numbers/enum.NP_Overflow.html) says otherwise, one that would lose precision returns `NP_Error::Lossy` and other types fail with `NP_Error::TypeMismatch`.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::pointer::{date::NP_Date, dec::NP_Dec};
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["count", {"type": "u64"}],
+    ///         ["price", {"type": "decimal", "exp": 2}],
+    ///         ["when",  {"type": "date"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["count"], 20u8)?;
+    /// new_buffer.set(&["price"], 2.5f32)?;
+    /// new_buffer.set(&["when"], "2020-11-12T00:56:20.392Z")?;
+    /// 
+    /// assert_eq!(new_buffer.get::<u64>(&["count"])?, Some(20));
+    /// assert_eq!(new_buffer.get::<NP_Dec>(&["price"])?, Some(NP_Dec::new(250, 2)));
+    /// assert_eq!(new_buffer.get::<NP_Date>(&["when"])?, Some(NP_Date::new(1605142580392)));
+    /// 
+    /// // a third of a cent doesn't fit
+    /// assert_eq!(new_buffer.set(&["price"], 2.333f32), Err(NP_Error::Lossy { expected: "decimal".to_owned(), path: vec!["price".to_owned()] }));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// Values can be set at any depth of the buffer.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
@@ -271,828 +791,3178 @@ impl<'buffer> NP_Buffer<'buffer> {
     /// ```
     /// 
     pub fn set<X: 'buffer>(&mut self, path: &[&str], value: X) -> Result<bool, NP_Error> where X: NP_Value<'buffer> + NP_Scalar {
-        let value_cursor = self.select(self.cursor.clone(), true, path)?;
-        match value_cursor {
-            Some(x) => {
-
-                // type does not match schema
-                if X::type_idx().1 != *self.memory.schema[x.schema_addr].get_type_key() {
-                    let mut err = "TypeError: Attempted to set value for type (".to_owned();
-                    err.push_str(X::type_idx().0);
-                    err.push_str(") into schema of type (");
-                    err.push_str(self.memory.schema[x.schema_addr].get_type_data().0);
-                    err.push_str(")\n");
-                    return Err(NP_Error::new(err));
-                }
+        self.set_from(self.cursor.clone(), path, value, self.overflow)
+    }
 
-                X::set_value(x, &self.memory, value)?;
-                Ok(true)
-            }
-            None => Ok(false)
-        }
+    /// Same as `set`, but numbers converted into an integer type that don't fit are handled with `overflow` instead of the buffer or schema policy.
+    /// 
+    /// See [NP_Overflow](../pointer/numbers/enum.NP_Overflow.html) for an example.
+    /// 
+    pub fn set_with_overflow<X>(&mut self, path: &[&str], value: X, overflow: NP_Overflow) -> Result<bool, NP_Error> where X: NP_Value<'buffer> + NP_Scalar + 'buffer {
+        self.set_from(self.cursor, path, value, Some(overflow))
     }
 
-    
-    /// Get an iterator for a collection
+    /// Same as `set`, but `create` decides if collections along the path that don't exist yet are made.
     /// 
+    /// With `NP_CreateIntermediate::No` nothing is set and `false` is returned if any collection on the path (including the one at the cursor) is missing.  The value itself is still made inside the last collection, a new list item or map key is fine.
     /// 
-    /// ## List Example
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    /// use no_proto::buffer::NP_CreateIntermediate;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "list",
-    ///     "of": {"type": "string"}
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
+    ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set value at 1 index
-    /// new_buffer.set(&["1"], "hello")?;
-    /// // set value at 4 index
-    /// new_buffer.set(&["4"], "world")?;
-    /// // push value onto the end
-    /// new_buffer.list_push(&[], "!")?;
+    /// // the root table doesn't exist yet
+    /// assert_eq!(new_buffer.set_opts(&["name"], "Jeb", NP_CreateIntermediate::No)?, false);
+    /// assert_eq!(new_buffer.set_opts(&["name"], "Jeb", NP_CreateIntermediate::Yes)?, true);
     /// 
-    /// // get iterator of root (list item)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     match item.index {
-    ///         0 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         1 => assert_eq!(item.get::<&str>().unwrap(), Some("hello")),
-    ///         2 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         3 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         4 => assert_eq!(item.get::<&str>().unwrap(), Some("world")),
-    ///         5 => assert_eq!(item.get::<&str>().unwrap(), Some("!")),
-    ///         _ => panic!()
-    ///     };
-    /// });
+    /// // the tags list doesn't exist yet
+    /// assert_eq!(new_buffer.set_opts(&["tags", "2"], "pilot", NP_CreateIntermediate::No)?, false);
+    /// assert_eq!(new_buffer.get::<&str>(&["tags", "2"])?, None);
+    /// 
+    /// new_buffer.set(&["tags", "0"], "brave")?;
+    /// assert_eq!(new_buffer.set_opts(&["tags", "2"], "pilot", NP_CreateIntermediate::No)?, true);
+    /// assert_eq!(new_buffer.get::<&str>(&["tags", "2"])?, Some("pilot"));
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    /// ## Table Example
+    pub fn set_opts<X>(&mut self, path: &[&str], value: X, create: NP_CreateIntermediate) -> Result<bool, NP_Error> where X: NP_Value<'buffer> + NP_Scalar + 'buffer {
+        if create == NP_CreateIntermediate::No && !path.is_empty() {
+            match self.select(self.cursor, &path[..(path.len() - 1)])? {
+                Some(parent) if parent.get_value(&self.memory).get_addr_value() != 0 => { },
+                _ => return Ok(false)
+            }
+        }
+        self.set_from(self.cursor, path, value, self.overflow)
+    }
+
+    /// Make sure a write of `bytes` bytes at `path` fits before the buffer reaches its max size, and grow the buffer so the write won't have to copy it.
+    /// 
+    /// Collections and items missing along the path are counted too, so oversized input can be turned away before any of it is written.  If the write could go past the max size (see `NP_Factory::empty_buffer_with`) `NP_Error::TooLarge` is returned and nothing changes.  `set` makes the same check with the size of its value, a write is refused instead of being left half done (big integers and custom values can still run out of space partway).  Overwriting a value that fits where it already is never needs more room.
+    /// 
+    /// The check counts the most bytes the write could need, a write that would just barely fit can be refused.
+    /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    /// use no_proto::buffer::{NP_Buffer_Options, NP_Growth};
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
     ///    "type": "table",
     ///    "columns": [
-    ///         ["age", {"type": "uint8"}],
     ///         ["name", {"type": "string"}],
-    ///         ["job", {"type": "string"}],
     ///         ["tags", {"type": "list", "of": {"type": "string"}}]
     ///     ]
     /// }"#)?;
     /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set value of age
-    /// new_buffer.set(&["age"], 20u8)?;
-    /// // set value of name
-    /// new_buffer.set(&["name"], "Bill Kerman")?;
-    /// // push value onto tags list
-    /// new_buffer.list_push(&["tags"], "rocket")?;
+    /// let mut new_buffer = factory.empty_buffer_with(None, NP_Buffer_Options {
+    ///     max_size: 128,
+    ///     growth: NP_Growth::Double,
+    ///     ..Default::default()
+    /// });
     /// 
-    /// // get iterator of root (table)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     
-    ///     match item.key {
-    ///         "name" => assert_eq!(item.get::<&str>().unwrap(), Some("Bill Kerman")),
-    ///         "age" =>  assert_eq!(item.get::<u8>().unwrap(), Some(20)),
-    ///         "job" => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         "tags" => { /* tags column is list, can't do anything with it here */ },
-    ///         _ => { panic!() }
-    ///     };
-    /// });
+    /// new_buffer.try_reserve(&["name"], 20)?;
+    /// new_buffer.set(&["name"], "Jeb Kermin")?;
     /// 
-    /// // we can also loop through items of the tags list
-    /// new_buffer.get_iter(&["tags"])?.unwrap().into_iter().for_each(|item| {
-    ///     assert_eq!(item.index, 0);
-    ///     assert_eq!(item.get::<&str>().unwrap(), Some("rocket"));
+    /// // too much input is turned away before anything is written
+    /// assert!(matches!(new_buffer.try_reserve(&["tags", "0"], 200), Err(NP_Error::TooLarge { .. })));
+    /// 
+    /// let before = new_buffer.read_bytes().to_vec();
+    /// let long_tag = "x".repeat(200);
+    /// assert!(matches!(new_buffer.set(&["tags", "0"], long_tag.as_str()), Err(NP_Error::TooLarge { .. })));
+    /// assert_eq!(new_buffer.read_bytes(), &before);
+    /// assert_eq!(new_buffer.get::<&str>(&["name"])?, Some("Jeb Kermin"));
+    /// 
+    /// // values that fit where they already are don't need any room
+    /// let counter: NP_Factory = NP_Factory::new(r#"{"type": "table", "columns": [["a", {"type": "u8"}]]}"#)?;
+    /// let mut small = counter.empty_buffer(None);
+    /// small.set(&["a"], 1u8)?;
+    /// 
+    /// let bytes = small.read_bytes().to_vec();
+    /// let mut full = counter.open_buffer_with(bytes.clone(), NP_Buffer_Options {
+    ///     max_size: bytes.len() + 1,
+    ///     ..Default::default()
     /// });
+    /// full.set(&["a"], 2u8)?;
+    /// assert_eq!(full.get::<u8>(&["a"])?, Some(2));
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    /// ## Map Example
+    pub fn try_reserve(&mut self, path: &[&str], bytes: usize) -> Result<(), NP_Error> {
+        let needed = path_cost(&self.memory, self.cursor, path)?.0 + bytes;
+        let available = self.memory.available();
+        if needed > available {
+            return Err(NP_Error::TooLarge { path: path.iter().map(|p| p.to_string()).collect(), needed, available });
+        }
+        self.memory.try_reserve(needed)
+    }
+
+    /// Set a decimal value, digits past the `exp` of the schema are rounded with `rounding` instead of the schema's mode.
+    /// 
+    /// See the [dec](../pointer/dec/index.html#rounding) module for an example.
+    /// 
+    pub fn set_rounded(&mut self, path: &[&str], value: NP_Dec, rounding: NP_Rounding) -> Result<bool, NP_Error> {
+        let value_cursor = match self.select_make(self.cursor, path)? {
+            Some(x) => x,
+            None => return Ok(false)
+        };
+
+        match &self.memory.schema[value_cursor.schema_addr] {
+            NP_Parsed_Schema::Decimal { exp, .. } => {
+                let rounded = value.round_to(*exp, rounding).ok_or_else(|| NP_Error::new_static("Decimal doesn't fit in the exp of the schema!"))?;
+                NP_Dec::set_value(value_cursor, &self.memory, rounded)?;
+                self.tick();
+                Ok(true)
+            },
+            schema => Err(NP_Error::new_type_mismatch(schema.get_type_name(), "decimal", path))
+        }
+    }
+
+    /// Handle integer overflows with this policy instead of the one in the schema, `None` goes back to the schema.
+    /// 
+    /// Used by `set` and `increment`, the policy isn't saved with the buffer.  See [NP_Overflow](../pointer/numbers/enum.NP_Overflow.html) for an example.
+    /// 
+    pub fn set_overflow(&mut self, overflow: Option<NP_Overflow>) {
+        self.overflow = overflow;
+    }
+
+    /// Set the value of a buffer whose root is a scalar type.
+    /// 
+    /// Same as `set` with an empty path, except the value is always set at the root of the buffer even if the cursor has been moved.
+    /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    /// use no_proto::pointer::ulid::NP_ULID;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "map",
-    ///    "value": {"type": "string"}
+    ///    "type": "ulid"
     /// }"#)?;
     /// 
+    /// let id = NP_ULID::generate(1604965249484, 50);
+    /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set value of color key
-    /// new_buffer.set(&["color"], "blue")?;
-    /// // set value of sport key
-    /// new_buffer.set(&["sport"], "soccor")?;
+    /// new_buffer.set_root(&id)?;
     /// 
-    /// // get iterator of root (map)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     
-    ///     match item.key {
-    ///         "color" => assert_eq!(item.get::<&str>().unwrap(), Some("blue")),
-    ///         "sport" => assert_eq!(item.get::<&str>().unwrap(), Some("soccor")),
-    ///         _ => panic!()
-    ///     }
-    /// });
+    /// assert_eq!(new_buffer.get_root::<&NP_ULID>()?, Some(&id));
+    /// assert_eq!(new_buffer.get_root::<&NP_ULID>()?.unwrap().get_time(), 1604965249484);
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    /// ## Tuple Example
+    pub fn set_root<X: 'buffer>(&mut self, value: X) -> Result<bool, NP_Error> where X: NP_Value<'buffer> + NP_Scalar {
+        self.set_from(NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), &[], value, self.overflow)
+    }
+
+    fn set_from<X: 'buffer>(&mut self, cursor: NP_Cursor, path: &[&str], value: X, overflow: Option<NP_Overflow>) -> Result<bool, NP_Error> where X: NP_Value<'buffer> + NP_Scalar {
+
+        np_span!("set", path = ?path);
+
+        // refuse writes that could run out of space before any of the path is made
+        let (cost, value_schema, reached, depth) = path_cost(&self.memory, cursor, path)?;
+        let existing = if depth == path.len() { Some(reached) } else { None };
+        let needed = cost + value_cost(&self.memory, value_schema, existing, value.as_dyn());
+        if needed > 0 {
+            let available = self.memory.available();
+            if needed > available {
+                return Err(NP_Error::TooLarge { path: path.iter().map(|p| p.to_string()).collect(), needed, available });
+            }
+        }
+
+        // only the part of the path that doesn't exist yet is walked again
+        let value_cursor = self.select_make(reached, &path[depth..])?;
+        match value_cursor {
+            Some(x) => {
+
+                // type does not match schema, convert the value if nothing is lost
+                if X::type_idx().1 != *self.memory.schema[x.schema_addr].get_type_key() {
+                    let converted = match value.as_dyn() {
+                        Some(dyn_value) => NP_Cursor::set_coerced(x, &self.memory, dyn_value, overflow, path)?,
+                        None => false
+                    };
+                    if !converted {
+                        return Err(NP_Error::new_type_mismatch(self.memory.schema[x.schema_addr].get_type_name(), X::type_idx().0, path));
+                    }
+                    self.tick();
+                    return Ok(true);
+                }
+
+                X::set_value(x, &self.memory, value)?;
+                self.tick();
+                Ok(true)
+            }
+            None => Ok(false)
+        }
+    }
+
+    /// Set an `any` value at the provided path, the type of the value is stored with it.
+    /// 
+    /// See the [any](../pointer/any/index.html) module for an example.
+    /// 
+    pub fn set_dyn(&mut self, path: &[&str], value: NP_Dyn<'buffer>) -> Result<bool, NP_Error> {
+        self.set(path, value)
+    }
+
+    /// Get an `any` value at the provided path as whatever type it was set with.
+    /// 
+    /// See the [any](../pointer/any/index.html) module for an example.
+    /// 
+    pub fn get_dyn<'get>(&'get self, path: &[&str]) -> Result<Option<NP_Dyn<'get>>, NP_Error> {
+        self.get(path)
+    }
+
+    /// Write the schema default into the buffer at the provided path.
+    /// 
+    /// Unlike `del`, the default is stored in the buffer so it takes up space and shows up as a set value with `get_state`.  This is useful for patches that need to say "set this back to the default" instead of "remove this".  If the schema doesn't have a default the data type default is used (`0`, `false`, empty strings, etc).
+    /// 
+    /// Only works on scalar values.
+    /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    /// use no_proto::buffer::NP_ValueState;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "tuple",
-    ///     "values": [
-    ///         {"type": "string"},
-    ///         {"type": "u8"},
-    ///         {"type": "bool"}
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["color", {"type": "string", "default": "red"}],
+    ///         ["count", {"type": "u32"}]
     ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set value at 0 index
-    /// new_buffer.set(&["0"], "hello")?;
-    /// // set value at 2 index
-    /// new_buffer.set(&["2"], false)?;
+    /// new_buffer.set(&["color"], "blue")?;
+    /// new_buffer.set(&["count"], 12u32)?;
     /// 
-    /// // get iterator of root (tuple item)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     match item.index {
-    ///         0 => assert_eq!(item.get::<&str>().unwrap(), Some("hello")),
-    ///         1 => assert_eq!(item.get::<u8>().unwrap(), None),
-    ///         2 => assert_eq!(item.get::<bool>().unwrap(), Some(false)),
-    ///         _ => panic!()
-    ///     };
-    /// });
+    /// new_buffer.reset(&["color"])?;
+    /// new_buffer.reset(&["count"])?;
+    /// assert_eq!(new_buffer.get_state::<&str>(&["color"])?, NP_ValueState::Set("red"));
+    /// assert_eq!(new_buffer.get_state::<u32>(&["count"])?, NP_ValueState::Set(0));
+    /// 
+    /// new_buffer.del(&["color"])?;
+    /// assert_eq!(new_buffer.get_state::<&str>(&["color"])?, NP_ValueState::Default("red"));
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn get_iter<'iter>(&'iter self, path: &'iter [&str]) -> Result<Option<NP_Generic_Iterator<'iter>>, NP_Error> {
-
-        let value = self.select(self.cursor.clone(), false, path)?;
-
-        let value = if let Some(x) = value {
-            x
-        } else {
-            return Ok(None);
-        };
-
-        let value_data = value.get_value(&self.memory);
+    pub fn reset(&mut self, path: &[&str]) -> Result<bool, NP_Error> {
+        let value_cursor = self.select_make(self.cursor.clone(), path)?;
+        match value_cursor {
+            Some(x) => {
+                match self.memory.schema[x.schema_addr].get_type_key() {
+                    NP_TypeKeys::Table | NP_TypeKeys::Tuple | NP_TypeKeys::List | NP_TypeKeys::Map | NP_TypeKeys::Log | NP_TypeKeys::Any | NP_TypeKeys::None => {
+                        return Err(NP_Error::new_static("Only scalar values can be reset to their default!"));
+                    },
+                    _ => { }
+                }
 
-        // value doesn't exist
-        if value_data.get_addr_value() == 0 {
-            return Ok(None);
+                NP_Cursor::set_schema_default(x, &self.memory)?;
+                self.tick();
+                Ok(true)
+            }
+            None => Ok(false)
         }
-
-        Ok(Some(NP_Generic_Iterator::new(value, &self.memory)?))
     }
 
-    /// Push a value onto the end of a list.
-    /// The path provided must resolve to a list type, and the type being pushed must match the schema
+    /// Add to an integer value in a single call and get the new value back.
     /// 
-    /// This is the most efficient way to add values to a list type.
+    /// Unset values count from the schema default (or zero).  The type of `delta` must match the schema, use a negative `delta` on signed types to count down.
+    /// 
+    /// If the result doesn't fit in the integer type `NP_Error::Overflow` is returned and the value is left alone, unless the schema has `"overflow": "saturate"` which clamps the value at the type's minimum or maximum or `"overflow": "wrap"` which wraps around.  `set_overflow` changes the policy for the whole buffer.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "list",
-    ///     "of": {"type": "string"}
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["views",   {"type": "u32", "default": 10}],
+    ///         ["retries", {"type": "u8", "overflow": "saturate"}],
+    ///         ["score",   {"type": "i8"}]
+    ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// new_buffer.set(&["3"], "launch")?;
-    /// new_buffer.list_push(&[], "this")?;
-    /// new_buffer.list_push(&[], "rocket")?;
-    /// 
-    /// // get iterator of root (list item)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     match item.index {
-    ///         0 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         1 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         2 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         3 => assert_eq!(item.get::<&str>().unwrap(), Some("launch")),
-    ///         4 => assert_eq!(item.get::<&str>().unwrap(), Some("this")),
-    ///         5 => assert_eq!(item.get::<&str>().unwrap(), Some("rocket")),
-    ///         _ => panic!()
-    ///     };
-    /// });
+    /// assert_eq!(new_buffer.increment(&["views"], 1u32)?, Some(11));
+    /// assert_eq!(new_buffer.increment(&["views"], 5u32)?, Some(16));
     /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// new_buffer.list_push(&[], "launch")?;
-    /// new_buffer.list_push(&[], "this")?;
-    /// new_buffer.list_push(&[], "rocket")?;
+    /// assert_eq!(new_buffer.increment(&["retries"], 200u8)?, Some(200));
+    /// assert_eq!(new_buffer.increment(&["retries"], 200u8)?, Some(255));
     /// 
-    /// // get iterator of root (list item)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     match item.index {
-    ///         0 => assert_eq!(item.get::<&str>().unwrap(), Some("launch")),
-    ///         1 => assert_eq!(item.get::<&str>().unwrap(), Some("this")),
-    ///         2 => assert_eq!(item.get::<&str>().unwrap(), Some("rocket")),
-    ///         _ => panic!()
-    ///     };
-    /// });
+    /// assert_eq!(new_buffer.increment(&["score"], -100i8)?, Some(-100));
+    /// assert_eq!(new_buffer.increment(&["score"], -100i8), Err(NP_Error::Overflow { path: vec!["score".to_owned()] }));
+    /// assert_eq!(new_buffer.get::<i8>(&["score"])?, Some(-100));
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn list_push<X>(&mut self, path: &[&str], value: X) -> Result<Option<u16>, NP_Error> where X: NP_Value<'buffer> + NP_Scalar {
+    pub fn increment<X: 'buffer>(&mut self, path: &[&str], delta: X) -> Result<Option<X>, NP_Error> where X: NP_Value<'buffer> + NP_Scalar + NP_Counter {
+        self.increment_from(path, delta, self.overflow)
+    }
+
+    /// Same as `increment`, but an overflow is handled with `overflow` instead of the buffer or schema policy.
+    /// 
+    pub fn increment_with_overflow<X>(&mut self, path: &[&str], delta: X, overflow: NP_Overflow) -> Result<Option<X>, NP_Error> where X: NP_Value<'buffer> + NP_Scalar + NP_Counter + 'buffer {
+        self.increment_from(path, delta, Some(overflow))
+    }
 
-        let list_cursor = if path.len() == 0 { self.cursor.clone() } else { match self.select(self.cursor.clone(), true, path)? {
+    fn increment_from<X>(&mut self, path: &[&str], delta: X, overflow: Option<NP_Overflow>) -> Result<Option<X>, NP_Error> where X: NP_Value<'buffer> + NP_Scalar + NP_Counter + 'buffer {
+        let value_cursor = match self.select_make(self.cursor, path)? {
             Some(x) => x,
             None => return Ok(None)
-        }};
-
-        match self.memory.schema[list_cursor.schema_addr] {
-            NP_Parsed_Schema::List { of, .. } => {
-
-                let of_schema = &self.memory.schema[of];
+        };
 
-                // type does not match schema
-                if X::type_idx().1 != *of_schema.get_type_key() {
-                    let mut err = "TypeError: Attempted to set value for type (".to_owned();
-                    err.push_str(X::type_idx().0);
-                    err.push_str(") into schema of type (");
-                    err.push_str(of_schema.get_type_data().0);
-                    err.push_str(")\n");
-                    return Err(NP_Error::new(err));
-                }
-            },
-            _ => return Err(NP_Error::new("Trying to push onto non list item!"))
+        // type does not match schema
+        if X::type_idx().1 != *self.memory.schema[value_cursor.schema_addr].get_type_key() {
+            return Err(NP_Error::new_type_mismatch(self.memory.schema[value_cursor.schema_addr].get_type_name(), X::type_idx().0, path));
         }
 
-        match NP_List::push(&list_cursor, &self.memory, None)? {
-            Some((index, new_item_addr)) => {
-                X::set_value(new_item_addr, &self.memory, value)?;
-                Ok(Some(index))
+        let overflow = overflow.unwrap_or_else(|| overflow_of(&self.memory.schema[value_cursor.schema_addr]));
+
+        match X::increment(value_cursor, &self.memory, delta, overflow)? {
+            Some(x) => {
+                self.tick();
+                Ok(Some(x))
             },
-            None => Ok(None)
+            None => Err(NP_Error::Overflow { path: path.iter().map(|p| p.to_string()).collect() })
         }
     }
 
-
-    /// Get length of String, Bytes, Table, Tuple, List or Map Type
-    /// 
-    /// If the type found at the path provided does not support length operations, you'll get `None`.
-    /// 
-    /// If there is no value at the path provodid, you will get `None`.
+    /// Append to the string at the provided path and get the new length in bytes.
     /// 
-    /// If an item is found and it's length is zero, you can expect `Some(0)`.
+    /// If the string is the last value in the buffer it grows in place, otherwise it's copied once to the end of the buffer.  Unset strings start from the schema default.
     /// 
-    /// ## String Example
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "string"
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["log", {"type": "string"}]
+    ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set initial value
-    /// new_buffer.set(&[], "hello")?;
-    /// // get length of value at root (String)
-    /// assert_eq!(new_buffer.length(&[])?, Some(5));
+    /// new_buffer.str_append(&["log"], "hello")?;
+    /// let size = new_buffer.calc_bytes()?.current_buffer;
+    /// 
+    /// // grows in place
+    /// assert_eq!(new_buffer.str_append(&["log"], ", world")?, Some(12));
+    /// assert_eq!(new_buffer.calc_bytes()?.current_buffer, size + 7);
+    /// 
+    /// // gives the space back
+    /// assert_eq!(new_buffer.str_truncate(&["log"], 5)?, Some(5));
+    /// assert_eq!(new_buffer.calc_bytes()?.current_buffer, size);
+    /// assert_eq!(new_buffer.get::<&str>(&["log"])?, Some("hello"));
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    /// ## Collection (List) Example
-    /// ```
-    /// use no_proto::error::NP_Error;
-    /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    pub fn str_append(&mut self, path: &[&str], more: &str) -> Result<Option<usize>, NP_Error> {
+        match self.select_string(path)? {
+            Some(x) => {
+                let len = string::append(x, &self.memory, more)?;
+                self.tick();
+                Ok(Some(len))
+            },
+            None => Ok(None)
+        }
+    }
+
+    /// Shorten the string at the provided path to `len` bytes and get the new length in bytes.
     /// 
-    /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "list",
-    ///     "of": {"type": "string"}
-    /// }"#)?;
+    /// Strings are shortened in place, strings at the end of the buffer give their space back.  Nothing happens if the string is already `len` bytes or shorter.  See `str_append` for an example.
     /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set value at 9th index
-    /// new_buffer.set(&["9"], "hello")?;
-    /// // get length of value at root (List)
-    /// assert_eq!(new_buffer.length(&[])?, Some(10));
+    pub fn str_truncate(&mut self, path: &[&str], len: usize) -> Result<Option<usize>, NP_Error> {
+        match self.select_string(path)? {
+            Some(x) => {
+                let len = string::truncate(x, &self.memory, len)?;
+                self.tick();
+                Ok(Some(len))
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn select_string(&mut self, path: &[&str]) -> Result<Option<NP_Cursor>, NP_Error> {
+        match self.select_make(self.cursor.clone(), path)? {
+            Some(x) => {
+                // type does not match schema
+                if NP_TypeKeys::UTF8String != *self.memory.schema[x.schema_addr].get_type_key() {
+                    return Err(NP_Error::new_type_mismatch(self.memory.schema[x.schema_addr].get_type_name(), "string", path));
+                }
+                Ok(Some(x))
+            },
+            None => Ok(None)
+        }
+    }
+
+
+    /// Set the value at the provided path from a JSON string.
     /// 
-    /// # Ok::<(), NP_Error>(()) 
-    /// ```
+    /// The JSON should be in the same format `json_encode` produces, collections are replaced with the provided values and `null` clears the value.
     /// 
-    /// ## Collection (Table) Example
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
     ///    "type": "table",
     ///    "columns": [
-    ///         ["age", {"type": "u8"}],
-    ///         ["name", {"type": "string"}]
+    ///         ["age", {"type": "uint8"}],
+    ///         ["name", {"type": "string"}],
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
     ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // get length of value at root (Table)
-    /// assert_eq!(new_buffer.length(&[])?, Some(2));
+    /// new_buffer.set_with_json(&[], r#"{"age": 30, "name": "Jeb Kermin", "tags": ["pilot"]}"#)?;
+    /// assert_eq!(new_buffer.get::<&str>(&["tags", "0"])?, Some("pilot"));
+    /// 
+    /// new_buffer.set_with_json(&["name"], "null")?;
+    /// assert_eq!("{\"age\":30,\"name\":null,\"tags\":[\"pilot\"]}", new_buffer.json_encode(&[])?.stringify());
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    /// ## Collection (Map) Example
+    pub fn set_with_json(&mut self, path: &[&str], json: &str) -> Result<bool, NP_Error> {
+        // parse everything first so invalid JSON never leaves a half written value behind
+        let value = json_decode(String::from(json))?;
+        self.set_json_value(path, &value)
+    }
+
+    /// Same as `set_with_json`, but takes an already parsed JSON value.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::json_flex::NP_JSON;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "u8"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set_json_value(&[], &NP_JSON::Array(vec![NP_JSON::Integer(1), NP_JSON::Integer(2)]))?;
+    /// assert_eq!(new_buffer.get::<u8>(&["1"])?, Some(2));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn set_json_value(&mut self, path: &[&str], value: &NP_JSON) -> Result<bool, NP_Error> {
+        let value_cursor = self.select_make(self.cursor.clone(), path)?;
+        match value_cursor {
+            Some(x) => {
+                NP_Cursor::set_from_json(x, &self.memory, value)?;
+                self.tick();
+                Ok(true)
+            }
+            None => Ok(false)
+        }
+    }
+
+    /// Copy the values covered by a mask into a new buffer with the same schema and options.
+    /// 
+    /// See the [mask](../mask/index.html) module for an example.
+    /// 
+    pub fn extract(&self, mask: &NP_Mask) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        let memory = NP_Memory::new(None, self.memory.factory_schema).with_options(self.memory.options());
+        if self.memory.has_inline() {
+            memory.start_inline();
+        }
+        if let Some(json) = mask.prune(NP_Cursor::json_encode_limited(&root, &self.memory, 0)?, &[]) {
+            NP_Cursor::set_from_json(root, &memory, &json)?;
+        }
+        Ok(NP_Buffer::_new(memory))
+    }
+
+    /// Replace the values covered by a mask with the values from another buffer, paths are relative to the root of both buffers.
+    /// 
+    /// Covered collections are replaced as a whole and covered values that aren't set in `other` are deleted.  Wildcards in the mask cover the keys of both buffers.  See the [mask](../mask/index.html) module for an example.
+    /// 
+    pub fn merge(&mut self, other: &NP_Buffer, mask: &NP_Mask) -> Result<(), NP_Error> {
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        let from = NP_Cursor::json_encode_limited(&root, &other.memory, 0)?;
+        let to = NP_Cursor::json_encode_limited(&root, &self.memory, 0)?;
+
+        for (path, value) in mask.expand(&from, &to) {
+            let path: Vec<&str> = path.iter().map(|key| key.as_str()).collect();
+            match value {
+                Some(value) if !matches!(value, NP_JSON::Null) => {
+                    if let Some(x) = self.select_make(root, &path)? {
+                        NP_Cursor::set_from_json(x, &self.memory, value)?;
+                    }
+                },
+                _ => {
+                    if let Some(x) = self.select(root, &path)? {
+                        NP_Cursor::set_from_json(x, &self.memory, &NP_JSON::Null)?;
+                    }
+                }
+            }
+        }
+
+        self.tick();
+        Ok(())
+    }
+
+    /// Merge two replicas of a buffer into a new buffer, using the merge strategy of each table column.  `b` is treated as the newer replica.
+    ///
+    /// Both buffers must have the same schema, the new buffer has the options of `a` and the tombstones of both.  If either buffer has a version, the version of the new buffer comes after both.  See the [crdt](../crdt/index.html) module for the strategies and an example.
+    ///
+    pub fn crdt_merge(a: &NP_Buffer<'buffer>, b: &NP_Buffer) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        if !a.has_schema(b.memory.fingerprint()) {
+            return Err(NP_Error::new_static("Can't merge buffers with different schemas!"));
+        }
+
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        let b_tombstones = b.tombstones();
+        let json = crdt::merge(a.memory.schema, &NP_Cursor::json_encode_limited(&root, &a.memory, 0)?, &NP_Cursor::json_encode_limited(&root, &b.memory, 0)?, &b_tombstones)?;
+
+        let memory = NP_Memory::new(None, a.memory.factory_schema).with_options(a.memory.options());
+        if a.memory.has_inline() {
+            memory.start_inline();
+        }
+        let tombstones = a.memory.has_tombstones() || b.memory.has_tombstones();
+        if tombstones {
+            memory.start_tombstones()?;
+        }
+        match (a.version(), b.version()) {
+            (Some(a_version), Some(b_version)) => {
+                memory.start_version()?;
+                version::write(&memory, &a_version.receive(&b_version, 0));
+            },
+            (Some(only), None) | (None, Some(only)) => {
+                memory.start_version()?;
+                version::write(&memory, &only);
+            },
+            (None, None) => { }
+        }
+        if tombstones {
+            for tombstone in crdt::merge_tombstones(&a.tombstones(), &b_tombstones).into_iter().rev() {
+                tombstone::record(&memory, tombstone.path_hash, tombstone.timestamp)?;
+            }
+        }
+        NP_Cursor::set_from_json(root, &memory, &json)?;
+        Ok(NP_Buffer::_new(memory))
+    }
+
+    /// Get an iterator for a collection
+    /// 
+    /// 
+    /// ## List Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set value at 1 index
+    /// new_buffer.set(&["1"], "hello")?;
+    /// // set value at 4 index
+    /// new_buffer.set(&["4"], "world")?;
+    /// // push value onto the end
+    /// new_buffer.list_push(&[], "!")?;
+    /// 
+    /// // get iterator of root (list item)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     match item.index {
+    ///         0 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         1 => assert_eq!(item.get::<&str>().unwrap(), Some("hello")),
+    ///         2 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         3 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         4 => assert_eq!(item.get::<&str>().unwrap(), Some("world")),
+    ///         5 => assert_eq!(item.get::<&str>().unwrap(), Some("!")),
+    ///         _ => panic!()
+    ///     };
+    /// });
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Table Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["age", {"type": "uint8"}],
+    ///         ["name", {"type": "string"}],
+    ///         ["job", {"type": "string"}],
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set value of age
+    /// new_buffer.set(&["age"], 20u8)?;
+    /// // set value of name
+    /// new_buffer.set(&["name"], "Bill Kerman")?;
+    /// // push value onto tags list
+    /// new_buffer.list_push(&["tags"], "rocket")?;
+    /// 
+    /// // get iterator of root (table)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     
+    ///     match item.key.as_ref() {
+    ///         "name" => assert_eq!(item.get::<&str>().unwrap(), Some("Bill Kerman")),
+    ///         "age" =>  assert_eq!(item.get::<u8>().unwrap(), Some(20)),
+    ///         "job" => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         "tags" => { /* tags column is list, can't do anything with it here */ },
+    ///         _ => { panic!() }
+    ///     };
+    /// });
+    /// 
+    /// // we can also loop through items of the tags list
+    /// new_buffer.get_iter(&["tags"])?.unwrap().into_iter().for_each(|item| {
+    ///     assert_eq!(item.index, 0);
+    ///     assert_eq!(item.get::<&str>().unwrap(), Some("rocket"));
+    /// });
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Map Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "map",
+    ///    "value": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set value of color key
+    /// new_buffer.set(&["color"], "blue")?;
+    /// // set value of sport key
+    /// new_buffer.set(&["sport"], "soccor")?;
+    /// 
+    /// // get iterator of root (map)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     
+    ///     match item.key.as_ref() {
+    ///         "color" => assert_eq!(item.get::<&str>().unwrap(), Some("blue")),
+    ///         "sport" => assert_eq!(item.get::<&str>().unwrap(), Some("soccor")),
+    ///         _ => panic!()
+    ///     }
+    /// });
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Tuple Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "tuple",
+    ///     "values": [
+    ///         {"type": "string"},
+    ///         {"type": "u8"},
+    ///         {"type": "bool"}
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set value at 0 index
+    /// new_buffer.set(&["0"], "hello")?;
+    /// // set value at 2 index
+    /// new_buffer.set(&["2"], false)?;
+    /// 
+    /// // get iterator of root (tuple item)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     match item.index {
+    ///         0 => assert_eq!(item.get::<&str>().unwrap(), Some("hello")),
+    ///         1 => assert_eq!(item.get::<u8>().unwrap(), None),
+    ///         2 => assert_eq!(item.get::<bool>().unwrap(), Some(false)),
+    ///         _ => panic!()
+    ///     };
+    /// });
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn get_iter<'iter>(&'iter self, path: &'iter [&str]) -> Result<Option<NP_Generic_Iterator<'iter>>, NP_Error> {
+
+        let value = self.select(self.cursor.clone(), path)?;
+
+        let value = if let Some(x) = value {
+            x
+        } else {
+            return Ok(None);
+        };
+
+        match self.memory.schema[value.schema_addr] {
+            NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Tuple { .. } | NP_Parsed_Schema::Map { .. } | NP_Parsed_Schema::Log { .. } => { },
+            _ => return Err(NP_Error::new_not_collection(path, "Tried to create iterator on non collection item!"))
+        }
+
+        let value_data = value.get_value(&self.memory);
+
+        // value doesn't exist
+        if value_data.get_addr_value() == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(NP_Generic_Iterator::new(value, &self.memory)?))
+    }
+
+    /// Same as `get_iter`, but the items can be set or cleared while looping.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "u8"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["0"], 1u8)?;
+    /// new_buffer.set(&["2"], 3u8)?;
+    /// 
+    /// // fill in the gap and clear the last item
+    /// for mut item in new_buffer.get_iter_mut(&[])?.unwrap() {
+    ///     match item.index {
+    ///         1 => item.set(2u8)?,
+    ///         2 => { item.del(); },
+    ///         _ => { }
+    ///     }
+    /// }
+    /// 
+    /// assert_eq!(new_buffer.get::<u8>(&["1"])?, Some(2));
+    /// assert_eq!(new_buffer.get::<u8>(&["2"])?, None);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn get_iter_mut<'iter>(&'iter mut self, path: &'iter [&str]) -> Result<Option<NP_Generic_Iterator_Mut<'iter>>, NP_Error> {
+        Ok(self.get_iter(path)?.map(|iter| NP_Generic_Iterator_Mut { iter }))
+    }
+
+    /// Push a value onto the end of a list.
+    /// The path provided must resolve to a list type, and the type being pushed must match the schema
+    /// 
+    /// This is the most efficient way to add values to a list type.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["3"], "launch")?;
+    /// new_buffer.list_push(&[], "this")?;
+    /// new_buffer.list_push(&[], "rocket")?;
+    /// 
+    /// // get iterator of root (list item)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     match item.index {
+    ///         0 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         1 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         2 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         3 => assert_eq!(item.get::<&str>().unwrap(), Some("launch")),
+    ///         4 => assert_eq!(item.get::<&str>().unwrap(), Some("this")),
+    ///         5 => assert_eq!(item.get::<&str>().unwrap(), Some("rocket")),
+    ///         _ => panic!()
+    ///     };
+    /// });
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.list_push(&[], "launch")?;
+    /// new_buffer.list_push(&[], "this")?;
+    /// new_buffer.list_push(&[], "rocket")?;
+    /// 
+    /// // get iterator of root (list item)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     match item.index {
+    ///         0 => assert_eq!(item.get::<&str>().unwrap(), Some("launch")),
+    ///         1 => assert_eq!(item.get::<&str>().unwrap(), Some("this")),
+    ///         2 => assert_eq!(item.get::<&str>().unwrap(), Some("rocket")),
+    ///         _ => panic!()
+    ///     };
+    /// });
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn list_push<X>(&mut self, path: &[&str], value: X) -> Result<Option<usize>, NP_Error> where X: NP_Value<'buffer> + NP_Scalar {
+
+        let list_cursor = if path.len() == 0 { self.cursor.clone() } else { match self.select_make(self.cursor.clone(), path)? {
+            Some(x) => x,
+            None => return Ok(None)
+        }};
+
+        match self.memory.schema[list_cursor.schema_addr] {
+            NP_Parsed_Schema::List { sorted: true, .. } => {
+                return Err(NP_Error::new_static("Items can only be added to sorted lists with sorted_insert!"));
+            },
+            NP_Parsed_Schema::List { of, .. } => {
+
+                let of_schema = &self.memory.schema[of];
+
+                // type does not match schema
+                if X::type_idx().1 != *of_schema.get_type_key() {
+                    return Err(NP_Error::new_type_mismatch(of_schema.get_type_name(), X::type_idx().0, path));
+                }
+            },
+            _ => return Err(NP_Error::new_not_collection(path, "Trying to push onto non list item!"))
+        }
+
+        match NP_List::push(&list_cursor, &self.memory, None)? {
+            Some((index, new_item_addr)) => {
+                X::set_value(new_item_addr, &self.memory, value)?;
+                self.tick();
+                Ok(Some(index))
+            },
+            None => Ok(None)
+        }
+    }
+
+    /// Move the items of a list at `index` and past it into a new buffer with the same schema and options, the original list is cut down to the items before `index`.
+    /// 
+    /// The list has the same path in the new buffer, paths are relative to the root of both buffers.  Moved items are numbered from zero, items before `index` keep their indexes.  Good for breaking up a list that's grown close to its size limit.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["events", {"type": "list", "of": {"type": "string"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// for event in ["launch", "orbit", "dock", "land"].iter() {
+    ///     new_buffer.list_push(&["events"], *event)?;
+    /// }
+    /// 
+    /// let tail = new_buffer.split_off_list(&["events"], 2)?;
+    /// assert_eq!(new_buffer.json_encode(&["events"])?.stringify(), r#"["launch","orbit"]"#);
+    /// assert_eq!(tail.json_encode(&["events"])?.stringify(), r#"["dock","land"]"#);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn split_off_list(&mut self, path: &[&str], index: usize) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        let memory = NP_Memory::new(None, self.memory.factory_schema).with_options(self.memory.options());
+        if self.memory.has_inline() {
+            memory.start_inline();
+        }
+
+        let list_cursor = match self.select(root, path)? {
+            Some(x) => x,
+            None => return Err(NP_Error::new_not_collection(path, "Trying to split non list item!"))
+        };
+        if let NP_Parsed_Schema::List { .. } = &self.memory.schema[list_cursor.schema_addr] { } else {
+            return Err(NP_Error::new_not_collection(path, "Trying to split non list item!"));
+        }
+
+        if list_cursor.get_value(&self.memory).get_addr_value() != 0 {
+            let new_list = opt_err(select_path(&memory, root, true, path)?)?;
+            NP_List::split_off(&list_cursor, index, &self.memory, &new_list, &memory)?;
+            self.tick();
+        }
+
+        Ok(NP_Buffer::_new(memory))
+    }
+
+    /// Add a value to a sorted list, in order.
+    /// The path provided must resolve to a `sorted_list` type, and the type being inserted must match the schema.
+    /// 
+    /// The value goes after any items that are equal to it, every later item's index moves up by one.  Sorted lists can't be changed with `set` or `list_push`, since those could put items out of order.  Returns the index of the new item.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "sorted_list",
+    ///    "of": {"type": "u32"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// assert_eq!(new_buffer.sorted_insert(&[], 30u32)?, Some(0));
+    /// assert_eq!(new_buffer.sorted_insert(&[], 10u32)?, Some(0));
+    /// assert_eq!(new_buffer.sorted_insert(&[], 20u32)?, Some(1));
+    /// assert_eq!(new_buffer.json_encode(&[])?.stringify(), "[10,20,30]");
+    /// 
+    /// assert!(new_buffer.sorted_contains(&[], 20u32)?);
+    /// assert!(!new_buffer.sorted_contains(&[], 25u32)?);
+    /// 
+    /// // values from 15 up to (but not including) 30
+    /// assert_eq!(new_buffer.sorted_range(&[], 15u32, 30u32)?, vec![20]);
+    /// 
+    /// // setting by index isn't allowed
+    /// assert!(new_buffer.set(&["3"], 5u32).is_err());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn sorted_insert<X>(&mut self, path: &[&str], value: X) -> Result<Option<usize>, NP_Error> where X: NP_Value<'buffer> + NP_Scalar {
+        let found = self.select_make(self.cursor, path)?;
+        let list_cursor = match self.sorted_list::<X>(found, path, false)? {
+            Some(x) => x,
+            None => return Ok(None)
+        };
+
+        match NP_List::push(&list_cursor, &self.memory, None)? {
+            Some((_index, new_item)) => {
+                X::set_value(new_item, &self.memory, value)?;
+                let index = NP_List::sorted_insert(&list_cursor, new_item, &self.memory)?;
+                self.tick();
+                Ok(Some(index))
+            },
+            None => Ok(None)
+        }
+    }
+
+    /// True if a sorted list has an item equal to `value`, or an item with a sort key equal to `value` if the list has a `key`.
+    /// 
+    /// Finds the value with a binary search over the items, see `sorted_insert` for an example.
+    /// 
+    pub fn sorted_contains<X>(&self, path: &[&str], value: X) -> Result<bool, NP_Error> where X: NP_Value<'buffer> + NP_Scalar {
+        let list_cursor = match self.sorted_list::<X>(self.select(self.cursor, path)?, path, true)? {
+            Some(x) => x,
+            None => return Ok(false)
+        };
+
+        let bytes = self.sorted_probe(&list_cursor, value)?;
+        let items = NP_List::sorted_items(&list_cursor, &self.memory);
+        let position = NP_List::sorted_position(&items, Some(&bytes), false, &self.memory);
+        Ok(items.get(position).map(|item| NP_List::sort_bytes(item, &self.memory) == Some(&bytes)).unwrap_or(false))
+    }
+
+    /// The items of a sorted list from `start` up to but not including `end`, in order.  If the list has a `key` the sort keys of the items are returned.
+    /// 
+    /// Both ends are found with a binary search over the items, see `sorted_insert` for an example.
+    /// 
+    pub fn sorted_range<'get, X>(&'get self, path: &[&str], start: X, end: X) -> Result<Vec<X>, NP_Error> where X: 'get + NP_Value<'get> + NP_Scalar {
+        let list_cursor = match self.sorted_list::<X>(self.select(self.cursor, path)?, path, true)? {
+            Some(x) => x,
+            None => return Ok(Vec::new())
+        };
+
+        let start = self.sorted_probe(&list_cursor, start)?;
+        let end = self.sorted_probe(&list_cursor, end)?;
+        let items = NP_List::sorted_items(&list_cursor, &self.memory);
+        let first = NP_List::sorted_position(&items, Some(&start), false, &self.memory);
+        let last = NP_List::sorted_position(&items, Some(&end), false, &self.memory).max(first);
+
+        let mut values = Vec::with_capacity(last - first);
+        for item in items[first..last].iter() {
+            if let Some(key) = NP_List::sort_cursor(item, &self.memory) {
+                if let Some(x) = X::into_value(&key, &self.memory)? {
+                    values.push(x);
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    // check the found cursor is a sorted list holding `X` values, or with `X` sort keys if `by_key` is true
+    fn sorted_list<'value, X: NP_Value<'value>>(&self, found: Option<NP_Cursor>, path: &[&str], by_key: bool) -> Result<Option<NP_Cursor>, NP_Error> {
+        let list_cursor = match found {
+            Some(x) => x,
+            None => return Ok(None)
+        };
+
+        match &self.memory.schema[list_cursor.schema_addr] {
+            NP_Parsed_Schema::List { of, sorted: true, key, .. } => {
+                let value_addr = if by_key { NP_List::key_schema(self.memory.schema, *of, key).unwrap_or(*of) } else { *of };
+                let value_schema = &self.memory.schema[value_addr];
+
+                // type does not match schema
+                if X::type_idx().1 != *value_schema.get_type_key() {
+                    return Err(NP_Error::new_get_type_mismatch(value_schema.get_type_name(), X::type_idx().0, path));
+                }
+            },
+            _ => return Err(NP_Error::new_static("Path isn't a sorted list!"))
+        }
+
+        Ok(Some(list_cursor))
+    }
+
+    // bytes `value` would have as the sort key of an item in the list, to compare with the stored items
+    fn sorted_probe<'value, X: NP_Value<'value>>(&self, list_cursor: &NP_Cursor, value: X) -> Result<Vec<u8>, NP_Error> {
+        let key_addr = match &self.memory.schema[list_cursor.schema_addr] {
+            NP_Parsed_Schema::List { of, key, .. } => NP_List::key_schema(self.memory.schema, *of, key).unwrap_or(*of),
+            _ => return Err(NP_Error::new_static("unreachable"))
+        };
+
+        let probe = NP_Memory::new(None, self.memory.factory_schema);
+        let cursor = NP_Cursor::new(ROOT_PTR_ADDR, key_addr, 0);
+        X::set_value(cursor, &probe, value)?;
+        Ok(compare::scalar_bytes(&cursor, &probe).map(|bytes| bytes.to_vec()).unwrap_or_default())
+    }
+
+    /// Add an item to a sorted list, the item is given as JSON and is kept in order by the list's sort key.
+    /// 
+    /// With a `key` (the path to a sortable value inside each item) a sorted list works as a priority queue: `sorted_push` adds items and `pop_min` takes the one with the smallest key off the front.  It isn't a binary heap, the items are kept fully sorted.  Pushing is a binary search that relinks only the items around the new one and popping reads the head of the list, but both give the items after them new indexes.  Like every sorted list a priority queue holds up to 256 items.  Returns the index the new item landed at, or `None` if the path doesn't exist.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "priority_queue",
+    ///    "key": ["priority"],
+    ///    "of": {"type": "table", "columns": [
+    ///         ["priority", {"type": "u8"}],
+    ///         ["task",     {"type": "string"}]
+    ///     ]}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.sorted_push(&[], r#"{"priority": 3, "task": "laundry"}"#)?;
+    /// new_buffer.sorted_push(&[], r#"{"priority": 1, "task": "launch"}"#)?;
+    /// new_buffer.sorted_push(&[], r#"{"priority": 2, "task": "refuel"}"#)?;
+    /// 
+    /// assert_eq!(new_buffer.pop_min(&[])?.unwrap()["task"].stringify(), r#""launch""#);
+    /// assert_eq!(new_buffer.pop_min(&[])?.unwrap()["task"].stringify(), r#""refuel""#);
+    /// assert_eq!(new_buffer.length(&[])?, Some(1));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn sorted_push(&mut self, path: &[&str], json: &str) -> Result<Option<usize>, NP_Error> {
+        let value = json_decode(String::from(json))?;
+
+        let list_cursor = match self.select_make(self.cursor, path)? {
+            Some(x) => x,
+            None => return Ok(None)
+        };
+
+        match &self.memory.schema[list_cursor.schema_addr] {
+            NP_Parsed_Schema::List { sorted: true, .. } => { },
+            _ => return Err(NP_Error::new_static("Path isn't a sorted list!"))
+        }
+
+        match NP_List::push(&list_cursor, &self.memory, None)? {
+            Some((_index, new_item)) => {
+                if let Err(e) = NP_Cursor::set_from_json(new_item, &self.memory, &value) {
+                    // leave the item out so the list stays in order
+                    let mut items = NP_List::sorted_items(&list_cursor, &self.memory);
+                    items.retain(|item| item.buff_addr != new_item.buff_addr);
+                    NP_List::relink(&list_cursor, &items, &self.memory)?;
+                    return Err(e);
+                }
+                let index = NP_List::sorted_insert(&list_cursor, new_item, &self.memory)?;
+                self.tick();
+                Ok(Some(index))
+            },
+            None => Ok(None)
+        }
+    }
+
+    /// Remove the first item of a sorted list and return it as JSON, the item with the smallest sort key.
+    /// 
+    /// The first item is read from the head of the list, then the items after it are numbered from zero again.  Returns `None` if the list is empty, see `sorted_push` for an example.
+    /// 
+    pub fn pop_min(&mut self, path: &[&str]) -> Result<Option<NP_JSON>, NP_Error> {
+        let list_cursor = match self.select(self.cursor, path)? {
+            Some(x) => x,
+            None => return Ok(None)
+        };
+
+        match &self.memory.schema[list_cursor.schema_addr] {
+            NP_Parsed_Schema::List { sorted: true, .. } => { },
+            _ => return Err(NP_Error::new_static("Path isn't a sorted list!"))
+        }
+
+        let first_item = match NP_List::first_item(&list_cursor, &self.memory) {
+            Some(x) => x,
+            None => return Ok(None)
+        };
+        let first = NP_Cursor::json_encode_limited(&first_item, &self.memory, path.len() + 1)?;
+
+        NP_List::drop_through(&list_cursor, &first_item, &self.memory)?;
+        self.tick();
+        Ok(Some(first))
+    }
+
+    /// Append a value to the end of a log.
+    /// The path provided must resolve to a log type, and the type being appended must match the schema.
+    /// 
+    /// Returns the index of the new item.  See the [log](../collection/log/index.html) module for an example.
+    /// 
+    pub fn log_append<X>(&mut self, path: &[&str], value: X) -> Result<Option<usize>, NP_Error> where X: NP_Value<'buffer> + NP_Scalar {
+
+        let log_cursor = if path.len() == 0 { self.cursor.clone() } else { match self.select_make(self.cursor.clone(), path)? {
+            Some(x) => x,
+            None => return Ok(None)
+        }};
+
+        match self.memory.schema[log_cursor.schema_addr] {
+            NP_Parsed_Schema::Log { of, .. } => {
+
+                let of_schema = &self.memory.schema[of];
+
+                // type does not match schema
+                if X::type_idx().1 != *of_schema.get_type_key() {
+                    return Err(NP_Error::new_type_mismatch(of_schema.get_type_name(), X::type_idx().0, path));
+                }
+            },
+            _ => return Err(NP_Error::new_not_collection(path, "Trying to append onto non log item!"))
+        }
+
+        let (index, new_item) = NP_Log::append(&log_cursor, &self.memory)?;
+        X::set_value(new_item, &self.memory, value)?;
+        self.tick();
+        Ok(Some(index))
+    }
+
+    /// Get length of String, Bytes, Table, Tuple, List, Log or Map Type
+    /// 
+    /// If the type found at the path provided does not support length operations, you'll get `None`.
+    /// 
+    /// If there is no value at the path provodid, you will get `None`.
+    /// 
+    /// If an item is found and it's length is zero, you can expect `Some(0)`.
+    /// 
+    /// ## String Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "string"
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set initial value
+    /// new_buffer.set(&[], "hello")?;
+    /// // get length of value at root (String)
+    /// assert_eq!(new_buffer.length(&[])?, Some(5));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Collection (List) Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set value at 9th index
+    /// new_buffer.set(&["9"], "hello")?;
+    /// // get length of value at root (List)
+    /// assert_eq!(new_buffer.length(&[])?, Some(10));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Collection (Table) Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["age", {"type": "u8"}],
+    ///         ["name", {"type": "string"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // get length of value at root (Table)
+    /// assert_eq!(new_buffer.length(&[])?, Some(2));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Collection (Map) Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "map",
+    ///    "value": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set values
+    /// new_buffer.set(&["foo"], "bar")?;
+    /// new_buffer.set(&["foo2"], "bar2")?;
+    /// // get length of value at root (Map)
+    /// assert_eq!(new_buffer.length(&[])?, Some(2));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Collection (Tuple) Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "tuple",
+    ///    "values": [
+    ///         {"type": "string"}, 
+    ///         {"type": "string"}
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // get length of value at root (Tuple)
+    /// assert_eq!(new_buffer.length(&[])?, Some(2));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn length(&self, path: &[&str]) -> Result<Option<usize>, NP_Error> {
+        let value_cursor = self.select(self.cursor.clone(), path)?;
+
+        let found_cursor = if let Some(x) = value_cursor {
+            x
+        } else {
+            return Ok(None);
+        };
+
+        let addr_value = found_cursor.get_value(&self.memory).get_addr_value();
+
+
+        match &self.memory.schema[found_cursor.schema_addr] {
+            NP_Parsed_Schema::List { .. } => {
+                if addr_value == 0 {
+                    return Ok(None);
+                }
+
+                Ok(Some(NP_List::length(&found_cursor, &self.memory)))
+            },
+            NP_Parsed_Schema::Log { .. } => {
+                if addr_value == 0 {
+                    return Ok(None);
+                }
+                Ok(Some(NP_Log::count(&found_cursor, &self.memory)))
+            },
+            NP_Parsed_Schema::Map { .. } => {
+                if addr_value == 0 {
+                    return Ok(None);
+                }
+                let mut count = 0usize;
+                let mut map_iter = NP_Map::new_iter(&found_cursor, &self.memory);
+
+                // key is maybe in map
+                while let Some((_ikey, _item)) = map_iter.step_iter(&self.memory) {
+                    count += 1;
+                }
+
+                Ok(Some(count))
+            },
+            NP_Parsed_Schema::Table { columns, ..} => {
+                Ok(Some(columns.len()))
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                Ok(Some(values.len()))
+            },
+            NP_Parsed_Schema::Bytes {  size, ..} => {
+                if *size > 0 {
+                    Ok(Some(*size as usize))
+                } else {
+                    let length_bytes = self.memory.get_2_bytes(addr_value as usize).unwrap_or(&[0u8; 2]);
+                    Ok(Some(u16::from_be_bytes(*length_bytes) as usize))
+                }
+            },
+            NP_Parsed_Schema::UTF8String { size, .. } => {
+                if *size > 0 {
+                    Ok(Some(*size as usize))
+                } else {
+                    let length_bytes = self.memory.get_2_bytes(addr_value as usize).unwrap_or(&[0u8; 2]);
+                    Ok(Some(u16::from_be_bytes(*length_bytes) as usize))
+                }
+            },
+            _ => {
+                Ok(None)
+            }
+        }
+  
+    }
+
+    /// Which columns of a table have a value, as a bitmap with one bit per column.
+    /// 
+    /// Bit `idx % 8` of byte `idx / 8` is set if the column at `idx` (its position in the schema) has a value.  Schema defaults don't count as values.  Reading the bitmap visits each vtable of the table once, so checking many columns this way is faster than looking each one up by name.
+    /// 
+    /// Returns `None` if the table doesn't exist in the buffer and an error if the path isn't a table.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["age",   {"type": "u8", "default": 10}],
+    ///         ["email", {"type": "string"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// assert_eq!(new_buffer.presence(&[])?, None);
+    /// 
+    /// new_buffer.set(&["name"], "Billy")?;
+    /// new_buffer.set(&["email"], "billy@example.com")?;
+    /// assert_eq!(new_buffer.presence(&[])?, Some(vec![0b101]));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn presence(&self, path: &[&str]) -> Result<Option<Vec<u8>>, NP_Error> {
+        let found_cursor = match self.select(self.cursor, path)? {
+            Some(x) => x,
+            None => return Ok(None)
+        };
+
+        match &self.memory.schema[found_cursor.schema_addr] {
+            NP_Parsed_Schema::Table { .. } => { },
+            _ => return Err(NP_Error::new_static("Presence can only be read from tables!"))
+        }
+
+        if found_cursor.get_value(&self.memory).get_addr_value() == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(NP_Table::presence(&found_cursor, &self.memory)))
+    }
+
+    /// The indexes of a tuple that have a value, in order.
+    /// 
+    /// Tuples only use space for the values that are set and the vtables holding them, every vtable holds 4 values.  Sorted tuples set every value when they're made, so all of their indexes are reported.  Schema defaults don't count as values.
+    /// 
+    /// Returns `None` if the tuple doesn't exist in the buffer and an error if the path isn't a tuple.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "tuple",
+    ///    "values": [
+    ///         {"type": "string"},
+    ///         {"type": "u8", "default": 5},
+    ///         {"type": "string"},
+    ///         {"type": "bool"},
+    ///         {"type": "u32"}
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// assert_eq!(new_buffer.tuple_len_set(&[])?, None);
+    /// 
+    /// new_buffer.set(&["2"], "hello")?;
+    /// new_buffer.set(&["0"], "world")?;
+    /// assert_eq!(new_buffer.tuple_len_set(&[])?, Some(vec![0, 2]));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn tuple_len_set(&self, path: &[&str]) -> Result<Option<Vec<usize>>, NP_Error> {
+        let found_cursor = match self.select(self.cursor, path)? {
+            Some(x) => x,
+            None => return Ok(None)
+        };
+
+        match &self.memory.schema[found_cursor.schema_addr] {
+            NP_Parsed_Schema::Tuple { .. } => { },
+            _ => return Err(NP_Error::new_static("Set indexes can only be read from tuples!"))
+        }
+
+        if found_cursor.get_value(&self.memory).get_addr_value() == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(NP_Tuple::set_indexes(&found_cursor, &self.memory)))
+    }
+
+    /// Clear an inner value from the buffer.
+    /// This can also be used to clear deeply nested collection objects or scalar objects.
+    /// 
+    /// Returns `true` if it found a value to delete (and deleted it), `false` otherwise.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set index 0
+    /// new_buffer.set(&["0"], "hello")?;
+    /// // del index 0
+    /// new_buffer.del(&["0"])?;
+    /// // value is gone now!
+    /// assert_eq!(None, new_buffer.get::<&str>(&["0"])?);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn del(&mut self, path: &[&str]) -> Result<bool, NP_Error> {
+
+        let value_cursor = self.select(self.cursor.clone(), path)?;
+        
+        match value_cursor {
+            Some(x) => {
+                if self.sortable {
+                    match &self.memory.schema[x.schema_addr] {
+                        NP_Parsed_Schema::Table { .. } => { return Ok(false) },
+                        NP_Parsed_Schema::Tuple { .. } => { return Ok(false) },
+                        NP_Parsed_Schema::List { .. } => { return Ok(false) },
+                        NP_Parsed_Schema::Map { .. } => { return Ok(false) },
+                        NP_Parsed_Schema::Log { .. } => { return Ok(false) },
+                        _ => NP_Cursor::set_default(x, &self.memory)?
+                    }
+                } else {
+                    // clear value address in buffer
+                    x.get_value_mut(&self.memory).set_addr_value(0);
+                }
+
+                if let Some(clock) = self.clock {
+                    tombstone::record(&self.memory, tombstone::hash_path(self.cursor_hash, path), clock())?;
+                }
+                self.tick();
+
+                Ok(true)
+            }
+            None => Ok(false)
+        }
+    }
+
+    /// Record a tombstone for every later `del`, with the time from `clock`.
+    /// 
+    /// Tombstones let replicas tell deleted values apart from values that were never set, see the [tombstone](../tombstone/index.html) module for an example.  The clock isn't saved with the buffer, call this again after opening it.
+    /// 
+    /// **WARNING** If the buffer has values and no tombstones yet it's compacted to make room for them, your cursor location and backup will be reset to the root.
+    /// 
+    pub fn track_deletes(&mut self, clock: fn() -> u64) -> Result<(), NP_Error> {
+        self.clock = Some(clock);
+        if !self.memory.has_tombstones() {
+            if self.memory.read_bytes().len() == tombstone::HEAD_ADDR {
+                self.memory.start_tombstones()?;
+            } else {
+                self.compact_with(None, None, NP_Compact_Flags::default())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// When the value at `path` was deleted, if it has a tombstone.
+    /// 
+    pub fn tombstone(&self, path: &[&str]) -> Option<u64> {
+        let path_hash = tombstone::hash_path(self.cursor_hash, path);
+        tombstone::read(&self.memory).into_iter().find(|tombstone| tombstone.path_hash == path_hash).map(|tombstone| tombstone.timestamp)
+    }
+
+    /// Every tombstone in the buffer, newest first.
+    /// 
+    pub fn tombstones(&self) -> Vec<NP_Tombstone> {
+        tombstone::read(&self.memory)
+    }
+
+    /// Move the version of the buffer forward on every later change, with the time from `clock` and the id of this node.
+    /// 
+    /// See the [version](../version/index.html) module for an example.  The clock isn't saved with the buffer, call this again after opening it.  The buffer keeps its current version, only the node id is replaced.
+    /// 
+    /// **WARNING** If the buffer has values and no version yet it's compacted to make room for it, your cursor location and backup will be reset to the root.
+    /// 
+    pub fn track_version(&mut self, node: u32, clock: fn() -> u64) -> Result<(), NP_Error> {
+        self.hlc = Some(clock);
+        if !self.memory.has_version() {
+            if self.memory.read_bytes().len() == tombstone::HEAD_ADDR + if self.memory.has_tombstones() { 2 } else { 0 } {
+                self.memory.start_version()?;
+            } else {
+                self.compact_with(None, None, NP_Compact_Flags::default())?;
+            }
+        }
+        let current = version::read(&self.memory).unwrap_or_default();
+        version::write(&self.memory, &NP_Version { node, ..current });
+        Ok(())
+    }
+
+    /// Store every `bool`, `u8`, `i8` and `option` value inside its pointer instead of giving it a byte of its own.
+    /// 
+    /// Each of these values is one byte smaller and one address lookup faster to read.  The setting is saved with the buffer, so it only needs to be called once.  Sortable buffers keep every value in its own bytes and return an error.
+    /// 
+    /// **WARNING** If the buffer has values they're compacted into the new layout, your cursor location and backup will be reset to the root.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["age",    {"type": "u8"}],
+    ///         ["active", {"type": "bool"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["age"], 20u8)?;
+    /// new_buffer.set(&["active"], true)?;
+    /// let size = new_buffer.read_bytes().len();
+    /// 
+    /// new_buffer.inline_small_values()?;
+    /// assert_eq!(new_buffer.read_bytes().len(), size - 2);
+    /// assert_eq!(new_buffer.get::<u8>(&["age"])?, Some(20));
+    /// assert_eq!(new_buffer.get::<bool>(&["active"])?, Some(true));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    pub fn inline_small_values(&mut self) -> Result<(), NP_Error> {
+        if self.sortable {
+            return Err(NP_Error::new_static("Sortable buffers can't inline values!"));
+        }
+        if !self.memory.has_inline() {
+            self.compact_with(None, None, NP_Compact_Flags { inline: true, ..Default::default() })?;
+        }
+        Ok(())
+    }
+
+    /// The version of the buffer, if `track_version` has been called on it.
+    /// 
+    pub fn version(&self) -> Option<NP_Version> {
+        version::read(&self.memory)
+    }
+
+    /// Store the fingerprint of this buffer's schema in the buffer, so factories with other schemas can't open it with `NP_Factory::open_checked_buffer`.
+    /// 
+    /// The fingerprint is saved with the buffer and kept through compaction, see the [fingerprint](../fingerprint/index.html) module for an example.
+    /// 
+    /// **WARNING** If the buffer has values and no fingerprint yet it's compacted to make room for it, your cursor location and backup will be reset to the root.
+    /// 
+    pub fn embed_fingerprint(&mut self) -> Result<(), NP_Error> {
+        if !self.memory.has_fingerprint() {
+            if self.memory.read_bytes().len() == fingerprint::address(&self.memory) {
+                fingerprint::start(&self.memory)?;
+            } else {
+                self.compact_with(None, None, NP_Compact_Flags { fingerprint: true, ..Default::default() })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The schema fingerprint stored in this buffer, if it has one.
+    pub fn fingerprint(&self) -> Option<u64> {
+        fingerprint::read(&self.memory)
+    }
+
+    // move the version forward after a change
+    fn tick(&mut self) {
+        if let Some(clock) = self.hlc {
+            if let Some(current) = version::read(&self.memory) {
+                version::write(&self.memory, &current.tick(clock()));
+            }
+        }
+    }
+  
+    /// Retrieve an inner value from the buffer. 
+    /// 
+    /// The type that you cast the request to will be compared to the schema, if it doesn't match the schema the request will fail.
+    /// 
+    /// Getting a value never changes the buffer, collections or list items along the path that don't exist aren't made.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// // a list where each item is a map where each key has a value containing a list of strings
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "map", "value": {
+    ///         "type": "list", "of": {"type": "string"}
+    ///     }}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // third item in the top level list -> key "alpha" of map at 3rd element -> 9th element of list at "alpha" key
+    /// // 
+    /// new_buffer.set(&["3", "alpha", "9"], "who would build a schema like this")?;
+    /// 
+    /// // get the same item we just set
+    /// let message = new_buffer.get::<&str>(&["3", "alpha", "9"])?;
+    /// 
+    /// assert_eq!(message, Some("who would build a schema like this"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn get<'get, X: 'get>(&'get self, path: &[&str]) -> Result<Option<X>, NP_Error> where X: NP_Value<'get> + NP_Scalar {
+        Ok(self.get_state(path)?.value())
+    }
+
+    /// Retrieve an inner value from the buffer with a path compiled by `NP_Factory::compile_path`.
+    /// 
+    /// Works like `get`, but skips looking up columns by name and parsing indexes.  Compiled paths always start at the root of the buffer.  See the [path](../path/index.html) module for an example.
+    /// 
+    pub fn get_h<'get, X: 'get>(&'get self, handle: &NP_PathHandle) -> Result<Option<X>, NP_Error> where X: NP_Value<'get> + NP_Scalar {
+        if !self.has_schema(handle.schema.fingerprint) {
+            return Err(NP_Error::new_static("Path was compiled for a different schema!"));
+        }
+
+        let schema = &self.memory.schema[handle.schema_addr];
+        if X::type_idx().1 != *schema.get_type_key() {
+            return Err(NP_Error::type_mismatch(schema.get_type_name(), X::type_idx().0, handle.path.len(), false));
+        }
+
+        let mut cursor = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        for step in handle.steps.iter() {
+            let next = match step {
+                NP_Path_Step::Column(idx, schema_addr) => NP_Table::select_idx(&cursor, *idx, *schema_addr, &self.memory),
+                NP_Path_Step::Tuple(idx) => NP_Tuple::select(cursor, *idx, false, &self.memory)?,
+                NP_Path_Step::List(idx) => match NP_List::select(cursor, *idx, false, &self.memory)? {
+                    Some((_, item)) => item,
+                    None => None
+                },
+                NP_Path_Step::Log(idx) => NP_Log::select(cursor, *idx, false, &self.memory)?,
+                NP_Path_Step::Key(key) => NP_Map::select(cursor, key, false, &self.memory)?
+            };
+            match next {
+                Some(next) => cursor = next,
+                None => return Ok(X::schema_default(schema))
+            }
+        }
+
+        match X::into_value(&cursor, &self.memory)? {
+            Some(x) => Ok(Some(x)),
+            None => Ok(X::schema_default(schema))
+        }
+    }
+
+    /// Address of the root value for the low level API, see the [raw](../raw/index.html) module.
+    /// 
+    pub fn raw_root(&self) -> NP_Raw_Addr {
+        NP_Raw_Addr::from_cursor(NP_Cursor::new(ROOT_PTR_ADDR, 0, 0))
+    }
+
+    /// Step into a collection by one key or index, `None` if the value doesn't exist.
+    /// 
+    pub fn raw_select(&self, at: NP_Raw_Addr, key: &str) -> Result<Option<NP_Raw_Addr>, NP_Error> {
+        let cursor = at.check(&self.memory)?;
+        Ok(self.select(cursor, &[key])?.map(NP_Raw_Addr::from_cursor))
+    }
+
+    /// Step into a collection by one key or index, making the value if it doesn't exist.
+    /// 
+    pub fn raw_select_make(&mut self, at: NP_Raw_Addr, key: &str) -> Result<Option<NP_Raw_Addr>, NP_Error> {
+        let cursor = at.check(&self.memory)?;
+        Ok(self.select_make(cursor, &[key])?.map(NP_Raw_Addr::from_cursor))
+    }
+
+    /// Read the value at a raw address, works like `get`.
+    /// 
+    pub fn raw_get<'get, X: 'get>(&'get self, at: NP_Raw_Addr) -> Result<Option<X>, NP_Error> where X: NP_Value<'get> + NP_Scalar {
+        let cursor = at.check(&self.memory)?;
+        Ok(self.state_from(cursor, &[])?.value())
+    }
+
+    /// Write the value at a raw address, works like `set`.
+    /// 
+    pub fn raw_set<X: 'buffer>(&mut self, at: NP_Raw_Addr, value: X) -> Result<bool, NP_Error> where X: NP_Value<'buffer> + NP_Scalar {
+        let cursor = at.check(&self.memory)?;
+        self.set_from(cursor, &[], value, self.overflow)
+    }
+
+    /// Retrieve an inner value from the buffer along with where it came from.
+    /// 
+    /// `get` returns the schema default when a value isn't set, this tells you if the value is stored in the buffer (`Set`), provided by the schema (`Default`) or missing entirely (`Unset`).
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_ValueState;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["color", {"type": "string", "default": "red"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["name"], "Billy")?;
+    /// 
+    /// assert_eq!(new_buffer.get_state::<&str>(&["name"])?, NP_ValueState::Set("Billy"));
+    /// assert_eq!(new_buffer.get_state::<&str>(&["color"])?, NP_ValueState::Default("red"));
+    /// 
+    /// // setting the default value explicitly is still a set value
+    /// new_buffer.set(&["color"], "red")?;
+    /// assert_eq!(new_buffer.get_state::<&str>(&["color"])?, NP_ValueState::Set("red"));
+    /// 
+    /// new_buffer.del(&["name"])?;
+    /// assert_eq!(new_buffer.get_state::<&str>(&["name"])?, NP_ValueState::Unset);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn get_state<'get, X: 'get>(&'get self, path: &[&str]) -> Result<NP_ValueState<X>, NP_Error> where X: NP_Value<'get> + NP_Scalar {
+        self.state_from(self.cursor.clone(), path)
+    }
+
+    /// Get the value of a buffer whose root is a scalar type.
+    /// 
+    /// Same as `get` with an empty path, except the value is always read from the root of the buffer even if the cursor has been moved.  See `set_root` for an example.
+    /// 
+    pub fn get_root<'get, X: 'get>(&'get self) -> Result<Option<X>, NP_Error> where X: NP_Value<'get> + NP_Scalar {
+        Ok(self.state_from(NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), &[])?.value())
+    }
+
+    fn state_from<'get, X: 'get>(&'get self, cursor: NP_Cursor, path: &[&str]) -> Result<NP_ValueState<X>, NP_Error> where X: NP_Value<'get> + NP_Scalar {
+        np_event!(path = ?path, "get");
+
+        let value_cursor = self.select(cursor, path)?;
+
+        match value_cursor {
+            Some(x) => {
+                                
+                // type does not match schema
+                if X::type_idx().1 != *self.memory.schema[x.schema_addr].get_type_key() {
+                    return Err(NP_Error::new_get_type_mismatch(self.memory.schema[x.schema_addr].get_type_name(), X::type_idx().0, path));
+                }
+
+                match X::into_value(&x, &self.memory)? {
+                    Some(x) => {
+                        Ok(NP_ValueState::Set(x))
+                    },
+                    None => { // no value found here, return default from schema
+                        match X::schema_default(&self.memory.schema[x.schema_addr]) {
+                            Some(y) => {
+                                Ok(NP_ValueState::Default(y))
+                            },
+                            None => { // no default in schema, no value to provide
+                                Ok(NP_ValueState::Unset)
+                            }
+                        }                        
+                    }
+                }
+            }
+            // the value's table, vtable or collection hasn't been made yet, the schema default still applies like it does for `get_h`
+            None => match self.schema_at(cursor.schema_addr, path) {
+                Some(schema_addr) => {
+                    let schema = &self.memory.schema[schema_addr];
+                    if X::type_idx().1 != *schema.get_type_key() {
+                        return Err(NP_Error::new_get_type_mismatch(schema.get_type_name(), X::type_idx().0, path));
+                    }
+                    Ok(X::schema_default(schema).map(NP_ValueState::Default).unwrap_or(NP_ValueState::Unset))
+                },
+                None => Ok(NP_ValueState::Unset)
+            }
+        }
+    }
+
+    // schema address the path leads to from `schema_addr`, without looking at the buffer
+    fn schema_at(&self, schema_addr: usize, path: &[&str]) -> Option<usize> {
+        let mut schema_addr = schema_addr;
+        for key in path.iter() {
+            schema_addr = match &self.memory.schema[schema_addr] {
+                NP_Parsed_Schema::Table { columns, columns_mapped, .. } => columns[*columns_mapped.get(key)?].2,
+                NP_Parsed_Schema::Tuple { values, .. } => *values.get(key.parse::<usize>().ok()?)?,
+                NP_Parsed_Schema::List { of, .. } | NP_Parsed_Schema::Log { of, .. } => *of,
+                NP_Parsed_Schema::Map { value, .. } => *value,
+                _ => return None
+            };
+        }
+        Some(schema_addr)
+    }
+
+    /// Read one column from every row of a list of tables.
+    /// 
+    /// The list is walked once and the column is found in each row directly, which is much faster than calling `get` with a path for every row.  The returned `Vec` has an item for every index up to the last row in the list, rows or values that aren't set use the column's schema default or are `None`.
+    /// 
+    /// Lists with `"layout": "columnar"` are the fastest to read this way, see the [list schema docs](../schema/index.html#list).
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "table", "columns": [
+    ///         ["time",  {"type": "u64"}],
+    ///         ["value", {"type": "u32", "default": 1}]
+    ///     ]}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["0", "time"], 100u64)?;
+    /// new_buffer.set(&["0", "value"], 5u32)?;
+    /// new_buffer.set(&["1", "time"], 200u64)?;
+    /// new_buffer.set(&["3", "time"], 400u64)?;
+    /// 
+    /// assert_eq!(new_buffer.read_column::<u64>(&[], "time")?, vec![Some(100), Some(200), None, Some(400)]);
+    /// assert_eq!(new_buffer.read_column::<u32>(&[], "value")?, vec![Some(5), Some(1), Some(1), Some(1)]);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn read_column<'get, X: 'get>(&'get self, list_path: &[&str], column: &str) -> Result<Vec<Option<X>>, NP_Error> where X: NP_Value<'get> + NP_Scalar {
+
+        let list_cursor = match self.select(self.cursor.clone(), list_path)? {
+            Some(x) => x,
+            None => return Ok(Vec::new())
+        };
+
+        let table_schema = match &self.memory.schema[list_cursor.schema_addr] {
+            NP_Parsed_Schema::List { of, .. } => *of,
+            _ => return Err(NP_Error::new_not_collection(list_path, "Trying to read column of non list item!"))
+        };
+
+        let (idx, column_schema) = match &self.memory.schema[table_schema] {
+            NP_Parsed_Schema::Table { columns, columns_mapped, .. } => match columns_mapped.get(column) {
+                Some(x) => (*x, columns[*x].2),
+                None => return Err(NP_Error::new(alloc::format!("No column named {:?} in this table!", column)))
+            },
+            _ => return Err(NP_Error::new_static("read_column needs a list of tables!"))
+        };
+
+        // type does not match schema
+        if X::type_idx().1 != *self.memory.schema[column_schema].get_type_key() {
+            return Err(NP_Error::new_get_type_mismatch(self.memory.schema[column_schema].get_type_name(), X::type_idx().0, list_path));
+        }
+
+        let default = || X::schema_default(&self.memory.schema[column_schema]);
+
+        // every index up to the last item gets a value, a wide list can't have more items than the buffer has bytes so any longer one is mostly gaps
+        if let NP_Parsed_Schema::List { wide: true, .. } = &self.memory.schema[list_cursor.schema_addr] {
+            let length = NP_List::length(&list_cursor, &self.memory);
+            if length > self.memory.read_bytes().len() {
+                return Err(NP_Error::new(alloc::format!("This wide list is too sparse to read as a column, it has {} indexes but the buffer is {} bytes!", length, self.memory.read_bytes().len())));
+            }
+        }
+
+        let mut values: Vec<Option<X>> = Vec::new();
+
+        let mut list_iter = NP_List::new_iter(&list_cursor, &self.memory, false, 0);
+
+        while let Some((_index, item)) = NP_List::step_iter(&mut list_iter, &self.memory) {
+            let value = match item.and_then(|row| NP_Table::select_idx(&row, idx, column_schema, &self.memory)) {
+                Some(cursor) => match X::into_value(&cursor, &self.memory)? {
+                    Some(x) => Some(x),
+                    None => default()
+                },
+                None => default()
+            };
+            values.push(value);
+        }
+
+        Ok(values)
+    }
+
+    /// This performs a compaction if the closure provided as the second argument returns `true`.
+    /// Compaction is a pretty expensive operation (requires full copy of the whole buffer) so should be done sparingly.
+    /// The closure is provided an argument that contains the original size of the buffer, how many bytes could be saved by compaction, and how large the new buffer would be after compaction.  The closure should return `true` to perform compaction, `false` otherwise.
+    /// 
+    /// The first argument, new_capacity, is the capacity of the underlying Vec<u8> that we'll be copying the data into.  The default is the size of the old buffer.
+    /// 
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "string"
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set initial value
+    /// new_buffer.set(&[], "hello")?;
+    /// // using 9 bytes
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 10,
+    ///     after_compaction: 10,
+    ///     wasted_bytes: 0
+    /// }, new_buffer.calc_bytes()?);
+    /// // update the value
+    /// new_buffer.set(&[], "hello, world")?;
+    /// // now using 25 bytes, with 7 bytes of wasted space
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 24,
+    ///     after_compaction: 17,
+    ///     wasted_bytes: 7
+    /// }, new_buffer.calc_bytes()?);
+    /// // compact to save space
+    /// new_buffer.maybe_compact(None, |compact_data| {
+    ///     // only compact if wasted bytes are greater than 5
+    ///     if compact_data.wasted_bytes > 5 {
+    ///         true
+    ///     } else {
+    ///         false
+    ///     }
+    /// })?;
+    /// // back down to 18 bytes with no wasted bytes
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 17,
+    ///     after_compaction: 17,
+    ///     wasted_bytes: 0
+    /// }, new_buffer.calc_bytes()?);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn maybe_compact<F>(&mut self, new_capacity: Option<u32>, mut callback: F) -> Result<(), NP_Error> where F: FnMut(NP_Size_Data) -> bool {
+
+        let bytes_data = self.calc_bytes()?;
+
+        if callback(bytes_data) {
+            self.compact(new_capacity)?;
+        }
+
+        return Ok(());
+    }
+
+    /// Compacts a buffer to remove an unused bytes or free space after a mutation.
+    /// This is a pretty expensive operation (requires full copy of the whole buffer) so should be done sparingly.
+    /// 
+    /// The first argument, new_capacity, is the capacity of the underlying Vec<u8> that we'll be copying the data into.  The default is the size of the old buffer.
+    /// 
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "string"
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set initial value
+    /// new_buffer.set(&[], "hello")?;
+    /// // using 11 bytes
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 10,
+    ///     after_compaction: 10,
+    ///     wasted_bytes: 0
+    /// }, new_buffer.calc_bytes()?);
+    /// // update the value
+    /// new_buffer.set(&[], "hello, world")?;
+    /// // now using 25 bytes, with 7 bytes of wasted bytes
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 24,
+    ///     after_compaction: 17,
+    ///     wasted_bytes: 7
+    /// }, new_buffer.calc_bytes()?);
+    /// // compact to save space
+    /// new_buffer.compact(None)?;
+    /// // back down to 18 bytes with no wasted bytes
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 17,
+    ///     after_compaction: 17,
+    ///     wasted_bytes: 0
+    /// }, new_buffer.calc_bytes()?);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn compact<'compact>(&mut self, new_capacity: Option<u32>) -> Result<(), NP_Error> {
+        self.compact_with(new_capacity, None, NP_Compact_Flags::default())?;
+        Ok(())
+    }
+
+    /// Compacts the buffer like `compact`, and stores identical strings and bytes values once.
+    /// 
+    /// Every dynamically sized string or bytes value with the same contents as one copied before it points at that copy instead.  Returns how many bytes were saved by sharing values, on top of what `compact` would save.
+    /// 
+    /// Once values are shared they're copied instead of updated in place, so later updates to these values use more space until the buffer is compacted again.  A plain `compact` stops sharing values.
+    /// 
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["0"], "pending")?;
+    /// new_buffer.set(&["1"], "pending")?;
+    /// new_buffer.set(&["2"], "pending")?;
+    /// 
+    /// // two copies of "pending" (and their length) removed
+    /// assert_eq!(new_buffer.compact_dedupe(None)?, 18);
+    /// 
+    /// assert_eq!(new_buffer.get::<&str>(&["2"])?, Some("pending"));
+    /// 
+    /// // updating a shared value doesn't change the others
+    /// new_buffer.set(&["0"], "done")?;
+    /// assert_eq!(new_buffer.get::<&str>(&["1"])?, Some("pending"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn compact_dedupe(&mut self, new_capacity: Option<u32>) -> Result<usize, NP_Error> {
+        self.compact_with(new_capacity, None, NP_Compact_Flags { dedupe: true, ..Default::default() })
+    }
+
+    /// Compacts the buffer into a canonical layout, buffers holding the same values end up with the same bytes no matter what order the values were set, changed or deleted in.
+    /// 
+    /// Map keys are put in order, and map keys, pointers and list items without a value are left out (the last item of a list is kept, it sets the length).  Good for content addressed storage or signing buffers, compact them this way right before closing.  The bytes only match between buffers with the same schema and options, tombstones and versions are copied as they are.
+    /// 
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["stats", {"type": "map", "value": {"type": "u32"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut first = factory.empty_buffer(None);
+    /// first.set(&["name"], "Jeb")?;
+    /// first.set(&["stats", "flights"], 12u32)?;
+    /// first.set(&["stats", "landings"], 11u32)?;
+    /// 
+    /// let mut second = factory.empty_buffer(None);
+    /// second.set(&["stats", "landings"], 11u32)?;
+    /// second.set(&["stats", "crashes"], 1u32)?;
+    /// second.set(&["stats", "flights"], 12u32)?;
+    /// second.set(&["name"], "Jebediah")?;
+    /// second.set(&["name"], "Jeb")?;
+    /// second.del(&["stats", "crashes"])?;
+    /// 
+    /// assert_ne!(first.read_bytes(), second.read_bytes());
+    /// 
+    /// first.compact_canonical(None)?;
+    /// second.compact_canonical(None)?;
+    /// assert_eq!(first.read_bytes(), second.read_bytes());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn compact_canonical(&mut self, new_capacity: Option<u32>) -> Result<(), NP_Error> {
+        self.compact_with(new_capacity, None, NP_Compact_Flags { canonical: true, ..Default::default() })?;
+        Ok(())
+    }
+
+    /// Deletes every sensitive table column in the buffer, then compacts it so none of their bytes are left.
+    /// 
+    /// Columns are sensitive when their schema has `"sensitive": true`.  Use this before sending a buffer somewhere that shouldn't see personal data.
+    /// 
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "table", "columns": [
+    ///         ["name",     {"type": "string"}],
+    ///         ["password", {"type": "string", "sensitive": true}]
+    ///     ]}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["0", "name"], "Jeb Kermin")?;
+    /// new_buffer.set(&["0", "password"], "hunter2")?;
+    /// 
+    /// new_buffer.compact_redacted(None)?;
+    /// 
+    /// assert_eq!(new_buffer.get::<&str>(&["0", "name"])?, Some("Jeb Kermin"));
+    /// assert_eq!(new_buffer.get::<&str>(&["0", "password"])?, None);
+    /// assert!(!new_buffer.read_bytes().windows(7).any(|bytes| bytes == b"hunter2"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn compact_redacted(&mut self, new_capacity: Option<u32>) -> Result<(), NP_Error> {
+        clear_sensitive(&NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), &self.memory);
+        self.compact_with(new_capacity, None, NP_Compact_Flags::default())?;
+        Ok(())
+    }
+
+    /// Compacts the buffer like `compact`, leaving out deprecated table columns and every value covered by `exclude`.
+    /// 
+    /// Deprecated columns are columns renamed to an empty string in the schema, see the [schema](../schema/index.html) docs.  Excluded map values are left out along with their keys, none of the bytes of dropped values are kept so old buffers can be pruned to what a retention policy allows.
+    /// 
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::mask::NP_Mask;
+    /// 
+    /// let old_factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["fax",   {"type": "string"}],
+    ///         ["logins", {"type": "map", "value": {"type": "u32"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = old_factory.empty_buffer(None);
+    /// new_buffer.set(&["name"], "Jeb Kermin")?;
+    /// new_buffer.set(&["fax"], "555-0199")?;
+    /// new_buffer.set(&["logins", "laptop"], 12u32)?;
+    /// new_buffer.set(&["logins", "phone"], 3u32)?;
+    /// 
+    /// // the fax column has since been deprecated
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["",      {"type": "string"}],
+    ///         ["logins", {"type": "map", "value": {"type": "u32"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.open_buffer(new_buffer.close());
+    /// let expired = NP_Mask::new(&factory.schema, &[&["logins", "phone"]])?;
+    /// new_buffer.compact_pruned(None, Some(&expired))?;
+    /// 
+    /// assert_eq!(new_buffer.json_encode(&[])?.stringify(), r#"{"name":"Jeb Kermin","":null,"logins":{"laptop":12}}"#);
+    /// assert!(!new_buffer.read_bytes().windows(8).any(|bytes| bytes == b"555-0199"));
+    /// assert!(!new_buffer.read_bytes().windows(5).any(|bytes| bytes == b"phone"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn compact_pruned(&mut self, new_capacity: Option<u32>, exclude: Option<&NP_Mask>) -> Result<(), NP_Error> {
+        clear_pruned(&NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), &self.memory, &mut Vec::new(), exclude);
+        self.compact_with(new_capacity, None, NP_Compact_Flags { prune: true, ..Default::default() })?;
+        Ok(())
+    }
+
+    /// Compacts the buffer like `compact`, copying into `target` instead of a new `Vec<u8>`.
+    /// 
+    /// Whatever `target` holds is cleared and its capacity is used for the compacted buffer.  Once the copy is done `target` gets the old bytes of the buffer, emptied, so a pair of `Vec`s can be passed back and forth between compactions without allocating as long as the buffer fits in their capacity.  If the compaction fails `target` is left empty.
+    /// 
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "string"
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&[], "hello")?;
+    /// new_buffer.set(&[], "hello, world")?;
+    /// 
+    /// let mut spare: Vec<u8> = Vec::with_capacity(1024);
+    /// let spare_addr = spare.as_ptr();
+    /// 
+    /// new_buffer.compact_into(&mut spare)?;
+    /// 
+    /// // the buffer is using the spare bytes now, and the old bytes are ready for the next compaction
+    /// assert_eq!(new_buffer.read_bytes().as_ptr(), spare_addr);
+    /// assert_eq!(new_buffer.read_bytes().len(), 17);
+    /// assert!(spare.is_empty() && spare.capacity() > 0);
+    /// assert_eq!(new_buffer.get::<&str>(&[])?, Some("hello, world"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn compact_into(&mut self, target: &mut Vec<u8>) -> Result<(), NP_Error> {
+        self.compact_with(None, Some(target), NP_Compact_Flags::default())?;
+        Ok(())
+    }
+
+    fn compact_with(&mut self, new_capacity: Option<u32>, mut target: Option<&mut Vec<u8>>, flags: NP_Compact_Flags) -> Result<usize, NP_Error> {
+
+        np_span!("compact", bytes = self.memory.read_bytes().len());
+
+        let (new_bytes, mut pending) = self.compact_begin(new_capacity, target.as_mut().map(|target| core::mem::take(*target)), flags)?;
+
+        self.compact_step(&new_bytes, &mut pending, usize::MAX)?;
+
+        let (saved, old_bytes) = self.compact_end(new_bytes);
+
+        if let Some(target) = target {
+            *target = old_bytes.reclaim();
+        }
+
+        Ok(saved)
+    }
+
+    // new memory with everything but the values copied, and the queue of values to compact into it
+    pub(crate) fn compact_begin(&self, new_capacity: Option<u32>, bytes: Option<Vec<u8>>, flags: NP_Compact_Flags) -> Result<(NP_Memory<'buffer>, Vec<(NP_Cursor, NP_Cursor)>), NP_Error> {
+
+        let NP_Compact_Flags { dedupe, inline, canonical, fingerprint, prune } = flags;
+
+        let capacity = match new_capacity {
+            Some(x) => { x as usize },
+            None => self.memory.read_bytes().len()
+        };
+
+        let new_bytes = match bytes {
+            Some(bytes) => NP_Memory::new_in(bytes, self.memory.factory_schema),
+            None => NP_Memory::new(Some(capacity), self.memory.factory_schema)
+        }.with_options(self.memory.options());
+
+        let tombstones = self.memory.has_tombstones() || self.clock.is_some();
+        if tombstones {
+            new_bytes.start_tombstones()?;
+        }
+
+        if self.memory.has_version() || self.hlc.is_some() {
+            version::copy(&self.memory, &new_bytes)?;
+        }
+
+        if fingerprint || self.memory.has_fingerprint() {
+            fingerprint::start(&new_bytes)?;
+        }
+
+        if tombstones {
+            tombstone::copy(&self.memory, &new_bytes)?;
+        }
+
+        if inline || self.memory.has_inline() {
+            new_bytes.start_inline();
+        }
+
+        if dedupe {
+            new_bytes.start_dedupe();
+        }
+
+        new_bytes.set_canonical(canonical);
+        new_bytes.set_pruning(prune);
+
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        Ok((new_bytes, NP_Cursor::compact_start(root, root)))
+    }
+
+    // copy up to `max_items` more values into memory from `compact_begin`, true once they're all copied
+    pub(crate) fn compact_step(&self, new_bytes: &NP_Memory<'buffer>, pending: &mut Vec<(NP_Cursor, NP_Cursor)>, max_items: usize) -> Result<bool, NP_Error> {
+        NP_Cursor::compact_steps(&self.memory, new_bytes, pending, max_items)
+    }
+
+    // switch to the compacted memory, returns the bytes saved by deduping and the old memory
+    pub(crate) fn compact_end(&mut self, new_bytes: NP_Memory<'buffer>) -> (usize, NP_Memory<'buffer>) {
+
+        new_bytes.set_canonical(false);
+        new_bytes.set_pruning(false);
+
+        let saved = if new_bytes.is_deduping() { new_bytes.end_dedupe() } else { 0 };
+
+        self.cursor = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        self.backup_cursor = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        self.cursor_hash = SEED;
+        self.backup_hash = SEED;
+
+        np_event!(before = self.memory.read_bytes().len(), after = new_bytes.read_bytes().len(), "compacted");
+        np_count!(COMPACTIONS);
+
+        (saved, core::mem::replace(&mut self.memory, new_bytes))
+    }
+
+    /// Recursively measures how many bytes each element in the buffer is using.
+    /// This will let you know how many bytes can be saved from a compaction.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "string"
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&[], "hello")?;
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 10,
+    ///     after_compaction: 10,
+    ///     wasted_bytes: 0
+    /// }, new_buffer.calc_bytes()?);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn calc_bytes<'bytes>(&self) -> Result<NP_Size_Data, NP_Error> {
+
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        self.memory.count_shared(true);
+        let real_bytes = NP_Cursor::calc_size(&root, &self.memory);
+        self.memory.count_shared(false);
+        let real_bytes = real_bytes? + ROOT_PTR_ADDR + tombstone::size(&self.memory) + version::size(&self.memory) + fingerprint::size(&self.memory);
+        let total_size = self.memory.read_bytes().len();
+        if total_size >= real_bytes {
+            Ok(NP_Size_Data {
+                current_buffer: total_size,
+                after_compaction: real_bytes,
+                wasted_bytes: total_size - real_bytes
+            })
+        } else {
+            Err(NP_Error::new_static("Error calculating bytes!"))
+        }
+    }
+
+    /// Bytes the value at `path` and everything below it will use after compaction, its pointer included.  Returns `None` if there's no value at `path`.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["name"], "Jeb Kermin")?;
+    /// new_buffer.set(&["tags", "0"], "pilot")?;
+    /// new_buffer.set(&["tags", "1"], "brave")?;
+    /// 
+    /// // pointer, length and 10 bytes of text
+    /// assert_eq!(new_buffer.size_of(&["name"])?, Some(14));
+    /// assert_eq!(new_buffer.size_of(&["tags"])?, Some(30));
+    /// assert_eq!(new_buffer.size_of(&["tags", "5"])?, None);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn size_of(&self, path: &[&str]) -> Result<Option<usize>, NP_Error> {
+
+        let found_cursor = match self.select(self.cursor, path)? {
+            Some(x) if x.get_value(&self.memory).get_addr_value() != 0 => x,
+            _ => return Ok(None)
+        };
+
+        self.memory.count_shared(true);
+        let size = NP_Cursor::calc_size(&found_cursor, &self.memory);
+        self.memory.count_shared(false);
+
+        Ok(Some(size?))
+    }
+
+    /// Bytes used by every node of the schema, to see which values take up the most space.
+    /// 
+    /// Nodes are listed in schema order with every node before the nodes below it, so the report reads like a tree.  The bytes of a node include the bytes of the nodes below it, nodes without any values are listed with zero bytes.
+    /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    /// use no_proto::buffer::NP_Node_Size;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "map",
-    ///    "value": {"type": "string"}
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
+    ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set values
-    /// new_buffer.set(&["foo"], "bar")?;
-    /// new_buffer.set(&["foo2"], "bar2")?;
-    /// // get length of value at root (Map)
-    /// assert_eq!(new_buffer.length(&[])?, Some(2));
+    /// new_buffer.set(&["tags", "0"], "pilot")?;
+    /// new_buffer.set(&["tags", "1"], "brave")?;
+    /// 
+    /// // the reserved byte in front of the root pointer isn't part of any value
+    /// assert_eq!(new_buffer.calc_bytes()?.after_compaction, 41);
+    /// 
+    /// let report: Vec<(String, usize, usize)> = new_buffer.size_report()?.into_iter().map(|node| {
+    ///     (node.path.join("."), node.values, node.bytes)
+    /// }).collect();
+    /// 
+    /// assert_eq!(report, vec![
+    ///     (String::from(""), 1, 40),
+    ///     (String::from("name"), 0, 0),
+    ///     (String::from("tags"), 1, 30),
+    ///     (String::from("tags.*"), 2, 24)
+    /// ]);
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    /// ## Collection (Tuple) Example
+    pub fn size_report(&self) -> Result<Vec<NP_Node_Size>, NP_Error> {
+
+        let mut nodes: Vec<NP_Node_Size> = Vec::new();
+        // index in `nodes` of each schema address
+        let mut index: Vec<Option<usize>> = alloc::vec![None; self.memory.schema.len()];
+
+        schema_nodes(self.memory.schema, 0, &mut Vec::new(), &mut nodes, &mut index);
+
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        measure_nodes(&root, &self.memory, &mut nodes, &index)?;
+
+        Ok(nodes)
+    }
+
+    /// Measure how the bytes in this buffer are used.
+    /// 
+    /// Allocation counts start at zero when a buffer is created, opened or compacted.
+    /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    /// use no_proto::schema::NP_TypeKeys;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "tuple",
-    ///    "values": [
-    ///         {"type": "string"}, 
-    ///         {"type": "string"}
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["age",  {"type": "u8"}]
     ///     ]
     /// }"#)?;
     /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// // get length of value at root (Tuple)
-    /// assert_eq!(new_buffer.length(&[])?, Some(2));
+    /// let mut new_buffer = factory.empty_buffer(Some(16));
+    /// new_buffer.set(&["name"], "Billy")?;
+    /// new_buffer.set(&["age"], 20u8)?;
+    /// new_buffer.set(&["name"], "Bob")?;
+    /// 
+    /// let stats = new_buffer.memory_stats()?;
+    /// 
+    /// // vtable, length and bytes of "Billy", then 20
+    /// assert_eq!(stats.allocations, 4);
+    /// assert_eq!(stats.growth_events, 1);
+    /// // root pointer and vtable
+    /// assert_eq!(stats.pointer_bytes, 13);
+    /// // "Bob" with its length and 20
+    /// assert_eq!(stats.payload_bytes, 6);
+    /// assert_eq!(stats.wasted_bytes, 2);
+    /// assert_eq!(stats.pointer_bytes + stats.payload_bytes + stats.wasted_bytes, stats.current_buffer);
+    /// assert_eq!(stats.bytes_by_type, vec![(NP_TypeKeys::UTF8String, 5), (NP_TypeKeys::Uint8, 1), (NP_TypeKeys::Table, 10)]);
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn length(&self, path: &[&str]) -> Result<Option<usize>, NP_Error> {
-        let value_cursor = self.select(self.cursor.clone(), false, path)?;
+    pub fn memory_stats(&self) -> Result<NP_Memory_Stats, NP_Error> {
 
-        let found_cursor = if let Some(x) = value_cursor {
-            x
-        } else {
-            return Ok(None);
+        let mut stats = NP_Memory_Stats {
+            current_buffer: self.memory.read_bytes().len(),
+            capacity: self.memory.capacity(),
+            allocations: self.memory.alloc_counts().0,
+            growth_events: self.memory.alloc_counts().1,
+            // root pointer, the reserved byte in front of it, any tombstones and the version
+            pointer_bytes: ROOT_PTR_ADDR + 2 + tombstone::size(&self.memory) + version::size(&self.memory),
+            payload_bytes: 0,
+            wasted_bytes: 0,
+            bytes_by_type: Vec::new()
         };
 
-        let addr_value = found_cursor.get_value(&self.memory).get_addr_value();
-
-
-        match &self.memory.schema[found_cursor.schema_addr] {
-            NP_Parsed_Schema::List { of, .. } => {
-                if addr_value == 0 {
-                    return Ok(None);
-                }
+        let mut seen: Vec<usize> = Vec::new();
 
-                let list_data = NP_List::get_list(addr_value as usize, &self.memory);
-                let tail_addr = list_data.get_tail() as usize;
-                if tail_addr == 0 {
-                    Ok(Some(0))
-                } else {
-                    let tail_cursor = NP_Cursor::new(tail_addr, *of, found_cursor.schema_addr);
-                    let cursor_data = tail_cursor.get_value(&self.memory);
-                    Ok(Some(cursor_data.get_index() as usize + 1))
-                }
-            },
-            NP_Parsed_Schema::Map { .. } => {
-                if addr_value == 0 {
-                    return Ok(None);
-                }
-                let mut count = 0usize;
-                let mut map_iter = NP_Map::new_iter(&found_cursor, &self.memory);
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        if root.get_value(&self.memory).get_addr_value() != 0 {
+            measure(&root, &self.memory, &mut stats, &mut seen)?;
+        }
 
-                // key is maybe in map
-                while let Some((_ikey, _item)) = map_iter.step_iter(&self.memory) {
-                    count += 1;
-                }
+        stats.bytes_by_type.sort_by_key(|(key, _)| *key as u8);
 
-                Ok(Some(count))
-            },
-            NP_Parsed_Schema::Table { columns, ..} => {
-                Ok(Some(columns.len()))
-            },
-            NP_Parsed_Schema::Tuple { values, .. } => {
-                Ok(Some(values.len()))
-            },
-            NP_Parsed_Schema::Bytes {  size, ..} => {
-                if *size > 0 {
-                    Ok(Some(*size as usize))
-                } else {
-                    let length_bytes = self.memory.get_2_bytes(addr_value as usize).unwrap_or(&[0u8; 2]);
-                    Ok(Some(u16::from_be_bytes(*length_bytes) as usize))
-                }
-            },
-            NP_Parsed_Schema::UTF8String { size, .. } => {
-                if *size > 0 {
-                    Ok(Some(*size as usize))
-                } else {
-                    let length_bytes = self.memory.get_2_bytes(addr_value as usize).unwrap_or(&[0u8; 2]);
-                    Ok(Some(u16::from_be_bytes(*length_bytes) as usize))
-                }
-            },
-            _ => {
-                Ok(None)
-            }
+        let used = stats.pointer_bytes + stats.payload_bytes;
+        if used > stats.current_buffer {
+            return Err(NP_Error::new_static("Error calculating bytes!"));
         }
-  
+        stats.wasted_bytes = stats.current_buffer - used;
+
+        Ok(stats)
     }
 
-    /// Clear an inner value from the buffer.
-    /// This can also be used to clear deeply nested collection objects or scalar objects.
+    /// Compare the logical contents of two buffers.
     /// 
-    /// Returns `true` if it found a value to delete (and deleted it), `false` otherwise.
+    /// Byte equality doesn't mean much once buffers have different mutation histories, the same values can end up at different addresses with different amounts of dead space between them.  This compares the values themselves: map keys can be in any order and collections without any values in them are treated the same as unset values.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "list",
-    ///     "of": {"type": "string"}
+    ///    "type": "map",
+    ///    "value": {"type": "string"}
     /// }"#)?;
     /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set index 0
-    /// new_buffer.set(&["0"], "hello")?;
-    /// // del index 0
-    /// new_buffer.del(&["0"])?;
-    /// // value is gone now!
-    /// assert_eq!(None, new_buffer.get::<&str>(&["0"])?);
+    /// let mut buffer_a = factory.empty_buffer(None);
+    /// buffer_a.set(&["name"], "hello")?;
+    /// buffer_a.set(&["color"], "blue")?;
+    /// 
+    /// let mut buffer_b = factory.empty_buffer(None);
+    /// buffer_b.set(&["color"], "red")?;
+    /// buffer_b.set(&["name"], "hello")?;
+    /// buffer_b.set(&["color"], "blue")?;
+    /// 
+    /// // different bytes, same values
+    /// assert_ne!(buffer_a.read_bytes(), buffer_b.read_bytes());
+    /// assert!(buffer_a.semantic_eq(&buffer_b));
+    /// assert_eq!(buffer_a.semantic_hash(), buffer_b.semantic_hash());
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn del(&mut self, path: &[&str]) -> Result<bool, NP_Error> {
-
-        let value_cursor = self.select(self.cursor.clone(), false, path)?;
-        
-        match value_cursor {
-            Some(x) => {
-                if self.sortable {
-                    match &self.memory.schema[x.schema_addr] {
-                        NP_Parsed_Schema::Table { .. } => { return Ok(false) },
-                        NP_Parsed_Schema::Tuple { .. } => { return Ok(false) },
-                        NP_Parsed_Schema::List { .. } => { return Ok(false) },
-                        NP_Parsed_Schema::Map { .. } => { return Ok(false) },
-                        _ => NP_Cursor::set_default(x, &self.memory)?
-                    }
-                } else {
-                    // clear value address in buffer
-                    x.get_value(&self.memory).set_addr_value(0);
-                }
+    pub fn semantic_eq(&self, other: &NP_Buffer) -> bool {
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        compare::eq(&root, &self.memory, &root, &other.memory)
+    }
 
-                Ok(true)
-            }
-            None => Ok(false)
-        }
+    /// Hash the logical contents of this buffer.
+    /// 
+    /// Buffers that are equal with `semantic_eq` always have the same hash.  Empty buffers hash to `0`.
+    /// 
+    pub fn semantic_hash(&self) -> u32 {
+        compare::hash(&NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), &self.memory).unwrap_or(0)
     }
-  
-    /// Retrieve an inner value from the buffer. 
+
+    /// Hash the live values of this buffer into a 64 bit hash, for deduplicating and caching buffers.
     /// 
-    /// The type that you cast the request to will be compared to the schema, if it doesn't match the schema the request will fail.
+    /// Values are hashed in schema order with map items in key order, so dead space, compaction and the order values were set in don't change the hash.  Buffers that are equal with `semantic_eq` always have the same content hash, the wider hash makes collisions between different buffers far less likely than with `semantic_hash`.  Empty buffers hash to `0`.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
     /// 
-    /// // a list where each item is a map where each key has a value containing a list of strings
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "list",
-    ///    "of": {"type": "map", "value": {
-    ///         "type": "list", "of": {"type": "string"}
-    ///     }}
+    ///    "type": "map",
+    ///    "value": {"type": "string"}
     /// }"#)?;
     /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// // third item in the top level list -> key "alpha" of map at 3rd element -> 9th element of list at "alpha" key
-    /// // 
-    /// new_buffer.set(&["3", "alpha", "9"], "who would build a schema like this")?;
+    /// let mut buffer_a = factory.empty_buffer(None);
+    /// buffer_a.set(&["name"], "hello")?;
+    /// buffer_a.set(&["color"], "blue")?;
     /// 
-    /// // get the same item we just set
-    /// let message = new_buffer.get::<&str>(&["3", "alpha", "9"])?;
+    /// let mut buffer_b = factory.empty_buffer(None);
+    /// buffer_b.set(&["color"], "red")?;
+    /// buffer_b.set(&["name"], "hello")?;
+    /// buffer_b.set(&["color"], "blue")?;
     /// 
-    /// assert_eq!(message, Some("who would build a schema like this"));
+    /// assert_eq!(buffer_a.content_hash(), buffer_b.content_hash());
+    /// 
+    /// buffer_b.set(&["color"], "green")?;
+    /// assert_ne!(buffer_a.content_hash(), buffer_b.content_hash());
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn get<'get, X: 'get>(&'get self, path: &[&str]) -> Result<Option<X>, NP_Error> where X: NP_Value<'get> + NP_Scalar {
-        let value_cursor = self.select(self.cursor.clone(), false, path)?;
-
-        match value_cursor {
-            Some(x) => {
-                                
-                // type does not match schema
-                if X::type_idx().1 != *self.memory.schema[x.schema_addr].get_type_key() {
-                    let mut err = "TypeError: Attempted to get value for type (".to_owned();
-                    err.push_str(X::type_idx().0);
-                    err.push_str(") for schema of type (");
-                    err.push_str(self.memory.schema[x.schema_addr].get_type_data().0);
-                    err.push_str(")\n");
-                    return Err(NP_Error::new(err));
-                }
+    pub fn content_hash(&self) -> u64 {
+        compare::content_hash(&NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), &self.memory).unwrap_or(0)
+    }
 
-                match X::into_value(&x, &self.memory)? {
-                    Some(x) => {
-                        Ok(Some(x))
-                    },
-                    None => { // no value found here, return default from schema
-                        match X::schema_default(&self.memory.schema[x.schema_addr]) {
-                            Some(y) => {
-                                Ok(Some(y))
-                            },
-                            None => { // no default in schema, no value to provide
-                                Ok(None)
-                            }
-                        }                        
-                    }
-                }
-            }
-            None => Ok(None)
-        }
+    /// A token for the live contents of this buffer, for conditional updates with `NP_Factory::apply_if_match`.
+    /// 
+    /// The etag is the 64 bit `content_hash` of the buffer, so it covers every value reachable from the root: values are hashed in schema order with map items in key order.  Compaction, dead space and the order values were set in don't change it, and neither does a collection that's been emptied out.  The schema isn't part of the etag, only compare etags of buffers from the same factory.  Empty buffers have an etag of `0`.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "map",
+    ///    "value": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut buffer = factory.empty_buffer(None);
+    /// buffer.set(&["name"], "hello")?;
+    /// let etag = buffer.etag();
+    /// assert_eq!(etag, buffer.content_hash());
+    /// 
+    /// buffer.set(&["name"], "world")?;
+    /// buffer.set(&["name"], "hello")?;
+    /// buffer.compact(None)?;
+    /// assert_eq!(etag, buffer.etag());
+    /// 
+    /// buffer.set(&["name"], "world")?;
+    /// assert_ne!(etag, buffer.etag());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn etag(&self) -> u64 {
+        self.content_hash()
     }
 
-    /// This performs a compaction if the closure provided as the second argument returns `true`.
-    /// Compaction is a pretty expensive operation (requires full copy of the whole buffer) so should be done sparingly.
-    /// The closure is provided an argument that contains the original size of the buffer, how many bytes could be saved by compaction, and how large the new buffer would be after compaction.  The closure should return `true` to perform compaction, `false` otherwise.
+    /// Order two sortable buffers without closing them.
     /// 
-    /// The first argument, new_capacity, is the capacity of the underlying Vec<u8> that we'll be copying the data into.  The default is the size of the old buffer.
+    /// This gives the same result as comparing the bytes from `close_sortable()`, so buffers can be ordered (or used as `BTreeMap` keys through a wrapper) without copying their bytes out first.
     /// 
-    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// This operation fails if either buffer isn't sortable or the buffers don't share the same schema.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    /// use core::cmp::Ordering;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "string"
+    ///    "type": "tuple",
+    ///    "sorted": true,
+    ///    "values": [
+    ///         {"type": "u8"},
+    ///         {"type": "string", "size": 6}
+    ///     ]
     /// }"#)?;
     /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set initial value
-    /// new_buffer.set(&[], "hello")?;
-    /// // using 9 bytes
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 10,
-    ///     after_compaction: 10,
-    ///     wasted_bytes: 0
-    /// }, new_buffer.calc_bytes()?);
-    /// // update the value
-    /// new_buffer.set(&[], "hello, world")?;
-    /// // now using 25 bytes, with 7 bytes of wasted space
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 24,
-    ///     after_compaction: 17,
-    ///     wasted_bytes: 7
-    /// }, new_buffer.calc_bytes()?);
-    /// // compact to save space
-    /// new_buffer.maybe_compact(None, |compact_data| {
-    ///     // only compact if wasted bytes are greater than 5
-    ///     if compact_data.wasted_bytes > 5 {
-    ///         true
-    ///     } else {
-    ///         false
-    ///     }
-    /// })?;
-    /// // back down to 18 bytes with no wasted bytes
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 17,
-    ///     after_compaction: 17,
-    ///     wasted_bytes: 0
-    /// }, new_buffer.calc_bytes()?);
+    /// let mut buffer_a = factory.empty_buffer(None);
+    /// buffer_a.set(&["0"], 55u8)?;
+    /// buffer_a.set(&["1"], "hello")?;
+    /// 
+    /// let mut buffer_b = factory.empty_buffer(None);
+    /// buffer_b.set(&["0"], 55u8)?;
+    /// buffer_b.set(&["1"], "world")?;
+    /// 
+    /// assert_eq!(buffer_a.cmp_sortable(&buffer_b)?, Ordering::Less);
+    /// assert_eq!(buffer_b.cmp_sortable(&buffer_a)?, Ordering::Greater);
+    /// assert_eq!(buffer_a.cmp_sortable(&buffer_a)?, Ordering::Equal);
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn maybe_compact<F>(&mut self, new_capacity: Option<u32>, mut callback: F) -> Result<(), NP_Error> where F: FnMut(NP_Size_Data) -> bool {
+    pub fn cmp_sortable(&self, other: &NP_Buffer) -> Result<Ordering, NP_Error> {
 
-        let bytes_data = self.calc_bytes()?;
+        if self.sortable == false || other.sortable == false {
+            return Err(NP_Error::new_static("Attempted to cmp_sortable() on buffer that isn't sortable!"));
+        }
 
-        if callback(bytes_data) {
-            self.compact(new_capacity)?;
+        // buffers from the same factory share the schema, otherwise compare the schemas themselves
+        if core::ptr::eq(self.memory.schema, other.memory.schema) == false {
+            if NP_Schema::_type_to_json(self.memory.schema, 0)?.stringify() != NP_Schema::_type_to_json(other.memory.schema, 0)?.stringify() {
+                return Err(NP_Error::new_static("Attempted to cmp_sortable() on buffers with different schemas!"));
+            }
         }
 
-        return Ok(());
+        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        Ok(compare::cmp_sortable(&root, &self.memory, &root, &other.memory))
     }
 
-    /// Compacts a buffer to remove an unused bytes or free space after a mutation.
-    /// This is a pretty expensive operation (requires full copy of the whole buffer) so should be done sparingly.
-    /// 
-    /// The first argument, new_capacity, is the capacity of the underlying Vec<u8> that we'll be copying the data into.  The default is the size of the old buffer.
+    /// Check that every required table column in this buffer has a value.
     /// 
-    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// Columns are required when their schema has `"nullable": false` (or `"required": true`).  Required columns are only checked in tables that exist, so a table nested in an unset column or a list item that hasn't been created won't be reported.  The root table is always checked.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "string"
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["id",      {"type": "u32", "nullable": false}],
+    ///         ["email",   {"type": "string"}],
+    ///         ["address", {"type": "table", "columns": [
+    ///             ["street", {"type": "string", "nullable": false}],
+    ///             ["city",   {"type": "string", "nullable": false}]
+    ///         ]}]
+    ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set initial value
-    /// new_buffer.set(&[], "hello")?;
-    /// // using 11 bytes
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 10,
-    ///     after_compaction: 10,
-    ///     wasted_bytes: 0
-    /// }, new_buffer.calc_bytes()?);
-    /// // update the value
-    /// new_buffer.set(&[], "hello, world")?;
-    /// // now using 25 bytes, with 7 bytes of wasted bytes
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 24,
-    ///     after_compaction: 17,
-    ///     wasted_bytes: 7
-    /// }, new_buffer.calc_bytes()?);
-    /// // compact to save space
-    /// new_buffer.compact(None)?;
-    /// // back down to 18 bytes with no wasted bytes
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 17,
-    ///     after_compaction: 17,
-    ///     wasted_bytes: 0
-    /// }, new_buffer.calc_bytes()?);
+    /// new_buffer.set(&["address", "city"], "Portland")?;
+    /// 
+    /// match new_buffer.validate() {
+    ///     Err(NP_Error::MissingRequired { paths }) => {
+    ///         assert_eq!(paths, vec![vec!["id"], vec!["address", "street"]]);
+    ///     },
+    ///     _ => panic!()
+    /// }
+    /// 
+    /// new_buffer.set(&["id"], 20u32)?;
+    /// new_buffer.set(&["address", "street"], "Main St")?;
+    /// assert_eq!(new_buffer.validate(), Ok(()));
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn compact<'compact>(&mut self, new_capacity: Option<u32>) -> Result<(), NP_Error> {
+    pub fn validate(&self) -> Result<(), NP_Error> {
+        let mut path: Vec<String> = Vec::new();
+        let mut missing: Vec<Vec<String>> = Vec::new();
+        unset_required(&NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), &self.memory, &mut path, &mut missing);
 
-        let capacity = match new_capacity {
-            Some(x) => { x as usize },
-            None => self.memory.read_bytes().len()
-        };
+        if missing.len() == 0 {
+            Ok(())
+        } else {
+            Err(NP_Error::MissingRequired { paths: missing })
+        }
+    }
 
-        let old_root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+    // reads only ever walk the buffer, making the path needs `&mut self`
+    fn select(&self, cursor: NP_Cursor, path: &[&str]) -> Result<Option<NP_Cursor>, NP_Error> {
+        select_path(&self.memory, cursor, false, path)
+    }
 
-        let new_bytes = NP_Memory::new(Some(capacity), self.memory.schema);
-        let new_root  = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+    fn select_make(&mut self, cursor: NP_Cursor, path: &[&str]) -> Result<Option<NP_Cursor>, NP_Error> {
+        select_path(&self.memory, cursor, true, path)
+    }
+}
 
-        NP_Cursor::compact(old_root, &self.memory, new_root, &new_bytes)?;
+// follow the path from the cursor, collections and items that don't exist are made if `make_path` is true
+// the most bytes making the collections and items missing along `path` could take and the schema at the end of it, nothing is changed
+// also gives the deepest cursor that already exists and how many keys of the path it took to get there, so the path is only walked once
+fn path_cost(memory: &NP_Memory, cursor: NP_Cursor, path: &[&str]) -> Result<(usize, usize, NP_Cursor, usize), NP_Error> {
 
-        self.cursor = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
-        self.backup_cursor = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+    let max_depth = memory.options().limits.max_depth;
+    if path.len() > max_depth {
+        return Err(NP_Error::new_limit("max_depth", max_depth));
+    }
 
-        self.memory = new_bytes;
+    let vtable = size_of::<NP_Vtable>();
 
-        Ok(())
+    let mut cost = 0usize;
+    let mut existing = Some(cursor);
+    let mut reached = (cursor, 0usize);
+    let mut schema_addr = cursor.schema_addr;
+
+    for (depth, key) in path.iter().enumerate() {
+        let index = || key.parse::<usize>().map_err(|_e| NP_Error::new_static("Need a number to index into collection, string found!"));
+
+        // (bytes to make the collection, bytes to make the item, schema of the item)
+        let (make_cost, item_cost, next_schema) = match &memory.schema[schema_addr] {
+            NP_Parsed_Schema::Table { columns, columns_mapped, .. } => match columns_mapped.get(key) {
+                Some(x) => (vtable, (*x / VTABLE_SLOTS) * vtable, columns[*x].2),
+                None => break
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => match values.get(index()?) {
+                Some(x) => (vtable, (index()? / VTABLE_SLOTS) * vtable, *x),
+                None => break
+            },
+            NP_Parsed_Schema::List { of, wide, .. } => (size_of::<NP_List_Bytes>(), if *wide { size_of::<NP_Pointer_Wide_List_Item>() } else { size_of::<NP_Pointer_List_Item>() }, *of),
+            // the map pointer is the head of its items
+            NP_Parsed_Schema::Map { value, .. } => (0, size_of::<NP_Pointer_Map_Item>() + NP_Map::stored_key_len(&memory.schema[schema_addr], key), *value),
+            NP_Parsed_Schema::Log { of, .. } => (size_of::<NP_Log_Bytes>(), size_of::<NP_Pointer_Log_Item>(), *of),
+            _ => break
+        };
+
+        let next = match existing {
+            Some(x) if x.get_value(memory).get_addr_value() != 0 => select_path(memory, x, false, &[*key])?,
+            _ => {
+                cost += make_cost;
+                None
+            }
+        };
+        match next {
+            Some(x) => reached = (x, depth + 1),
+            None => cost += item_cost
+        }
+
+        existing = next;
+        schema_addr = next_schema;
     }
 
-    /// Recursively measures how many bytes each element in the buffer is using.
-    /// This will let you know how many bytes can be saved from a compaction.
-    /// 
-    /// ```
-    /// use no_proto::error::NP_Error;
-    /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
-    /// 
-    /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "string"
-    /// }"#)?;
-    /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// new_buffer.set(&[], "hello")?;
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 10,
-    ///     after_compaction: 10,
-    ///     wasted_bytes: 0
-    /// }, new_buffer.calc_bytes()?);
-    /// 
-    /// # Ok::<(), NP_Error>(()) 
-    /// ```
-    /// 
-    pub fn calc_bytes<'bytes>(&self) -> Result<NP_Size_Data, NP_Error> {
+    Ok((cost, schema_addr, reached.0, reached.1))
+}
 
-        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
-        let real_bytes = NP_Cursor::calc_size(&root, &self.memory)? + ROOT_PTR_ADDR;
-        let total_size = self.memory.read_bytes().len();
-        if total_size >= real_bytes {
-            return Ok(NP_Size_Data {
-                current_buffer: total_size,
-                after_compaction: real_bytes,
-                wasted_bytes: total_size - real_bytes
-            });
-        } else {
-            return Err(NP_Error::new("Error calculating bytes!"));
+// the bytes writing `value` adds to the buffer, values overwritten where they already are cost nothing
+// big integers and custom values aren't measured, they can still run out of space partway
+fn value_cost(memory: &NP_Memory, schema_addr: usize, existing: Option<NP_Cursor>, value: Option<NP_Dyn>) -> usize {
+    let addr = existing.map(|x| x.get_value(memory).get_addr_value() as usize).unwrap_or(0);
+    let len = match value {
+        Some(NP_Dyn::Str(x)) => x.len(),
+        Some(NP_Dyn::Bytes(x)) => x.len(),
+        _ => 0
+    };
+    // flexible strings and bytes keep their bytes if the new value fits and nothing else points at them
+    let flexible = |shared: bool| {
+        let prev_len = if addr != 0 { memory.get_2_bytes(addr).map(|x| u16::from_be_bytes(*x) as usize).unwrap_or(0) } else { 0 };
+        if addr != 0 && !shared && !memory.has_shared() && !memory.is_deduping() && prev_len >= len { 0 } else { size_of::<u16>() + len }
+    };
+    match &memory.schema[schema_addr] {
+        NP_Parsed_Schema::UTF8String { size: 0, intern, .. } => flexible(*intern),
+        NP_Parsed_Schema::Bytes { size: 0, .. } => flexible(false),
+        NP_Parsed_Schema::Any { .. } => value.map(|x| x.set_cost(existing, memory)).unwrap_or(0),
+        _ if addr != 0 => 0,
+        // collated strings keep a sort key next to the value
+        NP_Parsed_Schema::UTF8String { size, collation: String_Collation::UnicodeCi, .. } => *size as usize * 2,
+        // an offset byte and padding to align the data go in front of tensors
+        NP_Parsed_Schema::Tensor { dtype, .. } => 1 + (tensor::dtype_size(dtype) - 1) + NP_SchemaNode::new(memory.schema, schema_addr).fixed_size().unwrap_or(0),
+        _ => match NP_SchemaNode::new(memory.schema, schema_addr).fixed_size() {
+            // one byte values are kept in their pointer when the buffer allows it
+            Some(1) if memory.has_inline() => 0,
+            Some(size) => size,
+            None => 0
         }
     }
+}
+
+fn select_path(memory: &NP_Memory, cursor: NP_Cursor, make_path: bool, path: &[&str]) -> Result<Option<NP_Cursor>, NP_Error> {
 
-    fn select(&self, cursor: NP_Cursor, make_path: bool, path: &[&str]) -> Result<Option<NP_Cursor>, NP_Error> {
+    let max_depth = memory.options().limits.max_depth;
+    if path.len() > max_depth {
+        return Err(NP_Error::new_limit("max_depth", max_depth));
+    }
 
-        let mut loop_cursor = cursor;
+    let mut loop_cursor = cursor;
 
-        let mut path_index = 0usize;
+    let mut path_index = 0usize;
+    
+    loop {
         
-        loop {
-            
-            if path.len() == path_index {
-                return Ok(Some(loop_cursor));
-            }
+        if path.len() == path_index {
+            return Ok(Some(loop_cursor));
+        }
 
-            // now select into collections
-            match &self.memory.schema[loop_cursor.schema_addr] {
-                NP_Parsed_Schema::Table {  .. } => {
-                    if let Some(next) = NP_Table::select(loop_cursor, path[path_index], make_path, &self.memory)? {
-                        loop_cursor = next;
-                        path_index += 1;
-                    } else {
-                        return Ok(None);
+        // now select into collections
+        match &memory.schema[loop_cursor.schema_addr] {
+            NP_Parsed_Schema::Table {  .. } => {
+                if let Some(next) = NP_Table::select(loop_cursor, path[path_index], make_path, memory)? {
+                    loop_cursor = next;
+                    path_index += 1;
+                } else {
+                    return Ok(None);
+                }
+            },
+            NP_Parsed_Schema::Tuple { .. } => {
+                match path[path_index].parse::<usize>() {
+                    Ok(x) => {
+                        if let Some(next) = NP_Tuple::select(loop_cursor, x, make_path, memory)? {
+                            loop_cursor = next;
+                            path_index += 1;
+                        } else {
+                            return Ok(None);
+                        }
+                    },
+                    Err(_e) => {
+                        return Err(NP_Error::new_static("Need a number to index into tuple, string found!"))
                     }
-                },
-                NP_Parsed_Schema::Tuple { .. } => {
-                    match path[path_index].parse::<usize>() {
-                        Ok(x) => {
-                            if let Some(next) = NP_Tuple::select(loop_cursor, x, make_path, &self.memory)? {
-                                loop_cursor = next;
-                                path_index += 1;
-                            } else {
-                                return Ok(None);
-                            }
-                        },
-                        Err(_e) => {
-                            return Err(NP_Error::new("Need a number to index into tuple, string found!"))
+                }
+            },
+            NP_Parsed_Schema::List { sorted, .. } => {
+                // items set by index could land out of order
+                if make_path && *sorted {
+                    return Err(NP_Error::new_static("Items can only be added to sorted lists with sorted_insert!"));
+                }
+                match path[path_index].parse::<usize>() {
+                    Ok(x) => {
+                        if let Some((_, Some(next))) = NP_List::select(loop_cursor, x, make_path, memory)? {
+                            loop_cursor = next;
+                            path_index += 1;
+                        } else {
+                            return Ok(None);
                         }
+                    },
+                    Err(_e) => {
+                        return Err(NP_Error::new_static("Need a number to index into list, string found!"))
                     }
-                },
-                NP_Parsed_Schema::List { .. } => {
-                    match path[path_index].parse::<usize>() {
-                        Ok(x) => {
-                            if let Some(next) = NP_List::select(loop_cursor, x, make_path, &self.memory)? {
-                                loop_cursor = opt_err(next.1)?;
-                                path_index += 1;
-                            } else {
-                                return Ok(None);
-                            }
-                        },
-                        Err(_e) => {
-                            return Err(NP_Error::new("Need a number to index into list, string found!"))
+                }
+            },
+            NP_Parsed_Schema::Map {  .. } => {
+                if let Some(next) = NP_Map::select(loop_cursor, path[path_index], make_path, memory)? {
+                    loop_cursor = next;
+                    path_index += 1;
+                } else {
+                    return Ok(None);
+                }
+
+            },
+            NP_Parsed_Schema::Log { .. } => {
+                match path[path_index].parse::<usize>() {
+                    Ok(x) => {
+                        if let Some(next) = NP_Log::select(loop_cursor, x, make_path, memory)? {
+                            loop_cursor = next;
+                            path_index += 1;
+                        } else {
+                            return Ok(None);
                         }
+                    },
+                    Err(_e) => {
+                        return Err(NP_Error::new_static("Need a number to index into log, string found!"))
                     }
-                },
-                NP_Parsed_Schema::Map {  .. } => {
-                    if let Some(next) = NP_Map::select(loop_cursor, path[path_index], make_path, &self.memory)? {
-                        loop_cursor = next;
-                        path_index += 1;
-                    } else {
-                        return Ok(None);
+                }
+            },
+            _ => { // we've reached a scalar value but not at the end of the path
+                return Ok(None);
+            }
+        }
+    }
+}
+
+
+
+// collect the paths of required columns without values in this table and every set value below it
+fn unset_required(cursor: &NP_Cursor, memory: &NP_Memory, path: &mut Vec<String>, missing: &mut Vec<Vec<String>>) {
+
+    let is_set = |item: &NP_Cursor| item.get_value(memory).get_addr_value() != 0;
+
+    match &memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::Table { required, .. } => {
+            let mut table = NP_Table::new_iter(cursor, memory);
+            while let Some((idx, col, item)) = table.step_iter(memory) {
+                path.push(String::from(col));
+                match item {
+                    Some(item) if is_set(&item) => unset_required(&item, memory, path, missing),
+                    _ => if required.get(idx) == Some(&true) { missing.push(path.clone()) }
+                }
+                path.pop();
+            }
+        },
+        NP_Parsed_Schema::Tuple { .. } => {
+            if is_set(cursor) == false { return; }
+            let mut tuple = NP_Tuple::new_iter(cursor, memory);
+            while let Some((idx, item)) = tuple.step_iter(memory) {
+                if let Some(item) = item {
+                    if is_set(&item) {
+                        path.push(idx.to_string());
+                        unset_required(&item, memory, path, missing);
+                        path.pop();
+                    }
+                }
+            }
+        },
+        NP_Parsed_Schema::List { .. } => {
+            if is_set(cursor) == false { return; }
+            let mut list = NP_List::new_iter(cursor, memory, true, 0);
+            while let Some((idx, item)) = list.step_iter(memory) {
+                if let Some(item) = item {
+                    if is_set(&item) {
+                        path.push(idx.to_string());
+                        unset_required(&item, memory, path, missing);
+                        path.pop();
+                    }
+                }
+            }
+        },
+        NP_Parsed_Schema::Log { .. } => {
+            if is_set(cursor) == false { return; }
+            let mut log = NP_Log::new_iter(cursor, memory);
+            while let Some((idx, item)) = log.step_iter(memory) {
+                if is_set(&item) {
+                    path.push(idx.to_string());
+                    unset_required(&item, memory, path, missing);
+                    path.pop();
+                }
+            }
+        },
+        NP_Parsed_Schema::Map { .. } => {
+            if is_set(cursor) == false { return; }
+            let mut map = NP_Map::new_iter(cursor, memory);
+            while let Some((key, item)) = map.step_iter(memory) {
+                if is_set(&item) {
+                    path.push(String::from(key));
+                    unset_required(&item, memory, path, missing);
+                    path.pop();
+                }
+            }
+        },
+        _ => { }
+    }
+}
+
+// leave out or mask sensitive columns of every table in json encoded from the schema at `address`
+fn redact_json(json: &mut NP_JSON, schema: &Vec<NP_Parsed_Schema>, address: usize, redaction: &NP_Redaction) {
+    match (&schema[address], json) {
+        (NP_Parsed_Schema::Table { columns, sensitive, .. }, NP_JSON::Dictionary(object)) => {
+            for column in columns.iter() {
+                if sensitive.get(column.0 as usize) == Some(&true) {
+                    match redaction {
+                        NP_Redaction::Omit => object.values.retain(|(key, _)| *key != column.1),
+                        NP_Redaction::Mask(mask) => if let Some(value) = object.get_mut(&column.1) {
+                            if !matches!(value, NP_JSON::Null) { *value = NP_JSON::String(String::from(*mask)) }
+                        }
                     }
+                } else if let Some(value) = object.get_mut(&column.1) {
+                    redact_json(value, schema, column.2, redaction);
+                }
+            }
+        },
+        (NP_Parsed_Schema::Tuple { values, .. }, NP_JSON::Array(items)) => {
+            for (item, addr) in items.iter_mut().zip(values.iter()) {
+                redact_json(item, schema, *addr, redaction);
+            }
+        },
+        (NP_Parsed_Schema::List { of, .. }, NP_JSON::Array(items)) | (NP_Parsed_Schema::Log { of, .. }, NP_JSON::Array(items)) => {
+            for item in items.iter_mut() {
+                redact_json(item, schema, *of, redaction);
+            }
+        },
+        (NP_Parsed_Schema::Map { value, .. }, NP_JSON::Dictionary(object)) => {
+            for (_key, item) in object.values.iter_mut() {
+                redact_json(item, schema, *value, redaction);
+            }
+        },
+        _ => { }
+    }
+}
 
-                },
-                _ => { // we've reached a scalar value but not at the end of the path
-                    return Ok(None);
+// clear the value of every sensitive column in this table and every set value below it
+fn clear_sensitive(cursor: &NP_Cursor, memory: &NP_Memory) {
+
+    let is_set = |item: &NP_Cursor| item.get_value(memory).get_addr_value() != 0;
+
+    match &memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::Table { sensitive, .. } => {
+            if !is_set(cursor) { return; }
+            let mut table = NP_Table::new_iter(cursor, memory);
+            while let Some((idx, _col, item)) = table.step_iter(memory) {
+                if let Some(item) = item {
+                    if sensitive.get(idx) == Some(&true) {
+                        item.get_value_mut(memory).set_addr_value(0);
+                    } else if is_set(&item) {
+                        clear_sensitive(&item, memory);
+                    }
                 }
             }
-        }
+        },
+        NP_Parsed_Schema::Tuple { .. } => {
+            if !is_set(cursor) { return; }
+            let mut tuple = NP_Tuple::new_iter(cursor, memory);
+            while let Some((_idx, item)) = tuple.step_iter(memory) {
+                if let Some(item) = item {
+                    if is_set(&item) { clear_sensitive(&item, memory) }
+                }
+            }
+        },
+        NP_Parsed_Schema::List { .. } => {
+            if !is_set(cursor) { return; }
+            let mut list = NP_List::new_iter(cursor, memory, true, 0);
+            while let Some((_idx, item)) = list.step_iter(memory) {
+                if let Some(item) = item {
+                    if is_set(&item) { clear_sensitive(&item, memory) }
+                }
+            }
+        },
+        NP_Parsed_Schema::Log { .. } => {
+            if !is_set(cursor) { return; }
+            let mut log = NP_Log::new_iter(cursor, memory);
+            while let Some((_idx, item)) = log.step_iter(memory) {
+                if is_set(&item) { clear_sensitive(&item, memory) }
+            }
+        },
+        NP_Parsed_Schema::Map { .. } => {
+            if !is_set(cursor) { return; }
+            let mut map = NP_Map::new_iter(cursor, memory);
+            while let Some((_key, item)) = map.step_iter(memory) {
+                if is_set(&item) { clear_sensitive(&item, memory) }
+            }
+        },
+        _ => { }
     }
 }
 
+// how `compact_with` copies values, all off is a plain `compact`
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct NP_Compact_Flags {
+    // store identical strings and bytes once
+    dedupe: bool,
+    // keep one byte values in their pointers
+    inline: bool,
+    // copy collections in a layout that doesn't depend on write order
+    canonical: bool,
+    // make room for the schema fingerprint
+    fingerprint: bool,
+    // leave out map keys without a value
+    prune: bool
+}
+
+// clear deprecated table columns and values covered by `exclude`, `path` is the path to `cursor`
+fn clear_pruned(cursor: &NP_Cursor, memory: &NP_Memory, path: &mut Vec<String>, exclude: Option<&NP_Mask>) {
 
+    let value = cursor.get_value_mut(memory);
+
+    if value.get_addr_value() == 0 { return; }
+
+    if let Some(mask) = exclude {
+        let keys: Vec<&str> = path.iter().map(|key| key.as_str()).collect();
+        if mask.allows(&keys) {
+            value.set_addr_value(0);
+            return;
+        }
+    }
+
+    let mut visit = |key: String, item: &NP_Cursor| {
+        path.push(key);
+        clear_pruned(item, memory, path, exclude);
+        path.pop();
+    };
+
+    match &memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::Table { .. } => {
+            let mut table = NP_Table::new_iter(cursor, memory);
+            while let Some((_idx, col, item)) = table.step_iter(memory) {
+                match item {
+                    Some(item) if col.is_empty() => item.get_value_mut(memory).set_addr_value(0),
+                    Some(item) => visit(col.to_string(), &item),
+                    None => { }
+                }
+            }
+        },
+        NP_Parsed_Schema::Tuple { .. } => {
+            let mut tuple = NP_Tuple::new_iter(cursor, memory);
+            while let Some((idx, item)) = tuple.step_iter(memory) {
+                if let Some(item) = item { visit(idx.to_string(), &item) }
+            }
+        },
+        NP_Parsed_Schema::List { .. } => {
+            let mut list = NP_List::new_iter(cursor, memory, true, 0);
+            while let Some((idx, item)) = list.step_iter(memory) {
+                if let Some(item) = item { visit(idx.to_string(), &item) }
+            }
+        },
+        NP_Parsed_Schema::Log { .. } => {
+            let mut log = NP_Log::new_iter(cursor, memory);
+            while let Some((idx, item)) = log.step_iter(memory) {
+                visit(idx.to_string(), &item)
+            }
+        },
+        NP_Parsed_Schema::Map { .. } => {
+            let mut map = NP_Map::new_iter(cursor, memory);
+            while let Some((key, item)) = map.step_iter(memory) {
+                visit(key.to_string(), &item)
+            }
+        },
+        _ => { }
+    }
+}
 
 /// NP Item
 pub struct NP_Item<'item> {
     /// index of this value
     pub index: usize,
-    /// Key at this index
-    pub key: &'item str,
+    /// Key at this index, keys of hashed maps are written as `#` and 16 hex digits
+    pub key: Cow<'item, str>,
     /// Column at this index
-    pub col: &'item str,
+    pub col: Cow<'item, str>,
     /// Cursor value
     cursor: Option<NP_Cursor>,
     parent: NP_Cursor,
@@ -1133,23 +4003,41 @@ impl<'item> NP_Item<'item> {
         }
     }
 
+}
+
+/// Item from `get_iter_mut`, reads like `NP_Item` and can also change the value
+pub struct NP_Item_Mut<'item> {
+    item: NP_Item<'item>
+}
+
+impl<'item> Deref for NP_Item_Mut<'item> {
+    type Target = NP_Item<'item>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.item
+    }
+}
+
+impl<'item> NP_Item_Mut<'item> {
+
     /// Set value at this pointer
-    pub fn set<X>(&'item mut self, value: X) -> Result<(), NP_Error> where X: NP_Value<'item> + NP_Scalar {
-        if let Some(cursor) = self.cursor {
-            X::set_value(cursor.clone(), self.memory, value)?;
+    pub fn set<X>(&mut self, value: X) -> Result<(), NP_Error> where X: NP_Value<'item> + NP_Scalar {
+        let item = &self.item;
+        if let Some(cursor) = item.cursor {
+            X::set_value(cursor.clone(), item.memory, value)?;
         } else {
-            match self.memory.schema[self.parent.schema_addr] {
+            match item.memory.schema[item.parent.schema_addr] {
                 NP_Parsed_Schema::List { .. } => {
-                    let item = opt_err(opt_err(NP_List::select(self.parent.clone(), self.index, true, self.memory)?)?.1)?;
-                    X::set_value(item, self.memory, value)?;
+                    let new_item = opt_err(opt_err(NP_List::select(item.parent.clone(), item.index, true, item.memory)?)?.1)?;
+                    X::set_value(new_item, item.memory, value)?;
                 }
                 NP_Parsed_Schema::Table { .. } => {
-                    let item = opt_err(NP_Table::select(self.parent.clone(), self.key, true, self.memory)?)?;
-                    X::set_value(item, self.memory, value)?;
+                    let new_item = opt_err(NP_Table::select(item.parent, &item.key, true, item.memory)?)?;
+                    X::set_value(new_item, item.memory, value)?;
                 },
                 NP_Parsed_Schema::Tuple { .. } => {
-                    let item = opt_err(NP_Tuple::select(self.parent.clone(), self.index, true, self.memory)?)?;
-                    X::set_value(item, self.memory, value)?;
+                    let new_item = opt_err(NP_Tuple::select(item.parent.clone(), item.index, true, item.memory)?)?;
+                    X::set_value(new_item, item.memory, value)?;
                 }
                 _ => { }
             }
@@ -1159,9 +4047,9 @@ impl<'item> NP_Item<'item> {
     }
 
     /// Clear the value at this pointer
-    pub fn del(&'item mut self) -> bool {
-        if let Some(cursor) = self.cursor {
-            let value = cursor.get_value(self.memory);
+    pub fn del(&mut self) -> bool {
+        if let Some(cursor) = self.item.cursor {
+            let value = cursor.get_value_mut(self.item.memory);
             value.set_addr_value(0);
             true
         } else {
@@ -1183,7 +4071,9 @@ pub enum NP_Iterator_Collection<'col> {
     /// Table
     Table(NP_Table<'col>),
     /// Tuple
-    Tuple(NP_Tuple<'col>)
+    Tuple(NP_Tuple<'col>),
+    /// Log
+    Log(NP_Log)
 }
 
 #[allow(missing_docs)]
@@ -1206,7 +4096,11 @@ impl<'col> NP_Iterator_Collection<'col> {
                 let map = NP_Map::new_iter(&cursor, memory);
                 Ok(NP_Iterator_Collection::Map(map))
             },
-            _ => Err(NP_Error::new("Tried to create iterator on non collection item!"))
+            NP_Parsed_Schema::Log { .. } => {
+                let log = NP_Log::new_iter(&cursor, memory);
+                Ok(NP_Iterator_Collection::Log(log))
+            },
+            _ => Err(NP_Error::new_static("Tried to create iterator on non collection item!"))
         }
     }
 }
@@ -1240,28 +4134,35 @@ impl<'it> Iterator for NP_Generic_Iterator<'it> {
             NP_Iterator_Collection::Map(x) => {
                 if let Some(next_item) = x.step_iter(&self.memory) {
                     self.index += 1;
-                    Some(NP_Item { memory: self.memory, key: next_item.0, col: next_item.0, index: self.index - 1, cursor: Some(next_item.1), parent: self.root.clone() })
+                    Some(NP_Item { memory: self.memory, key: next_item.0.clone(), col: next_item.0, index: self.index - 1, cursor: Some(next_item.1), parent: self.root })
                 } else {
                     None
                 }
             },
             NP_Iterator_Collection::List(x) => {
                 if let Some(next_item) = x.step_iter(&self.memory) {
-                    Some(NP_Item { memory: self.memory, key: "", col: "", index: next_item.0, cursor: next_item.1, parent: self.root.clone() })
+                    Some(NP_Item { memory: self.memory, key: Cow::Borrowed(""), col: Cow::Borrowed(""), index: next_item.0, cursor: next_item.1, parent: self.root })
                 } else {
                     None
                 }
             },
             NP_Iterator_Collection::Table(x) => {
                 if let Some(next_item) = x.step_iter(&self.memory) {
-                    Some(NP_Item { memory: self.memory, key: next_item.1, col: next_item.1, index: next_item.0, cursor: next_item.2, parent: self.root.clone() })
+                    Some(NP_Item { memory: self.memory, key: Cow::Borrowed(next_item.1), col: Cow::Borrowed(next_item.1), index: next_item.0, cursor: next_item.2, parent: self.root })
                 } else {
                     None
                 }
             },
             NP_Iterator_Collection::Tuple(x) => {
                 if let Some(next_item) = x.step_iter(&self.memory) {
-                    Some(NP_Item { memory: self.memory, key: "", col: "", index: next_item.0, cursor: next_item.1, parent: self.root.clone() })
+                    Some(NP_Item { memory: self.memory, key: Cow::Borrowed(""), col: Cow::Borrowed(""), index: next_item.0, cursor: next_item.1, parent: self.root })
+                } else {
+                    None
+                }
+            },
+            NP_Iterator_Collection::Log(x) => {
+                if let Some(next_item) = x.step_iter(&self.memory) {
+                    Some(NP_Item { memory: self.memory, key: Cow::Borrowed(""), col: Cow::Borrowed(""), index: next_item.0, cursor: Some(next_item.1), parent: self.root })
                 } else {
                     None
                 }
@@ -1269,4 +4170,123 @@ impl<'it> Iterator for NP_Generic_Iterator<'it> {
             _ => { None }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Iterator from `get_iter_mut`
+pub struct NP_Generic_Iterator_Mut<'it> {
+    iter: NP_Generic_Iterator<'it>
+}
+
+impl<'it> Iterator for NP_Generic_Iterator_Mut<'it> {
+    type Item = NP_Item_Mut<'it>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|item| NP_Item_Mut { item })
+    }
+}
+
+// add the bytes used by the value at this cursor to the stats, returns the size of the value without its pointer
+// every node of the schema below `address` in depth first order
+fn schema_nodes(schema: &[NP_Parsed_Schema], address: usize, path: &mut Vec<String>, nodes: &mut Vec<NP_Node_Size>, index: &mut Vec<Option<usize>>) {
+
+    index[address] = Some(nodes.len());
+    nodes.push(NP_Node_Size { path: path.clone(), values: 0, bytes: 0 });
+
+    let children: Vec<(String, usize)> = match &schema[address] {
+        NP_Parsed_Schema::Table { columns, .. } => columns.iter().map(|(_, name, addr)| (name.clone(), *addr)).collect(),
+        NP_Parsed_Schema::Tuple { values, .. } => values.iter().enumerate().map(|(idx, addr)| (idx.to_string(), *addr)).collect(),
+        NP_Parsed_Schema::List { of, .. } | NP_Parsed_Schema::Log { of, .. } => alloc::vec![(String::from("*"), *of)],
+        NP_Parsed_Schema::Map { value, .. } => alloc::vec![(String::from("*"), *value)],
+        _ => Vec::new()
+    };
+
+    for (key, child) in children {
+        path.push(key);
+        schema_nodes(schema, child, path, nodes, index);
+        path.pop();
+    }
+}
+
+// add the size of every set value below `cursor` to its schema node
+fn measure_nodes(cursor: &NP_Cursor, memory: &NP_Memory, nodes: &mut Vec<NP_Node_Size>, index: &[Option<usize>]) -> Result<(), NP_Error> {
+
+    if cursor.get_value(memory).get_addr_value() == 0 {
+        return Ok(());
+    }
+
+    if let Some(node) = index[cursor.schema_addr].and_then(|idx| nodes.get_mut(idx)) {
+        memory.count_shared(true);
+        let size = NP_Cursor::calc_size(cursor, memory);
+        memory.count_shared(false);
+        node.values += 1;
+        node.bytes += size?;
+    }
+
+    let items: Vec<NP_Cursor> = match &memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::Table { .. } => compare::table_items(cursor, memory).into_iter().map(|x| x.1).collect(),
+        NP_Parsed_Schema::Tuple { .. } => compare::tuple_items(cursor, memory).into_iter().map(|x| x.1).collect(),
+        NP_Parsed_Schema::List  { .. } => compare::list_items(cursor, memory).into_iter().map(|x| x.1).collect(),
+        NP_Parsed_Schema::Log   { .. } => compare::log_items(cursor, memory).into_iter().map(|x| x.1).collect(),
+        NP_Parsed_Schema::Map   { .. } => compare::map_items(cursor, memory).into_iter().map(|x| x.1).collect(),
+        _ => Vec::new()
+    };
+
+    for item in items.iter() {
+        measure_nodes(item, memory, nodes, index)?;
+    }
+
+    Ok(())
+}
+
+fn measure(cursor: &NP_Cursor, memory: &NP_Memory, stats: &mut NP_Memory_Stats, seen: &mut Vec<usize>) -> Result<usize, NP_Error> {
+
+    let value = cursor.get_value(memory);
+    let addr = value.get_addr_value() as usize;
+
+    if addr == 0 {
+        return Ok(0);
+    }
+
+    let size = NP_Cursor::calc_size(cursor, memory)? - value.get_size();
+
+    let items: Option<Vec<NP_Cursor>> = match &memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::Table { .. } => Some(compare::table_items(cursor, memory).into_iter().map(|x| x.1).collect()),
+        NP_Parsed_Schema::Tuple { .. } => Some(compare::tuple_items(cursor, memory).into_iter().map(|x| x.1).collect()),
+        NP_Parsed_Schema::List  { .. } => Some(compare::list_items(cursor, memory).into_iter().map(|x| x.1).collect()),
+        NP_Parsed_Schema::Log   { .. } => Some(compare::log_items(cursor, memory).into_iter().map(|x| x.1).collect()),
+        NP_Parsed_Schema::Map   { .. } => Some(compare::map_items(cursor, memory).into_iter().map(|x| x.1).collect()),
+        _ => None
+    };
+
+    let type_key = *memory.schema[cursor.schema_addr].get_type_key();
+
+    let own = match items {
+        Some(items) => {
+            let mut children = 0usize;
+            for item in items.iter() {
+                children += measure(item, memory, stats, seen)?;
+            }
+            // everything that isn't a child value is structure
+            let own = size.saturating_sub(children);
+            stats.pointer_bytes += own;
+            own
+        },
+        None => {
+            // shared values point at the same address
+            if seen.contains(&addr) {
+                0
+            } else {
+                seen.push(addr);
+                stats.payload_bytes += size;
+                size
+            }
+        }
+    };
+
+    match stats.bytes_by_type.iter_mut().find(|(key, _)| *key == type_key) {
+        Some(x) => x.1 += own,
+        None => stats.bytes_by_type.push((type_key, own))
+    }
+
+    Ok(size)
+}
@@ -0,0 +1,223 @@
+//! Buffers shared between one writer and many readers
+//!
+//! An `NP_Cell` holds the latest version of a buffer.  Readers on any thread call `load` to get that version as an `NP_Buffer_Reader`, the reader never changes even if a new version is published while it's being used.  A writer calls `update` to change a copy of the latest version and publish it, or `publish` to replace it with a new buffer.
+//!
+//! Loading never locks, waits or copies the bytes, publishing swaps a pointer and waits only for readers that were in the middle of a `load` when it swapped to finish picking up the old version, readers that start loading after the swap are never waited for.  Only one writer can publish at a time, a second writer publishing at the same moment gets an error instead of waiting.
+//!
+//! Good for small records that are read all the time and changed now and then, like configuration.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::cell::NP_Cell;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["host",    {"type": "string"}],
+//!         ["retries", {"type": "u8", "default": 3}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut config = factory.empty_buffer(None);
+//! config.set(&["host"], "localhost")?;
+//!
+//! let cell = NP_Cell::new(config);
+//! let before = cell.load();
+//!
+//! cell.update(|writer| {
+//!     writer.set(&["retries"], 5u8)?;
+//!     Ok(())
+//! })?;
+//!
+//! // readers loaded before the update keep the old version
+//! assert_eq!(before.get::<u8>(&["retries"])?, Some(3));
+//! assert_eq!(cell.load().get::<u8>(&["retries"])?, Some(5));
+//! assert_eq!(cell.load().get::<&str>(&["host"])?, Some("localhost"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::{NP_Buffer, NP_Shared_Buffer};
+use crate::error::NP_Error;
+use crate::reader::{NP_Buffer_Reader, NP_Buffer_Writer};
+use alloc::sync::Arc;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+/// The latest version of a buffer, shared between one writer and many readers.
+///
+/// See the [cell](index.html) module for an example.
+pub struct NP_Cell<'buffer> {
+    // from `Arc::into_raw`, the cell owns one strong count of the latest version
+    current: AtomicPtr<NP_Shared_Buffer<'buffer>>,
+    // readers between loading the pointer and making their reader, counted in the slot of the epoch they started in
+    loading: [AtomicUsize; 2],
+    // one more every time a version is published, the writer only waits for readers that started before it
+    epoch: AtomicUsize,
+    writing: AtomicBool,
+    _owns: PhantomData<Arc<NP_Shared_Buffer<'buffer>>>
+}
+
+impl<'buffer> NP_Cell<'buffer> {
+
+    /// Make a cell holding `buffer` as the first version
+    pub fn new(buffer: NP_Buffer<'buffer>) -> Self {
+        NP_Cell {
+            current: AtomicPtr::new(Arc::into_raw(Arc::new(buffer.share())) as *mut _),
+            loading: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            epoch: AtomicUsize::new(0),
+            writing: AtomicBool::new(false),
+            _owns: PhantomData
+        }
+    }
+
+    /// Get a reader of the latest version, never waits
+    pub fn load(&self) -> NP_Buffer_Reader<'buffer> {
+        let loading = loop {
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            let loading = &self.loading[epoch % 2];
+            loading.fetch_add(1, Ordering::SeqCst);
+            // a version was published in between, count this reader in the new epoch instead
+            if self.epoch.load(Ordering::SeqCst) == epoch {
+                break loading;
+            }
+            loading.fetch_sub(1, Ordering::SeqCst);
+        };
+        let current = self.current.load(Ordering::SeqCst);
+        // the version can't be dropped while its epoch's slot is above zero, the reader holds its own count of the bytes
+        let reader = unsafe { (*current).reader() };
+        loading.fetch_sub(1, Ordering::SeqCst);
+        reader
+    }
+
+    /// Change a copy of the latest version and publish it, nothing is published if `change` returns an error.
+    pub fn update<F>(&self, change: F) -> Result<(), NP_Error> where F: FnOnce(&mut NP_Buffer_Writer<'buffer>) -> Result<(), NP_Error> {
+        let _writing = self.start_write()?;
+
+        let mut writer = self.load().into_writer();
+        let result = change(&mut writer);
+        if result.is_ok() {
+            self.swap(writer.into_buffer());
+        }
+
+        result
+    }
+
+    /// Replace the latest version with `buffer`
+    pub fn publish(&self, buffer: NP_Buffer<'buffer>) -> Result<(), NP_Error> {
+        let _writing = self.start_write()?;
+        self.swap(buffer);
+        Ok(())
+    }
+
+    // the writer lets go of the cell when the guard is dropped, even if `change` panics
+    fn start_write(&self) -> Result<NP_Write_Guard<'_>, NP_Error> {
+        match self.writing.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => Ok(NP_Write_Guard { writing: &self.writing }),
+            Err(_) => Err(NP_Error::new_static("Another writer is publishing to this cell!"))
+        }
+    }
+
+    fn swap(&self, version: NP_Buffer<'buffer>) {
+        let old = self.current.swap(Arc::into_raw(Arc::new(version.share())) as *mut _, Ordering::SeqCst);
+
+        // readers starting from here on count in the other slot and can only see the new version
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst);
+
+        // readers that may have seen the old pointer have to make their reader before the cell drops it
+        while self.loading[epoch % 2].load(Ordering::SeqCst) != 0 {
+            core::hint::spin_loop();
+        }
+
+        drop(unsafe { Arc::from_raw(old) });
+    }
+}
+
+struct NP_Write_Guard<'cell> {
+    writing: &'cell AtomicBool
+}
+
+impl<'cell> Drop for NP_Write_Guard<'cell> {
+    fn drop(&mut self) {
+        self.writing.store(false, Ordering::SeqCst);
+    }
+}
+
+impl<'buffer> Drop for NP_Cell<'buffer> {
+    fn drop(&mut self) {
+        drop(unsafe { Arc::from_raw(*self.current.get_mut()) });
+    }
+}
+
+#[test]
+fn cell_works() -> Result<(), NP_Error> {
+    fn shared<T: Sync + Send>(_value: &T) { }
+
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u32"}}"#)?;
+    let cell = NP_Cell::new(factory.empty_buffer(None));
+    shared(&cell);
+
+    let first = cell.load();
+    for i in 0..10u32 {
+        cell.update(|writer| {
+            writer.list_push(&[], i)?;
+            Ok(())
+        })?;
+    }
+    assert_eq!(first.length(&[])?, None);
+    assert_eq!(cell.load().length(&[])?, Some(10));
+
+    // failed updates and writers inside writers publish nothing
+    assert!(cell.update(|writer| {
+        writer.set(&["0"], 100u32)?;
+        Err(NP_Error::new_static("changed my mind"))
+    }).is_err());
+    assert!(cell.update(|_writer| cell.publish(factory.empty_buffer(None))).is_err());
+    assert_eq!(cell.load().get::<u32>(&["0"])?, Some(0));
+
+    let mut replacement = factory.empty_buffer(None);
+    replacement.set(&["3"], 7u32)?;
+    cell.publish(replacement)?;
+    assert_eq!(cell.load().json_encode(&[])?.stringify(), "[null,null,null,7]");
+    assert_eq!(first.length(&[])?, None);
+
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn cell_threads_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u32"}}"#)?;
+    let cell = NP_Cell::new(factory.empty_buffer(None));
+
+    // a writer that panics lets go of the cell
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        cell.update(|_writer| panic!("writer failed"))
+    }));
+    assert!(panicked.is_err());
+    cell.update(|writer| writer.list_push(&[], 1u32).map(|_| ()))?;
+
+    // readers loading all the time don't keep the writer from publishing
+    let done = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        for _ in 0..2 {
+            scope.spawn(|| {
+                let mut last = 0;
+                while !done.load(Ordering::SeqCst) {
+                    let length = cell.load().length(&[]).unwrap().unwrap_or(0);
+                    assert!(length >= last);
+                    last = length;
+                }
+            });
+        }
+        for i in 0..20u32 {
+            cell.update(|writer| writer.list_push(&[], i).map(|_| ())).unwrap();
+        }
+        done.store(true, Ordering::SeqCst);
+    });
+    assert_eq!(cell.load().length(&[])?, Some(21));
+
+    Ok(())
+}
@@ -1,12 +1,24 @@
 use crate::utils::opt_err;
+use crate::collection::table::NP_Table;
+use crate::collection::tuple::NP_Tuple;
 use crate::{pointer::{NP_List_Bytes}};
+use crate::compare;
 use crate::{error::NP_Error, json_flex::{JSMAP, NP_JSON}, memory::{NP_Memory}, pointer::{NP_Value}, pointer::{NP_Cursor}, schema::NP_Parsed_Schema, schema::{NP_Schema, NP_TypeKeys}};
 
 use alloc::borrow::ToOwned;
 use alloc::boxed::Box;
 use alloc::{vec::*};
 use core::{hint::unreachable_unchecked};
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
+
+// optional flags byte after the list type key
+const FLAGS_MARKER: u8 = 0x80;
+const COLUMNAR_BIT: u8 = 0x01;
+const SORTED_BIT: u8 = 0x02;
+// the sort key path follows the flags, a count byte then each key as a length byte and UTF8 bytes
+const KEY_BIT: u8 = 0x04;
+// items have a u32 index instead of a u8
+const WIDE_BIT: u8 = 0x08;
 
 #[doc(hidden)]
 #[derive(Debug, Clone, Copy)]
@@ -34,11 +46,29 @@ pub struct NP_List {
 #[allow(missing_docs)]
 impl NP_List {
 
+    /// Largest index items of this list can have
+    #[inline(always)]
+    pub fn max_index(list_schema: &NP_Parsed_Schema) -> usize {
+        match list_schema {
+            NP_Parsed_Schema::List { wide: true, .. } => u32::MAX as usize,
+            _ => 255
+        }
+    }
+
+    /// Empty item pointer for this list, wide lists have a four byte index
+    #[inline(always)]
+    fn new_item(list_schema: &NP_Parsed_Schema) -> &'static [u8] {
+        match list_schema {
+            NP_Parsed_Schema::List { wide: true, .. } => &[0u8; 8],
+            _ => &[0u8; 5]
+        }
+    }
+
     #[inline(always)]
     pub fn select(list_cursor: NP_Cursor, index: usize, make_path: bool, memory: &NP_Memory) -> Result<Option<(usize, Option<NP_Cursor>)>, NP_Error> {
-        let list_value = list_cursor.get_value(memory);
+        let mut list_value = list_cursor.get_value(memory);
 
-        if index > 255 { return Ok(None) }
+        if index > Self::max_index(&memory.schema[list_cursor.schema_addr]) { return Ok(None) }
 
         let schema_of = match memory.schema[list_cursor.schema_addr] {
             NP_Parsed_Schema::List { of, .. } => of,
@@ -49,19 +79,38 @@ impl NP_List {
         if list_value.get_addr_value() == 0 {
             if make_path {
                 Self::make_list(&list_cursor, memory)?;
+                list_value = list_cursor.get_value(memory);
             } else {
                 return Ok(Some((index, None)))
             }
         }
 
-        let list_data = Self::get_list(list_value.get_addr_value() as usize, memory);
+        let list_addr = list_value.get_addr_value() as usize;
+
+        // reads only follow items that exist, nothing is made
+        if !make_path {
+            let mut list_iter = Self::new_iter(&list_cursor, memory, true, 0);
+            while let Some((idx, item)) = Self::step_iter(&mut list_iter, memory) {
+                if idx == index {
+                    return Ok(Some((index, item)))
+                }
+                // indexes always increase, stop past the index
+                if idx > index {
+                    break;
+                }
+            }
+            return Ok(Some((index, None)))
+        }
+
+        let mut list_data = Self::get_list_mut(list_addr, memory);
 
         // empty list
         if list_data.get_head() == 0 {
-            let new_cursor_addr = memory.malloc_borrow(&[0u8; 5])?; // malloc list item
+            let new_cursor_addr = memory.malloc_borrow(Self::new_item(&memory.schema[list_cursor.schema_addr]))?; // malloc list item
+            list_data = Self::get_list_mut(list_addr, memory);
             let new_cursor = NP_Cursor::new(new_cursor_addr, schema_of, list_cursor.schema_addr);
-            let new_cursor_value = new_cursor.get_value(memory);
-            new_cursor_value.set_index(index as u8);
+            let new_cursor_value = new_cursor.get_value_mut(memory);
+            new_cursor_value.set_index(index as u32);
             list_data.set_head(new_cursor_addr as u16);
             list_data.set_tail(new_cursor_addr as u16);
             return Ok(Some((index, Some(new_cursor))))
@@ -73,10 +122,11 @@ impl NP_List {
         let head_index = head.get_value(memory).get_index() as usize;
 
         if head_index > index { // index is in front of head
-            let new_cursor_addr = memory.malloc_borrow(&[0u8; 5])?; // malloc list item
+            let new_cursor_addr = memory.malloc_borrow(Self::new_item(&memory.schema[list_cursor.schema_addr]))?; // malloc list item
+            list_data = Self::get_list_mut(list_addr, memory);
             let new_cursor = NP_Cursor::new(new_cursor_addr, schema_of, list_cursor.schema_addr);
-            let new_cursor_value = new_cursor.get_value(memory);
-            new_cursor_value.set_index(index as u8);
+            let new_cursor_value = new_cursor.get_value_mut(memory);
+            new_cursor_value.set_index(index as u32);
             new_cursor_value.set_next_addr(head.buff_addr as u16);
             list_data.set_head(new_cursor_addr as u16);
             return Ok(Some((index, Some(new_cursor))))
@@ -87,14 +137,15 @@ impl NP_List {
         // is cursor in behind of or equal to tail
         let tail = NP_Cursor::new(list_data.get_tail() as usize, schema_of, list_cursor.schema_addr);
 
-        let tail_value = tail.get_value(memory);
-        let tail_index = tail_value.get_index() as usize;
+        let tail_index = tail.get_value(memory).get_index() as usize;
 
         if tail_index < index { // index is in front of head
-            let new_cursor_addr = memory.malloc_borrow(&[0u8; 5])?; // malloc list item
+            let new_cursor_addr = memory.malloc_borrow(Self::new_item(&memory.schema[list_cursor.schema_addr]))?; // malloc list item
+            list_data = Self::get_list_mut(list_addr, memory);
+            let tail_value = tail.get_value_mut(memory);
             let new_cursor = NP_Cursor::new(new_cursor_addr, schema_of, list_cursor.schema_addr);
-            let new_cursor_value = new_cursor.get_value(memory);
-            new_cursor_value.set_index(index as u8);
+            let new_cursor_value = new_cursor.get_value_mut(memory);
+            new_cursor_value.set_index(index as u32);
             tail_value.set_next_addr(new_cursor_addr as u16);
             list_data.set_tail(new_cursor_addr as u16);
             return Ok(Some((index, Some(new_cursor))))
@@ -103,34 +154,42 @@ impl NP_List {
         }
 
 
-        // the index is somewhere in the list
-        let mut list_iter = Self::new_iter(&list_cursor, memory, false, head_index as usize);
+        // the index is somewhere in the list, only items that exist are visited so gaps between indexes cost nothing
+        let mut list_iter = Self::new_iter(&list_cursor, memory, true, head_index);
+        let mut previous = head;
 
         while let Some((idx, item)) = Self::step_iter(&mut list_iter, memory) {
-            if index == idx {
-                if let Some(found_cursor) = item { // found cursor here
-                    return Ok(Some((index, Some(found_cursor))))
-                } else { // found index but no cursor
-                    return Ok(Some((index, Some(list_iter.make_item_in_loop(memory)?))))
-                }
+            let item = opt_err(item)?;
+            if index == idx { // found cursor here
+                return Ok(Some((index, Some(item))))
+            }
+            if idx > index { // index is between the previous item and this one
+                let new_cursor_addr = memory.malloc_borrow(Self::new_item(&memory.schema[list_cursor.schema_addr]))?; // malloc list item
+                let new_cursor = NP_Cursor::new(new_cursor_addr, schema_of, list_cursor.schema_addr);
+                let new_cursor_value = new_cursor.get_value_mut(memory);
+                new_cursor_value.set_index(index as u32);
+                new_cursor_value.set_next_addr(item.buff_addr as u16);
+                previous.get_value_mut(memory).set_next_addr(new_cursor_addr as u16);
+                return Ok(Some((index, Some(new_cursor))))
             }
+            previous = item;
         }
 
         // should never reach here
-        Err(NP_Error::new("unreachable"))
+        Err(NP_Error::new_static("unreachable"))
 
     }
 
     #[inline(always)]
     pub fn make_item_in_loop(self, memory: &NP_Memory) -> Result<NP_Cursor, NP_Error> {
         
-        let list_value = self.list.get_value(memory);
-        let list_data = Self::get_list(list_value.get_addr_value() as usize, memory);
+        let new_cursor_addr = memory.malloc_borrow(Self::new_item(&memory.schema[self.list.schema_addr]))?; // malloc list item
 
-        let new_cursor_addr = memory.malloc_borrow(&[0u8; 5])?; // malloc list item
+        let list_value = self.list.get_value(memory);
+        let list_data = Self::get_list_mut(list_value.get_addr_value() as usize, memory);
         let new_cursor = NP_Cursor::new(new_cursor_addr, self.schema_of, self.list.schema_addr);
-        let new_cursor_value = new_cursor.get_value(memory);
-        new_cursor_value.set_index(self.index as u8);
+        let new_cursor_value = new_cursor.get_value_mut(memory);
+        new_cursor_value.set_index(self.index as u32);
 
 
         if let Some(current) = self.current {
@@ -138,7 +197,7 @@ impl NP_List {
         
                 // set NEXT of previous to new cursor
                 let prev_cursor = NP_Cursor::new(prev.buff_addr, self.schema_of, self.list.schema_addr);
-                let prev_cursor_value = prev_cursor.get_value(memory);
+                let prev_cursor_value = prev_cursor.get_value_mut(memory);
                 prev_cursor_value.set_next_addr(new_cursor_addr as u16);
 
                 // set NEXT of this cursor to CURRENT
@@ -150,24 +209,53 @@ impl NP_List {
 
             Ok(new_cursor)
         } else {
-            Err(NP_Error::new("unreachable"))
+            Err(NP_Error::new_static("unreachable"))
+        }
+    }
+
+    /// One past the index of the last item, zero for empty lists
+    pub fn length(list_cursor: &NP_Cursor, memory: &NP_Memory) -> usize {
+        let list_addr = list_cursor.get_value(memory).get_addr_value() as usize;
+        if list_addr == 0 {
+            return 0;
         }
+
+        let tail_addr = Self::get_list(list_addr, memory).get_tail() as usize;
+        if tail_addr == 0 {
+            return 0;
+        }
+
+        let schema_of = match memory.schema[list_cursor.schema_addr] {
+            NP_Parsed_Schema::List { of, .. } => of,
+            _ => 0
+        };
+
+        NP_Cursor::new(tail_addr, schema_of, list_cursor.schema_addr).get_value(memory).get_index() as usize + 1
     }
 
     #[inline(always)]
     pub fn make_list<'make>(list_cursor: &NP_Cursor, memory: &'make NP_Memory) -> Result<(), NP_Error> {
         let list_addr = memory.malloc_borrow(&[0u8; 4])?; // head & tail
-        let value = list_cursor.get_value(memory);
+        let value = list_cursor.get_value_mut(memory);
         value.set_addr_value(list_addr as u16);
         Ok(())
     }
 
     #[inline(always)]
-    pub fn get_list<'list>(list_cursor_value_addr: usize, memory: &'list NP_Memory<'list>) -> &'list mut NP_List_Bytes {
-        if list_cursor_value_addr > memory.read_bytes().len() { // attack
-            unsafe { &mut *(memory.write_bytes().as_ptr() as *mut NP_List_Bytes) }
+    pub fn get_list<'list>(list_cursor_value_addr: usize, memory: &'list NP_Memory<'list>) -> &'list NP_List_Bytes {
+        if list_cursor_value_addr + 4 > memory.read_bytes().len() { // attack
+            unsafe { &*(memory.read_ptr() as *const NP_List_Bytes) }
+        } else { // normal operation
+            unsafe { &*(memory.read_ptr().add(list_cursor_value_addr) as *const NP_List_Bytes) }
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_list_mut<'list>(list_cursor_value_addr: usize, memory: &'list NP_Memory<'list>) -> &'list mut NP_List_Bytes {
+        if list_cursor_value_addr + 4 > memory.read_bytes().len() { // attack
+            unsafe { &mut *(memory.bytes_ptr() as *mut NP_List_Bytes) }
         } else { // normal operation
-            unsafe { &mut *(memory.write_bytes().as_ptr().add(list_cursor_value_addr as usize) as *mut NP_List_Bytes) }
+            unsafe { &mut *(memory.bytes_ptr().add(list_cursor_value_addr) as *mut NP_List_Bytes) }
         }
     }
 
@@ -183,11 +271,9 @@ impl NP_List {
             _ => 0
         };
 
-        let memory_bytes = memory.write_bytes();
+        if list_addr > 0 && list_addr + 4 <= memory.read_bytes().len() {
 
-        if list_addr > 0 && list_addr < (memory_bytes.len() + 4) {
-
-            let bytes = unsafe { &mut *(memory_bytes.as_ptr().add(list_addr) as *mut NP_List_Bytes) };
+            let bytes = Self::get_list(list_addr, memory);
 
             let tail_addr = bytes.get_tail() as usize;
 
@@ -229,34 +315,56 @@ impl NP_List {
 
                 match self.current {
                     Some(current) => { // subsequent iterations
-                        let current_cursor = NP_Cursor::new(current.buff_addr, self.schema_of, self.list.schema_addr);
-                        let value = current_cursor.get_value(memory);
-                        let next_addr = value.get_next_addr() as usize;
+                        if self.only_real {
+                            let current_cursor = NP_Cursor::new(current.buff_addr, self.schema_of, self.list.schema_addr);
+                            let next_addr = current_cursor.get_value(memory).get_next_addr() as usize;
 
-                        if next_addr == 0 {
-                            return None;
-                        }
+                            if next_addr == 0 {
+                                return None;
+                            }
 
-                        if self.only_real {    
                             let next_cursor = NP_Cursor::new(next_addr, self.schema_of, self.list.schema_addr);
                             let next_index = next_cursor.get_value(memory).get_index();
+
+                            // indexes must always increase, a corrupted next address can't loop back
+                            if next_index as usize <= current.index {
+                                return None;
+                            }
+
                             self.index = next_index as usize;
                             self.previous = self.current.clone();
                             self.current = Some(List_Item { buff_addr: next_addr, index: next_index as usize});
                             Some((next_index as usize, Some(next_cursor)))
                         } else {
-                            if current.index > self.index {
-                                self.index += 1;
-                                Some((self.index - 1, None))
-                            } else if current.index == self.index {
-                                let next_cursor = NP_Cursor::new(next_addr, self.schema_of, self.list.schema_addr);
-                                let next_index = next_cursor.get_value(memory).get_index();
-                                self.index += 1;
+                            let mut current = current;
+
+                            // current item was returned last step, move to the next one
+                            if current.index == self.index {
+                                let current_cursor = NP_Cursor::new(current.buff_addr, self.schema_of, self.list.schema_addr);
+                                let next_addr = current_cursor.get_value(memory).get_next_addr() as usize;
+
+                                if next_addr == 0 {
+                                    return None;
+                                }
+
+                                let next_index = NP_Cursor::new(next_addr, self.schema_of, self.list.schema_addr).get_value(memory).get_index() as usize;
+
+                                // indexes must always increase
+                                if next_index <= current.index {
+                                    return None;
+                                }
+
                                 self.previous = self.current.clone();
-                                self.current = Some(List_Item { buff_addr: next_addr, index: next_index as usize});
-                                Some((self.index - 1, Some(NP_Cursor::new(current.buff_addr, self.schema_of, self.list.schema_addr))))
+                                current = List_Item { buff_addr: next_addr, index: next_index };
+                                self.current = Some(current);
+                            }
+
+                            self.index += 1;
+
+                            if current.index == self.index {
+                                Some((self.index, Some(NP_Cursor::new(current.buff_addr, self.schema_of, self.list.schema_addr))))
                             } else {
-                                None
+                                Some((self.index, None))
                             }
                         }
                     },
@@ -277,11 +385,9 @@ impl NP_List {
     }
 
     #[inline(always)]
-    pub fn push<'push>(list_cursor: &NP_Cursor, memory: &NP_Memory, index: Option<usize>) -> Result<Option<(u16, NP_Cursor)>, NP_Error> {
+    pub fn push<'push>(list_cursor: &NP_Cursor, memory: &NP_Memory, index: Option<usize>) -> Result<Option<(usize, NP_Cursor)>, NP_Error> {
 
-        let list_value = list_cursor.get_value(memory);
-
-        if list_value.get_addr_value() == 0 {
+        if list_cursor.get_value(memory).get_addr_value() == 0 {
             Self::make_list(&list_cursor, memory)?;
         }
 
@@ -289,40 +395,42 @@ impl NP_List {
             NP_Parsed_Schema::List {  of, .. } => {
 
                 let mut new_index: usize = index.unwrap_or(0);
+                let max_index = Self::max_index(&memory.schema[list_cursor.schema_addr]);
 
-                let new_item_addr = memory.malloc_borrow(&[0u8; 5])?; // list item
+                let new_item_addr = memory.malloc_borrow(Self::new_item(&memory.schema[list_cursor.schema_addr]))?; // list item
 
-                let list_data = Self::get_list(list_value.get_addr_value() as usize, memory);
+                let list_value = list_cursor.get_value(memory);
+                let list_data = Self::get_list_mut(list_value.get_addr_value() as usize, memory);
 
                 let new_cursor = NP_Cursor::new(new_item_addr, of, list_cursor.schema_addr);
-                let new_cursor_value = new_cursor.get_value(memory);
+                let new_cursor_value = new_cursor.get_value_mut(memory);
                 
 
                 if list_data.get_head() == 0 { // empty list
                     list_data.set_head(new_item_addr as u16);
                     list_data.set_tail(new_item_addr as u16);
-                    if new_index > 255 {
-                        return Err(NP_Error::new("Index cannot be greater than 255!"))
+                    if new_index > max_index {
+                        return Err(NP_Error::new(alloc::format!("Index cannot be greater than {}!", max_index)))
                     }
-                    new_cursor_value.set_index(new_index as u8)
+                    new_cursor_value.set_index(new_index as u32)
                 } else { // list has items
                     let old_tail = NP_Cursor::new(list_data.get_tail() as usize, of, list_cursor.schema_addr);
-                    let old_tail_value = old_tail.get_value(memory);
-                    old_tail_value.set_next_addr(new_item_addr as u16);
+                    let old_tail_value = old_tail.get_value_mut(memory);
                     new_index = if let Some(idx) = index {
                         idx as usize
                     } else {
-                        (old_tail_value.get_index() + 1) as usize
+                        old_tail_value.get_index() as usize + 1
                     };
-                    if new_index > 255 {
-                        return Err(NP_Error::new("Index cannot be greater than 255!"))
+                    if new_index > max_index {
+                        return Err(NP_Error::new(alloc::format!("Index cannot be greater than {}!", max_index)))
                     }
-                    new_cursor_value.set_index(new_index as u8);
+                    old_tail_value.set_next_addr(new_item_addr as u16);
+                    new_cursor_value.set_index(new_index as u32);
                     list_data.set_tail(new_item_addr as u16);
                 }
 
 
-                return Ok(Some((new_index as u16, new_cursor)));
+                return Ok(Some((new_index, new_cursor)));
              
             },
             _ => Ok(None)
@@ -330,74 +438,326 @@ impl NP_List {
     }
 }
 
-impl<'value> NP_Value<'value> for NP_List {
+impl NP_List {
 
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("list", NP_TypeKeys::List) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("list", NP_TypeKeys::List) }
+    /// Put the items of a sorted list back in order after they were set by index, other lists are left alone.
+    pub fn restore_order(list_cursor: &NP_Cursor, memory: &NP_Memory) -> Result<(), NP_Error> {
+        match &memory.schema[list_cursor.schema_addr] {
+            NP_Parsed_Schema::List { sorted: true, .. } => { },
+            _ => return Ok(())
+        }
 
-    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
-        let mut schema_json = JSMAP::new();
-        schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
+        let mut items = Self::sorted_items(list_cursor, memory);
+        // stable, items with the same bytes keep their order
+        items.sort_by(|a, b| Self::sort_bytes(a, memory).cmp(&Self::sort_bytes(b, memory)));
+        Self::relink(list_cursor, &items, memory)
+    }
 
+    /// Items of a list that have a value, in list order.
+    pub fn sorted_items(list_cursor: &NP_Cursor, memory: &NP_Memory) -> Vec<NP_Cursor> {
+        compare::list_items(list_cursor, memory).into_iter().map(|x| x.1).filter(|item| item.get_value(memory).get_addr_value() != 0).collect()
+    }
 
-        let list_of = match &schema[address] {
-            NP_Parsed_Schema::List { i: _, sortable: _, of} => { *of },
-            _ => 0
-        };
+    /// Position of the first item in a sorted list with bytes after `bytes`, or the first with the same bytes if `inclusive` is false.
+    /// 
+    /// Binary search over the items, `items` must be in order.
+    pub fn sorted_position(items: &[NP_Cursor], bytes: Option<&[u8]>, inclusive: bool, memory: &NP_Memory) -> usize {
+        items.partition_point(|item| {
+            let item_bytes = Self::sort_bytes(item, memory);
+            if inclusive { item_bytes <= bytes } else { item_bytes < bytes }
+        })
+    }
 
-        schema_json.insert("of".to_owned(), NP_Schema::_type_to_json(schema, list_of)?);
+    /// Add a new item to a sorted list after any items with the same bytes, the value must already be set on `new_item` and it must be the tail of the list (just pushed).  Returns the index of the new item.
+    /// 
+    /// The position is found with a binary search over the items, then the new item is moved there by changing the links around it and the indexes of the items after it.  Lists with deleted items or gaps in their indexes are relinked in full once, which numbers them from zero again.
+    pub fn sorted_insert(list_cursor: &NP_Cursor, new_item: NP_Cursor, memory: &NP_Memory) -> Result<usize, NP_Error> {
+        let mut items: Vec<NP_Cursor> = compare::list_items(list_cursor, memory).into_iter().map(|x| x.1).collect();
 
-        Ok(NP_JSON::Dictionary(schema_json))
-    }
+        let in_place = items.last().map(|item| item.buff_addr) == Some(new_item.buff_addr) && items.iter().enumerate().all(|(idx, item)| {
+            let item_value = item.get_value(memory);
+            item_value.get_index() as usize == idx && item_value.get_addr_value() != 0
+        });
 
-    fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
+        if !in_place {
+            items.retain(|item| item.buff_addr != new_item.buff_addr && item.get_value(memory).get_addr_value() != 0);
+            let position = Self::sorted_position(&items, Self::sort_bytes(&new_item, memory), true, memory);
+            items.insert(position, new_item);
+            Self::relink(list_cursor, &items, memory)?;
+            return Ok(position);
+        }
 
-        let c_value = cursor.get_value(memory);
+        items.pop();
+        let position = Self::sorted_position(&items, Self::sort_bytes(&new_item, memory), true, memory);
+        if position == items.len() { // already at the end
+            return Ok(position);
+        }
 
-        if c_value.get_addr_value() == 0 {
-            return Ok(0) 
+        let list_data = Self::get_list_mut(list_cursor.get_value_mut(memory).get_addr_value() as usize, memory);
+
+        // take the new item off the tail
+        if let Some(old_tail) = items.last() {
+            old_tail.get_value_mut(memory).set_next_addr(0);
+            list_data.set_tail(old_tail.buff_addr as u16);
         }
 
-        // head + tail
-        let base_size = 4usize;
+        // and link it in front of the item at `position`
+        let new_value = new_item.get_value_mut(memory);
+        new_value.set_next_addr(items[position].buff_addr as u16);
+        new_value.set_index(position as u32);
+        match position {
+            0 => list_data.set_head(new_item.buff_addr as u16),
+            _ => items[position - 1].get_value_mut(memory).set_next_addr(new_item.buff_addr as u16)
+        }
 
-        let mut acc_size = 0usize;
+        for item in items[position..].iter() {
+            let item_value = item.get_value_mut(memory);
+            item_value.set_index(item_value.get_index() + 1);
+        }
 
-        let mut list_iter = Self::new_iter(&cursor, memory, true, 0);
+        Ok(position)
+    }
 
+    /// First item of a list that has a value, the smallest item of a sorted list.  Only the items in front of it are read.
+    pub fn first_item(list_cursor: &NP_Cursor, memory: &NP_Memory) -> Option<NP_Cursor> {
+        let mut list_iter = Self::new_iter(list_cursor, memory, true, 0);
         while let Some((_index, item)) = Self::step_iter(&mut list_iter, memory) {
-            if let Some(item_cursor) = &item {
-                acc_size += NP_Cursor::calc_size(item_cursor, memory)?;
+            match item {
+                Some(item) if item.get_value(memory).get_addr_value() != 0 => return Some(item),
+                _ => { }
             }
         }
+        None
+    }
 
-        Ok(acc_size + base_size)
+    /// Drop `item` and every item in front of it from the list, the items after it are numbered from zero again.
+    pub fn drop_through(list_cursor: &NP_Cursor, item: &NP_Cursor, memory: &NP_Memory) -> Result<(), NP_Error> {
+        let list_addr = list_cursor.get_value(memory).get_addr_value() as usize;
+        if list_addr == 0 {
+            return Ok(());
+        }
+
+        let item_value = item.get_value(memory);
+        let shift = item_value.get_index() + 1;
+        let next_addr = item_value.get_next_addr();
+
+        let list_data = Self::get_list_mut(list_addr, memory);
+        list_data.set_head(next_addr);
+        if next_addr == 0 {
+            list_data.set_tail(0);
+            return Ok(());
+        }
+
+        let mut list_iter = Self::new_iter(list_cursor, memory, true, 0);
+        let mut rest: Vec<NP_Cursor> = Vec::new();
+        while let Some((_index, item)) = Self::step_iter(&mut list_iter, memory) {
+            if let Some(item) = item {
+                rest.push(item);
+            }
+        }
+        for item in rest.iter() {
+            let item_value = item.get_value_mut(memory);
+            item_value.set_index(item_value.get_index() - shift);
+        }
+
+        Ok(())
     }
-    
-    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+
+    /// The value a sorted list orders this item by, the value at the list's `key` path inside the item.
+    pub fn sort_cursor(item: &NP_Cursor, memory: &NP_Memory) -> Option<NP_Cursor> {
+        let mut cursor = *item;
+        if let NP_Parsed_Schema::List { key, .. } = &memory.schema[item.parent_schema_addr] {
+            for step in key.iter() {
+                cursor = match &memory.schema[cursor.schema_addr] {
+                    NP_Parsed_Schema::Table { .. } => NP_Table::select(cursor, step, false, memory).ok()??,
+                    NP_Parsed_Schema::Tuple { .. } => NP_Tuple::select(cursor, step.parse::<usize>().ok()?, false, memory).ok()??,
+                    _ => return None
+                };
+            }
+        }
+        Some(cursor)
+    }
+
+    /// The bytes a sorted list orders this item by, items without a value at the sort key come first.
+    pub fn sort_bytes<'bytes>(item: &NP_Cursor, memory: &'bytes NP_Memory) -> Option<&'bytes [u8]> {
+        compare::scalar_bytes(&Self::sort_cursor(item, memory)?, memory)
+    }
+
+    /// Schema address of the value at `key` inside items with the schema at `of`, only tables and tuples can be stepped into.
+    pub fn key_schema(schema: &[NP_Parsed_Schema], of: usize, key: &[String]) -> Option<usize> {
+        let mut address = of;
+        for step in key.iter() {
+            address = match &schema[address] {
+                NP_Parsed_Schema::Table { columns, columns_mapped, .. } => columns[*columns_mapped.get(step)?].2,
+                NP_Parsed_Schema::Tuple { values, .. } => *values.get(step.parse::<usize>().ok()?)?,
+                _ => return None
+            };
+        }
+        Some(address)
+    }
+
+    /// Link the items in this order and number them from zero, items left out are dropped from the list.
+    pub fn relink(list_cursor: &NP_Cursor, items: &[NP_Cursor], memory: &NP_Memory) -> Result<(), NP_Error> {
+        let max_index = Self::max_index(&memory.schema[list_cursor.schema_addr]);
+        if items.len() > max_index + 1 {
+            return Err(NP_Error::new(alloc::format!("Index cannot be greater than {}!", max_index)));
+        }
+
+        let list_addr = list_cursor.get_value(memory).get_addr_value() as usize;
+        if list_addr == 0 {
+            return Ok(());
+        }
+
+        for (idx, item) in items.iter().enumerate() {
+            let item_value = item.get_value_mut(memory);
+            item_value.set_index(idx as u32);
+            item_value.set_next_addr(items.get(idx + 1).map(|next| next.buff_addr).unwrap_or(0) as u16);
+        }
+
+        let list_data = Self::get_list_mut(list_addr, memory);
+        list_data.set_head(items.first().map(|item| item.buff_addr).unwrap_or(0) as u16);
+        list_data.set_tail(items.last().map(|item| item.buff_addr).unwrap_or(0) as u16);
+
+        Ok(())
+    }
+
+    /// Copy the items at `at` and past it into the list at `to_cursor`, numbered from zero, then drop them from this list.  Items before `at` keep their indexes.
+    pub fn split_off(list_cursor: &NP_Cursor, at: usize, memory: &NP_Memory, to_cursor: &NP_Cursor, to_memory: &NP_Memory) -> Result<(), NP_Error> {
+        let list_addr = list_cursor.get_value(memory).get_addr_value() as usize;
+        if list_addr == 0 {
+            return Ok(());
+        }
+
+        Self::make_list(to_cursor, to_memory)?;
+
+        let mut last_kept: Option<NP_Cursor> = None;
+        let mut list_iter = Self::new_iter(list_cursor, memory, true, 0);
+
+        while let Some((index, item)) = Self::step_iter(&mut list_iter, memory) {
+            if let Some(old_item) = item {
+                if index < at {
+                    last_kept = Some(old_item);
+                } else if old_item.get_value(memory).get_addr_value() != 0 {
+                    let (_new_index, new_item) = opt_err(NP_List::push(to_cursor, to_memory, Some(index - at))?)?;
+                    NP_Cursor::compact(old_item, memory, new_item, to_memory)?;
+                }
+            }
+        }
+
+        let list_data = Self::get_list_mut(list_addr, memory);
+        match last_kept {
+            Some(item) => {
+                item.get_value_mut(memory).set_next_addr(0);
+                list_data.set_tail(item.buff_addr as u16);
+            },
+            None => {
+                list_data.set_head(0);
+                list_data.set_tail(0);
+            }
+        }
+
+        Ok(())
+    }
+
+    // canonical copies leave out items without a value, except the tail which sets the length of the list
+    fn skip_empty(list_cursor: &NP_Cursor, item: &NP_Cursor, from_memory: &NP_Memory, to_memory: &NP_Memory) -> bool {
+        if !to_memory.is_canonical() || item.get_value(from_memory).get_addr_value() != 0 {
+            return false;
+        }
+        let list_data = Self::get_list(list_cursor.get_value(from_memory).get_addr_value() as usize, from_memory);
+        list_data.get_tail() as usize != item.buff_addr
+    }
+
+    // copy a list of tables one column at a time, the structure of every row is made first so values of the same column end up next to each other
+    fn compact_columns<'value>(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory, pending: &mut Vec<(NP_Cursor, NP_Cursor)>) -> Result<NP_Cursor, NP_Error> {
+
+        let column_count = match &from_memory.schema[from_cursor.schema_addr] {
+            NP_Parsed_Schema::List { of, .. } => match &from_memory.schema[*of] {
+                NP_Parsed_Schema::Table { columns, .. } => columns.len(),
+                _ => 0
+            },
+            _ => 0
+        };
+
+        // (old column values, new vtable addresses, table schema) for each row
+        let mut rows: Vec<(Vec<Option<NP_Cursor>>, Vec<usize>, usize)> = Vec::new();
+
+        let mut list_iter = Self::new_iter(&from_cursor, from_memory, true, 0);
+
+        while let Some((index, item)) = Self::step_iter(&mut list_iter, from_memory) {
+            if let Some(old_item) = &item {
+                if Self::skip_empty(&from_cursor, old_item, from_memory, to_memory) {
+                    continue;
+                }
+                let (_new_index, new_item) = opt_err(NP_List::push(&to_cursor, to_memory, Some(index))?)?;
+
+                if old_item.get_value(from_memory).get_addr_value() == 0 {
+                    continue;
+                }
+
+                let mut old_columns: Vec<Option<NP_Cursor>> = Vec::with_capacity(column_count);
+                old_columns.resize(column_count, None);
+
+                let mut table = NP_Table::new_iter(old_item, from_memory);
+                while let Some((idx, _key, col)) = table.step_iter(from_memory) {
+                    if idx < column_count {
+                        old_columns[idx] = col.filter(|x| !to_memory.is_canonical() || x.get_value(from_memory).get_addr_value() != 0);
+                    }
+                }
+
+                let last_column = old_columns.iter().rposition(|x| x.is_some()).unwrap_or(0);
+
+                NP_Table::make_first_vtable(new_item, to_memory)?;
+                let mut vtables = Vec::with_capacity(last_column / 4 + 1);
+                vtables.push(new_item.get_value(to_memory).get_addr_value() as usize);
+                while vtables.len() <= last_column / 4 {
+                    let next = NP_Table::make_next_vtable(vtables[vtables.len() - 1], to_memory)?;
+                    vtables.push(next);
+                }
+
+                rows.push((old_columns, vtables, new_item.schema_addr));
+            }
+        }
+
+        for idx in 0..column_count {
+            for (old_columns, vtables, table_schema) in rows.iter() {
+                if let Some(old_col) = &old_columns[idx] {
+                    let item_addr = vtables[idx / 4] + ((idx % 4) * 2);
+                    NP_Cursor::compact_queue(*old_col, from_memory, NP_Cursor::new(item_addr, old_col.schema_addr, *table_schema), to_memory, pending)?;
+                }
+            }
+        }
+
+        Ok(to_cursor)
+    }
+
+    /// Bytes used by the items of this list, items that are collections with data are added to `pending` instead of measured.
+    pub fn size_items(cursor: &NP_Cursor, memory: &NP_Memory, pending: &mut Vec<NP_Cursor>) -> Result<usize, NP_Error> {
 
         let c_value = cursor.get_value(memory);
 
         if c_value.get_addr_value() == 0 {
-            return NP_JSON::Null
+            return Ok(0) 
         }
 
-        let mut json_list = Vec::new();
+        // head + tail
+        let base_size = 4usize;
 
-        let mut list_iter = Self::new_iter(&cursor, memory, false, 0);
+        let mut acc_size = 0usize;
 
-        while let Some((_index, item)) = Self::step_iter(&mut list_iter, memory) {
-             if let Some(item_cursor) = &item {
-                json_list.push(NP_Cursor::json_encode(item_cursor, memory));   
-            } else {
-                json_list.push(NP_JSON::Null);   
-            }    
+        let mut list_iter = NP_List::new_iter(cursor, memory, true, 0);
+
+        while let Some((_index, item)) = NP_List::step_iter(&mut list_iter, memory) {
+            if let Some(item_cursor) = &item {
+                acc_size += NP_Cursor::size_queue(item_cursor, memory, pending)?;
+            }
         }
 
-        NP_JSON::Array(json_list)
+        Ok(acc_size + base_size)
     }
 
-    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
+    /// Copy this list into `to_memory`, the items are added to `pending` and copied once this list is done.
+    pub fn compact_items(from_cursor: NP_Cursor, from_memory: &NP_Memory, to_cursor: NP_Cursor, to_memory: &NP_Memory, pending: &mut Vec<(NP_Cursor, NP_Cursor)>) -> Result<NP_Cursor, NP_Error> {
 
         let from_value = from_cursor.get_value(from_memory);
 
@@ -405,41 +765,172 @@ impl<'value> NP_Value<'value> for NP_List {
             return Ok(to_cursor) 
         }
 
-        Self::make_list(&to_cursor, to_memory)?;
+        NP_List::make_list(&to_cursor, to_memory)?;
 
-        let mut list_iter = Self::new_iter(&from_cursor, from_memory, true, 0);
+        if let NP_Parsed_Schema::List { columnar: true, .. } = &from_memory.schema[from_cursor.schema_addr] {
+            return NP_List::compact_columns(from_cursor, from_memory, to_cursor, to_memory, pending);
+        }
 
-        while let Some((index, item)) = Self::step_iter(&mut list_iter, from_memory) {
+        let mut list_iter = NP_List::new_iter(&from_cursor, from_memory, true, 0);
+
+        while let Some((index, item)) = NP_List::step_iter(&mut list_iter, from_memory) {
             if let Some(old_item) = &item {
+                if NP_List::skip_empty(&from_cursor, old_item, from_memory, to_memory) {
+                    continue;
+                }
                 let (_new_index, new_item) = opt_err(NP_List::push(&to_cursor, to_memory, Some(index))?)?;
-                NP_Cursor::compact(old_item.clone(), from_memory, new_item, to_memory)?;
+                NP_Cursor::compact_queue(*old_item, from_memory, new_item, to_memory, pending)?;
             }       
         }
 
         Ok(to_cursor)
     }
 
+}
+
+impl<'value> NP_Value<'value> for NP_List {
+
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("list", NP_TypeKeys::List) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("list", NP_TypeKeys::List) }
+
+    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
+        let mut schema_json = JSMAP::new();
+        match &schema[address] {
+            NP_Parsed_Schema::List { sorted: true, .. } => schema_json.insert("type".to_owned(), NP_JSON::String("sorted_list".to_owned())),
+            _ => schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()))
+        };
+
+        let list_of = match &schema[address] {
+            NP_Parsed_Schema::List { of, .. } => { *of },
+            _ => 0
+        };
+
+        if let NP_Parsed_Schema::List { columnar: true, .. } = &schema[address] {
+            schema_json.insert("layout".to_owned(), NP_JSON::String("columnar".to_owned()));
+        }
+
+        if let NP_Parsed_Schema::List { wide: true, .. } = &schema[address] {
+            schema_json.insert("wide".to_owned(), NP_JSON::True);
+        }
+
+        if let NP_Parsed_Schema::List { key, .. } = &schema[address] {
+            if !key.is_empty() {
+                schema_json.insert("key".to_owned(), NP_JSON::Array(key.iter().map(|step| NP_JSON::String(step.clone())).collect()));
+            }
+        }
+
+        schema_json.insert("of".to_owned(), NP_Schema::_type_to_json(schema, list_of)?);
+
+        Ok(NP_JSON::Dictionary(schema_json))
+    }
+
+    fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
+        // items are measured without recursion, see `NP_Cursor::calc_size`
+        Ok(NP_Cursor::calc_size(cursor, memory)? - cursor.get_value(memory).get_size())
+    }
+    
+    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+        // collections are encoded without recursion, see `NP_Cursor::json_encode`
+        NP_Cursor::json_encode(cursor, memory)
+    }
+
+    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
+        // items are copied without recursion, see `NP_Cursor::compact`
+        NP_Cursor::compact(from_cursor, from_memory, to_cursor, to_memory)
+    }
+
     fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
 
         let mut schema_bytes: Vec<u8> = Vec::new();
         schema_bytes.push(NP_TypeKeys::List as u8);
 
+        let columnar = match &json_schema["layout"] {
+            NP_JSON::String(layout) if layout == "columnar" => true,
+            NP_JSON::String(layout) if layout == "rows" => false,
+            NP_JSON::Null => false,
+            _ => return Err(NP_Error::new_schema("List layout must be \"rows\" or \"columnar\"!"))
+        };
+
+        if columnar {
+            match &json_schema["of"]["type"] {
+                NP_JSON::String(x) if x == "table" => { },
+                _ => return Err(NP_Error::new_schema("Only lists of tables can have a columnar layout!"))
+            }
+        }
+
+        let sorted = match &json_schema["type"] {
+            NP_JSON::String(x) => x == "sorted_list" || x == "priority_queue",
+            _ => false
+        };
+
+        let mut key: Vec<String> = Vec::new();
+        match &json_schema["key"] {
+            NP_JSON::Array(steps) if sorted => {
+                for step in steps.iter() {
+                    match step {
+                        NP_JSON::String(step) if step.len() < 256 => key.push(step.clone()),
+                        _ => return Err(NP_Error::new_schema("Sort keys must be a list of column names or tuple indexes!"))
+                    }
+                }
+            },
+            NP_JSON::Null => { },
+            _ => return Err(NP_Error::new_schema("Only sorted lists can have a sort key!"))
+        }
+
+        if key.len() > 255 {
+            return Err(NP_Error::new_schema("Sort keys can't be more than 255 steps long!"));
+        }
+
+        let wide = match &json_schema["wide"] {
+            NP_JSON::True => true,
+            NP_JSON::False | NP_JSON::Null => false,
+            _ => return Err(NP_Error::new_schema("List wide must be true or false!"))
+        };
+
+        let flags = if columnar { COLUMNAR_BIT } else { 0 } | if sorted { SORTED_BIT } else { 0 } | if key.is_empty() { 0 } else { KEY_BIT } | if wide { WIDE_BIT } else { 0 };
+        if flags != 0 {
+            schema_bytes.push(FLAGS_MARKER | flags);
+        }
+
+        if !key.is_empty() {
+            schema_bytes.push(key.len() as u8);
+            for step in key.iter() {
+                schema_bytes.push(step.len() as u8);
+                schema_bytes.extend_from_slice(step.as_bytes());
+            }
+        }
+
         let list_schema_addr = schema.len();
         schema.push(NP_Parsed_Schema::List {
             i: NP_TypeKeys::List,
             of: list_schema_addr + 1,
-            sortable: false
+            sortable: false,
+            columnar,
+            sorted,
+            key: key.clone(),
+            wide
         });
 
         match json_schema["of"] {
             NP_JSON::Null => {
-                return Err(NP_Error::new("Lists require an 'of' property that is a schema type!"))
+                return Err(NP_Error::new_schema("Lists require an 'of' property that is a schema type!"))
             },
             _ => { }
         }
 
         // let of_addr = schema.len();
-        let (_sortable, child_bytes, schema) = NP_Schema::from_json(schema, &Box::new(json_schema["of"].clone()))?;
+        let (of_sortable, child_bytes, schema) = NP_Schema::from_json(schema, &Box::new(json_schema["of"].clone()))?;
+
+        if sorted && key.is_empty() && !of_sortable {
+            return Err(NP_Error::new_schema("Sorted lists can only hold sortable items!"));
+        }
+
+        if !key.is_empty() {
+            match Self::key_schema(&schema, list_schema_addr + 1, &key) {
+                Some(key_addr) if schema[key_addr].is_sortable() => { },
+                _ => return Err(NP_Error::new_schema("Sort key must point to a sortable value inside the list items!"))
+            }
+        }
         
         schema_bytes.extend(child_bytes);
 
@@ -453,14 +944,33 @@ impl<'value> NP_Value<'value> for NP_List {
 
     fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
 
+        // type keys never have the high bit set, if it's there this is a flags byte in front of the item schema
+        let flags = if bytes[address + 1] & FLAGS_MARKER != 0 { bytes[address + 1] } else { 0 };
+        let mut of_address = if flags != 0 { address + 2 } else { address + 1 };
+
+        let mut key: Vec<String> = Vec::new();
+        if flags & KEY_BIT != 0 {
+            let steps = bytes[of_address] as usize;
+            of_address += 1;
+            for _ in 0..steps {
+                let step_len = bytes[of_address] as usize;
+                key.push(String::from_utf8_lossy(&bytes[(of_address + 1)..(of_address + 1 + step_len)]).into_owned());
+                of_address += 1 + step_len;
+            }
+        }
+
         let list_schema_addr = schema.len();
         schema.push(NP_Parsed_Schema::List {
             i: NP_TypeKeys::List,
             sortable: false,
-            of: list_schema_addr + 1
+            of: list_schema_addr + 1,
+            columnar: flags & COLUMNAR_BIT != 0,
+            sorted: flags & SORTED_BIT != 0,
+            key,
+            wide: flags & WIDE_BIT != 0
         });
         
-        let (_sortable, schema) = NP_Schema::from_bytes(schema, address + 1, bytes);
+        let (_sortable, schema) = NP_Schema::from_bytes(schema, of_address, bytes);
 
         (false, schema)
     }
@@ -523,4 +1033,334 @@ fn parseing_works() -> Result<(), NP_Error> {
     assert_eq!(new_buffer.get::<&str>(&["10"])?.unwrap(), "world");
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn sparse_iteration_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"list\",\"of\":{\"type\":\"string\"}}";
+    let factory = crate::NP_Factory::new(schema)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["2"], "hello")?;
+    buffer.set(&["5"], "world")?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[null,null,\"hello\",null,null,\"world\"]");
+
+    // insert between two existing items
+    buffer.set(&["4"], "there")?;
+    assert_eq!(buffer.get::<&str>(&["4"])?, Some("there"));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[null,null,\"hello\",null,\"there\",\"world\"]");
+    assert_eq!(buffer.get_iter(&[])?.unwrap().map(|item| item.index).collect::<Vec<usize>>(), [0usize, 1, 2, 3, 4, 5].to_vec());
+
+    Ok(())
+}
+
+#[test]
+fn columnar_layout_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"list\",\"layout\":\"columnar\",\"of\":{\"type\":\"table\",\"columns\":[[\"time\",{\"type\":\"uint64\"}],[\"name\",{\"type\":\"string\"}],[\"value\",{\"type\":\"uint32\"}]]}}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, compiled.schema.to_json()?.stringify());
+
+    assert!(crate::NP_Factory::new(r#"{"type": "list", "layout": "columnar", "of": {"type": "u8"}}"#).is_err());
+
+    let mut buffer = factory.empty_buffer(None);
+    for row in 0..4u32 {
+        let idx = row.to_string();
+        buffer.set(&[&idx, "time"], 1000u64 + row as u64)?;
+        buffer.set(&[&idx, "name"], "row")?;
+        if row != 2 {
+            buffer.set(&[&idx, "value"], row * 10)?;
+        }
+    }
+    buffer.set(&["6", "value"], 60u32)?;
+
+    let json = buffer.json_encode(&[])?.stringify();
+    buffer.compact(None)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), json);
+    assert_eq!(buffer.calc_bytes()?.wasted_bytes, 0);
+
+    // every time is next to the one in the row before it
+    let times: Vec<u8> = (0..4u64).flat_map(|x| (1000 + x).to_be_bytes().to_vec()).collect();
+    assert!(buffer.read_bytes().windows(times.len()).any(|x| x == &times[..]));
+
+    let values: Vec<u8> = [0u32, 10, 30, 60].iter().flat_map(|x| x.to_be_bytes().to_vec()).collect();
+    assert!(buffer.read_bytes().windows(values.len()).any(|x| x == &values[..]));
+
+    Ok(())
+}
+
+#[test]
+fn read_column_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["rows", {"type": "list", "of": {"type": "table", "columns": [
+            ["a", {"type": "u8"}], ["b", {"type": "u8"}], ["c", {"type": "u8"}],
+            ["d", {"type": "u8"}], ["e", {"type": "u8"}], ["f", {"type": "string"}]
+        ]}}]
+    ]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.read_column::<&str>(&["rows"], "f")?, Vec::<Option<&str>>::new());
+
+    buffer.set(&["rows", "0", "a"], 1u8)?;
+    buffer.set(&["rows", "1", "f"], "one")?;
+    buffer.set(&["rows", "2", "f"], "two")?;
+
+    assert_eq!(buffer.read_column::<&str>(&["rows"], "f")?, alloc::vec![None, Some("one"), Some("two")]);
+    assert_eq!(buffer.read_column::<u8>(&["rows"], "a")?, alloc::vec![Some(1), None, None]);
+
+    assert!(buffer.read_column::<u16>(&["rows"], "a").is_err());
+    assert!(buffer.read_column::<u8>(&["rows"], "z").is_err());
+    assert!(buffer.read_column::<u8>(&[], "rows").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn memory_stats_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["tags", {"type": "list", "of": {"type": "string", "intern": true}}],
+        ["count", {"type": "u16"}]
+    ]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["tags", "0"], "hello")?;
+    buffer.set(&["tags", "1"], "hello")?;
+    buffer.set(&["tags", "2"], "world")?;
+    buffer.set(&["count"], 2u16)?;
+    buffer.set(&["count"], 3u16)?;
+
+    let stats = buffer.memory_stats()?;
+    assert_eq!(stats.pointer_bytes + stats.payload_bytes + stats.wasted_bytes, stats.current_buffer);
+    // interned "hello" is counted once
+    assert_eq!(stats.payload_bytes, 7 + 7 + 2);
+    // root, vtable, list head and three items
+    assert_eq!(stats.pointer_bytes, 3 + 10 + 4 + 15);
+    assert_eq!(stats.wasted_bytes, 0);
+    assert_eq!(stats.bytes_by_type.iter().find(|x| x.0 == NP_TypeKeys::List), Some(&(NP_TypeKeys::List, 19)));
+
+    buffer.set(&["tags", "2"], "other")?;
+    let stats = buffer.memory_stats()?;
+    assert_eq!(stats.wasted_bytes, 7);
+    assert_eq!(stats.current_buffer - stats.wasted_bytes, buffer.calc_bytes()?.after_compaction);
+
+    buffer.compact(None)?;
+    let stats = buffer.memory_stats()?;
+    assert_eq!(stats.wasted_bytes, 0);
+    assert_eq!(stats.growth_events, 0);
+
+    Ok(())
+}
+
+#[test]
+fn sorted_list_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"sorted_list\",\"of\":{\"type\":\"string\",\"size\":4}}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory2 = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory2.schema.to_json()?.stringify());
+    assert!(crate::NP_Factory::new(r#"{"type": "sorted_list", "of": {"type": "string"}}"#).is_err());
+
+    let mut buffer = factory.empty_buffer(None);
+    for word in ["dddd", "bbbb", "aaaa", "cccc", "bbbb"].iter() {
+        buffer.sorted_insert(&[], *word)?;
+    }
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"["aaaa","bbbb","bbbb","cccc","dddd"]"#);
+    assert!(buffer.sorted_contains(&[], "cccc")?);
+    assert!(!buffer.sorted_contains(&[], "eeee")?);
+    assert_eq!(buffer.sorted_range(&[], "bbbb", "dddd")?, alloc::vec!["bbbb", "bbbb", "cccc"]);
+    assert!(buffer.list_push(&[], "eeee").is_err());
+    assert!(buffer.sorted_insert(&[], 5u8).is_err());
+
+    // deletes and compaction keep the order
+    buffer.del(&["1"])?;
+    buffer.compact(None)?;
+    assert_eq!(buffer.sorted_insert(&[], "abcd")?, Some(1));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"["aaaa","abcd","bbbb","cccc","dddd"]"#);
+
+    // a delete without compaction leaves an empty item, the next insert numbers the items again
+    buffer.del(&["0"])?;
+    assert_eq!(buffer.sorted_insert(&[], "bcde")?, Some(2));
+    assert_eq!(buffer.sorted_insert(&[], "aaab")?, Some(0));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"["aaab","abcd","bbbb","bcde","cccc","dddd"]"#);
+    for (idx, word) in ["aaab", "abcd", "bbbb", "bcde", "cccc", "dddd"].iter().enumerate() {
+        assert_eq!(buffer.get::<&str>(&[alloc::format!("{}", idx).as_str()])?, Some(*word));
+    }
+    assert_eq!(buffer.length(&[])?, Some(6));
+
+    // unsorted JSON is put in order
+    let from_json = factory.buffer_from_json(r#"["zzzz", "mmmm", "aaaa"]"#)?;
+    assert_eq!(from_json.json_encode(&[])?.stringify(), r#"["aaaa","mmmm","zzzz"]"#);
+    assert!(from_json.sorted_contains(&[], "mmmm")?);
+
+    Ok(())
+}
+
+#[test]
+fn priority_queue_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"sorted_list\",\"key\":[\"0\"],\"of\":{\"type\":\"tuple\",\"values\":[{\"type\":\"int32\"},{\"type\":\"string\"}]}}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory2 = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory2.schema.to_json()?.stringify());
+    assert!(crate::NP_Factory::new(r#"{"type": "priority_queue", "key": ["1"], "of": {"type": "tuple", "values": [{"type": "i32"}, {"type": "string"}]}}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type": "priority_queue", "key": ["missing"], "of": {"type": "table", "columns": [["a", {"type": "u8"}]]}}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type": "list", "key": ["0"], "of": {"type": "tuple", "values": [{"type": "i32"}]}}"#).is_err());
+
+    let mut buffer = factory.empty_buffer(None);
+    assert!(buffer.pop_min(&[])?.is_none());
+    for (key, name) in [(5, "e"), (-2, "a"), (9, "z"), (0, "c"), (-2, "b")].iter() {
+        buffer.sorted_push(&[], alloc::format!("[{}, \"{}\"]", key, name).as_str())?;
+    }
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"[[-2,"a"],[-2,"b"],[0,"c"],[5,"e"],[9,"z"]]"#);
+    assert!(buffer.sorted_contains(&[], 5i32)?);
+    assert!(!buffer.sorted_contains(&[], 6i32)?);
+    assert_eq!(buffer.sorted_range(&[], -2i32, 6i32)?, alloc::vec![-2, -2, 0, 5]);
+    assert!(buffer.sorted_push(&[], r#"["not a number", "x"]"#).is_err());
+    assert_eq!(buffer.length(&[])?, Some(5));
+
+    assert_eq!(buffer.pop_min(&[])?.unwrap().stringify(), r#"[-2,"a"]"#);
+    assert_eq!(buffer.pop_min(&[])?.unwrap().stringify(), r#"[-2,"b"]"#);
+    buffer.compact(None)?;
+    assert_eq!(buffer.sorted_push(&[], r#"[1, "d"]"#)?, Some(1));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"[[0,"c"],[1,"d"],[5,"e"],[9,"z"]]"#);
+
+    // deleted items at the front are skipped, the rest are numbered from zero
+    buffer.del(&["0"])?;
+    assert_eq!(buffer.pop_min(&[])?.unwrap().stringify(), r#"[1,"d"]"#);
+    assert_eq!(buffer.get::<i32>(&["0", "0"])?, Some(5));
+    assert_eq!(buffer.get::<i32>(&["1", "0"])?, Some(9));
+    assert_eq!(buffer.sorted_push(&[], r#"[7, "f"]"#)?, Some(1));
+    assert_eq!(buffer.pop_min(&[])?.unwrap().stringify(), r#"[5,"e"]"#);
+    assert_eq!(buffer.pop_min(&[])?.unwrap().stringify(), r#"[7,"f"]"#);
+    assert_eq!(buffer.pop_min(&[])?.unwrap().stringify(), r#"[9,"z"]"#);
+    assert!(buffer.pop_min(&[])?.is_none());
+    assert_eq!(buffer.sorted_push(&[], r#"[2, "g"]"#)?, Some(0));
+
+    // JSON set all at once is put in order by key
+    let from_json = factory.buffer_from_json(r#"[[3, "x"], [1, "y"]]"#)?;
+    assert_eq!(from_json.json_encode(&[])?.stringify(), r#"[[1,"y"],[3,"x"]]"#);
+
+    Ok(())
+}
+
+#[test]
+fn wide_lists_work() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"list\",\"wide\":true,\"of\":{\"type\":\"uint8\"}}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory2 = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory2.schema.to_json()?.stringify());
+    assert!(crate::NP_Factory::new(r#"{"type": "list", "wide": "yes", "of": {"type": "u8"}}"#).is_err());
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["70000"], 2u8)?;
+    buffer.set(&["300"], 1u8)?;
+    buffer.set(&["5"], 0u8)?;
+    assert_eq!(buffer.get::<u8>(&["70000"])?, Some(2));
+    assert_eq!(buffer.get::<u8>(&["300"])?, Some(1));
+    assert_eq!(buffer.length(&[])?, Some(70001));
+    buffer.compact(None)?;
+    assert_eq!(buffer.get::<u8>(&["70000"])?, Some(2));
+    assert_eq!(buffer.get::<u8>(&["5"])?, Some(0));
+    assert_eq!(buffer.list_push(&[], 3u8)?, Some(70001));
+    assert_eq!(buffer.get::<u8>(&["70001"])?, Some(3));
+
+    // normal lists stop at 255
+    let narrow = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u8"}}"#)?;
+    let mut narrow_buffer = narrow.empty_buffer(None);
+    narrow_buffer.set(&["255"], 1u8)?;
+    assert_eq!(narrow_buffer.get::<u8>(&["256"])?, None);
+    assert!(narrow_buffer.list_push(&[], 2u8).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn sparse_wide_lists_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "wide": true, "of": {"type": "u8"}}"#)?;
+
+    // gaps between far apart indexes are never filled in
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["4000000000"], 2u8)?;
+    buffer.set(&["5"], 0u8)?;
+    buffer.set(&["3000000000"], 1u8)?;
+    assert_eq!(buffer.get::<u8>(&["3000000000"])?, Some(1));
+    assert_eq!(buffer.length(&[])?, Some(4000000001));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"{"5":0,"3000000000":1,"4000000000":2}"#);
+    let mut written = alloc::string::String::new();
+    buffer.json_write_fmt(&[], &mut written)?;
+    assert_eq!(written, r#"{"5":0,"3000000000":1,"4000000000":2}"#);
+
+    let mut from_json = factory.empty_buffer(None);
+    from_json.set_with_json(&[], r#"{"4000000000": 2, "5": 0, "6": null, "3000000000": 1}"#)?;
+    assert!(buffer.semantic_eq(&from_json));
+    assert!(from_json.set_with_json(&[], r#"{"five": 5}"#).is_err());
+    let from_parser = factory.buffer_from_json(r#"{"4000000000": 2, "5": 0, "3000000000": 1}"#)?;
+    assert!(buffer.semantic_eq(&from_parser));
+
+    let lenient = factory.open_lenient(buffer.close()).0;
+    assert_eq!(lenient.json_encode(&[])?.stringify(), r#"{"5":0,"3000000000":1,"4000000000":2}"#);
+
+    // columns can't be read from lists that are mostly gaps
+    let rows = crate::NP_Factory::new(r#"{"type": "list", "wide": true, "of": {"type": "table", "columns": [["id", {"type": "u8"}]]}}"#)?;
+    let mut rows_buffer = rows.empty_buffer(None);
+    rows_buffer.set(&["1", "id"], 1u8)?;
+    assert_eq!(rows_buffer.read_column::<u8>(&[], "id")?, alloc::vec![None, Some(1)]);
+    rows_buffer.set(&["4000000000", "id"], 2u8)?;
+    assert!(rows_buffer.read_column::<u8>(&[], "id").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn reads_dont_make_items_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["tags", {"type": "list", "of": {"type": "string"}}],
+        ["nested", {"type": "list", "of": {"type": "list", "of": {"type": "u8"}}}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get::<&str>(&["tags", "4"])?, None);
+    buffer.set(&["tags", "2"], "middle")?;
+    buffer.set(&["nested", "1", "1"], 5u8)?;
+    let before = buffer.read_bytes().to_vec();
+
+    // before the head, after the tail, gaps and missing nested lists
+    assert_eq!(buffer.get::<&str>(&["tags", "0"])?, None);
+    assert_eq!(buffer.get::<&str>(&["tags", "9"])?, None);
+    assert_eq!(buffer.get::<u8>(&["nested", "0", "3"])?, None);
+    assert_eq!(buffer.get::<u8>(&["nested", "1", "0"])?, None);
+    assert_eq!(buffer.get::<u8>(&["nested", "1", "1"])?, Some(5));
+    assert_eq!(buffer.length(&["tags"])?, Some(3));
+    assert_eq!(buffer.read_bytes(), &before);
+
+    Ok(())
+}
+
+#[test]
+fn split_off_list_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "table", "columns": [["name", {"type": "string"}]]}}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["1", "name"], "one")?;
+    buffer.set(&["4", "name"], "four")?;
+    buffer.set(&["6", "name"], "six")?;
+
+    // gaps stay gaps in both halves
+    let tail = buffer.split_off_list(&[], 3)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"[null,{"name":"one"}]"#);
+    assert_eq!(tail.json_encode(&[])?.stringify(), r#"[null,{"name":"four"},null,{"name":"six"}]"#);
+    buffer.set(&["2", "name"], "two")?;
+    assert_eq!(buffer.length(&[])?, Some(3));
+
+    // splitting past the end moves nothing, splitting at zero moves everything
+    assert_eq!(buffer.split_off_list(&[], 10)?.length(&[])?, Some(0));
+    let all = buffer.split_off_list(&[], 0)?;
+    assert_eq!(all.get::<&str>(&["2", "name"])?, Some("two"));
+    assert_eq!(buffer.length(&[])?, Some(0));
+    buffer.set(&["0", "name"], "zero")?;
+    assert_eq!(buffer.get::<&str>(&["0", "name"])?, Some("zero"));
+
+    let not_list = crate::NP_Factory::new(r#"{"type": "string"}"#)?;
+    assert!(not_list.empty_buffer(None).split_off_list(&[], 0).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,497 @@
+//! Append only log collection
+//!
+//! Logs hold a sequence of values of a single type, like lists, but only support adding values to the end.  The log header stores the head, tail and number of items so appending is always constant time no matter how long the log is, and each item is one byte smaller than a list item since it doesn't store an index.
+//!
+//! Items can be read and updated by index, but new items can only be added at the end with `log_append` (or by setting the index one past the last item).  Iteration goes from the oldest item to the newest.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "log",
+//!    "of": {"type": "string"}
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.log_append(&[], "created")?;
+//! new_buffer.log_append(&[], "updated")?;
+//! assert_eq!(new_buffer.log_append(&[], "deleted")?, Some(2));
+//!
+//! assert_eq!(new_buffer.length(&[])?, Some(3));
+//! assert_eq!(new_buffer.get::<&str>(&["1"])?, Some("updated"));
+//!
+//! // items can't be added past the end of the log
+//! assert_eq!(new_buffer.set(&["10"], "hello")?, false);
+//!
+//! let events: Vec<String> = new_buffer.get_iter(&[])?.unwrap().map(|item| item.get::<&str>().unwrap().unwrap().to_string()).collect();
+//! assert_eq!(events, vec!["created", "updated", "deleted"]);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! ## Rings
+//! A log with `"type": "ring"` and a `"capacity"` keeps only the newest items.  Once the ring is full every append reuses the oldest item for the new value, so the ring never grows past its capacity and never needs to be compacted to get rid of old items.  Fixed size values (numbers, fixed size strings and so on) are overwritten in place, the space of the oldest value is reused.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "ring",
+//!    "capacity": 3,
+//!    "of": {"type": "f32"}
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! for sample in [20.5f32, 21.0, 21.5, 22.0, 22.5].iter() {
+//!     new_buffer.log_append(&[], *sample)?;
+//! }
+//!
+//! // the two oldest samples were overwritten
+//! assert_eq!(new_buffer.json_encode(&[])?.stringify(), "[21.5,22,22.5]");
+//! assert_eq!(new_buffer.get::<f32>(&["0"])?, Some(21.5));
+//!
+//! // the ring didn't grow
+//! let size = new_buffer.calc_bytes()?.current_buffer;
+//! new_buffer.log_append(&[], 23.0f32)?;
+//! assert_eq!(new_buffer.calc_bytes()?.current_buffer, size);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::{pointer::{NP_Log_Bytes}};
+use crate::{error::NP_Error, json_flex::{JSMAP, NP_JSON}, memory::{NP_Memory}, pointer::{NP_Value}, pointer::{NP_Cursor}, schema::NP_Parsed_Schema, schema::{NP_Schema, NP_TypeKeys}};
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::{vec::*};
+use alloc::string::ToString;
+
+// optional flags byte after the log type key, type keys never have the high bit set
+const FLAGS_MARKER: u8 = 0x80;
+// the capacity (u16) follows the flags
+const RING_BIT: u8 = 0x01;
+
+/// Log data type.
+///
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct NP_Log {
+    next: usize,
+    index: usize,
+    count: usize,
+    schema_of: usize,
+    log: NP_Cursor
+}
+
+#[allow(missing_docs)]
+impl NP_Log {
+
+    #[inline(always)]
+    pub fn select(log_cursor: NP_Cursor, index: usize, make_path: bool, memory: &NP_Memory) -> Result<Option<NP_Cursor>, NP_Error> {
+
+        let count = Self::count(&log_cursor, memory);
+
+        // new items can only go on the end
+        if index == count && make_path {
+            return Ok(Some(Self::append(&log_cursor, memory)?.1));
+        }
+
+        let mut log_iter = Self::new_iter(&log_cursor, memory);
+
+        while let Some((idx, item)) = log_iter.step_iter(memory) {
+            if idx == index {
+                return Ok(Some(item));
+            }
+        }
+
+        Ok(None)
+    }
+
+    #[inline(always)]
+    pub fn make_log<'make>(log_cursor: &NP_Cursor, memory: &'make NP_Memory) -> Result<(), NP_Error> {
+        let log_addr = memory.malloc_borrow(&[0u8; 6])?; // head, tail & count
+        let value = log_cursor.get_value_mut(memory);
+        value.set_addr_value(log_addr as u16);
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn get_log<'log>(log_cursor_value_addr: usize, memory: &'log NP_Memory<'log>) -> &'log NP_Log_Bytes {
+        if log_cursor_value_addr + 6 > memory.read_bytes().len() { // attack
+            unsafe { &*(memory.read_ptr() as *const NP_Log_Bytes) }
+        } else { // normal operation
+            unsafe { &*(memory.read_ptr().add(log_cursor_value_addr) as *const NP_Log_Bytes) }
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_log_mut<'log>(log_cursor_value_addr: usize, memory: &'log NP_Memory<'log>) -> &'log mut NP_Log_Bytes {
+        if log_cursor_value_addr + 6 > memory.read_bytes().len() { // attack
+            unsafe { &mut *(memory.bytes_ptr() as *mut NP_Log_Bytes) }
+        } else { // normal operation
+            unsafe { &mut *(memory.bytes_ptr().add(log_cursor_value_addr) as *mut NP_Log_Bytes) }
+        }
+    }
+
+    /// Number of items in the log
+    #[inline(always)]
+    pub fn count(log_cursor: &NP_Cursor, memory: &NP_Memory) -> usize {
+        let log_addr = log_cursor.get_value(memory).get_addr_value() as usize;
+        if log_addr == 0 {
+            0
+        } else {
+            Self::get_log(log_addr, memory).get_count() as usize
+        }
+    }
+
+    /// Add an empty item to the end of the log, returns the index and cursor of the new item
+    #[inline(always)]
+    pub fn append(log_cursor: &NP_Cursor, memory: &NP_Memory) -> Result<(usize, NP_Cursor), NP_Error> {
+
+        if log_cursor.get_value(memory).get_addr_value() == 0 {
+            Self::make_log(&log_cursor, memory)?;
+        }
+
+        let (schema_of, capacity) = match memory.schema[log_cursor.schema_addr] {
+            NP_Parsed_Schema::Log { of, capacity, .. } => (of, capacity),
+            _ => return Err(NP_Error::new_static("unreachable"))
+        };
+
+        let count = Self::count(log_cursor, memory);
+
+        // full rings move their oldest item to the end
+        if capacity != 0 && count >= capacity {
+            return Self::recycle(log_cursor, schema_of, memory);
+        }
+
+        if count >= u16::MAX as usize {
+            return Err(NP_Error::new_static("Logs cannot have more than 65,535 items!"))
+        }
+
+        let new_item_addr = memory.malloc_borrow(&[0u8; 4])?; // log item
+
+        let log_data = Self::get_log_mut(log_cursor.get_value_mut(memory).get_addr_value() as usize, memory);
+
+        let new_cursor = NP_Cursor::new(new_item_addr, schema_of, log_cursor.schema_addr);
+
+        if log_data.get_head() == 0 { // empty log
+            log_data.set_head(new_item_addr as u16);
+        } else {
+            let old_tail = NP_Cursor::new(log_data.get_tail() as usize, schema_of, log_cursor.schema_addr);
+            old_tail.get_value_mut(memory).set_next_addr(new_item_addr as u16);
+        }
+
+        log_data.set_tail(new_item_addr as u16);
+        log_data.set_count((count + 1) as u16);
+
+        Ok((count, new_cursor))
+    }
+
+    // reuse the oldest item of a full ring as the newest item
+    fn recycle(log_cursor: &NP_Cursor, schema_of: usize, memory: &NP_Memory) -> Result<(usize, NP_Cursor), NP_Error> {
+        let log_data = Self::get_log_mut(log_cursor.get_value_mut(memory).get_addr_value() as usize, memory);
+        let count = log_data.get_count() as usize;
+
+        let oldest = NP_Cursor::new(log_data.get_head() as usize, schema_of, log_cursor.schema_addr);
+        let oldest_value = oldest.get_value_mut(memory);
+
+        if count > 1 {
+            log_data.set_head(oldest_value.get_next_addr());
+            let old_tail = NP_Cursor::new(log_data.get_tail() as usize, schema_of, log_cursor.schema_addr);
+            old_tail.get_value_mut(memory).set_next_addr(oldest.buff_addr as u16);
+            log_data.set_tail(oldest.buff_addr as u16);
+            oldest_value.set_next_addr(0);
+        }
+
+        // scalars are overwritten in place by the next set, collections start over
+        match memory.schema[schema_of] {
+            NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::Tuple { .. } | NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Map { .. } | NP_Parsed_Schema::Log { .. } => {
+                oldest_value.set_addr_value(0);
+            },
+            _ => { }
+        }
+
+        Ok((count - 1, oldest))
+    }
+
+    #[inline(always)]
+    pub fn new_iter(log_cursor: &NP_Cursor, memory: &NP_Memory) -> Self {
+
+        let schema_of = match memory.schema[log_cursor.schema_addr] {
+            NP_Parsed_Schema::Log { of, .. } => of,
+            _ => 0
+        };
+
+        let log_addr = log_cursor.get_value(memory).get_addr_value() as usize;
+
+        let (head, count) = if log_addr == 0 {
+            (0, 0)
+        } else {
+            let log_data = Self::get_log(log_addr, memory);
+            (log_data.get_head() as usize, log_data.get_count() as usize)
+        };
+
+        Self {
+            next: head,
+            index: 0,
+            count,
+            schema_of,
+            log: log_cursor.clone()
+        }
+    }
+
+    #[inline(always)]
+    pub fn step_iter(&mut self, memory: &NP_Memory) -> Option<(usize, NP_Cursor)> {
+
+        // the count stops corrupted logs from looping forever
+        if self.next == 0 || self.index >= self.count {
+            return None;
+        }
+
+        let item = NP_Cursor::new(self.next, self.schema_of, self.log.schema_addr);
+        let this_index = self.index;
+
+        self.next = item.get_value(memory).get_next_addr() as usize;
+        self.index += 1;
+
+        Some((this_index, item))
+    }
+
+    /// Bytes used by the items of this log, items that are collections with data are added to `pending` instead of measured.
+    pub fn size_items(cursor: &NP_Cursor, memory: &NP_Memory, pending: &mut Vec<NP_Cursor>) -> Result<usize, NP_Error> {
+
+        if cursor.get_value(memory).get_addr_value() == 0 {
+            return Ok(0)
+        }
+
+        // head + tail + count
+        let mut acc_size = 6usize;
+
+        let mut log_iter = NP_Log::new_iter(cursor, memory);
+
+        while let Some((_index, item)) = log_iter.step_iter(memory) {
+            acc_size += NP_Cursor::size_queue(&item, memory, pending)?;
+        }
+
+        Ok(acc_size)
+    }
+
+    /// Copy this log into `to_memory`, the items are added to `pending` and copied once this log is done.
+    pub fn compact_items(from_cursor: NP_Cursor, from_memory: &NP_Memory, to_cursor: NP_Cursor, to_memory: &NP_Memory, pending: &mut Vec<(NP_Cursor, NP_Cursor)>) -> Result<NP_Cursor, NP_Error> {
+
+        if from_cursor.get_value(from_memory).get_addr_value() == 0 {
+            return Ok(to_cursor)
+        }
+
+        NP_Log::make_log(&to_cursor, to_memory)?;
+
+        let mut log_iter = NP_Log::new_iter(&from_cursor, from_memory);
+
+        // empty items are kept so indexes don't change
+        while let Some((_index, old_item)) = log_iter.step_iter(from_memory) {
+            let (_new_index, new_item) = NP_Log::append(&to_cursor, to_memory)?;
+            NP_Cursor::compact_queue(old_item, from_memory, new_item, to_memory, pending)?;
+        }
+
+        Ok(to_cursor)
+    }
+
+}
+
+impl<'value> NP_Value<'value> for NP_Log {
+
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("log", NP_TypeKeys::Log) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("log", NP_TypeKeys::Log) }
+
+    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
+        let mut schema_json = JSMAP::new();
+
+        let (log_of, capacity) = match &schema[address] {
+            NP_Parsed_Schema::Log { of, capacity, .. } => { (*of, *capacity) },
+            _ => (0, 0)
+        };
+
+        if capacity == 0 {
+            schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
+        } else {
+            schema_json.insert("type".to_owned(), NP_JSON::String("ring".to_owned()));
+            schema_json.insert("capacity".to_owned(), NP_JSON::Integer(capacity as i64));
+        }
+
+        schema_json.insert("of".to_owned(), NP_Schema::_type_to_json(schema, log_of)?);
+
+        Ok(NP_JSON::Dictionary(schema_json))
+    }
+
+    fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
+        // items are measured without recursion, see `NP_Cursor::calc_size`
+        Ok(NP_Cursor::calc_size(cursor, memory)? - cursor.get_value(memory).get_size())
+    }
+
+    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+        // collections are encoded without recursion, see `NP_Cursor::json_encode`
+        NP_Cursor::json_encode(cursor, memory)
+    }
+
+    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
+        // items are copied without recursion, see `NP_Cursor::compact`
+        NP_Cursor::compact(from_cursor, from_memory, to_cursor, to_memory)
+    }
+
+    fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
+
+        let mut schema_bytes: Vec<u8> = Vec::new();
+        schema_bytes.push(NP_TypeKeys::Log as u8);
+
+        let is_ring = match &json_schema["type"] {
+            NP_JSON::String(x) => x == "ring",
+            _ => false
+        };
+
+        let capacity = match &json_schema["capacity"] {
+            NP_JSON::Integer(x) if is_ring && *x > 0 && *x <= u16::MAX as i64 => *x as usize,
+            NP_JSON::Null if !is_ring => 0,
+            _ => return Err(NP_Error::new_schema("Rings require a 'capacity' property between 1 and 65,535, logs can't have one!"))
+        };
+
+        if capacity != 0 {
+            schema_bytes.push(FLAGS_MARKER | RING_BIT);
+            schema_bytes.extend_from_slice(&(capacity as u16).to_be_bytes());
+        }
+
+        let log_schema_addr = schema.len();
+        schema.push(NP_Parsed_Schema::Log {
+            i: NP_TypeKeys::Log,
+            of: log_schema_addr + 1,
+            sortable: false,
+            capacity
+        });
+
+        match json_schema["of"] {
+            NP_JSON::Null => {
+                return Err(NP_Error::new_schema("Logs require an 'of' property that is a schema type!"))
+            },
+            _ => { }
+        }
+
+        let (_sortable, child_bytes, schema) = NP_Schema::from_json(schema, &Box::new(json_schema["of"].clone()))?;
+
+        schema_bytes.extend(child_bytes);
+
+        return Ok((false, schema_bytes, schema))
+    }
+
+    fn schema_default(_schema: &NP_Parsed_Schema) -> Option<Self> {
+        None
+    }
+
+    fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+
+        let flags = if bytes[address + 1] & FLAGS_MARKER != 0 { bytes[address + 1] } else { 0 };
+        let (of_address, capacity) = if flags & RING_BIT != 0 {
+            (address + 4, u16::from_be_bytes([bytes[address + 2], bytes[address + 3]]) as usize)
+        } else if flags != 0 {
+            (address + 2, 0)
+        } else {
+            (address + 1, 0)
+        };
+
+        let log_schema_addr = schema.len();
+        schema.push(NP_Parsed_Schema::Log {
+            i: NP_TypeKeys::Log,
+            sortable: false,
+            of: log_schema_addr + 1,
+            capacity
+        });
+
+        let (_sortable, schema) = NP_Schema::from_bytes(schema, of_address, bytes);
+
+        (false, schema)
+    }
+}
+
+
+
+#[test]
+fn schema_parsing_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"log\",\"of\":{\"type\":\"string\"}}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+
+    let factory = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+
+    Ok(())
+}
+
+#[test]
+fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"log\",\"of\":{\"type\":\"string\"}}";
+    let factory = crate::NP_Factory::new(schema)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.log_append(&[], "hello")?;
+    buffer.log_append(&[], "world")?;
+    // header (6) + 2 items (4 each) + 2 strings
+    assert_eq!(buffer.calc_bytes()?.current_buffer, 3 + 6 + 8 + 7 + 7);
+    assert_eq!(buffer.calc_bytes()?.after_compaction, buffer.calc_bytes()?.current_buffer);
+
+    // updates in place, empty items keep their index
+    buffer.set(&["0"], "hello, world")?;
+    buffer.set(&["2"], "after")?;
+    buffer.log_append(&[], "last")?;
+    buffer.del(&["2"])?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[\"hello, world\",\"world\",null,\"last\"]");
+    buffer.compact(None)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[\"hello, world\",\"world\",null,\"last\"]");
+    assert_eq!(buffer.get::<&str>(&["3"])?, Some("last"));
+    assert_eq!(buffer.length(&[])?, Some(4));
+
+    buffer.del(&[])?;
+    buffer.compact(None)?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
+
+    Ok(())
+}
+
+#[test]
+fn ring_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"ring\",\"capacity\":3,\"of\":{\"type\":\"uint16\"}}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory2 = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory2.schema.to_json()?.stringify());
+    assert!(crate::NP_Factory::new(r#"{"type": "ring", "of": {"type": "u8"}}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type": "ring", "capacity": 0, "of": {"type": "u8"}}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type": "log", "capacity": 3, "of": {"type": "u8"}}"#).is_err());
+
+    let mut buffer = factory.empty_buffer(None);
+    for x in 0..3u16 {
+        assert_eq!(buffer.log_append(&[], x)?, Some(x as usize));
+    }
+    let full_size = buffer.calc_bytes()?.current_buffer;
+    for x in 3..10u16 {
+        assert_eq!(buffer.log_append(&[], x)?, Some(2));
+    }
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[7,8,9]");
+    assert_eq!(buffer.calc_bytes()?.current_buffer, full_size);
+    assert_eq!(buffer.length(&[])?, Some(3));
+
+    // setting one past the end is an append too
+    buffer.set(&["3"], 10u16)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[8,9,10]");
+    buffer.compact(None)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[8,9,10]");
+
+    // collection items start over when they're reused
+    let factory = crate::NP_Factory::new(r#"{"type": "ring", "capacity": 1, "of": {"type": "table", "columns": [["a", {"type": "u8"}], ["b", {"type": "u8"}]]}}"#)?;
+    let mut buffer = factory.buffer_from_json(r#"[{"a": 1, "b": 2}, {"a": 3}]"#)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"[{"a":3,"b":null}]"#);
+    buffer.set(&["1", "b"], 4u8)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"[{"a":null,"b":4}]"#);
+
+    Ok(())
+}
@@ -1,5 +1,4 @@
-use alloc::string::String;
-use crate::{pointer::NP_Map_Bytes, utils::opt_err};
+use crate::pointer::NP_Map_Bytes;
 use crate::pointer::NP_Cursor;
 use crate::{json_flex::JSMAP};
 use crate::pointer::{NP_Value};
@@ -9,12 +8,20 @@ use alloc::string::ToString;
 use alloc::vec::Vec;
 use alloc::boxed::Box;
 use alloc::borrow::ToOwned;
-use core::{hint::unreachable_unchecked};
+use alloc::borrow::Cow;
+use crate::hashmap::{murmurhash3_x86_32, SEED};
+
+// type keys never have the high bit set, if it's there this is a flags byte in front of the value schema
+const FLAGS_MARKER: u8 = 0x80;
+const HASHED_KEYS_BIT: u8 = 0x01;
+// bytes stored for each key of a hashed map, the murmur3 hash of the key and a second hash to catch collisions of the first
+const HASHED_KEY_LEN: usize = 8;
+const CHECK_SEED: u32 = 0x5bd1_e995;
 
 #[doc(hidden)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 struct Map_Item<'item> {
-    key: &'item str,
+    key: Cow<'item, str>,
     buff_addr: usize
 }
 
@@ -26,7 +33,9 @@ pub struct NP_Map<'map> {
     current: Option<Map_Item<'map>>,
     head: Option<Map_Item<'map>>,
     map: NP_Cursor,
-    value_of: usize
+    value_of: usize,
+    // steps left before the map must be looping
+    remaining: usize
 }
 
 #[allow(missing_docs)]
@@ -35,29 +44,200 @@ impl<'map> NP_Map<'map> {
     #[inline(always)]
     pub fn select(map_cursor: NP_Cursor, key: &str, make_path: bool, memory: &'map NP_Memory) -> Result<Option<NP_Cursor>, NP_Error> {
 
-        let mut map_iter = Self::new_iter(&map_cursor, memory);
+        let stored = Self::stored_key(&memory.schema[map_cursor.schema_addr], key)?;
 
         // key is maybe in map
-        while let Some((ikey, item)) = map_iter.step_iter(memory) {
-            if ikey == key {
-                return Ok(Some(item.clone()))
-            }
+        if let Some(item) = Self::find_stored(&map_cursor, &stored, memory) {
+            return Ok(Some(item));
         }
 
         // key is not in map
         if make_path {
-            Ok(Some(Self::insert(&map_cursor, memory, key)?))
+            if Self::hash_collides(&map_cursor, &stored, memory) {
+                return Err(NP_Error::new(alloc::format!("Map key ({}) has the same hash as another key!", key)));
+            }
+            Ok(Some(Self::insert_stored(&map_cursor, memory, &stored)?))
         } else {
             Ok(None)
         }
     }
 
+    /// Add a key that isn't in the map yet, like the keys of a JSON object.
+    /// 
+    /// Maps with text keys just select the key, hashed maps return an error if the hash of the key is already in the map since two different keys would share one value.
+    pub fn select_new(map_cursor: NP_Cursor, key: &str, memory: &'map NP_Memory) -> Result<NP_Cursor, NP_Error> {
+        let stored = Self::stored_key(&memory.schema[map_cursor.schema_addr], key)?;
+        match Self::find_stored(&map_cursor, &stored, memory) {
+            Some(_item) if matches!(memory.schema[map_cursor.schema_addr], NP_Parsed_Schema::Map { hashed: true, .. }) => {
+                Err(NP_Error::new(alloc::format!("Map key ({}) has the same hash as another key!", key)))
+            },
+            Some(item) => Ok(item),
+            None if Self::hash_collides(&map_cursor, &stored, memory) => {
+                Err(NP_Error::new(alloc::format!("Map key ({}) has the same hash as another key!", key)))
+            },
+            None => Self::insert_stored(&map_cursor, memory, &stored)
+        }
+    }
+
+    // true if a hashed map has a different key with the same murmur3 hash as this stored key, the check hashes tell them apart
+    fn hash_collides(map_cursor: &NP_Cursor, key: &[u8], memory: &NP_Memory) -> bool {
+        match memory.schema[map_cursor.schema_addr] {
+            NP_Parsed_Schema::Map { hashed: true, .. } => { },
+            _ => return false
+        }
+
+        if map_cursor.get_value(memory).get_addr_value() == 0 {
+            return false;
+        }
+
+        let bytes = memory.read_bytes();
+        let mut item_addr = Self::get_map(map_cursor.buff_addr, memory).get_head() as usize;
+        let mut remaining = Self::max_items(memory);
+
+        while item_addr != 0 && remaining > 0 {
+            remaining -= 1;
+            let item_value = NP_Cursor::new(item_addr, 0, map_cursor.schema_addr).get_value(memory);
+            let key_addr = item_value.get_key_addr() as usize;
+            if key_addr != 0 && bytes.get(key_addr..(key_addr + 4)) == key.get(..4) {
+                return true;
+            }
+            item_addr = item_value.get_next_addr() as usize;
+        }
+        false
+    }
+
+    /// The key as it's stored in the buffer.
+    /// 
+    /// Maps with hashed keys store the 4 bytes of the key's murmur3 hash followed by 4 bytes of a second murmur3 hash with another seed, the second hash catches keys whose first hashes collide.  Keys written as `#` and 16 hex digits (the way `step_iter` gives them back) are those 8 bytes, literal keys starting with `#` are escaped with a second `#`.
+    pub fn stored_key<'key>(map_schema: &NP_Parsed_Schema, key: &'key str) -> Result<Cow<'key, [u8]>, NP_Error> {
+        match map_schema {
+            NP_Parsed_Schema::Map { hashed: true, .. } => {
+                let hash = |literal: &str| ((murmurhash3_x86_32(literal.as_bytes(), SEED) as u64) << 32) | murmurhash3_x86_32(literal.as_bytes(), CHECK_SEED) as u64;
+                let hash = if let Some(literal) = key.strip_prefix('#') {
+                    if literal.starts_with('#') {
+                        hash(literal)
+                    } else if Self::is_hashed_key(key) {
+                        u64::from_str_radix(literal, 16).map_err(|_e| NP_Error::new_static("Hashed map key isn't a hash!"))?
+                    } else {
+                        return Err(NP_Error::new(alloc::format!("Hashed map key ({}) starts with # but isn't a hash, escape it as #{}", key, key)));
+                    }
+                } else {
+                    hash(key)
+                };
+                Ok(Cow::Owned(hash.to_be_bytes().to_vec()))
+            },
+            _ => Ok(Cow::Borrowed(key.as_bytes()))
+        }
+    }
+
+    /// Bytes the key takes up in a map with this schema, including the length byte of text keys
+    pub fn stored_key_len(map_schema: &NP_Parsed_Schema, key: &str) -> usize {
+        match map_schema {
+            NP_Parsed_Schema::Map { hashed: true, .. } => HASHED_KEY_LEN,
+            _ => 1 + key.len()
+        }
+    }
+
+    fn is_hashed_key(key: &str) -> bool {
+        key.len() == 1 + HASHED_KEY_LEN * 2 && key.starts_with('#') && key[1..].bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    }
+
+    /// Walk the map looking for a key without decoding every key along the way.
+    #[inline(always)]
+    pub fn find(map_cursor: &NP_Cursor, key: &str, memory: &NP_Memory) -> Option<NP_Cursor> {
+        let key = Self::stored_key(&memory.schema[map_cursor.schema_addr], key).ok()?;
+        Self::find_stored(map_cursor, &key, memory)
+    }
+
+    /// Look for a key already in the form it's stored in, see `stored_key`.
+    /// 
+    /// Text keys are stored with a length byte, so most entries are rejected by their length or first byte and only keys of the right size are compared as a whole.
+    /// 
+    /// This is a plain walk over the entries, one at a time, with no SIMD or block scan.  Entries are linked through the buffer instead of sitting side by side, so there's no block of keys to scan at once, and the one key compare per candidate is already a `memcmp`.  Lookups stay linear in the size of the map, use `"keys": "hashed"` or a table when that matters.
+    pub fn find_stored(map_cursor: &NP_Cursor, key: &[u8], memory: &NP_Memory) -> Option<NP_Cursor> {
+
+        if map_cursor.get_value(memory).get_addr_value() == 0 {
+            return None;
+        }
+
+        let (value_of, hashed) = match memory.schema[map_cursor.schema_addr] {
+            NP_Parsed_Schema::Map { value, hashed, .. } => (value, hashed),
+            _ => (0, false)
+        };
+
+        // text keys have a one byte length and `insert_stored` refuses longer ones, so a longer key can't be in the map
+        if !hashed && key.len() >= 255 {
+            return None;
+        }
+
+        let first = key.first().copied();
+        let bytes = memory.read_bytes();
+
+        let mut item_addr = Self::get_map(map_cursor.buff_addr, memory).get_head() as usize;
+        let mut remaining = Self::max_items(memory);
+
+        while item_addr != 0 && remaining > 0 {
+            remaining -= 1;
+            let item = NP_Cursor::new(item_addr, value_of, map_cursor.schema_addr);
+            let item_value = item.get_value(memory);
+            let key_addr = item_value.get_key_addr() as usize;
+
+            let matches = if hashed {
+                key_addr != 0 && bytes.get(key_addr..(key_addr + HASHED_KEY_LEN)) == Some(key)
+            } else if key_addr == 0 {
+                key.is_empty()
+            } else {
+                bytes.get(key_addr) == Some(&(key.len() as u8)) &&
+                (first.is_none() || bytes.get(key_addr + 1).copied() == first) &&
+                bytes.get((key_addr + 1)..(key_addr + 1 + key.len())) == Some(key)
+            };
+
+            if matches {
+                return Some(item);
+            }
+
+            item_addr = item_value.get_next_addr() as usize;
+        }
+
+        None
+    }
+
+    /// The key of a map item as it's given back by `step_iter`, hashed keys are written as `#` and 16 hex digits.
+    pub fn item_key<'key>(item: &NP_Cursor, memory: &'key NP_Memory) -> Cow<'key, str> {
+        let item_value = item.get_value(memory);
+        match memory.schema[item.parent_schema_addr] {
+            NP_Parsed_Schema::Map { hashed: true, .. } => {
+                let key_addr = item_value.get_key_addr() as usize;
+                match memory.read_bytes().get(key_addr..(key_addr + HASHED_KEY_LEN)) {
+                    Some(hash) if key_addr != 0 => Cow::Owned(alloc::format!("#{:016x}", u64::from_be_bytes([hash[0], hash[1], hash[2], hash[3], hash[4], hash[5], hash[6], hash[7]]))),
+                    _ => Cow::Borrowed("") // attack
+                }
+            },
+            _ => Cow::Borrowed(item_value.get_key(memory))
+        }
+    }
+
+    /// Items never share bytes, so a map can't have more items than fit in the buffer.  Stepping past this means a corrupted next address has made a loop.
     #[inline(always)]
-    pub fn get_map<'get>(map_buff_addr: usize, memory: &'get NP_Memory<'get>) -> &'get mut NP_Map_Bytes {
-        if map_buff_addr > memory.read_bytes().len() { // attack
-            unsafe { &mut *(memory.write_bytes().as_ptr() as *mut NP_Map_Bytes) }
+    pub fn max_items(memory: &NP_Memory) -> usize {
+        memory.read_bytes().len() / 6
+    }
+
+    #[inline(always)]
+    pub fn get_map<'get>(map_buff_addr: usize, memory: &'get NP_Memory<'get>) -> &'get NP_Map_Bytes {
+        if map_buff_addr + 2 > memory.read_bytes().len() { // attack
+            unsafe { &*(memory.read_ptr() as *const NP_Map_Bytes) }
+        } else { // normal operation
+            unsafe { &*(memory.read_ptr().add(map_buff_addr) as *const NP_Map_Bytes) }
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_map_mut<'get>(map_buff_addr: usize, memory: &'get NP_Memory<'get>) -> &'get mut NP_Map_Bytes {
+        if map_buff_addr + 2 > memory.read_bytes().len() { // attack
+            unsafe { &mut *(memory.bytes_ptr() as *mut NP_Map_Bytes) }
         } else { // normal operation
-            unsafe { &mut *(memory.write_bytes().as_ptr().add(map_buff_addr as usize) as *mut NP_Map_Bytes) }
+            unsafe { &mut *(memory.bytes_ptr().add(map_buff_addr) as *mut NP_Map_Bytes) }
         }
     }
 
@@ -74,33 +254,39 @@ impl<'map> NP_Map<'map> {
                 current: None,
                 head: None,
                 map: map_cursor.clone(),
-                value_of
+                value_of,
+                remaining: 0
             }
         }
 
         let head_addr = Self::get_map(map_cursor.buff_addr, memory).get_head();
 
         let head_cursor = NP_Cursor::new(head_addr as usize, value_of, map_cursor.schema_addr);
-        let head_cursor_value = head_cursor.get_value(memory);
 
         Self {
             current: None,
             head: Some(Map_Item {
-                key: head_cursor_value.get_key(memory),
+                key: Self::item_key(&head_cursor, memory),
                 buff_addr: head_cursor.buff_addr 
             }),
             map: map_cursor.clone(),
-            value_of
+            value_of,
+            remaining: Self::max_items(memory)
         }
     }
 
     #[inline(always)]
-    pub fn step_iter(&mut self, memory: &'map NP_Memory<'map>) -> Option<(&'map str, NP_Cursor)> {
+    pub fn step_iter(&mut self, memory: &'map NP_Memory<'map>) -> Option<(Cow<'map, str>, NP_Cursor)> {
         
-        match self.head {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        match &self.head {
             Some(head) => {
 
-                match self.current {
+                match &self.current {
                     Some(current) => { // subsequent iterations
                         let current_item = NP_Cursor::new(current.buff_addr, self.value_of, self.map.schema_addr);
                         let current_value = current_item.get_value(memory);
@@ -109,13 +295,13 @@ impl<'map> NP_Map<'map> {
                             return None;
                         } else {
                             let next_value_cursor = NP_Cursor::new(next_value, self.value_of, self.map.schema_addr);
-                            let next_value_value = next_value_cursor.get_value(memory);
-                            let key = next_value_value.get_key(memory);
-                            self.current = Some(Map_Item { buff_addr: next_value, key: key });
+                            let key = Self::item_key(&next_value_cursor, memory);
+                            self.current = Some(Map_Item { buff_addr: next_value, key: key.clone() });
                             return Some((key, next_value_cursor))
                         }
                     },
                     None => { // first iteration, get head
+                        let head = head.clone();
                         self.current = Some(head.clone());
                         return Some((head.key, NP_Cursor::new(head.buff_addr, self.value_of, self.map.schema_addr)))
                     }
@@ -127,27 +313,39 @@ impl<'map> NP_Map<'map> {
 
     }
 
+    /// Add a key to the map, see `stored_key` for how keys of hashed maps are written.
     #[inline(always)]
     pub fn insert(map_cursor: &NP_Cursor, memory: &NP_Memory, key: &str) -> Result<NP_Cursor, NP_Error> {
+        let key = Self::stored_key(&memory.schema[map_cursor.schema_addr], key)?;
+        Self::insert_stored(map_cursor, memory, &key)
+    }
 
-        let value_of = match memory.schema[map_cursor.schema_addr] {
-            NP_Parsed_Schema::Map { value, .. } => value,
-            _ => 0
+    fn insert_stored(map_cursor: &NP_Cursor, memory: &NP_Memory, key: &[u8]) -> Result<NP_Cursor, NP_Error> {
+
+        let (value_of, hashed) = match memory.schema[map_cursor.schema_addr] {
+            NP_Parsed_Schema::Map { value, hashed, .. } => (value, hashed),
+            _ => (0, false)
         };
 
         if key.len() >= 255 {
-            return Err(NP_Error::new("Key length cannot be larger than 255 charecters!"));
+            return Err(NP_Error::new_static("Key length cannot be larger than 255 charecters!"));
         }
 
-        let map_value = map_cursor.get_value(memory);
-
         let new_cursor_addr = memory.malloc_borrow(&[0u8; 6])?;
         let new_cursor = NP_Cursor::new(new_cursor_addr, value_of, map_cursor.schema_addr);
-        let new_cursor_value = new_cursor.get_value(memory);
 
-        // set key
-        let key_item_addr = memory.malloc_borrow(&[key.len() as u8])?;
-        memory.malloc_borrow(key.as_bytes())?;
+        // set key, hashes are always the same length
+        let key_item_addr = if hashed {
+            memory.malloc_borrow(key)?
+        } else {
+            let key_item_addr = memory.malloc_borrow(&[key.len() as u8])?;
+            memory.malloc_borrow(key)?;
+            key_item_addr
+        };
+
+        // malloc may have moved the buffer, get pointers after
+        let map_value = map_cursor.get_value_mut(memory);
+        let new_cursor_value = new_cursor.get_value_mut(memory);
         new_cursor_value.set_key_addr(key_item_addr as u16);
 
         let head = map_value.get_addr_value() as usize;
@@ -162,28 +360,9 @@ impl<'map> NP_Map<'map> {
         Ok(new_cursor)
     }
 
-}
-
-impl<'value> NP_Value<'value> for NP_Map<'value> {
-
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("map", NP_TypeKeys::Map) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("map", NP_TypeKeys::Map) }
-    
-    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
-        let mut schema_json = JSMAP::new();
-        schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
-
-        let value_of = match schema[address] {
-            NP_Parsed_Schema::Map { value, .. } => { value },
-            _ => 0
-        };
-
-        schema_json.insert("value".to_owned(), NP_Schema::_type_to_json(schema, value_of)?);
 
-        Ok(NP_JSON::Dictionary(schema_json))
-    }
-
-    fn get_size(cursor: &NP_Cursor, memory: &'value NP_Memory<'value>) -> Result<usize, NP_Error> {
+    /// Bytes used by the items of this map, items that are collections with data are added to `pending` instead of measured.
+    pub fn size_items(cursor: &NP_Cursor, memory: &NP_Memory, pending: &mut Vec<NP_Cursor>) -> Result<usize, NP_Error> {
 
         let c_value = cursor.get_value(memory);
 
@@ -193,13 +372,18 @@ impl<'value> NP_Value<'value> for NP_Map<'value> {
 
         let mut acc_size = 0usize;
 
-        let mut map_iter = Self::new_iter(&cursor, memory);
+        let mut map_iter = NP_Map::new_iter(cursor, memory);
+
+        let hashed = matches!(memory.schema[cursor.schema_addr], NP_Parsed_Schema::Map { hashed: true, .. });
 
-        while let Some((_index, item)) = Self::step_iter(&mut map_iter, memory) {
-            let key_size = item.get_value(memory).get_key_size(memory);
-            acc_size += 1; // length byte
-            acc_size += key_size;
-            acc_size += NP_Cursor::calc_size(&item, memory)?;
+        while let Some((_index, item)) = NP_Map::step_iter(&mut map_iter, memory) {
+            if hashed {
+                acc_size += HASHED_KEY_LEN;
+            } else {
+                acc_size += 1; // length byte
+                acc_size += item.get_value(memory).get_key_size(memory);
+            }
+            acc_size += NP_Cursor::size_queue(&item, memory, pending)?;
         }
 
 
@@ -207,43 +391,84 @@ impl<'value> NP_Value<'value> for NP_Map<'value> {
    
     }
 
-    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+    /// Copy this map into `to_memory`, the items are added to `pending` and copied once this map is done.
+    pub fn compact_items(from_cursor: NP_Cursor, from_memory: &NP_Memory, to_cursor: NP_Cursor, to_memory: &NP_Memory, pending: &mut Vec<(NP_Cursor, NP_Cursor)>) -> Result<NP_Cursor, NP_Error> {
 
-        let c_value = cursor.get_value(memory);
+        let from_value = from_cursor.get_value(from_memory);
 
-        if c_value.get_addr_value() == 0 {
-            return NP_JSON::Null
+        if from_value.get_addr_value() == 0 {
+            return Ok(to_cursor) 
         }
 
-        let mut json_map = JSMAP::new();
+        let mut map_iter = NP_Map::new_iter(&from_cursor, from_memory);
 
-        let mut map_iter = Self::new_iter(&cursor, memory);
+        // canonical copies put the keys in order and leave out keys without a value, new keys go in front so insert them last to first
+        if to_memory.is_canonical() {
+            let mut items: Vec<(Cow<str>, NP_Cursor)> = Vec::new();
+            while let Some(item) = NP_Map::step_iter(&mut map_iter, from_memory) {
+                if item.1.get_value(from_memory).get_addr_value() != 0 {
+                    items.push(item);
+                }
+            }
+            items.sort_by(|a, b| b.0.as_bytes().cmp(a.0.as_bytes()));
+            for (key, item) in items {
+                let new_item = NP_Map::insert(&to_cursor, to_memory, &key)?;
+                NP_Cursor::compact_queue(item, from_memory, new_item, to_memory, pending)?;
+            }
+            return Ok(to_cursor);
+        }
 
-        while let Some((key, item)) = Self::step_iter(&mut map_iter, memory) {
-            json_map.insert(String::from(key), NP_Cursor::json_encode(&item, memory));     
+        while let Some((key, item)) = NP_Map::step_iter(&mut map_iter, from_memory) {
+            // pruned values take their keys with them
+            if to_memory.is_pruning() && item.get_value(from_memory).get_addr_value() == 0 {
+                continue;
+            }
+            let new_item = NP_Map::insert(&to_cursor, to_memory, &key)?;
+            NP_Cursor::compact_queue(item, from_memory, new_item, to_memory, pending)?;
         }
 
-        NP_JSON::Dictionary(json_map)
-   
+
+        Ok(to_cursor)
     }
 
-    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
+}
 
-        let from_value = from_cursor.get_value(from_memory);
+impl<'value> NP_Value<'value> for NP_Map<'value> {
 
-        if from_value.get_addr_value() == 0 {
-            return Ok(to_cursor) 
-        }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("map", NP_TypeKeys::Map) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("map", NP_TypeKeys::Map) }
+    
+    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
+        let mut schema_json = JSMAP::new();
+        schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
 
-        let mut map_iter = Self::new_iter(&from_cursor, from_memory);
+        let value_of = match schema[address] {
+            NP_Parsed_Schema::Map { value, .. } => { value },
+            _ => 0
+        };
 
-        while let Some((key, item)) = Self::step_iter(&mut map_iter, from_memory) {
-            let new_item = Self::insert(&to_cursor, to_memory, key)?;
-            NP_Cursor::compact(item.clone(), from_memory, new_item, to_memory)?;    
+        if let NP_Parsed_Schema::Map { hashed: true, .. } = &schema[address] {
+            schema_json.insert("keys".to_owned(), NP_JSON::String("hashed".to_owned()));
         }
 
+        schema_json.insert("value".to_owned(), NP_Schema::_type_to_json(schema, value_of)?);
 
-        Ok(to_cursor)
+        Ok(NP_JSON::Dictionary(schema_json))
+    }
+
+    fn get_size(cursor: &NP_Cursor, memory: &'value NP_Memory<'value>) -> Result<usize, NP_Error> {
+        // items are measured without recursion, see `NP_Cursor::calc_size`
+        Ok(NP_Cursor::calc_size(cursor, memory)? - cursor.get_value(memory).get_size())
+    }
+
+    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+        // collections are encoded without recursion, see `NP_Cursor::json_encode`
+        NP_Cursor::json_encode(cursor, memory)
+    }
+
+    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
+        // items are copied without recursion, see `NP_Cursor::compact`
+        NP_Cursor::compact(from_cursor, from_memory, to_cursor, to_memory)
     }
 
     fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
@@ -251,16 +476,28 @@ impl<'value> NP_Value<'value> for NP_Map<'value> {
         let mut schema_data: Vec<u8> = Vec::new();
         schema_data.push(NP_TypeKeys::Map as u8);
 
+        let hashed = match &json_schema["keys"] {
+            NP_JSON::String(keys) if keys == "hashed" => true,
+            NP_JSON::String(keys) if keys == "text" => false,
+            NP_JSON::Null => false,
+            _ => return Err(NP_Error::new_schema("Map keys must be \"text\" or \"hashed\"!"))
+        };
+
+        if hashed {
+            schema_data.push(FLAGS_MARKER | HASHED_KEYS_BIT);
+        }
+
         let value_addr = schema.len();
         schema.push(NP_Parsed_Schema::Map {
             i: NP_TypeKeys::Map,
             value: value_addr + 1,
-            sortable: false
+            sortable: false,
+            hashed
         });
 
         match json_schema["value"] {
             NP_JSON::Null => {
-                return Err(NP_Error::new("Maps require a 'value' property that is a schema type!"))
+                return Err(NP_Error::new_schema("Maps require a 'value' property that is a schema type!"))
             },
             _ => { }
         }
@@ -279,13 +516,17 @@ impl<'value> NP_Value<'value> for NP_Map<'value> {
     }
 
     fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+        let flags = if bytes[address + 1] & FLAGS_MARKER != 0 { bytes[address + 1] } else { 0 };
+        let value_address = if flags != 0 { address + 2 } else { address + 1 };
+
         let of_addr = schema.len();
         schema.push(NP_Parsed_Schema::Map {
             i: NP_TypeKeys::Map,
             sortable: false,
-            value: of_addr + 1
+            value: of_addr + 1,
+            hashed: flags & HASHED_KEYS_BIT != 0
         });
-        let (_sortable, schema) = NP_Schema::from_bytes(schema, address + 1, bytes);
+        let (_sortable, schema) = NP_Schema::from_bytes(schema, value_address, bytes);
         (false, schema)
     }
 }
@@ -328,4 +569,138 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 55usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn key_lookup_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"map\",\"value\":{\"type\":\"u16\"}}";
+    let factory = crate::NP_Factory::new(schema)?;
+
+    // keys sharing lengths, first bytes and prefixes
+    let mut buffer = factory.empty_buffer(None);
+    let keys: Vec<alloc::string::String> = (0..200u16).map(|i| alloc::format!("key-{:03}", i)).collect();
+    for (i, key) in keys.iter().enumerate() {
+        buffer.set(&[key.as_str()], i as u16)?;
+    }
+    buffer.set(&["k"], 1000u16)?;
+    buffer.set(&[""], 2000u16)?;
+
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(buffer.get::<u16>(&[key.as_str()])?, Some(i as u16));
+    }
+    assert_eq!(buffer.get::<u16>(&["k"])?, Some(1000));
+    assert_eq!(buffer.get::<u16>(&[""])?, Some(2000));
+    assert_eq!(buffer.get::<u16>(&["key-200"])?, None);
+    assert_eq!(buffer.get::<u16>(&["key-00"])?, None);
+    assert_eq!(buffer.get::<u16>(&["key-0000"])?, None);
+
+    // a 256 byte key would have a length byte of 0 if it wrapped
+    let long_key = "x".repeat(256);
+    assert_eq!(buffer.get::<u16>(&[long_key.as_str()])?, None);
+    assert!(buffer.set(&[long_key.as_str()], 1u16).is_err());
+    assert_eq!(buffer.get::<u16>(&[""])?, Some(2000));
+
+    // setting an existing key doesn't add another entry
+    buffer.set(&["key-100"], 7u16)?;
+    assert_eq!(buffer.length(&[])?, Some(202));
+    assert_eq!(buffer.get::<u16>(&["key-100"])?, Some(7));
+
+    Ok(())
+}
+
+#[test]
+fn hashed_keys_work() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"map\",\"keys\":\"hashed\",\"value\":{\"type\":\"uint32\"}}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory2 = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory2.schema.to_json()?.stringify());
+    assert!(crate::NP_Factory::new(r#"{"type": "map", "keys": "short", "value": {"type": "u8"}}"#).is_err());
+
+    let long_key = "https://example.com/a/very/long/path/that/would/take/a/lot/of/space";
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&[long_key], 5u32)?;
+    buffer.set(&["short"], 6u32)?;
+    assert_eq!(buffer.get::<u32>(&[long_key])?, Some(5));
+    assert_eq!(buffer.get::<u32>(&["short"])?, Some(6));
+    assert_eq!(buffer.get::<u32>(&["other"])?, None);
+
+    // only the hashes are stored and they come back out as keys
+    let hashed = |key: &str| alloc::format!("#{:08x}{:08x}", murmurhash3_x86_32(key.as_bytes(), SEED), murmurhash3_x86_32(key.as_bytes(), CHECK_SEED));
+    let hashed_key = hashed(long_key);
+    assert_eq!(buffer.get::<u32>(&[hashed_key.as_str()])?, Some(5));
+    assert!(buffer.read_bytes().len() < long_key.len());
+    let json = buffer.json_encode(&[])?;
+    assert!(json.stringify().contains(&hashed_key));
+
+    // hashes survive compaction and setting the JSON again
+    buffer.compact(None)?;
+    assert_eq!(buffer.get::<u32>(&[long_key])?, Some(5));
+    let mut copy = factory.empty_buffer(None);
+    copy.set_json_value(&[], &json)?;
+    assert_eq!(copy.get::<u32>(&[long_key])?, Some(5));
+    assert_eq!(copy.length(&[])?, Some(2));
+
+    // each key is 8 bytes of hashes behind the item pointer
+    let mut small = factory.empty_buffer(None);
+    small.set(&["a"], 1u32)?;
+    let one_key = small.read_bytes().len();
+    small.set(&[long_key], 1u32)?;
+    assert_eq!(small.read_bytes().len() - one_key, 6 + 8 + 4);
+
+    // keys that start with # are escaped, hashes of keys in one JSON object can't collide
+    buffer.set(&["##tag"], 7u32)?;
+    assert_eq!(buffer.get::<u32>(&["##tag"])?, Some(7));
+    assert_eq!(buffer.get::<u32>(&[hashed("#tag").as_str()])?, Some(7));
+    assert!(buffer.set(&["#tag"], 8u32).is_err());
+    assert!(buffer.set(&["#1234"], 8u32).is_err());
+    let colliding = crate::json_flex::json_decode(alloc::format!(r#"{{"{}": 1, "{}": 2}}"#, hashed_key, long_key))?;
+    assert!(copy.set_json_value(&[], &colliding).is_err());
+
+    // these two keys have the same murmur3 hash, the second hash tells them apart and the second key is refused
+    assert_eq!(murmurhash3_x86_32(b"k85555", SEED), murmurhash3_x86_32(b"k138255", SEED));
+    let mut colliding = factory.empty_buffer(None);
+    colliding.set(&["k85555"], 1u32)?;
+    assert!(colliding.set(&["k138255"], 2u32).is_err());
+    assert_eq!(colliding.get::<u32>(&["k85555"])?, Some(1));
+    assert_eq!(colliding.get::<u32>(&["k138255"])?, None);
+
+    Ok(())
+}
+
+#[test]
+fn canonical_compaction_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "map", "value": {"type": "list", "of": {"type": "table", "columns": [
+        ["a", {"type": "u32"}], ["b", {"type": "u32"}], ["c", {"type": "u32"}], ["d", {"type": "u32"}], ["e", {"type": "string"}]
+    ]}}}"#)?;
+
+    let mut first = factory.empty_buffer(None);
+    first.set(&["x", "0", "a"], 1u32)?;
+    first.set(&["y", "3", "e"], "hello")?;
+    first.set(&["y", "5", "d"], 0u32)?;
+    first.del(&["y", "5", "d"])?;
+
+    // same values in another order, with values that were deleted again
+    let mut second = factory.empty_buffer(None);
+    second.set(&["z", "0", "a"], 9u32)?;
+    second.set(&["y", "1", "e"], "gone")?;
+    second.set(&["y", "3", "e"], "hello")?;
+    second.set(&["y", "3", "b"], 2u32)?;
+    second.set(&["x", "0", "a"], 1u32)?;
+    second.del(&["y", "1"])?;
+    second.del(&["y", "3", "b"])?;
+    second.del(&["z"])?;
+    second.set(&["y", "5", "a"], 3u32)?;
+    second.del(&["y", "5", "a"])?;
+
+    assert_ne!(first.read_bytes(), second.read_bytes());
+    first.compact_canonical(None)?;
+    second.compact_canonical(None)?;
+    assert_eq!(first.json_encode(&[])?.stringify(), second.json_encode(&[])?.stringify());
+    assert_eq!(first.read_bytes(), second.read_bytes());
+
+    // the empty tail item is kept so the length doesn't change
+    assert_eq!(second.length(&["y"])?, Some(6));
+    assert_eq!(second.get::<&str>(&["y", "3", "e"])?, Some("hello"));
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-//! Collections: NP_Table, NP_Tuple, NP_List & NP_Map
+//! Collections: NP_Table, NP_Tuple, NP_List, NP_Map & NP_Log
 
 /// Table data type
 pub mod table;
@@ -7,4 +7,6 @@ pub mod map;
 /// List data type
 pub mod list;
 /// Tuple data type
-pub mod tuple;
\ No newline at end of file
+pub mod tuple;
+/// Log data type
+pub mod log;
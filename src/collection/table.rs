@@ -1,8 +1,9 @@
 use crate::{hashmap::NP_HashMap};
 use alloc::string::String;
-use crate::pointer::{NP_Vtable};
+use crate::pointer::{NP_Vtable, NP_Pointer_Bytes};
 use crate::{pointer::{NP_Cursor}, schema::{NP_Parsed_Schema, NP_Schema_Addr}};
 use crate::{memory::{NP_Memory}, pointer::{NP_Value}, error::NP_Error, schema::{NP_Schema, NP_TypeKeys}, json_flex::{JSMAP, NP_JSON}};
+use crate::crdt::NP_Merge;
 
 use alloc::string::ToString;
 use alloc::vec::Vec;
@@ -10,13 +11,22 @@ use alloc::boxed::Box;
 use alloc::borrow::ToOwned;
 use core::{result::Result, hint::unreachable_unchecked};
 
+// set on a column's compiled schema size when the column is required
+const REQUIRED_BIT: usize = 0x8000;
+// set on a column's compiled schema size when the column is sensitive
+const SENSITIVE_BIT: usize = 0x4000;
+// set on a column's compiled schema size when a merge strategy byte follows the size
+const MERGE_BIT: usize = 0x2000;
+// column count marking the wide layout, normal tables always have at least one column
+const WIDE_MARKER: u8 = 0;
+
 /// The data type for tables in NoProto buffers.
 /// 
 #[doc(hidden)]
 #[derive(Debug)]
 pub struct NP_Table<'table> {
     index: usize,
-    v_table: Option<&'table mut NP_Vtable>,
+    v_table: Option<&'table NP_Vtable>,
     v_table_addr: usize,
     v_table_index: usize,
     table: NP_Cursor
@@ -55,7 +65,11 @@ impl<'table> NP_Table<'table> {
                             let next_vtable = this_vtable.get_next();
 
                             if next_vtable == 0 {
-                                vtable_address = Self::make_next_vtable(this_vtable, memory)?;
+                                // no vtable means no values, don't make one just to look
+                                if !make_path {
+                                    return Ok(None);
+                                }
+                                vtable_address = Self::make_next_vtable(vtable_address, memory)?;
                             } else {
                                 vtable_address = next_vtable as usize;
                             }
@@ -70,8 +84,54 @@ impl<'table> NP_Table<'table> {
                     None => Ok(None)
                 }
             },
-            _ => Err(NP_Error::new("unreachable"))
+            _ => Err(NP_Error::new_static("unreachable"))
+        }
+    }
+
+    /// Cursor for the column at `idx` without creating anything, `None` if the table or the vtable holding the column doesn't exist.
+    #[inline(always)]
+    pub fn select_idx(table_cursor: &NP_Cursor, idx: usize, column_schema: usize, memory: &NP_Memory) -> Option<NP_Cursor> {
+        let mut vtable_address = table_cursor.get_value(memory).get_addr_value() as usize;
+
+        for _ in 0..(idx / 4) {
+            if vtable_address == 0 {
+                return None;
+            }
+            vtable_address = Self::get_vtable(vtable_address, memory).get_next() as usize;
+        }
+
+        if vtable_address == 0 {
+            return None;
+        }
+
+        Some(NP_Cursor::new(vtable_address + ((idx % 4) * 2), column_schema, table_cursor.schema_addr))
+    }
+
+    /// Bitmap of the columns with a value, bit `idx % 8` of byte `idx / 8` is set if column `idx` has one.
+    /// 
+    /// Each vtable is read once, columns past the last vtable are unset without looking at them.
+    pub fn presence(table_cursor: &NP_Cursor, memory: &NP_Memory) -> Vec<u8> {
+        let columns = match &memory.schema[table_cursor.schema_addr] {
+            NP_Parsed_Schema::Table { columns, .. } => columns.len(),
+            _ => 0
+        };
+
+        let mut bitmap = alloc::vec![0u8; columns.div_ceil(8)];
+        let mut vtable_address = table_cursor.get_value(memory).get_addr_value() as usize;
+        let mut idx = 0usize;
+
+        while vtable_address != 0 && idx < columns {
+            let vtable = Self::get_vtable(vtable_address, memory);
+            for value in vtable.values.iter() {
+                if idx < columns && value.get_addr_value() != 0 {
+                    bitmap[idx / 8] |= 1 << (idx % 8);
+                }
+                idx += 1;
+            }
+            vtable_address = vtable.get_next() as usize;
         }
+
+        bitmap
     }
 
     #[inline(always)]
@@ -79,17 +139,19 @@ impl<'table> NP_Table<'table> {
 
         let first_vtable_addr = memory.malloc_borrow(&[0u8; 10])?;
         
-        let table_value = table_cursor.get_value(memory);
+        let table_value = table_cursor.get_value_mut(memory);
         table_value.set_addr_value(first_vtable_addr as u16);
 
         Ok(table_cursor)
     }
 
     #[inline(always)]
-    pub fn make_next_vtable<'make>(prev_vtable: &'make mut NP_Vtable, memory: &'make NP_Memory) -> Result<usize, NP_Error> {
+    pub fn make_next_vtable<'make>(prev_vtable_addr: usize, memory: &'make NP_Memory) -> Result<usize, NP_Error> {
 
         let vtable_addr = memory.malloc_borrow(&[0u8; 10])?;
         
+        // malloc may have moved the buffer, get the previous vtable after
+        let prev_vtable = Self::get_vtable_mut(prev_vtable_addr, memory);
         prev_vtable.set_next(vtable_addr as u16);
 
         Ok(vtable_addr)
@@ -116,11 +178,20 @@ impl<'table> NP_Table<'table> {
     }
 
     #[inline(always)]
-    pub fn get_vtable<'vtable>(v_table_addr: usize, memory: &'vtable NP_Memory) -> &'vtable mut NP_Vtable {
-        if v_table_addr > memory.read_bytes().len() { // attack
-            unsafe { &mut *(memory.write_bytes().as_ptr() as *mut NP_Vtable) }
+    pub fn get_vtable<'vtable>(v_table_addr: usize, memory: &'vtable NP_Memory) -> &'vtable NP_Vtable {
+        if v_table_addr + 10 > memory.read_bytes().len() { // attack
+            unsafe { &*(memory.read_ptr() as *const NP_Vtable) }
         } else { // normal operation
-            unsafe { &mut *(memory.write_bytes().as_ptr().add(v_table_addr) as *mut NP_Vtable) }
+            unsafe { &*(memory.read_ptr().add(v_table_addr) as *const NP_Vtable) }
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_vtable_mut<'vtable>(v_table_addr: usize, memory: &'vtable NP_Memory) -> &'vtable mut NP_Vtable {
+        if v_table_addr + 10 > memory.read_bytes().len() { // attack
+            unsafe { &mut *(memory.bytes_ptr() as *mut NP_Vtable) }
+        } else { // normal operation
+            unsafe { &mut *(memory.bytes_ptr().add(v_table_addr) as *mut NP_Vtable) }
         }
     }
 
@@ -137,11 +208,12 @@ impl<'table> NP_Table<'table> {
                 let v_table =  self.index / 4; // which vtable
                 let v_table_idx = self.index % 4; // which index on the selected vtable
 
-                if self.v_table_index > v_table {
+                if v_table > self.v_table_index {
                     self.v_table_index = v_table;
                     match &self.v_table {
-                        Some(vtable) => {
-                            let next_vtable = vtable.get_next() as usize;
+                        Some(_) => {
+                            // buffer may have moved since the last step, get vtable from address
+                            let next_vtable = Self::get_vtable(self.v_table_addr, memory).get_next() as usize;
                             if next_vtable > 0 {
                                 self.v_table = Some(Self::get_vtable(next_vtable, memory));
                                 self.v_table_addr = next_vtable;
@@ -167,17 +239,112 @@ impl<'table> NP_Table<'table> {
             _ => None
         }
     }
+
+    /// Bytes used by the items of this table, items that are collections with data are added to `pending` instead of measured.
+    pub fn size_items(cursor: &NP_Cursor, memory: &NP_Memory, pending: &mut Vec<NP_Cursor>) -> Result<usize, NP_Error> {
+
+        let c_value = cursor.get_value(memory);
+
+        if c_value.get_addr_value() == 0 {
+            return Ok(0) 
+        }
+
+        let mut acc_size = 0usize;
+
+        let mut nex_vtable = c_value.get_addr_value() as usize;
+
+        // a corrupted next address could loop, never count more vtables than the columns need
+        let mut vtables = match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Table { columns, .. } => columns.len().div_ceil(4),
+            _ => 0
+        };
+
+        while nex_vtable > 0 && nex_vtable + 10 <= memory.read_bytes().len() && vtables > 0 {
+            vtables -= 1;
+            acc_size += 10;
+            let vtable = NP_Table::get_vtable(nex_vtable, memory);
+            nex_vtable = vtable.get_next() as usize;
+        }
+
+        let mut table = NP_Table::new_iter(cursor, memory);
+
+        while let Some((_index, _key, item)) = table.step_iter(memory) {
+            if let Some(real) = item {
+                let add_size = NP_Cursor::size_queue(&real, memory, pending)?;
+                if add_size > 2 {
+                    // scalar cursor is part of vtable
+                    acc_size += add_size - 2;             
+                }
+            }         
+        }
+   
+        Ok(acc_size)
+    }
+
+    /// Copy this table into `to_memory`, the items are added to `pending` and copied once this table is done.
+    pub fn compact_items(from_cursor: NP_Cursor, from_memory: &NP_Memory, mut to_cursor: NP_Cursor, to_memory: &NP_Memory, pending: &mut Vec<(NP_Cursor, NP_Cursor)>) -> Result<NP_Cursor, NP_Error> {
+
+        let from_value = from_cursor.get_value(from_memory);
+
+        if from_value.get_addr_value() == 0 {
+            return Ok(to_cursor) 
+        }
+
+        to_cursor = NP_Table::make_first_vtable(to_cursor, to_memory)?;
+        let to_cursor_value = to_cursor.get_value(to_memory);
+        let mut last_real_vtable = to_cursor_value.get_addr_value() as usize;
+        let mut last_vtable_idx = 0usize;
+
+        let c: Vec<(u16, String, usize)>;
+        let col_schemas = match &from_memory.schema[from_cursor.schema_addr] {
+            NP_Parsed_Schema::Table { columns, .. } => {
+                columns
+            },
+            _ => { c = Vec::new(); &c }
+        };
+
+        let mut table = NP_Table::new_iter(&from_cursor, from_memory);
+
+        while let Some((idx, _key, item)) = table.step_iter(from_memory) {
+            // canonical copies leave out pointers without a value, so vtables don't depend on deleted values
+            let item = item.filter(|x| !to_memory.is_canonical() || x.get_value(from_memory).get_addr_value() != 0);
+           if let Some(real) = item {
+
+                let v_table =  idx / 4; // which vtable
+                let v_table_idx = idx % 4; // which index on the selected vtable
+                
+                while last_vtable_idx < v_table {
+                    last_real_vtable = NP_Table::make_next_vtable(last_real_vtable, to_memory)?;
+                    last_vtable_idx += 1;
+                }
+
+                let item_addr = last_real_vtable + (v_table_idx * 2);
+                NP_Cursor::compact_queue(real, from_memory, NP_Cursor::new(item_addr, col_schemas[idx].2, to_cursor.schema_addr), to_memory, pending)?;
+            }         
+        }
+
+        Ok(to_cursor)
+    }
+
 }
 
 impl<'value> NP_Value<'value> for NP_Table<'value> {
 
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("table", NP_TypeKeys::Table) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("table", NP_TypeKeys::Table) }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("table", NP_TypeKeys::Table) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("table", NP_TypeKeys::Table) }
 
     fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
-        let column_len = bytes[address + 1];
+        // wide tables have a u16 column count and u16 column name lengths
+        let wide = bytes[address + 1] == WIDE_MARKER;
+        let column_len = if wide { u16::from_be_bytes([bytes[address + 2], bytes[address + 3]]) } else { bytes[address + 1] as u16 };
+
+        let mut parsed_columns: Vec<(u16, String,  NP_Schema_Addr)> = Vec::new();
+
+        let mut required: Vec<bool> = Vec::new();
 
-        let mut parsed_columns: Vec<(u8, String,  NP_Schema_Addr)> = Vec::new();
+        let mut sensitive: Vec<bool> = Vec::new();
+
+        let mut merge: Vec<Option<NP_Merge>> = Vec::new();
 
         let table_schema_addr = schema.len();
 
@@ -185,31 +352,48 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
             i: NP_TypeKeys::Table,
             sortable: false,
             columns_mapped: NP_HashMap::new(),
-            columns: Vec::new()
+            columns: Vec::new(),
+            required: Vec::new(),
+            sensitive: Vec::new(),
+            merge: Vec::new(),
+            wide
         });
 
         let mut schema_parsed = schema;
 
-        let mut offset = address + 2;
+        let mut offset = if wide { address + 4 } else { address + 2 };
 
         let mut hash_map = NP_HashMap::new();
 
+        let name_size = if wide { 2 } else { 1 };
+
         for x in 0..column_len as usize {
-            let col_name_len = bytes[offset] as usize;
-            let col_name_bytes = &bytes[(offset + 1)..(offset + 1 + col_name_len)];
+            let col_name_len = if wide { u16::from_be_bytes([bytes[offset], bytes[offset + 1]]) as usize } else { bytes[offset] as usize };
+            let col_name_bytes = &bytes[(offset + name_size)..(offset + name_size + col_name_len)];
             let col_name = unsafe { core::str::from_utf8_unchecked(col_name_bytes) };
 
-            offset += 1 + col_name_len;
+            offset += name_size + col_name_len;
 
             let schema_size = u16::from_be_bytes([
                 bytes[offset],
                 bytes[offset + 1]
             ]) as usize;
 
+            // the three high bits of the column size mark required and sensitive columns, and columns with a merge strategy byte
+            required.push(schema_size & REQUIRED_BIT != 0);
+            sensitive.push(schema_size & SENSITIVE_BIT != 0);
+            if schema_size & MERGE_BIT != 0 {
+                merge.push(NP_Merge::from_u8(bytes[offset + 2]));
+                offset += 1;
+            } else {
+                merge.push(None);
+            }
+            let schema_size = schema_size & !(REQUIRED_BIT | SENSITIVE_BIT | MERGE_BIT);
+
             let column_addr = schema_parsed.len();
             let (_, schema) = NP_Schema::from_bytes(schema_parsed, offset + 2, bytes);
             schema_parsed = schema;
-            parsed_columns.push((x as u8, col_name.to_string(), column_addr));
+            parsed_columns.push((x as u16, col_name.to_string(), column_addr));
             hash_map.insert(col_name, x).unwrap_or_default();
             offset += schema_size + 2;
         }
@@ -218,7 +402,11 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
             i: NP_TypeKeys::Table,
             columns_mapped: hash_map,
             sortable: false,
-            columns: parsed_columns
+            columns: parsed_columns,
+            required: required,
+            sensitive: sensitive,
+            merge: merge,
+            wide
         };
 
         (false, schema_parsed)
@@ -228,12 +416,26 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
         let mut schema_json = JSMAP::new();
         schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
 
+        if let NP_Parsed_Schema::Table { wide: true, .. } = &schema[address] {
+            schema_json.insert("wide".to_owned(), NP_JSON::True);
+        }
+
         let columns: Vec<NP_JSON> = match &schema[address] {
-            NP_Parsed_Schema::Table { columns, .. } => {
+            NP_Parsed_Schema::Table { columns, required, sensitive, merge, .. } => {
                 columns.into_iter().map(|column| {
                     let mut cols: Vec<NP_JSON> = Vec::new();
                     cols.push(NP_JSON::String(column.1.to_string()));
-                    cols.push(NP_Schema::_type_to_json(&schema, column.2).unwrap_or(NP_JSON::Null));
+                    let mut column_json = NP_Schema::_type_to_json(&schema, column.2).unwrap_or(NP_JSON::Null);
+                    if let (Some(true), NP_JSON::Dictionary(map)) = (required.get(column.0 as usize), &mut column_json) {
+                        map.insert("nullable".to_owned(), NP_JSON::False);
+                    }
+                    if let (Some(true), NP_JSON::Dictionary(map)) = (sensitive.get(column.0 as usize), &mut column_json) {
+                        map.insert("sensitive".to_owned(), NP_JSON::True);
+                    }
+                    if let (Some(Some(strategy)), NP_JSON::Dictionary(map)) = (merge.get(column.0 as usize), &mut column_json) {
+                        map.insert("merge".to_owned(), NP_JSON::String(strategy.as_str().to_owned()));
+                    }
+                    cols.push(column_json);
                     NP_JSON::Array(cols)
                 }).collect()
             },
@@ -246,100 +448,18 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
     }
  
     fn get_size(cursor: &NP_Cursor, memory: &'value NP_Memory<'value>) -> Result<usize, NP_Error> {
-
-        let c_value = cursor.get_value(memory);
-
-        if c_value.get_addr_value() == 0 {
-            return Ok(0) 
-        }
-
-        let mut acc_size = 0usize;
-
-        let mut nex_vtable = c_value.get_addr_value() as usize;
-
-        while nex_vtable > 0 {
-            acc_size += 10;
-            let vtable = Self::get_vtable(nex_vtable, memory);
-            nex_vtable = vtable.get_next() as usize;
-        }
-
-        let mut table = Self::new_iter(&cursor, memory);
-
-        while let Some((_index, _key, item)) = table.step_iter(memory) {
-            if let Some(real) = item {
-                let add_size = NP_Cursor::calc_size(&real, memory)?;
-                if add_size > 2 {
-                    // scalar cursor is part of vtable
-                    acc_size += add_size - 2;             
-                }
-            }         
-        }
-   
-        Ok(acc_size)
+        // items are measured without recursion, see `NP_Cursor::calc_size`
+        Ok(NP_Cursor::calc_size(cursor, memory)? - cursor.get_value(memory).get_size())
     }
 
     fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
-
-        let c_value = cursor.get_value(memory);
-
-        if c_value.get_addr_value() == 0 { return NP_JSON::Null };
-
-        let mut json_map = JSMAP::new();
-
-        let mut table = Self::new_iter(&cursor, memory);
-
-        while let Some((_index, key, item)) = table.step_iter(memory) {
-            if let Some(real) = item {
-                json_map.insert(String::from(key), NP_Cursor::json_encode(&real, memory));  
-            } else {
-                json_map.insert(String::from(key), NP_JSON::Null);  
-            }            
-        }
-
-        NP_JSON::Dictionary(json_map)
+        // collections are encoded without recursion, see `NP_Cursor::json_encode`
+        NP_Cursor::json_encode(cursor, memory)
     }
 
-    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, mut to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
-
-        let from_value = from_cursor.get_value(from_memory);
-
-        if from_value.get_addr_value() == 0 {
-            return Ok(to_cursor) 
-        }
-
-        to_cursor = Self::make_first_vtable(to_cursor, to_memory)?;
-        let to_cursor_value = to_cursor.get_value(to_memory);
-        let mut last_real_vtable = to_cursor_value.get_addr_value() as usize;
-        let mut last_vtable_idx = 0usize;
-
-        let c: Vec<(u8, String, usize)>;
-        let col_schemas = match &from_memory.schema[from_cursor.schema_addr] {
-            NP_Parsed_Schema::Table { columns, .. } => {
-                columns
-            },
-            _ => { c = Vec::new(); &c }
-        };
-
-        let mut table = Self::new_iter(&from_cursor, from_memory);
-
-        while let Some((idx, _key, item)) = table.step_iter(from_memory) {
-           if let Some(real) = item {
-
-                let v_table =  idx / 4; // which vtable
-                let v_table_idx = idx % 4; // which index on the selected vtable
-                
-                if last_vtable_idx < v_table {
-                    let vtable_data = Self::get_vtable(last_real_vtable, to_memory);
-                    last_real_vtable = Self::make_next_vtable(vtable_data, to_memory)?;
-                    last_vtable_idx += 1;
-                }
-
-                let item_addr = last_real_vtable + (v_table_idx * 2);
-                NP_Cursor::compact(real.clone(), from_memory, NP_Cursor::new(item_addr, col_schemas[idx].2, to_cursor.schema_addr), to_memory)?;
-            }         
-        }
-
-        Ok(to_cursor)
+    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
+        // items are copied without recursion, see `NP_Cursor::compact`
+        NP_Cursor::compact(from_cursor, from_memory, to_cursor, to_memory)
     }
 
     fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
@@ -352,40 +472,69 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
             i: NP_TypeKeys::Table,
             sortable: false,
             columns: Vec::new(),
-            columns_mapped: NP_HashMap::new()
+            columns_mapped: NP_HashMap::new(),
+            required: Vec::new(),
+            sensitive: Vec::new(),
+            merge: Vec::new(),
+            wide: false
         });
 
+        let wide = matches!(json_schema["wide"], NP_JSON::True);
+        let max_len = if wide { u16::MAX as usize } else { 255 };
+
         let mut columns_mapped = NP_HashMap::new();
 
-        let mut columns: Vec<(u8, String, NP_Schema_Addr)> = Vec::new();
+        let mut columns: Vec<(u16, String, NP_Schema_Addr)> = Vec::new();
 
         let mut column_data: Vec<(String, Vec<u8>)> = Vec::new();
 
+        let mut required: Vec<bool> = Vec::new();
+
+        let mut sensitive: Vec<bool> = Vec::new();
+
+        let mut merge: Vec<Option<NP_Merge>> = Vec::new();
+
         let mut schema_parsed: Vec<NP_Parsed_Schema> = schema;
 
         match &json_schema["columns"] {
             NP_JSON::Array(cols) => {
-                let mut x: u8 = 0;
+                let mut x: u16 = 0;
                 for col in cols {
                     let column_name = match &col[0] {
                         NP_JSON::String(x) => x.clone(),
                         _ => "".to_owned()
                     };
-                    if column_name.len() > 255 {
-                        return Err(NP_Error::new("Table column names cannot be longer than 255 characters!"))
+                    if column_name.len() > max_len {
+                        return Err(NP_Error::new_schema(if wide { "Table column names cannot be longer than 65,535 characters!" } else { "Table column names cannot be longer than 255 characters, use \"wide\": true for more!" }))
                     }
+                    if x as usize == max_len {
+                        return Err(NP_Error::new_schema(if wide { "Tables cannot have more than 65,535 columns!" } else { "Tables cannot have more than 255 columns, use \"wide\": true for more!" }))
+                    }
+
+                    required.push(match (&col[1]["nullable"], &col[1]["required"]) {
+                        (NP_JSON::False, _) => true,
+                        (_, NP_JSON::True) => true,
+                        _ => false
+                    });
+
+                    sensitive.push(matches!(&col[1]["sensitive"], NP_JSON::True));
 
                     let column_schema_addr = schema_parsed.len();
                     columns.push((x, column_name.clone(), column_schema_addr));
                     let (_is_sortable, column_type, schema_p) = NP_Schema::from_json(schema_parsed, &Box::new(col[1].clone()))?;
                     schema_parsed = schema_p;
+                    merge.push(match &col[1]["merge"] {
+                        NP_JSON::String(strategy) => Some(NP_Merge::from_json(strategy, &schema_parsed[column_schema_addr])?),
+                        NP_JSON::Null => None,
+                        _ => return Err(NP_Error::new_schema("Column merge strategies must be strings!"))
+                    });
                     columns_mapped.insert(column_name.as_str(), x as usize)?;
                     column_data.push((column_name, column_type));
                     x += 1;
                 }
             },
             _ => { 
-                return Err(NP_Error::new("Tables require a 'columns' property that is an array of schemas!"))
+                return Err(NP_Error::new_schema("Tables require a 'columns' property that is an array of schemas!"))
             }
         }
 
@@ -393,32 +542,48 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
             i: NP_TypeKeys::Table,
             sortable: false,
             columns: columns,
-            columns_mapped
+            columns_mapped,
+            required: required.clone(),
+            sensitive: sensitive.clone(),
+            merge: merge.clone(),
+            wide
         };
 
-        if column_data.len() > 255 {
-            return Err(NP_Error::new("Tables cannot have more than 255 columns!"))
-        }
-
         if column_data.len() == 0 {
-            return Err(NP_Error::new("Tables must have at least one column!"))
+            return Err(NP_Error::new_schema("Tables must have at least one column!"))
         }
 
         // number of columns
-        schema_bytes.push(column_data.len() as u8);
+        if wide {
+            schema_bytes.push(WIDE_MARKER);
+            schema_bytes.extend((column_data.len() as u16).to_be_bytes().to_vec());
+        } else {
+            schema_bytes.push(column_data.len() as u8);
+        }
 
-        for col in column_data {
+        for (((col, is_required), is_sensitive), strategy) in column_data.into_iter().zip(required).zip(sensitive).zip(merge) {
             // colum name
             let bytes = col.0.as_bytes().to_vec();
-            schema_bytes.push(bytes.len() as u8);
+            if wide {
+                schema_bytes.extend((bytes.len() as u16).to_be_bytes().to_vec());
+            } else {
+                schema_bytes.push(bytes.len() as u8);
+            }
             schema_bytes.extend(bytes);
 
-            if col.1.len() > u16::max as usize {
-                return Err(NP_Error::new("Schema overflow error!"))
+            if col.1.len() >= MERGE_BIT {
+                return Err(NP_Error::new_schema("Schema overflow error!"))
             }
             
             // column type
-            schema_bytes.extend((col.1.len() as u16).to_be_bytes().to_vec());
+            let mut size = col.1.len();
+            if is_required { size |= REQUIRED_BIT }
+            if is_sensitive { size |= SENSITIVE_BIT }
+            if strategy.is_some() { size |= MERGE_BIT }
+            schema_bytes.extend((size as u16).to_be_bytes().to_vec());
+            if let Some(strategy) = strategy {
+                schema_bytes.push(strategy as u8);
+            }
             schema_bytes.extend(col.1);
         }
 
@@ -442,6 +607,48 @@ fn schema_parsing_works() -> Result<(), NP_Error> {
     Ok(())
 }
 
+#[test]
+fn required_columns_work() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"table\",\"columns\":[[\"age\",{\"type\":\"uint8\",\"nullable\":false}],[\"name\",{\"type\":\"string\"}]]}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, compiled.schema.to_json()?.stringify());
+
+    let mut buffer = compiled.empty_buffer(None);
+    buffer.set(&["name"], "hello")?;
+    assert_eq!(buffer.validate(), Err(NP_Error::MissingRequired { paths: [["age".to_owned()].to_vec()].to_vec() }));
+    buffer.set(&["age"], 2u8)?;
+    assert_eq!(buffer.validate(), Ok(()));
+
+    Ok(())
+}
+
+#[test]
+fn sensitive_columns_work() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"table\",\"columns\":[[\"name\",{\"type\":\"string\"}],[\"ssn\",{\"type\":\"string\",\"nullable\":false,\"sensitive\":true}],[\"friends\",{\"type\":\"map\",\"value\":{\"type\":\"table\",\"columns\":[[\"age\",{\"type\":\"uint8\",\"sensitive\":true}],[\"nick\",{\"type\":\"string\"}]]}}]]}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, compiled.schema.to_json()?.stringify());
+
+    let mut buffer = compiled.empty_buffer(None);
+    buffer.set(&["name"], "hello")?;
+    buffer.set(&["ssn"], "123-45-6789")?;
+    buffer.set(&["friends", "bob", "age"], 30u8)?;
+    buffer.set(&["friends", "bob", "nick"], "bobby")?;
+    assert_eq!(buffer.json_encode_redacted(&[], crate::buffer::NP_Redaction::Omit)?.stringify(), "{\"name\":\"hello\",\"friends\":{\"bob\":{\"nick\":\"bobby\"}}}");
+    assert_eq!(buffer.json_encode_redacted(&["friends"], crate::buffer::NP_Redaction::Mask("x"))?.stringify(), "{\"bob\":{\"age\":\"x\",\"nick\":\"bobby\"}}");
+
+    buffer.compact_redacted(None)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "{\"name\":\"hello\",\"ssn\":null,\"friends\":{\"bob\":{\"age\":null,\"nick\":\"bobby\"}}}");
+    assert_eq!(buffer.calc_bytes()?.wasted_bytes, 0);
+
+    Ok(())
+}
+
 #[test]
 fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     let schema = "{\"type\":\"table\",\"columns\":[[\"age\",{\"type\":\"uint8\"}],[\"name\",{\"type\":\"string\"}]]}";
@@ -499,4 +706,84 @@ fn test_vtables() -> Result<(), NP_Error> {
     assert_eq!(new_buffer.get::<u8>(&["rating"])?.unwrap(), 98u8);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn defaults_past_the_first_vtable_work() -> Result<(), NP_Error> {
+    let json = r#"{
+        "type": "table",
+        "columns": [
+            ["a", {"type": "u8"}],
+            ["b", {"type": "u8"}],
+            ["c", {"type": "u8"}],
+            ["d", {"type": "u8"}],
+            ["e", {"type": "bool", "default": true}]
+        ]
+    }"#;
+    let factory = crate::NP_Factory::new(json)?;
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+
+    for factory in [&factory, &compiled].iter() {
+        // only the first vtable exists
+        let mut buffer = factory.empty_buffer(None);
+        buffer.set(&["a"], 1u8)?;
+        assert_eq!(buffer.get::<bool>(&["e"])?, Some(true));
+        assert_eq!(buffer.get_state::<bool>(&["e"])?.is_set(), false);
+        assert_eq!(buffer.get::<u8>(&["d"])?, None);
+        assert!(buffer.get::<u8>(&["e"]).is_err());
+
+        buffer.set(&["e"], false)?;
+        assert_eq!(buffer.get::<bool>(&["e"])?, Some(false));
+    }
+
+    Ok(())
+}
+#[test]
+fn presence_works() -> Result<(), NP_Error> {
+    let columns: Vec<String> = (0..10).map(|x| alloc::format!(r#"["c{}", {{"type": "u8"}}]"#, x)).collect();
+    let factory = crate::NP_Factory::new(alloc::format!(r#"{{"type": "table", "columns": [{}]}}"#, columns.join(",")).as_str())?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["c1"], 1u8)?;
+    assert_eq!(buffer.presence(&[])?, Some(alloc::vec![0b10, 0]));
+
+    // looking up columns past the last vtable doesn't make vtables
+    let size = buffer.read_bytes().len();
+    assert_eq!(buffer.get::<u8>(&["c9"])?, None);
+    assert_eq!(buffer.read_bytes().len(), size);
+
+    buffer.set(&["c9"], 9u8)?;
+    buffer.set(&["c8"], 8u8)?;
+    buffer.set(&["c3"], 3u8)?;
+    assert_eq!(buffer.presence(&[])?, Some(alloc::vec![0b1010, 0b11]));
+    buffer.del(&["c8"])?;
+    assert_eq!(buffer.presence(&[])?, Some(alloc::vec![0b1010, 0b10]));
+    assert!(buffer.presence(&["c1"]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn wide_tables_work() -> Result<(), NP_Error> {
+    let long_name = "n".repeat(300);
+    let mut columns: Vec<String> = (0..400).map(|x| alloc::format!("[\"c{}\",{{\"type\":\"uint16\"}}]", x)).collect();
+    columns.push(alloc::format!("[\"{}\",{{\"type\":\"string\"}}]", long_name));
+    let schema = alloc::format!("{{\"type\":\"table\",\"wide\":true,\"columns\":[{}]}}", columns.join(","));
+
+    assert!(crate::NP_Factory::new(schema.replace("\"wide\":true,", "").as_str()).is_err());
+    let factory = crate::NP_Factory::new(schema.as_str())?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["c300"], 300u16)?;
+    buffer.set(&[long_name.as_str()], "long")?;
+    assert_eq!(buffer.get::<u16>(&["c300"])?, Some(300));
+    assert_eq!(buffer.get::<u16>(&["c299"])?, None);
+    buffer.compact(None)?;
+    assert_eq!(buffer.get::<u16>(&["c300"])?, Some(300));
+    assert_eq!(buffer.get::<&str>(&[long_name.as_str()])?, Some("long"));
+
+    Ok(())
+}
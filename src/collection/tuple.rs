@@ -1,4 +1,3 @@
-use alloc::string::String;
 use crate::utils::opt_err;
 use crate::{ pointer::NP_Vtable};
 use core::hint::unreachable_unchecked;
@@ -18,7 +17,7 @@ use alloc::string::ToString;
 #[derive(Debug)]
 pub struct NP_Tuple<'tuple> {
     index: usize,
-    v_table: Option<&'tuple mut NP_Vtable>,
+    v_table: Option<&'tuple NP_Vtable>,
     v_table_addr: usize,
     v_table_index: usize,
     table: NP_Cursor
@@ -60,7 +59,11 @@ impl<'tuple> NP_Tuple<'tuple> {
                     let next_vtable = this_vtable.get_next();
 
                     if next_vtable == 0 {
-                        vtable_address = Self::make_next_vtable(this_vtable, memory)?;
+                        // later values aren't set, only make vtables for values being set
+                        if !make_path {
+                            return Ok(None);
+                        }
+                        vtable_address = Self::make_next_vtable(vtable_address, memory)?;
                     } else {
                         vtable_address = next_vtable as usize;
                     }
@@ -73,15 +76,33 @@ impl<'tuple> NP_Tuple<'tuple> {
                 Ok(Some(NP_Cursor::new(item_address, column_schema_data, tuple_cursor.schema_addr)))
              
             },
-            _ => Err(NP_Error::new("unreachable"))
+            _ => Err(NP_Error::new_static("unreachable"))
         }
     }
 
+    /// Indexes of the values that are set, in order.  Vtables past the last one are never read.
+    pub fn set_indexes(tuple_cursor: &NP_Cursor, memory: &'tuple NP_Memory) -> Vec<usize> {
+        let mut indexes = Vec::new();
+        let mut tuple = Self::new_iter(tuple_cursor, memory);
+        while let Some((idx, item)) = tuple.step_iter(memory) {
+            match item {
+                Some(item) => {
+                    if item.get_value(memory).get_addr_value() != 0 {
+                        indexes.push(idx);
+                    }
+                },
+                // no more vtables
+                None => break
+            }
+        }
+        indexes
+    }
+
     pub fn make_first_vtable<'make>(table_cursor: NP_Cursor, memory: &'make NP_Memory) -> Result<NP_Cursor, NP_Error> {
 
         let first_vtable_addr = memory.malloc_borrow(&[0u8; 10])?;
         
-        let table_value = table_cursor.get_value(memory);
+        let table_value = table_cursor.get_value_mut(memory);
         table_value.set_addr_value(first_vtable_addr as u16);
 
 
@@ -90,10 +111,9 @@ impl<'tuple> NP_Tuple<'tuple> {
                 if *sortable {
                     // make all the vtables we'll need forever
                     let mut v_table_capacity = 4usize;
-                    let mut vtable = Self::get_vtable(first_vtable_addr, memory);
+                    let mut vtable_addr = first_vtable_addr;
                     while v_table_capacity < values.len() {
-                        let next_addr = Self::make_next_vtable(vtable, memory)?;
-                        vtable = Self::get_vtable(next_addr, memory);
+                        vtable_addr = Self::make_next_vtable(vtable_addr, memory)?;
                         v_table_capacity += 4;
                     }
 
@@ -111,10 +131,12 @@ impl<'tuple> NP_Tuple<'tuple> {
         Ok(table_cursor)
     }
 
-    pub fn make_next_vtable<'make>(prev_vtable: &'make mut NP_Vtable, memory: &'make NP_Memory) -> Result<usize, NP_Error> {
+    pub fn make_next_vtable<'make>(prev_vtable_addr: usize, memory: &'make NP_Memory) -> Result<usize, NP_Error> {
 
         let vtable_addr = memory.malloc_borrow(&[0u8; 10])?;
         
+        // malloc may have moved the buffer, get the previous vtable after
+        let prev_vtable = Self::get_vtable_mut(prev_vtable_addr, memory);
         prev_vtable.set_next(vtable_addr as u16);
 
         Ok(vtable_addr)
@@ -140,11 +162,20 @@ impl<'tuple> NP_Tuple<'tuple> {
     }
 
     #[inline(always)]
-    pub fn get_vtable<'vtable>(v_table_addr: usize, memory: &'vtable NP_Memory) -> &'vtable mut NP_Vtable {
-        if v_table_addr > memory.read_bytes().len() { // attack
-            unsafe { &mut *(memory.write_bytes().as_ptr() as *mut NP_Vtable) }
+    pub fn get_vtable<'vtable>(v_table_addr: usize, memory: &'vtable NP_Memory) -> &'vtable NP_Vtable {
+        if v_table_addr + 10 > memory.read_bytes().len() { // attack
+            unsafe { &*(memory.read_ptr() as *const NP_Vtable) }
         } else { // normal operation
-            unsafe { &mut *(memory.write_bytes().as_ptr().add(v_table_addr) as *mut NP_Vtable) }
+            unsafe { &*(memory.read_ptr().add(v_table_addr) as *const NP_Vtable) }
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_vtable_mut<'vtable>(v_table_addr: usize, memory: &'vtable NP_Memory) -> &'vtable mut NP_Vtable {
+        if v_table_addr + 10 > memory.read_bytes().len() { // attack
+            unsafe { &mut *(memory.bytes_ptr() as *mut NP_Vtable) }
+        } else { // normal operation
+            unsafe { &mut *(memory.bytes_ptr().add(v_table_addr) as *mut NP_Vtable) }
         }
     }
 
@@ -160,11 +191,12 @@ impl<'tuple> NP_Tuple<'tuple> {
                 let v_table =  self.index / 4; // which vtable
                 let v_table_idx = self.index % 4; // which index on the selected vtable
 
-                if self.v_table_index > v_table {
+                if v_table > self.v_table_index {
                     self.v_table_index = v_table;
                     match &self.v_table {
-                        Some(vtable) => {
-                            let next_vtable = vtable.get_next() as usize;
+                        Some(_) => {
+                            // buffer may have moved since the last step, get vtable from address
+                            let next_vtable = Self::get_vtable(self.v_table_addr, memory).get_next() as usize;
                             if next_vtable > 0 {
                                 self.v_table = Some(Self::get_vtable(next_vtable, memory));
                                 self.v_table_addr = next_vtable;
@@ -193,36 +225,9 @@ impl<'tuple> NP_Tuple<'tuple> {
         
     }
 
-}
-
-impl<'value> NP_Value<'value> for NP_Tuple<'value> {
-
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("tuple", NP_TypeKeys::Tuple) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("tuple", NP_TypeKeys::Tuple) }
-
-    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
-        let mut schema_json = JSMAP::new();
-        schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
-
-        let schema_state: (bool, Vec<NP_JSON>) = match &schema[address] {
-            NP_Parsed_Schema::Tuple { i: _, sortable, values } => {
-                (*sortable, values.into_iter().map(|column| {
-                    NP_Schema::_type_to_json(schema, *column).unwrap_or(NP_JSON::Null)
-                }).collect())
-            },
-            _ => (false, Vec::new())
-        };
-
-        schema_json.insert("values".to_owned(), NP_JSON::Array(schema_state.1));
 
-        if schema_state.0 {
-            schema_json.insert("sorted".to_owned(), NP_JSON::True);
-        }
-
-        Ok(NP_JSON::Dictionary(schema_json))
-    }
-
-    fn get_size(cursor: &NP_Cursor, memory: &'value NP_Memory<'value>) -> Result<usize, NP_Error> {
+    /// Bytes used by the items of this tuple, items that are collections with data are added to `pending` instead of measured.
+    pub fn size_items(cursor: &NP_Cursor, memory: &NP_Memory, pending: &mut Vec<NP_Cursor>) -> Result<usize, NP_Error> {
 
         let c_value = cursor.get_value(memory);
 
@@ -234,17 +239,24 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
 
         let mut nex_vtable = c_value.get_addr_value() as usize;
 
-        while nex_vtable > 0 {
+        // a corrupted next address could loop, never count more vtables than the values need
+        let mut vtables = match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Tuple { values, .. } => values.len().div_ceil(4),
+            _ => 0
+        };
+
+        while nex_vtable > 0 && nex_vtable + 10 <= memory.read_bytes().len() && vtables > 0 {
+            vtables -= 1;
             acc_size += 10;
-            let vtable = Self::get_vtable(nex_vtable, memory);
+            let vtable = NP_Tuple::get_vtable(nex_vtable, memory);
             nex_vtable = vtable.get_next() as usize;
         }
 
-        let mut table = Self::new_iter(&cursor, memory);
+        let mut table = NP_Tuple::new_iter(cursor, memory);
 
         while let Some((_index, item)) = table.step_iter(memory) {
             if let Some(real) = item {
-                let add_size = NP_Cursor::calc_size(&real, memory)?;
+                let add_size = NP_Cursor::size_queue(&real, memory, pending)?;
                 if add_size > 2 {
                     // scalar cursor is part of vtable
                     acc_size += add_size - 2;             
@@ -255,29 +267,8 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
         Ok(acc_size)
     }
 
-    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
-
-        let c_value = cursor.get_value(memory);
-
-        if c_value.get_addr_value() == 0 { return NP_JSON::Null };
-
-        let mut json_list = Vec::new();
-
-        let mut table = Self::new_iter(&cursor, memory);
-
-        while let Some((_idx, item)) = table.step_iter(memory) {
-            if let Some(real) = item {
-                json_list.push(NP_Cursor::json_encode(&real, memory));  
-            } else {
-                json_list.push(NP_JSON::Null);  
-            }
-        }
-
-
-        NP_JSON::Array(json_list)
-    }
-
-    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, mut to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
+    /// Copy this tuple into `to_memory`, the items are added to `pending` and copied once this tuple is done.
+    pub fn compact_items(from_cursor: NP_Cursor, from_memory: &NP_Memory, mut to_cursor: NP_Cursor, to_memory: &NP_Memory, pending: &mut Vec<(NP_Cursor, NP_Cursor)>) -> Result<NP_Cursor, NP_Error> {
 
         let from_value = from_cursor.get_value(from_memory);
 
@@ -285,41 +276,86 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
             return Ok(to_cursor) 
         }
 
-        to_cursor = Self::make_first_vtable(to_cursor, to_memory)?;
+        to_cursor = NP_Tuple::make_first_vtable(to_cursor, to_memory)?;
         let to_cursor_value = to_cursor.get_value(to_memory);
         let mut last_real_vtable = to_cursor_value.get_addr_value() as usize;
         let mut last_vtable_idx = 0usize;
 
-        let c: Vec<(u8, String, usize)>;
+        let c: Vec<usize>;
         let col_schemas = match &from_memory.schema[from_cursor.schema_addr] {
-            NP_Parsed_Schema::Table { columns, .. } => {
-                columns
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                values
             },
             _ => { c = Vec::new(); &c }
         };
 
-        let mut table = Self::new_iter(&from_cursor, from_memory);
+        let mut table = NP_Tuple::new_iter(&from_cursor, from_memory);
 
         while let Some((idx, item)) = table.step_iter(from_memory) {
+            // canonical copies leave out pointers without a value, so vtables don't depend on deleted values
+            let item = item.filter(|x| !to_memory.is_canonical() || x.get_value(from_memory).get_addr_value() != 0);
             if let Some(real) = item {
 
                 let v_table =  idx / 4; // which vtable
                 let v_table_idx = idx % 4; // which index on the selected vtable
                 
-                if last_vtable_idx < v_table {
-                    let vtable_data = Self::get_vtable(last_real_vtable, to_memory);
-                    last_real_vtable = Self::make_next_vtable(vtable_data, to_memory)?;
+                while last_vtable_idx < v_table {
+                    last_real_vtable = NP_Tuple::make_next_vtable(last_real_vtable, to_memory)?;
                     last_vtable_idx += 1;
                 }
 
                 let item_addr = last_real_vtable + (v_table_idx * 2);
-                NP_Cursor::compact(real.clone(), from_memory, NP_Cursor::new(item_addr, col_schemas[idx].2, to_cursor.schema_addr), to_memory)?;
+                NP_Cursor::compact_queue(real, from_memory, NP_Cursor::new(item_addr, col_schemas[idx], to_cursor.schema_addr), to_memory, pending)?;
             }            
         }
 
         Ok(to_cursor)
     }
 
+}
+
+impl<'value> NP_Value<'value> for NP_Tuple<'value> {
+
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("tuple", NP_TypeKeys::Tuple) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("tuple", NP_TypeKeys::Tuple) }
+
+    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
+        let mut schema_json = JSMAP::new();
+        schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
+
+        let schema_state: (bool, Vec<NP_JSON>) = match &schema[address] {
+            NP_Parsed_Schema::Tuple { i: _, sortable, values } => {
+                (*sortable, values.into_iter().map(|column| {
+                    NP_Schema::_type_to_json(schema, *column).unwrap_or(NP_JSON::Null)
+                }).collect())
+            },
+            _ => (false, Vec::new())
+        };
+
+        schema_json.insert("values".to_owned(), NP_JSON::Array(schema_state.1));
+
+        if schema_state.0 {
+            schema_json.insert("sorted".to_owned(), NP_JSON::True);
+        }
+
+        Ok(NP_JSON::Dictionary(schema_json))
+    }
+
+    fn get_size(cursor: &NP_Cursor, memory: &'value NP_Memory<'value>) -> Result<usize, NP_Error> {
+        // items are measured without recursion, see `NP_Cursor::calc_size`
+        Ok(NP_Cursor::calc_size(cursor, memory)? - cursor.get_value(memory).get_size())
+    }
+
+    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+        // collections are encoded without recursion, see `NP_Cursor::json_encode`
+        NP_Cursor::json_encode(cursor, memory)
+    }
+
+    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
+        // items are copied without recursion, see `NP_Cursor::compact`
+        NP_Cursor::compact(from_cursor, from_memory, to_cursor, to_memory)
+    }
+
 
     fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
 
@@ -358,13 +394,13 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
                     let (is_sortable, schema_bytes, _schema ) = NP_Schema::from_json(working_schema, &Box::new(col.clone()))?;
                     working_schema = _schema;
                     if sorted && is_sortable == false {
-                        return Err(NP_Error::new("All children of a sorted tuple must be sortable items!"))
+                        return Err(NP_Error::new_schema("All children of a sorted tuple must be sortable items!"))
                     }
                     column_schemas.push(schema_bytes);
                 }
             },
             _ => { 
-                return Err(NP_Error::new("Tuples require a 'values' property that is an array of schemas!"))
+                return Err(NP_Error::new_schema("Tuples require a 'values' property that is an array of schemas!"))
             }
         }
         
@@ -375,7 +411,7 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
         };
 
         if column_schemas.len() > 255 {
-            return Err(NP_Error::new("Tuples cannot have more than 255 values!"))
+            return Err(NP_Error::new_schema("Tuples cannot have more than 255 values!"))
         }
 
         // number of schema values
@@ -384,7 +420,7 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
         for col in column_schemas {
 
             if col.len() > u16::max as usize {
-                return Err(NP_Error::new("Schema overflow error!"))
+                return Err(NP_Error::new_schema("Schema overflow error!"))
             }
             
             // column type
@@ -488,4 +524,28 @@ fn sorting_tuples_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.read_bytes(), &[0u8, 0, 3, 0, 13, 0, 23, 0, 39, 0, 0, 0, 0, 104, 101, 108, 108, 111, 32, 32, 32, 32, 32, 76, 230, 170, 176, 120, 208, 69, 186, 109, 122, 100, 179, 210, 224, 68, 195, 20].to_vec());
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn sparse_tuples_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "tuple", "values": [{"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["1"], 1u8)?;
+    let size = buffer.read_bytes().len();
+
+    // reading later values doesn't make vtables for them
+    assert_eq!(buffer.get::<u8>(&["8"])?, None);
+    assert_eq!(buffer.read_bytes().len(), size);
+    assert_eq!(buffer.tuple_len_set(&[])?, Some(alloc::vec![1]));
+
+    buffer.set(&["8"], 8u8)?;
+    buffer.set(&["2"], 2u8)?;
+    buffer.del(&["1"])?;
+    assert_eq!(buffer.tuple_len_set(&[])?, Some(alloc::vec![2, 8]));
+    assert!(buffer.tuple_len_set(&["2"]).is_err());
+
+    let sorted = crate::NP_Factory::new(r#"{"type": "tuple", "sorted": true, "values": [{"type": "u8"}, {"type": "bool"}]}"#)?;
+    assert_eq!(sorted.empty_buffer(None).tuple_len_set(&[])?, Some(alloc::vec![0, 1]));
+
+    Ok(())
+}
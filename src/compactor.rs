@@ -0,0 +1,156 @@
+//! Compaction in small steps
+//!
+//! `NP_Buffer::compact` copies the whole buffer at once, which can take a while for very large buffers.  `NP_Buffer::into_compactor` returns an `NP_Compactor` that does the same copy a few values at a time, call `step` once per frame or tick until it returns true.
+//!
+//! The buffer can still be read between steps with `NP_Compactor::buffer`, it can't be changed until the compaction is done or cancelled.  `finish` runs any steps that are left and returns the compacted buffer, `cancel` returns the buffer as it was.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "list",
+//!    "of": {"type": "string"}
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! for i in 0..100 {
+//!     new_buffer.set(&[i.to_string().as_str()], "hello")?;
+//!     new_buffer.set(&[i.to_string().as_str()], "hello, world")?;
+//! }
+//! let size = new_buffer.calc_bytes()?;
+//!
+//! let mut compactor = new_buffer.into_compactor(None);
+//! let mut ticks = 0;
+//! while !compactor.step(10)? {
+//!     // the buffer can be read between steps
+//!     assert_eq!(compactor.buffer().get::<&str>(&["99"])?, Some("hello, world"));
+//!     ticks += 1;
+//! }
+//! assert!(ticks > 5);
+//!
+//! let new_buffer = compactor.finish()?;
+//! assert_eq!(new_buffer.read_bytes().len(), size.after_compaction);
+//! assert_eq!(new_buffer.get::<&str>(&["99"])?, Some("hello, world"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::{NP_Buffer, NP_Compact_Flags};
+use crate::error::NP_Error;
+use crate::memory::NP_Memory;
+use crate::pointer::NP_Cursor;
+use alloc::vec::Vec;
+
+/// Compacts a buffer a few values at a time, get one with `NP_Buffer::into_compactor`.
+///
+/// See the [compactor](index.html) module for an example.
+pub struct NP_Compactor<'buffer> {
+    buffer: NP_Buffer<'buffer>,
+    new_capacity: Option<u32>,
+    // the compacted copy made by the first step, and the values still to copy into it
+    copy: Option<(NP_Memory<'buffer>, Vec<(NP_Cursor, NP_Cursor)>)>,
+    done: bool
+}
+
+impl<'buffer> NP_Compactor<'buffer> {
+
+    #[doc(hidden)]
+    pub fn _new(buffer: NP_Buffer<'buffer>, new_capacity: Option<u32>) -> Self {
+        NP_Compactor { buffer, new_capacity, copy: None, done: false }
+    }
+
+    /// The buffer being compacted, as it was before compaction started
+    pub fn buffer(&self) -> &NP_Buffer<'buffer> {
+        &self.buffer
+    }
+
+    /// Copy up to `max_items` values, returns true once the compaction is done.
+    ///
+    /// Every value in a collection is an item, along with the collection itself.  If a step fails the copy is thrown away and the next step starts over.
+    ///
+    pub fn step(&mut self, max_items: usize) -> Result<bool, NP_Error> {
+
+        if self.done {
+            return Ok(true);
+        }
+
+        let (copy, mut pending) = match self.copy.take() {
+            Some(copy) => copy,
+            None => self.buffer.compact_begin(self.new_capacity, None, NP_Compact_Flags::default())?
+        };
+
+        if self.buffer.compact_step(&copy, &mut pending, max_items)? {
+            self.buffer.compact_end(copy);
+            self.done = true;
+        } else {
+            self.copy = Some((copy, pending));
+        }
+
+        Ok(self.done)
+    }
+
+    /// If every step has been run
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Run the steps that are left and return the compacted buffer.
+    ///
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    ///
+    pub fn finish(mut self) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        self.step(usize::MAX)?;
+        Ok(self.buffer)
+    }
+
+    /// Stop compacting, returns the buffer as it was unless every step has been run
+    pub fn cancel(self) -> NP_Buffer<'buffer> {
+        self.buffer
+    }
+}
+
+#[test]
+fn compactor_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["name",  {"type": "string"}],
+        ["tags",  {"type": "list", "of": {"type": "string"}}],
+        ["stats", {"type": "map", "value": {"type": "table", "columns": [["count", {"type": "u32"}]]}}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["name"], "Jeb")?;
+    buffer.set(&["name"], "Jeb Kermin")?;
+    for i in 0..20 {
+        let key = alloc::format!("{}", i);
+        buffer.set(&["tags", key.as_str()], "tag")?;
+        buffer.set(&["stats", key.as_str(), "count"], i as u32)?;
+    }
+    buffer.del(&["tags", "3"])?;
+
+    let mut compacted = factory.open_buffer(buffer.read_bytes().to_vec());
+    compacted.compact(None)?;
+
+    // one item at a time ends up with the same bytes as compacting all at once
+    let mut compactor = buffer.into_compactor(None);
+    let mut steps = 0;
+    while !compactor.step(1)? {
+        steps += 1;
+    }
+    assert!(steps > 40);
+    assert!(compactor.is_done());
+    assert!(compactor.step(1)?);
+    let buffer = compactor.finish()?;
+    assert_eq!(buffer.read_bytes(), compacted.read_bytes());
+    assert_eq!(buffer.get::<&str>(&["name"])?, Some("Jeb Kermin"));
+    assert_eq!(buffer.get::<u32>(&["stats", "19", "count"])?, Some(19));
+
+    // cancelled compactions leave the buffer alone
+    let mut compactor = buffer.into_compactor(None);
+    compactor.step(5)?;
+    let buffer = compactor.cancel();
+    assert_eq!(buffer.read_bytes(), compacted.read_bytes());
+
+    Ok(())
+}
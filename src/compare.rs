@@ -0,0 +1,429 @@
+//! Compare and hash buffer values by their logical content
+
+use crate::pointer::NP_Cursor;
+use crate::memory::NP_Memory;
+use crate::schema::NP_Parsed_Schema;
+use crate::hashmap::{murmurhash3_x86_32, fnv1a_64};
+use crate::collection::{list::NP_List, log::NP_Log, map::NP_Map, table::NP_Table, tuple::NP_Tuple};
+use alloc::vec::Vec;
+use alloc::borrow::Cow;
+use core::cmp::Ordering;
+
+// bytes of a scalar value, None if the value isn't set
+pub fn scalar_bytes<'bytes>(cursor: &NP_Cursor, memory: &'bytes NP_Memory) -> Option<&'bytes [u8]> {
+    let value = cursor.get_value(memory);
+    let addr = value.get_addr_value() as usize;
+
+    if addr == 0 {
+        return None;
+    }
+
+    // inline values are the low byte of the pointer's address
+    if memory.is_inline(cursor.schema_addr) {
+        return memory.read_bytes().get((cursor.buff_addr + 1)..(cursor.buff_addr + 2));
+    }
+
+    let size = NP_Cursor::calc_size(cursor, memory).ok()?.checked_sub(value.get_size())?;
+
+    // tensor padding depends on where the value landed in the buffer, skip it
+    let addr = match memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::Tensor { .. } => addr + (*memory.read_bytes().get(addr)? as usize),
+        _ => addr
+    };
+    let size = size - (addr - value.get_addr_value() as usize);
+
+    memory.read_bytes().get(addr..(addr + size))
+}
+
+pub fn table_items(cursor: &NP_Cursor, memory: &NP_Memory) -> Vec<(usize, NP_Cursor)> {
+    let mut items = Vec::new();
+    let mut table = NP_Table::new_iter(cursor, memory);
+    while let Some((idx, _col, item)) = table.step_iter(memory) {
+        if let Some(item) = item {
+            items.push((idx, item));
+        }
+    }
+    items
+}
+
+pub fn tuple_items(cursor: &NP_Cursor, memory: &NP_Memory) -> Vec<(usize, NP_Cursor)> {
+    let mut items = Vec::new();
+    let mut tuple = NP_Tuple::new_iter(cursor, memory);
+    while let Some((idx, item)) = tuple.step_iter(memory) {
+        if let Some(item) = item {
+            items.push((idx, item));
+        }
+    }
+    items
+}
+
+pub fn list_items(cursor: &NP_Cursor, memory: &NP_Memory) -> Vec<(usize, NP_Cursor)> {
+    let mut items = Vec::new();
+    let mut list = NP_List::new_iter(cursor, memory, true, 0);
+    while let Some((idx, item)) = list.step_iter(memory) {
+        if let Some(item) = item {
+            items.push((idx, item));
+        }
+    }
+    items
+}
+
+pub fn log_items(cursor: &NP_Cursor, memory: &NP_Memory) -> Vec<(usize, NP_Cursor)> {
+    let mut items = Vec::new();
+    let mut log = NP_Log::new_iter(cursor, memory);
+    while let Some((idx, item)) = log.step_iter(memory) {
+        items.push((idx, item));
+    }
+    items
+}
+
+pub fn map_items<'items>(cursor: &NP_Cursor, memory: &'items NP_Memory<'items>) -> Vec<(Cow<'items, str>, NP_Cursor)> {
+    let mut items = Vec::new();
+    let mut map = NP_Map::new_iter(cursor, memory);
+    while let Some((key, item)) = map.step_iter(memory) {
+        items.push((key, item));
+    }
+    items
+}
+
+// items of an indexed collection that hold a value, with their hashes
+fn hashed_items(items: Vec<(usize, NP_Cursor)>, memory: &NP_Memory) -> Vec<(usize, u32)> {
+    items.into_iter().filter_map(|(idx, item)| {
+        hash(&item, memory).map(|hash| (idx, hash))
+    }).collect()
+}
+
+fn indexed_eq(a_items: Vec<(usize, NP_Cursor)>, a_memory: &NP_Memory, b_items: Vec<(usize, NP_Cursor)>, b_memory: &NP_Memory) -> bool {
+    let a_items: Vec<(usize, NP_Cursor)> = a_items.into_iter().filter(|(_, item)| hash(item, a_memory).is_some()).collect();
+    let b_items: Vec<(usize, NP_Cursor)> = b_items.into_iter().filter(|(_, item)| hash(item, b_memory).is_some()).collect();
+
+    if a_items.len() != b_items.len() {
+        return false;
+    }
+
+    a_items.iter().zip(b_items.iter()).all(|((a_idx, a_item), (b_idx, b_item))| {
+        a_idx == b_idx && eq(a_item, a_memory, b_item, b_memory)
+    })
+}
+
+/// Hash the value at this cursor, returns None if there's no value here.
+///
+/// Collections without any values in them hash the same as unset values.
+pub fn hash(cursor: &NP_Cursor, memory: &NP_Memory) -> Option<u32> {
+
+    if cursor.get_value(memory).get_addr_value() == 0 {
+        return None;
+    }
+
+    let schema = &memory.schema[cursor.schema_addr];
+
+    let indexed = |items: Vec<(usize, u32)>| -> Option<u32> {
+        if items.len() == 0 {
+            return None;
+        }
+        let mut bytes: Vec<u8> = Vec::with_capacity(items.len() * 6);
+        for (idx, hash) in items {
+            bytes.extend_from_slice(&(idx as u16).to_be_bytes());
+            bytes.extend_from_slice(&hash.to_be_bytes());
+        }
+        Some(murmurhash3_x86_32(&bytes, *schema.get_type_key() as u32))
+    };
+
+    match schema {
+        NP_Parsed_Schema::Table { .. } => indexed(hashed_items(table_items(cursor, memory), memory)),
+        NP_Parsed_Schema::Tuple { .. } => indexed(hashed_items(tuple_items(cursor, memory), memory)),
+        NP_Parsed_Schema::List  { .. } => indexed(hashed_items(list_items(cursor, memory), memory)),
+        NP_Parsed_Schema::Log   { .. } => indexed(hashed_items(log_items(cursor, memory), memory)),
+        NP_Parsed_Schema::Map   { .. } => {
+            let mut found = false;
+            // adding the item hashes together keeps the result independent of key order
+            let mut acc = 0u32;
+            for (key, item) in map_items(cursor, memory) {
+                if let Some(item_hash) = hash(&item, memory) {
+                    let mut bytes: Vec<u8> = Vec::from(key.as_bytes());
+                    bytes.extend_from_slice(&item_hash.to_be_bytes());
+                    acc = acc.wrapping_add(murmurhash3_x86_32(&bytes, 0));
+                    found = true;
+                }
+            }
+            if found { Some(murmurhash3_x86_32(&acc.to_be_bytes(), *schema.get_type_key() as u32)) } else { None }
+        },
+        _ => {
+            scalar_bytes(cursor, memory).map(|bytes| murmurhash3_x86_32(bytes, *schema.get_type_key() as u32))
+        }
+    }
+}
+
+/// Hash the values at this cursor into a 64 bit hash, returns None if there's no value here.
+///
+/// Values are read in schema order (map items in key order) into one stream, dead space and collections without any values in them are skipped.
+pub fn content_hash(cursor: &NP_Cursor, memory: &NP_Memory) -> Option<u64> {
+    let mut content: Vec<u8> = Vec::new();
+    if !content_bytes(cursor, memory, &mut content) {
+        return None;
+    }
+    Some(fnv1a_64(&content))
+}
+
+// write the values at this cursor in schema order, false if there weren't any
+//
+// collection items start with a 1 byte and collections end with a 0 byte, so nested values can't run into each other
+fn content_bytes(cursor: &NP_Cursor, memory: &NP_Memory, out: &mut Vec<u8>) -> bool {
+
+    if cursor.get_value(memory).get_addr_value() == 0 {
+        return false;
+    }
+
+    let schema = &memory.schema[cursor.schema_addr];
+    let start = out.len();
+    out.push(*schema.get_type_key() as u8);
+
+    match schema {
+        NP_Parsed_Schema::Table { .. } => indexed_content(table_items(cursor, memory), memory, out),
+        NP_Parsed_Schema::Tuple { .. } => indexed_content(tuple_items(cursor, memory), memory, out),
+        NP_Parsed_Schema::List  { .. } => indexed_content(list_items(cursor, memory), memory, out),
+        NP_Parsed_Schema::Log   { .. } => indexed_content(log_items(cursor, memory), memory, out),
+        NP_Parsed_Schema::Map   { .. } => {
+            let mut items = map_items(cursor, memory);
+            items.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+            for (key, item) in items {
+                let item_start = out.len();
+                out.push(1);
+                out.push(key.len() as u8);
+                out.extend_from_slice(key.as_bytes());
+                if !content_bytes(&item, memory, out) {
+                    out.truncate(item_start);
+                }
+            }
+        },
+        _ => {
+            return match scalar_bytes(cursor, memory) {
+                Some(bytes) => {
+                    out.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+                    out.extend_from_slice(bytes);
+                    true
+                },
+                None => {
+                    out.truncate(start);
+                    false
+                }
+            }
+        }
+    }
+
+    if out.len() == start + 1 {
+        out.truncate(start);
+        return false;
+    }
+    out.push(0);
+    true
+}
+
+fn indexed_content(items: Vec<(usize, NP_Cursor)>, memory: &NP_Memory, out: &mut Vec<u8>) {
+    for (idx, item) in items {
+        let item_start = out.len();
+        out.push(1);
+        out.extend_from_slice(&(idx as u32).to_be_bytes());
+        if !content_bytes(&item, memory, out) {
+            out.truncate(item_start);
+        }
+    }
+}
+
+/// Compare the values at two cursors, possibly in different buffers.
+///
+/// Collections without any values in them are equal to unset values.
+pub fn eq(a: &NP_Cursor, a_memory: &NP_Memory, b: &NP_Cursor, b_memory: &NP_Memory) -> bool {
+
+    let a_schema = &a_memory.schema[a.schema_addr];
+    let b_schema = &b_memory.schema[b.schema_addr];
+
+    if a_schema.get_type_key() != b_schema.get_type_key() {
+        return hash(a, a_memory).is_none() && hash(b, b_memory).is_none();
+    }
+
+    match a_schema {
+        NP_Parsed_Schema::Table { .. } => indexed_eq(table_items(a, a_memory), a_memory, table_items(b, b_memory), b_memory),
+        NP_Parsed_Schema::Tuple { .. } => indexed_eq(tuple_items(a, a_memory), a_memory, tuple_items(b, b_memory), b_memory),
+        NP_Parsed_Schema::List  { .. } => indexed_eq(list_items(a, a_memory), a_memory, list_items(b, b_memory), b_memory),
+        NP_Parsed_Schema::Log   { .. } => indexed_eq(log_items(a, a_memory), a_memory, log_items(b, b_memory), b_memory),
+        NP_Parsed_Schema::Map   { .. } => {
+            let a_items: Vec<(Cow<str>, NP_Cursor)> = map_items(a, a_memory).into_iter().filter(|(_, item)| hash(item, a_memory).is_some()).collect();
+            let b_items: Vec<(Cow<str>, NP_Cursor)> = map_items(b, b_memory).into_iter().filter(|(_, item)| hash(item, b_memory).is_some()).collect();
+
+            if a_items.len() != b_items.len() {
+                return false;
+            }
+
+            a_items.iter().all(|(a_key, a_item)| {
+                match b_items.iter().find(|(b_key, _)| a_key == b_key) {
+                    Some((_, b_item)) => eq(a_item, a_memory, b_item, b_memory),
+                    None => false
+                }
+            })
+        },
+        _ => scalar_bytes(a, a_memory) == scalar_bytes(b, b_memory)
+    }
+}
+
+/// Order the values of two sortable tuples, value by value.
+///
+/// Sortable values are stored with a fixed size in an order preserving format, so comparing their bytes gives the same order as comparing the values.  Unset values sort first.
+pub fn cmp_sortable(a: &NP_Cursor, a_memory: &NP_Memory, b: &NP_Cursor, b_memory: &NP_Memory) -> Ordering {
+    let mut a_tuple = NP_Tuple::new_iter(a, a_memory);
+    let mut b_tuple = NP_Tuple::new_iter(b, b_memory);
+
+    loop {
+        match (a_tuple.step_iter(a_memory), b_tuple.step_iter(b_memory)) {
+            (Some((_, a_item)), Some((_, b_item))) => {
+                let a_bytes = a_item.and_then(|item| scalar_bytes(&item, a_memory));
+                let b_bytes = b_item.and_then(|item| scalar_bytes(&item, b_memory));
+                match a_bytes.cmp(&b_bytes) {
+                    Ordering::Equal => { },
+                    order => return order
+                }
+            },
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal
+        }
+    }
+}
+
+#[test]
+fn semantic_eq_works() -> Result<(), crate::error::NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["name", {"type": "string"}],
+        ["tags", {"type": "list", "of": {"type": "string"}}],
+        ["meta", {"type": "map", "value": {"type": "u32"}}]
+    ]}"#)?;
+
+    let mut buffer_a = factory.empty_buffer(None);
+    buffer_a.set(&["name"], "hello")?;
+    buffer_a.set(&["tags", "1"], "tag")?;
+
+    // empty collections match unset ones
+    let mut buffer_b = factory.empty_buffer(None);
+    buffer_b.set(&["meta", "count"], 20u32)?;
+    buffer_b.del(&["meta", "count"])?;
+    buffer_b.set(&["tags", "1"], "tag")?;
+    buffer_b.set(&["name"], "hello")?;
+    assert!(buffer_a.semantic_eq(&buffer_b));
+    assert_eq!(buffer_a.semantic_hash(), buffer_b.semantic_hash());
+
+    // list indexes matter
+    let mut buffer_c = factory.empty_buffer(None);
+    buffer_c.set(&["name"], "hello")?;
+    buffer_c.set(&["tags", "0"], "tag")?;
+    assert!(!buffer_a.semantic_eq(&buffer_c));
+    assert_ne!(buffer_a.semantic_hash(), buffer_c.semantic_hash());
+
+    // so do values
+    buffer_b.set(&["name"], "world")?;
+    assert!(!buffer_a.semantic_eq(&buffer_b));
+
+    Ok(())
+}
+
+#[test]
+fn semantic_eq_survives_compaction() -> Result<(), crate::error::NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["string", {"type": "string"}],
+        ["dec",    {"type": "decimal", "exp": 2}],
+        ["option", {"type": "option", "choices": ["red", "blue"]}],
+        ["list",   {"type": "list", "of": {"type": "string"}}],
+        ["map",    {"type": "map", "value": {"type": "u8"}}],
+        ["tuple",  {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}]
+    ]}"#)?;
+
+    let mut rand = crate::utils::Rand::new(2847);
+
+    for _ in 0..200 {
+        let len = rand.gen_range(0, 256) as usize;
+        let entropy: Vec<u8> = (0..len).map(|_| rand.gen_range(0, 255) as u8).collect();
+
+        let buffer = factory.arbitrary_buffer(&entropy)?;
+        let mut compacted = factory.open_buffer(buffer.read_bytes().to_vec());
+        compacted.compact(None)?;
+
+        // compacting maps reverses their key order
+        assert!(buffer.semantic_eq(&compacted));
+        assert_eq!(buffer.semantic_hash(), compacted.semantic_hash());
+        assert_eq!(buffer.content_hash(), compacted.content_hash());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn content_hash_works() -> Result<(), crate::error::NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["name", {"type": "string"}],
+        ["tags", {"type": "list", "of": {"type": "string"}}],
+        ["meta", {"type": "map", "value": {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}}]
+    ]}"#)?;
+
+    assert_eq!(factory.empty_buffer(None).content_hash(), 0);
+
+    let mut buffer_a = factory.empty_buffer(None);
+    buffer_a.set(&["name"], "hello")?;
+    buffer_a.set(&["meta", "a", "0"], 1u8)?;
+    buffer_a.set(&["meta", "b", "1"], "two")?;
+
+    let mut buffer_b = factory.empty_buffer(None);
+    buffer_b.set(&["tags", "3"], "gone")?;
+    buffer_b.set(&["meta", "b", "1"], "two")?;
+    buffer_b.set(&["meta", "a", "0"], 1u8)?;
+    buffer_b.set(&["name"], "hello")?;
+    buffer_b.del(&["tags", "3"])?;
+    assert_eq!(buffer_a.content_hash(), buffer_b.content_hash());
+    buffer_b.compact(None)?;
+    assert_eq!(buffer_a.content_hash(), buffer_b.content_hash());
+
+    // values moved between keys, indexes or columns change the hash
+    let mut buffer_c = factory.empty_buffer(None);
+    buffer_c.set(&["name"], "hello")?;
+    buffer_c.set(&["meta", "b", "0"], 1u8)?;
+    buffer_c.set(&["meta", "a", "1"], "two")?;
+    assert_ne!(buffer_a.content_hash(), buffer_c.content_hash());
+
+    let mut buffer_d = factory.empty_buffer(None);
+    buffer_d.set(&["name"], "hellotwo")?;
+    buffer_d.set(&["meta", "a", "0"], 1u8)?;
+    assert_ne!(buffer_a.content_hash(), buffer_d.content_hash());
+
+    Ok(())
+}
+
+#[test]
+fn cmp_sortable_matches_sortable_bytes() -> Result<(), crate::error::NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "tuple", "sorted": true, "values": [
+        {"type": "i16"},
+        {"type": "bool"},
+        {"type": "string", "size": 4},
+        {"type": "u32"},
+        {"type": "i8"}
+    ]}"#)?;
+
+    let mut rand = crate::utils::Rand::new(2848);
+
+    for _ in 0..500 {
+        let entropy_a: Vec<u8> = (0..24).map(|_| rand.gen_range(0, 255) as u8).collect();
+        let entropy_b: Vec<u8> = (0..24).map(|_| rand.gen_range(0, 255) as u8).collect();
+
+        let buffer_a = factory.arbitrary_buffer(&entropy_a)?;
+        let buffer_b = factory.arbitrary_buffer(&entropy_b)?;
+        let order = buffer_a.cmp_sortable(&buffer_b)?;
+
+        assert_eq!(order, buffer_a.close_sortable()?.cmp(&buffer_b.close_sortable()?));
+    }
+
+    // only sortable buffers with the same schema can be compared
+    let other = crate::NP_Factory::new(r#"{"type": "tuple", "sorted": true, "values": [{"type": "i16"}]}"#)?;
+    assert!(factory.empty_buffer(None).cmp_sortable(&other.empty_buffer(None)).is_err());
+
+    let unsorted = crate::NP_Factory::new(r#"{"type": "tuple", "values": [{"type": "i16"}]}"#)?;
+    assert!(unsorted.empty_buffer(None).cmp_sortable(&unsorted.empty_buffer(None)).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,282 @@
+//! Merging replicated buffers
+//!
+//! Table columns can declare how `NP_Buffer::crdt_merge` resolves two different values for the column with a `merge` property in the schema:
+//!
+//! | Strategy    | Types                     | Merged value                                                                 |
+//! |-------------|---------------------------|------------------------------------------------------------------------------|
+//! | `lww`       | any                       | The value from the second (newer) buffer, unless it's unset                   |
+//! | `max`       | numbers, `date`           | The larger value                                                             |
+//! | `sum`       | numbers                   | Both values added together                                                   |
+//! | `set_union` | `list`, `map`             | Every list item from both buffers once, or every map key (second buffer wins) |
+//!
+//! Columns without a strategy use `lww`, except tables which are merged column by column.
+//!
+//! `lww` columns use tombstones (see the [tombstone](../tombstone/index.html) module) to tell deletes apart from values that were never set.  If the newer buffer has a tombstone for a column and no value, the column is deleted in the merged buffer too.  Tombstones from both buffers are kept in the merged buffer, with the newest timestamp for each path.
+//!
+//! `sum` adds the whole value of both buffers, so it's meant for buffers that only hold the changes made since the last merge.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::buffer::NP_Buffer;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["title",  {"type": "string"}],
+//!         ["high",   {"type": "u32", "merge": "max"}],
+//!         ["views",  {"type": "u32", "merge": "sum"}],
+//!         ["tags",   {"type": "list", "of": {"type": "string"}, "merge": "set_union"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut phone = factory.empty_buffer(None);
+//! phone.set(&["title"], "Draft")?;
+//! phone.set(&["high"], 20u32)?;
+//! phone.set(&["views"], 3u32)?;
+//! phone.list_push(&["tags"], "rocket")?;
+//!
+//! let mut laptop = factory.empty_buffer(None);
+//! laptop.set(&["title"], "Final")?;
+//! laptop.set(&["high"], 12u32)?;
+//! laptop.set(&["views"], 4u32)?;
+//! laptop.list_push(&["tags"], "space")?;
+//! laptop.list_push(&["tags"], "rocket")?;
+//!
+//! let merged = NP_Buffer::crdt_merge(&phone, &laptop)?;
+//! assert_eq!(merged.json_encode(&[])?.stringify(), r#"{"title":"Final","high":20,"views":7,"tags":["rocket","space"]}"#);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::hashmap::murmurhash3_x86_32;
+use crate::json_flex::{JSMAP, NP_JSON};
+use crate::schema::{NP_Parsed_Schema, NP_TypeKeys};
+use crate::tombstone::NP_Tombstone;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// How a table column is merged by `NP_Buffer::crdt_merge`, see the [crdt](index.html) module
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum NP_Merge {
+    /// Last writer wins, the value from the second buffer
+    Lww = 1,
+    /// The larger number
+    Max = 2,
+    /// Both numbers added together
+    Sum = 3,
+    /// Every list item or map key from both buffers
+    SetUnion = 4
+}
+
+impl NP_Merge {
+
+    /// The name of this strategy in schemas
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NP_Merge::Lww => "lww",
+            NP_Merge::Max => "max",
+            NP_Merge::Sum => "sum",
+            NP_Merge::SetUnion => "set_union"
+        }
+    }
+
+    pub(crate) fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(NP_Merge::Lww),
+            2 => Some(NP_Merge::Max),
+            3 => Some(NP_Merge::Sum),
+            4 => Some(NP_Merge::SetUnion),
+            _ => None
+        }
+    }
+
+    /// Parse a strategy from a schema and check it works with the column type
+    pub(crate) fn from_json(name: &str, schema: &NP_Parsed_Schema) -> Result<Self, NP_Error> {
+        let strategy = match name {
+            "lww" => NP_Merge::Lww,
+            "max" => NP_Merge::Max,
+            "sum" => NP_Merge::Sum,
+            "set_union" => NP_Merge::SetUnion,
+            _ => return Err(NP_Error::new_schema("Merge strategy must be \"lww\", \"max\", \"sum\" or \"set_union\"!"))
+        };
+
+        let number = matches!(schema.get_type_key(),
+            NP_TypeKeys::Int8 | NP_TypeKeys::Int16 | NP_TypeKeys::Int32 | NP_TypeKeys::Int64 |
+            NP_TypeKeys::Uint8 | NP_TypeKeys::Uint16 | NP_TypeKeys::Uint32 | NP_TypeKeys::Uint64 |
+            NP_TypeKeys::Float | NP_TypeKeys::Double
+        );
+
+        let works = match strategy {
+            NP_Merge::Lww => true,
+            NP_Merge::Max => number || *schema.get_type_key() == NP_TypeKeys::Date,
+            NP_Merge::Sum => number,
+            NP_Merge::SetUnion => matches!(schema.get_type_key(), NP_TypeKeys::List | NP_TypeKeys::Map)
+        };
+
+        if works {
+            Ok(strategy)
+        } else {
+            Err(NP_Error::new_schema(String::from("The ") + name + " merge strategy can't be used with " + schema.get_type_data().0 + " columns!"))
+        }
+    }
+}
+
+/// Merge the JSON of two buffers (from the root) with the strategies in the schema
+pub(crate) fn merge(schema: &Vec<NP_Parsed_Schema>, a: &NP_JSON, b: &NP_JSON, b_tombstones: &[NP_Tombstone]) -> Result<NP_JSON, NP_Error> {
+    merge_value(schema, 0, None, a, b, crate::hashmap::SEED, b_tombstones)
+}
+
+/// Every tombstone from both buffers, with the newest timestamp for each path.  Newest first, like `NP_Buffer::tombstones`.
+pub(crate) fn merge_tombstones(a: &[NP_Tombstone], b: &[NP_Tombstone]) -> Vec<NP_Tombstone> {
+    let mut merged: Vec<NP_Tombstone> = Vec::new();
+    for tombstone in a.iter().chain(b.iter()) {
+        match merged.iter_mut().find(|other| other.path_hash == tombstone.path_hash) {
+            Some(other) => other.timestamp = core::cmp::max(other.timestamp, tombstone.timestamp),
+            None => merged.push(*tombstone)
+        }
+    }
+    merged
+}
+
+fn merge_value(schema: &Vec<NP_Parsed_Schema>, address: usize, strategy: Option<NP_Merge>, a: &NP_JSON, b: &NP_JSON, path_hash: u32, b_tombstones: &[NP_Tombstone]) -> Result<NP_JSON, NP_Error> {
+    let is_null = |json: &NP_JSON| matches!(json, NP_JSON::Null);
+
+    // one side unset, nothing to resolve
+    match strategy {
+        Some(NP_Merge::Max) | Some(NP_Merge::Sum) | Some(NP_Merge::SetUnion) if is_null(a) || is_null(b) => {
+            return Ok(if is_null(b) { a.clone() } else { b.clone() });
+        },
+        _ => { }
+    }
+
+    match (strategy, &schema[address]) {
+        (None, NP_Parsed_Schema::Table { columns, merge, .. }) if !is_null(a) || !is_null(b) => {
+            let mut merged = JSMAP::new();
+            for (idx, name, column_addr) in columns.iter() {
+                let column_hash = murmurhash3_x86_32(name.as_bytes(), path_hash);
+                let value = merge_value(schema, *column_addr, merge[*idx as usize], &a[name.as_str()], &b[name.as_str()], column_hash, b_tombstones)?;
+                merged.insert(name.clone(), value);
+            }
+            Ok(NP_JSON::Dictionary(merged))
+        },
+        (None, _) | (Some(NP_Merge::Lww), _) => {
+            if !is_null(b) || b_tombstones.iter().any(|tombstone| tombstone.path_hash == path_hash) {
+                Ok(b.clone())
+            } else {
+                Ok(a.clone())
+            }
+        },
+        (Some(NP_Merge::Max), _) => {
+            let a_larger = match (a, b) {
+                (NP_JSON::Float(_), _) | (_, NP_JSON::Float(_)) => number(a) as f64 >= number(b) as f64,
+                _ => number(a) >= number(b)
+            };
+            Ok(if a_larger { a.clone() } else { b.clone() })
+        },
+        (Some(NP_Merge::Sum), _) => {
+            match (a, b) {
+                (NP_JSON::Float(_), _) | (_, NP_JSON::Float(_)) => Ok(NP_JSON::Float(float(a) + float(b))),
+                _ => {
+                    let sum = number(a) + number(b);
+                    if sum >= i64::MIN as i128 && sum <= i64::MAX as i128 {
+                        Ok(NP_JSON::Integer(sum as i64))
+                    } else if sum >= 0 && sum <= u64::MAX as i128 {
+                        Ok(NP_JSON::Unsigned(sum as u64))
+                    } else {
+                        Err(NP_Error::new_static("Merged sum doesn't fit in a 64 bit integer!"))
+                    }
+                }
+            }
+        },
+        (Some(NP_Merge::SetUnion), _) => {
+            match (a, b) {
+                (NP_JSON::Array(a_items), NP_JSON::Array(b_items)) => {
+                    let mut seen: Vec<String> = Vec::new();
+                    let mut merged: Vec<NP_JSON> = Vec::new();
+                    for item in a_items.iter().chain(b_items.iter()) {
+                        if is_null(item) { continue; }
+                        let text = item.stringify();
+                        if !seen.contains(&text) {
+                            seen.push(text);
+                            merged.push(item.clone());
+                        }
+                    }
+                    Ok(NP_JSON::Array(merged))
+                },
+                (NP_JSON::Dictionary(a_map), NP_JSON::Dictionary(b_map)) => {
+                    let mut merged = JSMAP::new();
+                    for (key, value) in a_map.values.iter().chain(b_map.values.iter()) {
+                        merged.insert(key.clone(), value.clone());
+                    }
+                    Ok(NP_JSON::Dictionary(merged))
+                },
+                _ => Ok(b.clone())
+            }
+        }
+    }
+}
+
+fn number(json: &NP_JSON) -> i128 {
+    match json {
+        NP_JSON::Integer(x) => *x as i128,
+        NP_JSON::Unsigned(x) => *x as i128,
+        NP_JSON::Float(x) => *x as i128,
+        _ => 0
+    }
+}
+
+fn float(json: &NP_JSON) -> f64 {
+    match json {
+        NP_JSON::Float(x) => *x,
+        _ => number(json) as f64
+    }
+}
+
+#[test]
+fn crdt_merge_works() -> Result<(), NP_Error> {
+    use crate::buffer::NP_Buffer;
+
+    assert!(crate::NP_Factory::new(r#"{"type": "table", "columns": [["a", {"type": "string", "merge": "sum"}]]}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type": "table", "columns": [["a", {"type": "u8", "merge": "set_union"}]]}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type": "table", "columns": [["a", {"type": "u8", "merge": "avg"}]]}"#).is_err());
+
+    let schema = r#"{"type":"table","columns":[["name",{"type":"string","merge":"lww"}],["score",{"type":"double","merge":"max"}],["count",{"type":"uint64","merge":"sum"}],["meta",{"type":"table","columns":[["color",{"type":"string"}],["size",{"type":"uint8"}]]}],["seen",{"type":"map","value":{"type":"bool"},"merge":"set_union"}]]}"#;
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, compiled.schema.to_json()?.stringify());
+
+    let mut a = compiled.empty_buffer(None);
+    a.set(&["name"], "a")?;
+    a.set(&["score"], 2.5f64)?;
+    a.set(&["count"], u64::MAX - 1)?;
+    a.set(&["meta", "color"], "red")?;
+    a.set(&["meta", "size"], 3u8)?;
+    a.set(&["seen", "x"], true)?;
+
+    let mut b = compiled.empty_buffer(None);
+    b.track_deletes(|| 10)?;
+    b.set(&["score"], 1.5f64)?;
+    b.set(&["count"], 1u64)?;
+    b.set(&["meta", "size"], 4u8)?;
+    b.del(&["meta", "color"])?;
+    b.set(&["seen", "x"], false)?;
+    b.set(&["seen", "y"], true)?;
+
+    // deleted in b, never set in b, and merged column by column
+    let merged = NP_Buffer::crdt_merge(&a, &b)?;
+    assert_eq!(merged.json_encode(&[])?.stringify(), r#"{"name":"a","score":2.5,"count":18446744073709551615,"meta":{"color":null,"size":4},"seen":{"y":true,"x":false}}"#);
+    assert_eq!(merged.tombstones(), b.tombstones());
+
+    b.set(&["count"], 2u64)?;
+    assert!(NP_Buffer::crdt_merge(&a, &b).is_err());
+
+    let other = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u8"}}"#)?;
+    assert!(NP_Buffer::crdt_merge(&a, &other.empty_buffer(None)).is_err());
+
+    Ok(())
+}
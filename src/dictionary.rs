@@ -0,0 +1,192 @@
+//! Shared dictionary compression for small, similar buffers
+//!
+//! Compressed bytes are a list of tokens, each starting with a varint.  The lowest bit of the varint is set for a copy and clear for literal bytes, the rest is the length.  Literal tokens are followed by the bytes themselves, copy tokens by a varint with how far back the copy starts.  Copies can reach back into the dictionary, it's treated as if it came right before the bytes.
+
+use crate::error::NP_Error;
+use alloc::collections::{BTreeMap, BTreeSet, BinaryHeap};
+use alloc::vec::Vec;
+
+// bytes in a row used to find shared data while building a dictionary
+const GRAM_LEN: usize = 6;
+// samples are cut into pieces this long to pick the dictionary from
+const SEGMENT_LEN: usize = 32;
+// shortest copy worth a token
+const MIN_MATCH: usize = 4;
+// buffers can't be larger than their u16 address space
+const MAX_LEN: usize = u16::MAX as usize;
+
+/// Pick up to `size` bytes that show up in many of the samples.
+pub(crate) fn build(samples: &[&[u8]], size: usize) -> Vec<u8> {
+    // how many samples each gram shows up in
+    let mut counts: BTreeMap<&[u8], u32> = BTreeMap::new();
+    for sample in samples {
+        let mut seen: BTreeSet<&[u8]> = BTreeSet::new();
+        for gram in sample.windows(GRAM_LEN) {
+            if seen.insert(gram) {
+                *counts.entry(gram).or_insert(0) += 1;
+            }
+        }
+    }
+
+    // segments are worth as much as the grams they share with other samples, grams already in the dictionary are worth nothing
+    let worth = |segment: &[u8], covered: &BTreeSet<&[u8]>| -> u32 {
+        segment.windows(GRAM_LEN).filter(|gram| !covered.contains(gram)).map(|gram| counts.get(gram).unwrap_or(&1) - 1).sum()
+    };
+
+    let mut covered: BTreeSet<&[u8]> = BTreeSet::new();
+    let mut segments: BinaryHeap<(u32, &[u8])> = BinaryHeap::new();
+    for sample in samples {
+        for segment in sample.chunks(SEGMENT_LEN) {
+            segments.push((worth(segment, &covered), segment));
+        }
+    }
+
+    // take the best segment, scoring it again if segments taken since made it worth less
+    let mut dictionary: Vec<u8> = Vec::new();
+    while let Some((score, segment)) = segments.pop() {
+        let current = worth(segment, &covered);
+        if score == 0 || dictionary.len() + segment.len() > size {
+            continue;
+        }
+        if current < score {
+            segments.push((current, segment));
+            continue;
+        }
+        dictionary.extend_from_slice(segment);
+        covered.extend(segment.windows(GRAM_LEN));
+    }
+    dictionary
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], pos: &mut usize) -> Result<usize, NP_Error> {
+    let mut value = 0usize;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or_else(|| NP_Error::new_static("Compressed bytes are truncated!"))?;
+        *pos += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(NP_Error::new_static("Compressed bytes have a bad length!"))
+}
+
+fn write_literals(out: &mut Vec<u8>, literals: &[u8]) {
+    if !literals.is_empty() {
+        write_varint(out, literals.len() << 1);
+        out.extend_from_slice(literals);
+    }
+}
+
+/// Compress bytes against a dictionary
+pub(crate) fn compress(dictionary: &[u8], bytes: &[u8]) -> Vec<u8> {
+    let mut history: Vec<u8> = Vec::with_capacity(dictionary.len() + bytes.len());
+    history.extend_from_slice(dictionary);
+    history.extend_from_slice(bytes);
+
+    // last place each 4 bytes were seen
+    let mut seen: BTreeMap<&[u8], usize> = BTreeMap::new();
+    for pos in 0..(dictionary.len() + 1).saturating_sub(MIN_MATCH) {
+        seen.insert(&history[pos..(pos + MIN_MATCH)], pos);
+    }
+
+    let mut out: Vec<u8> = Vec::new();
+    let mut pos = dictionary.len();
+    let mut literals = pos;
+    while pos < history.len() {
+        let mut found: Option<(usize, usize)> = None;
+        if pos + MIN_MATCH <= history.len() {
+            if let Some(&candidate) = seen.get(&history[pos..(pos + MIN_MATCH)]) {
+                // copies can overlap the bytes they make
+                let len = history[candidate..].iter().zip(history[pos..].iter()).take_while(|(a, b)| a == b).count();
+                found = Some((len, pos - candidate));
+            }
+            seen.insert(&history[pos..(pos + MIN_MATCH)], pos);
+        }
+
+        match found {
+            Some((len, distance)) => {
+                write_literals(&mut out, &history[literals..pos]);
+                write_varint(&mut out, (len << 1) | 1);
+                write_varint(&mut out, distance);
+                for skipped in (pos + 1)..core::cmp::min(pos + len, (history.len() + 1).saturating_sub(MIN_MATCH)) {
+                    seen.insert(&history[skipped..(skipped + MIN_MATCH)], skipped);
+                }
+                pos += len;
+                literals = pos;
+            },
+            None => pos += 1
+        }
+    }
+    write_literals(&mut out, &history[literals..]);
+    out
+}
+
+/// Undo `compress` with the same dictionary
+pub(crate) fn decompress(dictionary: &[u8], bytes: &[u8]) -> Result<Vec<u8>, NP_Error> {
+    let mut history: Vec<u8> = dictionary.to_vec();
+    let mut pos = 0usize;
+    while pos < bytes.len() {
+        let token = read_varint(bytes, &mut pos)?;
+        let len = token >> 1;
+        if history.len() - dictionary.len() + len > MAX_LEN {
+            return Err(NP_Error::new_static("Compressed bytes are too large for a buffer!"));
+        }
+
+        if token & 1 == 0 {
+            let literals = bytes.get(pos..(pos + len)).ok_or_else(|| NP_Error::new_static("Compressed bytes are truncated!"))?;
+            history.extend_from_slice(literals);
+            pos += len;
+        } else {
+            let distance = read_varint(bytes, &mut pos)?;
+            if distance == 0 || distance > history.len() {
+                return Err(NP_Error::new_static("Compressed bytes copy from outside the dictionary!"));
+            }
+            let start = history.len() - distance;
+            for i in 0..len {
+                history.push(history[start + i]);
+            }
+        }
+    }
+    Ok(history.split_off(dictionary.len()))
+}
+
+#[test]
+fn dictionary_works() -> Result<(), NP_Error> {
+    let samples: Vec<Vec<u8>> = (0..20u8).map(|i| {
+        let mut sample = b"{\"name\":\"user\",\"email\":\"someone@example.com\",\"id\":".to_vec();
+        sample.push(i);
+        sample
+    }).collect();
+    let slices: Vec<&[u8]> = samples.iter().map(|sample| sample.as_slice()).collect();
+
+    let dictionary = build(&slices, 64);
+    assert!(!dictionary.is_empty() && dictionary.len() <= 64);
+    assert!(build(&slices, 0).is_empty());
+
+    for sample in samples.iter() {
+        let compressed = compress(&dictionary, sample);
+        assert!(compressed.len() < sample.len() / 2);
+        assert_eq!(decompress(&dictionary, &compressed)?, *sample);
+    }
+
+    // repeats and no dictionary at all
+    let repeated = [7u8; 300];
+    assert_eq!(decompress(&[], &compress(&[], &repeated))?, repeated.to_vec());
+    assert_eq!(decompress(&[], &compress(&[], &[]))?, Vec::new());
+
+    // copies from before the dictionary and runaway lengths fail
+    assert!(decompress(&dictionary, &[9, 200, 1]).is_err());
+    assert!(decompress(&[], &[0xFF, 0xFF, 0x0F]).is_err());
+    assert!(decompress(&[], &[6, 1]).is_err());
+
+    Ok(())
+}
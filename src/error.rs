@@ -1,27 +1,317 @@
 //! Primary error type used by the library
+//!
+//! Errors are split into a few kinds so callers can match on what went wrong instead of parsing the message.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//!
+//! let path = ["name"];
+//! let error = new_buffer.set(&path, 20u8).unwrap_err();
+//! match error {
+//!     NP_Error::TypeMismatch { expected, found, .. } => {
+//!         assert_eq!(expected, "string");
+//!         assert_eq!(found, "uint8");
+//!     },
+//!     _ => panic!()
+//! }
+//! // the keys of the path that lead to the mismatch
+//! assert_eq!(error.path_in(&path), Some(&["name"][..]));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! Every kind of error has a stable number from `NP_Error::code`, for logs or FFI callers that can't match on the enum.  Errors the library returns with a fixed message use `NP_Error::Static`, so making them never allocates.  That keeps `no_std` targets and hot paths that check for an error and discard it from paying for a message nobody reads.
+//!
+//! ```
+//! use no_proto::error::{NP_Error, NP_Error_Code};
+//!
+//! let error = NP_Error::new_static("Nothing to see here!");
+//! assert_eq!(error.code(), NP_Error_Code::Other);
+//! assert_eq!(error.code() as u16, 14);
+//! assert_eq!(NP_Error::OutOfSpace.code() as u16, 2);
+//! ```
+//!
+//! Before 0.7 `NP_Error` was a struct with a public `message` field.  Code that read `error.message` can call `error.message()` while it moves over to `to_string()` or a match on the kind, the messages are the same as they were.  New kinds may be added in minor versions, so matches on `NP_Error` need a `_` arm.
 
 use alloc::string::FromUtf8Error;
 use alloc::string::String;
+use alloc::vec::Vec;
 use alloc::borrow::ToOwned;
 use alloc::string::ToString;
 
 /// The error type used for errors in this library
-#[derive(Debug)]
-pub struct NP_Error {
-    /// The message of this error
-    pub message: String
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum NP_Error {
+    /// The type used to set or get a value doesn't match the type in the schema
+    TypeMismatch {
+        /// The type the schema expects at this path
+        expected: &'static str,
+        /// The type that was provided
+        found: &'static str,
+        /// How many keys of the path given to the call (relative to the buffer cursor) lead to the mismatch, see `NP_Error::path_in`
+        depth: usize,
+        /// True if a value was being set, false if it was being read
+        write: bool
+    },
+    /// The buffer has run out of address space
+    OutOfSpace,
+    /// A write was refused before anything was changed, it could make the buffer larger than its max size
+    TooLarge {
+        /// The path (relative to the buffer cursor) of the write
+        path: Vec<String>,
+        /// The most bytes the write could need
+        needed: usize,
+        /// The bytes left before the buffer reaches its max size
+        available: usize
+    },
+    /// The schema provided could not be parsed
+    InvalidSchema {
+        /// Details about why the schema is invalid
+        message: String
+    },
+    /// A collection operation was attempted on a path that isn't a collection
+    PathNotCollection {
+        /// The path (relative to the buffer cursor) that isn't a collection
+        path: Vec<String>,
+        /// What was attempted on the path
+        reason: &'static str
+    },
+    /// The buffer contains data that can't be read safely
+    CorruptBuffer {
+        /// The path to the first unreadable value
+        path: Vec<String>,
+        /// Why the value couldn't be read
+        reason: String
+    },
+    /// Required values in the buffer are unset
+    MissingRequired {
+        /// The path to every required value that isn't set
+        paths: Vec<Vec<String>>
+    },
+    /// An increment doesn't fit in the integer type of the schema
+    Overflow {
+        /// The path (relative to the buffer cursor) of the integer
+        path: Vec<String>
+    },
+    /// A value of another type can't be converted into the type of the schema without losing precision
+    Lossy {
+        /// The type the schema expects at this path
+        expected: String,
+        /// The path (relative to the buffer cursor) of the value
+        path: Vec<String>
+    },
+    /// A path outside of a buffer view was used
+    AccessDenied {
+        /// The path (relative to the buffer root) that the view can't access
+        path: Vec<String>
+    },
+    /// A conditional update was attempted on a buffer that changed since its etag was read
+    EtagMismatch {
+        /// The etag the update expected
+        expected: u64,
+        /// The etag of the buffer
+        found: u64
+    },
+    /// A buffer holds the fingerprint of a different schema than the factory opening it
+    SchemaMismatch {
+        /// The fingerprint of the factory's schema
+        expected: u64,
+        /// The fingerprint stored in the buffer
+        found: u64
+    },
+    /// Reading the buffer would go past one of its `NP_Limits`
+    LimitExceeded {
+        /// Name of the limit, `max_depth`, `max_steps` or `max_json_size`
+        limit: String,
+        /// The value of the limit
+        max: usize
+    },
+    /// Any other error
+    Custom {
+        /// The message of this error
+        message: String
+    },
+    /// Any other error, with a message that never changes
+    Static {
+        /// The message of this error
+        message: &'static str
+    }
+}
+
+/// Stable numbers for each kind of error, returned from `NP_Error::code`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum NP_Error_Code {
+    /// `NP_Error::TypeMismatch`
+    TypeMismatch = 1,
+    /// `NP_Error::OutOfSpace`
+    OutOfSpace = 2,
+    /// `NP_Error::TooLarge`
+    TooLarge = 3,
+    /// `NP_Error::InvalidSchema`
+    InvalidSchema = 4,
+    /// `NP_Error::PathNotCollection`
+    PathNotCollection = 5,
+    /// `NP_Error::CorruptBuffer`
+    CorruptBuffer = 6,
+    /// `NP_Error::MissingRequired`
+    MissingRequired = 7,
+    /// `NP_Error::Overflow`
+    Overflow = 8,
+    /// `NP_Error::Lossy`
+    Lossy = 9,
+    /// `NP_Error::AccessDenied`
+    AccessDenied = 10,
+    /// `NP_Error::EtagMismatch`
+    EtagMismatch = 11,
+    /// `NP_Error::SchemaMismatch`
+    SchemaMismatch = 12,
+    /// `NP_Error::LimitExceeded`
+    LimitExceeded = 13,
+    /// `NP_Error::Custom` and `NP_Error::Static`
+    Other = 14
 }
 
 impl NP_Error {
     /// Generate a new error with a specific message
     pub fn new<S: AsRef<str>>(message: S) -> Self {
-        NP_Error { message: message.as_ref().to_owned() }
+        NP_Error::Custom { message: message.as_ref().to_owned() }
+    }
+    /// Generate a new error with a message that never changes, nothing is allocated
+    pub fn new_static(message: &'static str) -> Self {
+        NP_Error::Static { message }
+    }
+    /// Generate a new schema error with a specific message
+    pub fn new_schema<S: AsRef<str>>(message: S) -> Self {
+        NP_Error::InvalidSchema { message: message.as_ref().to_owned() }
+    }
+    /// Generate a type mismatch error for a value that was being set, nothing is allocated
+    pub fn new_type_mismatch(expected: &'static str, found: &'static str, path: &[&str]) -> Self {
+        Self::type_mismatch(expected, found, path.len(), true)
+    }
+    /// Generate a type mismatch error for a value that was being read, nothing is allocated
+    pub fn new_get_type_mismatch(expected: &'static str, found: &'static str, path: &[&str]) -> Self {
+        Self::type_mismatch(expected, found, path.len(), false)
+    }
+    pub(crate) fn type_mismatch(expected: &'static str, found: &'static str, depth: usize, write: bool) -> Self {
+        np_count!(TYPE_ERRORS);
+        NP_Error::TypeMismatch { expected, found, depth, write }
+    }
+    /// The keys of `path` that lead to a type mismatch, `path` is the path given to the call that returned this error.
+    /// 
+    /// Type mismatches only count the keys so making them never allocates, other kinds of errors give `None`.
+    pub fn path_in<'path>(&self, path: &'path [&'path str]) -> Option<&'path [&'path str]> {
+        match self {
+            NP_Error::TypeMismatch { depth, .. } => Some(&path[..core::cmp::min(*depth, path.len())]),
+            _ => None
+        }
+    }
+    /// Generate an error for a path that isn't a collection, the reason is used as the message
+    pub fn new_not_collection(path: &[&str], reason: &'static str) -> Self {
+        NP_Error::PathNotCollection { path: path.iter().map(|p| p.to_string()).collect(), reason }
+    }
+    /// Generate an error for a limit that was exceeded
+    pub fn new_limit(limit: &str, max: usize) -> Self {
+        NP_Error::LimitExceeded { limit: limit.to_owned(), max }
     }
     /// Convert an option to an error type
     pub fn unwrap<T>(value: Option<T>) -> Result<T, NP_Error> {
         match value {
             Some(x) => Ok(x),
-            None => Err(NP_Error::new("Missing Value in option!"))
+            None => Err(NP_Error::new_static("Missing Value in option!"))
+        }
+    }
+    /// Get the message of this error
+    /// 
+    /// This replaces the public `message` field of older versions.
+    #[deprecated(since = "0.7.0", note = "use `to_string()` or match on the kind of error")]
+    pub fn message(&self) -> String {
+        self.to_string()
+    }
+    /// The kind of this error as a stable number
+    pub fn code(&self) -> NP_Error_Code {
+        match self {
+            NP_Error::TypeMismatch { .. } => NP_Error_Code::TypeMismatch,
+            NP_Error::OutOfSpace => NP_Error_Code::OutOfSpace,
+            NP_Error::TooLarge { .. } => NP_Error_Code::TooLarge,
+            NP_Error::InvalidSchema { .. } => NP_Error_Code::InvalidSchema,
+            NP_Error::PathNotCollection { .. } => NP_Error_Code::PathNotCollection,
+            NP_Error::CorruptBuffer { .. } => NP_Error_Code::CorruptBuffer,
+            NP_Error::MissingRequired { .. } => NP_Error_Code::MissingRequired,
+            NP_Error::Overflow { .. } => NP_Error_Code::Overflow,
+            NP_Error::Lossy { .. } => NP_Error_Code::Lossy,
+            NP_Error::AccessDenied { .. } => NP_Error_Code::AccessDenied,
+            NP_Error::EtagMismatch { .. } => NP_Error_Code::EtagMismatch,
+            NP_Error::SchemaMismatch { .. } => NP_Error_Code::SchemaMismatch,
+            NP_Error::LimitExceeded { .. } => NP_Error_Code::LimitExceeded,
+            NP_Error::Custom { .. } | NP_Error::Static { .. } => NP_Error_Code::Other
+        }
+    }
+}
+
+impl core::fmt::Display for NP_Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            NP_Error::TypeMismatch { expected, found, write: true, .. } => {
+                writeln!(f, "TypeError: Attempted to set value for type ({}) into schema of type ({})", found, expected)
+            },
+            NP_Error::TypeMismatch { expected, found, write: false, .. } => {
+                writeln!(f, "TypeError: Attempted to get value for type ({}) for schema of type ({})", found, expected)
+            },
+            NP_Error::OutOfSpace => {
+                write!(f, "Not enough space available in buffer!")
+            },
+            NP_Error::TooLarge { path, needed, available } => {
+                write!(f, "Write at path ({}) could need ({}) bytes but only ({}) are available", path.join("."), needed, available)
+            },
+            NP_Error::InvalidSchema { message } => {
+                write!(f, "{}", message)
+            },
+            NP_Error::PathNotCollection { reason, .. } => {
+                f.write_str(reason)
+            },
+            NP_Error::CorruptBuffer { path, reason } => {
+                write!(f, "Corrupt buffer at path ({}): {}", path.join("."), reason)
+            },
+            NP_Error::MissingRequired { paths } => {
+                let paths: Vec<String> = paths.iter().map(|path| path.join(".")).collect();
+                write!(f, "Required values are unset at paths ({})", paths.join(", "))
+            },
+            NP_Error::Overflow { path } => {
+                write!(f, "Integer overflow at path ({})", path.join("."))
+            },
+            NP_Error::Lossy { expected, path } => {
+                write!(f, "Value can't be stored as ({}) without losing precision at path ({})", expected, path.join("."))
+            },
+            NP_Error::AccessDenied { path } => {
+                write!(f, "Path ({}) is outside of this view!", path.join("."))
+            },
+            NP_Error::EtagMismatch { expected, found } => {
+                write!(f, "Buffer has changed, expected etag ({}) but found ({})", expected, found)
+            },
+            NP_Error::SchemaMismatch { expected, found } => {
+                write!(f, "Buffer was written with another schema, expected fingerprint ({:016x}) but found ({:016x})", expected, found)
+            },
+            NP_Error::LimitExceeded { limit, max } => {
+                write!(f, "Reading buffer goes past limit ({}) of ({})", limit, max)
+            },
+            NP_Error::Custom { message } => {
+                write!(f, "{}", message)
+            },
+            NP_Error::Static { message } => {
+                f.write_str(message)
+            }
         }
     }
 }
@@ -42,4 +332,57 @@ impl From<core::num::ParseIntError> for NP_Error {
     fn from(err: core::num::ParseIntError) -> NP_Error {
         NP_Error::new(err.to_string().as_str())
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn error_display_works() -> Result<(), NP_Error> {
+    assert_eq!(NP_Error::new("hello").to_string(), "hello");
+    assert_eq!(NP_Error::new_static("hello").to_string(), "hello");
+    assert_eq!(NP_Error::new_static("hello").code(), NP_Error::new("hello").code());
+    assert_eq!(NP_Error::OutOfSpace.to_string(), "Not enough space available in buffer!");
+    #[allow(deprecated)]
+    let message = NP_Error::OutOfSpace.message();
+    assert_eq!(message, NP_Error::OutOfSpace.to_string());
+    assert_eq!(NP_Error::new_not_collection(&["a", "b"], "Not here!").to_string(), "Not here!");
+    assert_eq!(NP_Error::TooLarge { path: ["a".to_owned()].to_vec(), needed: 12, available: 4 }.to_string(), "Write at path (a) could need (12) bytes but only (4) are available");
+    assert_eq!(NP_Error::SchemaMismatch { expected: 1, found: 255 }.to_string(), "Buffer was written with another schema, expected fingerprint (0000000000000001) but found (00000000000000ff)");
+    assert_eq!(NP_Error::new_limit("max_depth", 4).to_string(), "Reading buffer goes past limit (max_depth) of (4)");
+    assert_eq!(NP_Error::EtagMismatch { expected: 1, found: 2 }.to_string(), "Buffer has changed, expected etag (1) but found (2)");
+    assert_eq!(NP_Error::MissingRequired { paths: [["a".to_owned(), "b".to_owned()].to_vec(), ["c".to_owned()].to_vec()].to_vec() }.to_string(), "Required values are unset at paths (a.b, c)");
+    Ok(())
+}
+
+#[test]
+fn error_kinds_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [["tags", {"type": "list", "of": {"type": "string"}}], ["age", {"type": "u8"}]]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.set(&["age"], "hello"), Err(NP_Error::new_type_mismatch("uint8", "string", &["age"])));
+    assert_eq!(buffer.list_push(&["tags"], 2u8), Err(NP_Error::new_type_mismatch("string", "uint8", &["tags"])));
+    assert_eq!(buffer.list_push(&["age"], 2u8).err(), Some(NP_Error::new_not_collection(&["age"], "Trying to push onto non list item!")));
+    buffer.set(&["age"], 2u8)?;
+    assert_eq!(buffer.get_iter(&["age"]).err(), Some(NP_Error::new_not_collection(&["age"], "Tried to create iterator on non collection item!")));
+    assert_eq!(buffer.get::<&str>(&["age"]), Err(NP_Error::new_get_type_mismatch("uint8", "string", &["age"])));
+    let error = buffer.get::<&str>(&["age"]).unwrap_err();
+    assert_eq!(error.path_in(&["age"]), Some(&["age"][..]));
+    assert_eq!(NP_Error::OutOfSpace.path_in(&["age"]), None);
+    let handle = factory.compile_path(&["age"])?;
+    assert_eq!(buffer.get_h::<i64>(&handle), Err(NP_Error::new_get_type_mismatch("uint8", "int64", &["age"])));
+
+    match crate::NP_Factory::new(r#"{"type": "list"}"#) {
+        Err(NP_Error::InvalidSchema { .. }) => { },
+        _ => panic!()
+    }
+    Ok(())
+}
+
+#[test]
+fn error_messages_match_older_versions() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [["tags", {"type": "list", "of": {"type": "string"}}], ["age", {"type": "u8"}]]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.set(&["age"], "hello").unwrap_err().to_string(), "TypeError: Attempted to set value for type (string) into schema of type (uint8)\n");
+    assert_eq!(buffer.list_push(&["tags"], 2u8).unwrap_err().to_string(), "TypeError: Attempted to set value for type (uint8) into schema of type (string)\n");
+    assert_eq!(buffer.list_push(&["age"], 2u8).unwrap_err().to_string(), "Trying to push onto non list item!");
+    assert_eq!(buffer.get::<&str>(&["age"]).unwrap_err().to_string(), "TypeError: Attempted to get value for type (string) for schema of type (uint8)\n");
+    assert_eq!(buffer.get_iter(&["age"]).err().map(|e| e.to_string()), Some("Tried to create iterator on non collection item!".to_owned()));
+    Ok(())
+}
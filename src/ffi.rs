@@ -0,0 +1,430 @@
+//! C compatible API for reading and writing buffers from other languages
+//!
+//! Enable with the `ffi` feature.  Factories and buffers are passed across the boundary as opaque handles that must be released with their matching `_free` function.
+//!
+//! Since this crate is `no_std`, build the shared and static libraries from the `ffi/` folder with `cargo build --release`, then include `include/no_proto.h`.
+//!
+//! All strings are null terminated UTF-8.  Paths are passed as an array of strings along with the array length, an empty path points at the root of the buffer.
+//!
+//! Any pointer passed in may be null.  A null handle, string, path or out pointer makes the call fail (the `_free` functions do nothing) instead of crashing.  Pointers that aren't null must be valid for the call: handles must come from this library and not be released yet, strings must be null terminated and paths must have at least `path_len` entries.  Each function's `# Safety` section lists what it reads and how long returned pointers stay valid.
+//!
+//! Functions that return a status code use these values:
+//! - `NP_OK` (0): the operation worked
+//! - `NP_UNSET` (1): there is no value at the path
+//! - `NP_FAILED` (-1): the operation failed, a handle is null, a string isn't valid UTF-8 or the value doesn't match the schema
+//!
+//! ```c
+//! np_factory* factory = np_factory_new("{\"type\": \"table\", \"columns\": [[\"name\", {\"type\": \"string\"}], [\"age\", {\"type\": \"u8\"}]]}");
+//! np_buffer* buffer = np_buffer_empty(factory);
+//!
+//! const char* path[] = {"age"};
+//! np_buffer_set_i64(buffer, path, 1, 30);
+//!
+//! int64_t age;
+//! if (np_buffer_get_i64(buffer, path, 1, &age) == NP_OK) {
+//!     printf("age: %lld\n", age);
+//! }
+//!
+//! size_t len;
+//! const uint8_t* bytes = np_buffer_bytes(buffer, &len);
+//!
+//! np_buffer_free(buffer);
+//! np_factory_free(factory);
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::json_flex::NP_JSON;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The operation worked
+pub const NP_OK: i32 = 0;
+/// There is no value at the path
+pub const NP_UNSET: i32 = 1;
+/// The operation failed
+pub const NP_FAILED: i32 = -1;
+
+/// Buffer handle, holds the buffer bytes along with the factory used to open them
+pub struct NP_FFI_Buffer {
+    factory: *const NP_Factory,
+    bytes: Vec<u8>
+}
+
+impl NP_FFI_Buffer {
+    unsafe fn with_buffer<T, F>(&mut self, callback: F) -> Result<T, NP_Error> where F: FnOnce(&mut NP_Buffer) -> Result<T, NP_Error> {
+        let factory = &*self.factory;
+        let mut buffer = factory.open_buffer(core::mem::replace(&mut self.bytes, Vec::new()));
+        let result = callback(&mut buffer);
+        self.bytes = buffer.close();
+        result
+    }
+}
+
+unsafe fn read_str<'str>(text: *const u8) -> Option<&'str str> {
+    if text.is_null() {
+        return None;
+    }
+    let mut len = 0usize;
+    while *text.add(len) != 0 {
+        len += 1;
+    }
+    core::str::from_utf8(core::slice::from_raw_parts(text, len)).ok()
+}
+
+unsafe fn read_path<'path>(path: *const *const u8, path_len: usize) -> Option<Vec<&'path str>> {
+    if path_len == 0 {
+        return Some(Vec::new());
+    }
+    if path.is_null() {
+        return None;
+    }
+    (0..path_len).map(|x| read_str(*path.add(x))).collect()
+}
+
+// strings handed to C must not contain null bytes, otherwise `np_string_free` can't find their length
+fn write_str(text: String) -> *mut u8 {
+    if text.as_bytes().contains(&0) {
+        return core::ptr::null_mut();
+    }
+    let mut bytes = text.into_bytes();
+    bytes.push(0);
+    Box::into_raw(bytes.into_boxed_slice()) as *mut u8
+}
+
+unsafe fn get_json(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize) -> Option<NP_JSON> {
+    let buffer = buffer.as_mut()?;
+    let path = read_path(path, path_len)?;
+    buffer.with_buffer(|buffer| buffer.json_encode(&path)).ok()
+}
+
+unsafe fn set_json(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize, value: &NP_JSON) -> i32 {
+    let buffer = match buffer.as_mut() { Some(x) => x, None => return NP_FAILED };
+    let path = match read_path(path, path_len) { Some(x) => x, None => return NP_FAILED };
+    match buffer.with_buffer(|buffer| buffer.set_json_value(&path, value)) {
+        Ok(true) => NP_OK,
+        Ok(false) => NP_UNSET,
+        Err(_) => NP_FAILED
+    }
+}
+
+/// Parse a JSON schema into a new factory.  Returns null if the schema is invalid.
+/// 
+/// # Safety
+/// `schema` must be null or point to a null terminated string that stays readable for the whole call.
+#[no_mangle]
+pub unsafe extern "C" fn np_factory_new(schema: *const u8) -> *mut NP_Factory {
+    match read_str(schema).map(NP_Factory::new) {
+        Some(Ok(factory)) => Box::into_raw(Box::new(factory)),
+        _ => core::ptr::null_mut()
+    }
+}
+
+/// Release a factory.  Every buffer opened with the factory must be released first.
+/// 
+/// # Safety
+/// `factory` must be null or a handle from `np_factory_new` that hasn't been released yet.  Buffers opened with it hold a pointer to it, release them first and don't use the handle again after this call.
+#[no_mangle]
+pub unsafe extern "C" fn np_factory_free(factory: *mut NP_Factory) {
+    if factory.is_null() == false {
+        drop(Box::from_raw(factory));
+    }
+}
+
+/// Create a new empty buffer.  Returns null if the factory is null.
+/// 
+/// # Safety
+/// `factory` must be null or a live handle from `np_factory_new`.  The new buffer borrows the factory, so the factory must outlive it.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_empty(factory: *const NP_Factory) -> *mut NP_FFI_Buffer {
+    match factory.as_ref() {
+        Some(x) => Box::into_raw(Box::new(NP_FFI_Buffer { factory, bytes: x.empty_buffer(None).close() })),
+        None => core::ptr::null_mut()
+    }
+}
+
+/// Copy existing buffer bytes into a new buffer, the bytes are checked before they're used.  Returns null if the bytes aren't a valid buffer.
+/// 
+/// # Safety
+/// `factory` must be null or a live handle from `np_factory_new`, it must outlive the new buffer.  `bytes` must be null or point to `len` readable bytes, the bytes are copied so they only need to stay valid for the call.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_open(factory: *const NP_Factory, bytes: *const u8, len: usize) -> *mut NP_FFI_Buffer {
+    let factory_ref = match factory.as_ref() { Some(x) => x, None => return core::ptr::null_mut() };
+    if bytes.is_null() || len > isize::MAX as usize {
+        return core::ptr::null_mut();
+    }
+    match factory_ref.open_checked_buffer(core::slice::from_raw_parts(bytes, len).to_vec()) {
+        Ok(buffer) => Box::into_raw(Box::new(NP_FFI_Buffer { factory, bytes: buffer.close() })),
+        Err(_) => core::ptr::null_mut()
+    }
+}
+
+/// Release a buffer
+/// 
+/// # Safety
+/// `buffer` must be null or a handle from `np_buffer_empty` or `np_buffer_open` that hasn't been released yet, don't use the handle again after this call.  Pointers from `np_buffer_bytes` are invalid once the buffer is released.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_free(buffer: *mut NP_FFI_Buffer) {
+    if buffer.is_null() == false {
+        drop(Box::from_raw(buffer));
+    }
+}
+
+/// Get the current bytes of the buffer and write their length into `len`.  The pointer is owned by the buffer and is only valid until the buffer is changed or released.
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `len` must be null or point to a writable `size_t`.  The returned pointer is only valid until the next call that changes or releases `buffer`.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_bytes(buffer: *const NP_FFI_Buffer, len: *mut usize) -> *const u8 {
+    match (buffer.as_ref(), len.as_mut()) {
+        (Some(buffer), Some(len)) => {
+            *len = buffer.bytes.len();
+            buffer.bytes.as_ptr()
+        },
+        _ => core::ptr::null()
+    }
+}
+
+/// Remove unused space from the buffer
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_compact(buffer: *mut NP_FFI_Buffer) -> i32 {
+    match buffer.as_mut().map(|x| x.with_buffer(|buffer| buffer.compact(None))) {
+        Some(Ok(())) => NP_OK,
+        _ => NP_FAILED
+    }
+}
+
+/// Delete the value at the path
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `path` must be null or point to `path_len` readable string pointers, each null or a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_del(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize) -> i32 {
+    let buffer = match buffer.as_mut() { Some(x) => x, None => return NP_FAILED };
+    let path = match read_path(path, path_len) { Some(x) => x, None => return NP_FAILED };
+    match buffer.with_buffer(|buffer| buffer.del(&path)) {
+        Ok(true) => NP_OK,
+        Ok(false) => NP_UNSET,
+        Err(_) => NP_FAILED
+    }
+}
+
+/// Set the value at the path from a JSON string
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `path` must be null or point to `path_len` readable string pointers, each null or a null terminated string.  `json` must be null or point to a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_set_json(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize, json: *const u8) -> i32 {
+    let buffer = match buffer.as_mut() { Some(x) => x, None => return NP_FAILED };
+    let path = match read_path(path, path_len) { Some(x) => x, None => return NP_FAILED };
+    let json = match read_str(json) { Some(x) => x, None => return NP_FAILED };
+    match buffer.with_buffer(|buffer| buffer.set_with_json(&path, json)) {
+        Ok(true) => NP_OK,
+        Ok(false) => NP_UNSET,
+        Err(_) => NP_FAILED
+    }
+}
+
+/// Get the value at the path as a JSON string.  Returns null on failure, the string must be released with `np_string_free`.
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `path` must be null or point to `path_len` readable string pointers, each null or a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_json(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize) -> *mut u8 {
+    match get_json(buffer, path, path_len) {
+        Some(json) => write_str(json.stringify()),
+        None => core::ptr::null_mut()
+    }
+}
+
+/// Set an integer value, works with every integer type and dates
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `path` must be null or point to `path_len` readable string pointers, each null or a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_set_i64(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize, value: i64) -> i32 {
+    set_json(buffer, path, path_len, &NP_JSON::Integer(value))
+}
+
+/// Set a floating point value, works with `float`, `double` and `decimal` types
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `path` must be null or point to `path_len` readable string pointers, each null or a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_set_f64(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize, value: f64) -> i32 {
+    set_json(buffer, path, path_len, &NP_JSON::Float(value))
+}
+
+/// Set a boolean value
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `path` must be null or point to `path_len` readable string pointers, each null or a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_set_bool(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize, value: bool) -> i32 {
+    set_json(buffer, path, path_len, &if value { NP_JSON::True } else { NP_JSON::False })
+}
+
+/// Set a string value, works with `string`, `option`, `uuid` and `ulid` types
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `path` must be null or point to `path_len` readable string pointers, each null or a null terminated string.  `value` must be null or point to a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_set_string(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize, value: *const u8) -> i32 {
+    match read_str(value) {
+        Some(x) => set_json(buffer, path, path_len, &NP_JSON::String(String::from(x))),
+        None => NP_FAILED
+    }
+}
+
+/// Get an integer value into `out`, works with every integer type and dates
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `path` must be null or point to `path_len` readable string pointers, each null or a null terminated string.  `out` must be null or point to a writable `int64_t`.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_get_i64(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize, out: *mut i64) -> i32 {
+    match (get_json(buffer, path, path_len), out.as_mut()) {
+        (Some(NP_JSON::Integer(x)), Some(out)) => { *out = x; NP_OK },
+        // u64 values above i64::MAX keep their bits, cast `out` to uint64_t to read them
+        (Some(NP_JSON::Unsigned(x)), Some(out)) => { *out = x as i64; NP_OK },
+        (Some(NP_JSON::Null), Some(_)) => NP_UNSET,
+        _ => NP_FAILED
+    }
+}
+
+/// Get a floating point value into `out`, works with `float` and `double` types
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `path` must be null or point to `path_len` readable string pointers, each null or a null terminated string.  `out` must be null or point to a writable `double`.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_get_f64(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize, out: *mut f64) -> i32 {
+    match (get_json(buffer, path, path_len), out.as_mut()) {
+        (Some(NP_JSON::Float(x)), Some(out)) => { *out = x; NP_OK },
+        (Some(NP_JSON::Integer(x)), Some(out)) => { *out = x as f64; NP_OK },
+        (Some(NP_JSON::Unsigned(x)), Some(out)) => { *out = x as f64; NP_OK },
+        (Some(NP_JSON::Null), Some(_)) => NP_UNSET,
+        _ => NP_FAILED
+    }
+}
+
+/// Get a boolean value into `out`
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `path` must be null or point to `path_len` readable string pointers, each null or a null terminated string.  `out` must be null or point to a writable `bool`.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_get_bool(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize, out: *mut bool) -> i32 {
+    match (get_json(buffer, path, path_len), out.as_mut()) {
+        (Some(NP_JSON::True), Some(out)) => { *out = true; NP_OK },
+        (Some(NP_JSON::False), Some(out)) => { *out = false; NP_OK },
+        (Some(NP_JSON::Null), Some(_)) => NP_UNSET,
+        _ => NP_FAILED
+    }
+}
+
+/// Get a string value into `out`, works with `string`, `option`, `uuid` and `ulid` types.  The string must be released with `np_string_free`.
+/// 
+/// # Safety
+/// `buffer` must be null or a live handle from `np_buffer_empty` or `np_buffer_open`, and it can't be used from another thread during the call.  `path` must be null or point to `path_len` readable string pointers, each null or a null terminated string.  `out` must be null or point to a writable `char*`.
+#[no_mangle]
+pub unsafe extern "C" fn np_buffer_get_string(buffer: *mut NP_FFI_Buffer, path: *const *const u8, path_len: usize, out: *mut *mut u8) -> i32 {
+    match (get_json(buffer, path, path_len), out.as_mut()) {
+        (Some(NP_JSON::String(x)), Some(out)) => {
+            *out = write_str(x);
+            if out.is_null() { NP_FAILED } else { NP_OK }
+        },
+        (Some(NP_JSON::Null), Some(_)) => NP_UNSET,
+        _ => NP_FAILED
+    }
+}
+
+/// Release a string returned by this library
+/// 
+/// # Safety
+/// `text` must be null or a string returned by this library (`np_buffer_json` or `np_buffer_get_string`) that hasn't been released yet.  Strings from anywhere else can't be released with this function.
+#[no_mangle]
+pub unsafe extern "C" fn np_string_free(text: *mut u8) {
+    if text.is_null() {
+        return;
+    }
+    let mut len = 0usize;
+    while *text.add(len) != 0 {
+        len += 1;
+    }
+    drop(Box::from_raw(core::slice::from_raw_parts_mut(text, len + 1) as *mut [u8]));
+}
+
+#[test]
+fn ffi_works() -> Result<(), NP_Error> {
+    unsafe {
+        let factory = np_factory_new(b"{\"type\": \"table\", \"columns\": [[\"name\", {\"type\": \"string\"}], [\"age\", {\"type\": \"u8\"}], [\"tags\", {\"type\": \"list\", \"of\": {\"type\": \"string\"}}]]}\0".as_ptr());
+        assert!(factory.is_null() == false);
+        assert!(np_factory_new(b"{\"type\": \"nope\"}\0".as_ptr()).is_null());
+
+        let buffer = np_buffer_empty(factory);
+        let age = [b"age\0".as_ptr()];
+        let name = [b"name\0".as_ptr()];
+        let tags = [b"tags\0".as_ptr()];
+
+        assert_eq!(np_buffer_set_i64(buffer, age.as_ptr(), 1, 30), NP_OK);
+        assert_eq!(np_buffer_set_i64(buffer, age.as_ptr(), 1, 300), NP_FAILED);
+        assert_eq!(np_buffer_set_string(buffer, name.as_ptr(), 1, b"Jeb\0".as_ptr()), NP_OK);
+        assert_eq!(np_buffer_set_json(buffer, tags.as_ptr(), 1, b"[\"pilot\"]\0".as_ptr()), NP_OK);
+
+        let mut out_age = 0i64;
+        assert_eq!(np_buffer_get_i64(buffer, age.as_ptr(), 1, &mut out_age), NP_OK);
+        assert_eq!(out_age, 30);
+
+        let mut out_name: *mut u8 = core::ptr::null_mut();
+        assert_eq!(np_buffer_get_string(buffer, name.as_ptr(), 1, &mut out_name), NP_OK);
+        assert_eq!(read_str(out_name), Some("Jeb"));
+        np_string_free(out_name);
+
+        let json = np_buffer_json(buffer, core::ptr::null(), 0);
+        assert_eq!(read_str(json), Some("{\"name\":\"Jeb\",\"age\":30,\"tags\":[\"pilot\"]}"));
+        np_string_free(json);
+
+        assert_eq!(np_buffer_del(buffer, age.as_ptr(), 1), NP_OK);
+        assert_eq!(np_buffer_get_i64(buffer, age.as_ptr(), 1, &mut out_age), NP_UNSET);
+        assert_eq!(np_buffer_compact(buffer), NP_OK);
+
+        // copy the bytes into a new buffer
+        let mut len = 0usize;
+        let bytes = np_buffer_bytes(buffer, &mut len);
+        let opened = np_buffer_open(factory, bytes, len);
+        assert!(opened.is_null() == false);
+        assert_eq!(np_buffer_get_string(opened, name.as_ptr(), 1, &mut out_name), NP_OK);
+        assert_eq!(read_str(out_name), Some("Jeb"));
+        np_string_free(out_name);
+
+        assert!(np_buffer_open(factory, [0u8, 0, 200].as_ptr(), 3).is_null());
+
+        // null inputs fail instead of being read
+        let null_path = [core::ptr::null::<u8>()];
+        assert!(np_factory_new(core::ptr::null()).is_null());
+        assert!(np_buffer_empty(core::ptr::null()).is_null());
+        assert!(np_buffer_open(factory, core::ptr::null(), 4).is_null());
+        assert!(np_buffer_bytes(buffer, core::ptr::null_mut()).is_null());
+        assert_eq!(np_buffer_compact(core::ptr::null_mut()), NP_FAILED);
+        assert_eq!(np_buffer_set_i64(core::ptr::null_mut(), age.as_ptr(), 1, 30), NP_FAILED);
+        assert_eq!(np_buffer_set_i64(buffer, core::ptr::null(), 1, 30), NP_FAILED);
+        assert_eq!(np_buffer_set_i64(buffer, null_path.as_ptr(), 1, 30), NP_FAILED);
+        assert_eq!(np_buffer_set_string(buffer, name.as_ptr(), 1, core::ptr::null()), NP_FAILED);
+        assert_eq!(np_buffer_set_json(buffer, tags.as_ptr(), 1, core::ptr::null()), NP_FAILED);
+        assert_eq!(np_buffer_get_string(buffer, name.as_ptr(), 1, core::ptr::null_mut()), NP_FAILED);
+        assert!(np_buffer_json(core::ptr::null_mut(), core::ptr::null(), 0).is_null());
+        np_string_free(core::ptr::null_mut());
+        np_buffer_free(core::ptr::null_mut());
+        np_factory_free(core::ptr::null_mut());
+
+        np_buffer_free(opened);
+        np_buffer_free(buffer);
+        np_factory_free(factory);
+    }
+
+    Ok(())
+}
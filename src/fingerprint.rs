@@ -0,0 +1,132 @@
+//! Schema fingerprints stored in buffers
+//!
+//! `NP_Schema::fingerprint` is a 64 bit hash of a compiled schema, taken once when the factory is made.  It's the one schema identity in this library, fingerprinted buffers, sealed buffers, batches, compiled paths and snapshots all use it.  Buffers don't know which schema wrote them, opening one with the wrong factory reads garbage instead of failing.  Once `NP_Buffer::embed_fingerprint` is called the buffer carries the fingerprint of its schema, and `NP_Factory::open_checked_buffer` refuses it with `NP_Error::SchemaMismatch` if the factory's schema is different.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! // whitespace and formatting don't change the fingerprint
+//! let same = NP_Factory::new(r#"{"type":"table","columns":[["name",{"type":"string"}],["age",{"type":"u8"}]]}"#)?;
+//! assert_eq!(factory.schema.fingerprint()?, same.schema.fingerprint()?);
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.embed_fingerprint()?;
+//! new_buffer.set(&["name"], "Jeb Kermin")?;
+//! assert_eq!(new_buffer.fingerprint(), Some(factory.schema.fingerprint()?));
+//! let bytes = new_buffer.close();
+//!
+//! assert!(factory.open_checked_buffer(bytes.clone()).is_ok());
+//!
+//! let other: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["age",  {"type": "u8"}],
+//!         ["name", {"type": "string"}]
+//!     ]
+//! }"#)?;
+//! assert!(matches!(other.open_checked_buffer(bytes), Err(NP_Error::SchemaMismatch { .. })));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! The fingerprint is 8 bytes stored right after the root pointer, the tombstone pointer and the version, whichever the buffer has.  Buffers without one open with any factory, like before.
+//!
+
+use crate::error::NP_Error;
+use crate::hashmap::fnv1a_64;
+use crate::memory::NP_Memory;
+use crate::{tombstone, version};
+
+pub(crate) const FINGERPRINT_LEN: usize = 8;
+
+/// Fingerprint of a compiled schema
+pub(crate) fn of(compiled: &[u8]) -> u64 {
+    fnv1a_64(compiled)
+}
+
+// after the root pointer, tombstone pointer and version
+pub(crate) fn address(memory: &NP_Memory) -> usize {
+    tombstone::HEAD_ADDR + if memory.has_tombstones() { 2 } else { 0 } + version::size(memory)
+}
+
+/// The fingerprint stored in the buffer, if it has one
+pub(crate) fn read(memory: &NP_Memory) -> Option<u64> {
+    let address = address(memory);
+    let bytes = memory.read_bytes();
+    if !memory.has_fingerprint() || bytes.len() < address + FINGERPRINT_LEN {
+        return None;
+    }
+
+    let mut fingerprint = [0u8; 8];
+    fingerprint.copy_from_slice(&bytes[address..(address + FINGERPRINT_LEN)]);
+    Some(u64::from_be_bytes(fingerprint))
+}
+
+/// Store the fingerprint of the buffer's schema, the buffer must have nothing but its header.
+pub(crate) fn start(memory: &NP_Memory) -> Result<(), NP_Error> {
+    let fingerprint = memory.fingerprint();
+    memory.start_fingerprint()?;
+    let address = address(memory);
+    memory.write_bytes()[address..(address + FINGERPRINT_LEN)].copy_from_slice(&fingerprint.to_be_bytes());
+    Ok(())
+}
+
+/// Bytes used by the fingerprint
+pub(crate) fn size(memory: &NP_Memory) -> usize {
+    if memory.has_fingerprint() { FINGERPRINT_LEN } else { 0 }
+}
+
+/// Make sure a buffer with a fingerprint was written with this schema
+pub(crate) fn check(memory: &NP_Memory) -> Result<(), NP_Error> {
+    match read(memory) {
+        Some(found) => {
+            let expected = memory.fingerprint();
+            if found != expected {
+                return Err(NP_Error::SchemaMismatch { expected, found });
+            }
+            Ok(())
+        },
+        None => Ok(())
+    }
+}
+
+#[test]
+fn fingerprint_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    let fingerprint = factory.schema.fingerprint()?;
+    assert_ne!(fingerprint, crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u8"}}"#)?.schema.fingerprint()?);
+    assert_eq!(fingerprint, of(&factory.compile_schema()));
+    assert_eq!(fingerprint, crate::NP_Factory::new_compiled(factory.compile_schema()).schema.fingerprint()?);
+
+    // added after values are set, kept with tombstones, versions and compaction
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["0"], "hello")?;
+    assert_eq!(buffer.fingerprint(), None);
+    buffer.embed_fingerprint()?;
+    buffer.track_deletes(|| 1)?;
+    buffer.track_version(3, || 2)?;
+    buffer.set(&["1"], "world")?;
+    buffer.compact(None)?;
+    assert_eq!(buffer.fingerprint(), Some(fingerprint));
+    assert_eq!(buffer.get::<&str>(&["0"])?, Some("hello"));
+    assert_eq!(buffer.calc_bytes()?.wasted_bytes, 0);
+
+    let bytes = buffer.close();
+    let opened = factory.open_checked_buffer(bytes.clone())?;
+    assert_eq!(opened.get::<&str>(&["1"])?, Some("world"));
+    assert_eq!(opened.version().map(|version| version.node), Some(3));
+
+    let other = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u8"}}"#)?;
+    assert_eq!(other.open_checked_buffer(bytes).err(), Some(NP_Error::SchemaMismatch { expected: other.schema.fingerprint()?, found: fingerprint }));
+
+    Ok(())
+}
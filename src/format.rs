@@ -18,7 +18,7 @@
 //! | List Item    | 5                |
 //!  
 //! 
-//! The first byte of every buffer is a version/size number to be used by later version of NoProto.  The next 2 bytes of every buffer is the root pointer, these bytes should contain the address of the root object in the buffer.
+//! The first byte of every buffer is a version/size number to be used by later version of NoProto.  Its lowest bit is set after `compact_dedupe` when dynamically sized strings and bytes values may share their data, these values must be copied instead of updated in place.  Its second bit is set when the 2 bytes after the root pointer point to the first tombstone (see the `tombstone` module), and its third bit when a 16 byte version follows the root and tombstone pointers (see the `version` module).  Its fourth bit is set when `bool`, `u8`, `i8` and `option` values are stored in the low byte of their pointer's address instead of their own byte, the high byte of that address is always `1` (see `NP_Buffer::inline_small_values`).  The next 2 bytes of every buffer is the root pointer, these bytes should contain the address of the root object in the buffer.
 //! 
 //! Most of the time these bytes will point to the data immediately following them, but it's possible to clear the root object causing these bytes to be zero, or to update the root data which would cause this address to update to something else.
 //! 
@@ -437,6 +437,8 @@
 //! 
 //! If it's a string, the data should be utf-8 encoded when it's saved into the buffer and utf-8 decoded when it's retrieved.
 //! 
+//! Fixed size strings are padded with spaces on the right unless the schema sets `pad` or `pad_byte`.
+//! 
 //! ```
 //! use no_proto::error::NP_Error;
 //! use no_proto::NP_Factory;
@@ -477,6 +479,28 @@
 //! # Ok::<(), NP_Error>(()) 
 //! ```
 //! 
+//! ### any (Scalar)
+//! The first byte is the type key of the value, the value follows in the same format its type uses on its own.  Strings and bytes always have a 2 byte length prefix.
+//! 
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::any::NP_Dyn;
+//! 
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "any"
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set_dyn(&[], NP_Dyn::Str("hi"))?;
+//! assert_eq!(vec![0, 0, 3, 2, 0, 2, 104, 105], new_buffer.close());
+//! 
+//! // [0,     0, 3,        2,    0, 2, 104, 105]
+//! // [   root ptr, type key,   length,   h,   i]
+//!
+//! # Ok::<(), NP_Error>(()) 
+//! ```
+//! 
 //! 
 //! # NoProto Schema Format Documentation
 //! 
@@ -743,6 +767,27 @@
 //! # Ok::<(), NP_Error>(()) 
 //! ```
 //! 
+//! For strings the upper bits of the second byte hold flags: `8` is set for `"intern": true`, `16` for `"pad": "left"`, `32` for `"trim": true`, `64` if there is a custom `pad_byte` and `128` for `"collation": "unicode_ci"`.  The custom pad byte is the last byte of the schema, after the default value.
+//! 
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! 
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "string",
+//!    "size": 20,
+//!    "pad": "left",
+//!    "pad_byte": 48
+//! }"#)?;
+//!
+//! assert_eq!(vec![2, 80, 0, 20, 0, 0, 48], factory.compile_schema());
+//! 
+//! // [        2,                      80,             0, 20,                 0, 0,        48]
+//! // [data type, case and padding flags,  fixed size (u16),  default size (u16), pad byte]
+//!
+//! # Ok::<(), NP_Error>(()) 
+//! ```
+//! 
 //! ### date (Scalar)
 //! 
 //! The second byte is a 1 if there is a default value, 0 otherwise.
@@ -803,6 +848,8 @@
 //! # Ok::<(), NP_Error>(()) 
 //! ```
 //! 
+//! The top bits of each column schema size are flags: `0x8000` for required columns, `0x4000` for sensitive columns and `0x2000` if the column has a merge strategy.  When the merge bit is set one byte follows the size with the strategy (1 `lww`, 2 `max`, 3 `sum`, 4 `set_union`), then the column schema.
+//! 
 //! ### List (Collection)
 //! 
 //! ```
@@ -822,6 +869,13 @@
 //! # Ok::<(), NP_Error>(()) 
 //! ```
 //! 
+//! Type keys never have their high bit set, so if the byte after the data type has it set it's a flags byte and the "of" schema follows it.  The lowest bit of the flags is set for `"layout": "columnar"` and the second bit for `"type": "sorted_list"`.  The third bit is set when a sorted list has a `key`, the key follows the flags as a one byte step count and each step as a length byte and its UTF-8 bytes, then the "of" schema.
+//! 
+//! ```text
+//! // [       23,   129,     21, ...]
+//! // [data type, flags, "of" table schema]
+//! ```
+//! 
 //! ### Map (Collection)
 //! 
 //! ```
@@ -841,6 +895,13 @@
 //! # Ok::<(), NP_Error>(()) 
 //! ```
 //! 
+//! Maps use the same flags byte as lists, the lowest bit is set for `"keys": "hashed"`.
+//! 
+//! ```text
+//! // [       22,   129,        8, 0]
+//! // [data type, flags, value schema]
+//! ```
+//! 
 //! ### Tuple (Collection)
 //! 
 //! ```
@@ -26,36 +26,27 @@ impl NP_HashMap {
     }
 
     pub fn insert_hash(&mut self, hash: u32, value: usize) -> Result<(), NP_Error> {
-        if self.size + 1 > 255 {
-            return Err(NP_Error::new("Too many records in hash map!"));
-        }
-
-        self.size += 1;
-
         let bucket = hash as usize % MAP_SIZE;
 
-        if self.data[bucket].len() == 0 {
-            self.data[bucket].push((hash, value));
-        } else {
-            for (k, v) in self.data[bucket].iter_mut() {
-                if *k == hash {
-                    *v = value;
-                    return Ok(())
-                }
+        for (k, v) in self.data[bucket].iter_mut() {
+            if *k == hash {
+                *v = value;
+                return Ok(())
             }
-            self.data[bucket].push((hash, value));
         }
 
+        // only new keys count towards the size
+        if self.size == u16::MAX {
+            return Err(NP_Error::new_static("Too many records in hash map!"));
+        }
+
+        self.size += 1;
+        self.data[bucket].push((hash, value));
+
         Ok(())
     }
 
     pub fn insert(&mut self, key: &str, value: usize) -> Result<u32, NP_Error> {
-        
-        if self.size + 1 > 255 {
-            return Err(NP_Error::new("Too many records in hash map!"));
-        }
-
-        self.size += 1;
 
         let hash = murmurhash3_x86_32(key.as_bytes(), SEED);
     
@@ -85,12 +76,13 @@ impl NP_HashMap {
     }
 
     pub fn delete(&mut self, key: &str) {
-        self.size -= 1;
         let hash = murmurhash3_x86_32(key.as_bytes(), SEED);
         let bucket = hash as usize % MAP_SIZE;
         match self.data.get_mut(bucket) {
             Some(bucket) => {
+                let len = bucket.len();
                 bucket.retain(|(k, _v)| *k != hash);
+                self.size -= (len - bucket.len()) as u16;
             },
             _ => { }
         }
@@ -174,4 +166,8 @@ pub fn murmurhash3_x86_32(bytes: &[u8], seed: u32) -> u32 {
     h1 = fmix32(h1);
 
     return h1;
-}
\ No newline at end of file
+}
+/// 64 bit FNV-1a, for hashes that are stored or compared across builds
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325u64, |state, byte| (state ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
@@ -0,0 +1,289 @@
+//! Propose a schema from example JSON
+//!
+//! `NP_Schema::infer_from_json` looks at one or more example values and returns a JSON schema that can hold all of them.  It's meant as a starting point when prototyping or moving an existing JSON dataset to NoProto, the result should be reviewed before it's used for real data.
+//!
+//! - Objects become tables, with a column for every key seen in any example.  Objects with more keys than `max_columns` become maps.
+//! - Integers use the smallest type that holds every example, unsigned if none are negative.  Numbers with a decimal are `double`.
+//! - Strings that all look like UUIDs or ULIDs use those types, integers that all look like millisecond timestamps use `date`.  Both are optional.
+//! - Lists merge the types of all their items.  Set `examples` in the options to merge a list of example values the same way.
+//! - Values that are only ever `null` and scalars with mixed types become `any`.  Collections mixed with other types are an error.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::schema::NP_Schema;
+//! use no_proto::infer::NP_Infer_Options;
+//! use no_proto::json_flex::json_decode;
+//!
+//! let example = json_decode(r#"{
+//!     "id": "6ba7b810-9dad-11d1-80b4-00c04fd430c8",
+//!     "age": 31,
+//!     "score": -2.5,
+//!     "tags": ["pilot", "brave"],
+//!     "friends": [{"name": "Bob", "since": 1604965249484}, {"name": "Bill", "visits": 300}]
+//! }"#.to_owned())?;
+//!
+//! let schema = NP_Schema::infer_from_json(&example, NP_Infer_Options::default())?;
+//!
+//! assert_eq!(schema.stringify(), r#"{"type":"table","columns":[["id",{"type":"uuid"}],["age",{"type":"u8"}],["score",{"type":"double"}],["tags",{"type":"list","of":{"type":"string"}}],["friends",{"type":"list","of":{"type":"table","columns":[["name",{"type":"string"}],["since",{"type":"date"}],["visits",{"type":"u16"}]]}}]]}"#);
+//!
+//! // the example fits the proposed schema
+//! let factory = NP_Factory::new(&schema.stringify())?;
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set_json_value(&[], &example)?;
+//! assert_eq!(new_buffer.get::<u16>(&["friends", "1", "visits"])?, Some(300));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::json_flex::{JSMAP, NP_JSON};
+use crate::pointer::uuid::NP_UUID;
+use crate::pointer::ulid::NP_ULID;
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// 2000-01-01 and 2100-01-01 in milliseconds
+const DATE_MIN: i64 = 946_684_800_000;
+const DATE_MAX: i64 = 4_102_444_800_000;
+
+/// Options for `NP_Schema::infer_from_json`
+#[derive(Debug, Clone)]
+pub struct NP_Infer_Options {
+    /// The root value is a list of examples instead of a single value
+    pub examples: bool,
+    /// Use `uuid` and `ulid` types for strings that all match their format
+    pub detect_ids: bool,
+    /// Use the `date` type for integers that are all millisecond timestamps between the years 2000 and 2100
+    pub detect_dates: bool,
+    /// Objects with more keys than this become maps instead of tables, tables with more than 255 columns are made wide
+    pub max_columns: usize
+}
+
+impl Default for NP_Infer_Options {
+    fn default() -> Self {
+        NP_Infer_Options { examples: false, detect_ids: true, detect_dates: true, max_columns: 64 }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Inferred {
+    Null,
+    Bool,
+    Int { min: i128, max: i128 },
+    Float,
+    Str { uuid: bool, ulid: bool },
+    List(Box<Inferred>),
+    Object(Vec<(String, Inferred)>),
+    // scalars of different types
+    Any,
+    // collections with other types, can't be represented
+    Mixed
+}
+
+fn is_uuid(value: &str) -> bool {
+    value.len() == 36 && value.bytes().enumerate().all(|(idx, c)| {
+        if idx == 8 || idx == 13 || idx == 18 || idx == 23 { c == b'-' } else { c.is_ascii_hexdigit() }
+    }) && NP_UUID::from_string(value).is_some()
+}
+
+fn is_ulid(value: &str) -> bool {
+    value.bytes().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) && NP_ULID::from_string(value).is_some()
+}
+
+fn infer(json: &NP_JSON) -> Inferred {
+    match json {
+        NP_JSON::Null => Inferred::Null,
+        NP_JSON::True | NP_JSON::False => Inferred::Bool,
+        NP_JSON::Integer(x) => Inferred::Int { min: *x as i128, max: *x as i128 },
+        NP_JSON::Unsigned(x) => Inferred::Int { min: *x as i128, max: *x as i128 },
+        NP_JSON::Float(_) => Inferred::Float,
+        NP_JSON::String(x) => Inferred::Str { uuid: is_uuid(x), ulid: is_ulid(x) },
+        NP_JSON::Array(items) => {
+            Inferred::List(Box::new(items.iter().fold(Inferred::Null, |acc, item| merge(acc, infer(item)))))
+        },
+        NP_JSON::Dictionary(map) => {
+            Inferred::Object(map.values.iter().map(|(key, value)| (key.clone(), infer(value))).collect())
+        }
+    }
+}
+
+fn merge(a: Inferred, b: Inferred) -> Inferred {
+    match (a, b) {
+        (Inferred::Null, x) | (x, Inferred::Null) => x,
+        (Inferred::Bool, Inferred::Bool) => Inferred::Bool,
+        (Inferred::Int { min: a_min, max: a_max }, Inferred::Int { min: b_min, max: b_max }) => {
+            Inferred::Int { min: core::cmp::min(a_min, b_min), max: core::cmp::max(a_max, b_max) }
+        },
+        (Inferred::Int { .. }, Inferred::Float) | (Inferred::Float, Inferred::Int { .. }) | (Inferred::Float, Inferred::Float) => Inferred::Float,
+        (Inferred::Str { uuid: a_uuid, ulid: a_ulid }, Inferred::Str { uuid: b_uuid, ulid: b_ulid }) => {
+            Inferred::Str { uuid: a_uuid && b_uuid, ulid: a_ulid && b_ulid }
+        },
+        (Inferred::List(a), Inferred::List(b)) => Inferred::List(Box::new(merge(*a, *b))),
+        (Inferred::Object(mut a), Inferred::Object(b)) => {
+            for (key, value) in b {
+                match a.iter().position(|(a_key, _)| *a_key == key) {
+                    Some(idx) => {
+                        let existing = core::mem::replace(&mut a[idx].1, Inferred::Null);
+                        a[idx].1 = merge(existing, value);
+                    },
+                    None => a.push((key, value))
+                }
+            }
+            Inferred::Object(a)
+        },
+        (a, b) => {
+            if is_collection(&a) || is_collection(&b) { Inferred::Mixed } else { Inferred::Any }
+        }
+    }
+}
+
+fn is_collection(inferred: &Inferred) -> bool {
+    match inferred {
+        Inferred::List(_) | Inferred::Object(_) | Inferred::Mixed => true,
+        _ => false
+    }
+}
+
+fn type_json(type_name: &str) -> JSMAP {
+    let mut schema = JSMAP::new();
+    schema.insert("type".to_owned(), NP_JSON::String(type_name.to_owned()));
+    schema
+}
+
+fn to_schema(inferred: &Inferred, options: &NP_Infer_Options, path: &mut Vec<String>) -> Result<NP_JSON, NP_Error> {
+
+    let schema = match inferred {
+        Inferred::Null | Inferred::Any => type_json("any"),
+        Inferred::Mixed => {
+            return Err(NP_Error::new(String::from("Examples mix collections with other types at path ") + &path.join(".")));
+        },
+        Inferred::Bool => type_json("bool"),
+        Inferred::Float => type_json("double"),
+        Inferred::Int { min, max } => {
+            let type_name = if options.detect_dates && *min >= DATE_MIN as i128 && *max <= DATE_MAX as i128 {
+                "date"
+            } else if *min >= 0 {
+                match *max {
+                    x if x <= core::u8::MAX as i128 => "u8",
+                    x if x <= core::u16::MAX as i128 => "u16",
+                    x if x <= core::u32::MAX as i128 => "u32",
+                    _ => "u64"
+                }
+            } else {
+                match (*min, *max) {
+                    (a, b) if a >= core::i8::MIN as i128 && b <= core::i8::MAX as i128 => "i8",
+                    (a, b) if a >= core::i16::MIN as i128 && b <= core::i16::MAX as i128 => "i16",
+                    (a, b) if a >= core::i32::MIN as i128 && b <= core::i32::MAX as i128 => "i32",
+                    _ => "i64"
+                }
+            };
+            type_json(type_name)
+        },
+        Inferred::Str { uuid, ulid } => {
+            if options.detect_ids && *uuid {
+                type_json("uuid")
+            } else if options.detect_ids && *ulid {
+                type_json("ulid")
+            } else {
+                type_json("string")
+            }
+        },
+        Inferred::List(of) => {
+            path.push("*".to_owned());
+            let of = to_schema(of, options, path)?;
+            path.pop();
+            let mut schema = type_json("list");
+            schema.insert("of".to_owned(), of);
+            schema
+        },
+        Inferred::Object(columns) if columns.len() > options.max_columns || columns.is_empty() => {
+            let value = columns.iter().fold(Inferred::Null, |acc, (_key, value)| merge(acc, value.clone()));
+            path.push("*".to_owned());
+            let value = to_schema(&value, options, path)?;
+            path.pop();
+            let mut schema = type_json("map");
+            schema.insert("value".to_owned(), value);
+            schema
+        },
+        Inferred::Object(columns) => {
+            let mut schema_columns: Vec<NP_JSON> = Vec::with_capacity(columns.len());
+            let mut wide = columns.len() > 255;
+            for (key, value) in columns {
+                if key.len() > 65535 {
+                    return Err(NP_Error::new(String::from("Column names can't be longer than 65,535 bytes at path ") + &path.join(".")));
+                }
+                wide |= key.len() > 255;
+                path.push(key.clone());
+                let mut column: Vec<NP_JSON> = Vec::with_capacity(2);
+                column.push(NP_JSON::String(key.clone()));
+                column.push(to_schema(value, options, path)?);
+                schema_columns.push(NP_JSON::Array(column));
+                path.pop();
+            }
+            let mut schema = type_json("table");
+            if wide {
+                schema.insert("wide".to_owned(), NP_JSON::True);
+            }
+            schema.insert("columns".to_owned(), NP_JSON::Array(schema_columns));
+            schema
+        }
+    };
+
+    Ok(NP_JSON::Dictionary(schema))
+}
+
+/// Propose a schema for the example JSON value, see the [module docs](index.html).
+pub fn infer_from_json(json: &NP_JSON, options: NP_Infer_Options) -> Result<NP_JSON, NP_Error> {
+
+    let inferred = match (json, options.examples) {
+        (NP_JSON::Array(items), true) => items.iter().fold(Inferred::Null, |acc, item| merge(acc, infer(item))),
+        (_, true) => return Err(NP_Error::new_static("Examples must be a list of values!")),
+        (_, false) => infer(json)
+    };
+
+    let mut path: Vec<String> = Vec::new();
+    to_schema(&inferred, &options, &mut path)
+}
+
+#[test]
+fn infer_works() -> Result<(), NP_Error> {
+    use crate::json_flex::json_decode;
+
+    let infer = |json: &str, options: NP_Infer_Options| -> Result<String, NP_Error> {
+        Ok(infer_from_json(&*json_decode(json.to_owned())?, options)?.stringify())
+    };
+
+    let examples = NP_Infer_Options { examples: true, ..Default::default() };
+
+    assert_eq!(infer("[1, 255]", examples.clone())?, r#"{"type":"u8"}"#);
+    assert_eq!(infer("[-1, 255]", examples.clone())?, r#"{"type":"i16"}"#);
+    assert_eq!(infer("[0, 4294967296]", examples.clone())?, r#"{"type":"u64"}"#);
+    assert_eq!(infer("[-2147483648, 5]", examples.clone())?, r#"{"type":"i32"}"#);
+    assert_eq!(infer("[1, 2.5]", examples.clone())?, r#"{"type":"double"}"#);
+    assert_eq!(infer("[1, \"two\", null]", examples.clone())?, r#"{"type":"any"}"#);
+    assert_eq!(infer("[\"01ERASY5A5KVKANC1CJGRZXYW8\"]", examples.clone())?, r#"{"type":"ulid"}"#);
+    assert_eq!(infer("[\"01ERASY5A5KVKANC1CJGRZXYW8\", \"hello\"]", examples.clone())?, r#"{"type":"string"}"#);
+    assert_eq!(infer("[1604965249484]", examples.clone())?, r#"{"type":"date"}"#);
+    assert_eq!(infer("[1604965249484]", NP_Infer_Options { detect_dates: false, ..examples.clone() })?, r#"{"type":"u64"}"#);
+
+    // columns are merged across examples
+    assert_eq!(infer(r#"[{"a": 1}, {"b": true, "a": null}]"#, examples.clone())?, r#"{"type":"table","columns":[["a",{"type":"u8"}],["b",{"type":"bool"}]]}"#);
+
+    // large and empty objects become maps
+    assert_eq!(infer(r#"{"a": 1, "b": 2, "c": -3}"#, NP_Infer_Options { max_columns: 2, ..Default::default() })?, r#"{"type":"map","value":{"type":"i8"}}"#);
+    assert_eq!(infer_from_json(&NP_JSON::Dictionary(JSMAP::new()), NP_Infer_Options::default())?.stringify(), r#"{"type":"map","value":{"type":"any"}}"#);
+
+    // without the option a list is just a list
+    assert_eq!(infer("[[1], []]", NP_Infer_Options::default())?, r#"{"type":"list","of":{"type":"list","of":{"type":"u8"}}}"#);
+
+    let long_key = "k".repeat(300);
+    assert_eq!(infer(&alloc::format!(r#"{{"{}": 1}}"#, long_key), NP_Infer_Options::default())?, alloc::format!(r#"{{"type":"table","wide":true,"columns":[["{}",{{"type":"u8"}}]]}}"#, long_key));
+    assert!(infer(r#"[{"a": 1}, {"a": [1]}]"#, examples.clone()).is_err());
+    assert!(infer("1", examples.clone()).is_err());
+
+    Ok(())
+}
@@ -41,6 +41,7 @@ use alloc::borrow::ToOwned;
 use alloc::string::ToString;
 use core::str::FromStr;
 use core::ops::Index;
+use core::fmt::Write;
 use crate::{error::NP_Error};
 
 /// The JSON representation of a JS Map
@@ -110,6 +111,8 @@ pub enum NP_JSON {
     String(String), 
     /// Integer JSON type
     Integer(i64), 
+    /// Integer JSON type for numbers above `i64::MAX`
+    Unsigned(u64), 
     /// Float JSON type
     Float(f64), 
     /// Map JSON type
@@ -161,6 +164,9 @@ impl NP_JSON {
             NP_JSON::Integer(int) => {
                 NP_JSON::Integer(*int)
             },
+            NP_JSON::Unsigned(int) => {
+                NP_JSON::Unsigned(*int)
+            },
             NP_JSON::Float(num) => {
                 NP_JSON::Float(*num)
             },
@@ -189,6 +195,14 @@ impl NP_JSON {
             _ => None,
         }
     }
+    /// Get this value as a u64, works for positive `Integer` values and `Unsigned` values
+    pub fn into_u64(&self) -> Option<u64> {
+        match self {
+            &NP_JSON::Integer(v) if v >= 0 => Some(v as u64),
+            &NP_JSON::Unsigned(v) => Some(v),
+            _ => None,
+        }
+    }
     /// Get this value as an f64
     pub fn into_f64(&self) -> Option<&f64> {
         match self {
@@ -255,7 +269,7 @@ impl NP_JSON {
     /// Check if this value is an integer
     pub fn is_integer(&self) -> bool {
         match self {
-            &NP_JSON::Integer(_) => true,
+            &NP_JSON::Integer(_) | &NP_JSON::Unsigned(_) => true,
             _ => false,
         }
     }
@@ -301,16 +315,29 @@ impl NP_JSON {
             _ => None,
         }
     }
+    /// JSON value for a u64, `Unsigned` is only used if the value doesn't fit in an `Integer`
+    pub fn from_u64(value: u64) -> NP_JSON {
+        if value > i64::MAX as u64 {
+            NP_JSON::Unsigned(value)
+        } else {
+            NP_JSON::Integer(value as i64)
+        }
+    }
     /// Stringify this JSON object and it's children
     pub fn stringify(&self) -> String {
         match self {
             &NP_JSON::String(ref v) => {
                 let mut string: String = "\"".to_owned();
-                string.push_str(v.replace("\"", "\\\"").as_str());
+                escape_into(v, &mut string);
                 string.push_str("\"");
                 string
             },
             &NP_JSON::Integer(ref v) => v.to_string(),
+            &NP_JSON::Unsigned(ref v) => v.to_string(),
+            // JSON has no NaN or infinity
+            &NP_JSON::Float(ref v) if !v.is_finite() => "null".to_owned(),
+            // whole numbers outside the integer range are written with an exponent, the parser refuses them as integers
+            &NP_JSON::Float(ref v) if *v >= 18446744073709551616.0 || *v < -9223372036854775808.0 => alloc::format!("{:e}", v),
             &NP_JSON::Float(ref v) => v.to_string(),
             &NP_JSON::Dictionary(ref v) => {
                 let mut string: String = "{".to_owned();
@@ -322,7 +349,7 @@ impl NP_JSON {
                         string.push(',');
                     }
                     let mut substring = "\"".to_owned();
-                    substring.push_str(k.replace("\"", "\\\"").as_str());
+                    escape_into(k, &mut substring);
                     substring.push_str("\":");
                     string.push_str(substring.as_str());
                     string.push_str(&v.stringify());
@@ -353,6 +380,30 @@ impl NP_JSON {
     }
 }
 
+// escape a string for use between quotes in JSON
+fn escape_into(value: &str, string: &mut String) {
+    // writing to a String can't fail
+    let _ = write_escaped(value, string);
+}
+
+/// Write a string escaped for use between quotes in JSON
+pub(crate) fn write_escaped<W: Write>(value: &str, out: &mut W) -> core::fmt::Result {
+    for c in value.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            '\u{08}' => out.write_str("\\b")?,
+            '\u{0c}' => out.write_str("\\f")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?
+        }
+    }
+    Ok(())
+}
+
 impl Index<usize> for NP_JSON {
     type Output = NP_JSON;
     fn index<'a>(&'a self, id: usize) -> &'a Self::Output {
@@ -399,1421 +450,425 @@ impl<'a> Index<&'a str> for NP_JSON {
 }
 
 
-fn recursive(v: &mut NP_JSON,
-             a_chain: Vec<i64>,
-             d_chain: Vec<String>,
-             mut a_nest: i64,
-             mut d_nest: i64,
-             last_chain: char,
-             last_c: char,
-             func: fn(&mut NP_JSON,
-                      Option<String>,
-                      Vec<i64>,
-                      Vec<String>,
-                      i64,
-                      i64,
-                      char) -> Result<(), NP_Error>
-                     ,
-             value: Option<String>,
-             log: String)
-             -> Result<bool, NP_Error> {
-
-    let is_find = match *v {
-
-        NP_JSON::Array(ref mut vvz) => {
-            let i = *NP_Error::unwrap(a_chain.get(a_nest as usize))?;
-            let is_find: bool = {
-                let vvv = vvz.get_mut(i as usize);
-                let is_find: bool = match vvv {
-                    Some(mut vvvv) => {
-                        a_nest += 1;
-                        recursive(&mut vvvv,
-                                  a_chain.clone(),
-                                  d_chain.clone(),
-                                  a_nest,
-                                  d_nest,
-                                  last_chain,
-                                  last_c,
-                                  func,
-                                  value.clone(),
-                                  log)?;
-                        a_nest -= 1;
-                        true
-                    }
-                    None => false,
-                };
-                is_find
-            };
-            if !is_find {
-            }
-            is_find
-        }
-
-        NP_JSON::Dictionary(ref mut vv) => {
-            let o_key = d_chain.get(d_nest as usize);
-            match o_key {
-                Some(ref key) => {
-                    let vvv: Option<&mut NP_JSON> = vv.get_mut(*key);              
-
-                    let is_find: bool = match vvv {
-                        Some(mut vvvv) => {
-                            d_nest += 1;
-                            recursive(&mut vvvv,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      value.clone(),
-                                      log)?;
-                            d_nest -= 1;
-                            true
-                        }
-                        None => false,
-                    };
-                    is_find
-                }
-                None => false,
-            }
-        }
-        _ => true,
-    };
-
-    if !is_find {
-        func(v,
-             value,
-             a_chain.clone(),
-             d_chain.clone(),
-             a_nest,
-             d_nest,
-             last_c)?;
-    }
-    Ok(is_find)
-}
-
 /// Parse a JSON string into a JSON object in memory
+/// 
+/// Integers that don't fit in an `i64` are parsed as `NP_JSON::Unsigned` if they fit in a `u64`.  Larger integers and numbers too large for an `f64` (like `1e400`) are errors, so no number is quietly rounded or turned into `null`.  Every JSON escape sequence is supported, including UTF-16 surrogate pairs.
 pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
+    let mut parser = JSON_Parser::new(JSON_Str::new(text.as_str()));
+    let value = parser.value()?;
+    parser.finish()?;
+    Ok(Box::new(value))
+}
 
-    let mut ret = Box::new(NP_JSON::Null);
-
-    let mut pos: usize = 0;
-
-    let mut chain: Vec<char> = Vec::new();
-    let mut d_chain: Vec<String> = Vec::new();
-    let mut a_chain: Vec<i64> = Vec::new();
-    let mut last_chain: char = ' ';
-    let mut last_active_char: char = ' ';
-    let mut key: String;
-    let mut string: String = "".to_owned();
-    let mut num: String = "".to_owned();
-    let mut last_c: char = ' ';
-    let mut s_true: String = "".to_owned();
-    let mut s_false: String = "".to_owned();
-    let mut s_null: String = "".to_owned();
-
-    let body: Vec<char> = text.chars().collect();
-    let size = body.len();
-    let mut done = false;
-    while !done {
-
-        let c: char = body[pos];
-
-        match last_chain {
-            's' => {
-                string.push(c);
-            }
-            'w' => {
-                string.push(c);
-            }
-            'n' => {
-                num.push(c);
-            }
-            't' => {
-                s_true.push(c);
-            }
-            'f' => {
-                s_false.push(c);
-            }
-            '0' => {
-                s_null.push(c);
-            }
-            _ => {}
-        };
-
-        match c {
-
-            '[' => {
+// deeper documents are rejected so untrusted input can't overflow the stack
+const MAX_DEPTH: usize = 256;
 
-                match last_chain {
+/// Where a `JSON_Parser` gets its bytes from
+pub(crate) trait JSON_Source {
+    /// The next byte without consuming it, `None` at the end of the input
+    fn peek(&mut self) -> Option<u8>;
+    /// Consume the byte returned by `peek`
+    fn bump(&mut self);
+}
 
-                    's' => {}
-                    'w' => {}
+/// JSON text already in memory
+pub(crate) struct JSON_Str<'json> {
+    bytes: &'json [u8],
+    pos: usize
+}
 
-                    _ => {
+impl<'json> JSON_Str<'json> {
+    pub fn new(text: &'json str) -> Self {
+        JSON_Str { bytes: text.as_bytes(), pos: 0 }
+    }
+}
 
-                        let a = 'a';
-                        chain.push(a);
-                        last_chain = a;
-                        a_chain.push(0);
+impl<'json> JSON_Source for JSON_Str<'json> {
+    #[inline(always)]
+    fn peek(&mut self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+    #[inline(always)]
+    fn bump(&mut self) {
+        self.pos += 1;
+    }
+}
 
-                        let is_root = match *ret {
+/// JSON text read from an `std::io::Read` a chunk at a time
+#[cfg(feature = "std")]
+pub(crate) struct JSON_Reader<R: std::io::Read> {
+    reader: R,
+    chunk: Vec<u8>,
+    pos: usize,
+    len: usize,
+    /// The read error that ended the input early, if there was one
+    pub error: Option<std::io::Error>
+}
 
-                            NP_JSON::Null => {
-                                *ret = NP_JSON::Array(Vec::new());
-                                true
-                            }
+#[cfg(feature = "std")]
+impl<R: std::io::Read> JSON_Reader<R> {
+    pub fn new(reader: R) -> Self {
+        JSON_Reader { reader, chunk: alloc::vec![0u8; 4096], pos: 0, len: 0, error: None }
+    }
+}
 
-                            _ => false,
-                        };
-
-                        if !is_root {
-                            let a_nest = 0i64;
-                            let d_nest = 0i64;
-                            let log: String = "".to_owned();
-                            fn func(v: &mut NP_JSON,
-                                    _: Option<String>,
-                                    _: Vec<i64>,
-                                    d_chain: Vec<String>,
-                                    _: i64,
-                                    _: i64,
-                                    _: char) -> Result<(), NP_Error> {
-                                match *v {
-                                    NP_JSON::Array(ref mut vv) => {
-                                        vv.push(NP_JSON::Array(Vec::new()));
-                                    }
-                                    NP_JSON::Dictionary(ref mut vv) => {
-                                        let key = NP_Error::unwrap(d_chain.last())?.clone();
-                                        vv.insert(key, NP_JSON::Array(Vec::new()));
-                                    }
-                                    _ => {}
-                                };
-                                Ok(())
-                            }
-                            recursive(&mut ret,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      None,
-                                      log)?;
-                        }
-                    }
-                };
-                last_active_char = c.clone();
+#[cfg(feature = "std")]
+impl<R: std::io::Read> JSON_Source for JSON_Reader<R> {
+    fn peek(&mut self) -> Option<u8> {
+        while self.pos == self.len {
+            if self.error.is_some() {
+                return None;
             }
-
-            ']' => {
-                match last_chain {
-
-                    's' => {}
-                    'w' => {}
-                    't' => {
-
-                        NP_Error::unwrap(s_true.pop())?;
-                        s_true = s_true.trim().to_string();
-                        if s_true != "true" {
-                            return Err(NP_Error::new("JSON Parse Error"));
-                        }
-
-                        let a_nest = 0i64;
-                        let d_nest = 0i64;
-                        let log: String = "".to_owned();
-                        fn func(v: &mut NP_JSON,
-                                _: Option<String>,
-                                _: Vec<i64>,
-                                _: Vec<String>,
-                                _: i64,
-                                _: i64,
-                                _: char) -> Result<(), NP_Error> {
-                            match *v {
-                                NP_JSON::Array(ref mut vv) => {
-                                    vv.push(NP_JSON::True);
-                                }
-                                _ => {}
-                            };
-                            Ok(())
-                        }
-                        recursive(&mut ret,
-                                  a_chain.clone(),
-                                  d_chain.clone(),
-                                  a_nest,
-                                  d_nest,
-                                  last_chain,
-                                  last_c,
-                                  func,
-                                  None,
-                                  log)?;
-
-                        NP_Error::unwrap(chain.pop())?;
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                        NP_Error::unwrap(a_chain.pop())?;
-                        s_true = "".to_owned();
-                    }
-
-                    'f' => {
-
-                        NP_Error::unwrap(s_false.pop())?;
-                        s_false = s_false.trim().to_string();
-                        if s_false != "false" {
-                            return Err(NP_Error::new("JSON Parse Error"));
-                        }
-
-                        let a_nest = 0i64;
-                        let d_nest = 0i64;
-                        let log: String = "".to_owned();
-                        fn func(v: &mut NP_JSON,
-                                _: Option<String>,
-                                _: Vec<i64>,
-                                _: Vec<String>,
-                                _: i64,
-                                _: i64,
-                                _: char) -> Result<(), NP_Error> {
-                            match *v {
-                                NP_JSON::Array(ref mut vv) => {
-                                    vv.push(NP_JSON::False);
-                                }
-                                _ => {}
-                            };
-                            Ok(())
-                        }
-                        recursive(&mut ret,
-                                  a_chain.clone(),
-                                  d_chain.clone(),
-                                  a_nest,
-                                  d_nest,
-                                  last_chain,
-                                  last_c,
-                                  func,
-                                  None,
-                                  log)?;
-
-                        NP_Error::unwrap(chain.pop())?;
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                        NP_Error::unwrap(a_chain.pop())?;
-
-                        s_false = "".to_owned();
-                    }
-
-                    '0' => {
-
-                        NP_Error::unwrap(s_null.pop())?;
-                        s_null = s_null.trim().to_string();
-                        if s_null != "null" {
-                            return Err(NP_Error::new("JSON Parse Error"));
-                        }
-
-                        let a_nest = 0i64;
-                        let d_nest = 0i64;
-                        let log: String = "".to_owned();
-                        fn func(v: &mut NP_JSON,
-                                _: Option<String>,
-                                _: Vec<i64>,
-                                _: Vec<String>,
-                                _: i64,
-                                _: i64,
-                                _: char) -> Result<(), NP_Error> {
-                            match *v {
-                                NP_JSON::Array(ref mut vv) => {
-                                    vv.push(NP_JSON::Null);
-                                }
-                                _ => {}
-                            };
-                            Ok(())
-                        }
-                        recursive(&mut ret,
-                                  a_chain.clone(),
-                                  d_chain.clone(),
-                                  a_nest,
-                                  d_nest,
-                                  last_chain,
-                                  last_c,
-                                  func,
-                                  None,
-                                  log)?;
-
-
-                        NP_Error::unwrap(chain.pop())?;
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                        NP_Error::unwrap(a_chain.pop())?;
-
-                        s_null = "".to_owned();
-                    }
-
-                    'n' => {
-
-                        let a_nest = 0i64;
-                        let d_nest = 0i64;
-                        let log: String = "".to_owned();
-                        fn func(v: &mut NP_JSON,
-                                value: Option<String>,
-                                _: Vec<i64>,
-                                _: Vec<String>,
-                                _: i64,
-                                _: i64,
-                                _: char) -> Result<(), NP_Error> {
-                            match *v {
-                                NP_JSON::Array(ref mut vv) => {
-
-                                    let mut new_num = NP_Error::unwrap(value)?;
-                                    NP_Error::unwrap(new_num.pop())?;
-                                    new_num = new_num.trim().to_string();
-
-                                    match new_num.find('.') {
-                                        Some(_) => vv.push( NP_JSON::Float(f64::from_str(&new_num.clone())?) ),
-                                        None    => vv.push( NP_JSON::Integer(i64::from_str(&new_num.clone())?) ),
-                                    };
-                                }
-                                _ => {}
-                            };
-                            Ok(())
-                        }
-                        recursive(&mut ret,
-                                  a_chain.clone(),
-                                  d_chain.clone(),
-                                  a_nest,
-                                  d_nest,
-                                  last_chain,
-                                  last_c,
-                                  func,
-                                  Some(num),
-                                  log)?;
-
-                        num = "".to_owned();
-
-                        NP_Error::unwrap(chain.pop())?;
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                        NP_Error::unwrap(a_chain.pop())?;
-
-                    }
-
-                    'a' => {
-
-
-                        if last_active_char == ',' {
-
-                            let a_nest = 0i64;
-                            let d_nest = 0i64;
-                            let log: String = "".to_owned();
-                            fn func(v: &mut NP_JSON,
-                                    _: Option<String>,
-                                    _: Vec<i64>,
-                                    _: Vec<String>,
-                                    _: i64,
-                                    _: i64,
-                                    _: char) -> Result<(), NP_Error> {
-                                match *v {
-                                    NP_JSON::Array(ref mut vv) => {
-                                        vv.push(NP_JSON::Null);
-                                    }
-                                    _ => {}
-                                };
-                                Ok(())
-                            }
-                            recursive(&mut ret,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      None,
-                                      log)?;
-
-                        }
-
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                        NP_Error::unwrap(a_chain.pop())?;
-                    }
-
-                    _ => return Err(NP_Error::new("JSON Parse Error: Unknown chain from Array")),
-                }
-
-                last_active_char = c.clone();
-
+            match self.reader.read(&mut self.chunk) {
+                Ok(0) => return None,
+                Ok(len) => { self.pos = 0; self.len = len; },
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => { },
+                Err(e) => { self.error = Some(e); }
             }
+        }
+        Some(self.chunk[self.pos])
+    }
+    #[inline(always)]
+    fn bump(&mut self) {
+        self.pos += 1;
+    }
+}
 
-            '{' => {
-
-                match last_chain {
-
-                    's' => {}
-                    'w' => {}
-
-                    'v' => {
-
-                        let a = 'd';
-                        chain.push(a);
-                        last_chain = a;
+/// Pull parser for JSON text, `value` parses the next value into an `NP_JSON` while the other methods step through collections one token at a time
+pub(crate) struct JSON_Parser<S: JSON_Source> {
+    source: S,
+    pos: usize,
+    depth: usize
+}
 
-                        let a_nest = 0i64;
-                        let d_nest = 0i64;
-                        let log: String = "".to_owned();
+impl<S: JSON_Source> JSON_Parser<S> {
 
-                        fn func(v: &mut NP_JSON,
-                                _: Option<String>,
-                                _: Vec<i64>,
-                                d_chain: Vec<String>,
-                                _: i64,
-                                _: i64,
-                                _: char) -> Result<(), NP_Error> {
-                            match *v {
-                                NP_JSON::Array(ref mut vv) => {
-                                    vv.push(NP_JSON::Dictionary(JSMAP::new()));
-                                }
-                                NP_JSON::Dictionary(ref mut vv) => {
-                                    let key = NP_Error::unwrap(d_chain.last())?.clone();
-                                    vv.insert(key, NP_JSON::Dictionary(JSMAP::new()));
-                                }
-                                _ => {}
-                            };
-                            Ok(())
-                        }
-
-                        recursive(&mut ret,
-                                  a_chain.clone(),
-                                  d_chain.clone(),
-                                  a_nest,
-                                  d_nest,
-                                  last_chain,
-                                  last_c,
-                                  func,
-                                  None,
-                                  log)?;
-                    }
+    pub fn new(source: S) -> Self {
+        JSON_Parser { source, pos: 0, depth: 0 }
+    }
 
-                    _ => {
+    #[cfg(feature = "std")]
+    pub fn source(&self) -> &S {
+        &self.source
+    }
 
-                        let a = 'd';
-                        chain.push(a);
-                        last_chain = a;
+    pub fn error(&self, message: &str) -> NP_Error {
+        NP_Error::new(alloc::format!("Error parsing JSON at character {}, {}!", self.pos, message))
+    }
 
+    #[inline(always)]
+    pub fn peek(&mut self) -> Option<u8> {
+        self.source.peek()
+    }
 
-                        let is_root = match *ret {
-                            NP_JSON::Null => {
-                                *ret = NP_JSON::Dictionary(JSMAP::new());
-                                true
-                            }
-                            _ => false,
-                        };
-
-                        if !is_root {
-                            let a_nest = 0i64;
-                            let d_nest = 0i64;
-                            let log: String = "".to_owned();
-                            fn func(v: &mut NP_JSON,
-                                    _: Option<String>,
-                                    _: Vec<i64>,
-                                    d_chain: Vec<String>,
-                                    _: i64,
-                                    _: i64,
-                                    _: char) -> Result<(), NP_Error> {
-                                match *v {
-                                    NP_JSON::Array(ref mut vv) => {
-                                        vv.push(NP_JSON::Dictionary(JSMAP::new()));
-                                    }
-                                    NP_JSON::Dictionary(ref mut vv) => {
-                                        let key = NP_Error::unwrap(d_chain.last())?.clone();
-                                        vv.insert(key, NP_JSON::Dictionary(JSMAP::new()));
-                                    }
-                                    _ => {}
-                                };
-                                Ok(())
-                            }
-                            recursive(&mut ret,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      None,
-                                      log)?;
-                        }
-                    }
-                }
+    #[inline(always)]
+    fn bump(&mut self) {
+        self.source.bump();
+        self.pos += 1;
+    }
 
-                last_active_char = c.clone();
+    pub fn whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.bump();
+        }
+    }
 
-            }
+    fn expect(&mut self, c: u8) -> Result<(), NP_Error> {
+        self.whitespace();
+        if self.peek() == Some(c) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.error(&alloc::format!("expected '{}'", c as char)))
+        }
+    }
 
-            '}' => {
-                match last_chain {
-
-                    's' => {}
-                    'w' => {}
-
-                    't' => {
-
-                        NP_Error::unwrap(s_true.pop())?;
-                        s_true = s_true.trim().to_string();
-                        if s_true != "true" {
-                            return Err(NP_Error::new("JSON Parse Error"));
-                        }
-
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-
-                        if last_chain == 'v' {
-                            NP_Error::unwrap(chain.pop())?;
-                            let a_nest = 0i64;
-                            let d_nest = 0i64;
-                            let log: String = "".to_owned();
-                            fn func(v: &mut NP_JSON,
-                                    _: Option<String>,
-                                    _: Vec<i64>,
-                                    d_chain: Vec<String>,
-                                    _: i64,
-                                    _: i64,
-                                    _: char) -> Result<(), NP_Error> {
-
-                                match *v {
-                                    NP_JSON::Dictionary(ref mut vv) => {
-                                        let key = NP_Error::unwrap(d_chain.last())?.clone();
-                                        vv.insert(key, NP_JSON::True);
-                                    }
-                                    _ => {}
-                                };
-                                Ok(())
-                            }
-                            recursive(&mut ret,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      None,
-                                      log)?;
-                        }
-
-                        s_true = "".to_owned();
-                        NP_Error::unwrap(d_chain.pop())?;
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                    }
+    /// Only whitespace is allowed after the last value
+    pub fn finish(&mut self) -> Result<(), NP_Error> {
+        self.whitespace();
+        match self.peek() {
+            Some(_) => Err(self.error("unexpected characters after value")),
+            None => Ok(())
+        }
+    }
 
-                    'f' => {
-
-                        NP_Error::unwrap(s_false.pop())?;
-                        s_false = s_false.trim().to_string();
-                        if s_false != "false" {
-                            return Err(NP_Error::new("JSON Parse Error"));
-                        }
-
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-
-                        if last_chain == 'v' {
-                            NP_Error::unwrap(chain.pop())?;
-                            let a_nest = 0i64;
-                            let d_nest = 0i64;
-                            let log: String = "".to_owned();
-                            fn func(v: &mut NP_JSON,
-                                    _: Option<String>,
-                                    _: Vec<i64>,
-                                    d_chain: Vec<String>,
-                                    _: i64,
-                                    _: i64,
-                                    _: char) -> Result<(), NP_Error> {
-
-                                match *v {
-                                    NP_JSON::Dictionary(ref mut vv) => {
-                                        let key = NP_Error::unwrap(d_chain.last())?.clone();
-                                        vv.insert(key, NP_JSON::False);
-                                    }
-                                    _ => {}
-                                };
-                                Ok(())
-                            }
-                            recursive(&mut ret,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      None,
-                                      log)?;
-
-                        }
-
-                        s_false = "".to_owned();
-                        NP_Error::unwrap(d_chain.pop())?;
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                    }
+    /// Consume the opening bracket of a collection
+    pub fn enter(&mut self) -> Result<(), NP_Error> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("too deeply nested"));
+        }
+        self.depth += 1;
+        self.bump();
+        Ok(())
+    }
 
-                    '0' => {
-
-                        NP_Error::unwrap(s_null.pop())?;
-                        s_null = s_null.trim().to_string();
-                        if s_null != "null" {
-                            return Err(NP_Error::new("JSON Parse Error"));
-                        }
-
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-
-
-                        if last_chain == 'v' {
-                            NP_Error::unwrap(chain.pop())?;
-                            let a_nest = 0i64;
-                            let d_nest = 0i64;
-                            let log: String = "".to_owned();
-                            fn func(v: &mut NP_JSON,
-                                    _: Option<String>,
-                                    _: Vec<i64>,
-                                    d_chain: Vec<String>,
-                                    _: i64,
-                                    _: i64,
-                                    _: char) -> Result<(), NP_Error> {
-
-                                match *v {
-                                    NP_JSON::Dictionary(ref mut vv) => {
-                                        let key = NP_Error::unwrap(d_chain.last())?.clone();
-                                        vv.insert(key, NP_JSON::Null);
-                                    }
-                                    _ => {}
-                                };
-                                Ok(())
-                            }
-                            recursive(&mut ret,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      None,
-                                      log)?;
-                        }
-
-                        s_null = "".to_owned();
-                        NP_Error::unwrap(d_chain.pop())?;
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                    }
+    /// True if the collection has another item, false once its closing bracket has been consumed
+    pub fn more(&mut self, close: u8, first: &mut bool) -> Result<bool, NP_Error> {
+        self.whitespace();
+        let next = self.peek();
+        if next == Some(close) {
+            self.bump();
+            self.depth -= 1;
+            return Ok(false);
+        }
+        if *first {
+            *first = false;
+            return Ok(true);
+        }
+        if next == Some(b',') {
+            self.bump();
+            return Ok(true);
+        }
+        Err(self.error(&alloc::format!("expected ',' or '{}'", close as char)))
+    }
 
-                    'n' => {
-
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-
-                        if last_chain == 'v' {
-                            NP_Error::unwrap(chain.pop())?;
-                            let a_nest = 0i64;
-                            let d_nest = 0i64;
-                            let log: String = "".to_owned();
-                            fn func(v: &mut NP_JSON,
-                                    value: Option<String>,
-                                    _: Vec<i64>,
-                                    d_chain: Vec<String>,
-                                    _: i64,
-                                    _: i64,
-                                    _: char) -> Result<(), NP_Error> {
-
-                                match *v {
-                                    NP_JSON::Dictionary(ref mut vv) => {
-                                        let key = NP_Error::unwrap(d_chain.last())?.clone();
-                                        let mut value = NP_Error::unwrap(value)?;
-                                        NP_Error::unwrap(value.pop())?;
-                                        value = value.trim().to_string();
-                                        match value.find('.') {
-                                            Some(_) => vv.insert(key, NP_JSON::Float(f64::from_str(&value.clone())?)) ,
-                                            None    => vv.insert(key, NP_JSON::Integer(i64::from_str(&value.clone())?)),
-                                        };
-                                    }
-                                    _ => {}
-                                };
-                                Ok(())
-                            }
-                            recursive(&mut ret,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      Some(num.clone()),
-                                      log)?;
-
-                        }
-                        num = "".to_owned();
-                        NP_Error::unwrap(d_chain.pop())?;
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                    }
+    /// The key of the next object item, along with the colon after it
+    pub fn key(&mut self) -> Result<String, NP_Error> {
+        self.whitespace();
+        if self.peek() != Some(b'"') {
+            return Err(self.error("expected object key"));
+        }
+        let key = self.string()?;
+        self.expect(b':')?;
+        Ok(key)
+    }
 
-                    'v' => {
-                        NP_Error::unwrap(d_chain.pop())?;
-                        NP_Error::unwrap(chain.pop())?;
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                    }
+    /// Consume the next value if it's `null`
+    pub fn null(&mut self) -> Result<bool, NP_Error> {
+        self.whitespace();
+        if self.peek() == Some(b'n') {
+            self.literal("null")?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
 
-                    _ => {
-                        NP_Error::unwrap(d_chain.pop())?;
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                    }
+    pub fn value(&mut self) -> Result<NP_JSON, NP_Error> {
+        self.whitespace();
+        match self.peek() {
+            Some(b'{') => {
+                self.enter()?;
+                let mut map = JSMAP::new();
+                let mut first = true;
+                while self.more(b'}', &mut first)? {
+                    let key = self.key()?;
+                    let value = self.value()?;
+                    map.insert(key, value);
                 }
-                last_active_char = c.clone();
-            }
-
-            ':' => {
-                match last_chain {
-
-                    's' => {}
-                    'w' => {}
-
-                    'd' => {
-
-                        let v = 'v';
-                        chain.push(v);
-                        last_chain = v;
-
-                        key = string.clone();
-                        NP_Error::unwrap(key.pop())?;
-
-                        d_chain.push(key.clone());
-
-                        string = "".to_owned();
-                    }
-
-                    _ => {}
+                Ok(NP_JSON::Dictionary(map))
+            },
+            Some(b'[') => {
+                self.enter()?;
+                let mut list = Vec::new();
+                let mut first = true;
+                while self.more(b']', &mut first)? {
+                    list.push(self.value()?);
                 }
+                Ok(NP_JSON::Array(list))
+            },
+            Some(b'"') => Ok(NP_JSON::String(self.string()?)),
+            Some(b't') => { self.literal("true")?; Ok(NP_JSON::True) },
+            Some(b'f') => { self.literal("false")?; Ok(NP_JSON::False) },
+            Some(b'n') => { self.literal("null")?; Ok(NP_JSON::Null) },
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input"))
+        }
+    }
 
-                last_active_char = c.clone();
-
+    fn literal(&mut self, word: &str) -> Result<(), NP_Error> {
+        for c in word.bytes() {
+            if self.peek() != Some(c) {
+                return Err(self.error("unexpected character"));
             }
+            self.bump();
+        }
+        Ok(())
+    }
 
-            ',' => {
-                match last_chain {
-
-                    's' => {}
-                    'w' => {}
-
-                    't' => {
-
-                        NP_Error::unwrap(s_true.pop())?;
-                        s_true = s_true.trim().to_string();
-                        if s_true != "true" {
-                            return Err(NP_Error::new("JSON Parse Error"));
-                        }
-
-                        if last_chain == 't' {
-
-                            let a_nest = 0i64;
-                            let d_nest = 0i64;
-                            let log: String = "".to_owned();
-                            fn func(v: &mut NP_JSON,
-                                    _: Option<String>,
-                                    _: Vec<i64>,
-                                    d_chain: Vec<String>,
-                                    _: i64,
-                                    _: i64,
-                                    _: char) -> Result<(), NP_Error> {
-                                match *v {
-                                    NP_JSON::Array(ref mut vv) => {
-                                        vv.push(NP_JSON::True);
-                                    }
-                                    NP_JSON::Dictionary(ref mut vv) => {
-
-                                        let key = NP_Error::unwrap(d_chain.last())?.clone();
-
-                                        vv.insert(key, NP_JSON::True);
-
-                                    }
-                                    _ => {}
-                                };
-                                Ok(())
-                            }
-                            recursive(&mut ret,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      None,
-                                      log)?;
-
-                        }
-
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-
-                        if last_chain == 'v' {
-                            NP_Error::unwrap(chain.pop())?;
-                            last_chain = chain.last().unwrap_or(&' ').to_owned();
-                            NP_Error::unwrap(d_chain.pop())?;
-                        } else {
-                            let a = NP_Error::unwrap(a_chain.pop())?;
-                            a_chain.push(a + 1i64);
-                        }
-
-                        s_true = "".to_owned();
-                    }
-
-                    'f' => {
-
-                        NP_Error::unwrap(s_false.pop())?;
-                        s_false = s_false.trim().to_string();
-                        if s_false != "false" {
-                            return Err(NP_Error::new("JSON Parse Error"));
-                        }
-
-                        if last_chain == 'f' {
-                            let a_nest = 0i64;
-                            let d_nest = 0i64;
-                            let log: String = "".to_owned();
-                            fn func(v: &mut NP_JSON,
-                                    _: Option<String>,
-                                    _: Vec<i64>,
-                                    d_chain: Vec<String>,
-                                    _: i64,
-                                    _: i64,
-                                    _: char) -> Result<(), NP_Error> {
-                                match *v {
-                                    NP_JSON::Array(ref mut vv) => {
-                                        vv.push(NP_JSON::False);
-                                    }
-                                    NP_JSON::Dictionary(ref mut vv) => {
-
-                                        let key = NP_Error::unwrap(d_chain.last())?.clone();
-
-                                        vv.insert(key, NP_JSON::False);
-
-                                    }
-                                    _ => {}
-                                };
-                                Ok(())
-                            }
-                            recursive(&mut ret,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      None,
-                                      log)?;
-
-                        }
-
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-
-                        if last_chain == 'v' {
-                            NP_Error::unwrap(chain.pop())?;
-                            last_chain = chain.last().unwrap_or(&' ').to_owned();
-                            NP_Error::unwrap(d_chain.pop())?;
-                        } else {
-                            let a = NP_Error::unwrap(a_chain.pop())?;
-                            a_chain.push(a + 1i64);
-                        }
-
-                        s_false = "".to_owned();
-                    }
-
-                    '0' => {
-
-                        NP_Error::unwrap(s_null.pop())?;
-                        s_null = s_null.trim().to_string();
-                        if s_null != "null" {
-                            return Err(NP_Error::new("JSON Parse Error"));
-                        }
-
-                        if last_chain == '0' {
-                            let a_nest = 0i64;
-                            let d_nest = 0i64;
-                            let log: String = "".to_owned();
-                            fn func(v: &mut NP_JSON,
-                                    _: Option<String>,
-                                    _: Vec<i64>,
-                                    d_chain: Vec<String>,
-                                    _: i64,
-                                    _: i64,
-                                    _: char) -> Result<(), NP_Error> {
-                                match *v {
-                                    NP_JSON::Array(ref mut vv) => {
-                                        vv.push(NP_JSON::Null);
-                                    }
-                                    NP_JSON::Dictionary(ref mut vv) => {
-                                        let key = NP_Error::unwrap(d_chain.last())?.clone();
-                                        vv.insert(key, NP_JSON::Null);
-                                    }
-                                    _ => {}
-                                };
-                                Ok(())
-                            }
-                            NP_Error::unwrap(chain.pop())?;
-                            recursive(&mut ret,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      None,
-                                      log)?;
-
-                        }
-
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-
-                        if last_chain == 'v' {
-                            NP_Error::unwrap(chain.pop())?;
-                            last_chain = chain.last().unwrap_or(&' ').to_owned();
-                            NP_Error::unwrap(d_chain.pop())?;
-                        } else {
-                            let a = NP_Error::unwrap(a_chain.pop())?;
-                            a_chain.push(a + 1i64);
-                        }
-                        s_null = "".to_owned();
-                    }
-
-                    'a' => {
-                        let a = NP_Error::unwrap(a_chain.pop())?;
-                        a_chain.push(a + 1i64);
-                        if last_active_char == '[' || last_active_char == ',' {
-                            let a_nest = 0i64;
-                            let d_nest = 0i64;
-                            let log: String = "".to_owned();
-                            fn func(v: &mut NP_JSON,
-                                    _: Option<String>,
-                                    _: Vec<i64>,
-                                    _: Vec<String>,
-                                    _: i64,
-                                    _: i64,
-                                    _: char) -> Result<(), NP_Error> {
-                                match *v {
-                                    NP_JSON::Array(ref mut vv) => {
-                                        vv.push(NP_JSON::Null);
-                                    }
-                                    _ => {}
-                                };
-                                Ok(())
-                            }
-                            recursive(&mut ret,
-                                      a_chain.clone(),
-                                      d_chain.clone(),
-                                      a_nest,
-                                      d_nest,
-                                      last_chain,
-                                      last_c,
-                                      func,
-                                      None,
-                                      log)?;
-                        }
-                    }
-
-                    'n' => {
-
-                        let a_nest = 0i64;
-                        let d_nest = 0i64;
-                        let log: String = "".to_owned();
-                        fn func(v: &mut NP_JSON,
-                                value: Option<String>,
-                                _: Vec<i64>,
-                                d_chain: Vec<String>,
-                                _: i64,
-                                _: i64,
-                                _: char) -> Result<(), NP_Error> {
-                            match *v {
-                                NP_JSON::Array(ref mut vv) => {
-                                    let mut new_num = NP_Error::unwrap(value)?.clone();
-                                    NP_Error::unwrap(new_num.pop())?;
-                                    new_num = new_num.trim().to_string();
-
-                                    match new_num.find('.') {
-                                        Some(_) => {
-                                            vv.push(NP_JSON::Float(f64::from_str(&new_num)?))
-                                        }
-                                        None => {
-                                            vv.push(NP_JSON::Integer(i64::from_str(&new_num)?))
-                                        }
-                                    };
-
-                                }
-                                NP_JSON::Dictionary(ref mut vv) => {
-
-                                    let key = NP_Error::unwrap(d_chain.last())?.clone();
-
-                                    let mut new_num = NP_Error::unwrap(value)?.clone();
-                                    NP_Error::unwrap(new_num.pop())?;
-                                    new_num = new_num.trim().to_string();
-
-                                    match new_num.find('.') {
-                                        Some(_) => {
-                                            vv.insert(key,
-                                                      NP_JSON::Float(f64::from_str(&new_num)?))
-                                        }
-                                        None => {
-                                            vv.insert(key,
-                                                      NP_JSON::Integer(i64::from_str(&new_num)?))
-                                        }
-                                    };
-
-
-                                }
-                                _ => {}
-                            };
-                            Ok(())
-                        }
-                        recursive(&mut ret,
-                                  a_chain.clone(),
-                                  d_chain.clone(),
-                                  a_nest,
-                                  d_nest,
-                                  last_chain,
-                                  last_c,
-                                  func,
-                                  Some(num),
-                                  log)?;
-
-                        num = "".to_owned();
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-
-                        if last_chain == 'v' {
-                            NP_Error::unwrap(chain.pop())?;
-                            last_chain = chain.last().unwrap_or(&' ').to_owned();
-                            NP_Error::unwrap(d_chain.pop())?;
-                        } else {
-                            let a = NP_Error::unwrap(a_chain.pop())?;
-                            a_chain.push(a + 1i64);
-                        }
-
-                    }
-
-                    'v' => {
-                        NP_Error::unwrap(chain.pop())?;
-                        last_chain = chain.last().unwrap_or(&' ').to_owned();
-                        NP_Error::unwrap(d_chain.pop())?;
-                    }
-                    _ => {}
-                }
+    fn digits(&mut self, number: &mut String) -> Result<(), NP_Error> {
+        let start = number.len();
+        while let Some(c @ b'0'..=b'9') = self.peek() {
+            number.push(c as char);
+            self.bump();
+        }
+        if number.len() == start {
+            return Err(self.error("expected digit"));
+        }
+        Ok(())
+    }
 
-                last_active_char = c.clone();
+    fn number(&mut self) -> Result<NP_JSON, NP_Error> {
+        let mut number = String::new();
+        let mut integer = true;
 
+        if self.peek() == Some(b'-') {
+            number.push('-');
+            self.bump();
+        }
+        self.digits(&mut number)?;
+        if self.peek() == Some(b'.') {
+            integer = false;
+            number.push('.');
+            self.bump();
+            self.digits(&mut number)?;
+        }
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            integer = false;
+            number.push('e');
+            self.bump();
+            if let Some(c @ b'+') | Some(c @ b'-') = self.peek() {
+                number.push(c as char);
+                self.bump();
             }
+            self.digits(&mut number)?;
+        }
 
-            '"' => {
-
-
-                match last_chain {
-
-                    'w' => {
-                        if last_c != '\\' {
-
-                            NP_Error::unwrap(chain.pop())?;
-                            last_chain = chain.last().unwrap_or(&' ').to_owned();
+        // keep every integer exact if it fits in an i64 or u64
+        if integer {
+            if let Ok(x) = i64::from_str(&number) {
+                return Ok(NP_JSON::Integer(x));
+            }
+            if let Ok(x) = u64::from_str(&number) {
+                return Ok(NP_JSON::Unsigned(x));
+            }
+            // a float would quietly drop digits
+            return Err(self.error("integer doesn't fit in 64 bits"));
+        }
 
-                            if last_chain == 'v' {
+        let x = f64::from_str(&number)?;
+        if !x.is_finite() {
+            return Err(self.error("number is too large for a float"));
+        }
+        Ok(NP_JSON::Float(x))
+    }
 
-                                let a_nest = 0i64;
-                                let d_nest = 0i64;
-                                let log: String = "".to_owned();
-                                fn func(v: &mut NP_JSON,
-                                        value: Option<String>,
-                                        _: Vec<i64>,
-                                        d_chain: Vec<String>,
-                                        _: i64,
-                                        _: i64,
-                                        _: char) -> Result<(), NP_Error> {
+    fn hex(&mut self) -> Result<u32, NP_Error> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let digit = match self.peek() {
+                Some(c @ b'0'..=b'9') => c - b'0',
+                Some(c @ b'a'..=b'f') => c - b'a' + 10,
+                Some(c @ b'A'..=b'F') => c - b'A' + 10,
+                _ => return Err(self.error("expected 4 hex digits"))
+            };
+            value = (value << 4) | digit as u32;
+            self.bump();
+        }
+        Ok(value)
+    }
 
-                                    match *v {
-                                        NP_JSON::Dictionary(ref mut vv) => {
-                                            let key = NP_Error::unwrap(d_chain.last())?.clone();
-                                            let mut value = NP_Error::unwrap(value)?;
-                                            NP_Error::unwrap(value.pop())?;
-                                            vv.insert(key, NP_JSON::String(value.clone()));
-                                        }
-                                        _ => {}
-                                    };
-                                    Ok(())
+    fn string(&mut self) -> Result<String, NP_Error> {
+        // skip the opening quote
+        self.bump();
+
+        let mut bytes: Vec<u8> = Vec::new();
+
+        loop {
+            match self.peek() {
+                Some(b'"') => {
+                    self.bump();
+                    return Ok(String::from_utf8(bytes)?);
+                },
+                Some(b'\\') => {
+                    self.bump();
+                    let escape = self.peek();
+                    self.bump();
+                    let escaped = match escape {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{08}',
+                        Some(b'f') => '\u{0c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            let mut code = self.hex()?;
+                            // characters outside the basic plane are escaped as two UTF-16 surrogates
+                            if (0xD800..0xDC00).contains(&code) {
+                                self.literal("\\u").map_err(|_| self.error("unpaired surrogate"))?;
+                                let low = self.hex()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("unpaired surrogate"));
                                 }
-                                recursive(&mut ret,
-                                          a_chain.clone(),
-                                          d_chain.clone(),
-                                          a_nest,
-                                          d_nest,
-                                          last_chain,
-                                          last_c,
-                                          func,
-                                          Some(string.clone()),
-                                          log)?;
-                                string = "".to_owned();
-                            } else if last_chain != 'd' {
-                                NP_Error::unwrap(string.pop())?;
-                                let is_root = match *ret {
-                                    NP_JSON::Null => {
-                                        *ret = NP_JSON::String(string.clone());
-                                        true
-                                    }
-                                    _ => false,
-                                };
-
-                                if !is_root {
-                                    let a_nest = 0i64;
-                                    let d_nest = 0i64;
-                                    let log: String = "".to_owned();
-                                    fn func(v: &mut NP_JSON,
-                                            value: Option<String>,
-                                            _: Vec<i64>,
-                                            _: Vec<String>,
-                                            _: i64,
-                                            _: i64,
-                                            _: char) -> Result<(), NP_Error> {
-                                        match *v {
-                                            NP_JSON::Array(ref mut vv) => {
-                                                vv.push(NP_JSON::String(NP_Error::unwrap(value)?
-                                                                              .clone()));
-                                            }
-                                            _ => {}
-                                        };
-                                        Ok(())
-                                    }
-                                    recursive(&mut ret,
-                                              a_chain.clone(),
-                                              d_chain.clone(),
-                                              a_nest,
-                                              d_nest,
-                                              last_chain,
-                                              last_c,
-                                              func,
-                                              Some(string),
-                                              log)?;
-                                }
-                                string = "".to_owned();
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
                             }
-                        }
-                    }
-
-                    _ => {
-                        let w = 'w';
-                        chain.push(w);
-                        last_chain = w;
-                        string = "".to_owned();
-                    }
-                }
-
-                last_active_char = c.clone();
-
-            }
-            '\'' => {
-                match last_chain {
-                    's' => {
-                        if last_c != '\\' {
-
-                            NP_Error::unwrap(chain.pop())?;
-                            last_chain = chain.last().unwrap_or(&' ').to_owned();
-
-                            if last_chain == 'v' {
-                                let a_nest = 0i64;
-                                let d_nest = 0i64;
-                                let log: String = "".to_owned();
-                                fn func(v: &mut NP_JSON,
-                                        value: Option<String>,
-                                        _: Vec<i64>,
-                                        d_chain: Vec<String>,
-                                        _: i64,
-                                        _: i64,
-                                        _: char) -> Result<(), NP_Error> {
-
-                                    match *v {
-                                        NP_JSON::Dictionary(ref mut vv) => {
-                                            let key = NP_Error::unwrap(d_chain.last())?.clone();
-                                            let mut value = NP_Error::unwrap(value)?;
-                                            NP_Error::unwrap(value.pop())?;
-                                            vv.insert(key, NP_JSON::String(value.clone()));
-                                        }
-                                        _ => {}
-                                    };
-                                    Ok(())
-                                }
-                                recursive(&mut ret,
-                                          a_chain.clone(),
-                                          d_chain.clone(),
-                                          a_nest,
-                                          d_nest,
-                                          last_chain,
-                                          last_c,
-                                          func,
-                                          Some(string.clone()),
-                                          log)?;
-                                          NP_Error::unwrap(d_chain.pop())?;
-                                string = "".to_owned();
-                            } else {
-                                NP_Error::unwrap(string.pop())?;
-                                let is_root = match *ret {
-                                    NP_JSON::Null => {
-                                        *ret = NP_JSON::String(string.clone());
-                                        true
-                                    }
-                                    _ => false,
-                                };
-
-                                if !is_root {
-                                    let a_nest = 0i64;
-                                    let d_nest = 0i64;
-                                    let log: String = "".to_owned();
-                                    fn func(v: &mut NP_JSON,
-                                            value: Option<String>,
-                                            _: Vec<i64>,
-                                            _: Vec<String>,
-                                            _: i64,
-                                            _: i64,
-                                            _: char) -> Result<(), NP_Error> {
-                                        match *v {
-                                            NP_JSON::Array(ref mut vv) => {
-                                                vv.push(NP_JSON::String(NP_Error::unwrap(value)?
-                                                                              .clone()));
-                                            }
-                                            _ => {}
-                                        };
-                                        Ok(())
-                                    }
-                                    recursive(&mut ret,
-                                              a_chain.clone(),
-                                              d_chain.clone(),
-                                              a_nest,
-                                              d_nest,
-                                              last_chain,
-                                              last_c,
-                                              func,
-                                              Some(string),
-                                              log)?;
-                                }
-                                string = "".to_owned();
+                            match core::char::from_u32(code) {
+                                Some(c) => c,
+                                None => return Err(self.error("unpaired surrogate"))
                             }
-                        }
-                    }
-                    _ => {
-                        string = "".to_owned();
-                        let s = 's';
-                        chain.push(s);
-                        last_chain = s;
-                    }
-                }
-                last_active_char = c.clone();
-            }
-
-            '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                match last_chain {
-                    'n' => {}
-                    'w' => {}
-                    's' => {}
-                    _ => {
-                        num = "".to_owned();
-                        let n = 'n';
-                        chain.push(n);
-                        last_chain = n;
-                        num.push(c);
-                    }
-                }
-                last_active_char = c.clone();
-            }
-
-            '-' => {
-                match last_chain {
-                    'n' => {}
-                    'w' => {}
-                    's' => {}
-                    _ => {
-                        num = "".to_owned();
-                        let n = 'n';
-                        chain.push(n);
-                        last_chain = n;
-                        num.push(c);
-                    }
-                }
-                last_active_char = c.clone();
-            }
-
-            't' => {
-
-                match last_chain {
-                    'n' => {}
-                    'w' => {}
-                    's' => {}
-
-                    _ => {
-                        let t = 't';
-                        chain.push(t);
-                        last_chain = t;
-                        s_true = "".to_owned();
-                        s_true.push(c);
-                    }
-                }
-                last_active_char = c.clone();
-            }
-
-            'f' => {
-                match last_chain {
-                    'n' => {}
-                    'w' => {}
-                    's' => {}
-                    _ => {
-                        let f = 'f';
-                        chain.push(f);
-                        last_chain = f;
-                        s_false = "".to_owned();
-                        s_false.push(c);
-                    }
-                }
-                last_active_char = c.clone();
-            }
-
-            'n' => {
-                match last_chain {
-                    'n' => {}
-                    'w' => {}
-                    's' => {}
-                    _ => {
-                        let null = '0';
-                        chain.push(null);
-                        last_chain = null;
-                        s_null = "".to_owned();
-                        s_null.push(c);
-                    }
-                }
-                last_active_char = c.clone();
+                        },
+                        _ => return Err(self.error("invalid escape sequence"))
+                    };
+                    let mut encoded = [0u8; 4];
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut encoded).as_bytes());
+                },
+                Some(0x00..=0x1F) => return Err(self.error("control character in string")),
+                Some(c) => {
+                    bytes.push(c);
+                    self.bump();
+                },
+                None => return Err(self.error("unterminated string"))
             }
-
-            '\n' => {}
-            _ => {}
-        };
-
-        pos += 1;
-        if pos >= size {
-            done = true;
         }
-
-        last_c = c.clone();
-
     }
+}
 
-
-    Ok(ret)
-}
\ No newline at end of file
+#[test]
+fn json_fidelity_works() -> Result<(), NP_Error> {
+
+    // escapes and unicode survive a round trip
+    let text = r#"{"a\"b":"line\nbreak \\ tab\t \u00e9 \ud83d\ude00 \u0001","empty":{},"list":[]}"#;
+    let json = json_decode(text.to_owned())?;
+    assert_eq!(json["a\"b"].into_string().map(|x| x.as_str()), Some("line\nbreak \\ tab\t é 😀 \u{01}"));
+    assert_eq!(json.stringify(), "{\"a\\\"b\":\"line\\nbreak \\\\ tab\\t é 😀 \\u0001\",\"empty\":{},\"list\":[]}");
+    assert_eq!(json_decode(json.stringify())?.stringify(), json.stringify());
+
+    // integers keep every digit
+    let json = json_decode(String::from("[18446744073709551615, -9223372036854775808, 1e3, 2.5, 1.8446744073709552e19]"))?;
+    assert_eq!(json[0].into_u64(), Some(u64::MAX));
+    assert_eq!(json[1].into_i64(), Some(&i64::MIN));
+    assert_eq!(json[2].into_f64(), Some(&1000.0));
+    assert_eq!(json[4].is_float(), true);
+    assert_eq!(json.stringify(), "[18446744073709551615,-9223372036854775808,1000,2.5,1.8446744073709552e19]");
+    assert_eq!(json_decode(json.stringify())?.stringify(), json.stringify());
+
+    // numbers that can't be held exactly or at all are errors
+    assert!(json_decode(String::from("18446744073709551616")).is_err());
+    assert!(json_decode(String::from("-9223372036854775809")).is_err());
+    assert!(json_decode(String::from("[1e400]")).is_err());
+    assert!(json_decode(String::from("-1e400")).is_err());
+    assert_eq!(json_decode(String::from("1e-400"))?.into_f64(), Some(&0.0));
+
+    // large floats are written so they parse as floats again
+    for x in [1e20, -1e20, 1.5e300, -9.3e18, 123456789012345680000.0].iter() {
+        let text = NP_JSON::Float(*x).stringify();
+        assert_eq!(json_decode(text.clone())?.into_f64(), Some(x), "{}", text);
+    }
+    assert_eq!(NP_JSON::Float(1e20).stringify(), "1e20");
+    let factory = crate::NP_Factory::new(r#"{"type": "double"}"#)?;
+    let mut new_buffer = factory.empty_buffer(None);
+    new_buffer.set(&[], 1e20f64)?;
+    let text = new_buffer.json_encode(&[])?.stringify();
+    new_buffer.set_with_json(&[], &text)?;
+    assert_eq!(new_buffer.get::<f64>(&[])?, Some(1e20));
+
+    // scalars and invalid documents
+    assert_eq!(json_decode(String::from(" true "))?.is_true(), true);
+    assert!(json_decode(String::from("[1,]")).is_err());
+    assert!(json_decode(String::from("{\"a\" 1}")).is_err());
+    assert!(json_decode(String::from("\"\\ud83d\"")).is_err());
+    assert!(json_decode(String::from("[1] 2")).is_err());
+
+    // u64 values in buffers and schemas
+    let factory = crate::NP_Factory::new(r#"{"type": "u64", "default": 18446744073709551615}"#)?;
+    assert_eq!(factory.schema.to_json()?.stringify(), r#"{"type":"uint64","default":18446744073709551615}"#);
+    let mut new_buffer = factory.empty_buffer(None);
+    assert_eq!(new_buffer.get::<u64>(&[])?, Some(u64::MAX));
+    new_buffer.set_with_json(&[], "18446744073709551614")?;
+    assert_eq!(new_buffer.get::<u64>(&[])?, Some(u64::MAX - 1));
+    assert_eq!(new_buffer.json_encode(&[])?.stringify(), "18446744073709551614");
+
+    Ok(())
+}
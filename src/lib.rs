@@ -159,10 +159,10 @@
 //! 
 //! #### Limitations
 //! - Buffers cannot be larger than 2^16 bytes (~16kb).
-//! - Collections (Lists, Maps, Tuples & Tables) cannot have more than 255 immediate child items.
-//! - Enum/Option types are limited to 255 choices and choice strings cannot be larger than 255 bytes.
-//! - Tables are limited to 255 columns and column names cannot be larger than 255 bytes.
-//! - Buffers are not validated or checked before deserializing.
+//! - Collections (Lists, Maps, Tuples & Tables) cannot have more than 255 immediate child items, unless they are `wide` lists or tables.
+//! - Enum/Option types are limited to 254 choices (65,534 with `"wide": true`) and choice strings cannot be larger than 255 bytes.
+//! - Tables are limited to 255 columns and column names cannot be larger than 255 bytes, or 65,535 of each with `"wide": true`.
+//! - Buffers opened with `open_buffer` are trusted and not validated before deserializing.  Use `open_checked_buffer` (or `open_lenient` to recover what can be read) for bytes from the network or other untrusted sources.
 //! 
 //! #### Non Goals / Known Tradeoffs 
 //! If every CPU cycle counts, you don't mind compiling fixed schemas and you don't plan to mutate your buffers/objects, FlatBuffers/CapnProto is probably the way to go.  It's impossible to make a flexible format like NoProto as fast as formats that compile your schemas ahead of time and store data immutably.
@@ -191,29 +191,113 @@
 //! OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 //! SOFTWARE.
 
+#[macro_use]
+mod trace;
+#[cfg(feature = "alloc")]
 pub mod pointer;
+#[cfg(feature = "alloc")]
 pub mod collection;
+#[cfg(feature = "alloc")]
 pub mod buffer;
+#[cfg(feature = "alloc")]
 pub mod schema;
+#[cfg(feature = "alloc")]
 pub mod error;
+#[cfg(feature = "alloc")]
 pub mod json_flex;
+#[cfg(feature = "alloc")]
 pub mod format;
+#[cfg(feature = "alloc")]
 pub mod memory;
+#[cfg(feature = "alloc")]
 pub mod rpc;
+#[cfg(feature = "alloc")]
+pub mod recovery;
+#[cfg(feature = "alloc")]
+pub mod random;
+#[cfg(feature = "alloc")]
+pub mod infer;
+#[cfg(feature = "alloc")]
+pub mod mask;
+#[cfg(feature = "alloc")]
+pub mod view;
+#[cfg(feature = "alloc")]
+pub mod snapshot;
+#[cfg(feature = "alloc")]
+pub mod reader;
+#[cfg(feature = "alloc")]
+pub mod compactor;
+#[cfg(feature = "alloc")]
+pub mod cell;
+#[cfg(feature = "alloc")]
+pub mod path;
+#[cfg(feature = "alloc")]
+pub mod raw;
+#[cfg(feature = "alloc")]
+pub mod tombstone;
+#[cfg(feature = "alloc")]
+pub mod crdt;
+#[cfg(feature = "alloc")]
+pub mod version;
+#[cfg(feature = "alloc")]
+pub mod fingerprint;
+#[cfg(feature = "alloc")]
+pub mod batch;
+#[cfg(feature = "alloc")]
+pub mod np_file;
+pub mod no_alloc;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "bson")]
+pub mod bson;
+#[cfg(feature = "seal")]
+pub mod seal;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "wal")]
+pub mod wal;
+#[cfg(feature = "alloc")]
 mod hashmap;
+#[cfg(feature = "alloc")]
+mod compare;
+#[cfg(feature = "alloc")]
 mod utils;
+#[cfg(feature = "alloc")]
+mod dictionary;
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
-use crate::json_flex::NP_JSON;
+#[cfg(feature = "alloc")]
+use crate::json_flex::{NP_JSON, JSMAP};
+#[cfg(feature = "alloc")]
 use crate::schema::NP_Schema;
-use crate::json_flex::json_decode;
+#[cfg(feature = "alloc")]
+use crate::json_flex::{json_decode, JSON_Parser, JSON_Source, JSON_Str};
+#[cfg(feature = "alloc")]
+use crate::pointer::{NP_Cursor, custom::NP_Plugin};
+#[cfg(feature = "alloc")]
 use crate::error::NP_Error;
+#[cfg(feature = "alloc")]
 use crate::memory::NP_Memory;
-use buffer::{NP_Buffer, ROOT_PTR_ADDR};
+#[cfg(feature = "alloc")]
+use buffer::{NP_Buffer, NP_Buffer_Options, ROOT_PTR_ADDR};
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
-use alloc::{borrow::ToOwned};
+#[cfg(feature = "alloc")]
+use alloc::{borrow::ToOwned, boxed::Box, string::String};
+#[cfg(feature = "alloc")]
 use schema::NP_Parsed_Schema;
+#[cfg(feature = "alloc")]
+use recovery::{NP_Recovery_Report, recover, verify};
+#[cfg(feature = "alloc")]
+use batch::NP_Batch;
+#[cfg(feature = "alloc")]
+use path::NP_PathHandle;
 
 /// Factories are created from schemas.  Once you have a factory you can use it to create new buffers or open existing ones.
 /// 
@@ -280,13 +364,20 @@ use schema::NP_Parsed_Schema;
 /// 
 /// [Go to NP_Buffer docs](./buffer/struct.NP_Buffer.html)
 /// 
+#[cfg(feature = "alloc")]
 #[derive(Debug)]
 pub struct NP_Factory {
     /// schema data used by this factory
     pub schema: NP_Schema,
-    schema_bytes: Vec<u8>
+    schema_bytes: Vec<u8>,
+    roots: Vec<(String, NP_Factory)>
 }
 
+// first byte of compiled schemas with named roots, after the type keys
+#[cfg(feature = "alloc")]
+const MULTI_ROOT_KEY: u8 = 255;
+
+#[cfg(feature = "alloc")]
 impl NP_Factory {
     
     /// Generate a new factory from the given schema.
@@ -294,48 +385,251 @@ impl NP_Factory {
     /// This operation will fail if the schema provided is invalid or if the schema is not valid JSON.  If it fails you should get a useful error message letting you know what the problem is.
     /// 
     pub fn new(json_schema: &str) -> Result<NP_Factory, NP_Error> {
+        Self::new_with_plugins(json_schema, &[])
+    }
+
+    /// Generate a new factory from the given schema, with custom types from plugins.
+    /// 
+    /// The schema can use the `type_name` of any of the plugins as a type, see the [custom](./pointer/custom/index.html) module for an example.
+    /// 
+    pub fn new_with_plugins(json_schema: &str, plugins: &[&'static dyn NP_Plugin]) -> Result<NP_Factory, NP_Error> {
 
-        let parsed_value = json_decode(json_schema.to_owned())?;
+        let parsed_value = NP_Schema::resolve_definitions(json_decode(json_schema.to_owned())?)?;
+        let parsed_value = NP_Schema::resolve_plugins(parsed_value, plugins)?;
+
+        if let NP_JSON::Dictionary(_) = parsed_value["roots"] {
+            return Self::from_roots_json(parsed_value)?.with_plugins(plugins);
+        }
 
         let (is_sortable, schema_bytes, schema) = NP_Schema::from_json(Vec::new(), &parsed_value)?;
 
-        Ok(Self {
-            schema_bytes: schema_bytes,
+        Self {
             schema:  NP_Schema {
                 is_sortable: is_sortable,
-                parsed: schema
-            }
-        })      
+                parsed: schema,
+                fingerprint: fingerprint::of(&schema_bytes)
+            },
+            schema_bytes,
+            roots: Vec::new()
+        }.with_plugins(plugins)
         
     }
 
+    /// Create a new factory from a compiled schema byte array, with custom types from plugins.
+    /// 
+    /// Fails if the schema has a custom type none of the plugins are for.  Factories made with `new_compiled` can still read and write custom types as bytes, but not as JSON.
+    /// 
+    pub fn new_compiled_with_plugins(schema_bytes: Vec<u8>, plugins: &[&'static dyn NP_Plugin]) -> Result<NP_Factory, NP_Error> {
+        Self::new_compiled(schema_bytes).with_plugins(plugins)
+    }
+
+    // give the plugin types in this factory and its roots their plugins
+    fn with_plugins(mut self, plugins: &[&'static dyn NP_Plugin]) -> Result<NP_Factory, NP_Error> {
+        NP_Schema::attach_plugins(&mut self.schema.parsed, plugins)?;
+        for (_name, root) in self.roots.iter_mut() {
+            NP_Schema::attach_plugins(&mut root.schema.parsed, plugins)?;
+        }
+        Ok(self)
+    }
+
+    fn from_roots_json(parsed_value: Box<NP_JSON>) -> Result<NP_Factory, NP_Error> {
+
+        let mut roots: Vec<(String, NP_Factory)> = Vec::new();
+
+        if let NP_JSON::Dictionary(root_map) = *parsed_value {
+            for (key, value) in root_map.values {
+                if key != "roots" { continue; }
+                if let NP_JSON::Dictionary(named) = value {
+                    for (name, root_json) in named.values {
+                        if name.len() > 255 {
+                            return Err(NP_Error::new_schema("Root names can't be longer than 255 bytes!"));
+                        }
+                        let (is_sortable, schema_bytes, schema) = NP_Schema::from_json(Vec::new(), &Box::new(root_json))?;
+                        roots.push((name, NP_Factory {
+                            schema: NP_Schema {
+                                is_sortable: is_sortable,
+                                parsed: schema,
+                                fingerprint: fingerprint::of(&schema_bytes)
+                            },
+                            schema_bytes,
+                            roots: Vec::new()
+                        }));
+                    }
+                }
+            }
+        }
+
+        if roots.len() > 255 {
+            return Err(NP_Error::new_schema("Schemas can't have more than 255 roots!"));
+        }
+
+        Self::with_roots(roots)
+    }
+
+    // the first root is the default, so `empty_buffer`, `open_buffer` and friends still work
+    fn with_roots(roots: Vec<(String, NP_Factory)>) -> Result<NP_Factory, NP_Error> {
+        let first = match roots.first() {
+            Some((_, first)) => first,
+            None => return Err(NP_Error::new_schema("Schema roots must contain at least one named schema!"))
+        };
+
+        let (is_sortable, schema) = NP_Schema::from_bytes(Vec::new(), 0, &first.schema_bytes);
+
+        Ok(Self {
+            schema_bytes: first.schema_bytes.clone(),
+            schema: NP_Schema {
+                is_sortable: is_sortable,
+                parsed: schema,
+                fingerprint: first.schema.fingerprint
+            },
+            roots: roots
+        })
+    }
+
     /// Create a new factory from a compiled schema byte array.
     /// The byte schemas are at least an order of magnitude faster to parse than JSON schemas.
     /// 
     pub fn new_compiled(schema_bytes: Vec<u8>) -> Self {
+
+        if schema_bytes.first() == Some(&MULTI_ROOT_KEY) {
+            if let Some(factory) = Self::from_roots_bytes(&schema_bytes) {
+                return factory;
+            }
+        }
         
         let (is_sortable, schema) = NP_Schema::from_bytes(Vec::new(), 0, &schema_bytes);
 
         Self {
-            schema_bytes: schema_bytes,
             schema:  NP_Schema { 
                 is_sortable: is_sortable,
-                parsed: schema
-            }
+                parsed: schema,
+                fingerprint: fingerprint::of(&schema_bytes)
+            },
+            schema_bytes,
+            roots: Vec::new()
+        }
+    }
+
+    // [MULTI_ROOT_KEY, root count, then for each root: name length (u8), name, schema length (u16), schema]
+    fn from_roots_bytes(bytes: &[u8]) -> Option<NP_Factory> {
+        let mut roots: Vec<(String, NP_Factory)> = Vec::new();
+        let count = *bytes.get(1)? as usize;
+        let mut index = 2usize;
+        for _ in 0..count {
+            let name_len = *bytes.get(index)? as usize;
+            let name = core::str::from_utf8(bytes.get((index + 1)..(index + 1 + name_len))?).ok()?;
+            index += 1 + name_len;
+            let schema_len = u16::from_be_bytes([*bytes.get(index)?, *bytes.get(index + 1)?]) as usize;
+            let schema_bytes = bytes.get((index + 2)..(index + 2 + schema_len))?;
+            index += 2 + schema_len;
+            roots.push((name.to_owned(), NP_Factory::new_compiled(schema_bytes.to_vec())));
         }
+        Self::with_roots(roots).ok()
+    }
+
+    /// Get the factory for one of the named roots in this factory's schema.
+    /// 
+    /// Schemas with a `roots` object hold several named document types in one factory:
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///     "roots": {
+    ///         "user": {"type": "table", "columns": [["name", {"type": "string"}]]},
+    ///         "order": {"type": "table", "columns": [["total", {"type": "u32"}], ["paid", {"type": "bool"}]]}
+    ///     }
+    /// }"#)?;
+    /// 
+    /// assert_eq!(factory.root_names(), vec!["user", "order"]);
+    /// 
+    /// let mut order = factory.empty_buffer_of("order", None)?;
+    /// order.set(&["total"], 2500u32)?;
+    /// let bytes = order.close();
+    /// 
+    /// let order = factory.open_buffer_of("order", bytes)?;
+    /// assert_eq!(order.get::<u32>(&["total"])?, Some(2500));
+    /// 
+    /// // the first root is the default for `empty_buffer`, `open_buffer` and the other methods
+    /// let mut user = factory.empty_buffer(None);
+    /// user.set(&["name"], "Billy")?;
+    /// 
+    /// // any other factory method can be used through `factory_of`
+    /// let order_factory = NP_Factory::new_compiled(factory.compile_schema());
+    /// assert!(order_factory.factory_of("order").is_some());
+    /// assert!(order_factory.factory_of("invoice").is_none());
+    /// 
+    /// // exported schemas keep their roots
+    /// let exported = NP_Factory::new(&factory.export_schema()?.stringify())?;
+    /// assert_eq!(exported.root_names(), vec!["user", "order"]);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn factory_of(&self, name: &str) -> Option<&NP_Factory> {
+        self.roots.iter().find(|(root_name, _)| root_name == name).map(|(_, factory)| factory)
+    }
+
+    /// Names of the roots in this factory's schema, in the order they were declared.  Empty if the schema doesn't have named roots.
+    /// 
+    pub fn root_names(&self) -> Vec<&str> {
+        self.roots.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    fn require_root(&self, name: &str) -> Result<&NP_Factory, NP_Error> {
+        match self.factory_of(name) {
+            Some(factory) => Ok(factory),
+            None => Err(NP_Error::new(alloc::format!("No root named {:?} in this schema!", name)))
+        }
+    }
+
+    /// Generate a new empty buffer for one of the named roots in this factory's schema.
+    /// 
+    /// See `factory_of` for an example.
+    /// 
+    pub fn empty_buffer_of<'buffer>(&'buffer self, name: &str, capacity: Option<usize>) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        Ok(self.require_root(name)?.empty_buffer(capacity))
+    }
+
+    /// Open existing Vec<u8> as a buffer for one of the named roots in this factory's schema.
+    /// 
+    pub fn open_buffer_of<'buffer>(&'buffer self, name: &str, bytes: Vec<u8>) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        Ok(self.require_root(name)?.open_buffer(bytes))
     }
 
     /// Get a copy of the compiled schema byte array
     /// 
     pub fn compile_schema(&self) -> Vec<u8> {
-        self.schema_bytes.clone()
+        if self.roots.is_empty() {
+            return self.schema_bytes.clone();
+        }
+
+        let mut bytes: Vec<u8> = [MULTI_ROOT_KEY, self.roots.len() as u8].to_vec();
+        for (name, factory) in &self.roots {
+            bytes.push(name.len() as u8);
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(&(factory.schema_bytes.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(&factory.schema_bytes);
+        }
+        bytes
     }
 
 
     /// Exports this factorie's schema to JSON.  This works regardless of wether the factory was created with `NP_Factory::new` or `NP_Factory::new_compiled`.
     /// 
     pub fn export_schema(&self) -> Result<NP_JSON, NP_Error> {
-        self.schema.to_json()
+        if self.roots.is_empty() {
+            return self.schema.to_json();
+        }
+
+        let mut roots = JSMAP::new();
+        for (name, factory) in &self.roots {
+            roots.insert(name.clone(), factory.schema.to_json()?);
+        }
+        let mut schema = JSMAP::new();
+        schema.insert("roots".to_owned(), NP_JSON::Dictionary(roots));
+        Ok(NP_JSON::Dictionary(schema))
     }
 
     /// Open existing Vec<u8> sortable buffer that was closed with `.close_sortable()` 
@@ -387,7 +681,7 @@ impl NP_Factory {
         match &self.schema.parsed[0] {
             NP_Parsed_Schema::Tuple { values, sortable,  ..} => {
                 if *sortable == false {
-                    Err(NP_Error::new("Attempted to open sorted buffer when root wasn't sortable!"))
+                    Err(NP_Error::new_static("Attempted to open sorted buffer when root wasn't sortable!"))
                 } else {
                     let mut vtables = 1usize;
                     let mut length = values.len();
@@ -398,14 +692,14 @@ impl NP_Factory {
                     // how many leading bytes are identical across all buffers with this schema
                     let root_offset = ROOT_PTR_ADDR + 2 + (vtables * 10);
 
-                    let default_buffer = NP_Buffer::_new(NP_Memory::new(Some(root_offset + bytes.len()), &self.schema.parsed));
+                    let default_buffer = NP_Buffer::_new(NP_Memory::new(Some(root_offset + bytes.len()), &self.schema));
                     let mut use_bytes = default_buffer.close()[0..root_offset].to_vec();
                     use_bytes.extend_from_slice(&bytes[..]);
 
-                    Ok(NP_Buffer::_new(NP_Memory::existing(use_bytes, &self.schema.parsed)))
+                    Ok(NP_Buffer::_new(NP_Memory::existing(use_bytes, &self.schema)))
                 }
             },
-            _ => return Err(NP_Error::new("Attempted to open sorted buffer when root wasn't tuple!"))
+            _ => Err(NP_Error::new_static("Attempted to open sorted buffer when root wasn't tuple!"))
         }
     }
 
@@ -413,7 +707,126 @@ impl NP_Factory {
     /// Open existing Vec<u8> as buffer for this factory.  
     /// 
     pub fn open_buffer<'buffer>(&'buffer self, bytes: Vec<u8>) -> NP_Buffer<'buffer> {
-        NP_Buffer::_new(NP_Memory::existing(bytes, &self.schema.parsed))
+        NP_Buffer::_new(NP_Memory::existing(bytes, &self.schema))
+    }
+
+    /// Open existing Vec<u8> as buffer for this factory, failing if any part of the buffer can't be read safely.
+    /// 
+    /// `open_buffer` trusts the bytes it's given, this method doesn't.  Every pointer in the buffer is checked against the buffer bounds and the schema before the buffer is returned, so buffers opened this way can be read, updated and compacted without panics or out of bounds reads.  Use this for buffers that come from the network or other untrusted sources.
+    /// 
+    /// Buffers with a schema fingerprint (see `NP_Buffer::embed_fingerprint`) fail with `NP_Error::SchemaMismatch` unless they were written with this factory's schema.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["0"], "hello")?;
+    /// let bytes = new_buffer.close();
+    /// 
+    /// // good buffers open normally
+    /// let buffer = factory.open_checked_buffer(bytes.clone())?;
+    /// assert_eq!(buffer.get::<&str>(&["0"])?, Some("hello"));
+    /// 
+    /// // list head points past the end of the buffer
+    /// let mut bad_bytes = bytes.clone();
+    /// bad_bytes[3] = 255;
+    /// assert!(factory.open_checked_buffer(bad_bytes).is_err());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn open_checked_buffer<'buffer>(&'buffer self, bytes: Vec<u8>) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let memory = NP_Memory::existing(bytes, &self.schema);
+        fingerprint::check(&memory)?;
+        let report = recover(&memory);
+        match report.unreadable.into_iter().next() {
+            Some(unreadable) => Err(NP_Error::CorruptBuffer { path: unreadable.path, reason: unreadable.reason.to_owned() }),
+            None => Ok(NP_Buffer::_new(memory))
+        }
+    }
+
+    /// Open existing Vec<u8> as buffer for this factory, failing unless the whole buffer is well formed.
+    /// 
+    /// Stricter than `open_checked_buffer`, meant for ingestion boundaries where malformed buffers must never reach storage.  Every pointer is walked and checked for bounds, overlaps and cycles, and nothing is repaired: unknown header flags, list and log tails or counts that don't match their items, and booleans that aren't `0` or `1` are all rejected with `NP_Error::CorruptBuffer`.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["0"], "hello")?;
+    /// new_buffer.set(&["1"], "world")?;
+    /// let bytes = new_buffer.close();
+    /// 
+    /// let buffer = factory.open_verified(bytes.clone())?;
+    /// assert_eq!(buffer.get::<&str>(&["1"])?, Some("world"));
+    /// 
+    /// // point the list tail at the first item
+    /// let list_addr = u16::from_be_bytes([bytes[1], bytes[2]]) as usize;
+    /// let mut bad_bytes = bytes.clone();
+    /// bad_bytes[list_addr + 2] = bytes[list_addr];
+    /// bad_bytes[list_addr + 3] = bytes[list_addr + 1];
+    /// 
+    /// // checked opening repairs the tail, verified opening refuses the buffer
+    /// assert!(factory.open_checked_buffer(bad_bytes.clone()).is_ok());
+    /// assert!(factory.open_verified(bad_bytes).is_err());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn open_verified<'buffer>(&'buffer self, bytes: Vec<u8>) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let memory = NP_Memory::existing(bytes, &self.schema);
+        fingerprint::check(&memory)?;
+        let report = verify(&memory);
+        match report.unreadable.into_iter().next() {
+            Some(unreadable) => Err(NP_Error::CorruptBuffer { path: unreadable.path, reason: unreadable.reason.to_owned() }),
+            None => Ok(NP_Buffer::_new(memory))
+        }
+    }
+
+    /// Open existing Vec<u8> as buffer for this factory, detaching any values that can't be read safely.
+    /// 
+    /// Every pointer in the buffer is checked against the buffer bounds and the schema before the buffer is returned.  Values that point outside the buffer, loop back on themselves or contain invalid UTF8 are unset and listed in the returned report.
+    /// 
+    /// See the [recovery](recovery/index.html) module for an example.
+    /// 
+    pub fn open_lenient<'buffer>(&'buffer self, bytes: Vec<u8>) -> (NP_Buffer<'buffer>, NP_Recovery_Report) {
+        let memory = NP_Memory::existing(bytes, &self.schema);
+        let report = recover(&memory);
+        (NP_Buffer::_new(memory), report)
+    }
+
+    /// Generate a buffer with a plausible value for every type in this factory's schema.
+    /// 
+    /// The same seed always produces the same buffer.  See the [random](random/index.html) module for an example.
+    /// 
+    pub fn generate_sample<'buffer>(&'buffer self, seed: u32) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let memory = NP_Memory::new(None, &self.schema);
+        random::generate_sample(&memory, seed)?;
+        Ok(NP_Buffer::_new(memory))
+    }
+
+    /// Generate a random buffer that matches this factory's schema.
+    /// 
+    /// Every value in the buffer is decided by the `entropy` bytes, the same bytes always produce the same buffer.  Values are left unset once the entropy runs out.
+    /// 
+    /// See the [random](random/index.html) module for an example.
+    /// 
+    pub fn arbitrary_buffer<'buffer>(&'buffer self, entropy: &[u8]) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let memory = NP_Memory::new(None, &self.schema);
+        random::generate(&memory, entropy)?;
+        Ok(NP_Buffer::_new(memory))
     }
 
     /// Generate a new empty buffer from this factory.
@@ -424,6 +837,299 @@ impl NP_Factory {
     /// You can change the address size through compaction after the buffer is created, so it's fine to start with a smaller address space and convert it to a larger one later as needed.  It's also possible to go the other way, you can convert larger address space down to a smaller one durring compaction.
     /// 
     pub fn empty_buffer<'buffer>(&'buffer self, capacity: Option<usize>) -> NP_Buffer<'buffer> {
-        NP_Buffer::_new(NP_Memory::new(capacity, &self.schema.parsed))
+        NP_Buffer::_new(NP_Memory::new(capacity, &self.schema))
+    }
+
+    /// Generate a new empty buffer that keeps its bytes inline instead of on the heap, for small records like sensor readings.  Needs the `inline` feature.
+    /// 
+    /// Nothing is allocated for the bytes until the buffer grows past 128 bytes (`memory::INLINE_CAPACITY`), then they move to the heap and the buffer grows like any other.  Sharing the bytes with `freeze`, `into_reader` or an `NP_Cell` also moves them to the heap, and `close` copies them into a `Vec<u8>`.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["id",      {"type": "string"}],
+    ///         ["celsius", {"type": "i16"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_inline_buffer();
+    /// new_buffer.set(&["id"], "sensor")?;
+    /// new_buffer.set(&["celsius"], -40i16)?;
+    /// assert_eq!(new_buffer.get::<i16>(&["celsius"])?, Some(-40));
+    /// 
+    /// let stats = new_buffer.memory_stats()?;
+    /// assert_eq!(stats.capacity, 128);
+    /// assert_eq!(stats.growth_events, 0);
+    /// 
+    /// // past 128 bytes the bytes move to the heap
+    /// new_buffer.set(&["id"], "a sensor with a name that takes up more than one hundred and twenty eight bytes of the buffer on its own")?;
+    /// assert_eq!(new_buffer.memory_stats()?.growth_events, 1);
+    /// assert_eq!(new_buffer.get::<i16>(&["celsius"])?, Some(-40));
+    /// 
+    /// let bytes = new_buffer.close();
+    /// assert_eq!(factory.open_buffer(bytes).get::<i16>(&["celsius"])?, Some(-40));
+    /// 
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    #[cfg(feature = "inline")]
+    pub fn empty_inline_buffer(&self) -> NP_Buffer<'_> {
+        NP_Buffer::_new(NP_Memory::new_inline(&self.schema))
+    }
+
+    /// Generate a new empty buffer from this factory with a size limit and growth strategy.
+    /// 
+    /// Writes that could grow the buffer past `max_size` fail with `NP_Error::TooLarge` before anything is written (or `NP_Error::OutOfSpace` if they run out partway), so a buggy or malicious writer can't use unbounded memory.  The capacity is never grown past `max_size` either.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::{NP_Buffer_Options, NP_Growth};
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer_with(Some(16), NP_Buffer_Options {
+    ///     max_size: 32,
+    ///     growth: NP_Growth::Linear(8),
+    ///     ..Default::default()
+    /// });
+    /// 
+    /// new_buffer.set(&["0"], "hello")?;
+    /// assert!(matches!(new_buffer.set(&["1"], "this won't fit"), Err(NP_Error::TooLarge { .. })));
+    /// assert_eq!(new_buffer.get::<&str>(&["0"])?, Some("hello"));
+    /// 
+    /// // the limit is kept through compaction
+    /// new_buffer.compact(None)?;
+    /// assert!(matches!(new_buffer.set(&["1"], "this won't fit"), Err(NP_Error::TooLarge { .. })));
+    /// assert!(new_buffer.close().capacity() <= 32);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn empty_buffer_with<'buffer>(&'buffer self, capacity: Option<usize>, options: NP_Buffer_Options) -> NP_Buffer<'buffer> {
+        let capacity = core::cmp::min(capacity.unwrap_or(1024), options.max_size);
+        NP_Buffer::_new(NP_Memory::new(Some(capacity), &self.schema).with_options(options))
+    }
+
+    /// Estimate how many bytes a buffer holding `example_json` would use, the JSON is in the same format `json_encode` produces.
+    /// 
+    /// The example is written into a scratch buffer and measured, so the estimate is exact for buffers built with the same values and a good starting capacity for buffers with similar ones.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let example = r#"{"name": "Jeb Kermin", "tags": ["pilot", "brave"]}"#;
+    /// 
+    /// let size = factory.estimate_size(example)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer_sized_for(example)?;
+    /// new_buffer.set_with_json(&[], example)?;
+    /// assert_eq!(new_buffer.memory_stats()?.growth_events, 0);
+    /// assert_eq!(new_buffer.close().len(), size);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn estimate_size(&self, example_json: &str) -> Result<usize, NP_Error> {
+        let mut scratch = self.empty_buffer(None);
+        scratch.set_with_json(&[], example_json)?;
+        Ok(scratch.calc_bytes()?.after_compaction)
+    }
+
+    /// Generate a new empty buffer with enough capacity to hold `example_json` without growing.
+    /// 
+    /// See `estimate_size` for an example.
+    /// 
+    pub fn empty_buffer_sized_for<'buffer>(&'buffer self, example_json: &str) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        Ok(self.empty_buffer(Some(self.estimate_size(example_json)?)))
+    }
+
+    /// Generate a new buffer from JSON text in the same format `json_encode` produces.
+    /// 
+    /// Values are written into the buffer as the text is parsed, the whole document is never held as an `NP_JSON` value.  Use `buffer_from_json_reader` to read the text from a file or socket.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["scores", {"type": "list", "of": {"type": "u16"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let new_buffer = factory.buffer_from_json(r#"{"name": "Jeb Kermin", "scores": [200, null, 340]}"#)?;
+    /// assert_eq!(new_buffer.get::<&str>(&["name"])?, Some("Jeb Kermin"));
+    /// assert_eq!(new_buffer.get::<u16>(&["scores", "2"])?, Some(340));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn buffer_from_json<'buffer>(&'buffer self, json: &str) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        self.buffer_from_parser(&mut JSON_Parser::new(JSON_Str::new(json)))
+    }
+
+    /// Same as `buffer_from_json`, but reads the JSON text from a reader as it's parsed, needs the `std` feature.
+    /// 
+    /// The reader is read in 4KB chunks, read errors are returned as `NP_Error::Custom`.
+    /// 
+    /// ```
+    /// # #[cfg(feature = "std")] {
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "map",
+    ///    "value": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let file: &[u8] = br#"{"greeting": "hello"}"#;
+    /// 
+    /// let new_buffer = factory.buffer_from_json_reader(file)?;
+    /// assert_eq!(new_buffer.get::<&str>(&["greeting"])?, Some("hello"));
+    /// 
+    /// # }
+    /// # Ok::<(), no_proto::error::NP_Error>(()) 
+    /// ```
+    /// 
+    #[cfg(feature = "std")]
+    pub fn buffer_from_json_reader<'buffer, R: std::io::Read>(&'buffer self, reader: R) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let mut parser = JSON_Parser::new(json_flex::JSON_Reader::new(reader));
+        match self.buffer_from_parser(&mut parser) {
+            Err(e) => match &parser.source().error {
+                Some(io_error) => Err(NP_Error::new(alloc::format!("Error reading JSON: {}", io_error))),
+                None => Err(e)
+            },
+            Ok(buffer) => Ok(buffer)
+        }
+    }
+
+    fn buffer_from_parser<'buffer, S: JSON_Source>(&'buffer self, parser: &mut JSON_Parser<S>) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let memory = NP_Memory::new(None, &self.schema);
+        NP_Cursor::set_from_parser(NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), &memory, parser)?;
+        parser.finish()?;
+        Ok(NP_Buffer::_new(memory))
+    }
+
+    /// Generate a new buffer from a BSON document, needs the `bson` feature.
+    /// 
+    /// The root of the schema must be a table or map.  See the [bson](bson/index.html) module for an example.
+    /// 
+    #[cfg(feature = "bson")]
+    pub fn open_bson<'buffer>(&'buffer self, bytes: &[u8]) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let value = bson::decode(bytes, &self.schema.parsed, 0)?;
+        let memory = NP_Memory::new(None, &self.schema);
+        NP_Cursor::set_from_json(NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), &memory, &value)?;
+        Ok(NP_Buffer::_new(memory))
+    }
+
+    /// Decrypt a buffer made with `NP_Buffer::seal`, needs the `seal` feature.
+    /// 
+    /// Returns an error if the sealed buffer was made with a different schema, the key is wrong or any byte was changed.  See the [seal](seal/index.html) module for an example.
+    /// 
+    #[cfg(feature = "seal")]
+    pub fn open_sealed<'buffer>(&'buffer self, bytes: &[u8], key: &[u8; 32]) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let opened = seal::open(self.schema.fingerprint, bytes, key)?;
+        Ok(NP_Buffer::_new(NP_Memory::existing(opened, &self.schema)))
+    }
+
+    /// Update a buffer only if it hasn't changed since its etag was read, like an HTTP `If-Match` request.
+    /// 
+    /// The buffer is opened and its `etag` is compared with the one provided.  If they match, `patch` is run on the buffer and the updated bytes are returned, otherwise `NP_Error::EtagMismatch` is returned without running `patch`.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",   {"type": "string"}],
+    ///         ["visits", {"type": "u32"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["name"], "Jeb Kermin")?;
+    /// let etag = new_buffer.etag();
+    /// let stored = new_buffer.close();
+    /// 
+    /// // nobody else changed the buffer
+    /// let stored = factory.apply_if_match(stored, etag, |buffer| {
+    ///     buffer.set(&["visits"], 1u32)?;
+    ///     Ok(())
+    /// })?;
+    /// 
+    /// // the etag from before the first update is stale now
+    /// match factory.apply_if_match(stored.clone(), etag, |buffer| { buffer.set(&["visits"], 5u32)?; Ok(()) }) {
+    ///     Err(NP_Error::EtagMismatch { expected, found }) => {
+    ///         assert_eq!(expected, etag);
+    ///         assert_eq!(found, factory.open_buffer(stored.clone()).etag());
+    ///     },
+    ///     _ => panic!()
+    /// }
+    /// 
+    /// assert_eq!(factory.open_buffer(stored).get::<u32>(&["visits"])?, Some(1));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn apply_if_match<F>(&self, bytes: Vec<u8>, etag: u64, patch: F) -> Result<Vec<u8>, NP_Error> where F: FnOnce(&mut NP_Buffer) -> Result<(), NP_Error> {
+        let mut buffer = self.open_buffer(bytes);
+        let found = buffer.etag();
+        if found != etag {
+            return Err(NP_Error::EtagMismatch { expected: etag, found });
+        }
+        patch(&mut buffer)?;
+        Ok(buffer.close())
+    }
+
+    /// Check a path against the schema once, so buffers can follow it with `NP_Buffer::get_h` without looking up columns or parsing indexes.
+    /// 
+    /// See the [path](path/index.html) module for an example.
+    /// 
+    pub fn compile_path<'handle>(&'handle self, path: &[&str]) -> Result<NP_PathHandle<'handle>, NP_Error> {
+        NP_PathHandle::compile(&self.schema, path)
+    }
+
+    /// Start a new batch of buffers for this factory.
+    /// 
+    /// See the [batch](batch/index.html) module for an example.
+    /// 
+    pub fn empty_batch<'batch>(&'batch self) -> NP_Batch<'batch> {
+        NP_Batch::_new(self)
+    }
+
+    /// Open the bytes of a batch made with `NP_Batch::close`.
+    /// 
+    /// Fails if the batch was made with a different schema or its index points outside of the data.  See the [batch](batch/index.html) module for an example.
+    /// 
+    pub fn open_batch<'batch>(&'batch self, bytes: Vec<u8>) -> Result<NP_Batch<'batch>, NP_Error> {
+        NP_Batch::open(self, bytes)
+    }
+
+    /// Open existing Vec<u8> as buffer for this factory with a size limit and growth strategy.
+    /// 
+    /// See `empty_buffer_with` for details.  The existing bytes can be larger than `max_size`, but any write that grows the buffer will fail.
+    /// 
+    pub fn open_buffer_with<'buffer>(&'buffer self, bytes: Vec<u8>, options: NP_Buffer_Options) -> NP_Buffer<'buffer> {
+        NP_Buffer::_new(NP_Memory::existing(bytes, &self.schema).with_options(options))
     }
 }
\ No newline at end of file
@@ -0,0 +1,317 @@
+//! Sets of paths for partial record operations
+//!
+//! An `NP_Mask` is a list of paths checked against a schema, like a protobuf `FieldMask`.  Each path covers the value at the path and everything below it, `*` can be used in place of a list, log or map key to cover every item.
+//!
+//! Masks can be combined with `union` and `intersection`, and are used by:
+//! - `NP_Buffer::json_encode_masked` to copy only the masked values into JSON
+//! - `NP_Buffer::extract` to copy only the masked values into a new buffer
+//! - `NP_Buffer::merge` to copy the masked values of another buffer into this one
+//! - `NP_Buffer::view` to limit what a plugin can read and write
+//! - `NP_Buffer::compact_pruned` to drop the masked values while compacting
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::mask::NP_Mask;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",  {"type": "string"}],
+//!         ["email", {"type": "string"}],
+//!         ["tags",  {"type": "map", "value": {"type": "table", "columns": [
+//!             ["color", {"type": "string"}],
+//!             ["size",  {"type": "u8"}]
+//!         ]}}]
+//!     ]
+//! }"#)?;
+//!
+//! let contact = NP_Mask::new(&factory.schema, &[&["name"], &["email"]])?;
+//! let colors = NP_Mask::new(&factory.schema, &[&["name"], &["tags", "*", "color"]])?;
+//!
+//! let mut user = factory.empty_buffer(None);
+//! user.set(&["name"], "Jeb Kermin")?;
+//! user.set(&["email"], "jeb@ksc.com")?;
+//! user.set(&["tags", "rocket", "color"], "white")?;
+//! user.set(&["tags", "rocket", "size"], 9u8)?;
+//!
+//! assert_eq!(user.json_encode_masked(&contact.intersection(&colors))?.stringify(), r#"{"name":"Jeb Kermin"}"#);
+//!
+//! let extracted = user.extract(&contact.union(&colors))?;
+//! assert_eq!(extracted.json_encode(&[])?.stringify(), r#"{"name":"Jeb Kermin","email":"jeb@ksc.com","tags":{"rocket":{"color":"white","size":null}}}"#);
+//!
+//! // copy the contact info of one buffer into another
+//! let mut other = factory.empty_buffer(None);
+//! other.set(&["name"], "Bill Kerman")?;
+//! other.set(&["tags", "rocket", "size"], 2u8)?;
+//! other.merge(&user, &contact)?;
+//! assert_eq!(other.json_encode(&[])?.stringify(), r#"{"name":"Jeb Kermin","email":"jeb@ksc.com","tags":{"rocket":{"color":null,"size":2}}}"#);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::json_flex::NP_JSON;
+use crate::schema::{NP_Parsed_Schema, NP_Schema};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// A set of paths, see the [mask](index.html) module
+#[derive(Debug, Clone, PartialEq)]
+pub struct NP_Mask {
+    paths: Vec<Vec<String>>
+}
+
+impl NP_Mask {
+
+    /// Check every path against the schema and make a mask from them.
+    ///
+    /// Each path covers the value at the path and everything below it.  Use `*` in place of a list, log or map key to match every item.
+    ///
+    pub fn new(schema: &NP_Schema, paths: &[&[&str]]) -> Result<Self, NP_Error> {
+        for path in paths.iter() {
+            check_path(&schema.parsed, 0, path, path)?;
+        }
+        Ok(NP_Mask::from_paths(paths.iter().map(|path| path.iter().map(|key| key.to_string()).collect()).collect()))
+    }
+
+    // drop paths covered by another path
+    fn from_paths(mut paths: Vec<Vec<String>>) -> Self {
+        paths.sort_by_key(|path| path.len());
+        let mut kept: Vec<Vec<String>> = Vec::new();
+        for path in paths.into_iter() {
+            if !kept.iter().any(|other| covers(other, &path)) {
+                kept.push(path);
+            }
+        }
+        NP_Mask { paths: kept }
+    }
+
+    /// The paths in this mask, paths covered by a shorter path are left out
+    pub fn paths(&self) -> &Vec<Vec<String>> {
+        &self.paths
+    }
+
+    /// If this mask doesn't cover anything
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// If this mask covers the value at `path`
+    pub fn allows(&self, path: &[&str]) -> bool {
+        self.paths.iter().any(|mask| mask.len() <= path.len() && matches_path(mask, path))
+    }
+
+    /// A mask covering everything either mask covers
+    pub fn union(&self, other: &NP_Mask) -> NP_Mask {
+        NP_Mask::from_paths(self.paths.iter().chain(other.paths.iter()).cloned().collect())
+    }
+
+    /// A mask covering only what both masks cover
+    pub fn intersection(&self, other: &NP_Mask) -> NP_Mask {
+        let mut paths: Vec<Vec<String>> = Vec::new();
+        for a in self.paths.iter() {
+            for b in other.paths.iter() {
+                let overlaps = a.iter().zip(b.iter()).all(|(a, b)| a == b || a == "*" || b == "*");
+                if overlaps {
+                    // the longer path, with wildcards replaced by the other path's keys
+                    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+                    paths.push(long.iter().enumerate().map(|(idx, key)| {
+                        match short.get(idx) {
+                            Some(other) if key == "*" => other.clone(),
+                            _ => key.clone()
+                        }
+                    }).collect());
+                }
+            }
+        }
+        NP_Mask::from_paths(paths)
+    }
+
+    /// Leave out everything in `json` (the value at `path`) that isn't covered, `None` if nothing at or below `path` is covered
+    pub(crate) fn prune(&self, json: NP_JSON, path: &[&str]) -> Option<NP_JSON> {
+        if self.allows(path) {
+            return Some(json);
+        }
+
+        // the rest of every mask path below `path`
+        let below: Vec<&[String]> = self.paths.iter().filter(|mask| mask.len() > path.len() && matches_path(mask, path)).map(|mask| &mask[path.len()..]).collect();
+
+        if below.is_empty() {
+            None
+        } else {
+            Some(prune_json(json, &below))
+        }
+    }
+
+    /// Every path in the mask with wildcards replaced by the keys found in either JSON value (both from the root of a buffer), along with the value at each path in `from`
+    pub(crate) fn expand<'json>(&self, from: &'json NP_JSON, to: &NP_JSON) -> Vec<(Vec<String>, Option<&'json NP_JSON>)> {
+        let mut found: Vec<(Vec<String>, Option<&'json NP_JSON>)> = Vec::new();
+        for mask in self.paths.iter() {
+            expand_path(mask, Some(from), Some(to), &mut Vec::new(), &mut found);
+        }
+        found
+    }
+}
+
+// mask segments match the same key or `*`
+fn matches_path<S: AsRef<str>>(mask: &[String], path: &[S]) -> bool {
+    mask.iter().zip(path.iter()).all(|(mask, key)| mask == "*" || mask == key.as_ref())
+}
+
+// every value `path` covers is also covered by `mask`
+fn covers(mask: &[String], path: &[String]) -> bool {
+    mask.len() <= path.len() && mask.iter().zip(path.iter()).all(|(mask, key)| mask == "*" || mask == key)
+}
+
+fn check_path(schema: &Vec<NP_Parsed_Schema>, address: usize, rest: &[&str], path: &[&str]) -> Result<(), NP_Error> {
+    let key = match rest.first() {
+        Some(key) => *key,
+        None => return Ok(())
+    };
+
+    let not_found = || NP_Error::new(String::from("Path (") + &path.join(".") + ") isn't in the schema!");
+    let is_index = key == "*" || key.parse::<u16>().is_ok();
+
+    match &schema[address] {
+        NP_Parsed_Schema::Table { columns, .. } => match columns.iter().find(|column| column.1 == key) {
+            Some(column) => check_path(schema, column.2, &rest[1..], path),
+            None => Err(not_found())
+        },
+        NP_Parsed_Schema::Tuple { values, .. } => match key.parse::<usize>().ok().and_then(|idx| values.get(idx)) {
+            Some(value) => check_path(schema, *value, &rest[1..], path),
+            None => Err(not_found())
+        },
+        NP_Parsed_Schema::List { of, .. } | NP_Parsed_Schema::Log { of, .. } if is_index => check_path(schema, *of, &rest[1..], path),
+        NP_Parsed_Schema::Map { value, .. } => check_path(schema, *value, &rest[1..], path),
+        _ => Err(not_found())
+    }
+}
+
+// keep only the parts of the json matching one of the paths
+fn prune_json(json: NP_JSON, paths: &[&[String]]) -> NP_JSON {
+    if paths.iter().any(|path| path.is_empty()) {
+        return json;
+    }
+
+    let below = |key: &str| -> Vec<&[String]> {
+        paths.iter().filter(|path| path[0] == "*" || path[0] == key).map(|path| &path[1..]).collect()
+    };
+
+    match json {
+        NP_JSON::Dictionary(mut object) => {
+            let values = core::mem::take(&mut object.values);
+            for (key, value) in values.into_iter() {
+                let paths = below(&key);
+                if !paths.is_empty() {
+                    object.values.push((key, prune_json(value, &paths)));
+                }
+            }
+            NP_JSON::Dictionary(object)
+        },
+        NP_JSON::Array(items) => {
+            NP_JSON::Array(items.into_iter().enumerate().map(|(idx, item)| {
+                let paths = below(&idx.to_string());
+                if paths.is_empty() { NP_JSON::Null } else { prune_json(item, &paths) }
+            }).collect())
+        },
+        _ => NP_JSON::Null
+    }
+}
+
+fn json_child<'json>(json: Option<&'json NP_JSON>, key: &str) -> Option<&'json NP_JSON> {
+    match json {
+        Some(NP_JSON::Dictionary(object)) => object.get(key),
+        Some(NP_JSON::Array(items)) => key.parse::<usize>().ok().and_then(|idx| items.get(idx)),
+        _ => None
+    }
+}
+
+fn json_keys(json: Option<&NP_JSON>, keys: &mut Vec<String>) {
+    match json {
+        Some(NP_JSON::Dictionary(object)) => {
+            for (key, _) in object.values.iter() {
+                if !keys.contains(key) { keys.push(key.clone()) }
+            }
+        },
+        Some(NP_JSON::Array(items)) => {
+            for idx in keys.len()..items.len() {
+                keys.push(idx.to_string());
+            }
+        },
+        _ => { }
+    }
+}
+
+fn expand_path<'json>(mask: &[String], from: Option<&'json NP_JSON>, to: Option<&NP_JSON>, path: &mut Vec<String>, found: &mut Vec<(Vec<String>, Option<&'json NP_JSON>)>) {
+    let key = match mask.first() {
+        Some(key) => key,
+        None => {
+            found.push((path.clone(), from));
+            return;
+        }
+    };
+
+    let mut keys: Vec<String> = Vec::new();
+    if key == "*" {
+        json_keys(from, &mut keys);
+        json_keys(to, &mut keys);
+    } else {
+        keys.push(key.clone());
+    }
+
+    for key in keys.into_iter() {
+        let (from, to) = (json_child(from, &key), json_child(to, &key));
+        path.push(key);
+        expand_path(&mask[1..], from, to, path, found);
+        path.pop();
+    }
+}
+
+#[test]
+fn mask_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["id",    {"type": "u32"}],
+        ["tags",  {"type": "map", "value": {"type": "tuple", "values": [{"type": "string"}, {"type": "u8"}]}}],
+        ["notes", {"type": "list", "of": {"type": "string"}}]
+    ]}"#)?;
+
+    assert!(NP_Mask::new(&factory.schema, &[&["nope"]]).is_err());
+    assert!(NP_Mask::new(&factory.schema, &[&["id", "0"]]).is_err());
+    assert!(NP_Mask::new(&factory.schema, &[&["tags", "*", "2"]]).is_err());
+    assert!(NP_Mask::new(&factory.schema, &[&["notes", "first"]]).is_err());
+
+    let a = NP_Mask::new(&factory.schema, &[&["tags", "*", "0"], &["notes"], &["notes", "2"]])?;
+    let b = NP_Mask::new(&factory.schema, &[&["tags", "red"], &["notes", "1"], &["id"]])?;
+    assert_eq!(a.paths().len(), 2);
+    assert_eq!(a.union(&b).paths().len(), 4);
+    assert_eq!(a.union(&a), a);
+
+    let both = a.intersection(&b);
+    assert_eq!(both.paths(), &[["notes", "1"].iter().map(|key| key.to_string()).collect::<Vec<String>>(), ["tags", "red", "0"].iter().map(|key| key.to_string()).collect()].to_vec());
+    assert!(both.allows(&["tags", "red", "0"]));
+    assert!(!both.allows(&["tags", "blue", "0"]));
+    assert!(a.intersection(&NP_Mask::new(&factory.schema, &[&["id"]])?).is_empty());
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["id"], 4u32)?;
+    buffer.set(&["tags", "red", "0"], "crimson")?;
+    buffer.set(&["tags", "red", "1"], 2u8)?;
+    buffer.set(&["tags", "blue", "0"], "navy")?;
+    buffer.set(&["notes", "2"], "hello")?;
+    assert_eq!(buffer.json_encode_masked(&both)?.stringify(), r#"{"tags":{"red":["crimson",null]},"notes":[null,null,null]}"#);
+
+    let extracted = buffer.extract(&a)?;
+    assert_eq!(extracted.json_encode(&[])?.stringify(), r#"{"id":null,"tags":{"red":["crimson",null],"blue":["navy",null]},"notes":[null,null,"hello"]}"#);
+
+    // wildcards cover keys from both buffers, keys missing from the other buffer are cleared
+    let mut other = factory.empty_buffer(None);
+    other.set(&["tags", "green", "0"], "lime")?;
+    other.set(&["tags", "green", "1"], 7u8)?;
+    other.set(&["notes", "0"], "bye")?;
+    other.merge(&buffer, &a)?;
+    assert_eq!(other.json_encode(&[])?.stringify(), r#"{"id":null,"tags":{"red":["crimson",null],"blue":["navy",null],"green":[null,7]},"notes":[null,null,"hello"]}"#);
+
+    Ok(())
+}
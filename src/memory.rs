@@ -1,15 +1,174 @@
 //! Internal buffer memory management
 
-use crate::{schema::NP_Parsed_Schema};
+use crate::{schema::{NP_Parsed_Schema, NP_Schema}};
 use crate::{error::NP_Error};
+use crate::buffer::{NP_Buffer_Options, NP_Growth};
+use crate::hashmap::{murmurhash3_x86_32, SEED};
 use core::cell::UnsafeCell;
 use alloc::vec::Vec;
+use alloc::sync::Arc;
+use alloc::collections::{BTreeMap, BTreeSet};
+
+// set in the reserved first byte once values have been deduplicated, dynamically sized strings and bytes can be shared after that
+const SHARED_BIT: u8 = 0x01;
+// set in the reserved first byte when the two bytes after the root pointer point to the first tombstone
+const TOMBSTONE_BIT: u8 = 0x02;
+// set in the reserved first byte when the buffer has a version after the root and tombstone pointers
+const VERSION_BIT: u8 = 0x04;
+// set in the reserved first byte when one byte values are stored in their pointer's address instead of their own bytes
+const INLINE_BIT: u8 = 0x08;
+// set in the reserved first byte when the buffer has its schema's fingerprint after the version
+const FINGERPRINT_BIT: u8 = 0x10;
+// high byte of an address holding an inline value, keeps set values from reading as zero
+pub(crate) const INLINE_TAG: u16 = 0x0100;
+
+/// Bytes an inline buffer holds before it moves them to the heap, see `NP_Factory::empty_inline_buffer`.
+#[cfg(feature = "inline")]
+pub const INLINE_CAPACITY: usize = 128;
+
+// where the bytes of a buffer are kept
+#[allow(clippy::large_enum_variant)]
+enum NP_Store {
+    Heap(Arc<Vec<u8>>),
+    // length and bytes of a small buffer, kept in the memory itself until they grow past `INLINE_CAPACITY`
+    #[cfg(feature = "inline")]
+    Inline(usize, [u8; INLINE_CAPACITY])
+}
+
+impl NP_Store {
+
+    #[inline(always)]
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            NP_Store::Heap(bytes) => bytes.as_slice(),
+            #[cfg(feature = "inline")]
+            NP_Store::Inline(len, bytes) => &bytes[..*len]
+        }
+    }
+
+    #[inline(always)]
+    fn capacity(&self) -> usize {
+        match self {
+            NP_Store::Heap(bytes) => bytes.capacity(),
+            #[cfg(feature = "inline")]
+            NP_Store::Inline(..) => INLINE_CAPACITY
+        }
+    }
+
+    // the bytes of writable memory, `NP_Memory::store` has made sure nothing else shares them
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        match self {
+            NP_Store::Heap(bytes) => unsafe { &mut *(Arc::as_ptr(bytes) as *mut Vec<u8>) }.as_mut_slice(),
+            #[cfg(feature = "inline")]
+            NP_Store::Inline(len, bytes) => &mut bytes[..*len]
+        }
+    }
+
+    // the bytes as a `Vec` with room for `capacity` bytes, inline bytes move to the heap
+    #[inline(always)]
+    fn heap(&mut self, capacity: usize) -> &mut Vec<u8> {
+        #[cfg(feature = "inline")]
+        {
+            if let NP_Store::Inline(len, bytes) = self {
+                let mut heap = Vec::with_capacity(core::cmp::max(capacity, *len));
+                heap.extend_from_slice(&bytes[..*len]);
+                *self = NP_Store::Heap(Arc::new(heap));
+            }
+        }
+        match self {
+            NP_Store::Heap(bytes) => {
+                let bytes = unsafe { &mut *(Arc::as_ptr(bytes) as *mut Vec<u8>) };
+                bytes.reserve_exact(capacity.saturating_sub(bytes.len()));
+                bytes
+            },
+            #[cfg(feature = "inline")]
+            NP_Store::Inline(..) => unreachable!()
+        }
+    }
+
+    // make room for `capacity` bytes, inline bytes only move to the heap once they don't fit
+    fn reserve(&mut self, capacity: usize) {
+        if capacity > self.capacity() {
+            self.heap(capacity);
+        }
+    }
+
+    fn extend(&mut self, new_bytes: &[u8]) {
+        #[cfg(feature = "inline")]
+        {
+            if let NP_Store::Inline(len, bytes) = self {
+                if *len + new_bytes.len() <= INLINE_CAPACITY {
+                    bytes[*len..(*len + new_bytes.len())].copy_from_slice(new_bytes);
+                    *len += new_bytes.len();
+                    return;
+                }
+            }
+        }
+        let len = self.as_slice().len();
+        self.heap(len + new_bytes.len()).extend_from_slice(new_bytes);
+    }
+
+    fn resize(&mut self, new_len: usize) {
+        #[cfg(feature = "inline")]
+        {
+            if let NP_Store::Inline(len, bytes) = self {
+                if new_len <= INLINE_CAPACITY {
+                    if new_len > *len {
+                        bytes[*len..new_len].iter_mut().for_each(|byte| *byte = 0);
+                    }
+                    *len = new_len;
+                    return;
+                }
+            }
+        }
+        self.heap(new_len).resize(new_len, 0);
+    }
+
+    // bytes that can be shared with frozen memory, inline bytes move to the heap
+    fn shared(&mut self) -> &Arc<Vec<u8>> {
+        let len = self.as_slice().len();
+        self.heap(len);
+        match self {
+            NP_Store::Heap(bytes) => bytes,
+            #[cfg(feature = "inline")]
+            NP_Store::Inline(..) => unreachable!()
+        }
+    }
+
+    fn into_vec(self) -> Vec<u8> {
+        match self {
+            NP_Store::Heap(bytes) => Arc::try_unwrap(bytes).unwrap_or_else(|shared| (*shared).clone()),
+            #[cfg(feature = "inline")]
+            NP_Store::Inline(len, bytes) => bytes[..len].to_vec()
+        }
+    }
+}
 
 
 #[doc(hidden)]
 pub struct NP_Memory<'memory> {
-    bytes: UnsafeCell<Vec<u8>>,
-    pub schema: &'memory Vec<NP_Parsed_Schema>
+    bytes: UnsafeCell<NP_Store>,
+    // set when a snapshot starts sharing the bytes, the next access makes a private copy first
+    unshare: UnsafeCell<bool>,
+    // snapshots read the shared bytes and never allocate
+    frozen: bool,
+    pub schema: &'memory Vec<NP_Parsed_Schema>,
+    // the factory's schema that `schema` belongs to, new memories for the same buffer are made from it
+    pub factory_schema: &'memory NP_Schema,
+    // hash of interned values to their addresses, only covers values interned since the buffer was opened
+    interned: UnsafeCell<BTreeMap<u32, Vec<usize>>>,
+    // shared values already counted by `calc_bytes`, the only state a snapshot changes
+    counted: UnsafeCell<Option<BTreeSet<usize>>>,
+    // bytes saved so far by a deduplicating compaction
+    deduped: UnsafeCell<Option<usize>>,
+    // set while a canonical compaction copies into this memory
+    canonical: UnsafeCell<bool>,
+    // set while a pruning compaction copies into this memory
+    pruning: UnsafeCell<bool>,
+    // (allocations, times the Vec had to grow) since the buffer was created or opened
+    counters: UnsafeCell<(usize, usize)>,
+    options: NP_Buffer_Options
 }
 
 
@@ -18,62 +177,501 @@ pub struct NP_Memory<'memory> {
 impl<'memory> NP_Memory<'memory> {
 
 
-    pub fn existing(bytes: Vec<u8>, schema: &'memory Vec<NP_Parsed_Schema>) -> Self {
+    pub fn existing(mut bytes: Vec<u8>, schema: &'memory NP_Schema) -> Self {
 
-        NP_Memory {
-            bytes: UnsafeCell::new(bytes),
-            schema: schema
+        // always leave room for the root pointer
+        if bytes.len() < 3 {
+            bytes.resize(3, 0);
         }
+
+        Self::from_store(NP_Store::Heap(Arc::new(bytes)), schema)
     }
 
 
-    pub fn new(capacity: Option<usize>, schema: &'memory Vec<NP_Parsed_Schema>) -> Self {
+    pub fn new(capacity: Option<usize>, schema: &'memory NP_Schema) -> Self {
         let use_size = match capacity {
             Some(x) => x,
             None => 1024
         };
 
-        let mut new_bytes = Vec::with_capacity(use_size);
+        Self::new_in(Vec::with_capacity(use_size), schema)
+    }
+
+    /// New, empty memory using the capacity of `new_bytes`, whatever they hold is cleared.
+    pub fn new_in(mut new_bytes: Vec<u8>, schema: &'memory NP_Schema) -> Self {
+
+        new_bytes.clear();
 
         // size, root pointer
         new_bytes.extend(&[0u8; 3]);
 
+        Self::from_store(NP_Store::Heap(Arc::new(new_bytes)), schema)
+    }
+
+    /// New, empty memory that keeps its bytes inline until they grow past `INLINE_CAPACITY`, nothing is allocated for them until then.
+    #[cfg(feature = "inline")]
+    pub fn new_inline(schema: &'memory NP_Schema) -> Self {
+        // size, root pointer
+        Self::from_store(NP_Store::Inline(3, [0u8; INLINE_CAPACITY]), schema)
+    }
+
+    fn from_store(store: NP_Store, schema: &'memory NP_Schema) -> Self {
         NP_Memory {
-            bytes: UnsafeCell::new(new_bytes),
-            schema: schema,
+            bytes: UnsafeCell::new(store),
+            unshare: UnsafeCell::new(false),
+            frozen: false,
+            schema: &schema.parsed,
+            factory_schema: schema,
+            interned: UnsafeCell::new(BTreeMap::new()),
+            counted: UnsafeCell::new(None),
+            deduped: UnsafeCell::new(None),
+            canonical: UnsafeCell::new(false),
+            pruning: UnsafeCell::new(false),
+            counters: UnsafeCell::new((0, 0)),
+            options: NP_Buffer_Options::default()
         }
     }
 
+    pub fn with_options(mut self, options: NP_Buffer_Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    #[inline(always)]
+    pub fn options(&self) -> NP_Buffer_Options {
+        self.options
+    }
+
+    /// Fingerprint of the factory's schema, see the [fingerprint](../fingerprint/index.html) module.
+    #[inline(always)]
+    pub fn fingerprint(&self) -> u64 {
+        self.factory_schema.fingerprint
+    }
+
+    // only writes go through here, frozen memory shares its bytes with other threads and never does
+    #[inline(always)]
+    #[allow(clippy::mut_from_ref)]
+    fn store(&self) -> &mut NP_Store {
+        assert!(!self.frozen, "frozen memory can't be written to");
+        let store = unsafe { &mut *self.bytes.get() };
+        let unshare = unsafe { &mut *self.unshare.get() };
+        if *unshare {
+            *unshare = false;
+            // not shared after `make_mut`, only frozen memory keeps its bytes shared
+            match store {
+                NP_Store::Heap(bytes) => { Arc::make_mut(bytes); },
+                // inline bytes are never shared
+                #[cfg(feature = "inline")]
+                NP_Store::Inline(..) => { }
+            }
+        }
+        store
+    }
+
+    #[inline(always)]
+    fn bytes(&self) -> &mut [u8] {
+        self.store().as_mut_slice()
+    }
+
+    // bookkeeping for writes and compaction, frozen memory is read from many threads at once and never changes any of it
+    #[inline(always)]
+    #[allow(clippy::mut_from_ref)]
+    fn state<'state, T>(&self, cell: &'state UnsafeCell<T>) -> &'state mut T {
+        assert!(!self.frozen, "frozen memory can't be written to");
+        unsafe { &mut *cell.get() }
+    }
+
+    // reads never unshare or copy the bytes, frozen memory only ever borrows them immutably
+    #[inline(always)]
+    fn bytes_ref(&self) -> &[u8] {
+        unsafe { &*self.bytes.get() }.as_slice()
+    }
+
+    /// Share the bytes written so far with a new, read only copy of this memory.  This memory copies the bytes the next time it's used, the copy never changes.
+    pub fn freeze(&self) -> NP_Memory<'memory> {
+        Self::shared(self.share(), self.factory_schema, self.options)
+    }
+
+    /// The bytes written so far, this memory copies them the next time it's used.
+    pub fn share(&self) -> Arc<Vec<u8>> {
+        if self.frozen {
+            return match unsafe { &*self.bytes.get() } {
+                NP_Store::Heap(bytes) => Arc::clone(bytes),
+                #[cfg(feature = "inline")]
+                NP_Store::Inline(..) => unreachable!()
+            };
+        }
+        // the copy is made the next time the bytes are written, not now
+        let store = unsafe { &mut *self.bytes.get() };
+        let shared = Arc::clone(store.shared());
+        unsafe { *self.unshare.get() = true };
+        shared
+    }
+
+    /// Frozen memory reading bytes from `share`.
+    pub fn shared(bytes: Arc<Vec<u8>>, schema: &'memory NP_Schema, options: NP_Buffer_Options) -> NP_Memory<'memory> {
+        NP_Memory {
+            bytes: UnsafeCell::new(NP_Store::Heap(bytes)),
+            schema: &schema.parsed,
+            factory_schema: schema,
+            interned: UnsafeCell::new(BTreeMap::new()),
+            counted: UnsafeCell::new(None),
+            deduped: UnsafeCell::new(None),
+            canonical: UnsafeCell::new(false),
+            pruning: UnsafeCell::new(false),
+            counters: UnsafeCell::new((0, 0)),
+            unshare: UnsafeCell::new(false),
+            frozen: true,
+            options
+        }
+    }
+
+    /// Writable memory from frozen memory, the bytes are copied the first time they're used if anything else still shares them.
+    pub fn thaw(mut self) -> NP_Memory<'memory> {
+        if self.frozen {
+            self.frozen = false;
+            self.unshare = UnsafeCell::new(true);
+        }
+        self
+    }
+
     pub fn malloc_borrow(&self, bytes: &[u8])  -> Result<usize, NP_Error> {
-        let self_bytes = unsafe { &mut *self.bytes.get() };
+        let location = self.read_bytes().len();
 
-        let location = self_bytes.len();
+        self.reserve(location + bytes.len())?;
+        self.store().extend(bytes);
+        Ok(location)
+    }
+
+    // make room for the buffer to reach `new_len` bytes, growing it the way the options ask for
+    #[inline(always)]
+    fn reserve(&self, new_len: usize) -> Result<(), NP_Error> {
+
+        if self.frozen {
+            return Err(NP_Error::new_static("Snapshots can't be changed!"));
+        }
 
         // not enough space left?
-        if location + bytes.len() >= core::u16::MAX as usize {
-            return Err(NP_Error::new("Not enough space available in buffer!"))
+        if new_len >= core::u16::MAX as usize || new_len > self.options.max_size {
+            return Err(NP_Error::OutOfSpace)
         }
 
-        self_bytes.extend(bytes);
-        Ok(location)
+        let counters = self.state(&self.counters);
+        counters.0 += 1;
+        np_count!(ALLOCATIONS);
+
+        self.grow_capacity(new_len);
+
+        Ok(())
+    }
+
+    // grow the capacity so the buffer can reach `new_len` bytes without another copy
+    #[inline(always)]
+    fn grow_capacity(&self, new_len: usize) {
+        let counters = self.state(&self.counters);
+        let store = self.store();
+
+        let capacity = store.capacity();
+        if new_len > capacity {
+            counters.1 += 1;
+            np_count!(GROWS);
+            let target = match self.options.growth {
+                NP_Growth::Double => core::cmp::max(capacity * 2, new_len),
+                NP_Growth::Linear(step) => new_len + step
+            };
+            // never hold more capacity than the buffer is allowed to use
+            let target = core::cmp::max(core::cmp::min(target, self.options.max_size), new_len);
+            np_event!(from = capacity, to = target, "grow");
+            store.reserve(target);
+        }
+    }
+
+    /// How many more bytes can be written before the buffer reaches its max size.
+    pub fn available(&self) -> usize {
+        let limit = core::cmp::min(self.options.max_size, u16::MAX as usize - 1);
+        limit.saturating_sub(self.read_bytes().len())
+    }
+
+    /// Make sure `bytes` more bytes can be written without running out of space or growing the buffer again.
+    pub fn try_reserve(&self, bytes: usize) -> Result<(), NP_Error> {
+        if self.frozen {
+            return Err(NP_Error::new_static("Snapshots can't be changed!"));
+        }
+        if bytes > self.available() {
+            return Err(NP_Error::OutOfSpace);
+        }
+        self.grow_capacity(self.read_bytes().len() + bytes);
+        Ok(())
+    }
+
+    /// How many allocations were made and how many times the buffer had to grow since it was created or opened.
+    pub fn alloc_counts(&self) -> (usize, usize) {
+        unsafe { *self.counters.get() }
+    }
+
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        unsafe { &*self.bytes.get() }.capacity()
     }
 
     pub fn malloc(&self, bytes: Vec<u8>) -> Result<usize, NP_Error> {
         self.malloc_borrow(&bytes)
     }
 
+    /// Like `malloc_borrow`, but pads the buffer with zeros first so the returned address is a multiple of `align`.
+    ///
+    /// Addresses are relative to the start of the buffer, so the bytes are only aligned in memory if the buffer itself is.
+    pub fn malloc_aligned(&self, bytes: &[u8], align: usize) -> Result<usize, NP_Error> {
+        let location = self.read_bytes().len();
+        let padding = (align - (location % align)) % align;
+
+        self.reserve(location + padding + bytes.len())?;
+        let store = self.store();
+        store.resize(location + padding);
+        store.extend(bytes);
+        Ok(location + padding)
+    }
+
+    /// Like `malloc_borrow`, but returns the address of an identical value interned earlier instead of storing the bytes again.
+    /// 
+    /// Values at interned addresses are shared, they must never be updated in place.
+    pub fn malloc_interned(&self, bytes: &[u8]) -> Result<usize, NP_Error> {
+        if self.frozen {
+            return Err(NP_Error::new_static("Snapshots can't be changed!"));
+        }
+
+        let interned = self.state(&self.interned);
+
+        let hash = murmurhash3_x86_32(bytes, SEED);
+
+        if let Some(addrs) = interned.get(&hash) {
+            for addr in addrs {
+                if self.read_bytes().get(*addr..(*addr + bytes.len())) == Some(bytes) {
+                    return Ok(*addr);
+                }
+            }
+        }
+
+        let addr = self.malloc_borrow(bytes)?;
+        interned.entry(hash).or_insert_with(Vec::new).push(addr);
+        Ok(addr)
+    }
+
+    /// Start deduplicating, every dynamically sized string and bytes value stored with `malloc_dedupe` is shared with identical values.
+    pub fn start_dedupe(&self) {
+        let deduped = self.state(&self.deduped);
+        *deduped = Some(0);
+    }
+
+    /// Stop deduplicating, returns how many bytes were saved.
+    /// 
+    /// The intern index is cleared since it now holds values that aren't interned by their schema.
+    pub fn end_dedupe(&self) -> usize {
+        let deduped = self.state(&self.deduped);
+        let interned = self.state(&self.interned);
+        interned.clear();
+        deduped.take().unwrap_or(0)
+    }
+
     #[inline(always)]
-    pub fn read_bytes(&self) -> &Vec<u8> {
-        let self_bytes = unsafe { &*self.bytes.get() };
-        self_bytes
-    }   
+    pub fn is_deduping(&self) -> bool {
+        unsafe { &*self.deduped.get() }.is_some()
+    }
+
+    /// Start or stop copying values in a canonical layout, collections are copied in an order that doesn't depend on how they were written.
+    pub fn set_canonical(&self, canonical: bool) {
+        let flag = self.state(&self.canonical);
+        *flag = canonical;
+    }
+
+    #[inline(always)]
+    pub fn is_canonical(&self) -> bool {
+        unsafe { *self.canonical.get() }
+    }
+
+    /// Start or stop leaving out map keys without a value while copying into this memory.
+    pub fn set_pruning(&self, pruning: bool) {
+        let flag = self.state(&self.pruning);
+        *flag = pruning;
+    }
+
+    #[inline(always)]
+    pub fn is_pruning(&self) -> bool {
+        unsafe { *self.pruning.get() }
+    }
+
+    /// Like `malloc_interned`, but counts the bytes saved and marks the buffer as having shared values.
+    pub fn malloc_dedupe(&self, bytes: &[u8]) -> Result<usize, NP_Error> {
+        let end = self.read_bytes().len();
+        let addr = self.malloc_interned(bytes)?;
+        if addr < end {
+            let deduped = self.state(&self.deduped);
+            if let Some(x) = deduped {
+                *x += bytes.len();
+            }
+            self.write_bytes()[0] |= SHARED_BIT;
+        }
+        Ok(addr)
+    }
+
+    /// True if dynamically sized strings and bytes in this buffer may be shared, they must be copied instead of updated in place.
+    #[inline(always)]
+    pub fn has_shared(&self) -> bool {
+        self.read_bytes()[0] & SHARED_BIT != 0
+    }
+
+    /// True if this buffer records deletes, see the `tombstone` module.
+    #[inline(always)]
+    pub fn has_tombstones(&self) -> bool {
+        self.read_bytes()[0] & TOMBSTONE_BIT != 0
+    }
+
+    /// Make room for the tombstone pointer, only works while the buffer has nothing but its root pointer.
+    pub fn start_tombstones(&self) -> Result<(), NP_Error> {
+        if self.read_bytes().len() != crate::tombstone::HEAD_ADDR {
+            return Err(NP_Error::new_static("Tombstones can only be added to empty buffers!"));
+        }
+        self.malloc_borrow(&[0u8; 2])?;
+        self.write_bytes()[0] |= TOMBSTONE_BIT;
+        Ok(())
+    }
+
+    /// True if this buffer has a version, see the `version` module.
+    #[inline(always)]
+    pub fn has_version(&self) -> bool {
+        self.read_bytes()[0] & VERSION_BIT != 0
+    }
+
+    /// Make room for the version, only works while the buffer has nothing but its root pointer and tombstone pointer.
+    pub fn start_version(&self) -> Result<(), NP_Error> {
+        let header = crate::tombstone::HEAD_ADDR + if self.has_tombstones() { 2 } else { 0 };
+        if self.read_bytes().len() != header {
+            return Err(NP_Error::new_static("A version can only be added to empty buffers!"));
+        }
+        self.malloc_borrow(&[0u8; crate::version::VERSION_LEN])?;
+        self.write_bytes()[0] |= VERSION_BIT;
+        Ok(())
+    }
+
+    /// True if this buffer has the fingerprint of its schema, see the `fingerprint` module.
+    #[inline(always)]
+    pub fn has_fingerprint(&self) -> bool {
+        self.read_bytes()[0] & FINGERPRINT_BIT != 0
+    }
+
+    /// Make room for the fingerprint, only works while the buffer has nothing but its header.
+    pub fn start_fingerprint(&self) -> Result<(), NP_Error> {
+        if self.read_bytes().len() != crate::fingerprint::address(self) {
+            return Err(NP_Error::new_static("A fingerprint can only be added to empty buffers!"));
+        }
+        self.malloc_borrow(&[0u8; crate::fingerprint::FINGERPRINT_LEN])?;
+        self.write_bytes()[0] |= FINGERPRINT_BIT;
+        Ok(())
+    }
+
+    /// True if one byte values (`bool`, `u8`, `i8` and `option`) are stored in their pointers, see `NP_Buffer::inline_small_values`.
+    #[inline(always)]
+    pub fn has_inline(&self) -> bool {
+        self.read_bytes()[0] & INLINE_BIT != 0
+    }
+
+    /// True if the reserved first byte has flags this version doesn't know about.
+    pub fn has_unknown_flags(&self) -> bool {
+        self.read_bytes()[0] & !(SHARED_BIT | TOMBSTONE_BIT | VERSION_BIT | INLINE_BIT | FINGERPRINT_BIT) != 0
+    }
+
+    /// Store one byte values in their pointers from now on, only safe while the buffer has no one byte values.
+    pub fn start_inline(&self) {
+        self.write_bytes()[0] |= INLINE_BIT;
+    }
+
+    /// True if the value with this schema is stored in its pointer.
+    pub fn is_inline(&self, schema_addr: usize) -> bool {
+        self.has_inline() && matches!(self.schema[schema_addr], NP_Parsed_Schema::Boolean { .. } | NP_Parsed_Schema::Int8 { .. } | NP_Parsed_Schema::Uint8 { .. } | NP_Parsed_Schema::Enum { wide: false, .. })
+    }
+
+    /// Read a one byte value from the address in its pointer.
+    #[inline(always)]
+    pub fn get_small(&self, addr_value: u16) -> Option<u8> {
+        if addr_value == 0 {
+            return None;
+        }
+        if self.has_inline() {
+            Some(addr_value as u8)
+        } else {
+            self.get_1_byte(addr_value as usize)
+        }
+    }
+
+    /// Write a one byte value, returns the address to put in its pointer.
+    pub fn set_small(&self, addr_value: u16, value: u8) -> Result<u16, NP_Error> {
+        if self.has_inline() {
+            Ok(INLINE_TAG | value as u16)
+        } else if addr_value != 0 {
+            self.write_bytes()[addr_value as usize] = value;
+            Ok(addr_value)
+        } else {
+            Ok(self.malloc_borrow(&[value])? as u16)
+        }
+    }
+
+    /// Bytes a one byte value uses outside of its pointer.
+    #[inline(always)]
+    pub fn small_size(&self, addr_value: u16) -> usize {
+        if addr_value == 0 || self.has_inline() { 0 } else { 1 }
+    }
+
+    /// Start or stop counting shared values once, used while measuring the buffer.
+    /// 
+    /// Measuring a snapshot counts into it as well, readers never measure so frozen memory they hold isn't changed.
+    pub fn count_shared(&self, enabled: bool) {
+        let counted = unsafe { &mut *self.counted.get() };
+        *counted = if enabled { Some(BTreeSet::new()) } else { None };
+    }
+
+    /// False if a shared value at this address has already been counted.
+    pub fn first_count(&self, address: usize) -> bool {
+        let counted = unsafe { &mut *self.counted.get() };
+        match counted {
+            Some(x) => x.insert(address),
+            None => true
+        }
+    }
 
     #[inline(always)]
-    pub fn write_bytes(&self) -> &mut Vec<u8> {
-        let self_bytes = unsafe { &mut *self.bytes.get() };
+    pub fn read_bytes(&self) -> &[u8] {
+        self.bytes_ref()
+    }
+
+    #[inline(always)]
+    pub fn write_bytes(&self) -> &mut [u8] {
+        let self_bytes = self.bytes();
         self_bytes
     }
 
+    /// Shorten the buffer to `len` bytes, for giving back space at the end of the buffer.
+    pub fn truncate(&self, len: usize) {
+        if len < self.read_bytes().len() {
+            self.store().resize(len);
+        }
+    }
+
+    /// Pointer to the first byte, for changing fixed size structures in the buffer.
+    /// 
+    /// Writable memory makes its own copy of shared bytes first.  Frozen memory panics, use `read_ptr` to read.
+    #[inline(always)]
+    pub fn bytes_ptr(&self) -> *mut u8 {
+        self.bytes().as_mut_ptr()
+    }
+
+    /// Pointer to the first byte, for reading fixed size structures in the buffer.
+    /// 
+    /// Only shared references may be made from it, it never copies shared bytes so frozen memory can use it.
+    #[inline(always)]
+    pub fn read_ptr(&self) -> *const u8 {
+        self.bytes_ref().as_ptr()
+    }
+
     #[inline(always)]
     pub fn get_1_byte(&self, address: usize) -> Option<u8> {
 
@@ -82,7 +680,11 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
+        let self_bytes = self.bytes_ref();
+
+        if self_bytes.len() < address + 1 {
+            return None;
+        }
  
         Some(self_bytes[address])
     }
@@ -95,7 +697,7 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
+        let self_bytes = self.bytes_ref();
 
         if self_bytes.len() < address + 2 {
             return None;
@@ -114,7 +716,7 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
+        let self_bytes = self.bytes_ref();
 
         if self_bytes.len() < address + 4 {
             return None;
@@ -133,7 +735,7 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
+        let self_bytes = self.bytes_ref();
 
         if self_bytes.len() < address + 8 {
             return None;
@@ -152,7 +754,7 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
+        let self_bytes = self.bytes_ref();
 
         if self_bytes.len() < address + 16 {
             return None;
@@ -171,7 +773,7 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
+        let self_bytes = self.bytes_ref();
 
         if self_bytes.len() < address + 32 {
             return None;
@@ -182,7 +784,52 @@ impl<'memory> NP_Memory<'memory> {
         Some(unsafe { &*(slice as *const [u8] as *const [u8; 32]) })
     }
 
+    /// The bytes, emptied so their capacity can be used again.  Bytes shared with a snapshot stay with the snapshot.
+    pub fn reclaim(self) -> Vec<u8> {
+        match self.bytes.into_inner() {
+            NP_Store::Heap(bytes) => match Arc::try_unwrap(bytes) {
+                Ok(mut bytes) => {
+                    bytes.clear();
+                    bytes
+                },
+                Err(_shared) => Vec::new()
+            },
+            #[cfg(feature = "inline")]
+            NP_Store::Inline(..) => Vec::new()
+        }
+    }
+
     pub fn dump(self) -> Vec<u8> {
-        self.bytes.into_inner()
+        self.bytes.into_inner().into_vec()
+    }
+}
+#[cfg(feature = "inline")]
+#[test]
+fn inline_store_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+
+    // small buffers stay inline through writes, compaction and readers
+    let mut buffer = factory.empty_inline_buffer();
+    buffer.set(&["0"], "hello")?;
+    buffer.set(&["0"], "hi")?;
+    buffer.compact(None)?;
+    assert_eq!(buffer.memory_stats()?.growth_events, 0);
+    let reader = buffer.into_reader();
+    let first = reader.clone();
+    assert_eq!(first.get::<&str>(&["0"])?, Some("hi"));
+
+    // the writer spills to the heap once the bytes don't fit
+    let mut writer = reader.into_writer();
+    for i in 1..20 {
+        writer.set(&[alloc::format!("{}", i).as_str()], "world")?;
     }
-}
\ No newline at end of file
+    assert!(writer.memory_stats()?.capacity > INLINE_CAPACITY);
+    assert_eq!(writer.get::<&str>(&["0"])?, Some("hi"));
+    assert_eq!(writer.get::<&str>(&["19"])?, Some("world"));
+    assert_eq!(first.length(&[])?, Some(1));
+
+    let bytes = writer.into_buffer().close();
+    assert_eq!(factory.open_buffer(bytes).get::<&str>(&["19"])?, Some("world"));
+
+    Ok(())
+}
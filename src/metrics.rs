@@ -0,0 +1,71 @@
+//! Process wide counters, enabled with the `metrics` feature
+//!
+//! Every buffer adds to a few global counters as it's used, `NP_Metrics::read` returns their current values.  The counters only ever go up, so they can be scraped and reported as they are, for example as Prometheus counters.
+//!
+//! - `allocations`: space allocated inside any buffer
+//! - `grows`: allocations that made the `Vec<u8>` of a buffer grow (and likely copy)
+//! - `compactions`: buffers compacted, including every `NP_Compactor` that finished
+//! - `type_errors`: values read or written with a type that doesn't match the schema
+//!
+//! Counters are updated with relaxed atomic adds, without the feature they're compiled out entirely.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::metrics::NP_Metrics;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}]
+//!     ]
+//! }"#)?;
+//!
+//! let before = NP_Metrics::read();
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb Kermin")?;
+//! assert!(new_buffer.get::<u8>(&["name"]).is_err());
+//! new_buffer.compact(None)?;
+//!
+//! let after = NP_Metrics::read();
+//! assert!(after.allocations >= before.allocations + 3);
+//! assert!(after.compactions >= before.compactions + 1);
+//! assert!(after.type_errors >= before.type_errors + 1);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+pub(crate) static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+pub(crate) static GROWS: AtomicUsize = AtomicUsize::new(0);
+pub(crate) static COMPACTIONS: AtomicUsize = AtomicUsize::new(0);
+pub(crate) static TYPE_ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+/// Values of the process wide counters, see the [metrics](index.html) module
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct NP_Metrics {
+    /// Space allocated inside any buffer
+    pub allocations: usize,
+    /// Allocations that made the `Vec<u8>` of a buffer grow
+    pub grows: usize,
+    /// Buffers compacted
+    pub compactions: usize,
+    /// Values read or written with a type that doesn't match the schema
+    pub type_errors: usize
+}
+
+impl NP_Metrics {
+
+    /// The counters as they are now
+    pub fn read() -> Self {
+        NP_Metrics {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            grows: GROWS.load(Ordering::Relaxed),
+            compactions: COMPACTIONS.load(Ordering::Relaxed),
+            type_errors: TYPE_ERRORS.load(Ordering::Relaxed)
+        }
+    }
+}
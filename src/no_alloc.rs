@@ -0,0 +1,472 @@
+//! Read only buffer access without a heap
+//!
+//! For devices that only consume buffers, `NP_Static_Buffer` reads scalar values straight out of a `&[u8]` using a schema stored in a constant.  Nothing in this module allocates, and it's the only part of the crate available when the default `alloc` feature is turned off:
+//!
+//! ```toml
+//! no_proto = { version = "0.7", default-features = false }
+//! ```
+//!
+//! The schema constant is generated from a normal schema with `NP_Schema::to_static_schema` (on a machine that has `alloc`), then pasted into the device code.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::no_alloc::{NP_Static_Schema, NP_Static_Buffer, NP_Static_Value};
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["readings", {"type": "list", "of": {"type": "i16"}}]
+//!     ]
+//! }"#)?;
+//!
+//! // generate the constant once
+//! assert_eq!(factory.schema.to_static_schema(), r#"NP_Static_Schema::Table { columns: &[("name", NP_Static_Schema::UTF8String { size: 0 }), ("readings", NP_Static_Schema::List { of: &NP_Static_Schema::Int16 })] }"#);
+//!
+//! // and use it on the device
+//! const SCHEMA: NP_Static_Schema = NP_Static_Schema::Table { columns: &[("name", NP_Static_Schema::UTF8String { size: 0 }), ("readings", NP_Static_Schema::List { of: &NP_Static_Schema::Int16 })] };
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "sensor")?;
+//! new_buffer.set(&["readings", "2"], -40i16)?;
+//! let bytes = new_buffer.close();
+//!
+//! let reader = NP_Static_Buffer::new(&SCHEMA, &bytes);
+//! assert_eq!(reader.get(&["name"]), Some(NP_Static_Value::String("sensor")));
+//! assert_eq!(reader.get(&["readings", "2"]), Some(NP_Static_Value::Int16(-40)));
+//! assert_eq!(reader.get(&["readings", "0"]), None);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use core::convert::TryInto;
+
+#[cfg(feature = "alloc")]
+use crate::schema::{NP_Schema, NP_Parsed_Schema};
+#[cfg(feature = "alloc")]
+use alloc::{string::{String, ToString}, vec::Vec, format};
+
+// must match `buffer::ROOT_PTR_ADDR`, which isn't available without `alloc`
+const ROOT_PTR_ADDR: usize = 1;
+// must match `memory::INLINE_BIT`, one byte values are stored in their pointer when set
+const INLINE_BIT: u8 = 0x08;
+
+// lists and maps can't hold more items than this, stops loops in corrupted buffers
+const MAX_ITEMS: usize = 256;
+// wide list items take 8 bytes, a buffer can't hold more than this many
+const MAX_WIDE_ITEMS: usize = 8192;
+
+/// Schema that can be stored in a constant
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(missing_docs)]
+pub enum NP_Static_Schema {
+    None,
+    Any,
+    UTF8String { size: u16 },
+    /// Fixed size string with a `unicode_ci` sort key in front of the value
+    CollatedString { size: u16 },
+    Bytes      { size: u16 },
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Uint8,
+    Uint16,
+    Uint32,
+    Uint64,
+    Float,
+    Double,
+    Decimal    { exp: u8 },
+    Boolean,
+    Geo        { size: u8 },
+    Date,
+    Enum       { choices: &'static [&'static str] },
+    /// Option with a two byte choice index
+    WideEnum   { choices: &'static [&'static str] },
+    Uuid,
+    Ulid,
+    Table      { columns: &'static [(&'static str, NP_Static_Schema)] },
+    Map        { value: &'static NP_Static_Schema },
+    List       { of: &'static NP_Static_Schema },
+    /// List with a four byte item index
+    WideList   { of: &'static NP_Static_Schema },
+    Log        { of: &'static NP_Static_Schema },
+    Tensor     { size: u16 },
+    BigInt,
+    Tuple      { values: &'static [NP_Static_Schema] }
+}
+
+/// Scalar value read from a static buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(missing_docs)]
+pub enum NP_Static_Value<'buffer> {
+    String(&'buffer str),
+    Bytes(&'buffer [u8]),
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    Int64(i64),
+    Uint8(u8),
+    Uint16(u16),
+    Uint32(u32),
+    Uint64(u64),
+    Float(f32),
+    Double(f64),
+    Decimal { num: i64, exp: u8 },
+    Boolean(bool),
+    Geo { lat: f64, lng: f64 },
+    Date(u64),
+    Enum(&'static str),
+    Uuid(&'buffer [u8; 16]),
+    Ulid(&'buffer [u8; 16]),
+    /// little endian bytes of the tensor values
+    Tensor(&'buffer [u8]),
+    /// big endian magnitude bytes, may have leading zeros
+    BigInt { negative: bool, magnitude: &'buffer [u8] }
+}
+
+/// Read only view of buffer bytes using a static schema
+#[derive(Debug, Clone, Copy)]
+pub struct NP_Static_Buffer<'buffer> {
+    schema: &'static NP_Static_Schema,
+    bytes: &'buffer [u8]
+}
+
+impl<'buffer> NP_Static_Buffer<'buffer> {
+
+    /// Read the bytes of a closed buffer with the provided schema
+    pub fn new(schema: &'static NP_Static_Schema, bytes: &'buffer [u8]) -> Self {
+        NP_Static_Buffer { schema, bytes }
+    }
+
+    /// Get the scalar value at the provided path.
+    ///
+    /// Returns `None` if the value isn't set, the path doesn't exist in the schema, the path points to a collection or the bytes are corrupted.  Schema defaults are not used.
+    ///
+    pub fn get(&self, path: &[&str]) -> Option<NP_Static_Value<'buffer>> {
+
+        let mut schema = self.schema;
+        let mut pointer = ROOT_PTR_ADDR;
+
+        for key in path {
+            let addr = self.value_addr(pointer)?;
+            match schema {
+                NP_Static_Schema::Table { columns } => {
+                    let index = columns.iter().position(|column| column.0 == *key)?;
+                    pointer = self.vtable_pointer(addr, index)?;
+                    schema = &columns[index].1;
+                },
+                NP_Static_Schema::Tuple { values } => {
+                    let index = key.parse::<usize>().ok()?;
+                    schema = values.get(index)?;
+                    pointer = self.vtable_pointer(addr, index)?;
+                },
+                NP_Static_Schema::List { of } => {
+                    pointer = self.list_pointer(addr, key.parse::<usize>().ok()?, false)?;
+                    schema = of;
+                },
+                NP_Static_Schema::WideList { of } => {
+                    pointer = self.list_pointer(addr, key.parse::<usize>().ok()?, true)?;
+                    schema = of;
+                },
+                NP_Static_Schema::Log { of } => {
+                    pointer = self.log_pointer(addr, key.parse::<usize>().ok()?)?;
+                    schema = of;
+                },
+                NP_Static_Schema::Map { value } => {
+                    pointer = self.map_pointer(addr, key)?;
+                    schema = value;
+                },
+                _ => return None
+            }
+        }
+
+        let addr = self.value_addr(pointer)?;
+
+        // inline values are the low byte of the pointer's address
+        match schema {
+            NP_Static_Schema::Int8 | NP_Static_Schema::Uint8 | NP_Static_Schema::Boolean | NP_Static_Schema::Enum { .. } if self.bytes.first()? & INLINE_BIT != 0 => {
+                self.scalar(schema, pointer + 1)
+            },
+            _ => self.scalar(schema, addr)
+        }
+    }
+
+    fn u16_at(&self, addr: usize) -> Option<usize> {
+        let bytes = self.bytes.get(addr..(addr + 2))?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+    }
+
+    fn fixed(&self, addr: usize, len: usize) -> Option<&'buffer [u8]> {
+        self.bytes.get(addr..(addr + len))
+    }
+
+    // every pointer type starts with the value address, zero means no value
+    fn value_addr(&self, pointer: usize) -> Option<usize> {
+        match self.u16_at(pointer)? {
+            0 => None,
+            addr => Some(addr)
+        }
+    }
+
+    // vtables hold 4 pointers followed by the address of the next vtable
+    fn vtable_pointer(&self, vtable_addr: usize, index: usize) -> Option<usize> {
+        let mut vtable_addr = vtable_addr;
+        for _ in 0..(index / 4) {
+            vtable_addr = match self.u16_at(vtable_addr + 8)? {
+                0 => return None,
+                next => next
+            };
+        }
+        let pointer = vtable_addr + ((index % 4) * 2);
+        self.fixed(pointer, 2)?;
+        Some(pointer)
+    }
+
+    // list items are [addr, next, index] and sorted by index, the index is a u32 in wide lists
+    fn list_pointer(&self, list_addr: usize, index: usize, wide: bool) -> Option<usize> {
+        let mut item = self.u16_at(list_addr)?;
+        let mut count = 0usize;
+        while item != 0 && count < if wide { MAX_WIDE_ITEMS } else { MAX_ITEMS } {
+            let item_index = if wide { (self.u16_at(item + 4)? << 16) | self.u16_at(item + 6)? } else { *self.bytes.get(item + 4)? as usize };
+            if item_index == index {
+                return Some(item);
+            }
+            if item_index > index {
+                return None;
+            }
+            item = self.u16_at(item + 2)?;
+            count += 1;
+        }
+        None
+    }
+
+    // logs are [head, tail, count] followed by items [addr, next] in append order
+    fn log_pointer(&self, log_addr: usize, index: usize) -> Option<usize> {
+        if index >= self.u16_at(log_addr + 4)? {
+            return None;
+        }
+        let mut item = self.u16_at(log_addr)?;
+        for _ in 0..index {
+            item = match self.u16_at(item + 2)? {
+                0 => return None,
+                next => next
+            };
+        }
+        Some(item)
+    }
+
+    // the map pointer holds the head item, map items are [addr, next, key addr], keys are a length byte followed by the key
+    fn map_pointer(&self, head: usize, key: &str) -> Option<usize> {
+        let mut item = head;
+        let mut count = 0usize;
+        while item != 0 && count < MAX_ITEMS {
+            let key_addr = self.u16_at(item + 4)?;
+            let key_len = *self.bytes.get(key_addr)? as usize;
+            if self.fixed(key_addr + 1, key_len)? == key.as_bytes() {
+                return Some(item);
+            }
+            item = self.u16_at(item + 2)?;
+            count += 1;
+        }
+        None
+    }
+
+    fn sized(&self, addr: usize, size: u16) -> Option<&'buffer [u8]> {
+        if size > 0 {
+            self.fixed(addr, size as usize)
+        } else {
+            self.fixed(addr + 2, self.u16_at(addr)?)
+        }
+    }
+
+    fn scalar(&self, schema: &'static NP_Static_Schema, addr: usize) -> Option<NP_Static_Value<'buffer>> {
+
+        // numbers are big endian, signed numbers have their first bit flipped so they sort correctly
+        macro_rules! number {
+            ($t: ty, $addr: expr, $signed: literal) => {{
+                let mut be_bytes = <$t>::default().to_be_bytes();
+                let len = be_bytes.len();
+                be_bytes.copy_from_slice(self.fixed($addr, len)?);
+                if $signed {
+                    be_bytes[0] ^= 0x80; // same as `utils::to_signed`
+                }
+                <$t>::from_be_bytes(be_bytes)
+            }}
+        }
+
+        Some(match schema {
+            NP_Static_Schema::UTF8String { size } => NP_Static_Value::String(core::str::from_utf8(self.sized(addr, *size)?).ok()?),
+            NP_Static_Schema::CollatedString { size } => NP_Static_Value::String(core::str::from_utf8(self.fixed(addr + *size as usize, *size as usize)?).ok()?),
+            NP_Static_Schema::Bytes { size }      => NP_Static_Value::Bytes(self.sized(addr, *size)?),
+            NP_Static_Schema::Int8                => NP_Static_Value::Int8(number!(i8, addr, true)),
+            NP_Static_Schema::Int16               => NP_Static_Value::Int16(number!(i16, addr, true)),
+            NP_Static_Schema::Int32               => NP_Static_Value::Int32(number!(i32, addr, true)),
+            NP_Static_Schema::Int64               => NP_Static_Value::Int64(number!(i64, addr, true)),
+            NP_Static_Schema::Uint8               => NP_Static_Value::Uint8(number!(u8, addr, false)),
+            NP_Static_Schema::Uint16              => NP_Static_Value::Uint16(number!(u16, addr, false)),
+            NP_Static_Schema::Uint32              => NP_Static_Value::Uint32(number!(u32, addr, false)),
+            NP_Static_Schema::Uint64              => NP_Static_Value::Uint64(number!(u64, addr, false)),
+            NP_Static_Schema::Float               => NP_Static_Value::Float(number!(f32, addr, false)),
+            NP_Static_Schema::Double              => NP_Static_Value::Double(number!(f64, addr, false)),
+            NP_Static_Schema::Decimal { exp }     => NP_Static_Value::Decimal { num: number!(i64, addr, true), exp: *exp },
+            NP_Static_Schema::Boolean             => NP_Static_Value::Boolean(*self.bytes.get(addr)? == 1),
+            NP_Static_Schema::Date                => NP_Static_Value::Date(number!(u64, addr, false)),
+            // same devisers as `NP_Geo::get_deviser`
+            NP_Static_Schema::Geo { size: 16 }    => NP_Static_Value::Geo { lat: number!(i64, addr, true) as f64 / 1000000000f64, lng: number!(i64, addr + 8, true) as f64 / 1000000000f64 },
+            NP_Static_Schema::Geo { size: 8 }     => NP_Static_Value::Geo { lat: number!(i32, addr, true) as f64 / 10000000f64, lng: number!(i32, addr + 4, true) as f64 / 10000000f64 },
+            NP_Static_Schema::Geo { size: 4 }     => NP_Static_Value::Geo { lat: number!(i16, addr, true) as f64 / 100f64, lng: number!(i16, addr + 2, true) as f64 / 100f64 },
+            NP_Static_Schema::Enum { choices }    => NP_Static_Value::Enum(choices.get(*self.bytes.get(addr)? as usize)?),
+            NP_Static_Schema::WideEnum { choices } => NP_Static_Value::Enum(choices.get(self.u16_at(addr)?)?),
+            NP_Static_Schema::Uuid                => NP_Static_Value::Uuid(self.fixed(addr, 16)?.try_into().ok()?),
+            NP_Static_Schema::Ulid                => NP_Static_Value::Ulid(self.fixed(addr, 16)?.try_into().ok()?),
+            // tensor data starts after an offset byte and alignment padding
+            NP_Static_Schema::Tensor { size }     => NP_Static_Value::Tensor(self.fixed(addr + *self.bytes.get(addr)? as usize, *size as usize)?),
+            NP_Static_Schema::BigInt              => {
+                let (sign, magnitude) = self.sized(addr, 0)?.split_first()?;
+                NP_Static_Value::BigInt { negative: *sign == 1, magnitude }
+            },
+            _ => return None
+        })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl NP_Schema {
+    /// Generate the Rust source of a `NP_Static_Schema` constant for this schema, to read buffers without `alloc`.
+    /// 
+    /// See the [no_alloc module](../no_alloc/index.html) for an example.
+    /// 
+    pub fn to_static_schema(&self) -> String {
+        static_schema(&self.parsed, 0)
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn static_schema(schema: &[NP_Parsed_Schema], address: usize) -> String {
+    match &schema[address] {
+        NP_Parsed_Schema::None                  => "NP_Static_Schema::None".to_string(),
+        NP_Parsed_Schema::Any { .. }            => "NP_Static_Schema::Any".to_string(),
+        NP_Parsed_Schema::UTF8String { size, collation: crate::schema::String_Collation::UnicodeCi, .. } => format!("NP_Static_Schema::CollatedString {{ size: {} }}", size),
+        NP_Parsed_Schema::UTF8String { size, .. } => format!("NP_Static_Schema::UTF8String {{ size: {} }}", size),
+        NP_Parsed_Schema::Bytes { size, .. }    => format!("NP_Static_Schema::Bytes {{ size: {} }}", size),
+        NP_Parsed_Schema::Int8 { .. }           => "NP_Static_Schema::Int8".to_string(),
+        NP_Parsed_Schema::Int16 { .. }          => "NP_Static_Schema::Int16".to_string(),
+        NP_Parsed_Schema::Int32 { .. }          => "NP_Static_Schema::Int32".to_string(),
+        NP_Parsed_Schema::Int64 { .. }          => "NP_Static_Schema::Int64".to_string(),
+        NP_Parsed_Schema::Uint8 { .. }          => "NP_Static_Schema::Uint8".to_string(),
+        NP_Parsed_Schema::Uint16 { .. }         => "NP_Static_Schema::Uint16".to_string(),
+        NP_Parsed_Schema::Uint32 { .. }         => "NP_Static_Schema::Uint32".to_string(),
+        NP_Parsed_Schema::Uint64 { .. }         => "NP_Static_Schema::Uint64".to_string(),
+        NP_Parsed_Schema::Float { .. }          => "NP_Static_Schema::Float".to_string(),
+        NP_Parsed_Schema::Double { .. }         => "NP_Static_Schema::Double".to_string(),
+        NP_Parsed_Schema::Decimal { exp, .. }   => format!("NP_Static_Schema::Decimal {{ exp: {} }}", exp),
+        NP_Parsed_Schema::Boolean { .. }        => "NP_Static_Schema::Boolean".to_string(),
+        NP_Parsed_Schema::Geo { size, .. }      => format!("NP_Static_Schema::Geo {{ size: {} }}", size),
+        NP_Parsed_Schema::Date { .. }           => "NP_Static_Schema::Date".to_string(),
+        NP_Parsed_Schema::Enum { choices, wide, .. }  => {
+            let choices: Vec<String> = choices.iter().map(|choice| format!("{:?}", choice.to_string())).collect();
+            format!("NP_Static_Schema::{} {{ choices: &[{}] }}", if *wide { "WideEnum" } else { "Enum" }, choices.join(", "))
+        },
+        NP_Parsed_Schema::Uuid { .. }           => "NP_Static_Schema::Uuid".to_string(),
+        NP_Parsed_Schema::Ulid { .. }           => "NP_Static_Schema::Ulid".to_string(),
+        NP_Parsed_Schema::BigInt { .. }         => "NP_Static_Schema::BigInt".to_string(),
+        // plugin values are read as their raw bytes
+        NP_Parsed_Schema::Custom { .. }         => "NP_Static_Schema::Bytes { size: 0 }".to_string(),
+        NP_Parsed_Schema::Tensor { dtype, shape, .. } => format!("NP_Static_Schema::Tensor {{ size: {} }}", shape.iter().product::<usize>() * crate::pointer::tensor::dtype_size(dtype)),
+        NP_Parsed_Schema::Table { columns, .. } => {
+            let columns: Vec<String> = columns.iter().map(|(_, name, addr)| format!("({:?}, {})", name, static_schema(schema, *addr))).collect();
+            format!("NP_Static_Schema::Table {{ columns: &[{}] }}", columns.join(", "))
+        },
+        NP_Parsed_Schema::Map { value, .. }     => format!("NP_Static_Schema::Map {{ value: &{} }}", static_schema(schema, *value)),
+        NP_Parsed_Schema::List { of, wide: false, .. } => format!("NP_Static_Schema::List {{ of: &{} }}", static_schema(schema, *of)),
+        NP_Parsed_Schema::List { of, wide: true, .. }  => format!("NP_Static_Schema::WideList {{ of: &{} }}", static_schema(schema, *of)),
+        NP_Parsed_Schema::Log { of, .. }        => format!("NP_Static_Schema::Log {{ of: &{} }}", static_schema(schema, *of)),
+        NP_Parsed_Schema::Tuple { values, .. }  => {
+            let values: Vec<String> = values.iter().map(|addr| static_schema(schema, *addr)).collect();
+            format!("NP_Static_Schema::Tuple {{ values: &[{}] }}", values.join(", "))
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn static_buffer_matches_buffer() -> Result<(), crate::error::NP_Error> {
+    use crate::pointer::{dec::NP_Dec, geo::NP_Geo, option::NP_Enum, uuid::NP_UUID};
+
+    const SCHEMA: NP_Static_Schema = NP_Static_Schema::Table { columns: &[("string", NP_Static_Schema::UTF8String { size: 0 }), ("fixed", NP_Static_Schema::Bytes { size: 3 }), ("i32", NP_Static_Schema::Int32), ("u64", NP_Static_Schema::Uint64), ("f64", NP_Static_Schema::Double), ("dec", NP_Static_Schema::Decimal { exp: 2 }), ("bool", NP_Static_Schema::Boolean), ("geo", NP_Static_Schema::Geo { size: 8 }), ("option", NP_Static_Schema::Enum { choices: &["red", "blue"] }), ("uuid", NP_Static_Schema::Uuid), ("map", NP_Static_Schema::Map { value: &NP_Static_Schema::Int8 }), ("tuple", NP_Static_Schema::Tuple { values: &[NP_Static_Schema::Uint8, NP_Static_Schema::UTF8String { size: 0 }] })] };
+
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["string", {"type": "string"}],
+        ["fixed",  {"type": "bytes", "size": 3}],
+        ["i32",    {"type": "i32"}],
+        ["u64",    {"type": "u64"}],
+        ["f64",    {"type": "double"}],
+        ["dec",    {"type": "decimal", "exp": 2}],
+        ["bool",   {"type": "bool"}],
+        ["geo",    {"type": "geo8"}],
+        ["option", {"type": "option", "choices": ["red", "blue"]}],
+        ["uuid",   {"type": "uuid"}],
+        ["map",    {"type": "map", "value": {"type": "i8"}}],
+        ["tuple",  {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}]
+    ]}"#)?;
+
+    assert_eq!(factory.schema.to_static_schema(), r#"NP_Static_Schema::Table { columns: &[("string", NP_Static_Schema::UTF8String { size: 0 }), ("fixed", NP_Static_Schema::Bytes { size: 3 }), ("i32", NP_Static_Schema::Int32), ("u64", NP_Static_Schema::Uint64), ("f64", NP_Static_Schema::Double), ("dec", NP_Static_Schema::Decimal { exp: 2 }), ("bool", NP_Static_Schema::Boolean), ("geo", NP_Static_Schema::Geo { size: 8 }), ("option", NP_Static_Schema::Enum { choices: &["red", "blue"] }), ("uuid", NP_Static_Schema::Uuid), ("map", NP_Static_Schema::Map { value: &NP_Static_Schema::Int8 }), ("tuple", NP_Static_Schema::Tuple { values: &[NP_Static_Schema::Uint8, NP_Static_Schema::UTF8String { size: 0 }] })] }"#);
+
+    let uuid = NP_UUID::generate(5);
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["string"], "hello")?;
+    buffer.set(&["fixed"], &[1u8, 2, 3] as &[u8])?;
+    buffer.set(&["i32"], -2000i32)?;
+    buffer.set(&["u64"], u64::MAX)?;
+    buffer.set(&["f64"], 1.5f64)?;
+    buffer.set(&["dec"], NP_Dec::new(-314, 2))?;
+    buffer.set(&["bool"], true)?;
+    buffer.set(&["geo"], NP_Geo::new(8, 45.5, -122.25))?;
+    buffer.set(&["option"], NP_Enum::new("blue"))?;
+    buffer.set(&["uuid"], &uuid)?;
+    buffer.set(&["map", "a"], -1i8)?;
+    buffer.set(&["map", "b"], 2i8)?;
+    buffer.set(&["tuple", "1"], "last")?;
+    let bytes = buffer.close();
+
+    let reader = NP_Static_Buffer::new(&SCHEMA, &bytes);
+    assert_eq!(reader.get(&["string"]), Some(NP_Static_Value::String("hello")));
+    assert_eq!(reader.get(&["fixed"]), Some(NP_Static_Value::Bytes(&[1, 2, 3])));
+    assert_eq!(reader.get(&["i32"]), Some(NP_Static_Value::Int32(-2000)));
+    assert_eq!(reader.get(&["u64"]), Some(NP_Static_Value::Uint64(u64::MAX)));
+    assert_eq!(reader.get(&["f64"]), Some(NP_Static_Value::Double(1.5)));
+    assert_eq!(reader.get(&["dec"]), Some(NP_Static_Value::Decimal { num: -314, exp: 2 }));
+    assert_eq!(reader.get(&["bool"]), Some(NP_Static_Value::Boolean(true)));
+    assert_eq!(reader.get(&["geo"]), Some(NP_Static_Value::Geo { lat: 45.5, lng: -122.25 }));
+    assert_eq!(reader.get(&["option"]), Some(NP_Static_Value::Enum("blue")));
+    assert_eq!(reader.get(&["uuid"]), Some(NP_Static_Value::Uuid(&uuid.value)));
+    assert_eq!(reader.get(&["map", "a"]), Some(NP_Static_Value::Int8(-1)));
+    assert_eq!(reader.get(&["map", "b"]), Some(NP_Static_Value::Int8(2)));
+    assert_eq!(reader.get(&["map", "c"]), None);
+    assert_eq!(reader.get(&["tuple", "0"]), None);
+    assert_eq!(reader.get(&["tuple", "1"]), Some(NP_Static_Value::String("last")));
+    assert_eq!(reader.get(&["tuple", "2"]), None);
+    assert_eq!(reader.get(&["nope"]), None);
+    assert_eq!(reader.get(&["map"]), None);
+
+    // corrupted bytes never panic
+    for len in 0..bytes.len() {
+        NP_Static_Buffer::new(&SCHEMA, &bytes[..len]).get(&["tuple", "1"]);
+    }
+
+    // one byte values stored in their pointers
+    let mut inline = factory.open_buffer(bytes);
+    inline.inline_small_values()?;
+    let bytes = inline.close();
+    let reader = NP_Static_Buffer::new(&SCHEMA, &bytes);
+    assert_eq!(reader.get(&["bool"]), Some(NP_Static_Value::Boolean(true)));
+    assert_eq!(reader.get(&["option"]), Some(NP_Static_Value::Enum("blue")));
+    assert_eq!(reader.get(&["map", "a"]), Some(NP_Static_Value::Int8(-1)));
+    assert_eq!(reader.get(&["tuple", "0"]), None);
+    assert_eq!(reader.get(&["i32"]), Some(NP_Static_Value::Int32(-2000)));
+
+    Ok(())
+}
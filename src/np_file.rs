@@ -0,0 +1,455 @@
+//! Paged files of sorted buffers
+//!
+//! An NP file stores buffers in order of a sortable key, like the rows of a table on disk.  Files carry their own compiled schema, so they can be opened without the schema they were written with.
+//!
+//! Buffers are grouped into pages and the first key of every page is kept in an index at the front of the file.  `NP_File::seek` only reads the index and the pages it needs, so files can be much larger than memory when they're opened from a `std::io::Read + Seek` source like a `std::fs::File` (wrap it in `NP_Read_Seek`, needs the `std` feature).
+//!
+//! Keys are bytes compared in order, the bytes from `NP_Buffer::close_sortable` or big endian numbers both work.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::np_file::{NP_File, NP_File_Writer};
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! // keys must be added in order
+//! let mut writer = NP_File_Writer::new(&factory, 4096);
+//! for (id, name) in [(1u32, "Jeb"), (5u32, "Bill"), (9u32, "Bob")].iter() {
+//!     let mut user = factory.empty_buffer(None);
+//!     user.set(&["name"], *name)?;
+//!     writer.append(&id.to_be_bytes(), &user)?;
+//! }
+//! let bytes: Vec<u8> = writer.finish();
+//!
+//! let mut file = NP_File::open(bytes)?;
+//! assert_eq!(file.len(), 3);
+//!
+//! // exact matches
+//! let bill = file.get(&5u32.to_be_bytes())?.unwrap();
+//! assert_eq!(bill.get::<&str>(&["name"])?, Some("Bill"));
+//! assert!(file.get(&6u32.to_be_bytes())?.is_none());
+//!
+//! // every buffer from the first key that's at least 2
+//! let names: Vec<String> = file.seek(&2u32.to_be_bytes())?
+//!     .map(|record| record.unwrap().1.get::<&str>(&["name"]).unwrap().unwrap().to_owned())
+//!     .collect();
+//! assert_eq!(names, vec!["Bill", "Bob"]);
+//!
+//! assert_eq!(file.scan()?.count(), 3);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! Files are laid out like this, every number is big endian:
+//!
+//! | Bytes    | Contents                                                             |
+//! |----------|----------------------------------------------------------------------|
+//! | 0 - 3    | `NPF` and the file format version                                    |
+//! | 4 - 7    | Length of the compiled schema (u32)                                  |
+//! | ...      | Compiled schema                                                      |
+//! | 8 bytes  | Number of buffers (u64)                                              |
+//! | 4 bytes  | Number of pages (u32)                                                |
+//! | ...      | Page index, for each page: address (u64), length (u32), first key length (u16) and first key |
+//! | ...      | Pages, for each buffer: key length (u16), key, buffer length (u32), buffer |
+//!
+
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::NP_Factory;
+use alloc::vec::Vec;
+use alloc::string::ToString;
+
+/// File format version written by `NP_File_Writer`
+pub const NP_FILE_VERSION: u8 = 1;
+
+const MAGIC: &[u8; 3] = b"NPF";
+
+/// Something an `NP_File` can read bytes from at any address
+pub trait NP_File_Source {
+    /// Read `len` bytes starting at `address`, fail if there aren't that many
+    fn read_at(&mut self, address: u64, len: usize) -> Result<Vec<u8>, NP_Error>;
+}
+
+impl NP_File_Source for Vec<u8> {
+    fn read_at(&mut self, address: u64, len: usize) -> Result<Vec<u8>, NP_Error> {
+        self.as_slice().read_at(address, len)
+    }
+}
+
+impl NP_File_Source for &[u8] {
+    fn read_at(&mut self, address: u64, len: usize) -> Result<Vec<u8>, NP_Error> {
+        let start = address as usize;
+        match start.checked_add(len).and_then(|end| self.get(start..end)) {
+            Some(bytes) => Ok(bytes.to_vec()),
+            None => Err(NP_Error::new_static("NP file is truncated!"))
+        }
+    }
+}
+
+/// Read an `NP_File` from any `std::io::Read + Seek` source, like a `std::fs::File`.  Needs the `std` feature.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct NP_Read_Seek<R>(pub R);
+
+#[cfg(feature = "std")]
+impl<R> NP_File_Source for NP_Read_Seek<R> where R: std::io::Read + std::io::Seek {
+    fn read_at(&mut self, address: u64, len: usize) -> Result<Vec<u8>, NP_Error> {
+        let mut bytes: Vec<u8> = alloc::vec![0u8; len];
+        self.0.seek(std::io::SeekFrom::Start(address)).map_err(|err| NP_Error::new(err.to_string()))?;
+        self.0.read_exact(&mut bytes).map_err(|err| NP_Error::new(err.to_string()))?;
+        Ok(bytes)
+    }
+}
+
+fn read_number<const N: usize>(bytes: &[u8], address: usize) -> [u8; N] {
+    let mut number = [0u8; N];
+    number.copy_from_slice(&bytes[address..(address + N)]);
+    number
+}
+
+/// Writes buffers into an NP file, see the [np_file](index.html) module.
+#[derive(Debug)]
+pub struct NP_File_Writer<'writer> {
+    factory: &'writer NP_Factory,
+    page_size: usize,
+    // (first key, bytes) of every page
+    pages: Vec<(Vec<u8>, Vec<u8>)>,
+    last_key: Option<Vec<u8>>,
+    count: u64
+}
+
+impl<'writer> NP_File_Writer<'writer> {
+
+    /// Start a file for buffers of `factory`.  A new page is started once a page has `page_size` bytes or more in it.
+    pub fn new(factory: &'writer NP_Factory, page_size: usize) -> Self {
+        NP_File_Writer { factory, page_size, pages: Vec::new(), last_key: None, count: 0 }
+    }
+
+    /// Add a buffer to the end of the file.
+    ///
+    /// Keys must be added in order, each key has to be the same or larger than the one before it.  Keys can be up to 65,535 bytes.
+    ///
+    pub fn append(&mut self, key: &[u8], buffer: &NP_Buffer) -> Result<(), NP_Error> {
+        if !buffer.has_schema(self.factory.schema.fingerprint) {
+            return Err(NP_Error::new_static("Can't add a buffer with a different schema to this file!"));
+        }
+
+        if key.len() > u16::MAX as usize {
+            return Err(NP_Error::new_static("NP file keys can't be longer than 65,535 bytes!"));
+        }
+
+        if let Some(last) = &self.last_key {
+            if key < last.as_slice() {
+                return Err(NP_Error::new_static("NP file keys must be added in order!"));
+            }
+        }
+
+        let bytes = buffer.read_bytes();
+        if bytes.len() > u32::MAX as usize {
+            return Err(NP_Error::OutOfSpace);
+        }
+
+        match self.pages.last() {
+            Some((_, page)) if page.len() < self.page_size => { },
+            _ => self.pages.push((key.to_vec(), Vec::new()))
+        }
+
+        if let Some((_, page)) = self.pages.last_mut() {
+            page.extend_from_slice(&(key.len() as u16).to_be_bytes());
+            page.extend_from_slice(key);
+            page.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            page.extend_from_slice(bytes);
+        }
+
+        self.last_key = Some(key.to_vec());
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Get the bytes of the file
+    pub fn finish(self) -> Vec<u8> {
+        let schema = self.factory.compile_schema();
+
+        let mut file: Vec<u8> = Vec::new();
+        file.extend_from_slice(MAGIC);
+        file.push(NP_FILE_VERSION);
+        file.extend_from_slice(&(schema.len() as u32).to_be_bytes());
+        file.extend_from_slice(&schema);
+        file.extend_from_slice(&self.count.to_be_bytes());
+        file.extend_from_slice(&(self.pages.len() as u32).to_be_bytes());
+
+        let index_len: usize = self.pages.iter().map(|(key, _)| 14 + key.len()).sum();
+        let mut address = (file.len() + index_len) as u64;
+        for (key, page) in self.pages.iter() {
+            file.extend_from_slice(&address.to_be_bytes());
+            file.extend_from_slice(&(page.len() as u32).to_be_bytes());
+            file.extend_from_slice(&(key.len() as u16).to_be_bytes());
+            file.extend_from_slice(key);
+            address += page.len() as u64;
+        }
+
+        for (_, page) in self.pages.iter() {
+            file.extend_from_slice(page);
+        }
+        file
+    }
+}
+
+#[derive(Debug)]
+struct NP_Page {
+    first_key: Vec<u8>,
+    address: u64,
+    len: usize
+}
+
+/// An open NP file, see the [np_file](index.html) module.
+#[derive(Debug)]
+pub struct NP_File<S: NP_File_Source> {
+    source: S,
+    factory: NP_Factory,
+    pages: Vec<NP_Page>,
+    count: u64
+}
+
+impl<S: NP_File_Source> NP_File<S> {
+
+    /// Open a file, reading its schema and page index.
+    pub fn open(mut source: S) -> Result<Self, NP_Error> {
+        let header = source.read_at(0, 8)?;
+        if &header[0..3] != MAGIC || header[3] != NP_FILE_VERSION {
+            return Err(NP_Error::new_static("Not an NP file, or an unknown NP file version!"));
+        }
+
+        let schema_len = u32::from_be_bytes(read_number(&header, 4)) as u64;
+        let factory = NP_Factory::new_compiled(source.read_at(8, schema_len as usize)?);
+
+        let mut address = 8 + schema_len;
+        let counts = source.read_at(address, 12)?;
+        let count = u64::from_be_bytes(read_number(&counts, 0));
+        let page_count = u32::from_be_bytes(read_number(&counts, 8));
+        address += 12;
+
+        let mut pages: Vec<NP_Page> = Vec::new();
+        for _ in 0..page_count {
+            let entry = source.read_at(address, 14)?;
+            let key_len = u16::from_be_bytes(read_number(&entry, 12)) as usize;
+            pages.push(NP_Page {
+                address: u64::from_be_bytes(read_number(&entry, 0)),
+                len: u32::from_be_bytes(read_number(&entry, 8)) as usize,
+                first_key: source.read_at(address + 14, key_len)?
+            });
+            address += 14 + key_len as u64;
+        }
+
+        Ok(NP_File { source, factory, pages, count })
+    }
+
+    /// The factory for the schema stored in the file
+    pub fn factory(&self) -> &NP_Factory {
+        &self.factory
+    }
+
+    /// How many buffers are in the file
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    /// If the file has no buffers
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// How many pages the buffers are split into
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Iterate over every `(key, buffer)` in the file, in key order.
+    pub fn scan(&mut self) -> Result<NP_File_Scan<'_, S>, NP_Error> {
+        let mut scan = NP_File_Scan { source: &mut self.source, factory: &self.factory, pages: &self.pages, page: 0, bytes: Vec::new(), pos: 0 };
+        scan.load()?;
+        Ok(scan)
+    }
+
+    /// Iterate over every `(key, buffer)` in key order, starting at the first key that's the same or larger than `key`.
+    ///
+    /// Only the page with `key` and the pages after it are read.
+    ///
+    pub fn seek(&mut self, key: &[u8]) -> Result<NP_File_Scan<'_, S>, NP_Error> {
+        // last page starting before the key, duplicate keys can span pages so step back over pages starting with it
+        let mut page = self.pages.partition_point(|page| page.first_key.as_slice() < key).saturating_sub(1);
+        while page > 0 && self.pages[page].first_key.as_slice() >= key {
+            page -= 1;
+        }
+
+        let mut scan = NP_File_Scan { source: &mut self.source, factory: &self.factory, pages: &self.pages, page, bytes: Vec::new(), pos: 0 };
+        scan.load()?;
+        while scan.next_page()? {
+            match scan.peek_key() {
+                Some(next) if next < key => scan.skip_record(),
+                _ => break
+            }
+        }
+        Ok(scan)
+    }
+
+    /// Get the first buffer with exactly this key.
+    pub fn get(&mut self, key: &[u8]) -> Result<Option<NP_Buffer<'_>>, NP_Error> {
+        match self.seek(key)?.next() {
+            Some(record) => {
+                let (found, buffer) = record?;
+                Ok(if found == key { Some(buffer) } else { None })
+            },
+            None => Ok(None)
+        }
+    }
+}
+
+/// Iterator over the `(key, buffer)` records of an `NP_File`, from `NP_File::scan` or `NP_File::seek`.
+#[derive(Debug)]
+pub struct NP_File_Scan<'file, S: NP_File_Source> {
+    source: &'file mut S,
+    factory: &'file NP_Factory,
+    pages: &'file [NP_Page],
+    page: usize,
+    bytes: Vec<u8>,
+    pos: usize
+}
+
+impl<'file, S: NP_File_Source> NP_File_Scan<'file, S> {
+
+    // read the current page, if there is one
+    fn load(&mut self) -> Result<(), NP_Error> {
+        self.pos = 0;
+        self.bytes = Vec::new();
+        self.bytes = match self.pages.get(self.page) {
+            Some(page) => self.source.read_at(page.address, page.len)?,
+            None => Vec::new()
+        };
+        Ok(())
+    }
+
+    // (key start, key end, buffer start, buffer end) of the next record in the page
+    fn next_record(&self) -> Option<(usize, usize, usize, usize)> {
+        let key_start = self.pos + 2;
+        let key_len = u16::from_be_bytes(read_number(self.bytes.get(self.pos..key_start)?, 0)) as usize;
+        let buffer_start = key_start + key_len + 4;
+        let buffer_len = u32::from_be_bytes(read_number(self.bytes.get((key_start + key_len)..buffer_start)?, 0)) as usize;
+        if buffer_start + buffer_len > self.bytes.len() {
+            return None;
+        }
+        Some((key_start, key_start + key_len, buffer_start, buffer_start + buffer_len))
+    }
+
+    // move to the next page once this one is used up, false at the end of the file
+    fn next_page(&mut self) -> Result<bool, NP_Error> {
+        while self.pos >= self.bytes.len() {
+            if self.page + 1 >= self.pages.len() {
+                return Ok(false);
+            }
+            self.page += 1;
+            if let Err(err) = self.load() {
+                self.page = self.pages.len();
+                return Err(err);
+            }
+        }
+        Ok(true)
+    }
+
+    fn peek_key(&self) -> Option<&[u8]> {
+        let (key_start, key_end, _, _) = self.next_record()?;
+        Some(&self.bytes[key_start..key_end])
+    }
+
+    fn skip_record(&mut self) {
+        self.pos = match self.next_record() {
+            Some((_, _, _, end)) => end,
+            None => self.bytes.len()
+        };
+    }
+}
+
+impl<'file, S: NP_File_Source> Iterator for NP_File_Scan<'file, S> {
+    type Item = Result<(Vec<u8>, NP_Buffer<'file>), NP_Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_page() {
+            Ok(true) => { },
+            Ok(false) => return None,
+            Err(err) => return Some(Err(err))
+        }
+
+        match self.next_record() {
+            Some((key_start, key_end, buffer_start, buffer_end)) => {
+                self.pos = buffer_end;
+                let key = self.bytes[key_start..key_end].to_vec();
+                let buffer = self.factory.open_buffer(self.bytes[buffer_start..buffer_end].to_vec());
+                Some(Ok((key, buffer)))
+            },
+            None => {
+                self.page = self.pages.len();
+                self.bytes.clear();
+                Some(Err(NP_Error::new_static("NP file page is corrupt!")))
+            }
+        }
+    }
+}
+
+#[test]
+fn np_file_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "u32"}"#)?;
+
+    // empty files
+    let mut file = NP_File::open(NP_File_Writer::new(&factory, 64).finish())?;
+    assert!(file.is_empty());
+    assert!(file.scan()?.next().is_none());
+    assert!(file.get(&[1])?.is_none());
+
+    // small pages, duplicate keys across pages
+    let mut writer = NP_File_Writer::new(&factory, 32);
+    for i in 0..100u32 {
+        let mut buffer = factory.empty_buffer(None);
+        buffer.set(&[], i)?;
+        let key = if (40..50).contains(&i) { 40u16 } else { i as u16 };
+        writer.append(&key.to_be_bytes(), &buffer)?;
+    }
+    assert!(writer.append(&[0, 0], &factory.empty_buffer(None)).is_err());
+    let other = crate::NP_Factory::new(r#"{"type": "string"}"#)?;
+    assert!(writer.append(&[9, 9], &other.empty_buffer(None)).is_err());
+    let bytes = writer.finish();
+
+    let mut file = NP_File::open(bytes.as_slice())?;
+    assert_eq!(file.len(), 100);
+    assert!(file.page_count() > 10);
+    assert_eq!(file.factory().schema.to_json()?.stringify(), r#"{"type":"uint32"}"#);
+
+    let values: Vec<u32> = file.scan()?.map(|record| record.unwrap().1.get::<u32>(&[]).unwrap().unwrap()).collect();
+    assert_eq!(values, (0..100u32).collect::<Vec<u32>>());
+
+    let first = |file: &mut NP_File<&[u8]>, key: u16| -> Option<u32> {
+        file.seek(&key.to_be_bytes()).unwrap().next().map(|record| record.unwrap().1.get::<u32>(&[]).unwrap().unwrap())
+    };
+    assert_eq!(first(&mut file, 0), Some(0));
+    assert_eq!(first(&mut file, 40), Some(40));
+    assert_eq!(first(&mut file, 45), Some(50));
+    assert_eq!(first(&mut file, 99), Some(99));
+    assert_eq!(first(&mut file, 100), None);
+    assert_eq!(file.seek(&40u16.to_be_bytes())?.take_while(|record| record.as_ref().unwrap().0 == 40u16.to_be_bytes()).count(), 10);
+    assert_eq!(file.get(&77u16.to_be_bytes())?.unwrap().get::<u32>(&[])?, Some(77));
+    assert!(file.get(&[0, 44])?.is_none());
+
+    // broken files
+    assert!(NP_File::open(&bytes[..20]).is_err());
+    assert!(NP_File::open(&b"NPF\x09"[..]).is_err());
+    let mut truncated = NP_File::open(&bytes[..(bytes.len() - 3)])?;
+    assert!(truncated.scan()?.last().unwrap().is_err());
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! Precompiled paths
+//!
+//! Every `get` with a `&[&str]` path looks up each table column by name and parses each list index from a string.  `NP_Factory::compile_path` does that work once and returns an `NP_PathHandle`, `NP_Buffer::get_h` then walks straight to the value.
+//!
+//! Handles are always relative to the root of the buffer, and can be used with any buffer of the factory they were compiled with.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",    {"type": "string"}],
+//!         ["friends", {"type": "list", "of": {"type": "table", "columns": [
+//!             ["name",  {"type": "string"}],
+//!             ["age",   {"type": "u8", "default": 18}]
+//!         ]}}]
+//!     ]
+//! }"#)?;
+//!
+//! let first_friend = factory.compile_path(&["friends", "0", "name"])?;
+//! let friend_age = factory.compile_path(&["friends", "0", "age"])?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["friends", "0", "name"], "Bill")?;
+//!
+//! assert_eq!(new_buffer.get_h::<&str>(&first_friend)?, Some("Bill"));
+//! assert_eq!(new_buffer.get_h::<u8>(&friend_age)?, Some(18));
+//!
+//! // paths are checked against the schema when they're compiled
+//! assert!(factory.compile_path(&["friends", "zero"]).is_err());
+//! assert!(factory.compile_path(&["enemies"]).is_err());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::schema::{NP_Parsed_Schema, NP_Schema, NP_Schema_Addr};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// One step of a compiled path
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum NP_Path_Step {
+    /// Table column index and the column's schema address
+    Column(usize, NP_Schema_Addr),
+    /// Tuple index
+    Tuple(usize),
+    /// List index
+    List(usize),
+    /// Log index
+    Log(usize),
+    /// Map key
+    Key(String)
+}
+
+/// A path checked against a schema, get one with `NP_Factory::compile_path`.
+///
+/// See the [path](index.html) module for an example.
+#[derive(Debug, Clone)]
+pub struct NP_PathHandle<'factory> {
+    pub(crate) schema: &'factory NP_Schema,
+    pub(crate) steps: Vec<NP_Path_Step>,
+    pub(crate) path: Vec<String>,
+    // schema of the value the path points to
+    pub(crate) schema_addr: NP_Schema_Addr
+}
+
+impl<'factory> NP_PathHandle<'factory> {
+
+    pub(crate) fn compile(schema: &'factory NP_Schema, path: &[&str]) -> Result<Self, NP_Error> {
+        let mut steps: Vec<NP_Path_Step> = Vec::with_capacity(path.len());
+        let mut schema_addr: NP_Schema_Addr = 0;
+
+        let index = |key: &str| -> Result<usize, NP_Error> {
+            key.parse::<usize>().map_err(|_| NP_Error::new_static("Need a number to index into a tuple, list or log, string found!"))
+        };
+
+        for key in path.iter() {
+            let (step, next) = match &schema.parsed[schema_addr] {
+                NP_Parsed_Schema::Table { columns, columns_mapped, .. } => {
+                    match columns_mapped.get(key) {
+                        Some(idx) => (NP_Path_Step::Column(*idx, columns[*idx].2), columns[*idx].2),
+                        None => return Err(NP_Error::new(alloc::format!("Column '{}' isn't in the table!", key)))
+                    }
+                },
+                NP_Parsed_Schema::Tuple { values, .. } => {
+                    let idx = index(key)?;
+                    match values.get(idx) {
+                        Some(value) => (NP_Path_Step::Tuple(idx), *value),
+                        None => return Err(NP_Error::new(alloc::format!("Index {} is past the end of the tuple!", idx)))
+                    }
+                },
+                NP_Parsed_Schema::List { of, .. } => (NP_Path_Step::List(index(key)?), *of),
+                NP_Parsed_Schema::Log { of, .. } => (NP_Path_Step::Log(index(key)?), *of),
+                NP_Parsed_Schema::Map { value, .. } => (NP_Path_Step::Key(key.to_string()), *value),
+                _ => return Err(NP_Error::new_static("Path continues past a scalar value!"))
+            };
+            steps.push(step);
+            schema_addr = next;
+        }
+
+        Ok(NP_PathHandle {
+            schema,
+            steps,
+            path: path.iter().map(|key| key.to_string()).collect(),
+            schema_addr
+        })
+    }
+
+    /// The path this handle was compiled from
+    pub fn path(&self) -> Vec<&str> {
+        self.path.iter().map(|key| key.as_str()).collect()
+    }
+}
+
+#[test]
+fn path_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "tuple", "values": [
+        {"type": "map", "value": {"type": "log", "of": {"type": "u32"}}},
+        {"type": "table", "columns": [["a", {"type": "u8"}], ["b", {"type": "u8"}], ["c", {"type": "u8"}], ["d", {"type": "u8"}], ["e", {"type": "string"}]]}
+    ]}"#)?;
+
+    let deep = factory.compile_path(&["0", "key", "0"])?;
+    let column = factory.compile_path(&["1", "e"])?;
+    assert_eq!(deep.path(), alloc::vec!["0", "key", "0"]);
+    assert!(factory.compile_path(&["2"]).is_err());
+    assert!(factory.compile_path(&["1", "e", "more"]).is_err());
+    assert!(factory.compile_path(&["0", "key", "last"]).is_err());
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get_h::<u32>(&deep)?, None);
+    assert_eq!(buffer.get_h::<&str>(&column)?, None);
+
+    buffer.set(&["0", "key", "0"], 7u32)?;
+    buffer.set(&["1", "e"], "fifth column")?;
+    assert_eq!(buffer.get_h::<u32>(&deep)?, Some(7));
+    assert_eq!(buffer.get_h::<&str>(&column)?, Some("fifth column"));
+    assert!(buffer.get_h::<u8>(&column).is_err());
+
+    // the same schema from another factory works, a different schema doesn't
+    let same = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(same.open_buffer(buffer.read_bytes().to_vec()).get_h::<u32>(&deep)?, Some(7));
+    let other = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u32"}}"#)?;
+    assert!(other.empty_buffer(None).get_h::<u32>(&deep).is_err());
+
+    Ok(())
+}
@@ -1,3 +1,33 @@
+//! Any values can hold a value of any scalar type that's picked when the value is set.
+//! 
+//! Values are set and read with [`NP_Dyn`](enum.NP_Dyn.html), the type of each value is stored in the buffer next to it.
+//! 
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::any::NP_Dyn;
+//! 
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "map",
+//!    "value": {"type": "any"}
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set_dyn(&["name"], NP_Dyn::Str("Billy"))?;
+//! new_buffer.set_dyn(&["age"], NP_Dyn::U8(20))?;
+//! 
+//! assert_eq!(new_buffer.get_dyn(&["name"])?, Some(NP_Dyn::Str("Billy")));
+//! assert_eq!(new_buffer.get_dyn(&["age"])?, Some(NP_Dyn::U8(20)));
+//! assert_eq!(new_buffer.get_dyn(&["color"])?, None);
+//! 
+//! // the type can change every time the value is set
+//! new_buffer.set_dyn(&["age"], NP_Dyn::F64(20.5))?;
+//! assert_eq!(new_buffer.get_dyn(&["age"])?, Some(NP_Dyn::F64(20.5)));
+//!
+//! # Ok::<(), NP_Error>(()) 
+//! ```
+//! 
+
 use crate::{json_flex::{JSMAP}, schema::{NP_Parsed_Schema}};
 use alloc::vec::Vec;
 use crate::error::NP_Error;
@@ -16,8 +46,8 @@ pub struct NP_Any { }
 #[allow(unused_variables)]
 impl<'value> NP_Value<'value> for NP_Any {
 
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("any", NP_TypeKeys::Any) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("any", NP_TypeKeys::Any) }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("any", NP_TypeKeys::Any) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("any", NP_TypeKeys::Any) }
 
     fn schema_to_json(_schema: &Vec<NP_Parsed_Schema>, _address: usize)-> Result<NP_JSON, NP_Error> {
         let mut schema_json = JSMAP::new();
@@ -27,10 +57,10 @@ impl<'value> NP_Value<'value> for NP_Any {
     }
 
     fn set_value<'set>(cursor: NP_Cursor, memory: &'set NP_Memory, value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
-        Err(NP_Error::new("Can't use .set() with (Any), must cast first with NP_Any::cast<T>(pointer)."))
+        Err(NP_Error::new_static("Can't use .set() with (Any), use NP_Dyn instead."))
     }
     fn into_value(cursor: &NP_Cursor, memory: &'value NP_Memory) -> Result<Option<Self>, NP_Error> where Self: Sized {
-        Err(NP_Error::new("Type (Any) doesn't support .into()!"))
+        Err(NP_Error::new_static("Type (Any) doesn't support .into(), use NP_Dyn instead."))
     }
     fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
         NP_JSON::Null
@@ -39,7 +69,7 @@ impl<'value> NP_Value<'value> for NP_Any {
         Ok(0)
     }
     fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
-        Err(NP_Error::new("Cannot compact an ANY field!"))
+        Err(NP_Error::new_static("Cannot compact an ANY field!"))
     }
     fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, _json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
 
@@ -72,6 +102,258 @@ impl<'value> Default for NP_Any {
     }
 }
 
+/// A value of any scalar type, used to set and get `any` values.
+/// 
+/// Check out documentation [here](../any/index.html).
+/// 
+#[derive(Debug, Clone, PartialEq)]
+pub enum NP_Dyn<'any> {
+    /// A boolean value
+    Bool(bool),
+    /// An 8 bit signed integer
+    I8(i8),
+    /// A 16 bit signed integer
+    I16(i16),
+    /// A 32 bit signed integer
+    I32(i32),
+    /// A 64 bit signed integer
+    I64(i64),
+    /// An 8 bit unsigned integer
+    U8(u8),
+    /// A 16 bit unsigned integer
+    U16(u16),
+    /// A 32 bit unsigned integer
+    U32(u32),
+    /// A 64 bit unsigned integer
+    U64(u64),
+    /// A 32 bit float
+    F32(f32),
+    /// A 64 bit float
+    F64(f64),
+    /// A string, up to 65,535 bytes
+    Str(&'any str),
+    /// Bytes, up to 65,535 bytes
+    Bytes(&'any [u8])
+}
+
+impl<'any> super::NP_Scalar for NP_Dyn<'any> {}
+
+impl<'any> NP_Dyn<'any> {
+
+    /// The type of this value, stored in the buffer in front of it
+    pub fn type_key(&self) -> NP_TypeKeys {
+        match self {
+            NP_Dyn::Bool(_)  => NP_TypeKeys::Boolean,
+            NP_Dyn::I8(_)    => NP_TypeKeys::Int8,
+            NP_Dyn::I16(_)   => NP_TypeKeys::Int16,
+            NP_Dyn::I32(_)   => NP_TypeKeys::Int32,
+            NP_Dyn::I64(_)   => NP_TypeKeys::Int64,
+            NP_Dyn::U8(_)    => NP_TypeKeys::Uint8,
+            NP_Dyn::U16(_)   => NP_TypeKeys::Uint16,
+            NP_Dyn::U32(_)   => NP_TypeKeys::Uint32,
+            NP_Dyn::U64(_)   => NP_TypeKeys::Uint64,
+            NP_Dyn::F32(_)   => NP_TypeKeys::Float,
+            NP_Dyn::F64(_)   => NP_TypeKeys::Double,
+            NP_Dyn::Str(_)   => NP_TypeKeys::UTF8String,
+            NP_Dyn::Bytes(_) => NP_TypeKeys::Bytes
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.push(self.type_key() as u8);
+        match self {
+            NP_Dyn::Bool(x)  => bytes.push(if *x { 1 } else { 0 }),
+            NP_Dyn::I8(x)    => bytes.extend_from_slice(&x.to_be_bytes()),
+            NP_Dyn::I16(x)   => bytes.extend_from_slice(&x.to_be_bytes()),
+            NP_Dyn::I32(x)   => bytes.extend_from_slice(&x.to_be_bytes()),
+            NP_Dyn::I64(x)   => bytes.extend_from_slice(&x.to_be_bytes()),
+            NP_Dyn::U8(x)    => bytes.extend_from_slice(&x.to_be_bytes()),
+            NP_Dyn::U16(x)   => bytes.extend_from_slice(&x.to_be_bytes()),
+            NP_Dyn::U32(x)   => bytes.extend_from_slice(&x.to_be_bytes()),
+            NP_Dyn::U64(x)   => bytes.extend_from_slice(&x.to_be_bytes()),
+            NP_Dyn::F32(x)   => bytes.extend_from_slice(&x.to_be_bytes()),
+            NP_Dyn::F64(x)   => bytes.extend_from_slice(&x.to_be_bytes()),
+            NP_Dyn::Str(x)   => {
+                bytes.extend_from_slice(&(x.len() as u16).to_be_bytes());
+                bytes.extend_from_slice(x.as_bytes());
+            },
+            NP_Dyn::Bytes(x) => {
+                bytes.extend_from_slice(&(x.len() as u16).to_be_bytes());
+                bytes.extend_from_slice(x);
+            }
+        }
+        bytes
+    }
+
+    /// Bytes setting this value over the one at `cursor` adds to the buffer, nothing if it's written in place
+    pub(crate) fn set_cost(&self, cursor: Option<NP_Cursor>, memory: &NP_Memory) -> usize {
+        // type key, length and bytes, without copying long values to count them
+        let size = match self {
+            NP_Dyn::Str(x) => 1 + core::mem::size_of::<u16>() + x.len(),
+            NP_Dyn::Bytes(x) => 1 + core::mem::size_of::<u16>() + x.len(),
+            _ => self.to_bytes().len()
+        };
+        let addr = cursor.map(|x| x.get_value(memory).get_addr_value() as usize).unwrap_or(0);
+        if addr != 0 && stored_size(addr, memory).ok() == Some(size) && memory.get_1_byte(addr) == Some(self.type_key() as u8) {
+            0
+        } else {
+            size
+        }
+    }
+}
+
+// size of the value at this address including its type key
+fn stored_size(addr: usize, memory: &NP_Memory) -> Result<usize, NP_Error> {
+
+    if addr == 0 {
+        return Ok(0);
+    }
+
+    let type_key = match memory.get_1_byte(addr) {
+        Some(x) => NP_TypeKeys::from(x),
+        None => return Err(NP_Error::new_static("Any value is outside of buffer!"))
+    };
+
+    let size = match type_key {
+        NP_TypeKeys::Boolean | NP_TypeKeys::Int8 | NP_TypeKeys::Uint8 => 1,
+        NP_TypeKeys::Int16 | NP_TypeKeys::Uint16 => 2,
+        NP_TypeKeys::Int32 | NP_TypeKeys::Uint32 | NP_TypeKeys::Float => 4,
+        NP_TypeKeys::Int64 | NP_TypeKeys::Uint64 | NP_TypeKeys::Double => 8,
+        NP_TypeKeys::UTF8String | NP_TypeKeys::Bytes => {
+            match memory.get_2_bytes(addr + 1) {
+                Some(x) => 2 + u16::from_be_bytes(*x) as usize,
+                None => return Err(NP_Error::new_static("Any value is outside of buffer!"))
+            }
+        },
+        _ => return Err(NP_Error::new_static("Unknown type in any value!"))
+    };
+
+    if addr + 1 + size > memory.read_bytes().len() {
+        return Err(NP_Error::new_static("Any value is outside of buffer!"));
+    }
+
+    Ok(1 + size)
+}
+
+impl<'value> NP_Value<'value> for NP_Dyn<'value> {
+
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("any", NP_TypeKeys::Any) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("any", NP_TypeKeys::Any) }
+
+    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
+        NP_Any::schema_to_json(schema, address)
+    }
+
+    fn schema_default(_schema: &NP_Parsed_Schema) -> Option<Self> {
+        None
+    }
+
+    fn set_value<'set>(cursor: NP_Cursor, memory: &'set NP_Memory, value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
+
+        let len = match &value {
+            NP_Dyn::Str(x) => x.len(),
+            NP_Dyn::Bytes(x) => x.len(),
+            _ => 0
+        };
+
+        if len > core::u16::MAX as usize {
+            return Err(NP_Error::new_static("Any value too large!"));
+        }
+
+        let bytes = value.to_bytes();
+
+        let c_value = cursor.get_value(memory);
+        let addr = c_value.get_addr_value() as usize;
+
+        // values of the same type and size are updated in place
+        if addr != 0 && stored_size(addr, memory).ok() == Some(bytes.len()) && memory.get_1_byte(addr) == Some(bytes[0]) {
+            let write_bytes = memory.write_bytes();
+            write_bytes[addr..(addr + bytes.len())].copy_from_slice(&bytes);
+            return Ok(cursor);
+        }
+
+        let new_addr = memory.malloc(bytes)?;
+        cursor.get_value_mut(memory).set_addr_value(new_addr as u16);
+
+        Ok(cursor)
+    }
+
+    fn into_value(cursor: &NP_Cursor, memory: &'value NP_Memory) -> Result<Option<Self>, NP_Error> where Self: Sized {
+
+        let c_value = cursor.get_value(memory);
+
+        let addr = c_value.get_addr_value() as usize;
+
+        // empty value
+        if addr == 0 {
+            return Ok(None);
+        }
+
+        let size = stored_size(addr, memory)?;
+        let bytes = &memory.read_bytes()[(addr + 1)..(addr + size)];
+
+        let value = match NP_TypeKeys::from(memory.read_bytes()[addr]) {
+            NP_TypeKeys::Boolean    => NP_Dyn::Bool(bytes[0] == 1),
+            NP_TypeKeys::Int8       => NP_Dyn::I8(i8::from_be_bytes([bytes[0]])),
+            NP_TypeKeys::Int16      => NP_Dyn::I16(i16::from_be_bytes([bytes[0], bytes[1]])),
+            NP_TypeKeys::Int32      => NP_Dyn::I32(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            NP_TypeKeys::Int64      => NP_Dyn::I64(i64::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])),
+            NP_TypeKeys::Uint8      => NP_Dyn::U8(bytes[0]),
+            NP_TypeKeys::Uint16     => NP_Dyn::U16(u16::from_be_bytes([bytes[0], bytes[1]])),
+            NP_TypeKeys::Uint32     => NP_Dyn::U32(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            NP_TypeKeys::Uint64     => NP_Dyn::U64(u64::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])),
+            NP_TypeKeys::Float      => NP_Dyn::F32(f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            NP_TypeKeys::Double     => NP_Dyn::F64(f64::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])),
+            NP_TypeKeys::UTF8String => {
+                match core::str::from_utf8(&bytes[2..]) {
+                    Ok(x) => NP_Dyn::Str(x),
+                    Err(_) => return Err(NP_Error::new_static("String is not valid UTF8!"))
+                }
+            },
+            NP_TypeKeys::Bytes      => NP_Dyn::Bytes(&bytes[2..]),
+            _ => return Err(NP_Error::new_static("Unknown type in any value!"))
+        };
+
+        Ok(Some(value))
+    }
+
+    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+        match Self::into_value(cursor, memory) {
+            Ok(Some(value)) => {
+                match value {
+                    NP_Dyn::Bool(x)  => if x { NP_JSON::True } else { NP_JSON::False },
+                    NP_Dyn::I8(x)    => NP_JSON::Integer(x as i64),
+                    NP_Dyn::I16(x)   => NP_JSON::Integer(x as i64),
+                    NP_Dyn::I32(x)   => NP_JSON::Integer(x as i64),
+                    NP_Dyn::I64(x)   => NP_JSON::Integer(x),
+                    NP_Dyn::U8(x)    => NP_JSON::Integer(x as i64),
+                    NP_Dyn::U16(x)   => NP_JSON::Integer(x as i64),
+                    NP_Dyn::U32(x)   => NP_JSON::Integer(x as i64),
+                    NP_Dyn::U64(x)   => NP_JSON::from_u64(x),
+                    NP_Dyn::F32(x)   => NP_JSON::Float(x as f64),
+                    NP_Dyn::F64(x)   => NP_JSON::Float(x),
+                    NP_Dyn::Str(x)   => NP_JSON::String(x.to_owned()),
+                    NP_Dyn::Bytes(x) => NP_JSON::Array(x.iter().map(|b| NP_JSON::Integer(*b as i64)).collect())
+                }
+            },
+            _ => NP_JSON::Null
+        }
+    }
+
+    fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
+        stored_size(cursor.get_value(memory).get_addr_value() as usize, memory)
+    }
+
+    fn from_json_to_schema(schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
+        NP_Any::from_json_to_schema(schema, json_schema)
+    }
+
+    fn from_bytes_to_schema(schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+        NP_Any::from_bytes_to_schema(schema, address, bytes)
+    }
+}
+
 
 #[test]
 fn schema_parsing_works() -> Result<(), NP_Error> {
@@ -81,3 +363,61 @@ fn schema_parsing_works() -> Result<(), NP_Error> {
     
     Ok(())
 }
+
+#[test]
+fn set_get_dyn_works() -> Result<(), NP_Error> {
+    use alloc::string::ToString;
+
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "any"}}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+
+    let bytes = [1u8, 2, 3];
+    let values = [
+        NP_Dyn::Bool(true), NP_Dyn::I8(-8), NP_Dyn::I16(-16), NP_Dyn::I32(-32), NP_Dyn::I64(-64),
+        NP_Dyn::U8(8), NP_Dyn::U16(16), NP_Dyn::U32(32), NP_Dyn::U64(64), NP_Dyn::F32(2.5), NP_Dyn::F64(-2.5),
+        NP_Dyn::Str("hello"), NP_Dyn::Bytes(&bytes)
+    ];
+
+    for (idx, value) in values.iter().enumerate() {
+        buffer.set_dyn(&[&idx.to_string()], value.clone())?;
+    }
+    for (idx, value) in values.iter().enumerate() {
+        assert_eq!(buffer.get_dyn(&[&idx.to_string()])?.as_ref(), Some(value));
+    }
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[true,-8,-16,-32,-64,8,16,32,64,2.5,-2.5,\"hello\",[1,2,3]]");
+
+    // same type and size updates in place
+    let size = buffer.calc_bytes()?.current_buffer;
+    buffer.set_dyn(&["4"], NP_Dyn::I64(64))?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer, size);
+    buffer.set_dyn(&["4"], NP_Dyn::Str("changed"))?;
+    assert_eq!(buffer.get_dyn(&["4"])?, Some(NP_Dyn::Str("changed")));
+
+    buffer.compact(None)?;
+    assert_eq!(buffer.calc_bytes()?.wasted_bytes, 0);
+    assert_eq!(buffer.get_dyn(&["12"])?, Some(NP_Dyn::Bytes(&bytes)));
+
+    // JSON picks the closest type
+    buffer.set_with_json(&[], r#"[1, 2.5, "three", [4], false]"#)?;
+    assert_eq!(buffer.get_dyn(&["0"])?, Some(NP_Dyn::I64(1)));
+    assert_eq!(buffer.get_dyn(&["1"])?, Some(NP_Dyn::F64(2.5)));
+    assert_eq!(buffer.get_dyn(&["2"])?, Some(NP_Dyn::Str("three")));
+    assert_eq!(buffer.get_dyn(&["3"])?, Some(NP_Dyn::Bytes(&[4])));
+    assert_eq!(buffer.get_dyn(&["4"])?, Some(NP_Dyn::Bool(false)));
+
+    // only any values can be used with NP_Dyn
+    let factory = crate::NP_Factory::new(r#"{"type": "u8"}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    assert!(buffer.set_dyn(&[], NP_Dyn::U8(1)).is_err());
+
+    // unknown types aren't read
+    let factory = crate::NP_Factory::new(r#"{"type": "any"}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set_dyn(&[], NP_Dyn::U8(1))?;
+    let mut bytes = buffer.close();
+    bytes[3] = NP_TypeKeys::Table as u8;
+    assert!(factory.open_buffer(bytes.clone()).get_dyn(&[]).is_err());
+    assert!(factory.open_checked_buffer(bytes).is_err());
+
+    Ok(())
+}
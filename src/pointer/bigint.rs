@@ -0,0 +1,485 @@
+//! Represents an arbitrary precision integer
+//!
+//! Big integers are stored as a sign byte followed by the magnitude bytes (big endian) with a size prefix, so they can hold values far outside the range of `i64` or `u64`.
+//!
+//! Values can be converted to and from decimal strings, native integers and, with the `num-bigint` feature enabled, `num_bigint::BigInt`.  In JSON they're always encoded as strings so no precision is lost.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::bigint::NP_BigInt;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "bigint"
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&[], NP_BigInt::from_string("-340282366920938463463374607431768211456").unwrap())?;
+//!
+//! let value = new_buffer.get::<NP_BigInt>(&[])?.unwrap();
+//! assert!(value.is_negative());
+//! assert_eq!(value.to_string(), "-340282366920938463463374607431768211456");
+//! assert_eq!(value.to_i128(), None);
+//!
+//! new_buffer.set(&[], NP_BigInt::from(-42i64))?;
+//! assert_eq!(new_buffer.get::<NP_BigInt>(&[])?.unwrap().to_i128(), Some(-42));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::{json_flex::JSMAP, schema::{NP_Parsed_Schema}};
+use crate::error::NP_Error;
+use crate::{schema::{NP_TypeKeys}, pointer::NP_Value, json_flex::NP_JSON};
+use core::fmt::{Display, Formatter};
+
+use alloc::vec::Vec;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::{borrow::ToOwned};
+use super::{NP_Cursor};
+use crate::NP_Memory;
+use alloc::string::ToString;
+
+/// Holds an arbitrary precision integer.
+///
+/// Check out documentation [here](../bigint/index.html).
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct NP_BigInt {
+    negative: bool,
+    // big endian, never has leading zeros
+    magnitude: Vec<u8>
+}
+
+impl super::NP_Scalar for NP_BigInt {}
+
+impl NP_BigInt {
+
+    /// Create a big integer from its sign and big endian magnitude bytes.
+    ///
+    /// Leading zero bytes are removed, zero is never negative.
+    ///
+    pub fn new(negative: bool, magnitude: &[u8]) -> Self {
+        let start = magnitude.iter().position(|x| *x != 0).unwrap_or(magnitude.len());
+        let magnitude = magnitude[start..].to_vec();
+        NP_BigInt { negative: negative && magnitude.len() > 0, magnitude }
+    }
+
+    /// Parse a big integer from a decimal string, with an optional leading `-` or `+`.
+    ///
+    pub fn from_string(value: &str) -> Option<Self> {
+
+        let (negative, digits) = match value.as_bytes().first() {
+            Some(b'-') => (true, &value[1..]),
+            Some(b'+') => (false, &value[1..]),
+            _ => (false, value)
+        };
+
+        if digits.len() == 0 {
+            return None;
+        }
+
+        let mut magnitude: Vec<u8> = Vec::new();
+
+        for digit in digits.chars() {
+            // magnitude = (magnitude * 10) + digit
+            let mut carry = digit.to_digit(10)?;
+            for byte in magnitude.iter_mut().rev() {
+                let next = (*byte as u32 * 10) + carry;
+                *byte = next as u8;
+                carry = next >> 8;
+            }
+            if carry > 0 {
+                magnitude.insert(0, carry as u8);
+            }
+        }
+
+        Some(NP_BigInt::new(negative, &magnitude))
+    }
+
+    /// Is this value below zero?
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Is this value zero?
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.len() == 0
+    }
+
+    /// The big endian bytes of the absolute value, without leading zeros
+    pub fn magnitude(&self) -> &[u8] {
+        &self.magnitude
+    }
+
+    /// Convert into an `i128`, returns `None` if the value doesn't fit.
+    pub fn to_i128(&self) -> Option<i128> {
+        let value = self.to_u128_magnitude()?;
+        if self.negative {
+            if value <= i128::MAX as u128 + 1 { Some((value as i128).wrapping_neg()) } else { None }
+        } else {
+            if value <= i128::MAX as u128 { Some(value as i128) } else { None }
+        }
+    }
+
+    /// Convert into a `u128`, returns `None` if the value is negative or doesn't fit.
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.negative {
+            return None;
+        }
+        self.to_u128_magnitude()
+    }
+
+    fn to_u128_magnitude(&self) -> Option<u128> {
+        if self.magnitude.len() > 16 {
+            return None;
+        }
+        Some(self.magnitude.iter().fold(0u128, |value, byte| (value << 8) | *byte as u128))
+    }
+
+    // sign byte then magnitude, the format used in buffers and compiled schemas
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.magnitude.len() + 1);
+        bytes.push(if self.negative { 1 } else { 0 });
+        bytes.extend_from_slice(&self.magnitude);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (sign, magnitude) = bytes.split_first()?;
+        Some(NP_BigInt::new(*sign == 1, magnitude))
+    }
+}
+
+impl Display for NP_BigInt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // divide by 10^9 until nothing is left, each remainder is 9 decimal digits
+        let mut chunks: Vec<u32> = Vec::new();
+        let mut magnitude = self.magnitude.clone();
+
+        while magnitude.len() > 0 {
+            let mut remainder = 0u64;
+            for byte in magnitude.iter_mut() {
+                let value = (remainder << 8) | *byte as u64;
+                *byte = (value / 1_000_000_000) as u8;
+                remainder = value % 1_000_000_000;
+            }
+            chunks.push(remainder as u32);
+            let start = magnitude.iter().position(|x| *x != 0).unwrap_or(magnitude.len());
+            magnitude.drain(..start);
+        }
+
+        let mut result = String::with_capacity(chunks.len() * 9 + 1);
+        if self.negative {
+            result.push('-');
+        }
+        result.push_str(&chunks.pop().unwrap_or(0).to_string());
+        while let Some(chunk) = chunks.pop() {
+            let digits = chunk.to_string();
+            for _ in digits.len()..9 {
+                result.push('0');
+            }
+            result.push_str(&digits);
+        }
+
+        f.write_str(&result)
+    }
+}
+
+macro_rules! bigint_from {
+    ($t:ty) => {
+        impl From<$t> for NP_BigInt {
+            fn from(value: $t) -> Self {
+                NP_BigInt::new(false, &value.to_be_bytes())
+            }
+        }
+    };
+    ($t:ty, $unsigned:ty) => {
+        impl From<$t> for NP_BigInt {
+            fn from(value: $t) -> Self {
+                if value < 0 {
+                    NP_BigInt::new(true, &(value as $unsigned).wrapping_neg().to_be_bytes())
+                } else {
+                    NP_BigInt::new(false, &(value as $unsigned).to_be_bytes())
+                }
+            }
+        }
+    };
+}
+
+bigint_from!(u64);
+bigint_from!(u128);
+bigint_from!(i64, u64);
+bigint_from!(i128, u128);
+
+#[cfg(feature = "num-bigint")]
+impl From<num_bigint::BigInt> for NP_BigInt {
+    fn from(value: num_bigint::BigInt) -> Self {
+        let (sign, magnitude) = value.to_bytes_be();
+        NP_BigInt::new(sign == num_bigint::Sign::Minus, &magnitude)
+    }
+}
+
+#[cfg(feature = "num-bigint")]
+impl From<NP_BigInt> for num_bigint::BigInt {
+    fn from(value: NP_BigInt) -> Self {
+        let sign = if value.negative { num_bigint::Sign::Minus } else { num_bigint::Sign::Plus };
+        num_bigint::BigInt::from_bytes_be(sign, &value.magnitude)
+    }
+}
+
+impl<'value> NP_Value<'value> for NP_BigInt {
+
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("bigint", NP_TypeKeys::BigInt) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("bigint", NP_TypeKeys::BigInt) }
+
+    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
+        let mut schema_json = JSMAP::new();
+        schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
+
+        match &schema[address] {
+            NP_Parsed_Schema::BigInt { default, .. } => {
+                if let Some(d) = default {
+                    schema_json.insert("default".to_owned(), NP_JSON::String(d.to_string()));
+                }
+            },
+            _ => { }
+        }
+
+        Ok(NP_JSON::Dictionary(schema_json))
+    }
+
+    fn schema_default(schema: &'value NP_Parsed_Schema) -> Option<Self> {
+        match schema {
+            NP_Parsed_Schema::BigInt { default, .. } => default.clone(),
+            _ => None
+        }
+    }
+
+    fn set_value<'set>(cursor: NP_Cursor, memory: &'set NP_Memory, value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
+
+        let bytes = value.to_bytes();
+
+        if bytes.len() > core::u16::MAX as usize {
+            return Err(NP_Error::new_static("Big integer too large!"));
+        }
+
+        let addr_value = cursor.get_value(memory).get_addr_value() as usize;
+
+        let prev_size: usize = if addr_value != 0 {
+            u16::from_be_bytes(*memory.get_2_bytes(addr_value).unwrap_or(&[0; 2])) as usize
+        } else {
+            0
+        };
+
+        let size_bytes = (bytes.len() as u16).to_be_bytes();
+
+        if prev_size >= bytes.len() {
+            // previous value is larger than this one, use existing memory
+            let write_bytes = memory.write_bytes();
+            write_bytes[addr_value..(addr_value + 2)].copy_from_slice(&size_bytes);
+            write_bytes[(addr_value + 2)..(addr_value + 2 + bytes.len())].copy_from_slice(&bytes);
+            return Ok(cursor);
+        }
+
+        // not enough space or space has not been allocted yet
+        let new_addr = memory.malloc_borrow(&size_bytes)?;
+        memory.malloc(bytes)?;
+        cursor.get_value_mut(memory).set_addr_value(new_addr as u16);
+
+        Ok(cursor)
+    }
+
+    fn into_value(cursor: &NP_Cursor, memory: &'value NP_Memory) -> Result<Option<Self>, NP_Error> where Self: Sized {
+
+        let value_addr = cursor.get_value(memory).get_addr_value() as usize;
+
+        // empty value
+        if value_addr == 0 {
+            return Ok(None);
+        }
+
+        let size = u16::from_be_bytes(*memory.get_2_bytes(value_addr).unwrap_or(&[0; 2])) as usize;
+
+        match memory.read_bytes().get((value_addr + 2)..(value_addr + 2 + size)) {
+            Some(bytes) => Ok(NP_BigInt::from_bytes(bytes)),
+            None => Ok(None) // attack
+        }
+    }
+
+    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+
+        match Self::into_value(cursor, memory) {
+            Ok(Some(value)) => NP_JSON::String(value.to_string()),
+            Ok(None) => {
+                match &memory.schema[cursor.schema_addr] {
+                    NP_Parsed_Schema::BigInt { default: Some(d), .. } => NP_JSON::String(d.to_string()),
+                    _ => NP_JSON::Null
+                }
+            },
+            Err(_e) => NP_JSON::Null
+        }
+    }
+
+    fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
+
+        let value_addr = cursor.get_value(memory).get_addr_value() as usize;
+
+        // empty value
+        if value_addr == 0 {
+            return Ok(0);
+        }
+
+        let size = u16::from_be_bytes(*memory.get_2_bytes(value_addr).unwrap_or(&[0; 2])) as usize;
+
+        Ok(size + 2)
+    }
+
+    fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
+
+        let mut schema_data: Vec<u8> = Vec::new();
+        schema_data.push(NP_TypeKeys::BigInt as u8);
+
+        let default = match &json_schema["default"] {
+            NP_JSON::String(x) => match NP_BigInt::from_string(x.as_str()) {
+                Some(value) => Some(value),
+                None => return Err(NP_Error::new_schema("Default for bigint must be a string of decimal digits!"))
+            },
+            NP_JSON::Integer(x) => Some(NP_BigInt::from(*x)),
+            NP_JSON::Unsigned(x) => Some(NP_BigInt::from(*x)),
+            _ => None
+        };
+
+        match &default {
+            Some(value) => {
+                let default_bytes = value.to_bytes();
+                if default_bytes.len() >= core::u16::MAX as usize {
+                    return Err(NP_Error::new_schema("Default for bigint is too large!"));
+                }
+                schema_data.extend((default_bytes.len() as u16 + 1).to_be_bytes().to_vec());
+                schema_data.extend(default_bytes);
+            },
+            None => {
+                schema_data.extend(0u16.to_be_bytes().to_vec());
+            }
+        }
+
+        schema.push(NP_Parsed_Schema::BigInt {
+            i: NP_TypeKeys::BigInt,
+            sortable: false,
+            default
+        });
+
+        Ok((false, schema_data, schema))
+    }
+
+    fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+
+        // default value size
+        let default_size = u16::from_be_bytes([
+            bytes[address + 1],
+            bytes[address + 2]
+        ]) as usize;
+
+        let default = if default_size == 0 {
+            None
+        } else {
+            NP_BigInt::from_bytes(&bytes[(address + 3)..(address + 3 + (default_size - 1))])
+        };
+
+        schema.push(NP_Parsed_Schema::BigInt {
+            i: NP_TypeKeys::BigInt,
+            sortable: false,
+            default
+        });
+
+        (false, schema)
+    }
+}
+
+#[test]
+fn schema_parsing_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"bigint\",\"default\":\"-123456789012345678901234567890\"}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory2 = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory2.schema.to_json()?.stringify());
+
+    let schema = "{\"type\":\"bigint\"}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory2 = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory2.schema.to_json()?.stringify());
+
+    assert!(crate::NP_Factory::new("{\"type\":\"bigint\",\"default\":\"12a\"}").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn conversions_work() {
+    for value in ["0", "1", "-1", "255", "256", "-65536", "1000000000", "999999999999999999999999999999", "-340282366920938463463374607431768211456"].iter() {
+        assert_eq!(NP_BigInt::from_string(value).unwrap().to_string(), *value);
+    }
+    assert_eq!(NP_BigInt::from_string("-000").unwrap(), NP_BigInt::default());
+    assert_eq!(NP_BigInt::from_string("+0042").unwrap().to_string(), "42");
+    assert_eq!(NP_BigInt::from_string(""), None);
+    assert_eq!(NP_BigInt::from_string("-"), None);
+    assert_eq!(NP_BigInt::from_string("1.5"), None);
+
+    assert_eq!(NP_BigInt::from(i128::MIN).to_i128(), Some(i128::MIN));
+    assert_eq!(NP_BigInt::from(i128::MIN).to_string(), i128::MIN.to_string());
+    assert_eq!(NP_BigInt::from(u128::MAX).to_u128(), Some(u128::MAX));
+    assert_eq!(NP_BigInt::from(u128::MAX).to_i128(), None);
+    assert_eq!(NP_BigInt::from(-5i64).to_u128(), None);
+    assert_eq!(NP_BigInt::from(258u64).magnitude(), &[1, 2]);
+}
+
+#[cfg(feature = "num-bigint")]
+#[test]
+fn num_bigint_conversions_work() {
+    let value = NP_BigInt::from_string("-123456789012345678901234567890").unwrap();
+    let big: num_bigint::BigInt = value.clone().into();
+    assert_eq!(big.to_string(), "-123456789012345678901234567890");
+    assert_eq!(NP_BigInt::from(big), value);
+}
+
+#[test]
+fn default_value_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"bigint\",\"default\":\"18446744073709551616\"}";
+    let factory = crate::NP_Factory::new(schema)?;
+    let buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get::<NP_BigInt>(&[])?.unwrap(), NP_BigInt::from(u64::MAX as u128 + 1));
+
+    Ok(())
+}
+
+#[test]
+fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new("{\"type\":\"bigint\"}")?;
+    let mut buffer = factory.empty_buffer(None);
+    let big = NP_BigInt::from_string("123456789012345678901234567890123456789").unwrap();
+    buffer.set(&[], big.clone())?;
+    assert_eq!(buffer.get::<NP_BigInt>(&[])?, Some(big));
+
+    // smaller values are updated in place
+    let size = buffer.calc_bytes()?.current_buffer;
+    buffer.set(&[], NP_BigInt::from(-7i64))?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer, size);
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "\"-7\"");
+
+    buffer.compact(None)?;
+    assert_eq!(buffer.get::<NP_BigInt>(&[])?, Some(NP_BigInt::from(-7i64)));
+    assert_eq!(buffer.calc_bytes()?.current_buffer, 7);
+
+    buffer.del(&[])?;
+    assert_eq!(buffer.get::<NP_BigInt>(&[])?, None);
+
+    Ok(())
+}
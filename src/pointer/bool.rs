@@ -27,14 +27,15 @@ use alloc::{borrow::ToOwned};
 use crate::NP_Memory;
 use alloc::string::ToString;
 
-use super::NP_Cursor;
+use super::{NP_Cursor, any::NP_Dyn};
 
 impl super::NP_Scalar for bool {}
 
 impl<'value> NP_Value<'value> for bool {
 
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("bool", NP_TypeKeys::Boolean) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("bool", NP_TypeKeys::Boolean) }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("bool", NP_TypeKeys::Boolean) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("bool", NP_TypeKeys::Boolean) }
+    fn as_dyn(&self) -> Option<NP_Dyn<'_>> { Some(NP_Dyn::Bool(*self)) }
 
     fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
         let mut schema_json = JSMAP::new();
@@ -71,53 +72,18 @@ impl<'value> NP_Value<'value> for bool {
     fn set_value<'set>(cursor: NP_Cursor, memory: &'set NP_Memory, value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
 
         let c_value = cursor.get_value(memory);
-        let mut value_address = c_value.get_addr_value();  
 
-        if value_address != 0 { // existing value, replace
+        let value_address = memory.set_small(c_value.get_addr_value(), if value { 1 } else { 0 })?;
+        cursor.get_value_mut(memory).set_addr_value(value_address);
 
-            // overwrite existing values in buffer
-            memory.write_bytes()[value_address as usize] = if value == true {
-                1
-            } else {
-                0
-            };
-
-            return Ok(cursor);
-
-        } else { // new value
-
-            let bytes = if value == true {
-                [1] as [u8; 1]
-            } else {
-                [0] as [u8; 1]
-            };
-
-            value_address = memory.malloc_borrow(&bytes)? as u16;
-            c_value.set_addr_value(value_address as u16);
-
-            return Ok(cursor);
-
-        }
-        
+        Ok(cursor)
     }
 
     fn into_value(cursor: &NP_Cursor, memory: &'value NP_Memory) -> Result<Option<Self>, NP_Error> where Self: Sized {
 
         let c_value = cursor.get_value(memory);
 
-        let value_addr = c_value.get_addr_value() as usize;
-
-        // empty value
-        if value_addr == 0 {
-            return Ok(None);
-        }
-
-        Ok(match memory.get_1_byte(value_addr) {
-            Some(x) => {
-                Some(if x == 1 { true } else { false })
-            },
-            None => None
-        })
+        Ok(memory.get_small(c_value.get_addr_value()).map(|x| x == 1))
     }
 
     fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
@@ -160,11 +126,7 @@ impl<'value> NP_Value<'value> for bool {
 
     fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
         let c_value = cursor.get_value(memory);
-        if c_value.get_addr_value() == 0 {
-            Ok(0) 
-        } else {
-            Ok(core::mem::size_of::<u8>())
-        }
+        Ok(memory.small_size(c_value.get_addr_value()))
     }
 
     fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
@@ -200,11 +162,10 @@ impl<'value> NP_Value<'value> for bool {
         schema.push(NP_Parsed_Schema::Boolean {
             i: NP_TypeKeys::Boolean,
             sortable: true,
-            default: match bytes[address] {
-                0 => None,
+            default: match bytes[address + 1] {
                 1 => Some(true),
                 2 => Some(false),
-                _ => unreachable!()
+                _ => None
             }
         });
         (true, schema)
@@ -231,6 +192,10 @@ fn default_value_works() -> Result<(), NP_Error> {
     let buffer = factory.empty_buffer(None);
     assert_eq!(buffer.get::<bool>(&[])?.unwrap(), false);
 
+    let factory = crate::NP_Factory::new_compiled(factory.compile_schema());
+    let buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get::<bool>(&[])?.unwrap(), false);
+
     Ok(())
 }
 
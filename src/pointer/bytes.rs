@@ -26,7 +26,7 @@ use core::hint::unreachable_unchecked;
 use alloc::vec::Vec;
 use alloc::boxed::Box;
 use alloc::{borrow::ToOwned};
-use super::{NP_Cursor};
+use super::{NP_Cursor, any::NP_Dyn};
 use crate::NP_Memory;
 use alloc::string::ToString;
 
@@ -38,8 +38,9 @@ impl super::NP_Scalar for &[u8] {}
 impl<'value> NP_Value<'value> for &'value [u8] {
 
 
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("bytes", NP_TypeKeys::Bytes) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("bytes", NP_TypeKeys::Bytes) }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("bytes", NP_TypeKeys::Bytes) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("bytes", NP_TypeKeys::Bytes) }
+    fn as_dyn(&self) -> Option<NP_Dyn<'_>> { Some(NP_Dyn::Bytes(self)) }
 
     fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
         let mut schema_json = JSMAP::new();
@@ -89,8 +90,6 @@ impl<'value> NP_Value<'value> for &'value [u8] {
     
         let str_size = bytes.len() as usize;
     
-        let write_bytes = memory.write_bytes();
-    
         let size = match memory.schema[cursor.schema_addr] {
             NP_Parsed_Schema::Bytes { size, .. } => size,
             _ => 0
@@ -108,10 +107,12 @@ impl<'value> NP_Value<'value> for &'value [u8] {
                 }
     
                 let new_addr = memory.malloc(empty_bytes)? as usize;
-                c_value.set_addr_value(new_addr as u16);
+                cursor.get_value_mut(memory).set_addr_value(new_addr as u16);
             }
 
-            let addr = c_value.get_addr_value() as usize;
+            let addr = cursor.get_value(memory).get_addr_value() as usize;
+
+            let write_bytes = memory.write_bytes();
     
             for x in 0..(size as usize) {
                 if x < bytes.len() {
@@ -126,6 +127,19 @@ impl<'value> NP_Value<'value> for &'value [u8] {
             return Ok(cursor);
         }
     
+        // deduplicated values are shared, always point at a copy
+        if memory.is_deduping() {
+            if str_size > core::u16::MAX as usize {
+                return Err(NP_Error::new_static("String too large!"));
+            }
+            let mut shared: Vec<u8> = Vec::with_capacity(str_size + 2);
+            shared.extend_from_slice(&(str_size as u16).to_be_bytes());
+            shared.extend_from_slice(bytes);
+            let new_addr = memory.malloc_dedupe(&shared)?;
+            cursor.get_value_mut(memory).set_addr_value(new_addr as u16);
+            return Ok(cursor);
+        }
+
         // flexible size
         let addr_value = c_value.get_addr_value() as usize;
     
@@ -136,14 +150,15 @@ impl<'value> NP_Value<'value> for &'value [u8] {
             0 as usize
         };
     
-        if prev_size >= str_size as usize {
+        if prev_size >= str_size as usize && memory.has_shared() == false {
             // previous string is larger than this one, use existing memory
     
             // update string length in buffer
             if str_size > core::u16::MAX as usize {
-                return Err(NP_Error::new("String too large!"));
+                return Err(NP_Error::new_static("String too large!"));
             }
             let size_bytes = (str_size as u16).to_be_bytes();
+            let write_bytes = memory.write_bytes();
             // set string size
             for x in 0..size_bytes.len() {
                 write_bytes[(addr_value + x)] = size_bytes[x];
@@ -163,13 +178,13 @@ impl<'value> NP_Value<'value> for &'value [u8] {
             // first bytes are string length
             let new_addr = {
                 if str_size > core::u16::MAX as usize {
-                    return Err(NP_Error::new("String too large!"));
+                    return Err(NP_Error::new_static("String too large!"));
                 }
                 let size_bytes = (str_size as u16).to_be_bytes();
                 memory.malloc_borrow(&size_bytes)?
             };
     
-            c_value.set_addr_value(new_addr as u16);
+            cursor.get_value_mut(memory).set_addr_value(new_addr as u16);
     
             memory.malloc_borrow(bytes)?;
     
@@ -199,7 +214,10 @@ impl<'value> NP_Value<'value> for &'value [u8] {
                     // fixed size
 
                     // get bytes
-                    let bytes = &memory.read_bytes()[(value_addr)..(value_addr + (size as usize))];
+                    let bytes = match memory.read_bytes().get((value_addr)..(value_addr + (size as usize))) {
+                        Some(x) => x,
+                        None => return Ok(None) // attack
+                    };
 
                     return Ok(Some(bytes));
                 } else {
@@ -209,12 +227,15 @@ impl<'value> NP_Value<'value> for &'value [u8] {
                     let bytes_size: usize = u16::from_be_bytes(*memory.get_2_bytes(value_addr).unwrap_or(&[0; 2])) as usize;
 
                     // get bytes
-                    let bytes = &memory.read_bytes()[(value_addr + 2)..(value_addr + 2 + bytes_size)];
+                    let bytes = match memory.read_bytes().get((value_addr + 2)..(value_addr + 2 + bytes_size)) {
+                        Some(x) => x,
+                        None => return Ok(None) // attack
+                    };
 
                     return Ok(Some(bytes));
                 }
             }
-            _ => Err(NP_Error::new("unreachable")),
+            _ => Err(NP_Error::new_static("unreachable")),
         }
     }
 
@@ -272,13 +293,18 @@ impl<'value> NP_Value<'value> for &'value [u8] {
                     return Ok(size as usize);
                 }
 
+                // shared values are only counted once when measuring the buffer
+                if memory.has_shared() && memory.first_count(value_addr) == false {
+                    return Ok(0);
+                }
+
                 // dynamic size
                 let bytes_size: usize = u16::from_be_bytes(*memory.get_2_bytes(value_addr).unwrap_or(&[0; 2])) as usize;
 
                 // return total size of this string plus length
                 return Ok(bytes_size + 2);
             }
-            _ => Err(NP_Error::new("unreachable")),
+            _ => Err(NP_Error::new_static("unreachable")),
         }
     }
 
@@ -293,10 +319,10 @@ impl<'value> NP_Value<'value> for &'value [u8] {
             NP_JSON::Integer(x) => {
                 has_fixed_size = true;
                 if x < 1 {
-                    return Err(NP_Error::new("Fixed size for bytes must be larger than 1!"));
+                    return Err(NP_Error::new_schema("Fixed size for bytes must be larger than 1!"));
                 }
                 if x > u16::MAX.into() {
-                    return Err(NP_Error::new("Fixed size for bytes cannot be larger than 2^16!"));
+                    return Err(NP_Error::new_schema("Fixed size for bytes cannot be larger than 2^16!"));
                 }
                 schema_data.extend((x as u16).to_be_bytes().to_vec());
                 x as u16
@@ -304,10 +330,10 @@ impl<'value> NP_Value<'value> for &'value [u8] {
             NP_JSON::Float(x) => {
                 has_fixed_size = true;
                 if x < 1.0 {
-                    return Err(NP_Error::new("Fixed size for bytes must be larger than 1!"));
+                    return Err(NP_Error::new_schema("Fixed size for bytes must be larger than 1!"));
                 }
                 if x > u16::MAX.into() {
-                    return Err(NP_Error::new("Fixed size for bytes cannot be larger than 2^16!"));
+                    return Err(NP_Error::new_schema("Fixed size for bytes cannot be larger than 2^16!"));
                 }
 
                 schema_data.extend((x as u16).to_be_bytes().to_vec());
@@ -439,4 +465,46 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn dedupe_compaction_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["tags",  {"type": "list", "of": {"type": "string"}}],
+        ["blobs", {"type": "list", "of": {"type": "bytes"}}],
+        ["fixed", {"type": "bytes", "size": 3}]
+    ]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["tags", "0"], "red")?;
+    buffer.set(&["tags", "1"], "red")?;
+    buffer.set(&["tags", "2"], "blue")?;
+    buffer.set(&["blobs", "0"], &[1u8, 2, 3] as &[u8])?;
+    buffer.set(&["blobs", "1"], &[1u8, 2, 3] as &[u8])?;
+    buffer.set(&["fixed"], &[1u8, 2, 3] as &[u8])?;
+
+    let compacted = buffer.calc_bytes()?.after_compaction;
+    assert_eq!(buffer.compact_dedupe(None)?, 10);
+    assert_eq!(buffer.calc_bytes()?.current_buffer, compacted - 10);
+    assert_eq!(buffer.calc_bytes()?.wasted_bytes, 0);
+
+    // shared values are copied when they change
+    buffer.set(&["blobs", "0"], &[9u8] as &[u8])?;
+    buffer.str_truncate(&["tags", "0"], 1)?;
+    assert_eq!(buffer.get::<&[u8]>(&["blobs", "1"])?, Some(&[1u8, 2, 3] as &[u8]));
+    assert_eq!(buffer.get::<&str>(&["tags", "0"])?, Some("r"));
+    assert_eq!(buffer.get::<&str>(&["tags", "1"])?, Some("red"));
+    assert_eq!(buffer.get::<&[u8]>(&["fixed"])?, Some(&[1u8, 2, 3] as &[u8]));
+
+    // shared values pass validation
+    buffer.set(&["blobs", "0"], &[1u8, 2, 3] as &[u8])?;
+    assert_eq!(buffer.compact_dedupe(None)?, 5);
+    let bytes = buffer.close();
+    let (mut buffer, report) = factory.open_lenient(bytes);
+    assert!(report.is_clean());
+
+    // a plain compaction stops sharing
+    let size = buffer.calc_bytes()?.current_buffer;
+    buffer.compact(None)?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer, size + 5);
+
+    Ok(())
+}
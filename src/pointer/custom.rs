@@ -0,0 +1,579 @@
+//! Custom types from plugins
+//!
+//! Crates using NoProto can add their own scalar types without changing the schema parser.  Implement `NP_Plugin` for the type, pick a type key from `NP_PLUGIN_KEYS` and pass the plugin to `NP_Factory::new_with_plugins`.  Schemas can then use the plugin's `type_name` like any built in type.
+//!
+//! Plugin values are stored as bytes with a size prefix, the plugin decides what the bytes mean.  They're converted to and from JSON by the plugin, and can be read and written as raw bytes with `NP_Custom`.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::json_flex::NP_JSON;
+//! use no_proto::pointer::custom::{NP_Plugin, NP_Custom};
+//!
+//! // RGB colors, written as "#rrggbb" strings in JSON
+//! struct Color;
+//!
+//! impl NP_Plugin for Color {
+//!     fn type_name(&self) -> &str { "color" }
+//!     fn type_key(&self) -> u8 { 64 }
+//!
+//!     fn value_from_json(&self, _schema: &[u8], json: &NP_JSON) -> Result<Vec<u8>, NP_Error> {
+//!         match json {
+//!             NP_JSON::String(hex) if hex.len() == 7 && hex.starts_with('#') => {
+//!                 (0..3).map(|i| u8::from_str_radix(&hex[(1 + i * 2)..(3 + i * 2)], 16).map_err(|_| NP_Error::new("Bad color!"))).collect()
+//!             },
+//!             _ => Err(NP_Error::new("Colors must be strings like #ff8800!"))
+//!         }
+//!     }
+//!
+//!     fn value_to_json(&self, _schema: &[u8], bytes: &[u8]) -> NP_JSON {
+//!         NP_JSON::String(bytes.iter().fold(String::from("#"), |hex, byte| format!("{}{:02x}", hex, byte)))
+//!     }
+//! }
+//!
+//! static COLOR: Color = Color;
+//!
+//! let factory: NP_Factory = NP_Factory::new_with_plugins(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",  {"type": "string"}],
+//!         ["color", {"type": "color"}]
+//!     ]
+//! }"#, &[&COLOR])?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set_with_json(&["color"], r##""#ff8800""##)?;
+//! assert_eq!(new_buffer.get::<NP_Custom>(&["color"])?.unwrap().bytes(), &[255, 136, 0]);
+//!
+//! new_buffer.set(&["color"], NP_Custom::new(&[0, 0, 255]))?;
+//! assert_eq!(new_buffer.json_encode(&["color"])?.stringify(), r##""#0000ff""##);
+//!
+//! // the plugin is needed to parse the schema
+//! assert!(NP_Factory::new(r#"{"type": "color"}"#).is_err());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::{json_flex::JSMAP, schema::{NP_Parsed_Schema}};
+use crate::error::NP_Error;
+use crate::{schema::{NP_TypeKeys}, pointer::NP_Value, json_flex::NP_JSON};
+use core::fmt::{Debug, Formatter};
+use core::ops::RangeInclusive;
+
+use alloc::vec::Vec;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::{borrow::ToOwned};
+use super::{NP_Cursor};
+use crate::NP_Memory;
+
+/// Type keys plugins can use, built in types never use these.
+pub const NP_PLUGIN_KEYS: RangeInclusive<u8> = 64..=127;
+
+/// A custom scalar type, see the [custom](index.html) module.
+///
+/// Plugins are passed to factories as `&'static` references, a `static` instance of a unit struct works well.
+///
+pub trait NP_Plugin: Sync {
+
+    /// Name of the type in JSON schemas
+    fn type_name(&self) -> &str;
+
+    /// Type key of this type in compiled schemas, must be in `NP_PLUGIN_KEYS` and can't change once schemas are compiled.
+    fn type_key(&self) -> u8;
+
+    /// Options for this type from its JSON schema, they're stored in the compiled schema and passed to the other methods as `schema`.
+    fn schema_from_json(&self, _json_schema: &NP_JSON) -> Result<Vec<u8>, NP_Error> {
+        Ok(Vec::new())
+    }
+
+    /// Add the options in `schema` back to the JSON schema, `type` is already set.
+    fn schema_to_json(&self, _schema: &[u8], _json_schema: &mut JSMAP) { }
+
+    /// Convert a JSON value into the bytes stored in the buffer
+    fn value_from_json(&self, schema: &[u8], json: &NP_JSON) -> Result<Vec<u8>, NP_Error>;
+
+    /// Convert bytes from the buffer into JSON
+    fn value_to_json(&self, schema: &[u8], bytes: &[u8]) -> NP_JSON;
+}
+
+/// A plugin in a parsed schema
+#[derive(Clone, Copy)]
+pub struct NP_Plugin_Ref(pub &'static dyn NP_Plugin);
+
+impl Debug for NP_Plugin_Ref {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "NP_Plugin({})", self.0.type_name())
+    }
+}
+
+impl PartialEq for NP_Plugin_Ref {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.type_key() == other.0.type_key() && self.0.type_name() == other.0.type_name()
+    }
+}
+
+/// Typed values of a plugin type, so they can be used with `NP_Buffer::get`, `NP_Buffer::set` and the other typed methods.
+///
+/// This is the stable way to add value types from outside of NoProto, implementing the internal `NP_Value` trait isn't supported.  Values are only ever seen as the bytes stored in the buffer, every type that implements this trait also implements `NP_Value`.
+///
+/// ```
+/// use no_proto::error::NP_Error;
+/// use no_proto::NP_Factory;
+/// use no_proto::json_flex::NP_JSON;
+/// use no_proto::pointer::custom::{NP_Plugin, NP_Plugin_Value};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Point { x: i16, y: i16 }
+///
+/// impl<'value> NP_Plugin_Value<'value> for Point {
+///     fn type_name() -> &'static str { "point" }
+///
+///     fn to_bytes(&self) -> Vec<u8> {
+///         [self.x.to_be_bytes(), self.y.to_be_bytes()].concat()
+///     }
+///
+///     fn from_bytes(bytes: &'value [u8]) -> Option<Self> {
+///         match bytes {
+///             [a, b, c, d] => Some(Point { x: i16::from_be_bytes([*a, *b]), y: i16::from_be_bytes([*c, *d]) }),
+///             _ => None
+///         }
+///     }
+/// }
+///
+/// struct PointPlugin;
+///
+/// impl NP_Plugin for PointPlugin {
+///     fn type_name(&self) -> &str { "point" }
+///     fn type_key(&self) -> u8 { 65 }
+///
+///     fn value_from_json(&self, _schema: &[u8], json: &NP_JSON) -> Result<Vec<u8>, NP_Error> {
+///         match (&json[0], &json[1]) {
+///             (NP_JSON::Integer(x), NP_JSON::Integer(y)) => Ok(Point { x: *x as i16, y: *y as i16 }.to_bytes()),
+///             _ => Err(NP_Error::new("Points are [x, y] arrays!"))
+///         }
+///     }
+///
+///     fn value_to_json(&self, _schema: &[u8], bytes: &[u8]) -> NP_JSON {
+///         match Point::from_bytes(bytes) {
+///             Some(point) => NP_JSON::Array(vec![NP_JSON::Integer(point.x as i64), NP_JSON::Integer(point.y as i64)]),
+///             None => NP_JSON::Null
+///         }
+///     }
+/// }
+///
+/// static POINT: PointPlugin = PointPlugin;
+///
+/// let factory: NP_Factory = NP_Factory::new_with_plugins(r#"{
+///    "type": "list",
+///    "of": {"type": "point"}
+/// }"#, &[&POINT])?;
+///
+/// let mut new_buffer = factory.empty_buffer(None);
+/// new_buffer.set(&["0"], Point { x: -3, y: 7 })?;
+/// new_buffer.set_with_json(&["1"], "[10, 20]")?;
+///
+/// assert_eq!(new_buffer.get::<Point>(&["1"])?, Some(Point { x: 10, y: 20 }));
+/// assert_eq!(new_buffer.json_encode(&[])?.stringify(), "[[-3,7],[10,20]]");
+///
+/// # Ok::<(), NP_Error>(())
+/// ```
+///
+pub trait NP_Plugin_Value<'value>: Sized {
+
+    /// `type_name` of the plugin this is a value of
+    fn type_name() -> &'static str;
+
+    /// The bytes stored in the buffer for this value
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Read a value from the bytes in the buffer, `None` if the bytes aren't a valid value
+    fn from_bytes(bytes: &'value [u8]) -> Option<Self>;
+}
+
+impl<'value, T: NP_Plugin_Value<'value>> super::NP_Scalar for T {}
+
+impl<'value, T: NP_Plugin_Value<'value>> NP_Value<'value> for T {
+
+    fn type_idx() -> (&'static str, NP_TypeKeys) { (T::type_name(), NP_TypeKeys::Custom) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { (T::type_name(), NP_TypeKeys::Custom) }
+
+    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
+        NP_Custom::schema_to_json(schema, address)
+    }
+
+    fn schema_default(_schema: &'value NP_Parsed_Schema) -> Option<Self> {
+        None
+    }
+
+    fn set_value<'set>(cursor: NP_Cursor, memory: &'set NP_Memory, value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
+        check_name::<T>(&cursor, memory)?;
+        NP_Custom::set_value(cursor, memory, NP_Custom::new(&value.to_bytes()))
+    }
+
+    fn into_value(cursor: &NP_Cursor, memory: &'value NP_Memory) -> Result<Option<Self>, NP_Error> where Self: Sized {
+        check_name::<T>(cursor, memory)?;
+        match NP_Custom::into_value(cursor, memory)? {
+            Some(value) => Ok(T::from_bytes(value.bytes())),
+            None => Ok(None)
+        }
+    }
+
+    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+        NP_Custom::to_json(cursor, memory)
+    }
+
+    fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
+        NP_Custom::get_size(cursor, memory)
+    }
+
+    fn from_json_to_schema(schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
+        NP_Custom::from_json_to_schema(schema, json_schema)
+    }
+
+    fn from_bytes_to_schema(schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+        NP_Custom::from_bytes_to_schema(schema, address, bytes)
+    }
+}
+
+// every plugin type has the same type key, the names have to match too
+fn check_name<'value, T: NP_Plugin_Value<'value>>(cursor: &NP_Cursor, memory: &NP_Memory) -> Result<(), NP_Error> {
+    match &memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::Custom { name, .. } if name == T::type_name() => Ok(()),
+        schema => Err(NP_Error::new_type_mismatch(schema.get_type_name(), T::type_name(), &[]))
+    }
+}
+
+/// Raw bytes of a plugin value.
+///
+/// Check out documentation [here](../custom/index.html).
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NP_Custom<'custom> {
+    bytes: &'custom [u8]
+}
+
+impl<'custom> super::NP_Scalar for NP_Custom<'custom> {}
+
+impl<'custom> NP_Custom<'custom> {
+
+    /// Wrap the bytes of a plugin value
+    pub fn new(bytes: &'custom [u8]) -> Self {
+        NP_Custom { bytes }
+    }
+
+    /// The bytes of this value
+    pub fn bytes(&self) -> &'custom [u8] {
+        self.bytes
+    }
+
+    /// Set a plugin value from JSON with the plugin of its schema
+    pub fn set_from_json(cursor: NP_Cursor, memory: &NP_Memory, json: &NP_JSON) -> Result<(), NP_Error> {
+        let bytes = match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Custom { plugin: Some(plugin), data, .. } => plugin.0.value_from_json(data, json)?,
+            NP_Parsed_Schema::Custom { name, .. } => return Err(NP_Error::new(alloc::format!("No plugin for the {:?} type!", name))),
+            _ => return Err(NP_Error::new_static("unreachable"))
+        };
+        NP_Custom::set_value(cursor, memory, NP_Custom::new(&bytes))?;
+        Ok(())
+    }
+}
+
+impl<'value> NP_Value<'value> for NP_Custom<'value> {
+
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("custom", NP_TypeKeys::Custom) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("custom", NP_TypeKeys::Custom) }
+
+    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
+        let mut schema_json = JSMAP::new();
+
+        if let NP_Parsed_Schema::Custom { name, data, plugin, .. } = &schema[address] {
+            schema_json.insert("type".to_owned(), NP_JSON::String(name.clone()));
+            if let Some(plugin) = plugin {
+                plugin.0.schema_to_json(data, &mut schema_json);
+            }
+        }
+
+        Ok(NP_JSON::Dictionary(schema_json))
+    }
+
+    fn schema_default(_schema: &'value NP_Parsed_Schema) -> Option<Self> {
+        None
+    }
+
+    fn set_value<'set>(cursor: NP_Cursor, memory: &'set NP_Memory, value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
+
+        let bytes = value.bytes;
+
+        if bytes.len() > u16::MAX as usize {
+            return Err(NP_Error::new_static("Custom value too large!"));
+        }
+
+        let addr_value = cursor.get_value(memory).get_addr_value() as usize;
+
+        let prev_size: usize = if addr_value != 0 {
+            u16::from_be_bytes(*memory.get_2_bytes(addr_value).unwrap_or(&[0; 2])) as usize
+        } else {
+            0
+        };
+
+        let size_bytes = (bytes.len() as u16).to_be_bytes();
+
+        if addr_value != 0 && prev_size >= bytes.len() {
+            // previous value is larger than this one, use existing memory
+            let write_bytes = memory.write_bytes();
+            write_bytes[addr_value..(addr_value + 2)].copy_from_slice(&size_bytes);
+            write_bytes[(addr_value + 2)..(addr_value + 2 + bytes.len())].copy_from_slice(bytes);
+            return Ok(cursor);
+        }
+
+        // not enough space or space has not been allocted yet
+        let new_addr = memory.malloc_borrow(&size_bytes)?;
+        memory.malloc_borrow(bytes)?;
+        cursor.get_value_mut(memory).set_addr_value(new_addr as u16);
+
+        Ok(cursor)
+    }
+
+    fn into_value(cursor: &NP_Cursor, memory: &'value NP_Memory) -> Result<Option<Self>, NP_Error> where Self: Sized {
+
+        let value_addr = cursor.get_value(memory).get_addr_value() as usize;
+
+        // empty value
+        if value_addr == 0 {
+            return Ok(None);
+        }
+
+        let size = u16::from_be_bytes(*memory.get_2_bytes(value_addr).unwrap_or(&[0; 2])) as usize;
+
+        match memory.read_bytes().get((value_addr + 2)..(value_addr + 2 + size)) {
+            Some(bytes) => Ok(Some(NP_Custom::new(bytes))),
+            None => Ok(None) // attack
+        }
+    }
+
+    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+
+        let value = match Self::into_value(cursor, memory) {
+            Ok(Some(value)) => value,
+            _ => return NP_JSON::Null
+        };
+
+        match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Custom { plugin: Some(plugin), data, .. } => plugin.0.value_to_json(data, value.bytes),
+            // without the plugin all we have are the bytes
+            _ => NP_JSON::Array(value.bytes.iter().map(|x| NP_JSON::Integer(*x as i64)).collect())
+        }
+    }
+
+    fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
+
+        let value_addr = cursor.get_value(memory).get_addr_value() as usize;
+
+        // empty value
+        if value_addr == 0 {
+            return Ok(0);
+        }
+
+        let size = u16::from_be_bytes(*memory.get_2_bytes(value_addr).unwrap_or(&[0; 2])) as usize;
+
+        Ok(size + 2)
+    }
+
+    fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
+
+        // `NP_Schema::resolve_plugins` puts plugin types in this form
+        let key = match &json_schema["key"] {
+            NP_JSON::Integer(x) if *x >= 0 && *x <= 255 && NP_PLUGIN_KEYS.contains(&(*x as u8)) => *x as u8,
+            _ => return Err(NP_Error::new_schema("Plugin type keys must be from 64 to 127!"))
+        };
+
+        let name = match &json_schema["name"] {
+            NP_JSON::String(x) if x.len() < 256 => x.clone(),
+            _ => return Err(NP_Error::new_schema("Plugin types need a name up to 255 bytes long!"))
+        };
+
+        let mut data: Vec<u8> = Vec::new();
+        if let NP_JSON::Array(bytes) = &json_schema["data"] {
+            for byte in bytes.iter() {
+                match byte {
+                    NP_JSON::Integer(x) if *x >= 0 && *x <= 255 => data.push(*x as u8),
+                    _ => return Err(NP_Error::new_schema("Plugin schema data must be bytes!"))
+                }
+            }
+        }
+
+        if data.len() > u16::MAX as usize {
+            return Err(NP_Error::new_schema("Plugin schema data is too large!"));
+        }
+
+        // [type key, name length, name, data length (u16), data]
+        let mut schema_data: Vec<u8> = Vec::new();
+        schema_data.push(key);
+        schema_data.push(name.len() as u8);
+        schema_data.extend_from_slice(name.as_bytes());
+        schema_data.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        schema_data.extend_from_slice(&data);
+
+        schema.push(NP_Parsed_Schema::Custom {
+            i: NP_TypeKeys::Custom,
+            sortable: false,
+            key,
+            name,
+            data,
+            plugin: None
+        });
+
+        Ok((false, schema_data, schema))
+    }
+
+    fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+
+        let name_len = bytes[address + 1] as usize;
+        let name = String::from_utf8_lossy(&bytes[(address + 2)..(address + 2 + name_len)]).into_owned();
+        let data_addr = address + 2 + name_len;
+        let data_len = u16::from_be_bytes([bytes[data_addr], bytes[data_addr + 1]]) as usize;
+
+        schema.push(NP_Parsed_Schema::Custom {
+            i: NP_TypeKeys::Custom,
+            sortable: false,
+            key: bytes[address],
+            name,
+            data: bytes[(data_addr + 2)..(data_addr + 2 + data_len)].to_vec(),
+            plugin: None
+        });
+
+        (false, schema)
+    }
+}
+
+#[cfg(test)]
+struct Fixed;
+
+// fixed point numbers, the schema holds the number of decimal places
+#[cfg(test)]
+impl NP_Plugin for Fixed {
+    fn type_name(&self) -> &str { "fixed" }
+    fn type_key(&self) -> u8 { 100 }
+
+    fn schema_from_json(&self, json_schema: &NP_JSON) -> Result<Vec<u8>, NP_Error> {
+        match &json_schema["places"] {
+            NP_JSON::Integer(x) if *x >= 0 && *x < 10 => Ok(alloc::vec![*x as u8]),
+            _ => Err(NP_Error::new_schema("Fixed needs 'places'!"))
+        }
+    }
+
+    fn schema_to_json(&self, schema: &[u8], json_schema: &mut JSMAP) {
+        json_schema.insert("places".to_owned(), NP_JSON::Integer(schema[0] as i64));
+    }
+
+    fn value_from_json(&self, schema: &[u8], json: &NP_JSON) -> Result<Vec<u8>, NP_Error> {
+        match json {
+            NP_JSON::Float(x) => Ok(((x * 10f64.powi(schema[0] as i32)) as i32).to_be_bytes().to_vec()),
+            _ => Err(NP_Error::new_static("Fixed values must be numbers!"))
+        }
+    }
+
+    fn value_to_json(&self, schema: &[u8], bytes: &[u8]) -> NP_JSON {
+        match bytes {
+            [a, b, c, d] => NP_JSON::Float(i32::from_be_bytes([*a, *b, *c, *d]) as f64 / 10f64.powi(schema[0] as i32)),
+            _ => NP_JSON::Null
+        }
+    }
+}
+
+#[cfg(test)]
+static FIXED: Fixed = Fixed;
+
+#[test]
+fn schema_parsing_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"list\",\"of\":{\"type\":\"fixed\",\"places\":2}}";
+    let factory = crate::NP_Factory::new_with_plugins(schema, &[&FIXED])?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory2 = crate::NP_Factory::new_compiled_with_plugins(factory.compile_schema(), &[&FIXED])?;
+    assert_eq!(schema, factory2.schema.to_json()?.stringify());
+
+    assert!(crate::NP_Factory::new(schema).is_err());
+    assert!(crate::NP_Factory::new_with_plugins(r#"{"type": "fixed"}"#, &[&FIXED]).is_err());
+    assert!(crate::NP_Factory::new_with_plugins(schema, &[&FIXED, &FIXED]).is_err());
+    assert!(crate::NP_Factory::new_with_plugins(r#"{"type": "$plugin", "key": 100, "name": "fixed"}"#, &[]).is_err());
+    assert!(crate::NP_Factory::new_compiled_with_plugins(factory.compile_schema(), &[]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new_with_plugins(r#"{"type": "table", "columns": [["price", {"type": "fixed", "places": 2}]]}"#, &[&FIXED])?;
+
+    let cents = 99i32.to_be_bytes();
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set_with_json(&["price"], "12.5")?;
+    assert_eq!(buffer.get::<NP_Custom>(&["price"])?.unwrap().bytes(), &1250i32.to_be_bytes());
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"{"price":12.5}"#);
+    assert!(buffer.set_with_json(&["price"], "\"twelve\"").is_err());
+    assert!(buffer.get::<u32>(&["price"]).is_err());
+
+    buffer.del(&["price"])?;
+    assert_eq!(buffer.get::<NP_Custom>(&["price"])?, None);
+    buffer.set(&["price"], NP_Custom::new(&cents))?;
+    buffer.compact(None)?;
+    assert_eq!(buffer.json_encode(&["price"])?.stringify(), "0.99");
+
+    // without the plugin the bytes are still there
+    let bytes = buffer.close();
+    let plain = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(plain.open_buffer(bytes).json_encode(&["price"])?.stringify(), "[0,0,0,99]");
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[derive(Debug, PartialEq)]
+struct Cents(i32);
+
+#[cfg(test)]
+impl<'value> NP_Plugin_Value<'value> for Cents {
+    fn type_name() -> &'static str { "fixed" }
+    fn to_bytes(&self) -> Vec<u8> { self.0.to_be_bytes().to_vec() }
+    fn from_bytes(bytes: &'value [u8]) -> Option<Self> {
+        match bytes {
+            [a, b, c, d] => Some(Cents(i32::from_be_bytes([*a, *b, *c, *d]))),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug, PartialEq)]
+struct Other;
+
+#[cfg(test)]
+impl<'value> NP_Plugin_Value<'value> for Other {
+    fn type_name() -> &'static str { "other" }
+    fn to_bytes(&self) -> Vec<u8> { Vec::new() }
+    fn from_bytes(_bytes: &'value [u8]) -> Option<Self> { Some(Other) }
+}
+
+#[test]
+fn plugin_values_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new_with_plugins(r#"{"type": "tuple", "values": [{"type": "fixed", "places": 2}, {"type": "u8"}]}"#, &[&FIXED])?;
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get::<Cents>(&["0"])?, None);
+    buffer.set(&["0"], Cents(-250))?;
+    assert_eq!(buffer.get::<Cents>(&["0"])?, Some(Cents(-250)));
+    assert_eq!(buffer.json_encode(&["0"])?.stringify(), "-2.5");
+
+    // plugin values only go with their own plugin's type
+    assert!(buffer.set(&["0"], Other).is_err());
+    assert!(buffer.get::<Other>(&["0"]).is_err());
+    assert!(buffer.set(&["1"], Cents(1)).is_err());
+
+    buffer.set(&["0"], Cents(7))?;
+    buffer.compact(None)?;
+    assert_eq!(buffer.get::<Cents>(&["0"])?, Some(Cents(7)));
+
+    Ok(())
+}
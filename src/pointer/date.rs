@@ -2,6 +2,8 @@
 //! 
 //! Epoch should be stored in milliseconds.
 //! 
+//! Dates convert to and from `std::time::SystemTime` with the `std` feature enabled, and to and from `chrono::DateTime<Utc>` with the `chrono` feature enabled.
+//! 
 //! ```
 //! use no_proto::error::NP_Error;
 //! use no_proto::NP_Factory;
@@ -52,6 +54,79 @@ impl NP_Date {
     pub fn new(time_ms: u64) -> Self {
         NP_Date { value: time_ms }
     }
+
+    /// Parse an RFC 3339 date like `2020-11-12T00:56:20.392Z`, digits past milliseconds are dropped.
+    /// 
+    /// Returns `None` if the string isn't a valid date or is before the unix epoch.
+    /// 
+    /// ```
+    /// use no_proto::pointer::date::NP_Date;
+    /// 
+    /// assert_eq!(NP_Date::from_rfc3339("2020-11-12T00:56:20.392Z"), Some(NP_Date::new(1605142580392)));
+    /// assert_eq!(NP_Date::from_rfc3339("2020-11-11T16:56:20.392-08:00"), Some(NP_Date::new(1605142580392)));
+    /// assert_eq!(NP_Date::from_rfc3339("2020-02-30T00:00:00Z"), None);
+    /// ```
+    pub fn from_rfc3339(value: &str) -> Option<Self> {
+        let bytes = value.as_bytes();
+
+        let digits = |start: usize, len: usize| -> Option<i64> {
+            let slice = bytes.get(start..start + len)?;
+            let mut result = 0i64;
+            for byte in slice {
+                if !byte.is_ascii_digit() { return None; }
+                result = result * 10 + (byte - b'0') as i64;
+            }
+            Some(result)
+        };
+
+        let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+        if separators.iter().any(|(idx, sep)| bytes.get(*idx) != Some(sep)) || !matches!(bytes.get(10), Some(b'T') | Some(b't') | Some(b' ')) {
+            return None;
+        }
+
+        let (year, month, day) = (digits(0, 4)?, digits(5, 2)?, digits(8, 2)?);
+        let (hour, minute, second) = (digits(11, 2)?, digits(14, 2)?, digits(17, 2)?);
+
+        let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let month_days = [31, if leap { 29 } else { 28 }, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+        if !(1..=12).contains(&month) || !(1..=month_days[month as usize - 1]).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+
+        // fraction of a second
+        let mut idx = 19;
+        let mut millis = 0i64;
+        if bytes.get(idx) == Some(&b'.') {
+            idx += 1;
+            let start = idx;
+            while bytes.get(idx).is_some_and(|b| b.is_ascii_digit()) {
+                if idx - start < 3 { millis = millis * 10 + (bytes[idx] - b'0') as i64; }
+                idx += 1;
+            }
+            if idx == start { return None; }
+            for _ in (idx - start)..3 { millis *= 10; }
+        }
+
+        let offset = match bytes.get(idx) {
+            Some(b'Z') | Some(b'z') if bytes.len() == idx + 1 => 0,
+            Some(sign @ b'+') | Some(sign @ b'-') if bytes.len() == idx + 6 && bytes[idx + 3] == b':' => {
+                let minutes = digits(idx + 1, 2)? * 60 + digits(idx + 4, 2)?;
+                if *sign == b'-' { -minutes } else { minutes }
+            },
+            _ => return None
+        };
+
+        // days since the epoch from the civil date
+        let shifted_year = if month <= 2 { year - 1 } else { year };
+        let era = shifted_year.div_euclid(400);
+        let year_of_era = shifted_year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146097 + day_of_era - 719468;
+
+        let ms = (((days * 24 + hour) * 60 + minute - offset) * 60 + second) * 1000 + millis;
+        if ms < 0 { None } else { Some(NP_Date::new(ms as u64)) }
+    }
 }
 
 impl Default for NP_Date {
@@ -60,6 +135,38 @@ impl Default for NP_Date {
      }
 }
 
+/// Dates before the unix epoch become the epoch
+#[cfg(feature = "std")]
+impl From<std::time::SystemTime> for NP_Date {
+    fn from(time: std::time::SystemTime) -> Self {
+        let since_epoch = time.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+        NP_Date::new(since_epoch.as_millis().min(u64::MAX as u128) as u64)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<NP_Date> for std::time::SystemTime {
+    fn from(date: NP_Date) -> Self {
+        std::time::UNIX_EPOCH + core::time::Duration::from_millis(date.value)
+    }
+}
+
+/// Dates before the unix epoch become the epoch
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for NP_Date {
+    fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+        NP_Date::new(time.timestamp_millis().max(0) as u64)
+    }
+}
+
+/// Dates too far in the future for chrono become chrono's largest date
+#[cfg(feature = "chrono")]
+impl From<NP_Date> for chrono::DateTime<chrono::Utc> {
+    fn from(date: NP_Date) -> Self {
+        chrono::DateTime::from_timestamp_millis(date.value.min(i64::MAX as u64) as i64).unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC)
+    }
+}
+
 impl Debug for NP_Date {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.value)
@@ -68,8 +175,8 @@ impl Debug for NP_Date {
 
 impl<'value> NP_Value<'value> for NP_Date {
 
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("date", NP_TypeKeys::Date) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("date", NP_TypeKeys::Date) }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("date", NP_TypeKeys::Date) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("date", NP_TypeKeys::Date) }
 
     fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
         let mut schema_json = JSMAP::new();
@@ -78,7 +185,7 @@ impl<'value> NP_Value<'value> for NP_Date {
         match &schema[address] {
             NP_Parsed_Schema::Date { i: _, default, sortable: _} => {
                 if let Some(d) = default {
-                    schema_json.insert("default".to_owned(), NP_JSON::Integer(d.value as i64));
+                    schema_json.insert("default".to_owned(), NP_JSON::from_u64(d.value));
                 }
             },
             _ => { }
@@ -120,7 +227,7 @@ impl<'value> NP_Value<'value> for NP_Date {
 
             let bytes = value.value.to_be_bytes();
             value_address = memory.malloc_borrow(&bytes)?;
-            c_value.set_addr_value(value_address as u16);
+            cursor.get_value_mut(memory).set_addr_value(value_address as u16);
         }                    
 
         Ok(cursor)
@@ -151,13 +258,13 @@ impl<'value> NP_Value<'value> for NP_Date {
             Ok(x) => {
                 match x {
                     Some(y) => {
-                        NP_JSON::Integer(y.value as i64)
+                        NP_JSON::from_u64(y.value)
                     },
                     None => {
                         match memory.schema[cursor.schema_addr] {
                             NP_Parsed_Schema::Date { i: _, default, sortable: _} => {
                                 if let Some(d) = default {
-                                    NP_JSON::Integer(d.value.clone() as i64)
+                                    NP_JSON::from_u64(d.value)
                                 } else {
                                     NP_JSON::Null
                                 }
@@ -195,6 +302,11 @@ impl<'value> NP_Value<'value> for NP_Date {
                 schema_data.extend((x as u64).to_be_bytes().to_vec());
                 Some(NP_Date { value: x as u64})
             },
+            NP_JSON::Unsigned(x) => {
+                schema_data.push(1);
+                schema_data.extend(x.to_be_bytes().to_vec());
+                Some(NP_Date { value: x })
+            },
             _ => {
                 schema_data.push(0);
                 None
@@ -266,4 +378,22 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn system_time_conversions_work() {
+    let time = std::time::UNIX_EPOCH + core::time::Duration::from_millis(1605138980392);
+    assert_eq!(NP_Date::from(time), NP_Date::new(1605138980392));
+    assert_eq!(std::time::SystemTime::from(NP_Date::new(1605138980392)), time);
+    assert_eq!(NP_Date::from(std::time::UNIX_EPOCH - core::time::Duration::from_secs(1)), NP_Date::new(0));
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn chrono_conversions_work() {
+    let time = chrono::DateTime::from_timestamp_millis(1605138980392).unwrap();
+    assert_eq!(NP_Date::from(time), NP_Date::new(1605138980392));
+    assert_eq!(chrono::DateTime::<chrono::Utc>::from(NP_Date::new(1605138980392)), time);
+    assert_eq!(NP_Date::from(chrono::DateTime::from_timestamp_millis(-5).unwrap()), NP_Date::new(0));
+}
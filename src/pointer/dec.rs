@@ -10,6 +10,8 @@
 //! 
 //! A value of "2039.756" could be stored as `NP_Dec {num: 2039756, exp: 3}`.  It could also be stored as: `NP_Dec {num: 203975600, exp: 5}`.
 //! 
+//! With the `rust_decimal` feature enabled values convert to and from `rust_decimal::Decimal` with `TryFrom`.
+//! 
 //! The range of possible floating point values depends on the `exp` value.  The `num` property is an i64 variable so it can safely store 9.22e18 to -9.22e18.  
 //! 
 //! If `exp` is zero, all values stored are whole numbers.
@@ -62,8 +64,38 @@
 //! # Ok::<(), NP_Error>(()) 
 //! ```
 //!
+//! ## Rounding
+//! 
+//! Values with more decimal places than the schema's `exp` are truncated when they're set.  Set `"round"` in the schema to `"half_up"`, `"half_even"`, `"floor"` or `"ceil"` to round them instead, or use `NP_Buffer::set_rounded` to pick a mode for a single value.
+//! 
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::dec::{NP_Dec, NP_Rounding};
+//! 
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["cut",   {"type": "decimal", "exp": 2}],
+//!         ["price", {"type": "decimal", "exp": 2, "round": "half_even"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["cut"], NP_Dec::new(10259, 3))?;
+//! new_buffer.set(&["price"], NP_Dec::new(10265, 3))?;
+//! 
+//! assert_eq!(new_buffer.get::<NP_Dec>(&["cut"])?, Some(NP_Dec::new(1025, 2)));
+//! assert_eq!(new_buffer.get::<NP_Dec>(&["price"])?, Some(NP_Dec::new(1026, 2)));
+//! 
+//! new_buffer.set_rounded(&["cut"], NP_Dec::new(10259, 3), NP_Rounding::HalfUp)?;
+//! assert_eq!(new_buffer.get::<NP_Dec>(&["cut"])?, Some(NP_Dec::new(1026, 2)));
+//!
+//! # Ok::<(), NP_Error>(()) 
+//! ```
+//!
 
-use alloc::prelude::v1::Box;
+use alloc::boxed::Box;
 use crate::utils::to_signed;
 use crate::schema::{NP_Parsed_Schema};
 use alloc::vec::Vec;
@@ -78,6 +110,45 @@ use super::{NP_Cursor};
 use crate::NP_Memory;
 use alloc::string::ToString;
 
+/// How digits past the `exp` of a decimal are dropped, see [rounding](index.html#rounding).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NP_Rounding {
+    /// Drop the digits, rounding toward zero (the default)
+    Truncate,
+    /// Round to the nearest value, halves away from zero
+    HalfUp,
+    /// Round to the nearest value, halves to the even neighbor (banker's rounding)
+    HalfEven,
+    /// Round toward negative infinity
+    Floor,
+    /// Round toward positive infinity
+    Ceil
+}
+
+impl NP_Rounding {
+    const NAMES: [(&'static str, NP_Rounding); 5] = [
+        ("truncate", NP_Rounding::Truncate),
+        ("half_up", NP_Rounding::HalfUp),
+        ("half_even", NP_Rounding::HalfEven),
+        ("floor", NP_Rounding::Floor),
+        ("ceil", NP_Rounding::Ceil)
+    ];
+
+    /// Name of this mode in schemas
+    pub fn name(&self) -> &'static str {
+        NP_Rounding::NAMES.iter().find(|(_, mode)| mode == self).map(|(name, _)| *name).unwrap_or("truncate")
+    }
+
+    /// Mode with this name in schemas
+    pub fn from_name(name: &str) -> Option<Self> {
+        NP_Rounding::NAMES.iter().find(|(mode_name, _)| *mode_name == name).map(|(_, mode)| *mode)
+    }
+
+    fn index(&self) -> u8 {
+        NP_Rounding::NAMES.iter().position(|(_, mode)| mode == self).unwrap_or(0) as u8
+    }
+}
+
 /// Holds fixed decimal data.
 /// 
 /// Check out documentation [here](../dec/index.html).
@@ -156,6 +227,51 @@ impl NP_Dec {
         *self
     }
 
+    /// Change the exponent of this NP_Dec with a rounding mode, unlike `shift_exp` this doesn't change `self`.
+    /// 
+    /// Returns `None` if a larger `exp` doesn't fit in an i64.
+    /// 
+    /// ```
+    /// use no_proto::pointer::dec::{NP_Dec, NP_Rounding};
+    /// 
+    /// let my_num = NP_Dec::new(-2205, 3); // value is -2.205
+    /// 
+    /// assert_eq!(my_num.round_to(2, NP_Rounding::Truncate), Some(NP_Dec::new(-220, 2)));
+    /// assert_eq!(my_num.round_to(2, NP_Rounding::HalfUp), Some(NP_Dec::new(-221, 2)));
+    /// assert_eq!(my_num.round_to(2, NP_Rounding::HalfEven), Some(NP_Dec::new(-220, 2)));
+    /// assert_eq!(my_num.round_to(2, NP_Rounding::Floor), Some(NP_Dec::new(-221, 2)));
+    /// assert_eq!(my_num.round_to(2, NP_Rounding::Ceil), Some(NP_Dec::new(-220, 2)));
+    /// assert_eq!(my_num.round_to(4, NP_Rounding::Floor), Some(NP_Dec::new(-22050, 4)));
+    /// ```
+    /// 
+    pub fn round_to(&self, exp: u8, rounding: NP_Rounding) -> Option<NP_Dec> {
+        if exp >= self.exp {
+            let mut num = self.num;
+            for _ in self.exp..exp {
+                num = num.checked_mul(10)?;
+            }
+            return Some(NP_Dec::new(num, exp));
+        }
+
+        // past 10^20 every i64 rounds the same way
+        let divisor = 10i128.pow(u32::min((self.exp - exp) as u32, 20));
+        let num = self.num as i128;
+        let quotient = num / divisor;
+        let remainder = num % divisor;
+
+        let rounded = match rounding {
+            NP_Rounding::Truncate => quotient,
+            NP_Rounding::Floor if remainder < 0 => quotient - 1,
+            NP_Rounding::Ceil if remainder > 0 => quotient + 1,
+            NP_Rounding::Floor | NP_Rounding::Ceil => quotient,
+            NP_Rounding::HalfUp if remainder.abs() * 2 >= divisor => quotient + num.signum(),
+            NP_Rounding::HalfEven if remainder.abs() * 2 > divisor || (remainder.abs() * 2 == divisor && quotient % 2 != 0) => quotient + num.signum(),
+            NP_Rounding::HalfUp | NP_Rounding::HalfEven => quotient
+        };
+
+        Some(NP_Dec::new(rounded as i64, exp))
+    }
+
     /// Generate a new NP_Dec value
     /// 
     /// First argument is the `num` value, second is the `exp` or exponent.
@@ -563,6 +679,30 @@ impl Into<NP_Dec> for f32 {
     }
 }
 
+/// Converts a `rust_decimal::Decimal` into a NP_Dec, fails if the number doesn't fit in an i64
+#[cfg(feature = "rust_decimal")]
+impl core::convert::TryFrom<rust_decimal::Decimal> for NP_Dec {
+    type Error = NP_Error;
+
+    fn try_from(value: rust_decimal::Decimal) -> Result<Self, Self::Error> {
+        let value = value.normalize();
+        match i64::try_from(value.mantissa()) {
+            Ok(num) => Ok(NP_Dec::new(num, value.scale() as u8)),
+            Err(_) => Err(NP_Error::new_static("Decimal is too large for NP_Dec!"))
+        }
+    }
+}
+
+/// Converts a NP_Dec into a `rust_decimal::Decimal`, fails if `exp` is more than 28
+#[cfg(feature = "rust_decimal")]
+impl core::convert::TryFrom<NP_Dec> for rust_decimal::Decimal {
+    type Error = NP_Error;
+
+    fn try_from(value: NP_Dec) -> Result<Self, Self::Error> {
+        rust_decimal::Decimal::try_new(value.num, value.exp as u32).map_err(|_| NP_Error::new_static("NP_Dec has too many decimal places for rust_decimal!"))
+    }
+}
+
 impl core::ops::DivAssign for NP_Dec { // a /= b
     fn div_assign(&mut self, other: NP_Dec) { 
         if self.exp != other.exp {
@@ -669,16 +809,20 @@ impl Default for NP_Dec {
 
 impl<'value> NP_Value<'value> for NP_Dec {
 
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("decimal", NP_TypeKeys::Decimal) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("decimal", NP_TypeKeys::Decimal) }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("decimal", NP_TypeKeys::Decimal) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("decimal", NP_TypeKeys::Decimal) }
 
     fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
         let mut schema_json = JSMAP::new();
         schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
 
         match &schema[address] {
-            NP_Parsed_Schema::Decimal { i: _, sortable: _, default, exp} => {
+            NP_Parsed_Schema::Decimal { default, exp, round, .. } => {
                 schema_json.insert("exp".to_owned(), NP_JSON::Integer(exp.clone() as i64));
+
+                if *round != NP_Rounding::Truncate {
+                    schema_json.insert("round".to_owned(), NP_JSON::String(round.name().to_owned()));
+                }
     
                 if let Some(d) = default {
                     let value = NP_Dec::new(d.num.clone(), exp.clone());
@@ -687,14 +831,14 @@ impl<'value> NP_Value<'value> for NP_Dec {
         
                 Ok(NP_JSON::Dictionary(schema_json))
             },
-            _ => Err(NP_Error::new("unreachable"))
+            _ => Err(NP_Error::new_static("unreachable"))
         }
     }
 
     fn schema_default(schema: &NP_Parsed_Schema) -> Option<Self> {
 
         match schema {
-            NP_Parsed_Schema::Decimal { i: _, sortable: _, default, exp: _} => {
+            NP_Parsed_Schema::Decimal { default, .. } => {
                 if let Some(d) = default {
                     Some(d.clone())
                 } else {
@@ -712,18 +856,17 @@ impl<'value> NP_Value<'value> for NP_Dec {
 
         let mut value_address = c_value.get_addr_value() as usize;
 
-        let exp = match memory.schema[cursor.schema_addr] {
-            NP_Parsed_Schema::Decimal { i: _, sortable: _, default: _, exp} => {
-                exp
+        let (exp, round) = match memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Decimal { exp, round, .. } => {
+                (exp, round)
             },
-            _ => 0
+            _ => (0, NP_Rounding::Truncate)
         };
 
-
-        let mut cloned_value = (value).clone();
-        cloned_value.shift_exp(exp);
-
-        let i64_value = cloned_value.num;
+        let i64_value = match value.round_to(exp, round) {
+            Some(x) => x.num,
+            None => return Err(NP_Error::new_static("Decimal doesn't fit in the exp of the schema!"))
+        };
 
         if value_address != 0 { // existing value, replace
             let mut bytes = i64_value.to_be_bytes();
@@ -746,7 +889,7 @@ impl<'value> NP_Value<'value> for NP_Dec {
             be_bytes[0] = to_unsigned(be_bytes[0]);
 
             value_address = memory.malloc_borrow(&be_bytes)?;
-            c_value.set_addr_value(value_address as u16);
+            cursor.get_value_mut(memory).set_addr_value(value_address as u16);
 
         }
 
@@ -765,7 +908,7 @@ impl<'value> NP_Value<'value> for NP_Dec {
         }
 
         let exp = match memory.schema[cursor.schema_addr] {
-            NP_Parsed_Schema::Decimal { i: _, sortable: _, default: _, exp} => {
+            NP_Parsed_Schema::Decimal { i: _, sortable: _, default: _, exp, round: _ } => {
                 exp
             },
             _ => 0
@@ -805,7 +948,7 @@ impl<'value> NP_Value<'value> for NP_Dec {
                     },
                     None => {
                         match memory.schema[cursor.schema_addr] {
-                            NP_Parsed_Schema::Decimal { i: _, sortable: _, default, exp} => {
+                            NP_Parsed_Schema::Decimal { i: _, sortable: _, default, exp, round: _ } => {
                                 if let Some(d) = default {
                                     let mut object = JSMAP::new();
 
@@ -849,13 +992,13 @@ impl<'value> NP_Value<'value> for NP_Dec {
         match json_schema["exp"] {
             NP_JSON::Integer(x) => {
                 if x > 255 || x < 0 {
-                    return Err(NP_Error::new("Decimal 'exp' property must be between 0 and 255!"))
+                    return Err(NP_Error::new_schema("Decimal 'exp' property must be between 0 and 255!"))
                 }
                 exp = x as u8;
                 schema_data.push(x as u8);
             },
             _ => {
-                return Err(NP_Error::new("Decimal type requires 'exp' property!"))
+                return Err(NP_Error::new_schema("Decimal type requires 'exp' property!"))
             }
         }
 
@@ -881,11 +1024,21 @@ impl<'value> NP_Value<'value> for NP_Dec {
             }
         };
 
+        let round = match &json_schema["round"] {
+            NP_JSON::Null => NP_Rounding::Truncate,
+            NP_JSON::String(x) => NP_Rounding::from_name(x).ok_or_else(|| NP_Error::new_schema("Decimal 'round' must be \"truncate\", \"half_up\", \"half_even\", \"floor\" or \"ceil\"!"))?,
+            _ => return Err(NP_Error::new_schema("Decimal 'round' must be a string!"))
+        };
+
+        // the rounding mode is kept above the default flag
+        schema_data[2] |= round.index() << 1;
+
         schema.push(NP_Parsed_Schema::Decimal {
             i: NP_TypeKeys::Decimal,
             default,
             sortable: true,
-            exp: exp
+            exp: exp,
+            round
         });
 
         return Ok((true, schema_data, schema))
@@ -895,7 +1048,9 @@ impl<'value> NP_Value<'value> for NP_Dec {
     fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
         let exp = bytes[address + 1];
 
-        let default = if bytes[address + 2] == 0 {
+        let round = NP_Rounding::NAMES.get((bytes[address + 2] >> 1) as usize).map(|(_, mode)| *mode).unwrap_or(NP_Rounding::Truncate);
+
+        let default = if bytes[address + 2] & 1 == 0 {
             None
         } else {
             let mut slice = 0i64.to_be_bytes();
@@ -908,7 +1063,8 @@ impl<'value> NP_Value<'value> for NP_Dec {
             i: NP_TypeKeys::Decimal,
             exp: exp,
             default,
-            sortable: true
+            sortable: true,
+            round
         });
 
         (true, schema)
@@ -953,4 +1109,48 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "rust_decimal")]
+#[test]
+fn rust_decimal_conversions_work() -> Result<(), NP_Error> {
+    use core::convert::TryFrom;
+
+    let value = NP_Dec::try_from(rust_decimal::Decimal::new(-22030, 4))?;
+    assert_eq!((value.num, value.exp), (-2203, 3));
+    assert_eq!(rust_decimal::Decimal::try_from(value)?, rust_decimal::Decimal::new(-2203, 3));
+    assert!(NP_Dec::try_from(rust_decimal::Decimal::MAX).is_err());
+    assert!(rust_decimal::Decimal::try_from(NP_Dec::new(1, 40)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn rounding_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"decimal\",\"exp\":1,\"round\":\"ceil\",\"default\":2.5}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get::<NP_Dec>(&[])?, Some(NP_Dec::new(25, 1)));
+    buffer.set(&[], NP_Dec::new(1201, 3))?;
+    assert_eq!(buffer.get::<NP_Dec>(&[])?, Some(NP_Dec::new(13, 1)));
+    buffer.set(&[], NP_Dec::new(-1299, 3))?;
+    assert_eq!(buffer.get::<NP_Dec>(&[])?, Some(NP_Dec::new(-12, 1)));
+    buffer.set_rounded(&[], NP_Dec::new(-1250, 3), NP_Rounding::HalfEven)?;
+    assert_eq!(buffer.get::<NP_Dec>(&[])?, Some(NP_Dec::new(-12, 1)));
+    assert!(buffer.set(&[], NP_Dec::new(i64::MAX, 0)).is_err());
+
+    for (num, half_up, half_even) in [(15i64, 2i64, 2i64), (25, 3, 2), (-25, -3, -2), (24, 2, 2), (-26, -3, -3)].iter() {
+        assert_eq!(NP_Dec::new(*num, 1).round_to(0, NP_Rounding::HalfUp), Some(NP_Dec::new(*half_up, 0)));
+        assert_eq!(NP_Dec::new(*num, 1).round_to(0, NP_Rounding::HalfEven), Some(NP_Dec::new(*half_even, 0)));
+    }
+    assert_eq!(NP_Dec::new(i64::MIN, 255).round_to(0, NP_Rounding::Floor), Some(NP_Dec::new(-1, 0)));
+    assert_eq!(NP_Dec::new(i64::MAX, 255).round_to(0, NP_Rounding::HalfUp), Some(NP_Dec::new(0, 0)));
+
+    assert!(crate::NP_Factory::new("{\"type\":\"decimal\",\"exp\":1,\"round\":\"up\"}").is_err());
+
+    Ok(())
+}
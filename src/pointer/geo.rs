@@ -116,13 +116,13 @@ impl<'value> NP_Value<'value> for NP_Geo_Bytes {
     fn schema_default(_schema: &NP_Parsed_Schema) -> Option<Self> {
         None
     }
-    fn type_idx() -> (&'value str, NP_TypeKeys) { NP_Geo::type_idx() }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { NP_Geo::type_idx() }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { NP_Geo::type_idx() }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { NP_Geo::type_idx() }
 
     fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> { NP_Geo::schema_to_json(schema, address)}
 
     fn set_value<'set>(_cursor: NP_Cursor, _memory: &'set NP_Memory, _value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
-        Err(NP_Error::new("Can't set value with NP_Geo_Bytes, use NP_Geo instead!"))
+        Err(NP_Error::new_static("Can't set value with NP_Geo_Bytes, use NP_Geo instead!"))
     }
     fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
         NP_Geo::to_json(cursor, memory)
@@ -202,7 +202,7 @@ impl<'value> NP_Value<'value> for NP_Geo_Bytes {
 /// 
 /// Check out documentation [here](../geo/index.html).
 /// 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct NP_Geo {
     /// The size of this geographic coordinate.  4, 8 or 16
     pub size: u8,
@@ -289,6 +289,19 @@ impl NP_Geo {
      }
 }
 
+/// Makes a full resolution coordinate from `(lat, lng)`, it's stored at the resolution of the schema
+impl From<(f64, f64)> for NP_Geo {
+    fn from((lat, lng): (f64, f64)) -> Self {
+        NP_Geo::new(16, lat, lng)
+    }
+}
+
+impl From<NP_Geo> for (f64, f64) {
+    fn from(geo: NP_Geo) -> Self {
+        (geo.lat, geo.lng)
+    }
+}
+
 impl Default for NP_Geo {
     fn default() -> Self { 
         NP_Geo { lat: 0.0, lng: 0.0, size: 0 }
@@ -312,7 +325,7 @@ fn geo_default_value(size: u8, json: &NP_JSON) -> Result<Option<NP_Geo_Bytes>, N
                     }
                 },
                 None => {
-                    return Err(NP_Error::new("Default values for NP_Geo should have lat key!"))
+                    return Err(NP_Error::new_schema("Default values for NP_Geo should have lat key!"))
                 }
             };
             let mut lng = 0f64;
@@ -329,7 +342,7 @@ fn geo_default_value(size: u8, json: &NP_JSON) -> Result<Option<NP_Geo_Bytes>, N
                     }
                 },
                 None => {
-                    return Err(NP_Error::new("Default values for NP_Geo should have lng key!"))
+                    return Err(NP_Error::new_schema("Default values for NP_Geo should have lng key!"))
                 }
             };
 
@@ -357,8 +370,8 @@ impl<'value> NP_Value<'value> for NP_Geo {
         }
     }
 
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("geo", NP_TypeKeys::Geo) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("geo", NP_TypeKeys::Geo) }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("geo", NP_TypeKeys::Geo) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("geo", NP_TypeKeys::Geo) }
 
     fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
         let mut schema_json = JSMAP::new();
@@ -378,7 +391,7 @@ impl<'value> NP_Value<'value> for NP_Geo {
         
                 Ok(NP_JSON::Dictionary(schema_json))
             },
-            _ => Err(NP_Error::new("unreachable"))
+            _ => Err(NP_Error::new_static("unreachable"))
         }
 
 
@@ -401,8 +414,6 @@ impl<'value> NP_Value<'value> for NP_Geo {
             unreachable!();
         }
 
-        let write_bytes = memory.write_bytes();
-
         let half_value_bytes = value_bytes_size / 2;
 
         // convert input values into bytes
@@ -476,6 +487,8 @@ impl<'value> NP_Value<'value> for NP_Geo {
 
         if value_address != 0 { // existing value, replace
 
+            let write_bytes = memory.write_bytes();
+
             // overwrite existing values in buffer
             for x in 0..value_bytes.len() {
                 if x < value_bytes_size {
@@ -493,6 +506,8 @@ impl<'value> NP_Value<'value> for NP_Geo {
                 _ => { 0 }
             };
 
+            let write_bytes = memory.write_bytes();
+
             // set values in buffer
             for x in 0..value_bytes.len() {
                 if x < value_bytes_size {
@@ -500,7 +515,7 @@ impl<'value> NP_Value<'value> for NP_Geo {
                 }
             }
 
-            c_value.set_addr_value(value_address as u16);
+            cursor.get_value_mut(memory).set_addr_value(value_address as u16);
 
         }
 
@@ -850,4 +865,14 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn tuple_conversions_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new("{\"type\":\"geo8\"}")?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&[], NP_Geo::from((45.509616, -122.714625)))?;
+    let (lat, lng): (f64, f64) = buffer.get::<NP_Geo>(&[])?.unwrap().into();
+    assert_eq!((lat, lng), (45.509616, -122.714625));
+
+    Ok(())
+}
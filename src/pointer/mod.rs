@@ -22,22 +22,26 @@ pub mod ulid;
 pub mod uuid;
 pub mod option;
 pub mod date;
+pub mod tensor;
+pub mod bigint;
+pub mod custom;
 
-use crate::buffer::ROOT_PTR_ADDR;
-use core::{fmt::{Debug}};
+use crate::buffer::{ROOT_PTR_ADDR, NP_Limits};
+use core::{fmt::{Debug, Write}};
 
-use alloc::prelude::v1::Box;
+use alloc::boxed::Box;
 use crate::{pointer::dec::NP_Dec, schema::NP_Schema_Addr};
 use crate::NP_Parsed_Schema;
-use crate::{json_flex::NP_JSON};
+use crate::{json_flex::{NP_JSON, JSMAP, JSON_Parser, JSON_Source, write_escaped}};
 use crate::memory::{NP_Memory};
 use crate::NP_Error;
-use crate::{schema::{NP_TypeKeys}, collection::{map::NP_Map, table::NP_Table, list::NP_List, tuple::NP_Tuple}};
+use crate::utils::opt_err;
+use crate::{schema::{NP_TypeKeys}, collection::{map::NP_Map, table::NP_Table, list::NP_List, tuple::NP_Tuple, log::NP_Log}};
 
-use alloc::{string::String, vec::Vec, borrow::ToOwned};
+use alloc::{string::{String, ToString}, vec::Vec, borrow::{Cow, ToOwned}};
 use bytes::NP_Bytes;
 
-use self::{date::NP_Date, geo::NP_Geo, option::NP_Enum, string::NP_String, ulid::{NP_ULID, _NP_ULID}, uuid::{NP_UUID, _NP_UUID}};
+use self::{any::NP_Dyn, bigint::NP_BigInt, custom::NP_Custom, date::NP_Date, geo::NP_Geo, numbers::{NP_Overflow, NP_NonFinite}, option::NP_Enum, string::NP_String, tensor::NP_Tensor, ulid::{NP_ULID, _NP_ULID}, uuid::{NP_UUID, _NP_UUID}};
 
 #[doc(hidden)]
 #[derive(Debug, Copy, Clone)]
@@ -61,6 +65,23 @@ pub struct NP_Pointer_List_Item {
     pub index: u8
 }
 
+#[doc(hidden)]
+#[derive(Debug)]
+#[repr(C)]
+pub struct NP_Pointer_Wide_List_Item {
+    pub addr_value: [u8; 2],
+    pub next_value: [u8; 2],
+    pub index: [u8; 4]
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+#[repr(C)]
+pub struct NP_Pointer_Log_Item {
+    pub addr_value: [u8; 2],
+    pub next_value: [u8; 2]
+}
+
 #[doc(hidden)]
 #[derive(Debug)]
 #[repr(C)]
@@ -78,8 +99,8 @@ pub trait NP_Pointer_Bytes {
     fn set_addr_value(&mut self, addr: u16)                        {   }
     fn get_next_addr(&self) -> u16                                 { 0 }
     fn set_next_addr(&mut self, addr: u16)                         {   }
-    fn set_index(&mut self, index: u8)                             {   }
-    fn get_index(&self) -> u8                                      { 0 }
+    fn set_index(&mut self, index: u32)                            {   }
+    fn get_index(&self) -> u32                                     { 0 }
     fn set_key_addr(&mut self, hash: u16)                          {   }
     fn get_key_addr(&self) -> u16                                  { 0 }
     fn reset(&mut self)                                            {   }
@@ -110,14 +131,48 @@ impl NP_Pointer_Bytes for NP_Pointer_List_Item {
     #[inline(always)]
     fn set_next_addr(&mut self, addr: u16) { self.next_value = addr.to_be_bytes() }
     #[inline(always)]
-    fn set_index(&mut self, index: u8)  { self.index = index }
+    fn set_index(&mut self, index: u32)  { self.index = index as u8 }
     #[inline(always)]
-    fn get_index(&self) -> u8  { self.index }
+    fn get_index(&self) -> u32  { self.index as u32 }
     #[inline(always)]
     fn reset(&mut self) { self.addr_value = [0; 2]; self.next_value = [0; 2]; self.index = 0; }
     #[inline(always)]
     fn get_size(&self) -> usize { 5 }
 }
+impl NP_Pointer_Bytes for NP_Pointer_Wide_List_Item {
+    fn get_type(&self) -> &str { "Wide List Item" }
+    #[inline(always)]
+    fn get_addr_value(&self) -> u16 { u16::from_be_bytes(self.addr_value) }
+    #[inline(always)]
+    fn set_addr_value(&mut self, addr: u16) { self.addr_value = addr.to_be_bytes() }
+    #[inline(always)]
+    fn get_next_addr(&self) -> u16 { u16::from_be_bytes(self.next_value) }
+    #[inline(always)]
+    fn set_next_addr(&mut self, addr: u16) { self.next_value = addr.to_be_bytes() }
+    #[inline(always)]
+    fn set_index(&mut self, index: u32)  { self.index = index.to_be_bytes() }
+    #[inline(always)]
+    fn get_index(&self) -> u32  { u32::from_be_bytes(self.index) }
+    #[inline(always)]
+    fn reset(&mut self) { self.addr_value = [0; 2]; self.next_value = [0; 2]; self.index = [0; 4]; }
+    #[inline(always)]
+    fn get_size(&self) -> usize { 8 }
+}
+impl NP_Pointer_Bytes for NP_Pointer_Log_Item {
+    fn get_type(&self) -> &str { "Log Item" }
+    #[inline(always)]
+    fn get_addr_value(&self) -> u16 { u16::from_be_bytes(self.addr_value) }
+    #[inline(always)]
+    fn set_addr_value(&mut self, addr: u16) { self.addr_value = addr.to_be_bytes() }
+    #[inline(always)]
+    fn get_next_addr(&self) -> u16 { u16::from_be_bytes(self.next_value) }
+    #[inline(always)]
+    fn set_next_addr(&mut self, addr: u16) { self.next_value = addr.to_be_bytes() }
+    #[inline(always)]
+    fn reset(&mut self) { self.addr_value = [0; 2]; self.next_value = [0; 2]; }
+    #[inline(always)]
+    fn get_size(&self) -> usize { 4 }
+}
 impl NP_Pointer_Bytes for NP_Pointer_Map_Item {
     fn get_type(&self) -> &str { "Map Item" }
     #[inline(always)]
@@ -142,9 +197,11 @@ impl NP_Pointer_Bytes for NP_Pointer_Map_Item {
         if key_addr == 0 {
             return "";
         } else {
-            let key_length = memory.read_bytes()[key_addr] as usize;
-            let key_bytes = &memory.read_bytes()[(key_addr + 1)..(key_addr + 1 + key_length)];
-            unsafe { core::str::from_utf8_unchecked(key_bytes) }
+            let key_length = memory.get_1_byte(key_addr).unwrap_or(0) as usize;
+            match memory.read_bytes().get((key_addr + 1)..(key_addr + 1 + key_length)) {
+                Some(key_bytes) => core::str::from_utf8(key_bytes).unwrap_or(""),
+                None => "" // attack
+            }
         }
     }
     #[inline(always)]
@@ -153,7 +210,7 @@ impl NP_Pointer_Bytes for NP_Pointer_Map_Item {
         if key_addr == 0 {
             return 0;
         } else {
-            return memory.read_bytes()[key_addr] as usize;
+            return memory.get_1_byte(key_addr).unwrap_or(0) as usize;
         }
     }
 }
@@ -207,13 +264,55 @@ impl NP_List_Bytes {
     }
 }
 
+#[repr(C)]
+#[derive(Debug)]
+#[doc(hidden)]
+#[allow(missing_docs)]
+pub struct NP_Log_Bytes {
+    head: [u8; 2],
+    tail: [u8; 2],
+    count: [u8; 2]
+}
+
+#[allow(missing_docs)]
+impl NP_Log_Bytes {
+    #[inline(always)]
+    pub fn set_head(&mut self, head: u16) {
+        self.head = head.to_be_bytes();
+    }
+    #[inline(always)]
+    pub fn get_head(&self) -> u16 {
+        u16::from_be_bytes(self.head)
+    }
+    #[inline(always)]
+    pub fn set_tail(&mut self, tail: u16) {
+        self.tail = tail.to_be_bytes();
+    }
+    #[inline(always)]
+    pub fn get_tail(&self) -> u16 {
+        u16::from_be_bytes(self.tail)
+    }
+    #[inline(always)]
+    pub fn set_count(&mut self, count: u16) {
+        self.count = count.to_be_bytes();
+    }
+    #[inline(always)]
+    pub fn get_count(&self) -> u16 {
+        u16::from_be_bytes(self.count)
+    }
+}
+
+/// Values held by each vtable of a table or tuple
+#[doc(hidden)]
+pub const VTABLE_SLOTS: usize = 4;
+
 // holds 4 u16 addresses and a next value (10 bytes)
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 #[doc(hidden)]
 #[allow(missing_docs)]
 pub struct NP_Vtable {
-    pub values: [NP_Pointer_Scalar; 4],
+    pub values: [NP_Pointer_Scalar; VTABLE_SLOTS],
     next: [u8; 2]
 }
 
@@ -260,19 +359,61 @@ impl NP_Cursor {
 
     #[inline(always)]
     /// Get the value bytes of this cursor
-    pub fn get_value<'value>(&self, memory: &'value NP_Memory<'value>) -> &'value mut dyn NP_Pointer_Bytes {
-        let ptr = memory.write_bytes().as_mut_ptr();
-        // if requesting root pointer or address is higher than buffer length
-        if self.buff_addr == ROOT_PTR_ADDR || self.buff_addr > memory.read_bytes().len() {
+    pub fn get_value<'value>(&self, memory: &'value NP_Memory<'value>) -> &'value dyn NP_Pointer_Bytes {
+        let ptr = memory.read_ptr();
+        let buff_len = memory.read_bytes().len();
+        // if requesting root pointer or pointer extends past buffer length
+        if self.buff_addr == ROOT_PTR_ADDR || self.buff_addr + 2 > buff_len {
+            unsafe { &*(ptr.add(ROOT_PTR_ADDR) as *const NP_Pointer_Scalar) }
+        } else {
+            match memory.schema[self.parent_schema_addr] {
+                NP_Parsed_Schema::List { wide: true, .. } if self.buff_addr + 8 <= buff_len => {
+                    unsafe { &*(ptr.add(self.buff_addr) as *const NP_Pointer_Wide_List_Item) }
+                },
+                NP_Parsed_Schema::List { wide: false, .. } if self.buff_addr + 5 <= buff_len => {
+                    unsafe { &*(ptr.add(self.buff_addr) as *const NP_Pointer_List_Item) }
+                },
+                NP_Parsed_Schema::Map { .. } if self.buff_addr + 6 <= buff_len => {
+                    unsafe { &*(ptr.add(self.buff_addr) as *const NP_Pointer_Map_Item) }
+                },
+                NP_Parsed_Schema::Log { .. } if self.buff_addr + 4 <= buff_len => {
+                    unsafe { &*(ptr.add(self.buff_addr) as *const NP_Pointer_Log_Item) }
+                },
+                NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Map { .. } | NP_Parsed_Schema::Log { .. } => { // attack
+                    unsafe { &*(ptr.add(ROOT_PTR_ADDR) as *const NP_Pointer_Scalar) }
+                },
+                _ => { // parent is scalar, table or tuple
+                    unsafe { &*(ptr.add(self.buff_addr) as *const NP_Pointer_Scalar) }
+                }
+            }                   
+        }
+    }
+
+    #[inline(always)]
+    /// Get the value bytes of this cursor to change them, frozen memory can't be changed
+    pub fn get_value_mut<'value>(&self, memory: &'value NP_Memory<'value>) -> &'value mut dyn NP_Pointer_Bytes {
+        let ptr = memory.bytes_ptr();
+        let buff_len = memory.read_bytes().len();
+        // if requesting root pointer or pointer extends past buffer length
+        if self.buff_addr == ROOT_PTR_ADDR || self.buff_addr + 2 > buff_len {
             unsafe { &mut *(ptr.add(ROOT_PTR_ADDR) as *mut NP_Pointer_Scalar) }
         } else {
             match memory.schema[self.parent_schema_addr] {
-                NP_Parsed_Schema::List { .. } => {
+                NP_Parsed_Schema::List { wide: true, .. } if self.buff_addr + 8 <= buff_len => {
+                    unsafe { &mut *(ptr.add(self.buff_addr) as *mut NP_Pointer_Wide_List_Item) }
+                },
+                NP_Parsed_Schema::List { wide: false, .. } if self.buff_addr + 5 <= buff_len => {
                     unsafe { &mut *(ptr.add(self.buff_addr) as *mut NP_Pointer_List_Item) }
                 },
-                NP_Parsed_Schema::Map { .. } => {
+                NP_Parsed_Schema::Map { .. } if self.buff_addr + 6 <= buff_len => {
                     unsafe { &mut *(ptr.add(self.buff_addr) as *mut NP_Pointer_Map_Item) }
                 },
+                NP_Parsed_Schema::Log { .. } if self.buff_addr + 4 <= buff_len => {
+                    unsafe { &mut *(ptr.add(self.buff_addr) as *mut NP_Pointer_Log_Item) }
+                },
+                NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Map { .. } | NP_Parsed_Schema::Log { .. } => { // attack
+                    unsafe { &mut *(ptr.add(ROOT_PTR_ADDR) as *mut NP_Pointer_Scalar) }
+                },
                 _ => { // parent is scalar, table or tuple
                     unsafe { &mut *(ptr.add(self.buff_addr) as *mut NP_Pointer_Scalar) }
                 }
@@ -287,7 +428,7 @@ impl NP_Cursor {
 
         match memory.schema[cursor.schema_addr].get_type_key() {
             NP_TypeKeys::None           => { NP_JSON::Null },
-            NP_TypeKeys::Any            => { NP_JSON::Null },
+            NP_TypeKeys::Any            => {    NP_Dyn::to_json(cursor, memory) },
             NP_TypeKeys::UTF8String     => { NP_String::to_json(cursor, memory) },
             NP_TypeKeys::Bytes          => {  NP_Bytes::to_json(cursor, memory) },
             NP_TypeKeys::Int8           => {        i8::to_json(cursor, memory) },
@@ -307,20 +448,240 @@ impl NP_Cursor {
             NP_TypeKeys::Ulid           => {  _NP_ULID::to_json(cursor, memory) },
             NP_TypeKeys::Date           => {   NP_Date::to_json(cursor, memory) },
             NP_TypeKeys::Enum           => {   NP_Enum::to_json(cursor, memory) },
-            NP_TypeKeys::Table          => {  NP_Table::to_json(cursor, memory) },
-            NP_TypeKeys::Map            => {    NP_Map::to_json(cursor, memory) },
-            NP_TypeKeys::List           => {   NP_List::to_json(cursor, memory) },
-            NP_TypeKeys::Tuple          => {  NP_Tuple::to_json(cursor, memory) }
+            NP_TypeKeys::Tensor         => { NP_Tensor::to_json(cursor, memory) },
+            NP_TypeKeys::BigInt         => { NP_BigInt::to_json(cursor, memory) },
+            NP_TypeKeys::Custom         => { NP_Custom::to_json(cursor, memory) },
+            NP_TypeKeys::Table | NP_TypeKeys::Map | NP_TypeKeys::List | NP_TypeKeys::Tuple | NP_TypeKeys::Log => { Self::json_encode_collection(cursor, memory) }
+        }
+
+    }
+
+    // collections are encoded with a stack of their own instead of recursively, so deep buffers don't use up the stack
+    fn json_encode_collection(cursor: &NP_Cursor, memory: &NP_Memory) -> NP_JSON {
+        let mut sink = NP_Null_Writer;
+        let mut budget = NP_Json_Budget::new(&mut sink, NP_Limits::unlimited(), 0);
+        Self::json_encode_items(cursor, memory, &mut budget).unwrap_or(NP_JSON::Null)
+    }
+
+    /// Same as `json_encode`, but stops with `NP_Error::LimitExceeded` as soon as encoding goes past one of the buffer's `NP_Limits`.
+    /// 
+    /// `depth` is how many collections are above the cursor.
+    /// 
+    pub fn json_encode_limited(cursor: &NP_Cursor, memory: &NP_Memory, depth: usize) -> Result<NP_JSON, NP_Error> {
+        let mut sink = NP_Null_Writer;
+        let mut budget = NP_Json_Budget::new(&mut sink, memory.options().limits, depth);
+        match Self::json_encode_items(cursor, memory, &mut budget) {
+            Ok(json) => Ok(json),
+            Err(_) => Err(budget.into_error())
         }
+    }
+
+    fn json_encode_items<W: core::fmt::Write>(cursor: &NP_Cursor, memory: &NP_Memory, budget: &mut NP_Json_Budget<W>) -> Result<NP_JSON, core::fmt::Error> {
+
+        let root = match NP_Json_Iter::new(cursor, memory) {
+            Some(iter) => iter,
+            None => {
+                // collections without a value are null
+                let json = if is_collection(&memory.schema[cursor.schema_addr]) { NP_JSON::Null } else { NP_Cursor::json_encode(cursor, memory) };
+                budget.count(&json)?;
+                return Ok(json);
+            }
+        };
 
+        budget.enter(0)?;
+        budget.count_str("[]")?;
+
+        // (items left to encode, key in the parent collection, items encoded so far, nothing encoded yet)
+        let empty = root.empty();
+        let mut stack = alloc::vec![(root, None, empty, true)];
+
+        while let Some((iter, _key, json, first)) = stack.last_mut() {
+            match iter.step(memory) {
+                Some((key, item)) => {
+                    budget.step(!*first)?;
+                    *first = false;
+                    if let Some(key) = &key {
+                        budget.count_key(key)?;
+                    }
+                    let key = key.map(Cow::into_owned);
+                    match item.map(|item| (item, NP_Json_Iter::new(&item, memory))) {
+                        Some((_, Some(inner))) => {
+                            budget.enter(stack.len())?;
+                            budget.count_str("[]")?;
+                            let empty = inner.empty();
+                            stack.push((inner, key, empty, true));
+                        },
+                        Some((item, None)) => {
+                            let value = NP_Cursor::json_encode(&item, memory);
+                            budget.count(&value)?;
+                            json_append(json, key, value);
+                        },
+                        None => {
+                            budget.count_str("null")?;
+                            json_append(json, key, NP_JSON::Null);
+                        }
+                    }
+                },
+                None => {
+                    if let Some((_, key, done, _)) = stack.pop() {
+                        match stack.last_mut() {
+                            Some((_, _, parent, _)) => json_append(parent, key, done),
+                            None => return Ok(done)
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(NP_JSON::Null)
+    }
+
+    /// Writes this pointer and all it's descendants as JSON text, the same text `json_encode(..).stringify()` produces.
+    /// Collections and strings are streamed straight from the buffer, other values are converted one at a time.
+    /// 
+    /// Collections are written with a stack of their own instead of recursively, so deep buffers don't use up the stack.
+    /// 
+    pub fn json_write<W: core::fmt::Write>(cursor: &NP_Cursor, memory: &NP_Memory, out: &mut W) -> core::fmt::Result {
+        let mut budget = NP_Json_Budget::new(out, NP_Limits::unlimited(), 0);
+        Self::json_write_items(cursor, memory, &mut budget)
+    }
+
+    /// Same as `json_write`, but stops with `NP_Error::LimitExceeded` as soon as writing goes past one of the buffer's `NP_Limits`.
+    /// 
+    /// `depth` is how many collections are above the cursor.  Text written before a limit is reached stays in `out`.
+    /// 
+    pub fn json_write_limited<W: core::fmt::Write>(cursor: &NP_Cursor, memory: &NP_Memory, depth: usize, out: &mut W) -> Result<(), NP_Error> {
+        let mut budget = NP_Json_Budget::new(out, memory.options().limits, depth);
+        match Self::json_write_items(cursor, memory, &mut budget) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(budget.into_error())
+        }
+    }
+
+    fn json_write_items<W: core::fmt::Write>(cursor: &NP_Cursor, memory: &NP_Memory, out: &mut NP_Json_Budget<W>) -> core::fmt::Result {
+
+        let root = match NP_Json_Iter::new(cursor, memory) {
+            Some(iter) => iter,
+            None => return Self::json_write_value(cursor, memory, out)
+        };
+
+        out.enter(0)?;
+        out.write_char(root.open())?;
+
+        // (items left to write, nothing written yet)
+        let mut stack = alloc::vec![(root, true)];
+
+        while let Some((iter, first)) = stack.last_mut() {
+            match iter.step(memory) {
+                Some((key, item)) => {
+                    out.step(false)?;
+                    if !*first { out.write_char(',')?; }
+                    *first = false;
+                    if let Some(key) = key {
+                        out.write_char('"')?;
+                        write_escaped(&key, out)?;
+                        out.write_str("\":")?;
+                    }
+                    match item.map(|item| (item, NP_Json_Iter::new(&item, memory))) {
+                        Some((_, Some(inner))) => {
+                            out.enter(stack.len())?;
+                            out.write_char(inner.open())?;
+                            stack.push((inner, true));
+                        },
+                        Some((item, None)) => Self::json_write_value(&item, memory, out)?,
+                        None => out.write_str("null")?
+                    }
+                },
+                None => {
+                    out.write_char(iter.close())?;
+                    stack.pop();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // scalars and empty collections
+    fn json_write_value<W: core::fmt::Write>(cursor: &NP_Cursor, memory: &NP_Memory, out: &mut W) -> core::fmt::Result {
+
+        if let NP_TypeKeys::UTF8String = memory.schema[cursor.schema_addr].get_type_key() {
+            if let Ok(Some(value)) = NP_String::into_value(cursor, memory) {
+                out.write_char('"')?;
+                write_escaped(value, out)?;
+                return out.write_char('"');
+            }
+        }
+
+        out.write_str(&NP_Cursor::json_encode(cursor, memory).stringify())
     }
 
     /// Compact from old cursor and memory into new cursor and memory
     /// 
+    /// Values inside collections are copied one at a time from a queue instead of recursively, so deep buffers don't use up the stack.
+    /// 
     pub fn compact(from_cursor: NP_Cursor, from_memory: &NP_Memory, to_cursor: NP_Cursor, to_memory: &NP_Memory) -> Result<NP_Cursor, NP_Error> {
 
+        if from_cursor.get_value(from_memory).get_addr_value() == 0 || !is_collection(&from_memory.schema[from_cursor.schema_addr]) {
+            return Self::compact_value(from_cursor, from_memory, to_cursor, to_memory, &mut Vec::new());
+        }
+
+        let mut pending = Self::compact_start(from_cursor, to_cursor);
+        Self::compact_steps(from_memory, to_memory, &mut pending, usize::MAX)?;
+
+        Ok(to_cursor)
+    }
+
+    /// The queue for a copy of one value, pass it to `compact_steps` to do the copying.
+    /// 
+    pub fn compact_start(from_cursor: NP_Cursor, to_cursor: NP_Cursor) -> Vec<(NP_Cursor, NP_Cursor)> {
+        alloc::vec![(from_cursor, to_cursor)]
+    }
+
+    /// Copy up to `max_items` values from the queue made by `compact_start`, returns true once everything has been copied.
+    /// 
+    /// Collections queue their items as they're copied, so every item counts as a step.  The queue is emptied if a value can't be copied.
+    /// 
+    pub fn compact_steps(from_memory: &NP_Memory, to_memory: &NP_Memory, pending: &mut Vec<(NP_Cursor, NP_Cursor)>, max_items: usize) -> Result<bool, NP_Error> {
+
+        let mut steps = 0;
+
+        while steps < max_items {
+            let (from, to) = match pending.pop() {
+                Some(next) => next,
+                None => break
+            };
+            let queued = pending.len();
+            if let Err(e) = Self::compact_value(from, from_memory, to, to_memory, pending) {
+                pending.clear();
+                return Err(e);
+            }
+            // children were queued in order, copy the first one next
+            pending[queued..].reverse();
+            steps += 1;
+        }
+
+        Ok(pending.is_empty())
+    }
+
+    /// Copy a value inside the collection being copied, values with data wait in `pending` until their parent is done.
+    /// 
+    /// Scalars wait their turn too so values land in the same order a depth first copy would put them.
+    /// 
+    pub fn compact_queue(from_cursor: NP_Cursor, from_memory: &NP_Memory, to_cursor: NP_Cursor, to_memory: &NP_Memory, pending: &mut Vec<(NP_Cursor, NP_Cursor)>) -> Result<NP_Cursor, NP_Error> {
+
+        if from_cursor.get_value(from_memory).get_addr_value() == 0 {
+            return Self::compact_value(from_cursor, from_memory, to_cursor, to_memory, pending);
+        }
+
+        pending.push((from_cursor, to_cursor));
+        Ok(to_cursor)
+    }
+
+    fn compact_value(from_cursor: NP_Cursor, from_memory: &NP_Memory, to_cursor: NP_Cursor, to_memory: &NP_Memory, pending: &mut Vec<(NP_Cursor, NP_Cursor)>) -> Result<NP_Cursor, NP_Error> {
+
         match from_memory.schema[from_cursor.schema_addr].get_type_key() {
-            NP_TypeKeys::Any           => { Ok(to_cursor) }
+            NP_TypeKeys::Any           => {    NP_Dyn::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
             NP_TypeKeys::UTF8String    => { NP_String::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
             NP_TypeKeys::Bytes         => {  NP_Bytes::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
             NP_TypeKeys::Int8          => {        i8::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
@@ -340,11 +701,15 @@ impl NP_Cursor {
             NP_TypeKeys::Ulid          => {  _NP_ULID::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
             NP_TypeKeys::Date          => {   NP_Date::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
             NP_TypeKeys::Enum          => {   NP_Enum::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
-            NP_TypeKeys::Table         => {  NP_Table::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
-            NP_TypeKeys::Map           => {    NP_Map::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
-            NP_TypeKeys::List          => {   NP_List::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
-            NP_TypeKeys::Tuple         => {  NP_Tuple::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
-            _ => { Err(NP_Error::new("unreachable")) }
+            NP_TypeKeys::Tensor        => { NP_Tensor::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
+            NP_TypeKeys::BigInt        => { NP_BigInt::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
+            NP_TypeKeys::Custom        => { NP_Custom::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
+            NP_TypeKeys::Table         => {  NP_Table::compact_items(from_cursor, from_memory, to_cursor, to_memory, pending) }
+            NP_TypeKeys::Map           => {    NP_Map::compact_items(from_cursor, from_memory, to_cursor, to_memory, pending) }
+            NP_TypeKeys::List          => {   NP_List::compact_items(from_cursor, from_memory, to_cursor, to_memory, pending) }
+            NP_TypeKeys::Tuple         => {  NP_Tuple::compact_items(from_cursor, from_memory, to_cursor, to_memory, pending) }
+            NP_TypeKeys::Log           => {    NP_Log::compact_items(from_cursor, from_memory, to_cursor, to_memory, pending) }
+            _ => { Err(NP_Error::new_static("unreachable")) }
         }
     }
 
@@ -353,12 +718,13 @@ impl NP_Cursor {
     pub fn set_default(cursor: NP_Cursor, memory: &NP_Memory) -> Result<(), NP_Error> {
 
         match memory.schema[cursor.schema_addr].get_type_key() {
-            NP_TypeKeys::None        => { return Err(NP_Error::new("unreachable")); },
-            NP_TypeKeys::Any         => { return Err(NP_Error::new("unreachable")); },
-            NP_TypeKeys::Table       => { return Err(NP_Error::new("unreachable")); },
-            NP_TypeKeys::Map         => { return Err(NP_Error::new("unreachable")); },
-            NP_TypeKeys::List        => { return Err(NP_Error::new("unreachable")); },
-            NP_TypeKeys::Tuple       => { return Err(NP_Error::new("unreachable")); },
+            NP_TypeKeys::None        => { return Err(NP_Error::new_static("unreachable")); },
+            NP_TypeKeys::Any         => { return Err(NP_Error::new_static("unreachable")); },
+            NP_TypeKeys::Table       => { return Err(NP_Error::new_static("unreachable")); },
+            NP_TypeKeys::Map         => { return Err(NP_Error::new_static("unreachable")); },
+            NP_TypeKeys::List        => { return Err(NP_Error::new_static("unreachable")); },
+            NP_TypeKeys::Tuple       => { return Err(NP_Error::new_static("unreachable")); },
+            NP_TypeKeys::Log         => { return Err(NP_Error::new_static("unreachable")); },
             NP_TypeKeys::UTF8String  => {  NP_String::set_value(cursor, memory, &String::default())?; },
             NP_TypeKeys::Bytes       => {   NP_Bytes::set_value(cursor, memory, &NP_Bytes::default())?; },
             NP_TypeKeys::Int8        => {         i8::set_value(cursor, memory, i8::default())?; },
@@ -377,7 +743,548 @@ impl NP_Cursor {
             NP_TypeKeys::Uuid        => {   _NP_UUID::set_value(cursor, memory, &NP_UUID::default())?; },
             NP_TypeKeys::Ulid        => {   _NP_ULID::set_value(cursor, memory, &NP_ULID::default())?; },
             NP_TypeKeys::Date        => {    NP_Date::set_value(cursor, memory, NP_Date::default())?; },
-            NP_TypeKeys::Enum        => {    NP_Enum::set_value(cursor, memory, NP_Enum::default())?; }
+            NP_TypeKeys::Enum        => {    NP_Enum::set_value(cursor, memory, NP_Enum::default())?; },
+            NP_TypeKeys::Tensor      => {  NP_Tensor::set_value(cursor, memory, opt_err(NP_Tensor::zeros(&memory.schema[cursor.schema_addr]))?)?; },
+            NP_TypeKeys::BigInt      => {  NP_BigInt::set_value(cursor, memory, NP_BigInt::default())?; },
+            NP_TypeKeys::Custom      => {  NP_Custom::set_value(cursor, memory, NP_Custom::default())?; }
+        }
+
+        Ok(())
+    }
+
+    /// Write the schema default for this value into the buffer, or the data type default if the schema doesn't have one.
+    /// 
+    pub fn set_schema_default(cursor: NP_Cursor, memory: &NP_Memory) -> Result<(), NP_Error> {
+
+        fn schema_default<'value, X: NP_Value<'value>>(cursor: NP_Cursor, memory: &'value NP_Memory) -> Result<bool, NP_Error> {
+            match X::schema_default(&memory.schema[cursor.schema_addr]) {
+                Some(x) => { X::set_value(cursor, memory, x)?; Ok(true) },
+                None => Ok(false)
+            }
+        }
+
+        let has_default = match memory.schema[cursor.schema_addr].get_type_key() {
+            NP_TypeKeys::UTF8String  => { schema_default::<&str>(cursor, memory)? },
+            NP_TypeKeys::Bytes       => { schema_default::<&[u8]>(cursor, memory)? },
+            NP_TypeKeys::Int8        => { schema_default::<i8>(cursor, memory)? },
+            NP_TypeKeys::Int16       => { schema_default::<i16>(cursor, memory)? },
+            NP_TypeKeys::Int32       => { schema_default::<i32>(cursor, memory)? },
+            NP_TypeKeys::Int64       => { schema_default::<i64>(cursor, memory)? },
+            NP_TypeKeys::Uint8       => { schema_default::<u8>(cursor, memory)? },
+            NP_TypeKeys::Uint16      => { schema_default::<u16>(cursor, memory)? },
+            NP_TypeKeys::Uint32      => { schema_default::<u32>(cursor, memory)? },
+            NP_TypeKeys::Uint64      => { schema_default::<u64>(cursor, memory)? },
+            NP_TypeKeys::Float       => { schema_default::<f32>(cursor, memory)? },
+            NP_TypeKeys::Double      => { schema_default::<f64>(cursor, memory)? },
+            NP_TypeKeys::Decimal     => { schema_default::<NP_Dec>(cursor, memory)? },
+            NP_TypeKeys::Boolean     => { schema_default::<bool>(cursor, memory)? },
+            NP_TypeKeys::Geo         => { schema_default::<NP_Geo>(cursor, memory)? },
+            NP_TypeKeys::Date        => { schema_default::<NP_Date>(cursor, memory)? },
+            NP_TypeKeys::Enum        => { schema_default::<NP_Enum>(cursor, memory)? },
+            NP_TypeKeys::BigInt      => { schema_default::<NP_BigInt>(cursor, memory)? },
+            _ => false
+        };
+
+        if has_default == false {
+            NP_Cursor::set_default(cursor, memory)?;
+        }
+
+        Ok(())
+    }
+
+    /// Set this value and all it's descendants from a JSON value.
+    /// 
+    /// The JSON uses the same layout `json_encode` produces.  `null` clears the value, or sets the type default inside sortable tuples.
+    /// 
+    pub fn set_from_json(cursor: NP_Cursor, memory: &NP_Memory, json: &NP_JSON) -> Result<(), NP_Error> {
+
+        let sortable = match &memory.schema[cursor.parent_schema_addr] {
+            NP_Parsed_Schema::Tuple { sortable, .. } => *sortable,
+            _ => false
+        };
+
+        if let NP_JSON::Null = json {
+            match memory.schema[cursor.schema_addr].get_type_key() {
+                NP_TypeKeys::Table | NP_TypeKeys::Tuple | NP_TypeKeys::List | NP_TypeKeys::Map | NP_TypeKeys::Log if sortable => { },
+                _ if sortable => { NP_Cursor::set_default(cursor, memory)?; },
+                _ => { cursor.get_value_mut(memory).set_addr_value(0); }
+            }
+            return Ok(());
+        }
+
+        let mismatch = || {
+            NP_Error::new(String::from("JSON value doesn't match schema type ") + memory.schema[cursor.schema_addr].get_type_data().0)
+        };
+
+        let int = |min: i64, max: i64| -> Result<i64, NP_Error> {
+            match json {
+                NP_JSON::Integer(x) if *x >= min && *x <= max => Ok(*x),
+                NP_JSON::Integer(_) | NP_JSON::Unsigned(_) => Err(NP_Error::new_static("JSON number out of range for schema type!")),
+                _ => Err(mismatch())
+            }
+        };
+
+        // older versions encoded u64 and date values above i64::MAX as negative numbers, so cast those back the same way
+        let uint = || -> Result<u64, NP_Error> {
+            match json {
+                NP_JSON::Unsigned(x) => Ok(*x),
+                _ => Ok(int(i64::MIN, i64::MAX)? as u64)
+            }
+        };
+
+        match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::None => { return Err(mismatch()); },
+            NP_Parsed_Schema::Any { .. } => {
+                match json {
+                    NP_JSON::True       => { NP_Dyn::set_value(cursor, memory, NP_Dyn::Bool(true))?; },
+                    NP_JSON::False      => { NP_Dyn::set_value(cursor, memory, NP_Dyn::Bool(false))?; },
+                    NP_JSON::Integer(x) => { NP_Dyn::set_value(cursor, memory, NP_Dyn::I64(*x))?; },
+                    NP_JSON::Unsigned(x) => { NP_Dyn::set_value(cursor, memory, NP_Dyn::U64(*x))?; },
+                    NP_JSON::Float(x)   => { NP_Dyn::set_value(cursor, memory, NP_Dyn::F64(*x))?; },
+                    NP_JSON::String(x)  => { NP_Dyn::set_value(cursor, memory, NP_Dyn::Str(x))?; },
+                    NP_JSON::Array(items) => {
+                        let mut bytes: Vec<u8> = Vec::with_capacity(items.len());
+                        for item in items {
+                            match item {
+                                NP_JSON::Integer(x) if *x >= 0 && *x <= 255 => bytes.push(*x as u8),
+                                _ => { return Err(NP_Error::new_static("Any values can only hold arrays of numbers between 0 and 255!")); }
+                            }
+                        }
+                        NP_Dyn::set_value(cursor, memory, NP_Dyn::Bytes(&bytes))?;
+                    },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::UTF8String { .. } => {
+                match json {
+                    NP_JSON::String(x) => { NP_String::set_value(cursor, memory, x)?; },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::Bytes { .. } => {
+                match json {
+                    NP_JSON::Array(items) => {
+                        let mut bytes: Vec<u8> = Vec::with_capacity(items.len());
+                        for item in items {
+                            match item {
+                                NP_JSON::Integer(x) if *x >= 0 && *x <= 255 => bytes.push(*x as u8),
+                                _ => { return Err(NP_Error::new_static("Bytes must be an array of numbers between 0 and 255!")); }
+                            }
+                        }
+                        NP_Bytes::set_value(cursor, memory, &bytes)?;
+                    },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::Int8    { .. } => { i8::set_value(cursor, memory, int(i8::MIN as i64, i8::MAX as i64)? as i8)?; },
+            NP_Parsed_Schema::Int16   { .. } => { i16::set_value(cursor, memory, int(i16::MIN as i64, i16::MAX as i64)? as i16)?; },
+            NP_Parsed_Schema::Int32   { .. } => { i32::set_value(cursor, memory, int(i32::MIN as i64, i32::MAX as i64)? as i32)?; },
+            NP_Parsed_Schema::Int64   { .. } => { i64::set_value(cursor, memory, int(i64::MIN, i64::MAX)?)?; },
+            NP_Parsed_Schema::Uint8   { .. } => { u8::set_value(cursor, memory, int(0, u8::MAX as i64)? as u8)?; },
+            NP_Parsed_Schema::Uint16  { .. } => { u16::set_value(cursor, memory, int(0, u16::MAX as i64)? as u16)?; },
+            NP_Parsed_Schema::Uint32  { .. } => { u32::set_value(cursor, memory, int(0, u32::MAX as i64)? as u32)?; },
+            NP_Parsed_Schema::Uint64  { .. } => { u64::set_value(cursor, memory, uint()?)?; },
+            NP_Parsed_Schema::Float   { non_finite, .. } => { f32::set_value(cursor, memory, json_float(json).or_else(|| named_float(json, *non_finite)).ok_or_else(mismatch)? as f32)?; },
+            NP_Parsed_Schema::Double  { non_finite, .. } => { f64::set_value(cursor, memory, json_float(json).or_else(|| named_float(json, *non_finite)).ok_or_else(mismatch)?)?; },
+            NP_Parsed_Schema::Decimal { exp, .. } => {
+                let value = match json {
+                    NP_JSON::Dictionary(_) => {
+                        match (&json["num"], &json["exp"]) {
+                            (NP_JSON::Integer(num), NP_JSON::Integer(exp)) if *exp >= 0 && *exp <= 255 => NP_Dec::new(*num, *exp as u8),
+                            _ => { return Err(NP_Error::new_static("Decimal objects need integer \"num\" and \"exp\" properties!")); }
+                        }
+                    },
+                    NP_JSON::Integer(x) => NP_Dec::new(*x, 0),
+                    NP_JSON::Float(x) => {
+                        let mut scaled = *x;
+                        for _ in 0..*exp { scaled *= 10f64; }
+                        // round half away from zero
+                        NP_Dec::new(if scaled < 0f64 { scaled - 0.5f64 } else { scaled + 0.5f64 } as i64, *exp)
+                    },
+                    _ => { return Err(mismatch()); }
+                };
+                NP_Dec::set_value(cursor, memory, value)?;
+            },
+            NP_Parsed_Schema::Boolean { .. } => {
+                match json {
+                    NP_JSON::True => { bool::set_value(cursor, memory, true)?; },
+                    NP_JSON::False => { bool::set_value(cursor, memory, false)?; },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::Geo { size, .. } => {
+                match (json_float(&json["lat"]), json_float(&json["lng"])) {
+                    (Some(lat), Some(lng)) => { NP_Geo::set_value(cursor, memory, NP_Geo::new(*size, lat, lng))?; },
+                    _ => { return Err(NP_Error::new_static("Geo values need number \"lat\" and \"lng\" properties!")); }
+                }
+            },
+            NP_Parsed_Schema::Date { .. } => {
+                let value = match json {
+                    NP_JSON::String(x) => NP_Date::from_rfc3339(x).ok_or_else(|| NP_Error::new_static("Dates must be RFC 3339 strings or milliseconds since the unix epoch!"))?,
+                    _ => NP_Date::new(uint()?)
+                };
+                NP_Date::set_value(cursor, memory, value)?;
+            },
+            NP_Parsed_Schema::Enum { .. } => {
+                match json {
+                    NP_JSON::String(x) => { NP_Enum::set_value(cursor, memory, NP_Enum::new(x.as_str()))?; },
+                    NP_JSON::Integer(x) => { NP_Enum::set_value(cursor, memory, option::choice_of_value(&memory.schema[cursor.schema_addr], *x).ok_or_else(|| NP_Error::new_static("Option not found, cannot set uknown option!"))?)?; },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::Uuid { .. } => {
+                match json {
+                    NP_JSON::String(x) => { _NP_UUID::set_value(cursor, memory, &NP_UUID::from_string(x).ok_or_else(mismatch)?)?; },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::Tensor { .. } => {
+                match json {
+                    NP_JSON::Array(items) => { NP_Tensor::set_value(cursor, memory, NP_Tensor::from_json_values(&memory.schema[cursor.schema_addr], items)?)?; },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::BigInt { .. } => {
+                match json {
+                    NP_JSON::String(x) => { NP_BigInt::set_value(cursor, memory, NP_BigInt::from_string(x).ok_or_else(mismatch)?)?; },
+                    NP_JSON::Integer(x) => { NP_BigInt::set_value(cursor, memory, NP_BigInt::from(*x))?; },
+                    NP_JSON::Unsigned(x) => { NP_BigInt::set_value(cursor, memory, NP_BigInt::from(*x))?; },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::Custom { .. } => {
+                NP_Custom::set_from_json(cursor, memory, json)?;
+            },
+            NP_Parsed_Schema::Ulid { .. } => {
+                match json {
+                    NP_JSON::String(x) => { _NP_ULID::set_value(cursor, memory, &NP_ULID::from_string(x).ok_or_else(mismatch)?)?; },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::Table { .. } => {
+                match json {
+                    NP_JSON::Dictionary(object) => {
+                        if sortable == false { cursor.get_value_mut(memory).set_addr_value(0); }
+                        for (key, value) in object.values.iter() {
+                            match NP_Table::select(cursor, key, true, memory)? {
+                                Some(column) => NP_Cursor::set_from_json(column, memory, value)?,
+                                None => { return Err(NP_Error::new(String::from("Column not found in table: ") + key)); }
+                            }
+                        }
+                    },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::Tuple { values, sortable: tuple_sortable, .. } => {
+                match json {
+                    NP_JSON::Array(items) => {
+                        if items.len() > values.len() {
+                            return Err(NP_Error::new_static("Too many values for tuple!"));
+                        }
+                        if *tuple_sortable == false && sortable == false { cursor.get_value_mut(memory).set_addr_value(0); }
+                        for (idx, item) in items.iter().enumerate() {
+                            NP_Cursor::set_from_json(opt_err(NP_Tuple::select(cursor, idx, true, memory)?)?, memory, item)?;
+                        }
+                    },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::List { wide, .. } => {
+                match json {
+                    NP_JSON::Array(items) => {
+                        cursor.get_value_mut(memory).set_addr_value(0);
+                        for (idx, item) in items.iter().enumerate() {
+                            if let NP_JSON::Null = item { continue; }
+                            let list_item = opt_err(opt_err(NP_List::select(cursor, idx, true, memory)?)?.1)?;
+                            NP_Cursor::set_from_json(list_item, memory, item)?;
+                        }
+                        NP_List::restore_order(&cursor, memory)?;
+                    },
+                    NP_JSON::Dictionary(object) if *wide => {
+                        cursor.get_value_mut(memory).set_addr_value(0);
+                        for (key, item) in object.values.iter() {
+                            if let NP_JSON::Null = item { continue; }
+                            let list_item = opt_err(opt_err(NP_List::select(cursor, wide_index(key)?, true, memory)?)?.1)?;
+                            NP_Cursor::set_from_json(list_item, memory, item)?;
+                        }
+                        NP_List::restore_order(&cursor, memory)?;
+                    },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::Map { .. } => {
+                match json {
+                    NP_JSON::Dictionary(object) => {
+                        cursor.get_value_mut(memory).set_addr_value(0);
+                        for (key, value) in object.values.iter() {
+                            if let NP_JSON::Null = value { continue; }
+                            let map_item = NP_Map::select_new(cursor, key, memory)?;
+                            NP_Cursor::set_from_json(map_item, memory, value)?;
+                        }
+                    },
+                    _ => { return Err(mismatch()); }
+                }
+            },
+            NP_Parsed_Schema::Log { .. } => {
+                match json {
+                    NP_JSON::Array(items) => {
+                        cursor.get_value_mut(memory).set_addr_value(0);
+                        for item in items.iter() {
+                            let (_index, log_item) = NP_Log::append(&cursor, memory)?;
+                            if let NP_JSON::Null = item { continue; }
+                            NP_Cursor::set_from_json(log_item, memory, item)?;
+                        }
+                    },
+                    _ => { return Err(mismatch()); }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set a value of a different type than the schema, converting it when nothing is lost.
+    /// 
+    /// Integers that don't fit are handled with the `overflow` policy, or the schema's if it's `None`.  Returns `false` if the value can't be converted into the schema type at all.
+    /// 
+    pub fn set_coerced(cursor: NP_Cursor, memory: &NP_Memory, value: NP_Dyn, overflow: Option<NP_Overflow>, path: &[&str]) -> Result<bool, NP_Error> {
+
+        let schema = &memory.schema[cursor.schema_addr];
+        let overflow = overflow.unwrap_or_else(|| numbers::overflow_of(schema));
+
+        let lossy = || NP_Error::Lossy { expected: schema.get_type_data().0.to_owned(), path: path.iter().map(|p| (*p).to_owned()).collect() };
+        let overflow_error = || NP_Error::Overflow { path: path.iter().map(|p| (*p).to_owned()).collect() };
+
+        // whole numbers of any type, floats only if they have no fraction
+        let whole = || -> Result<Option<i128>, NP_Error> {
+            Ok(match value {
+                NP_Dyn::I8(x) => Some(x as i128),
+                NP_Dyn::I16(x) => Some(x as i128),
+                NP_Dyn::I32(x) => Some(x as i128),
+                NP_Dyn::I64(x) => Some(x as i128),
+                NP_Dyn::U8(x) => Some(x as i128),
+                NP_Dyn::U16(x) => Some(x as i128),
+                NP_Dyn::U32(x) => Some(x as i128),
+                NP_Dyn::U64(x) => Some(x as i128),
+                NP_Dyn::F32(x) if x.is_finite() && x == (x as i128) as f32 => Some(x as i128),
+                NP_Dyn::F64(x) if x.is_finite() && x == (x as i128) as f64 => Some(x as i128),
+                NP_Dyn::F32(_) | NP_Dyn::F64(_) => return Err(lossy()),
+                _ => None
+            })
+        };
+
+        macro_rules! int {
+            ($t:ty) => {
+                match whole()? {
+                    Some(x) if x >= <$t>::MIN as i128 && x <= <$t>::MAX as i128 => { <$t>::set_value(cursor, memory, x as $t)?; },
+                    Some(x) => match overflow {
+                        NP_Overflow::Saturate => { <$t>::set_value(cursor, memory, x.max(<$t>::MIN as i128).min(<$t>::MAX as i128) as $t)?; },
+                        NP_Overflow::Wrap => { <$t>::set_value(cursor, memory, x as $t)?; },
+                        NP_Overflow::Strict => return Err(overflow_error())
+                    },
+                    None => return Ok(false)
+                }
+            }
+        }
+
+        match schema {
+            NP_Parsed_Schema::Any { .. } => { NP_Dyn::set_value(cursor, memory, value)?; },
+            NP_Parsed_Schema::Int8   { .. } => int!(i8),
+            NP_Parsed_Schema::Int16  { .. } => int!(i16),
+            NP_Parsed_Schema::Int32  { .. } => int!(i32),
+            NP_Parsed_Schema::Int64  { .. } => int!(i64),
+            NP_Parsed_Schema::Uint8  { .. } => int!(u8),
+            NP_Parsed_Schema::Uint16 { .. } => int!(u16),
+            NP_Parsed_Schema::Uint32 { .. } => int!(u32),
+            NP_Parsed_Schema::Uint64 { .. } => int!(u64),
+            NP_Parsed_Schema::Float { .. } => {
+                let float = match value {
+                    NP_Dyn::F64(x) if x.is_nan() || x as f32 as f64 == x => x as f32,
+                    NP_Dyn::F64(_) => return Err(lossy()),
+                    _ => match whole()? {
+                        Some(x) if (x as f32) as i128 == x => x as f32,
+                        Some(_) => return Err(lossy()),
+                        None => return Ok(false)
+                    }
+                };
+                f32::set_value(cursor, memory, float)?;
+            },
+            NP_Parsed_Schema::Double { .. } => {
+                let float = match value {
+                    NP_Dyn::F32(x) => x as f64,
+                    _ => match whole()? {
+                        Some(x) if (x as f64) as i128 == x => x as f64,
+                        Some(_) => return Err(lossy()),
+                        None => return Ok(false)
+                    }
+                };
+                f64::set_value(cursor, memory, float)?;
+            },
+            NP_Parsed_Schema::Decimal { exp, .. } => {
+                let scale = 10i128.pow(*exp as u32);
+                let num = match value {
+                    NP_Dyn::F32(_) | NP_Dyn::F64(_) => {
+                        let float = match value { NP_Dyn::F32(x) => x as f64, NP_Dyn::F64(x) => x, _ => 0f64 };
+                        if !float.is_finite() { return Err(lossy()); }
+                        let scaled = float * scale as f64;
+                        // round half away from zero
+                        (if scaled < 0f64 { scaled - 0.5f64 } else { scaled + 0.5f64 }) as i128
+                    },
+                    _ => match whole()? {
+                        Some(x) => x.checked_mul(scale).ok_or_else(overflow_error)?,
+                        None => return Ok(false)
+                    }
+                };
+                if num < i64::MIN as i128 || num > i64::MAX as i128 { return Err(overflow_error()); }
+                let dec = NP_Dec::new(num as i64, *exp);
+                // floats must come back out the same at their own precision
+                match value {
+                    NP_Dyn::F32(x) if dec.to_float() as f32 != x => return Err(lossy()),
+                    NP_Dyn::F64(x) if dec.to_float() != x => return Err(lossy()),
+                    _ => { }
+                }
+                NP_Dec::set_value(cursor, memory, dec)?;
+            },
+            NP_Parsed_Schema::Date { .. } => {
+                let date = match value {
+                    NP_Dyn::Str(x) => NP_Date::from_rfc3339(x).ok_or_else(|| NP_Error::new(String::from("Not an RFC 3339 date: ") + x))?,
+                    _ => match whole()? {
+                        Some(x) if x >= 0 && x <= u64::MAX as i128 => NP_Date::new(x as u64),
+                        Some(_) => return Err(overflow_error()),
+                        None => return Ok(false)
+                    }
+                };
+                NP_Date::set_value(cursor, memory, date)?;
+            },
+            NP_Parsed_Schema::BigInt { .. } => {
+                let big = match value {
+                    NP_Dyn::Str(x) => NP_BigInt::from_string(x).ok_or_else(|| NP_Error::new(String::from("Not a whole number: ") + x))?,
+                    _ => match whole()? {
+                        Some(x) => NP_BigInt::from(x),
+                        None => return Ok(false)
+                    }
+                };
+                NP_BigInt::set_value(cursor, memory, big)?;
+            },
+            NP_Parsed_Schema::Enum { .. } => {
+                match value {
+                    NP_Dyn::Str(x) => { NP_Enum::set_value(cursor, memory, NP_Enum::new(x))?; },
+                    _ => match whole()? {
+                        Some(x) => {
+                            // numbered choices, see the option module
+                            let choice = if x >= i64::MIN as i128 && x <= i64::MAX as i128 { option::choice_of_value(schema, x as i64) } else { None };
+                            NP_Enum::set_value(cursor, memory, choice.ok_or_else(|| NP_Error::new_static("Option not found, cannot set uknown option!"))?)?;
+                        },
+                        None => return Ok(false)
+                    }
+                }
+            },
+            NP_Parsed_Schema::Uuid { .. } => {
+                match value {
+                    NP_Dyn::Str(x) => { _NP_UUID::set_value(cursor, memory, &NP_UUID::from_string(x).ok_or_else(|| NP_Error::new(String::from("Not a UUID: ") + x))?)?; },
+                    _ => return Ok(false)
+                }
+            },
+            NP_Parsed_Schema::Ulid { .. } => {
+                match value {
+                    NP_Dyn::Str(x) => { _NP_ULID::set_value(cursor, memory, &NP_ULID::from_string(x).ok_or_else(|| NP_Error::new(String::from("Not a ULID: ") + x))?)?; },
+                    _ => return Ok(false)
+                }
+            },
+            _ => return Ok(false)
+        }
+
+        Ok(true)
+    }
+
+    /// Same as `set_from_json`, but reads the JSON from a parser as it goes.  Collections are written as their items are parsed, only scalar values are parsed into an `NP_JSON` first.
+    /// 
+    pub(crate) fn set_from_parser<S: JSON_Source>(cursor: NP_Cursor, memory: &NP_Memory, parser: &mut JSON_Parser<S>) -> Result<(), NP_Error> {
+
+        let sortable = match &memory.schema[cursor.parent_schema_addr] {
+            NP_Parsed_Schema::Tuple { sortable, .. } => *sortable,
+            _ => false
+        };
+
+        parser.whitespace();
+
+        match (&memory.schema[cursor.schema_addr], parser.peek()) {
+            (NP_Parsed_Schema::Table { .. }, Some(b'{')) => {
+                parser.enter()?;
+                if !sortable { cursor.get_value_mut(memory).set_addr_value(0); }
+                let mut first = true;
+                while parser.more(b'}', &mut first)? {
+                    let key = parser.key()?;
+                    match NP_Table::select(cursor, &key, true, memory)? {
+                        Some(column) => NP_Cursor::set_from_parser(column, memory, parser)?,
+                        None => { return Err(NP_Error::new(String::from("Column not found in table: ") + &key)); }
+                    }
+                }
+            },
+            (NP_Parsed_Schema::Tuple { values, sortable: tuple_sortable, .. }, Some(b'[')) => {
+                parser.enter()?;
+                if !*tuple_sortable && !sortable { cursor.get_value_mut(memory).set_addr_value(0); }
+                let mut first = true;
+                let mut idx = 0;
+                while parser.more(b']', &mut first)? {
+                    if idx >= values.len() {
+                        return Err(NP_Error::new_static("Too many values for tuple!"));
+                    }
+                    NP_Cursor::set_from_parser(opt_err(NP_Tuple::select(cursor, idx, true, memory)?)?, memory, parser)?;
+                    idx += 1;
+                }
+            },
+            (NP_Parsed_Schema::List { .. }, Some(b'[')) => {
+                parser.enter()?;
+                cursor.get_value_mut(memory).set_addr_value(0);
+                let mut first = true;
+                let mut idx = 0;
+                while parser.more(b']', &mut first)? {
+                    if !parser.null()? {
+                        let list_item = opt_err(opt_err(NP_List::select(cursor, idx, true, memory)?)?.1)?;
+                        NP_Cursor::set_from_parser(list_item, memory, parser)?;
+                    }
+                    idx += 1;
+                }
+                NP_List::restore_order(&cursor, memory)?;
+            },
+            (NP_Parsed_Schema::List { wide: true, .. }, Some(b'{')) => {
+                parser.enter()?;
+                cursor.get_value_mut(memory).set_addr_value(0);
+                let mut first = true;
+                while parser.more(b'}', &mut first)? {
+                    let index = wide_index(&parser.key()?)?;
+                    if parser.null()? { continue; }
+                    let list_item = opt_err(opt_err(NP_List::select(cursor, index, true, memory)?)?.1)?;
+                    NP_Cursor::set_from_parser(list_item, memory, parser)?;
+                }
+                NP_List::restore_order(&cursor, memory)?;
+            },
+            (NP_Parsed_Schema::Map { .. }, Some(b'{')) => {
+                parser.enter()?;
+                cursor.get_value_mut(memory).set_addr_value(0);
+                let mut first = true;
+                while parser.more(b'}', &mut first)? {
+                    let key = parser.key()?;
+                    if parser.null()? { continue; }
+                    let map_item = NP_Map::select_new(cursor, &key, memory)?;
+                    NP_Cursor::set_from_parser(map_item, memory, parser)?;
+                }
+            },
+            (NP_Parsed_Schema::Log { .. }, Some(b'[')) => {
+                parser.enter()?;
+                cursor.get_value_mut(memory).set_addr_value(0);
+                let mut first = true;
+                while parser.more(b']', &mut first)? {
+                    let (_index, log_item) = NP_Log::append(&cursor, memory)?;
+                    if parser.null()? { continue; }
+                    NP_Cursor::set_from_parser(log_item, memory, parser)?;
+                }
+            },
+            _ => {
+                let value = parser.value()?;
+                NP_Cursor::set_from_json(cursor, memory, &value)?;
+            }
         }
 
         Ok(())
@@ -385,6 +1292,8 @@ impl NP_Cursor {
 
     /// Calculate the number of bytes used by this pointer and it's descendants.
     /// 
+    /// Collections are measured one at a time from a queue instead of recursively, so deep buffers don't use up the stack.
+    /// 
     pub fn calc_size(cursor: &NP_Cursor, memory: &NP_Memory) -> Result<usize, NP_Error> {
         
         let value = cursor.get_value(&memory);
@@ -397,10 +1306,44 @@ impl NP_Cursor {
             return Ok(base_size);
         }
 
-        // get the size of the value based on schema
-        let type_size = match memory.schema[cursor.schema_addr].get_type_key() {
+        if !is_collection(&memory.schema[cursor.schema_addr]) {
+            return Ok(Self::value_size(cursor, memory, &mut Vec::new())? + base_size);
+        }
+
+        let mut pending = alloc::vec![*cursor];
+        let mut total = base_size;
+
+        while let Some(next) = pending.pop() {
+            total += Self::value_size(&next, memory, &mut pending)?;
+        }
+
+        Ok(total)
+    }
+
+    /// Size of a value inside the collection being measured, collections with data are added to `pending` and measured once their parent is done.
+    /// 
+    pub fn size_queue(cursor: &NP_Cursor, memory: &NP_Memory, pending: &mut Vec<NP_Cursor>) -> Result<usize, NP_Error> {
+
+        let value = cursor.get_value(memory);
+        let base_size = value.get_size();
+
+        if value.get_addr_value() == 0 {
+            return Ok(base_size);
+        }
+
+        if !is_collection(&memory.schema[cursor.schema_addr]) {
+            return Ok(Self::value_size(cursor, memory, pending)? + base_size);
+        }
+
+        pending.push(*cursor);
+        Ok(base_size)
+    }
+
+    // bytes used by the value of a pointer, not counting the pointer
+    fn value_size(cursor: &NP_Cursor, memory: &NP_Memory, pending: &mut Vec<NP_Cursor>) -> Result<usize, NP_Error> {
+        match memory.schema[cursor.schema_addr].get_type_key() {
             NP_TypeKeys::None         => { Ok(0) },
-            NP_TypeKeys::Any          => { Ok(0) },
+            NP_TypeKeys::Any          => {    NP_Dyn::get_size(cursor, memory) },
             NP_TypeKeys::UTF8String   => { NP_String::get_size(cursor, memory) },
             NP_TypeKeys::Bytes        => {  NP_Bytes::get_size(cursor, memory) },
             NP_TypeKeys::Int8         => {        i8::get_size(cursor, memory) },
@@ -420,32 +1363,226 @@ impl NP_Cursor {
             NP_TypeKeys::Ulid         => {  _NP_ULID::get_size(cursor, memory) },
             NP_TypeKeys::Date         => {   NP_Date::get_size(cursor, memory) },
             NP_TypeKeys::Enum         => {   NP_Enum::get_size(cursor, memory) },
-            NP_TypeKeys::Table        => {  NP_Table::get_size(cursor, memory) },
-            NP_TypeKeys::Map          => {    NP_Map::get_size(cursor, memory) },
-            NP_TypeKeys::List         => {   NP_List::get_size(cursor, memory) },
-            NP_TypeKeys::Tuple        => {  NP_Tuple::get_size(cursor, memory) }
-        }?;
+            NP_TypeKeys::Table        => {  NP_Table::size_items(cursor, memory, pending) },
+            NP_TypeKeys::Map          => {    NP_Map::size_items(cursor, memory, pending) },
+            NP_TypeKeys::List         => {   NP_List::size_items(cursor, memory, pending) },
+            NP_TypeKeys::Tuple        => {  NP_Tuple::size_items(cursor, memory, pending) },
+            NP_TypeKeys::Log          => {    NP_Log::size_items(cursor, memory, pending) },
+            NP_TypeKeys::Tensor       => { NP_Tensor::get_size(cursor, memory) },
+            NP_TypeKeys::BigInt       => { NP_BigInt::get_size(cursor, memory) },
+            NP_TypeKeys::Custom       => { NP_Custom::get_size(cursor, memory) }
+        }
+    }
+}
+
+
+// types that hold other values
+fn is_collection(schema: &NP_Parsed_Schema) -> bool {
+    matches!(schema, NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::Tuple { .. } | NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Map { .. } | NP_Parsed_Schema::Log { .. })
+}
+
+// steps through the items of any collection while it's written as JSON, tables and maps have keys
+enum NP_Json_Iter<'iter> {
+    Table(NP_Table<'iter>),
+    Tuple(NP_Tuple<'iter>),
+    List(NP_List),
+    // wide lists are encoded as objects keyed by index, so gaps between items are left out
+    WideList(NP_List),
+    Map(NP_Map<'iter>),
+    Log(NP_Log)
+}
 
-        Ok(type_size + base_size)
+impl<'iter> NP_Json_Iter<'iter> {
+
+    // `None` for scalars and collections without a value
+    fn new(cursor: &NP_Cursor, memory: &'iter NP_Memory<'iter>) -> Option<Self> {
+        if !is_collection(&memory.schema[cursor.schema_addr]) || cursor.get_value(memory).get_addr_value() == 0 {
+            return None;
+        }
+        match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Table { .. } => Some(NP_Json_Iter::Table(NP_Table::new_iter(cursor, memory))),
+            NP_Parsed_Schema::Tuple { .. } => Some(NP_Json_Iter::Tuple(NP_Tuple::new_iter(cursor, memory))),
+            NP_Parsed_Schema::List { wide: true, .. } => Some(NP_Json_Iter::WideList(NP_List::new_iter(cursor, memory, true, 0))),
+            NP_Parsed_Schema::List { .. } => Some(NP_Json_Iter::List(NP_List::new_iter(cursor, memory, false, 0))),
+            NP_Parsed_Schema::Map { .. } => Some(NP_Json_Iter::Map(NP_Map::new_iter(cursor, memory))),
+            _ => Some(NP_Json_Iter::Log(NP_Log::new_iter(cursor, memory)))
+        }
     }
+
+    fn is_object(&self) -> bool {
+        matches!(self, NP_Json_Iter::Table(_) | NP_Json_Iter::WideList(_) | NP_Json_Iter::Map(_))
+    }
+
+    fn open(&self) -> char {
+        if self.is_object() { '{' } else { '[' }
+    }
+
+    fn close(&self) -> char {
+        if self.is_object() { '}' } else { ']' }
+    }
+
+    fn empty(&self) -> NP_JSON {
+        if self.is_object() { NP_JSON::Dictionary(JSMAP::new()) } else { NP_JSON::Array(Vec::new()) }
+    }
+
+    // the next item and its key, `None` items are unset
+    fn step(&mut self, memory: &'iter NP_Memory<'iter>) -> Option<(Option<Cow<'iter, str>>, Option<NP_Cursor>)> {
+        match self {
+            NP_Json_Iter::Table(table) => table.step_iter(memory).map(|(_index, key, item)| (Some(Cow::Borrowed(key)), item)),
+            NP_Json_Iter::Tuple(tuple) => tuple.step_iter(memory).map(|(_index, item)| (None, item)),
+            NP_Json_Iter::List(list) => list.step_iter(memory).map(|(_index, item)| (None, item)),
+            NP_Json_Iter::WideList(list) => list.step_iter(memory).map(|(index, item)| (Some(Cow::Owned(index.to_string())), item)),
+            NP_Json_Iter::Map(map) => map.step_iter(memory).map(|(key, item)| (Some(key), Some(item))),
+            NP_Json_Iter::Log(log) => log.step_iter(memory).map(|(_index, item)| (None, Some(item)))
+        }
+    }
+}
+
+// JSON text and the work done making it, checked against `NP_Limits` as encoding goes
+struct NP_Json_Budget<'out, W: core::fmt::Write> {
+    out: &'out mut W,
+    limits: NP_Limits,
+    // collections above the value being encoded
+    depth: usize,
+    steps: usize,
+    written: usize,
+    error: Option<NP_Error>
 }
 
+impl<'out, W: core::fmt::Write> NP_Json_Budget<'out, W> {
+
+    fn new(out: &'out mut W, limits: NP_Limits, depth: usize) -> Self {
+        NP_Json_Budget { out, limits, depth, steps: 0, written: 0, error: None }
+    }
+
+    fn exceeded(&mut self, limit: &str, max: usize) -> core::fmt::Result {
+        self.error = Some(NP_Error::new_limit(limit, max));
+        Err(core::fmt::Error)
+    }
+
+    // a collection with items is opened `nested` collections below the cursor
+    fn enter(&mut self, nested: usize) -> core::fmt::Result {
+        if self.depth.saturating_add(nested) >= self.limits.max_depth {
+            return self.exceeded("max_depth", self.limits.max_depth);
+        }
+        Ok(())
+    }
+
+    // one collection item, `comma` when it follows another item in `json_encode`
+    fn step(&mut self, comma: bool) -> core::fmt::Result {
+        self.steps += 1;
+        if self.steps > self.limits.max_steps {
+            return self.exceeded("max_steps", self.limits.max_steps);
+        }
+        if comma { self.count_str(",")?; }
+        Ok(())
+    }
+
+    // `json_encode` doesn't write text, it only counts how long the text would be
+    fn counts_size(&self) -> bool {
+        self.limits.max_json_size != usize::MAX
+    }
+
+    fn count_str(&mut self, s: &str) -> core::fmt::Result {
+        if self.counts_size() { self.write_str(s)?; }
+        Ok(())
+    }
+
+    fn count_key(&mut self, key: &str) -> core::fmt::Result {
+        if self.counts_size() {
+            self.write_str("\"\":")?;
+            write_escaped(key, self)?;
+        }
+        Ok(())
+    }
+
+    fn count(&mut self, json: &NP_JSON) -> core::fmt::Result {
+        if self.counts_size() { self.write_str(&json.stringify())?; }
+        Ok(())
+    }
+
+    fn into_error(self) -> NP_Error {
+        self.error.unwrap_or_else(|| NP_Error::new_static("Failed to write JSON!"))
+    }
+}
+
+impl<'out, W: core::fmt::Write> core::fmt::Write for NP_Json_Budget<'out, W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.written = self.written.saturating_add(s.len());
+        if self.written > self.limits.max_json_size {
+            return self.exceeded("max_json_size", self.limits.max_json_size);
+        }
+        self.out.write_str(s)
+    }
+}
+
+// `json_encode` builds `NP_JSON` instead of text, the text it would make is only counted
+struct NP_Null_Writer;
+
+impl core::fmt::Write for NP_Null_Writer {
+    fn write_str(&mut self, _s: &str) -> core::fmt::Result {
+        Ok(())
+    }
+}
+
+// add an item to an encoded table, map or array
+fn json_append(json: &mut NP_JSON, key: Option<String>, value: NP_JSON) {
+    match json {
+        NP_JSON::Dictionary(map) => { map.insert(key.unwrap_or_default(), value); },
+        NP_JSON::Array(list) => list.push(value),
+        _ => { }
+    }
+}
+
+// keys of a wide list encoded as a JSON object
+fn wide_index(key: &str) -> Result<usize, NP_Error> {
+    match key.parse::<u32>() {
+        Ok(index) => Ok(index as usize),
+        Err(_) => Err(NP_Error::new(alloc::format!("Wide list keys must be indexes, found {:?}!", key)))
+    }
+}
+
+// JSON numbers parse as integers when they have no decimal point
+fn json_float(json: &NP_JSON) -> Option<f64> {
+    match json {
+        NP_JSON::Float(x) => Some(*x),
+        NP_JSON::Integer(x) => Some(*x as f64),
+        NP_JSON::Unsigned(x) => Some(*x as f64),
+        _ => None
+    }
+}
+
+// NaN and infinity are strings in JSON when the schema asks for it
+fn named_float(json: &NP_JSON, non_finite: NP_NonFinite) -> Option<f64> {
+    match (json, non_finite) {
+        (NP_JSON::String(x), NP_NonFinite::String) if x == "NaN" => Some(f64::NAN),
+        (NP_JSON::String(x), NP_NonFinite::String) if x == "Infinity" => Some(f64::INFINITY),
+        (NP_JSON::String(x), NP_NonFinite::String) if x == "-Infinity" => Some(f64::NEG_INFINITY),
+        _ => None
+    }
+}
 
 /// This trait is used to restrict which types can be set/get in the buffer
 pub trait NP_Scalar {}
 
 /// This trait is used to implement types as NoProto buffer types.
 /// This includes all the type data, encoding and decoding methods.
+/// 
+/// It works directly on buffer internals and changes between versions, types from outside NoProto should implement `NP_Plugin_Value` from the [custom](custom/index.html) module instead.
 #[doc(hidden)]
 pub trait NP_Value<'value> {
 
     /// Get the type information for this type (static)
     /// 
-    fn type_idx() -> (&'value str, NP_TypeKeys);
+    fn type_idx() -> (&'static str, NP_TypeKeys);
 
     /// Get the type information for this type (instance)
     /// 
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys);
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys);
+
+    /// This value as a dynamic scalar, used to convert it when it's set into a different schema type
+    /// 
+    fn as_dyn(&self) -> Option<NP_Dyn<'_>> { None }
 
     /// Convert the schema byte array for this type into JSON
     /// 
@@ -505,4 +1642,432 @@ pub trait NP_Value<'value> {
 /*
 // unsigned integer size:        0 to (2^i) -1
 //   signed integer size: -2^(i-1) to  2^(i-1) 
-*/
\ No newline at end of file
+*/
+#[test]
+fn set_from_json_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["string", {"type": "string"}],
+        ["bytes",  {"type": "bytes"}],
+        ["i8",     {"type": "i8"}],
+        ["u64",    {"type": "u64"}],
+        ["f32",    {"type": "float"}],
+        ["dec",    {"type": "decimal", "exp": 2}],
+        ["bool",   {"type": "bool"}],
+        ["date",   {"type": "date"}],
+        ["option", {"type": "option", "choices": ["red", "blue"]}],
+        ["uuid",   {"type": "uuid"}],
+        ["ulid",   {"type": "ulid"}],
+        ["list",   {"type": "list", "of": {"type": "string"}}],
+        ["map",    {"type": "map", "value": {"type": "u8"}}],
+        ["tuple",  {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}],
+        ["geo",    {"type": "geo16"}]
+    ]}"#)?;
+
+    let mut rand = crate::utils::Rand::new(2852);
+
+    // every generated buffer survives a trip through JSON
+    for _ in 0..200 {
+        let len = rand.gen_range(0, 512) as usize;
+        let entropy: Vec<u8> = (0..len).map(|_| rand.gen_range(0, 255) as u8).collect();
+
+        let mut buffer = factory.arbitrary_buffer(&entropy)?;
+        // geo values are truncated when set, so they don't always survive the trip
+        buffer.del(&["geo"])?;
+        let json = buffer.json_encode(&[])?.stringify();
+
+        let mut from_json = factory.empty_buffer(None);
+        from_json.set_with_json(&[], &json)?;
+        assert!(buffer.semantic_eq(&from_json), "{}", json);
+    }
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set_with_json(&["dec"], "3.456")?;
+    assert_eq!(buffer.get::<NP_Dec>(&["dec"])?, Some(NP_Dec::new(346, 2)));
+    buffer.set_with_json(&["geo"], r#"{"lat": 1.5, "lng": -2.25}"#)?;
+    assert_eq!(buffer.json_encode(&["geo"])?.stringify(), r#"{"lat":1.5,"lng":-2.25}"#);
+    buffer.set_with_json(&["date"], r#""2020-11-12T00:56:20.392Z""#)?;
+    assert_eq!(buffer.get::<NP_Date>(&["date"])?, Some(NP_Date::new(1605142580392)));
+    buffer.set_with_json(&["list"], r#"["a", null, "c"]"#)?;
+    assert_eq!(buffer.json_encode(&["list"])?.stringify(), r#"["a",null,"c"]"#);
+    buffer.set_with_json(&["list"], r#"["b"]"#)?;
+    assert_eq!(buffer.json_encode(&["list"])?.stringify(), r#"["b"]"#);
+
+    assert!(buffer.set_with_json(&["i8"], "200").is_err());
+    assert!(buffer.set_with_json(&["option"], r#""green""#).is_err());
+    assert!(buffer.set_with_json(&["bool"], "1").is_err());
+    assert!(buffer.set_with_json(&[], r#"{"nope": 1}"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn set_coerced_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["i8",     {"type": "i8"}],
+        ["u64",    {"type": "u64"}],
+        ["f32",    {"type": "float"}],
+        ["f64",    {"type": "double"}],
+        ["dec",    {"type": "decimal", "exp": 2}],
+        ["date",   {"type": "date"}],
+        ["big",    {"type": "bigint"}],
+        ["option", {"type": "option", "choices": ["red", "blue"]}],
+        ["uuid",   {"type": "uuid"}],
+        ["any",    {"type": "any"}],
+        ["string", {"type": "string"}]
+    ]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+
+    buffer.set(&["i8"], -5i64)?;
+    buffer.set(&["u64"], 7u8)?;
+    buffer.set(&["f32"], 3i32)?;
+    buffer.set(&["f64"], 1.5f32)?;
+    buffer.set(&["dec"], 12u16)?;
+    buffer.set(&["date"], "1970-01-02T00:00:00+01:00")?;
+    buffer.set(&["big"], "123456789012345678901234567890")?;
+    buffer.set(&["option"], "blue")?;
+    buffer.set(&["uuid"], "67E55044-10B1-426F-9247-BB680E5FE0C8")?;
+    buffer.set(&["any"], 9u16)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"{"i8":-5,"u64":7,"f32":3,"f64":1.5,"dec":{"num":1200,"exp":2},"date":82800000,"big":"123456789012345678901234567890","option":"blue","uuid":"67E55044-10B1-426F-9247-BB680E5FE0C8","any":9,"string":null}"#);
+
+    buffer.set(&["dec"], 0.1f32)?;
+    assert_eq!(buffer.get::<NP_Dec>(&["dec"])?, Some(NP_Dec::new(10, 2)));
+    buffer.set(&["i8"], 4.0f64)?;
+    assert_eq!(buffer.get::<i8>(&["i8"])?, Some(4));
+
+    // out of range, lossy and unconvertable values leave the old value alone
+    assert_eq!(buffer.set(&["i8"], 200u8), Err(NP_Error::Overflow { path: alloc::vec!["i8".to_owned()] }));
+    assert_eq!(buffer.set(&["u64"], -1i8), Err(NP_Error::Overflow { path: alloc::vec!["u64".to_owned()] }));
+    assert_eq!(buffer.set(&["i8"], 4.5f32), Err(NP_Error::Lossy { expected: "int8".to_owned(), path: alloc::vec!["i8".to_owned()] }));
+    assert_eq!(buffer.set(&["f32"], 0.1f64), Err(NP_Error::Lossy { expected: "float".to_owned(), path: alloc::vec!["f32".to_owned()] }));
+    assert_eq!(buffer.set(&["f32"], u64::MAX - 1), Err(NP_Error::Lossy { expected: "float".to_owned(), path: alloc::vec!["f32".to_owned()] }));
+    assert_eq!(buffer.set(&["dec"], 0.125f64), Err(NP_Error::Lossy { expected: "decimal".to_owned(), path: alloc::vec!["dec".to_owned()] }));
+    assert_eq!(buffer.set(&["dec"], i64::MAX), Err(NP_Error::Overflow { path: alloc::vec!["dec".to_owned()] }));
+    assert!(buffer.set(&["date"], "yesterday").is_err());
+    assert!(buffer.set(&["option"], 2u8).is_err());
+    assert_eq!(buffer.set(&["string"], 1u8), Err(NP_Error::new_type_mismatch("string", "uint8", &["string"])));
+    assert_eq!(buffer.get::<i8>(&["i8"])?, Some(4));
+    assert_eq!(buffer.get::<NP_Dec>(&["dec"])?, Some(NP_Dec::new(10, 2)));
+
+    Ok(())
+}
+
+#[test]
+fn set_schema_default_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["dec",    {"type": "decimal", "exp": 2, "default": 2.5}],
+        ["option", {"type": "option", "choices": ["red", "blue"], "default": "blue"}],
+        ["bytes",  {"type": "bytes", "default": [1, 2, 3]}],
+        ["uuid",   {"type": "uuid"}]
+    ]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    for column in &["dec", "option", "bytes", "uuid"] {
+        buffer.reset(&[column])?;
+    }
+    assert_eq!(buffer.get_state::<NP_Dec>(&["dec"])?, crate::buffer::NP_ValueState::Set(NP_Dec::new(250, 2)));
+    assert_eq!(buffer.get_state::<NP_Enum>(&["option"])?, crate::buffer::NP_ValueState::Set(NP_Enum::new("blue")));
+    assert_eq!(buffer.get::<&[u8]>(&["bytes"])?, Some(&[1u8, 2, 3][..]));
+    assert_eq!(buffer.get::<&NP_UUID>(&["uuid"])?, Some(&NP_UUID::default()));
+    assert!(buffer.reset(&[]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn scalar_roots_work() -> Result<(), NP_Error> {
+    use crate::buffer::NP_ValueState;
+
+    let uuid = NP_UUID::generate(2879);
+    let ulid = NP_ULID::generate(1606680515909, 2879);
+    let bytes = [1u8, 2, 3];
+
+    macro_rules! check_root {
+        ($schema: expr, $t: ty, $value: expr) => {{
+            let factory = crate::NP_Factory::new($schema)?;
+            let mut buffer = factory.empty_buffer(None);
+            assert_eq!(buffer.get_state::<$t>(&[])?, NP_ValueState::Unset);
+            buffer.set_root::<$t>($value)?;
+            assert_eq!(buffer.get_root::<$t>()?, Some($value));
+            assert_eq!(buffer.get_state::<$t>(&[])?, NP_ValueState::Set($value));
+            buffer.validate()?;
+
+            // the same value comes back through JSON
+            let json = buffer.json_encode(&[])?.stringify();
+            let mut from_json = factory.empty_buffer(None);
+            from_json.set_with_json(&[], &json)?;
+            assert!(from_json.semantic_eq(&buffer));
+
+            buffer.compact(None)?;
+            assert_eq!(buffer.get_root::<$t>()?, Some($value));
+            assert_eq!(buffer.calc_bytes()?.wasted_bytes, 0);
+
+            let opened = factory.open_checked_buffer(buffer.close())?;
+            assert_eq!(opened.get_root::<$t>()?, Some($value));
+            assert_eq!(opened.json_encode(&[])?.stringify(), json);
+
+            let mut opened = factory.open_buffer(opened.close());
+            opened.del(&[])?;
+            assert_eq!(opened.get_root::<$t>()?, None);
+        }}
+    }
+
+    check_root!(r#"{"type": "string"}"#, &str, "hello");
+    check_root!(r#"{"type": "string", "size": 8}"#, &str, "hello   ");
+    check_root!(r#"{"type": "bytes"}"#, &[u8], &bytes[..]);
+    check_root!(r#"{"type": "bytes", "size": 3}"#, &[u8], &bytes[..]);
+    check_root!(r#"{"type": "i8"}"#, i8, -8i8);
+    check_root!(r#"{"type": "i16"}"#, i16, -16i16);
+    check_root!(r#"{"type": "i32"}"#, i32, -32i32);
+    check_root!(r#"{"type": "i64"}"#, i64, -64i64);
+    check_root!(r#"{"type": "u8"}"#, u8, 8u8);
+    check_root!(r#"{"type": "u16"}"#, u16, 16u16);
+    check_root!(r#"{"type": "u32"}"#, u32, 32u32);
+    check_root!(r#"{"type": "u64"}"#, u64, 64u64);
+    check_root!(r#"{"type": "float"}"#, f32, 2.5f32);
+    check_root!(r#"{"type": "double"}"#, f64, -2.5f64);
+    check_root!(r#"{"type": "decimal", "exp": 2}"#, NP_Dec, NP_Dec::new(12345, 2));
+    check_root!(r#"{"type": "bool"}"#, bool, true);
+    check_root!(r#"{"type": "geo4"}"#, NP_Geo, NP_Geo::new(4, 45.5, -122.71));
+    check_root!(r#"{"type": "geo8"}"#, NP_Geo, NP_Geo::new(8, 45.5096, -122.7146));
+    check_root!(r#"{"type": "geo16"}"#, NP_Geo, NP_Geo::new(16, 45.509616, -122.714625));
+    check_root!(r#"{"type": "uuid"}"#, &NP_UUID, &uuid);
+    check_root!(r#"{"type": "ulid"}"#, &NP_ULID, &ulid);
+    check_root!(r#"{"type": "date"}"#, NP_Date, NP_Date::new(1604965249484));
+    check_root!(r#"{"type": "option", "choices": ["red", "blue"]}"#, NP_Enum, NP_Enum::new("blue"));
+    check_root!(r#"{"type": "bigint"}"#, NP_BigInt, NP_BigInt::from(-123456789i64));
+    check_root!(r#"{"type": "tensor", "dtype": "f32", "shape": [2, 2]}"#, NP_Tensor, NP_Tensor::new(&[2, 2], &[1.0f32, 2.0, 3.0, 4.0])?);
+    check_root!(r#"{"type": "string", "size": 8, "collation": "unicode_ci"}"#, &str, "Hello   ");
+
+    // roots of the wrong type are errors unless they convert without loss
+    let factory = crate::NP_Factory::new(r#"{"type": "u8"}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    assert!(buffer.set_root(800u16).is_err());
+    assert!(buffer.set_root("8").is_err());
+    buffer.set_root(8u16)?;
+    assert_eq!(buffer.get_root::<u8>()?, Some(8));
+    assert!(buffer.get_root::<&str>().is_err());
+
+    // the cursor doesn't change where the root is
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["0"], "hello")?;
+    buffer.move_cursor(&["0"])?;
+    assert!(buffer.get_root::<&str>().is_err());
+    assert_eq!(buffer.get::<&str>(&[])?, Some("hello"));
+
+    Ok(())
+}
+
+#[test]
+fn json_write_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["string", {"type": "string", "default": "hi"}],
+        ["u64",    {"type": "u64"}],
+        ["double", {"type": "double"}],
+        ["any",    {"type": "any"}],
+        ["list",   {"type": "list", "of": {"type": "table", "columns": [["name", {"type": "string"}], ["tags", {"type": "list", "of": {"type": "string"}}]]}}],
+        ["map",    {"type": "map", "value": {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}}],
+        ["log",    {"type": "log", "of": {"type": "i32"}}],
+        ["tensor", {"type": "tensor", "dtype": "i16", "shape": [2]}]
+    ]}"#)?;
+
+    let write = |buffer: &crate::buffer::NP_Buffer, path: &[&str]| -> Result<String, NP_Error> {
+        let mut json = String::new();
+        buffer.json_write_fmt(path, &mut json)?;
+        assert_eq!(json, buffer.json_encode(path)?.stringify());
+        Ok(json)
+    };
+
+    // defaults and empty values
+    let mut new_buffer = factory.empty_buffer(None);
+    assert_eq!(write(&new_buffer, &[])?, "null");
+    new_buffer.set(&["u64"], u64::MAX)?;
+    assert_eq!(write(&new_buffer, &[])?, r#"{"string":"hi","u64":18446744073709551615,"double":null,"any":null,"list":null,"map":null,"log":null,"tensor":null}"#);
+    assert_eq!(write(&new_buffer, &["double"])?, "null");
+
+    // sparse lists and escaped strings and keys
+    new_buffer.set(&["list", "2", "tags", "1"], "quote \" and\nline")?;
+    new_buffer.set(&["map", "a\\b", "1"], "\u{01}")?;
+    assert_eq!(write(&new_buffer, &["list"])?, r#"[null,null,{"name":null,"tags":[null,"quote \" and\nline"]}]"#);
+    assert_eq!(write(&new_buffer, &["map"])?, r#"{"a\\b":[null,"\u0001"]}"#);
+
+    for seed in 0..50 {
+        let buffer = factory.generate_sample(seed)?;
+        write(&buffer, &[])?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn json_limits_work() -> Result<(), NP_Error> {
+    use crate::buffer::{NP_Buffer_Options, NP_Limits};
+
+    // limits are counted while encoding, so they stop at the same text json_encode and json_write_fmt make
+    let factory = crate::NP_Factory::new(r#"{"type": "map", "value": {"type": "list", "of": {"type": "string"}}}"#)?;
+    let mut new_buffer = factory.empty_buffer(None);
+    new_buffer.set(&["a\"b", "2"], "line\nbreak")?;
+    new_buffer.set(&["c", "0"], "x")?;
+    let bytes = new_buffer.close();
+    let json = r#"{"c":["x"],"a\"b":[null,null,"line\nbreak"]}"#;
+
+    let open = |limits: NP_Limits| factory.open_buffer_with(bytes.clone(), NP_Buffer_Options { limits, ..Default::default() });
+    let check = |limits: NP_Limits| -> (bool, bool) {
+        let buffer = open(limits);
+        let mut text = String::new();
+        (buffer.json_encode(&[]).is_ok(), buffer.json_write_fmt(&[], &mut text).is_ok())
+    };
+
+    assert_eq!(open(NP_Limits::default()).json_encode(&[])?.stringify(), json);
+    assert_eq!(check(NP_Limits { max_json_size: json.len(), ..Default::default() }), (true, true));
+    assert_eq!(check(NP_Limits { max_json_size: json.len() - 1, ..Default::default() }), (false, false));
+    assert_eq!(check(NP_Limits { max_steps: 6, ..Default::default() }), (true, true));
+    assert_eq!(check(NP_Limits { max_steps: 5, ..Default::default() }), (false, false));
+    assert_eq!(check(NP_Limits { max_depth: 2, ..Default::default() }), (true, true));
+    assert_eq!(check(NP_Limits { max_depth: 1, ..Default::default() }), (false, false));
+    assert_eq!(open(NP_Limits { max_steps: 5, ..Default::default() }).json_encode(&["c"])?.stringify(), r#"["x"]"#);
+
+    // the default limits turn away buffers nested deeper than 64 collections
+    let mut schema = String::from(r#"{"type": "u8"}"#);
+    for _ in 0..70 {
+        schema = alloc::format!(r#"{{"type": "list", "of": {}}}"#, schema);
+    }
+    let factory = crate::NP_Factory::new(&schema)?;
+    let path = alloc::vec!["0"; 70];
+    let mut new_buffer = factory.empty_buffer_with(None, NP_Buffer_Options { limits: NP_Limits::unlimited(), ..Default::default() });
+    new_buffer.set(&path, 1u8)?;
+    assert_eq!(new_buffer.json_encode(&path[..60])?.stringify(), "[[[[[[[[[[1]]]]]]]]]]");
+    let bytes = new_buffer.close();
+
+    let buffer = factory.open_buffer(bytes.clone());
+    assert_eq!(buffer.json_encode(&[]).err(), Some(NP_Error::new_limit("max_depth", 64)));
+    assert_eq!(buffer.get::<u8>(&path), Err(NP_Error::new_limit("max_depth", 64)));
+    assert_eq!(buffer.json_encode(&path[..60]).err(), Some(NP_Error::new_limit("max_depth", 64)));
+
+    let buffer = factory.open_buffer_with(bytes, NP_Buffer_Options { limits: NP_Limits::unlimited(), ..Default::default() });
+    assert_eq!(buffer.get::<u8>(&path)?, Some(1));
+
+    Ok(())
+}
+
+#[test]
+fn buffer_from_json_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["string", {"type": "string"}],
+        ["u64",    {"type": "u64"}],
+        ["dec",    {"type": "decimal", "exp": 2}],
+        ["geo",    {"type": "geo16"}],
+        ["any",    {"type": "any"}],
+        ["list",   {"type": "list", "of": {"type": "table", "columns": [["name", {"type": "string"}], ["tags", {"type": "list", "of": {"type": "string"}}]]}}],
+        ["map",    {"type": "map", "value": {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}}],
+        ["sorted", {"type": "tuple", "values": [{"type": "u8"}, {"type": "string", "size": 4}], "sorted": true}],
+        ["log",    {"type": "log", "of": {"type": "i32"}}],
+        ["tensor", {"type": "tensor", "dtype": "i16", "shape": [2]}]
+    ]}"#)?;
+
+    // same values as parsing the whole document first
+    for seed in 0..50 {
+        let sample = factory.generate_sample(seed)?;
+        let json = sample.json_encode(&[])?.stringify();
+        let streamed = factory.buffer_from_json(&json)?;
+        let mut parsed = factory.empty_buffer(None);
+        parsed.set_with_json(&[], &json)?;
+        assert_eq!(streamed.json_encode(&[])?.stringify(), parsed.json_encode(&[])?.stringify());
+        assert!(streamed.semantic_eq(&parsed));
+    }
+
+    let new_buffer = factory.buffer_from_json(r#" {"list": [null, {"tags": ["a"]}], "map": {"x": null, "y": [1]}, "log": [null, 3]} "#)?;
+    assert_eq!(new_buffer.get::<&str>(&["list", "1", "tags", "0"])?, Some("a"));
+    assert_eq!(new_buffer.length(&["list"])?, Some(2));
+    assert_eq!(new_buffer.json_encode(&["map"])?.stringify(), r#"{"y":[1,null]}"#);
+    assert_eq!(new_buffer.get::<i32>(&["log", "1"])?, Some(3));
+    assert_eq!(factory.buffer_from_json("null")?.json_encode(&[])?.stringify(), "null");
+
+    assert!(factory.buffer_from_json(r#"{"missing": 1}"#).is_err());
+    assert!(factory.buffer_from_json(r#"{"sorted": [1, "abcd", 2]}"#).is_err());
+    assert!(factory.buffer_from_json(r#"{"list": [}"#).is_err());
+    assert!(factory.buffer_from_json(r#"{"u64": 1} {}"#).is_err());
+    assert!(factory.buffer_from_json(r#"{"u64": "1"}"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn inline_values_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["bool",   {"type": "bool"}],
+        ["u8",     {"type": "u8"}],
+        ["i8",     {"type": "i8"}],
+        ["option", {"type": "option", "choices": ["red", "blue"]}],
+        ["u16",    {"type": "u16"}],
+        ["list",   {"type": "list", "of": {"type": "u8"}}]
+    ]}"#)?;
+
+    let mut plain = factory.empty_buffer(None);
+    plain.set(&["bool"], true)?;
+    plain.set(&["u8"], 0u8)?;
+    plain.set(&["i8"], -20i8)?;
+    plain.set(&["option"], NP_Enum::new("blue"))?;
+    plain.set(&["u16"], 300u16)?;
+    plain.set(&["list", "2"], 255u8)?;
+
+    let mut buffer = factory.open_buffer(plain.read_bytes().to_vec());
+    buffer.inline_small_values()?;
+    assert_eq!(buffer.read_bytes().len(), plain.read_bytes().len() - 5);
+    assert_eq!(buffer.get::<bool>(&["bool"])?, Some(true));
+    assert_eq!(buffer.get::<u8>(&["u8"])?, Some(0));
+    assert_eq!(buffer.get::<i8>(&["i8"])?, Some(-20));
+    assert_eq!(buffer.get::<NP_Enum>(&["option"])?, Some(NP_Enum::new("blue")));
+    assert_eq!(buffer.get::<u16>(&["u16"])?, Some(300));
+    assert_eq!(buffer.get::<u8>(&["list", "2"])?, Some(255));
+    assert!(buffer.semantic_eq(&plain));
+    assert_eq!(buffer.semantic_hash(), plain.semantic_hash());
+
+    // updates never allocate and the layout survives compaction and reopening
+    let size = buffer.read_bytes().len();
+    buffer.set(&["bool"], false)?;
+    buffer.set(&["i8"], 127i8)?;
+    assert_eq!(buffer.read_bytes().len(), size);
+    buffer.del(&["u8"])?;
+    assert_eq!(buffer.get::<u8>(&["u8"])?, None);
+    buffer.compact(None)?;
+    let reopened = factory.open_buffer(buffer.close());
+    assert_eq!(reopened.json_encode(&[])?.stringify(), r#"{"bool":false,"u8":null,"i8":127,"option":"blue","u16":300,"list":[null,null,255]}"#);
+    let (_, report) = factory.open_lenient(reopened.read_bytes().to_vec());
+    assert!(report.is_clean());
+
+    // sortable buffers keep their layout
+    let sortable = crate::NP_Factory::new(r#"{"type": "tuple", "sorted": true, "values": [{"type": "u8"}]}"#)?;
+    assert!(sortable.empty_buffer(None).inline_small_values().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn deep_nesting_works() -> Result<(), NP_Error> {
+    // a list of lists of lists... 1,000 levels deep with a u8 at the bottom
+    let leaf = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u8"}}"#)?.compile_schema();
+    let mut schema: Vec<u8> = alloc::vec![leaf[0]; 999];
+    schema.extend_from_slice(&leaf);
+    let factory = crate::NP_Factory::new_compiled(schema);
+
+    // far past the default limits, which only guard against untrusted buffers
+    let path: Vec<&str> = alloc::vec!["0"; 1000];
+    let limits = crate::buffer::NP_Limits::unlimited();
+    let mut buffer = factory.empty_buffer_with(None, crate::buffer::NP_Buffer_Options { limits, ..Default::default() });
+    buffer.set(&path, 42u8)?;
+    assert_eq!(buffer.get::<u8>(&path)?, Some(42));
+
+    let json = buffer.json_encode(&[])?.stringify();
+    assert_eq!(json, alloc::format!("{}42{}", "[".repeat(1000), "]".repeat(1000)));
+    let mut written = String::new();
+    buffer.json_write_fmt(&[], &mut written)?;
+    assert_eq!(written, json);
+
+    let size = buffer.calc_bytes()?;
+    buffer.compact(None)?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer, size.after_compaction);
+    assert_eq!(buffer.get::<u8>(&path)?, Some(42));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), json);
+
+    Ok(())
+}
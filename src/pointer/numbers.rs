@@ -31,7 +31,7 @@
 //! 
 
 
-use alloc::prelude::v1::Box;
+use alloc::boxed::Box;
 use crate::schema::NP_Parsed_Schema;
 use alloc::vec::Vec;
 use crate::utils::to_unsigned;
@@ -41,7 +41,7 @@ use crate::{schema::NP_TypeKeys, pointer::NP_Value, json_flex::NP_JSON, json_fle
 
 use alloc::string::ToString;
 use alloc::{borrow::ToOwned};
-use super::{NP_Cursor};
+use super::{NP_Cursor, any::NP_Dyn};
 use crate::NP_Memory;
 
 /// The type of number being used
@@ -59,13 +59,15 @@ pub enum NP_NumType {
 
 
 macro_rules! noproto_number {
-    ($t:ty, $str1: tt, $str2: tt, $tkey: expr, $numType: expr) => {
+    ($t:ty, $str1: tt, $str2: tt, $tkey: expr, $numType: expr, $dyn: ident) => {
 
         impl<'value> NP_Value<'value> for $t {
 
-            fn type_idx() -> (&'value str, NP_TypeKeys) { ($str1, $tkey) }
+            fn type_idx() -> (&'static str, NP_TypeKeys) { ($str1, $tkey) }
 
-            fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ($str1, $tkey) }
+            fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ($str1, $tkey) }
+
+            fn as_dyn(&self) -> Option<NP_Dyn<'_>> { Some(NP_Dyn::$dyn(*self)) }
 
             fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
                 let mut schema_json = JSMAP::new();
@@ -78,7 +80,7 @@ macro_rules! noproto_number {
                             schema_json.insert("default".to_owned(), NP_JSON::Integer(default_val as i64));
                         },
                         NP_NumType::unsigned => {
-                            schema_json.insert("default".to_owned(), NP_JSON::Integer(default_val as i64));
+                            schema_json.insert("default".to_owned(), NP_JSON::from_u64(default_val as u64));
                         },
                         NP_NumType::floating => {
                             schema_json.insert("default".to_owned(), NP_JSON::Float(default_val as f64));
@@ -86,6 +88,17 @@ macro_rules! noproto_number {
                     };
                     
                 }
+
+                if non_finite_of(&schema[address]) != NP_NonFinite::Null {
+                    let policy = if non_finite_of(&schema[address]) == NP_NonFinite::String { "string" } else { "error" };
+                    schema_json.insert("non_finite".to_owned(), NP_JSON::String(policy.to_owned()));
+                }
+
+                match overflow_of(&schema[address]) {
+                    NP_Overflow::Saturate => { schema_json.insert("overflow".to_owned(), NP_JSON::String("saturate".to_owned())); },
+                    NP_Overflow::Wrap => { schema_json.insert("overflow".to_owned(), NP_JSON::String("wrap".to_owned())); },
+                    NP_Overflow::Strict => { }
+                }
         
                 Ok(NP_JSON::Dictionary(schema_json))
             }
@@ -96,20 +109,33 @@ macro_rules! noproto_number {
     
             fn set_value<'set>(cursor: NP_Cursor, memory: &'set NP_Memory, value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
 
+                if let NP_NumType::floating = $numType {
+                    if !(value as f64).is_finite() && non_finite_of(&memory.schema[cursor.schema_addr]) == NP_NonFinite::Error {
+                        return Err(NP_Error::new_static("NaN and infinity aren't allowed by the schema!"));
+                    }
+                }
+
                 let c_value = cursor.get_value(memory);
 
+                let mut bytes = value.to_be_bytes();
+
+                match $numType {
+                    NP_NumType::signed => {
+                        bytes[0] = to_unsigned(bytes[0]);
+                    },
+                    _ => {}
+                };
+
+                // one byte numbers can be stored in their pointer
+                if bytes.len() == 1 {
+                    let value_address = memory.set_small(c_value.get_addr_value(), bytes[0])?;
+                    cursor.get_value_mut(memory).set_addr_value(value_address);
+                    return Ok(cursor);
+                }
+
                 let mut value_address = c_value.get_addr_value() as usize;
 
                 if value_address != 0 { // existing value, replace
-                    let mut bytes = value.to_be_bytes();
-
-                    match $numType {
-                        NP_NumType::signed => {
-                            bytes[0] = to_unsigned(bytes[0]);
-                        },
-                        _ => {}
-                    };
-        
                     let write_bytes = memory.write_bytes();
         
                     // overwrite existing values in buffer
@@ -118,18 +144,8 @@ macro_rules! noproto_number {
                     }
                     return Ok(cursor);
                 } else { // new value
-        
-                    let mut bytes = value.to_be_bytes();
-
-                    match $numType {
-                        NP_NumType::signed => {
-                            bytes[0] = to_unsigned(bytes[0]);
-                        },
-                        _ => {}
-                    };
-        
                     value_address = memory.malloc_borrow(&bytes)?;
-                    c_value.set_addr_value(value_address as u16);
+                    cursor.get_value_mut(memory).set_addr_value(value_address as u16);
 
                     return Ok(cursor);
                 }
@@ -147,10 +163,17 @@ macro_rules! noproto_number {
                     return Ok(None);
                 }
         
-                let read_memory = memory.read_bytes();
                 let mut be_bytes = <$t>::default().to_be_bytes();
-                for x in 0..be_bytes.len() {
-                    be_bytes[x] = read_memory[value_addr + x];
+                if be_bytes.len() == 1 {
+                    be_bytes[0] = match memory.get_small(value_addr as u16) {
+                        Some(x) => x,
+                        None => return Ok(None)
+                    };
+                } else {
+                    let read_memory = memory.read_bytes();
+                    for x in 0..be_bytes.len() {
+                        be_bytes[x] = read_memory[value_addr + x];
+                    }
                 }
 
                 match $numType {
@@ -170,7 +193,8 @@ macro_rules! noproto_number {
                         match x {
                             Some(y) => {
                                 match $numType {
-                                    NP_NumType::floating => NP_JSON::Float(y as f64),
+                                    NP_NumType::floating => float_json(y as f64, &memory.schema[cursor.schema_addr]),
+                                    NP_NumType::unsigned => NP_JSON::from_u64(y as u64),
                                     _ => NP_JSON::Integer(y as i64)
                                 }
                             },
@@ -179,7 +203,8 @@ macro_rules! noproto_number {
                                 match <$t>::schema_default(&schema) {
                                     Some(v) => {
                                         match $numType {
-                                            NP_NumType::floating => { NP_JSON::Float(v as f64) },
+                                            NP_NumType::floating => { float_json(v as f64, schema) },
+                                            NP_NumType::unsigned => { NP_JSON::from_u64(v as u64) },
                                             _ => { NP_JSON::Integer(v as i64) }
                                         }
                                     },
@@ -200,6 +225,8 @@ macro_rules! noproto_number {
 
                 if c_value.get_addr_value() == 0 {
                     Ok(0) 
+                } else if core::mem::size_of::<Self>() == 1 {
+                    Ok(memory.small_size(c_value.get_addr_value()))
                 } else {
                     Ok(core::mem::size_of::<Self>())
                 }
@@ -219,41 +246,76 @@ macro_rules! noproto_number {
                         schema_data.push(1);
                         schema_data.extend((x as $t).to_be_bytes().to_vec());
                     },
+                    NP_JSON::Unsigned(x) => {
+                        schema_data.push(1);
+                        schema_data.extend((x as $t).to_be_bytes().to_vec());
+                    },
                     _ => {
                         schema_data.push(0);
                     }
                 };
 
+                let overflow = match &json_schema["overflow"] {
+                    NP_JSON::String(x) if x == "saturate" => NP_Overflow::Saturate,
+                    NP_JSON::String(x) if x == "wrap" => NP_Overflow::Wrap,
+                    NP_JSON::String(x) if x == "error" => NP_Overflow::Strict,
+                    NP_JSON::Null => NP_Overflow::Strict,
+                    _ => return Err(NP_Error::new_schema("Number overflow must be \"error\", \"saturate\" or \"wrap\"!"))
+                };
+
+                // second and third bits of the default flag mark saturating and wrapping integers
+                match overflow {
+                    NP_Overflow::Saturate => { schema_data[1] |= 2; },
+                    NP_Overflow::Wrap => { schema_data[1] |= 4; },
+                    NP_Overflow::Strict => { }
+                }
+
+                let non_finite = match &json_schema["non_finite"] {
+                    NP_JSON::Null => NP_NonFinite::Null,
+                    _ if !matches!($numType, NP_NumType::floating) => return Err(NP_Error::new_schema("Only float and double types have a non_finite policy!")),
+                    NP_JSON::String(x) if x == "null" => NP_NonFinite::Null,
+                    NP_JSON::String(x) if x == "string" => NP_NonFinite::String,
+                    NP_JSON::String(x) if x == "error" => NP_NonFinite::Error,
+                    _ => return Err(NP_Error::new_schema("Float non_finite must be \"null\", \"string\" or \"error\"!"))
+                };
+
+                // fourth and fifth bits of the default flag mark floats that keep NaN and infinity as strings or refuse them
+                match non_finite {
+                    NP_NonFinite::String => { schema_data[1] |= 8; },
+                    NP_NonFinite::Error => { schema_data[1] |= 16; },
+                    NP_NonFinite::Null => { }
+                }
+
                 let use_schema = match $tkey {
                     NP_TypeKeys::Int8 => {
-                        NP_Parsed_Schema::Int8 { sortable: true, i: $tkey, default: i8::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Int8 { sortable: true, i: $tkey, default: i8::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Int16 => {
-                        NP_Parsed_Schema::Int16 { sortable: true, i: $tkey, default: i16::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Int16 { sortable: true, i: $tkey, default: i16::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Int32 => {
-                        NP_Parsed_Schema::Int32 { sortable: true, i: $tkey, default: i32::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Int32 { sortable: true, i: $tkey, default: i32::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Int64 => {
-                        NP_Parsed_Schema::Int64 { sortable: true, i: $tkey, default: i64::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Int64 { sortable: true, i: $tkey, default: i64::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Uint8 => {
-                        NP_Parsed_Schema::Uint8 { sortable: true, i: $tkey, default: u8::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Uint8 { sortable: true, i: $tkey, default: u8::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Uint16 => {
-                        NP_Parsed_Schema::Uint16 { sortable: true, i: $tkey, default: u16::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Uint16 { sortable: true, i: $tkey, default: u16::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Uint32 => {
-                        NP_Parsed_Schema::Uint32 { sortable: true, i: $tkey, default: u32::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Uint32 { sortable: true, i: $tkey, default: u32::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Uint64 => {
-                        NP_Parsed_Schema::Uint64 { sortable: true, i: $tkey, default: u64::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Uint64 { sortable: true, i: $tkey, default: u64::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Float => {
-                        NP_Parsed_Schema::Float { sortable: false, i: $tkey, default: f32::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Float { sortable: false, i: $tkey, default: f32::np_get_default_from_json(&json_schema["default"]), non_finite }
                     },
                     NP_TypeKeys::Double => {
-                        NP_Parsed_Schema::Double { sortable: false, i: $tkey, default: f64::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Double { sortable: false, i: $tkey, default: f64::np_get_default_from_json(&json_schema["default"]), non_finite }
                     },
                     _ => { unreachable!() }
                 };
@@ -265,36 +327,46 @@ macro_rules! noproto_number {
             }
 
             fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+                let overflow = match bytes[address + 1] {
+                    x if x & 2 != 0 => NP_Overflow::Saturate,
+                    x if x & 4 != 0 => NP_Overflow::Wrap,
+                    _ => NP_Overflow::Strict
+                };
+                let non_finite = match bytes[address + 1] {
+                    x if x & 8 != 0 => NP_NonFinite::String,
+                    x if x & 16 != 0 => NP_NonFinite::Error,
+                    _ => NP_NonFinite::Null
+                };
                 schema.push(match $tkey {
                     NP_TypeKeys::Int8 => {
-                        NP_Parsed_Schema::Int8 { sortable: true, i: $tkey, default: i8::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Int8 { sortable: true, i: $tkey, default: i8::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Int16 => {
-                        NP_Parsed_Schema::Int16 { sortable: true, i: $tkey, default: i16::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Int16 { sortable: true, i: $tkey, default: i16::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Int32 => {
-                        NP_Parsed_Schema::Int32 { sortable: true, i: $tkey, default: i32::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Int32 { sortable: true, i: $tkey, default: i32::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Int64 => {
-                        NP_Parsed_Schema::Int64 { sortable: true, i: $tkey, default: i64::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Int64 { sortable: true, i: $tkey, default: i64::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Uint8 => {
-                        NP_Parsed_Schema::Uint8 { sortable: true, i: $tkey, default: u8::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Uint8 { sortable: true, i: $tkey, default: u8::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Uint16 => {
-                        NP_Parsed_Schema::Uint16 { sortable: true, i: $tkey, default: u16::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Uint16 { sortable: true, i: $tkey, default: u16::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Uint32 => {
-                        NP_Parsed_Schema::Uint32 { sortable: true, i: $tkey, default: u32::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Uint32 { sortable: true, i: $tkey, default: u32::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Uint64 => {
-                        NP_Parsed_Schema::Uint64 { sortable: true, i: $tkey, default: u64::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Uint64 { sortable: true, i: $tkey, default: u64::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Float => {
-                        NP_Parsed_Schema::Float { sortable: false, i: $tkey, default: f32::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Float { sortable: false, i: $tkey, default: f32::np_get_default_from_bytes(address, bytes), non_finite }
                     },
                     NP_TypeKeys::Double => {
-                        NP_Parsed_Schema::Double { sortable: false, i: $tkey, default: f64::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Double { sortable: false, i: $tkey, default: f64::np_get_default_from_bytes(address, bytes), non_finite }
                     },
                     _ => { unreachable!() }
                 });
@@ -305,21 +377,177 @@ macro_rules! noproto_number {
 }
 
 // signed integers
-noproto_number!(i8,    "int8",  "i8", NP_TypeKeys::Int8  , NP_NumType::signed);
-noproto_number!(i16,  "int16", "i16", NP_TypeKeys::Int16 , NP_NumType::signed);
-noproto_number!(i32,  "int32", "i32", NP_TypeKeys::Int32 , NP_NumType::signed);
-noproto_number!(i64,  "int64", "i64", NP_TypeKeys::Int64 , NP_NumType::signed);
+noproto_number!(i8,    "int8",  "i8", NP_TypeKeys::Int8  , NP_NumType::signed, I8);
+noproto_number!(i16,  "int16", "i16", NP_TypeKeys::Int16 , NP_NumType::signed, I16);
+noproto_number!(i32,  "int32", "i32", NP_TypeKeys::Int32 , NP_NumType::signed, I32);
+noproto_number!(i64,  "int64", "i64", NP_TypeKeys::Int64 , NP_NumType::signed, I64);
 
 // unsigned integers
-noproto_number!(u8,   "uint8",  "u8", NP_TypeKeys::Uint8 , NP_NumType::unsigned);
-noproto_number!(u16, "uint16", "u16", NP_TypeKeys::Uint16, NP_NumType::unsigned);
-noproto_number!(u32, "uint32", "u32", NP_TypeKeys::Uint32, NP_NumType::unsigned);
-noproto_number!(u64, "uint64", "u64", NP_TypeKeys::Uint64, NP_NumType::unsigned);
+noproto_number!(u8,   "uint8",  "u8", NP_TypeKeys::Uint8 , NP_NumType::unsigned, U8);
+noproto_number!(u16, "uint16", "u16", NP_TypeKeys::Uint16, NP_NumType::unsigned, U16);
+noproto_number!(u32, "uint32", "u32", NP_TypeKeys::Uint32, NP_NumType::unsigned, U32);
+noproto_number!(u64, "uint64", "u64", NP_TypeKeys::Uint64, NP_NumType::unsigned, U64);
 
 // floating point
-noproto_number!(f32,  "float", "f32", NP_TypeKeys::Float , NP_NumType::floating);
-noproto_number!(f64, "double", "f64", NP_TypeKeys::Double, NP_NumType::floating);
+noproto_number!(f32,  "float", "f32", NP_TypeKeys::Float , NP_NumType::floating, F32);
+noproto_number!(f64, "double", "f64", NP_TypeKeys::Double, NP_NumType::floating, F64);
+
+
+/// What happens when a number doesn't fit in the integer type of the schema.
+/// 
+/// Applies to `NP_Buffer::increment` and to numbers of other types converted by `NP_Buffer::set`.  Integer schemas pick one with `"overflow": "error"`, `"saturate"` or `"wrap"`, `NP_Buffer::set_overflow` picks one for every integer in a buffer and `set_with_overflow` or `increment_with_overflow` for a single call.
+/// 
+/// ```
+/// use no_proto::error::NP_Error;
+/// use no_proto::NP_Factory;
+/// use no_proto::pointer::numbers::NP_Overflow;
+/// 
+/// let factory: NP_Factory = NP_Factory::new(r#"{
+///    "type": "table",
+///    "columns": [
+///         ["level", {"type": "u8"}],
+///         ["tick",  {"type": "u8", "overflow": "wrap"}]
+///     ]
+/// }"#)?;
+/// 
+/// let mut new_buffer = factory.empty_buffer(None);
+/// assert_eq!(new_buffer.set(&["level"], 300u16), Err(NP_Error::Overflow { path: vec!["level".to_owned()] }));
+/// new_buffer.set_with_overflow(&["level"], 300u16, NP_Overflow::Saturate)?;
+/// assert_eq!(new_buffer.get::<u8>(&["level"])?, Some(255));
+/// 
+/// new_buffer.set(&["tick"], 300u16)?;
+/// assert_eq!(new_buffer.get::<u8>(&["tick"])?, Some(44));
+/// 
+/// // the buffer's policy is used over the schema's
+/// new_buffer.set_overflow(Some(NP_Overflow::Strict));
+/// assert!(new_buffer.increment(&["tick"], 250u8).is_err());
+/// 
+/// # Ok::<(), NP_Error>(()) 
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NP_Overflow {
+    /// Return `NP_Error::Overflow` and leave the value alone (the default)
+    Strict,
+    /// Clamp the value at the type's minimum or maximum
+    Saturate,
+    /// Wrap around like two's complement arithmetic
+    Wrap
+}
+
+/// How float and double types handle NaN and infinity, set in the schema with `"non_finite"`.
+/// 
+/// JSON has no NaN or infinity, by default they're stored like any other float and encoded as `null`.
+/// 
+/// ```
+/// use no_proto::error::NP_Error;
+/// use no_proto::NP_Factory;
+/// 
+/// let factory: NP_Factory = NP_Factory::new(r#"{
+///    "type": "table",
+///    "columns": [
+///         ["plain",  {"type": "double"}],
+///         ["named",  {"type": "double", "non_finite": "string"}],
+///         ["finite", {"type": "double", "non_finite": "error"}]
+///     ]
+/// }"#)?;
+/// 
+/// let mut new_buffer = factory.empty_buffer(None);
+/// new_buffer.set(&["plain"], f64::NAN)?;
+/// new_buffer.set(&["named"], f64::NEG_INFINITY)?;
+/// assert!(new_buffer.set(&["finite"], f64::INFINITY).is_err());
+/// 
+/// assert_eq!(new_buffer.json_encode(&[])?.stringify(), r#"{"plain":null,"named":"-Infinity","finite":null}"#);
+/// 
+/// // the strings come back in from JSON
+/// new_buffer.set_with_json(&["named"], r#""NaN""#)?;
+/// assert!(new_buffer.get::<f64>(&["named"])?.unwrap().is_nan());
+/// 
+/// # Ok::<(), NP_Error>(()) 
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NP_NonFinite {
+    /// Store them, encode them as `null` in JSON (the default)
+    Null,
+    /// Store them, encode them as `"NaN"`, `"Infinity"` and `"-Infinity"` in JSON
+    String,
+    /// Refuse to set them
+    Error
+}
+
+/// Non finite policy of a float schema, `Null` for anything else
+pub(crate) fn non_finite_of(schema: &NP_Parsed_Schema) -> NP_NonFinite {
+    match schema {
+        NP_Parsed_Schema::Float  { non_finite, .. } => *non_finite,
+        NP_Parsed_Schema::Double { non_finite, .. } => *non_finite,
+        _ => NP_NonFinite::Null
+    }
+}
+
+/// A float as JSON, NaN and infinity become strings if the schema asks for it
+fn float_json(value: f64, schema: &NP_Parsed_Schema) -> NP_JSON {
+    match non_finite_of(schema) {
+        NP_NonFinite::String if value.is_nan() => NP_JSON::String("NaN".to_owned()),
+        NP_NonFinite::String if value.is_infinite() => NP_JSON::String(if value > 0f64 { "Infinity" } else { "-Infinity" }.to_owned()),
+        _ => NP_JSON::Float(value)
+    }
+}
+
+/// Overflow policy of an integer schema, `Strict` for anything else
+pub(crate) fn overflow_of(schema: &NP_Parsed_Schema) -> NP_Overflow {
+    match schema {
+        NP_Parsed_Schema::Int8   { overflow, .. } => *overflow,
+        NP_Parsed_Schema::Int16  { overflow, .. } => *overflow,
+        NP_Parsed_Schema::Int32  { overflow, .. } => *overflow,
+        NP_Parsed_Schema::Int64  { overflow, .. } => *overflow,
+        NP_Parsed_Schema::Uint8  { overflow, .. } => *overflow,
+        NP_Parsed_Schema::Uint16 { overflow, .. } => *overflow,
+        NP_Parsed_Schema::Uint32 { overflow, .. } => *overflow,
+        NP_Parsed_Schema::Uint64 { overflow, .. } => *overflow,
+        _ => NP_Overflow::Strict
+    }
+}
+
+/// Integer types that can be used as counters with `NP_Buffer::increment`
+pub trait NP_Counter: Sized + Copy {
+    /// Add `delta` to the value at the cursor and store it, returns `None` if the result overflows with the `Strict` policy.
+    #[doc(hidden)]
+    fn increment(cursor: NP_Cursor, memory: &NP_Memory, delta: Self, overflow: NP_Overflow) -> Result<Option<Self>, NP_Error>;
+}
 
+macro_rules! noproto_counter {
+    ($t:ty) => {
+        impl NP_Counter for $t {
+            fn increment(cursor: NP_Cursor, memory: &NP_Memory, delta: Self, overflow: NP_Overflow) -> Result<Option<Self>, NP_Error> {
+                let schema = &memory.schema[cursor.schema_addr];
+
+                // unset values count from the schema default
+                let current = match <$t>::into_value(&cursor, memory)? {
+                    Some(x) => x,
+                    None => <$t>::np_get_default(schema).unwrap_or(0)
+                };
+
+                let next = match (current.checked_add(delta), overflow) {
+                    (Some(x), _) => x,
+                    (None, NP_Overflow::Saturate) => current.saturating_add(delta),
+                    (None, NP_Overflow::Wrap) => current.wrapping_add(delta),
+                    (None, NP_Overflow::Strict) => return Ok(None)
+                };
+
+                <$t>::set_value(cursor, memory, next)?;
+                Ok(Some(next))
+            }
+        }
+    }
+}
+
+noproto_counter!(i8);
+noproto_counter!(i16);
+noproto_counter!(i32);
+noproto_counter!(i64);
+noproto_counter!(u8);
+noproto_counter!(u16);
+noproto_counter!(u32);
+noproto_counter!(u64);
 
 impl super::NP_Scalar for i8 {}
 impl super::NP_Scalar for i16 {}
@@ -343,7 +571,7 @@ impl NP_BigEndian for i8 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Int8 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Int8 { default, .. } => { *default },
             _ => None
         }
     }
@@ -355,17 +583,20 @@ impl NP_BigEndian for i8 {
             NP_JSON::Integer(x) => {
                 Some(*x as Self)
             },
+            NP_JSON::Unsigned(x) => {
+                Some(*x as Self)
+            },
             _ => {
                 None
             }
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 1] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 2)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 3)]);
             Some(i8::from_be_bytes(slice))
         }
     }
@@ -414,7 +645,7 @@ impl NP_BigEndian for i16 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Int16 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Int16 { default, .. } => { *default },
             _ => None
         }
     }
@@ -426,17 +657,20 @@ impl NP_BigEndian for i16 {
             NP_JSON::Integer(x) => {
                 Some(*x as Self)
             },
+            NP_JSON::Unsigned(x) => {
+                Some(*x as Self)
+            },
             _ => {
                 None
             }
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 2] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 3)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 4)]);
             Some(i16::from_be_bytes(slice))
         }
     }
@@ -487,7 +721,7 @@ impl NP_BigEndian for i32 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Int32 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Int32 { default, .. } => { *default },
             _ => None
         }
     }
@@ -499,17 +733,20 @@ impl NP_BigEndian for i32 {
             NP_JSON::Integer(x) => {
                 Some(*x as Self)
             },
+            NP_JSON::Unsigned(x) => {
+                Some(*x as Self)
+            },
             _ => {
                 None
             }
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 4] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 5)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 6)]);
             Some(i32::from_be_bytes(slice))
         }
     }
@@ -536,6 +773,10 @@ fn i32_default_value_works() -> Result<(), NP_Error> {
     let buffer = factory.empty_buffer(None);
     assert_eq!(buffer.get::<i32>(&[])?.unwrap(), 293i32);
 
+    let factory = crate::NP_Factory::new_compiled(factory.compile_schema());
+    let buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get::<i32>(&[])?.unwrap(), 293i32);
+
     Ok(())
 }
 
@@ -561,7 +802,7 @@ impl NP_BigEndian for i64 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Int64 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Int64 { default, .. } => { *default },
             _ => None
         }
     }
@@ -573,17 +814,20 @@ impl NP_BigEndian for i64 {
             NP_JSON::Integer(x) => {
                 Some(*x as Self)
             },
+            NP_JSON::Unsigned(x) => {
+                Some(*x as Self)
+            },
             _ => {
                 None
             }
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 8] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 9)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 10)]);
             Some(i64::from_be_bytes(slice))
         }
     }
@@ -635,7 +879,7 @@ impl NP_BigEndian for u8 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Uint8 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Uint8 { default, .. } => { *default },
             _ => None
         }
     }
@@ -647,17 +891,20 @@ impl NP_BigEndian for u8 {
             NP_JSON::Integer(x) => {
                 Some(*x as Self)
             },
+            NP_JSON::Unsigned(x) => {
+                Some(*x as Self)
+            },
             _ => {
                 None
             }
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 1] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 2)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 3)]);
             Some(u8::from_be_bytes(slice))
         }
     }
@@ -709,7 +956,7 @@ impl NP_BigEndian for u16 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Uint16 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Uint16 { default, .. } => { *default },
             _ => None
         }
     }
@@ -721,17 +968,20 @@ impl NP_BigEndian for u16 {
             NP_JSON::Integer(x) => {
                 Some(*x as Self)
             },
+            NP_JSON::Unsigned(x) => {
+                Some(*x as Self)
+            },
             _ => {
                 None
             }
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 2] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 3)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 4)]);
             Some(u16::from_be_bytes(slice))
         }
     }
@@ -783,7 +1033,7 @@ impl NP_BigEndian for u32 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Uint32 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Uint32 { default, .. } => { *default },
             _ => None
         }
     }
@@ -795,17 +1045,20 @@ impl NP_BigEndian for u32 {
             NP_JSON::Integer(x) => {
                 Some(*x as Self)
             },
+            NP_JSON::Unsigned(x) => {
+                Some(*x as Self)
+            },
             _ => {
                 None
             }
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 4] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 5)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 6)]);
             Some(u32::from_be_bytes(slice))
         }
     }
@@ -856,7 +1109,7 @@ impl NP_BigEndian for u64 {
            
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Uint64 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Uint64 { default, .. } => { *default },
             _ => None
         }
     }
@@ -868,17 +1121,20 @@ impl NP_BigEndian for u64 {
             NP_JSON::Integer(x) => {
                 Some(*x as Self)
             },
+            NP_JSON::Unsigned(x) => {
+                Some(*x as Self)
+            },
             _ => {
                 None
             }
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 8] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 9)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 10)]);
             Some(u64::from_be_bytes(slice))
         }
     }
@@ -928,7 +1184,7 @@ impl NP_BigEndian for f32 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Float { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Float { sortable: _, i: _, default, non_finite: _ } => { *default },
             _ => None
         }
     }
@@ -940,17 +1196,20 @@ impl NP_BigEndian for f32 {
             NP_JSON::Integer(x) => {
                 Some(*x as Self)
             },
+            NP_JSON::Unsigned(x) => {
+                Some(*x as Self)
+            },
             _ => {
                 None
             }
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 4] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 5)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 6)]);
             Some(f32::from_be_bytes(slice))
         }
     }
@@ -1000,7 +1259,7 @@ impl NP_BigEndian for f64 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Double { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Double { sortable: _, i: _, default, non_finite: _ } => { *default },
             _ => None
         }
     }
@@ -1012,17 +1271,20 @@ impl NP_BigEndian for f64 {
             NP_JSON::Integer(x) => {
                 Some(*x as Self)
             },
+            NP_JSON::Unsigned(x) => {
+                Some(*x as Self)
+            },
             _ => {
                 None
             }
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 8] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 9)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 10)]);
             Some(f64::from_be_bytes(slice))
         }
     }
@@ -1065,4 +1327,87 @@ fn double_set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn counter_overflow_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"int16\",\"default\":-5,\"overflow\":\"saturate\"}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory2 = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory2.schema.to_json()?.stringify());
+
+    let mut buffer = factory2.empty_buffer(None);
+    assert_eq!(buffer.increment(&[], 10i16)?, Some(5));
+    assert_eq!(buffer.increment(&[], i16::MIN)?, Some(i16::MIN + 5));
+    assert_eq!(buffer.increment(&[], i16::MIN)?, Some(i16::MIN));
+
+    let factory = crate::NP_Factory::new("{\"type\":\"u64\",\"overflow\":\"error\"}")?;
+    assert_eq!("{\"type\":\"uint64\"}", factory.schema.to_json()?.stringify());
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&[], u64::MAX)?;
+    assert_eq!(buffer.increment(&[], 1u64), Err(NP_Error::Overflow { path: Vec::new() }));
+    assert_eq!(buffer.increment(&[], 1u32).is_err(), true);
+    assert_eq!(buffer.get::<u64>(&[])?, Some(u64::MAX));
+
+    let schema = "{\"type\":\"uint8\",\"overflow\":\"wrap\"}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, crate::NP_Factory::new_compiled(factory.compile_schema()).schema.to_json()?.stringify());
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.increment(&[], 200u8)?, Some(200));
+    assert_eq!(buffer.increment(&[], 100u8)?, Some(44));
+    buffer.set(&[], -1i8)?;
+    assert_eq!(buffer.get::<u8>(&[])?, Some(255));
+
+    // per buffer and per call policies come before the schema
+    buffer.set_overflow(Some(NP_Overflow::Saturate));
+    assert_eq!(buffer.increment(&[], 100u8)?, Some(255));
+    assert_eq!(buffer.increment_with_overflow(&[], 1u8, NP_Overflow::Strict), Err(NP_Error::Overflow { path: Vec::new() }));
+    buffer.set_with_overflow(&[], -1i8, NP_Overflow::Wrap)?;
+    assert_eq!(buffer.get::<u8>(&[])?, Some(255));
+    buffer.set(&[], 1000i32)?;
+    assert_eq!(buffer.get::<u8>(&[])?, Some(255));
+    buffer.set(&[], -3i64)?;
+    assert_eq!(buffer.get::<u8>(&[])?, Some(0));
+    buffer.set_overflow(None);
+    assert!(buffer.set(&[], 256.0f64).is_ok());
+    assert_eq!(buffer.get::<u8>(&[])?, Some(0));
+
+    assert!(crate::NP_Factory::new("{\"type\":\"u8\",\"overflow\":\"clamp\"}").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn non_finite_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"float\",\"non_finite\":\"string\"}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&[], f32::INFINITY)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "\"Infinity\"");
+    buffer.set_with_json(&[], "\"-Infinity\"")?;
+    assert_eq!(buffer.get::<f32>(&[])?, Some(f32::NEG_INFINITY));
+    assert!(buffer.set_with_json(&[], "\"Inf\"").is_err());
+
+    let factory = crate::NP_Factory::new("{\"type\":\"double\",\"non_finite\":\"error\"}")?;
+    let mut buffer = factory.empty_buffer(None);
+    assert!(buffer.set(&[], f64::NAN).is_err());
+    assert!(buffer.set(&[], f32::NEG_INFINITY).is_err());
+    buffer.set(&[], 1.5f64)?;
+    assert_eq!(buffer.get::<f64>(&[])?, Some(1.5));
+
+    // the default leaves NaN to JSON as null and won't read it back from a string
+    let factory = crate::NP_Factory::new("{\"type\":\"double\"}")?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&[], f64::NAN)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "null");
+    assert!(buffer.set_with_json(&[], "\"NaN\"").is_err());
+
+    assert!(crate::NP_Factory::new("{\"type\":\"u8\",\"non_finite\":\"error\"}").is_err());
+    assert!(crate::NP_Factory::new("{\"type\":\"float\",\"non_finite\":\"zero\"}").is_err());
+
+    Ok(())
+}
@@ -18,6 +18,36 @@
 //! # Ok::<(), NP_Error>(()) 
 //! ```
 //! 
+//! ## Numbered Choices
+//! 
+//! Choices can be given explicit numbers with `["name", number]`, choices without one count up from the one before it like Rust and C enums.  Numbers are only used to find choices, the buffer still stores the choice's index.  Set `"wide": true` to store the index in two bytes and allow up to 65,534 choices.
+//! 
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::option::NP_Enum;
+//! 
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "option",
+//!    "wide": true,
+//!    "choices": [["pending", 10], "active", ["closed", -1]]
+//! }"#)?;
+//!
+//! assert_eq!(factory.schema.root().choice_values(), Some(vec![10, 11, -1]));
+//! 
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&[], 11i64)?;
+//! assert_eq!(new_buffer.get::<NP_Enum>(&[])?, Some(NP_Enum::new("active")));
+//! 
+//! new_buffer.set_with_json(&[], "-1")?;
+//! assert_eq!(new_buffer.get::<NP_Enum>(&[])?, Some(NP_Enum::new("closed")));
+//! 
+//! // numbers that aren't in the schema can't be set
+//! assert!(new_buffer.set(&[], 12i64).is_err());
+//!
+//! # Ok::<(), NP_Error>(()) 
+//! ```
+//! 
 
 use crate::{memory::NP_Memory, schema::{NP_Parsed_Schema}};
 use alloc::vec::Vec;
@@ -83,20 +113,50 @@ impl Default for NP_Enum {
      }
 }
 
+// choice count marking the extended schema layout, normal schemas have at most 254 choices
+const EXTENDED: u8 = 255;
+const WIDE_FLAG: u8 = 1;
+const VALUES_FLAG: u8 = 2;
+
+/// Find the choice with this number in an option schema
+pub(crate) fn choice_of_value(schema: &NP_Parsed_Schema, number: i64) -> Option<NP_Enum> {
+    match schema {
+        NP_Parsed_Schema::Enum { choices, values, .. } => values.iter().position(|value| *value == number).map(|idx| choices[idx].clone()),
+        _ => None
+    }
+}
+
+/// Number a choice gets when the schema doesn't give it one
+fn next_value(values: &[i64]) -> Option<i64> {
+    match values.last() {
+        Some(last) => last.checked_add(1),
+        None => Some(0)
+    }
+}
+
 impl<'value> NP_Value<'value> for NP_Enum {
 
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("option", NP_TypeKeys::Enum) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("option", NP_TypeKeys::Enum) }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("option", NP_TypeKeys::Enum) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("option", NP_TypeKeys::Enum) }
 
     fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
         let mut schema_json = JSMAP::new();
         schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
 
         match &schema[address] {
-            NP_Parsed_Schema::Enum { i: _, choices, default, sortable: _} => {
+            NP_Parsed_Schema::Enum { i: _, choices, values, wide, default, sortable: _} => {
 
-                let options: Vec<NP_JSON> = choices.into_iter().map(|value| {
-                    NP_JSON::String(value.to_string())
+                if *wide {
+                    schema_json.insert("wide".to_owned(), NP_JSON::True);
+                }
+
+                // only choices that don't count up from the one before need their number
+                let options: Vec<NP_JSON> = choices.iter().enumerate().map(|(idx, value)| {
+                    if next_value(&values[..idx]) == Some(values[idx]) {
+                        NP_JSON::String(value.to_string())
+                    } else {
+                        NP_JSON::Array(alloc::vec![NP_JSON::String(value.to_string()), NP_JSON::Integer(values[idx])])
+                    }
                 }).collect();
             
                 if let Some(d) = default {
@@ -116,7 +176,7 @@ impl<'value> NP_Value<'value> for NP_Enum {
     fn schema_default(schema: &NP_Parsed_Schema) -> Option<Self> {
 
         match schema {
-            NP_Parsed_Schema::Enum { i: _, choices: _, default, sortable: _} => {
+            NP_Parsed_Schema::Enum { default, .. } => {
                 if let Some(d) = default {
                     Some(d.clone())
                 } else {
@@ -132,45 +192,33 @@ impl<'value> NP_Value<'value> for NP_Enum {
         let c_value = cursor.get_value(memory);
 
         match &memory.schema[cursor.schema_addr] {
-            NP_Parsed_Schema::Enum { i: _, choices, default: _, sortable: _} => {
-
-                let mut value_num: i32 = -1;
-
-                {
-                    let mut ct: u16 = 0;
-        
-                    for opt in choices {
-                        if *opt == value {
-                            value_num = ct as i32;
-                        }
-                        ct += 1;
-                    };
-        
-                    if value_num == -1 {
-                        return Err(NP_Error::new("Option not found, cannot set uknown option!"));
+            NP_Parsed_Schema::Enum { choices, wide, .. } => {
+
+                let value_num = match choices.iter().position(|opt| *opt == value) {
+                    Some(x) => x,
+                    None => return Err(NP_Error::new_static("Option not found, cannot set uknown option!"))
+                };
+
+                if *wide {
+                    let bytes = (value_num as u16).to_be_bytes();
+                    let addr_value = c_value.get_addr_value() as usize;
+                    if addr_value != 0 {
+                        memory.write_bytes()[addr_value..(addr_value + 2)].copy_from_slice(&bytes);
+                    } else {
+                        let addr_value = memory.malloc_borrow(&bytes)?;
+                        cursor.get_value_mut(memory).set_addr_value(addr_value as u16);
                     }
+                    return Ok(cursor);
                 }
         
                 let bytes = value_num as u8;
 
-                let mut addr_value = c_value.get_addr_value() as usize;
-        
-                if addr_value != 0 { // existing value, replace
-        
-                    let write_bytes = memory.write_bytes();
-        
-                    write_bytes[addr_value] = bytes;
-                    return Ok(cursor);
-        
-                } else { // new value
-        
-                    addr_value = memory.malloc_borrow(&[bytes])?;
-                    c_value.set_addr_value(addr_value as u16);
+                let addr_value = memory.set_small(c_value.get_addr_value(), bytes)?;
+                cursor.get_value_mut(memory).set_addr_value(addr_value);
 
-                    return Ok(cursor);
-                }     
+                Ok(cursor)
             },
-            _ => Err(NP_Error::new("unreachable"))
+            _ => Err(NP_Error::new_static("unreachable"))
         }               
     }
 
@@ -178,20 +226,20 @@ impl<'value> NP_Value<'value> for NP_Enum {
 
         let c_value = cursor.get_value(memory);
 
-        let value_addr = c_value.get_addr_value() as usize;
-
-        // empty value
-        if value_addr == 0 {
-            return Ok(None);
-        }
-  
         match &memory.schema[cursor.schema_addr] {
-            NP_Parsed_Schema::Enum { i: _, choices, default: _, sortable: _} => {
-                Ok(match memory.get_1_byte(value_addr) {
-                    Some(x) => {
-                        let value_num = x as usize;
-        
-                        if value_num > choices.len() {
+            NP_Parsed_Schema::Enum { choices, wide, .. } => {
+                let index = if *wide {
+                    match c_value.get_addr_value() as usize {
+                        0 => None,
+                        addr => Some(u16::from_be_bytes([memory.read_bytes()[addr], memory.read_bytes()[addr + 1]]) as usize)
+                    }
+                } else {
+                    memory.get_small(c_value.get_addr_value()).map(|x| x as usize)
+                };
+
+                Ok(match index {
+                    Some(value_num) => {
+                        if value_num >= choices.len() {
                             None
                         } else {
                             Some(choices[value_num].clone())
@@ -200,7 +248,7 @@ impl<'value> NP_Value<'value> for NP_Enum {
                     None => None
                 })
             },
-            _ => Err(NP_Error::new("unreachable"))
+            _ => Err(NP_Error::new_static("unreachable"))
         }
     }
 
@@ -216,7 +264,7 @@ impl<'value> NP_Value<'value> for NP_Enum {
                             },
                             NP_Enum::None => {
                                 match &memory.schema[cursor.schema_addr] {
-                                    NP_Parsed_Schema::Enum { i: _, choices: _, default, sortable: _} => {
+                                    NP_Parsed_Schema::Enum { default, .. } => {
                                         if let Some(d) = default {
                                             match d {
                                                 NP_Enum::Some(val) => {
@@ -237,7 +285,7 @@ impl<'value> NP_Value<'value> for NP_Enum {
                     },
                     None => {
                         match &memory.schema[cursor.schema_addr] {
-                            NP_Parsed_Schema::Enum { i: _, choices: _, default, sortable: _} => {
+                            NP_Parsed_Schema::Enum { default, .. } => {
                                 if let Some(d) = default {
                                     match d {
                                         NP_Enum::Some(x) => NP_JSON::String(x.clone()),
@@ -261,12 +309,9 @@ impl<'value> NP_Value<'value> for NP_Enum {
     fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
         let c_value = cursor.get_value(memory);
 
-        let value_address = c_value.get_addr_value() as usize;
-
-        if value_address == 0 {
-            return Ok(0) 
-        } else {
-            Ok(core::mem::size_of::<u8>())
+        match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Enum { wide: true, .. } => Ok(if c_value.get_addr_value() == 0 { 0 } else { 2 }),
+            _ => Ok(memory.small_size(c_value.get_addr_value()))
         }
     }
 
@@ -277,6 +322,14 @@ impl<'value> NP_Value<'value> for NP_Enum {
         schema_data.push(NP_TypeKeys::Enum as u8);
 
         let mut choices: Vec<NP_Enum> = Vec::new();
+        let mut values: Vec<i64> = Vec::new();
+        let mut numbered = false;
+
+        let wide = match &json_schema["wide"] {
+            NP_JSON::True => true,
+            NP_JSON::False | NP_JSON::Null => false,
+            _ => return Err(NP_Error::new_schema("'option' wide must be true or false!"))
+        };
 
         let mut default_stir: Option<String> = None;
 
@@ -288,55 +341,90 @@ impl<'value> NP_Value<'value> for NP_Enum {
         }
 
         let mut default_value: Option<NP_Enum> = None;
-        let mut default_index: Option<u8> = None;
+        let mut default_index: Option<u16> = None;
 
         match &json_schema["choices"] {
             NP_JSON::Array(x) => {
                 for opt in x {
-                    match opt {
-                        NP_JSON::String(stir) => {
-                            if stir.len() > 255 {
-                                return Err(NP_Error::new("'option' choices cannot be longer than 255 characters each!"))
-                            }
-
-                            if let Some(def) = &default_stir {
-                                if def == stir {
-                                    default_value = Some(NP_Enum::new(def.clone()));
-                                    default_index = Some(choices.len() as u8);
-                                }
-                            }
-                            choices.push(NP_Enum::new(stir.clone()));
+                    let (stir, value) = match opt {
+                        NP_JSON::String(stir) => (stir, next_value(&values)),
+                        NP_JSON::Array(pair) => match pair.as_slice() {
+                            [NP_JSON::String(stir), NP_JSON::Integer(value)] => {
+                                numbered = true;
+                                (stir, Some(*value))
+                            },
+                            _ => return Err(NP_Error::new_schema("'option' numbered choices must look like [\"name\", number]!"))
                         },
-                        _ => {}
+                        _ => continue
+                    };
+
+                    if stir.len() > 255 {
+                        return Err(NP_Error::new_schema("'option' choices cannot be longer than 255 characters each!"))
                     }
+
+                    let value = match value {
+                        Some(x) if !values.contains(&x) => x,
+                        _ => return Err(NP_Error::new_schema(alloc::format!("'option' choice '{}' needs a number that isn't used by another choice!", stir)))
+                    };
+
+                    if let Some(def) = &default_stir {
+                        if def == stir {
+                            default_value = Some(NP_Enum::new(def.clone()));
+                            default_index = Some(choices.len() as u16);
+                        }
+                    }
+                    choices.push(NP_Enum::new(stir.clone()));
+                    values.push(value);
                 }
             },
             _ => {
-                return Err(NP_Error::new("'option' type requires a 'choices' key with an array of strings!"))
+                return Err(NP_Error::new_schema("'option' type requires a 'choices' key with an array of strings!"))
             }
         }
 
-        if choices.len() > 254 {
-            return Err(NP_Error::new("'option' type cannot have more than 254 choices!"))
-        }
-
-        // default value
-        match &default_index {
-            Some(x) => schema_data.push(*x + 1),
-            None => schema_data.push(0)
+        if wide {
+            if choices.len() > 65534 {
+                return Err(NP_Error::new_schema("Wide 'option' type cannot have more than 65,534 choices!"))
+            }
+        } else if choices.len() > 254 {
+            return Err(NP_Error::new_schema("'option' type cannot have more than 254 choices, use \"wide\": true for more!"))
         }
 
-        // choices
-        schema_data.push(choices.len() as u8);
-        for choice in &choices {
-            schema_data.push(choice.len() as u8);
-            schema_data.extend(choice.as_bytes().to_vec())
+        let default_byte = match &default_index {
+            Some(x) => *x + 1,
+            None => 0
+        };
+
+        if wide || numbered {
+            // extended layout: [key, 0, 255, flags, default u16, choices u16, (len, choice, number i64)...]
+            schema_data.extend_from_slice(&[0, EXTENDED, if wide { WIDE_FLAG } else { 0 } | if numbered { VALUES_FLAG } else { 0 }]);
+            schema_data.extend_from_slice(&default_byte.to_be_bytes());
+            schema_data.extend_from_slice(&(choices.len() as u16).to_be_bytes());
+            for (choice, value) in choices.iter().zip(values.iter()) {
+                schema_data.push(choice.len() as u8);
+                schema_data.extend(choice.as_bytes().to_vec());
+                if numbered {
+                    schema_data.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+        } else {
+            // default value
+            schema_data.push(default_byte as u8);
+
+            // choices
+            schema_data.push(choices.len() as u8);
+            for choice in &choices {
+                schema_data.push(choice.len() as u8);
+                schema_data.extend(choice.as_bytes().to_vec())
+            }
         }
 
         schema.push(NP_Parsed_Schema::Enum { 
             i: NP_TypeKeys::Enum,
             default: default_value,
             choices: choices,
+            values,
+            wide,
             sortable: true
         });
 
@@ -345,17 +433,24 @@ impl<'value> NP_Value<'value> for NP_Enum {
     }
 
     fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
-        let mut default_index: Option<u8> = None;
+        let mut default_index: Option<u16> = None;
         let mut default_value: Option<NP_Enum> = None;
 
-        if bytes[address + 1] > 0 {
-            default_index = Some(bytes[address + 1] - 1);
-        }
+        let extended = bytes[address + 2] == EXTENDED;
+        let flags = if extended { bytes[address + 3] } else { 0 };
+
+        let (default_byte, choices_len, mut offset) = if extended {
+            (u16::from_be_bytes([bytes[address + 4], bytes[address + 5]]), u16::from_be_bytes([bytes[address + 6], bytes[address + 7]]), address + 8)
+        } else {
+            (bytes[address + 1] as u16, bytes[address + 2] as u16, address + 3)
+        };
 
-        let choices_len = bytes[address + 2];
+        if default_byte > 0 {
+            default_index = Some(default_byte - 1);
+        }
 
         let mut choices: Vec<NP_Enum> = Vec::new();
-        let mut offset: usize = address + 3;
+        let mut values: Vec<i64> = Vec::new();
         for x in 0..choices_len {
             let choice_size = bytes[offset] as usize;
             let choice_bytes = &bytes[(offset + 1)..(offset + 1 + choice_size)];
@@ -363,6 +458,15 @@ impl<'value> NP_Value<'value> for NP_Enum {
             choices.push(NP_Enum::new(choice_string.to_string()));
             offset += 1 + choice_size;
 
+            if flags & VALUES_FLAG != 0 {
+                let mut value_bytes = [0u8; 8];
+                value_bytes.copy_from_slice(&bytes[offset..(offset + 8)]);
+                values.push(i64::from_be_bytes(value_bytes));
+                offset += 8;
+            } else {
+                values.push(x as i64);
+            }
+
             if let Some(def) = default_index {
                 if def == x {
                     default_value = Some(NP_Enum::new(choice_string.to_string()));
@@ -374,7 +478,9 @@ impl<'value> NP_Value<'value> for NP_Enum {
             i: NP_TypeKeys::Enum,
             sortable: true,
             default: default_value,
-            choices: choices
+            choices: choices,
+            values,
+            wide: flags & WIDE_FLAG != 0
         });
 
         (true, schema)
@@ -419,4 +525,40 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn wide_and_numbered_options_work() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"option\",\"wide\":true,\"default\":\"b\",\"choices\":[\"a\",[\"b\",5],\"c\",[\"d\",-2]]}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    assert_eq!(factory.schema.root().choice_values(), Some(alloc::vec![0, 5, 6, -2]));
+    assert_eq!(factory.schema.root().fixed_size(), Some(2));
+    let factory = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get::<NP_Enum>(&[])?, Some(NP_Enum::new("b")));
+    buffer.set(&[], 6u8)?;
+    assert_eq!(buffer.get::<NP_Enum>(&[])?, Some(NP_Enum::new("c")));
+    assert_eq!(buffer.calc_bytes()?.current_buffer, 5usize);
+    buffer.set(&[], NP_Enum::new("d"))?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "\"d\"");
+    assert!(buffer.set(&[], 1i64).is_err());
+    buffer.compact(None)?;
+    assert_eq!(buffer.get::<NP_Enum>(&[])?, Some(NP_Enum::new("d")));
+
+    // more choices than fit in one byte
+    let choices: Vec<String> = (0..1000).map(|x| alloc::format!("\"{}\"", x)).collect();
+    let narrow = alloc::format!("{{\"type\":\"option\",\"choices\":[{}]}}", choices.join(","));
+    assert!(crate::NP_Factory::new(narrow.as_str()).is_err());
+    let wide = crate::NP_Factory::new(narrow.replace("\"choices\"", "\"wide\":true,\"choices\"").as_str())?;
+    let mut buffer = wide.empty_buffer(None);
+    buffer.set(&[], NP_Enum::new("999"))?;
+    assert_eq!(buffer.get::<NP_Enum>(&[])?, Some(NP_Enum::new("999")));
+
+    // numbers can't repeat
+    assert!(crate::NP_Factory::new("{\"type\":\"option\",\"choices\":[[\"a\",1],[\"b\",0],\"c\"]}").is_err());
+    assert!(crate::NP_Factory::new("{\"type\":\"option\",\"choices\":[[\"a\",\"1\"]]}").is_err());
+
+    Ok(())
+}
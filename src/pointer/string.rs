@@ -15,12 +15,71 @@
 //!
 //! # Ok::<(), NP_Error>(())
 //! ```
+//!
+//! ## Padding
+//! Strings with a `size` are padded with spaces on the right by default, and the padding is returned when they're read.
+//!
+//! - `"pad": "left"` puts the padding before the value, so numeric strings sort by their value.
+//! - `"pad_byte"` sets the padding to any ascii byte, use `0` to keep trailing spaces in values.
+//! - `"trim": true` removes the padding when the value is read.
+//!
+//! ## Collation
+//! Fixed size strings sort by their utf-8 bytes, so `"Zebra"` comes before `"apple"`.  With `"collation": "unicode_ci"` a lowercased sort key (using the unicode lowercase mapping) is stored in front of each value, buffers then sort case insensitively and the original value is still returned when it's read.
+//! Values that only differ by case sort by their original bytes.  Unicode normalization is not applied, so precomposed and decomposed characters sort apart.
+//!
+//! ## Interning
+//! Strings without a `size` can set `"intern": true` to store repeated values once.  Setting a value that's identical to one interned earlier points at the existing copy instead of writing the bytes again, which is a big saving for labels repeated across long lists.
+//! Reading is unchanged (interned buffers can be read by older versions) and interned values are copied instead of updated in place.
+//! Only values set since the buffer was created or opened are shared, compaction shares every duplicate.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "list",
+//!    "of": {"type": "string", "intern": true}
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["0"], "pending")?;
+//! let size = new_buffer.calc_bytes()?.current_buffer;
+//!
+//! new_buffer.set(&["1"], "pending")?;
+//!
+//! // only the list item pointer was added
+//! assert_eq!(new_buffer.calc_bytes()?.current_buffer, size + 5);
+//! assert_eq!(new_buffer.get::<&str>(&["1"])?, Some("pending"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "string",
+//!    "size": 6,
+//!    "pad": "left",
+//!    "pad_byte": 0,
+//!    "trim": true
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&[], "42 ")?;
+//!
+//! assert_eq!("42 ", new_buffer.get::<&str>(&[])?.unwrap());
+//! assert_eq!(&[0, 0, 0, b'4', b'2', b' '], &new_buffer.read_bytes()[3..]);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
 
 use alloc::string::String;
-use alloc::prelude::v1::Box;
+use alloc::boxed::Box;
 use core::hint::unreachable_unchecked;
 
-use crate::{error::NP_Error, schema::String_Case};
+use crate::{error::NP_Error, schema::{String_Case, String_Collation}};
 use crate::{
     json_flex::JSMAP,
     memory::NP_Memory,
@@ -29,27 +88,38 @@ use crate::{
 use crate::{json_flex::NP_JSON, pointer::NP_Value, schema::NP_TypeKeys};
 use alloc::vec::Vec;
 
-use super::{NP_Cursor, NP_Scalar};
+use super::{NP_Cursor, NP_Scalar, any::NP_Dyn};
 use alloc::borrow::ToOwned;
 use core::str;
 use alloc::string::ToString;
 
+// flags packed into the case byte of the compiled schema
+const INTERN_BIT: u8 = 0x08;
+const PAD_LEFT_BIT: u8 = 0x10;
+const TRIM_BIT: u8 = 0x20;
+// a custom pad byte follows the default value
+const PAD_BYTE_BIT: u8 = 0x40;
+const COLLATE_BIT: u8 = 0x80;
+
 /// &str type alias
 pub type NP_String<'string> = &'string str;
 
 impl NP_Scalar for &str {}
 
 impl<'value> NP_Value<'value> for &'value str {
-    fn type_idx() -> (&'value str, NP_TypeKeys) {
+    fn type_idx() -> (&'static str, NP_TypeKeys) {
         ("string", NP_TypeKeys::UTF8String)
     }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) {
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) {
         ("string", NP_TypeKeys::UTF8String)
     }
+    fn as_dyn(&self) -> Option<NP_Dyn<'_>> {
+        Some(NP_Dyn::Str(self))
+    }
 
     fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<NP_JSON, NP_Error> {
         match &schema[address] {
-            NP_Parsed_Schema::UTF8String { size, default, case, ..} => {
+            NP_Parsed_Schema::UTF8String { size, default, case, pad_byte, pad_left, trim, collation, intern, ..} => {
                 let mut schema_json = JSMAP::new();
                 schema_json.insert(
                     "type".to_owned(),
@@ -70,6 +140,26 @@ impl<'value> NP_Value<'value> for &'value str {
                     schema_json.insert("size".to_owned(), NP_JSON::Integer(size.clone().into()));
                 }
 
+                if *pad_left {
+                    schema_json.insert("pad".to_owned(), NP_JSON::String("left".to_owned()));
+                }
+
+                if *pad_byte != 32 {
+                    schema_json.insert("pad_byte".to_owned(), NP_JSON::Integer((*pad_byte).into()));
+                }
+
+                if *trim {
+                    schema_json.insert("trim".to_owned(), NP_JSON::True);
+                }
+
+                if *collation == String_Collation::UnicodeCi {
+                    schema_json.insert("collation".to_owned(), NP_JSON::String("unicode_ci".to_owned()));
+                }
+
+                if *intern {
+                    schema_json.insert("intern".to_owned(), NP_JSON::True);
+                }
+
                 if let Some(default_value) = default {
                     schema_json.insert(
                         "default".to_owned(),
@@ -85,8 +175,9 @@ impl<'value> NP_Value<'value> for &'value str {
 
     fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
 
-        // case byte
-        let case_byte = String_Case::from(bytes[address + 1]);
+        // case byte, padding flags are in the upper bits
+        let flags = bytes[address + 1];
+        let case_byte = String_Case::from(flags & 0x03);
 
         // fixed size
         let fixed_size = u16::from_be_bytes([bytes[address + 2], bytes[address + 3]]);
@@ -94,6 +185,15 @@ impl<'value> NP_Value<'value> for &'value str {
         // default value size
         let default_size = u16::from_be_bytes([bytes[address + 4], bytes[address + 5]]) as usize;
 
+        let collation = if flags & COLLATE_BIT != 0 { String_Collation::UnicodeCi } else { String_Collation::None };
+
+        let pad_byte = if flags & PAD_BYTE_BIT != 0 {
+            let pad_addr = address + 6 + if default_size == 0 { 0 } else { default_size - 1 };
+            bytes[pad_addr]
+        } else {
+            32
+        };
+
         if default_size == 0 {
             schema.push(NP_Parsed_Schema::UTF8String {
                 i: NP_TypeKeys::UTF8String,
@@ -101,6 +201,11 @@ impl<'value> NP_Value<'value> for &'value str {
                 case: case_byte,
                 sortable: fixed_size > 0,
                 size: fixed_size,
+                pad_byte: pad_byte,
+                pad_left: flags & PAD_LEFT_BIT != 0,
+                trim: flags & TRIM_BIT != 0,
+                collation: collation,
+                intern: flags & INTERN_BIT != 0,
             })
         } else {
             let default_bytes = str::from_utf8(&bytes[(address + 6)..(address + 6 + (default_size - 1))]).unwrap_or_default();
@@ -111,6 +216,11 @@ impl<'value> NP_Value<'value> for &'value str {
                 size: fixed_size,
                 case: case_byte,
                 sortable: fixed_size > 0,
+                pad_byte: pad_byte,
+                pad_left: flags & PAD_LEFT_BIT != 0,
+                trim: flags & TRIM_BIT != 0,
+                collation: collation,
+                intern: flags & INTERN_BIT != 0,
             })
         }
 
@@ -128,14 +238,26 @@ impl<'value> NP_Value<'value> for &'value str {
         }
 
         match memory.schema[cursor.schema_addr] {
-            NP_Parsed_Schema::UTF8String { size, .. } => {
+            NP_Parsed_Schema::UTF8String { size, pad_byte, pad_left, trim, collation, .. } => {
                 if size > 0 {
                     // fixed size
 
+                    // the value is after the sort key
+                    let value_addr = if collation == String_Collation::UnicodeCi { value_addr + size as usize } else { value_addr };
+
                     // get bytes
-                    let bytes = &memory.read_bytes()[(value_addr)..(value_addr + (size as usize))];
+                    let bytes = match memory.read_bytes().get((value_addr)..(value_addr + (size as usize))) {
+                        Some(x) => x,
+                        None => return Ok(None) // attack
+                    };
 
-                    return Ok(Some(unsafe { str::from_utf8_unchecked(bytes) }));
+                    let value = unsafe { str::from_utf8_unchecked(bytes) };
+
+                    if trim {
+                        return Ok(Some(trim_padding(value, pad_byte, pad_left)));
+                    }
+
+                    return Ok(Some(value));
                 } else {
                     // dynamic size
                     // get size of bytes
@@ -143,12 +265,15 @@ impl<'value> NP_Value<'value> for &'value str {
                     let bytes_size: usize = u16::from_be_bytes(*memory.get_2_bytes(value_addr).unwrap_or(&[0u8; 2])) as usize;
 
                     // get bytes
-                    let bytes = &memory.read_bytes()[(value_addr + 2)..(value_addr + 2 + bytes_size)];
+                    let bytes = match memory.read_bytes().get((value_addr + 2)..(value_addr + 2 + bytes_size)) {
+                        Some(x) => x,
+                        None => return Ok(None) // attack
+                    };
 
                     return Ok(Some(unsafe { str::from_utf8_unchecked(bytes) }));
                 }
             }
-            _ => Err(NP_Error::new("unreachable")),
+            _ => Err(NP_Error::new_static("unreachable")),
         }
     }
 
@@ -172,10 +297,15 @@ impl<'value> NP_Value<'value> for &'value str {
         }
 
         match memory.schema[cursor.schema_addr] {
-            NP_Parsed_Schema::UTF8String { size, .. } => {
-                // fixed size
+            NP_Parsed_Schema::UTF8String { size, collation, intern, .. } => {
+                // fixed size, collated strings have a sort key of the same size
                 if size > 0 {
-                    return Ok(size as usize);
+                    return Ok(if collation == String_Collation::UnicodeCi { size as usize * 2 } else { size as usize });
+                }
+
+                // shared values are only counted once when measuring the buffer
+                if (intern || memory.has_shared()) && memory.first_count(value_addr) == false {
+                    return Ok(0);
                 }
 
                 // dynamic size
@@ -184,7 +314,7 @@ impl<'value> NP_Value<'value> for &'value str {
                 // return total size of this string plus length bytes
                 return Ok(bytes_size + 2);
             }
-            _ => Err(NP_Error::new("unreachable")),
+            _ => Err(NP_Error::new_static("unreachable")),
         }
     }
 
@@ -207,10 +337,59 @@ impl<'value> NP_Value<'value> for &'value str {
         }
 
         if set == 2 {
-            return Err(NP_Error::new("Only one of uppercase and lowercase can be set!"));
+            return Err(NP_Error::new_schema("Only one of uppercase and lowercase can be set!"));
         }
 
-        schema_data.push(case_byte as u8);
+        let mut flags = case_byte as u8;
+
+        let pad_left = match &json_schema["pad"] {
+            NP_JSON::String(side) => match side.as_str() {
+                "left" => true,
+                "right" => false,
+                _ => return Err(NP_Error::new_schema("String pad must be \"left\" or \"right\"!"))
+            },
+            NP_JSON::Null => false,
+            _ => return Err(NP_Error::new_schema("String pad must be \"left\" or \"right\"!"))
+        };
+
+        let pad_byte = match &json_schema["pad_byte"] {
+            NP_JSON::Integer(x) => {
+                if *x < 0 || *x > 127 {
+                    return Err(NP_Error::new_schema("String pad_byte must be an ascii byte (0 - 127)!"));
+                }
+                *x as u8
+            },
+            NP_JSON::Null => 32,
+            _ => return Err(NP_Error::new_schema("String pad_byte must be an ascii byte (0 - 127)!"))
+        };
+
+        let trim = match &json_schema["trim"] {
+            NP_JSON::True => true,
+            _ => false
+        };
+
+        let collation = match &json_schema["collation"] {
+            NP_JSON::String(name) => match name.as_str() {
+                "unicode_ci" => String_Collation::UnicodeCi,
+                "bytes" => String_Collation::None,
+                _ => return Err(NP_Error::new_schema("String collation must be \"bytes\" or \"unicode_ci\"!"))
+            },
+            NP_JSON::Null => String_Collation::None,
+            _ => return Err(NP_Error::new_schema("String collation must be \"bytes\" or \"unicode_ci\"!"))
+        };
+
+        if pad_left { flags |= PAD_LEFT_BIT; }
+        if trim { flags |= TRIM_BIT; }
+        let intern = match &json_schema["intern"] {
+            NP_JSON::True => true,
+            _ => false
+        };
+
+        if pad_byte != 32 { flags |= PAD_BYTE_BIT; }
+        if intern { flags |= INTERN_BIT; }
+        if collation == String_Collation::UnicodeCi { flags |= COLLATE_BIT; }
+
+        schema_data.push(flags);
 
         let mut has_fixed_size = false;
 
@@ -218,12 +397,12 @@ impl<'value> NP_Value<'value> for &'value str {
             NP_JSON::Integer(x) => {
                 has_fixed_size = true;
                 if x < 1 {
-                    return Err(NP_Error::new(
+                    return Err(NP_Error::new_schema(
                         "Fixed size for string must be larger than 1!",
                     ));
                 }
                 if x > u16::MAX.into() {
-                    return Err(NP_Error::new(
+                    return Err(NP_Error::new_schema(
                         "Fixed size for string cannot be larger than 2^16!",
                     ));
                 }
@@ -233,12 +412,12 @@ impl<'value> NP_Value<'value> for &'value str {
             NP_JSON::Float(x) => {
                 has_fixed_size = true;
                 if x < 1.0 {
-                    return Err(NP_Error::new(
+                    return Err(NP_Error::new_schema(
                         "Fixed size for string must be larger than 1!",
                     ));
                 }
                 if x > u16::MAX.into() {
-                    return Err(NP_Error::new(
+                    return Err(NP_Error::new_schema(
                         "Fixed size for string cannot be larger than 2^16!",
                     ));
                 }
@@ -256,7 +435,7 @@ impl<'value> NP_Value<'value> for &'value str {
             NP_JSON::String(bytes) => {
                 let str_bytes = bytes.clone().into_bytes();
                 if str_bytes.len() > u16::max as usize - 1 {
-                    return Err(NP_Error::new(
+                    return Err(NP_Error::new_schema(
                         "Default string value cannot be larger than 2^16 bytes!",
                     ));
                 }
@@ -270,12 +449,33 @@ impl<'value> NP_Value<'value> for &'value str {
             }
         };
 
+        if (pad_left || trim || pad_byte != 32) && has_fixed_size == false {
+            return Err(NP_Error::new_schema("String padding options need a fixed size!"));
+        }
+
+        if collation == String_Collation::UnicodeCi && has_fixed_size == false {
+            return Err(NP_Error::new_schema("String collation needs a fixed size!"));
+        }
+
+        if intern && has_fixed_size {
+            return Err(NP_Error::new_schema("Fixed size strings can't be interned!"));
+        }
+
+        if pad_byte != 32 {
+            schema_data.push(pad_byte);
+        }
+
         schema.push(NP_Parsed_Schema::UTF8String {
             i: NP_TypeKeys::UTF8String,
             size: size,
             default: default,
             case: case_byte,
             sortable: has_fixed_size,
+            pad_byte: pad_byte,
+            pad_left: pad_left,
+            trim: trim,
+            collation: collation,
+            intern: intern,
         });
 
         return Ok((has_fixed_size, schema_data, schema));
@@ -304,9 +504,9 @@ impl<'value> NP_Value<'value> for &'value str {
 
         let c_value = cursor.get_value(memory);
 
-        let (size, case) = match memory.schema[cursor.schema_addr] {
-            NP_Parsed_Schema::UTF8String { size, case, .. } => (size, case),
-            _ => (0, String_Case::None)
+        let (size, case, pad_byte, pad_left, collation, intern) = match memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::UTF8String { size, case, pad_byte, pad_left, collation, intern, .. } => (size, case, pad_byte, pad_left, collation, intern),
+            _ => (0, String_Case::None, 32, false, String_Collation::None, false)
         };
 
         let mut bytes = value.as_bytes();
@@ -328,39 +528,54 @@ impl<'value> NP_Value<'value> for &'value str {
     
         let str_size = bytes.len() as usize;
     
-        let write_bytes = memory.write_bytes();
-    
 
         if size > 0 {
             // fixed size bytes
     
+            let collated = collation == String_Collation::UnicodeCi;
+
             if c_value.get_addr_value() == 0 {
-                // malloc new bytes
+                // malloc new bytes, collated strings have room for the sort key
+                let total = if collated { size as usize * 2 } else { size as usize };
     
-                let mut empty_bytes: Vec<u8> = Vec::with_capacity(size as usize);
-                for _x in 0..size {
-                    empty_bytes.push(32); // white space
+                let mut empty_bytes: Vec<u8> = Vec::with_capacity(total);
+                for _x in 0..total {
+                    empty_bytes.push(pad_byte);
                 }
     
                 let new_addr = memory.malloc(empty_bytes)? as usize;
-                c_value.set_addr_value(new_addr as u16);
+                cursor.get_value_mut(memory).set_addr_value(new_addr as u16);
             }
 
-            let addr = c_value.get_addr_value() as usize;
-    
-            for x in 0..(size as usize) {
-                if x < bytes.len() {
-                    // assign values of bytes
-                    write_bytes[(addr + x)] = bytes[x];
-                } else {
-                    // rest is white space
-                    write_bytes[(addr + x)] = 32;
-                }
+            let mut addr = cursor.get_value(memory).get_addr_value() as usize;
+
+            let write_bytes = memory.write_bytes();
+
+            if collated {
+                let key = collation_key(bytes, size as usize);
+                write_padded(&mut write_bytes[addr..(addr + size as usize)], key.as_bytes(), pad_byte, pad_left);
+                addr += size as usize;
             }
+
+            write_padded(&mut write_bytes[addr..(addr + size as usize)], bytes, pad_byte, pad_left);
     
             return Ok(cursor);
         }
     
+        if str_size > core::u16::MAX as usize {
+            return Err(NP_Error::new_static("String too large!"));
+        }
+
+        // interned and deduplicated values are shared, always point at a copy
+        if intern || memory.is_deduping() {
+            let mut interned: Vec<u8> = Vec::with_capacity(str_size + 2);
+            interned.extend_from_slice(&(str_size as u16).to_be_bytes());
+            interned.extend_from_slice(bytes);
+            let new_addr = if memory.is_deduping() { memory.malloc_dedupe(&interned)? } else { memory.malloc_interned(&interned)? };
+            cursor.get_value_mut(memory).set_addr_value(new_addr as u16);
+            return Ok(cursor);
+        }
+
         // flexible size
         let addr_value = c_value.get_addr_value() as usize;
     
@@ -371,14 +586,15 @@ impl<'value> NP_Value<'value> for &'value str {
             0 as usize
         };
     
-        if prev_size >= str_size as usize {
+        if prev_size >= str_size as usize && memory.has_shared() == false {
             // previous string is larger than this one, use existing memory
     
             // update string length in buffer
             if str_size > core::u16::MAX as usize {
-                return Err(NP_Error::new("String too large!"));
+                return Err(NP_Error::new_static("String too large!"));
             }
             let size_bytes = (str_size as u16).to_be_bytes();
+            let write_bytes = memory.write_bytes();
             // set string size
             for x in 0..size_bytes.len() {
                 write_bytes[(addr_value + x)] = size_bytes[x];
@@ -398,13 +614,13 @@ impl<'value> NP_Value<'value> for &'value str {
             // first bytes are string length
             let new_addr = {
                 if str_size > core::u16::MAX as usize {
-                    return Err(NP_Error::new("String too large!"));
+                    return Err(NP_Error::new_static("String too large!"));
                 }
                 let size_bytes = (str_size as u16).to_be_bytes();
                 memory.malloc_borrow(&size_bytes)?
             };
     
-            c_value.set_addr_value(new_addr as u16);
+            cursor.get_value_mut(memory).set_addr_value(new_addr as u16);
     
             memory.malloc_borrow(bytes)?;
     
@@ -413,6 +629,169 @@ impl<'value> NP_Value<'value> for &'value str {
     }
 }
 
+// apply the schema casing to new string bytes
+fn with_case(value: &str, case: String_Case) -> String {
+    let mut owned = String::from(value);
+    match case {
+        String_Case::Uppercase => owned.make_ascii_uppercase(),
+        String_Case::Lowercase => owned.make_ascii_lowercase(),
+        String_Case::None => { }
+    }
+    owned
+}
+
+// write a fixed size value, the rest of the space is padding
+fn write_padded(dest: &mut [u8], bytes: &[u8], pad_byte: u8, pad_left: bool) {
+    let size = dest.len();
+
+    // padding goes before the value when padding on the left
+    let offset = if pad_left { size.saturating_sub(bytes.len()) } else { 0 };
+
+    for x in 0..size {
+        if x >= offset && x - offset < bytes.len() {
+            dest[x] = bytes[x - offset];
+        } else {
+            dest[x] = pad_byte;
+        }
+    }
+}
+
+// lowercased sort key for `unicode_ci` collation, cut to `size` bytes on a character boundary
+fn collation_key(bytes: &[u8], size: usize) -> String {
+    let value = match str::from_utf8(bytes) {
+        Ok(x) => x,
+        Err(e) => unsafe { str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) }
+    };
+
+    let mut key = String::with_capacity(size);
+    for c in value.chars().flat_map(|c| c.to_lowercase()) {
+        if key.len() + c.len_utf8() > size {
+            break;
+        }
+        key.push(c);
+    }
+    key
+}
+
+// remove fixed size padding from a value
+fn trim_padding(value: &str, pad_byte: u8, pad_left: bool) -> &str {
+    let pad = pad_byte as char;
+    if pad_left { value.trim_start_matches(pad) } else { value.trim_end_matches(pad) }
+}
+
+// the current value for fixed size or unset strings, fixed size padding is removed
+fn current_value(cursor: &NP_Cursor, memory: &NP_Memory) -> Result<String, NP_Error> {
+    let value = match <&str>::into_value(cursor, memory)? {
+        Some(x) => x,
+        None => <&str>::schema_default(&memory.schema[cursor.schema_addr]).unwrap_or("")
+    };
+    match memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::UTF8String { size, pad_byte, pad_left, .. } if size > 0 => {
+            Ok(String::from(trim_padding(value, pad_byte, pad_left)))
+        },
+        _ => Ok(String::from(value))
+    }
+}
+
+/// Append to the string at the cursor, returns the new length in bytes.
+/// 
+/// If the string is the last value in the buffer it's extended in place, otherwise it's copied to the end of the buffer once.
+#[doc(hidden)]
+pub fn append(cursor: NP_Cursor, memory: &NP_Memory, more: &str) -> Result<usize, NP_Error> {
+
+    let (size, case, intern) = match memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::UTF8String { size, case, intern, .. } => (size, case, intern),
+        _ => return Err(NP_Error::new_static("unreachable"))
+    };
+
+    let more = with_case(more, case);
+
+    let addr_value = cursor.get_value(memory).get_addr_value() as usize;
+
+    // fixed size, shared strings and unset values are written in one go
+    if size > 0 || intern || memory.has_shared() || addr_value == 0 {
+        let mut value = current_value(&cursor, memory)?;
+        value.push_str(&more);
+        <&str>::set_value(cursor, memory, value.as_str())?;
+        return Ok(if size > 0 { usize::min(value.len(), size as usize) } else { value.len() });
+    }
+
+    let prev_size = u16::from_be_bytes(*memory.get_2_bytes(addr_value).unwrap_or(&[0u8; 2])) as usize;
+    let new_size = prev_size + more.len();
+
+    if new_size > core::u16::MAX as usize {
+        return Err(NP_Error::new_static("String too large!"));
+    }
+
+    let end = addr_value + 2 + prev_size;
+
+    if end == memory.read_bytes().len() {
+        // last value in the buffer, grow it in place
+        memory.malloc_borrow(more.as_bytes())?;
+        memory.write_bytes()[addr_value..(addr_value + 2)].copy_from_slice(&(new_size as u16).to_be_bytes());
+    } else {
+        let mut new_bytes: Vec<u8> = Vec::with_capacity(new_size + 2);
+        new_bytes.extend_from_slice(&(new_size as u16).to_be_bytes());
+        match memory.read_bytes().get((addr_value + 2)..end) {
+            Some(x) => new_bytes.extend_from_slice(x),
+            None => return Err(NP_Error::new_static("String extends past end of buffer!"))
+        }
+        new_bytes.extend_from_slice(more.as_bytes());
+        let new_addr = memory.malloc(new_bytes)?;
+        cursor.get_value_mut(memory).set_addr_value(new_addr as u16);
+    }
+
+    Ok(new_size)
+}
+
+/// Truncate the string at the cursor to `len` bytes, returns the new length in bytes.
+/// 
+/// Strings are always shortened in place, if the string is the last value in the buffer the buffer shrinks too.
+#[doc(hidden)]
+pub fn truncate(cursor: NP_Cursor, memory: &NP_Memory, len: usize) -> Result<usize, NP_Error> {
+
+    let (size, intern) = match memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::UTF8String { size, intern, .. } => (size, intern),
+        _ => return Err(NP_Error::new_static("unreachable"))
+    };
+
+    let addr_value = cursor.get_value(memory).get_addr_value() as usize;
+
+    if size > 0 || intern || memory.has_shared() || addr_value == 0 {
+        let value = current_value(&cursor, memory)?;
+        if len >= value.len() {
+            return Ok(value.len());
+        }
+        if value.is_char_boundary(len) == false {
+            return Err(NP_Error::new_static("Can't truncate a string in the middle of a character!"));
+        }
+        <&str>::set_value(cursor, memory, &value[..len])?;
+        return Ok(len);
+    }
+
+    let prev_size = u16::from_be_bytes(*memory.get_2_bytes(addr_value).unwrap_or(&[0u8; 2])) as usize;
+
+    if len >= prev_size {
+        return Ok(prev_size);
+    }
+
+    // utf8 continuation bytes start with 0b10
+    match memory.read_bytes().get(addr_value + 2 + len) {
+        Some(x) if x & 0xC0 == 0x80 => return Err(NP_Error::new_static("Can't truncate a string in the middle of a character!")),
+        Some(_) => { },
+        None => return Err(NP_Error::new_static("String extends past end of buffer!"))
+    }
+
+    // last value in the buffer, give the space back
+    if addr_value + 2 + prev_size == memory.read_bytes().len() {
+        memory.truncate(addr_value + 2 + len);
+    }
+
+    memory.write_bytes()[addr_value..(addr_value + 2)].copy_from_slice(&(len as u16).to_be_bytes());
+
+    Ok(len)
+}
+
 #[test]
 fn schema_parsing_works() -> Result<(), NP_Error> {
     let schema = "{\"type\":\"string\",\"default\":\"hello\"}";
@@ -435,6 +814,16 @@ fn schema_parsing_works() -> Result<(), NP_Error> {
     let factory = crate::NP_Factory::new(schema)?;
     assert_eq!(schema, factory.schema.to_json()?.stringify());
 
+    let schema = "{\"type\":\"string\",\"size\":10,\"pad\":\"left\",\"pad_byte\":48,\"trim\":true,\"default\":\"hi\"}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+
+    assert!(crate::NP_Factory::new(r#"{"type": "string", "trim": true}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type": "string", "size": 4, "pad": "middle"}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type": "string", "size": 4, "pad_byte": 200}"#).is_err());
+
     Ok(())
 }
 
@@ -459,6 +848,129 @@ fn fixed_size_works() -> Result<(), NP_Error> {
     Ok(())
 }
 
+#[test]
+fn padding_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["plain",  {"type": "string", "size": 5}],
+        ["zeros",  {"type": "string", "size": 5, "pad_byte": 0, "trim": true}],
+        ["number", {"type": "string", "size": 5, "pad": "left", "pad_byte": 48, "trim": true}]
+    ]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+
+    buffer.set(&["plain"], "ab ")?;
+    assert_eq!(buffer.get::<&str>(&["plain"])?, Some("ab   "));
+
+    // trailing spaces survive when they aren't the padding
+    buffer.set(&["zeros"], "ab ")?;
+    assert_eq!(buffer.get::<&str>(&["zeros"])?, Some("ab "));
+    buffer.set(&["zeros"], "abcdefg")?;
+    assert_eq!(buffer.get::<&str>(&["zeros"])?, Some("abcde"));
+
+    buffer.set(&["number"], "42")?;
+    assert_eq!(buffer.get::<&str>(&["number"])?, Some("42"));
+    assert_eq!(buffer.str_append(&["number"], "1")?, Some(3));
+    assert_eq!(buffer.get::<&str>(&["number"])?, Some("421"));
+
+    // left padded numbers sort by value
+    let factory = crate::NP_Factory::new(r#"{"type": "string", "size": 4, "pad": "left"}"#)?;
+    let mut small = factory.empty_buffer(None);
+    small.set(&[], "9")?;
+    let mut large = factory.empty_buffer(None);
+    large.set(&[], "10")?;
+    assert!(small.read_bytes() < large.read_bytes());
+
+    Ok(())
+}
+
+#[test]
+fn collation_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"string\",\"size\":8,\"collation\":\"unicode_ci\"}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, compiled.schema.to_json()?.stringify());
+
+    assert!(crate::NP_Factory::new(r#"{"type": "string", "collation": "unicode_ci"}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type": "string", "size": 4, "collation": "klingon"}"#).is_err());
+
+    let factory = crate::NP_Factory::new(r#"{"type": "tuple", "sorted": true, "values": [
+        {"type": "string", "size": 8, "collation": "unicode_ci"}
+    ]}"#)?;
+
+    let buffer = |value: &'static str| -> Result<crate::buffer::NP_Buffer, NP_Error> {
+        let mut buffer = factory.empty_buffer(None);
+        buffer.set(&["0"], value)?;
+        Ok(buffer)
+    };
+
+    let zebra = buffer("Zebra")?;
+    let apple = buffer("apple")?;
+    let eclair = buffer("ÉCLAIR")?;
+    let eclair_lower = buffer("éclair")?;
+
+    // original values are returned
+    assert_eq!(zebra.get::<&str>(&["0"])?, Some("Zebra   "));
+    assert_eq!(eclair.get::<&str>(&["0"])?, Some("ÉCLAIR "));
+
+    assert_eq!(apple.cmp_sortable(&zebra)?, core::cmp::Ordering::Less);
+    assert_eq!(zebra.cmp_sortable(&eclair)?, core::cmp::Ordering::Less);
+    // same key, ties broken by the original bytes
+    assert_eq!(eclair.cmp_sortable(&eclair_lower)?, core::cmp::Ordering::Less);
+
+    let mut compacted = buffer("Apple")?;
+    compacted.compact(None)?;
+    assert_eq!(compacted.get::<&str>(&["0"])?, Some("Apple   "));
+    assert_eq!(compacted.cmp_sortable(&apple)?, core::cmp::Ordering::Less);
+
+    Ok(())
+}
+
+#[test]
+fn interning_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"string\",\"intern\":true}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, compiled.schema.to_json()?.stringify());
+
+    assert!(crate::NP_Factory::new(r#"{"type": "string", "size": 4, "intern": true}"#).is_err());
+
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "string", "intern": true}}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["0"], "pending")?;
+    buffer.set(&["1"], "done")?;
+    let size = buffer.calc_bytes()?.current_buffer;
+    buffer.set(&["2"], "pending")?;
+    buffer.set(&["3"], "done")?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer, size + 10);
+    assert_eq!(buffer.calc_bytes()?.wasted_bytes, 0);
+
+    // shared values are never changed in place
+    buffer.set(&["0"], "done")?;
+    assert_eq!(buffer.str_append(&["2"], "!")?, Some(8));
+    assert_eq!(buffer.get::<&str>(&["0"])?, Some("done"));
+    assert_eq!(buffer.get::<&str>(&["1"])?, Some("done"));
+    assert_eq!(buffer.get::<&str>(&["2"])?, Some("pending!"));
+    assert_eq!(buffer.get::<&str>(&["3"])?, Some("done"));
+
+    // buffers with shared values pass validation
+    let bytes = buffer.close();
+    let buffer = factory.open_checked_buffer(bytes.clone())?;
+    assert_eq!(buffer.get::<&str>(&["3"])?, Some("done"));
+
+    // values written before the buffer was opened are shared after compaction
+    let mut buffer = factory.open_buffer(bytes);
+    buffer.set(&["4"], "pending!")?;
+    buffer.compact(None)?;
+    let compacted = buffer.calc_bytes()?.current_buffer;
+    buffer.set(&["5"], "done")?;
+    buffer.set(&["6"], "pending!")?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer, compacted + 10);
+    assert_eq!(buffer.get::<&str>(&["4"])?, Some("pending!"));
+
+    Ok(())
+}
+
 #[test]
 fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     let schema = "{\"type\":\"string\"}";
@@ -494,5 +1006,46 @@ fn uppercase_lowercase_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.get::<&str>(&[])?.unwrap(),"HELLO");
 
 
+    Ok(())
+}
+
+#[test]
+fn append_truncate_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["name",  {"type": "string", "default": "ab"}],
+        ["code",  {"type": "string", "size": 4}],
+        ["upper", {"type": "string", "uppercase": true}],
+        ["other", {"type": "u8"}]
+    ]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+
+    // unset strings start from the default
+    assert_eq!(buffer.str_append(&["name"], "c")?, Some(3));
+    buffer.set(&["other"], 1u8)?;
+
+    // not the last value, copied to the end once
+    let size = buffer.calc_bytes()?.current_buffer;
+    assert_eq!(buffer.str_append(&["name"], "dé")?, Some(6));
+    assert_eq!(buffer.calc_bytes()?.current_buffer, size + 8);
+    assert_eq!(buffer.get::<&str>(&["name"])?, Some("abcdé"));
+
+    assert!(buffer.str_truncate(&["name"], 5).is_err());
+    assert_eq!(buffer.str_truncate(&["name"], 10)?, Some(6));
+    assert_eq!(buffer.str_truncate(&["name"], 2)?, Some(2));
+    assert_eq!(buffer.calc_bytes()?.current_buffer, size + 4);
+    assert_eq!(buffer.get::<&str>(&["name"])?, Some("ab"));
+
+    assert_eq!(buffer.str_append(&["code"], "xy")?, Some(2));
+    assert_eq!(buffer.str_append(&["code"], "zzz")?, Some(4));
+    assert_eq!(buffer.get::<&str>(&["code"])?, Some("xyzz"));
+    assert_eq!(buffer.str_truncate(&["code"], 1)?, Some(1));
+    assert_eq!(buffer.get::<&str>(&["code"])?, Some("x   "));
+
+    buffer.str_append(&["upper"], "abc")?;
+    buffer.str_append(&["upper"], "def")?;
+    assert_eq!(buffer.get::<&str>(&["upper"])?, Some("ABCDEF"));
+
+    assert!(buffer.str_append(&["other"], "a").is_err());
+
     Ok(())
 }
@@ -0,0 +1,666 @@
+//! Represents a fixed shape tensor (or matrix) of numbers
+//!
+//! The data type and shape of a tensor are set in the schema, every value is stored contiguously in row major order.  Values are always stored in little endian byte order and aligned to the size of the data type, so on little endian machines the data can be borrowed as a numeric slice without copying it.
+//!
+//! The supported data types (`dtype`) are `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`, `f32` and `f64`.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::tensor::NP_Tensor;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "tensor",
+//!    "dtype": "f32",
+//!    "shape": [2, 3]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&[], NP_Tensor::new(&[2, 3], &[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0])?)?;
+//!
+//! let tensor = new_buffer.get::<NP_Tensor>(&[])?.unwrap();
+//! assert_eq!(tensor.shape(), &[2, 3]);
+//! assert_eq!(tensor.strides(), vec![3, 1]);
+//! assert_eq!(tensor.get::<f32>(&[1, 0]), Some(4.0));
+//! assert_eq!(tensor.to_vec::<f32>(), Some(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]));
+//!
+//! // zero copy access, only available on little endian machines
+//! if cfg!(target_endian = "little") {
+//!     assert_eq!(tensor.as_slice::<f32>(), Some(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0][..]));
+//! }
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! Tensors must fit inside a single buffer, so a tensor schema can't hold more than 65,535 bytes of data.
+//!
+//! ## Alignment
+//! Tensor data is always placed at an address inside the buffer that's a multiple of the `dtype` size, this holds after updates, compaction and when buffers are closed and opened again.  Buffer addresses are relative to the start of the buffer's `Vec<u8>`, every common allocator hands those out with at least 8 byte alignment so the data ends up naturally aligned in memory.
+//!
+//! `as_slice` still checks the real pointer before reinterpreting any bytes and returns `None` if the data isn't aligned or the machine is big endian, use `to_vec` or `get` to read the values on those platforms.
+//!
+
+use crate::{memory::NP_Memory, schema::{NP_Parsed_Schema}};
+use crate::json_flex::{JSMAP, NP_JSON};
+use crate::schema::{NP_TypeKeys};
+use crate::{pointer::NP_Value, error::NP_Error};
+use crate::pointer::NP_Scalar;
+use core::convert::TryFrom;
+
+use alloc::vec::Vec;
+use alloc::boxed::Box;
+use alloc::borrow::{Cow, ToOwned};
+use alloc::string::ToString;
+
+use super::NP_Cursor;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Numeric types that can be stored in a tensor
+///
+/// This trait is sealed, it's implemented for `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`, `f32` and `f64`.
+///
+pub trait NP_Tensor_Item: Copy + sealed::Sealed {
+    #[doc(hidden)]
+    const DTYPE: NP_TypeKeys;
+    #[doc(hidden)]
+    fn from_le(bytes: &[u8]) -> Self;
+    #[doc(hidden)]
+    fn write_le(self, bytes: &mut Vec<u8>);
+    #[doc(hidden)]
+    fn from_json(json: &NP_JSON) -> Option<Self>;
+    #[doc(hidden)]
+    fn to_json(self) -> NP_JSON;
+}
+
+macro_rules! tensor_int {
+    ($t:ty, $key:expr) => {
+        impl sealed::Sealed for $t {}
+        impl NP_Tensor_Item for $t {
+            const DTYPE: NP_TypeKeys = $key;
+            fn from_le(bytes: &[u8]) -> Self {
+                let mut le = [0u8; core::mem::size_of::<$t>()];
+                le.copy_from_slice(bytes);
+                <$t>::from_le_bytes(le)
+            }
+            fn write_le(self, bytes: &mut Vec<u8>) { bytes.extend_from_slice(&self.to_le_bytes()); }
+            fn from_json(json: &NP_JSON) -> Option<Self> {
+                match json {
+                    NP_JSON::Integer(x) => <$t>::try_from(*x).ok(),
+                    _ => None
+                }
+            }
+            fn to_json(self) -> NP_JSON { NP_JSON::Integer(self as i64) }
+        }
+    };
+}
+
+macro_rules! tensor_float {
+    ($t:ty, $key:expr) => {
+        impl sealed::Sealed for $t {}
+        impl NP_Tensor_Item for $t {
+            const DTYPE: NP_TypeKeys = $key;
+            fn from_le(bytes: &[u8]) -> Self {
+                let mut le = [0u8; core::mem::size_of::<$t>()];
+                le.copy_from_slice(bytes);
+                <$t>::from_le_bytes(le)
+            }
+            fn write_le(self, bytes: &mut Vec<u8>) { bytes.extend_from_slice(&self.to_le_bytes()); }
+            fn from_json(json: &NP_JSON) -> Option<Self> {
+                match json {
+                    NP_JSON::Float(x) => Some(*x as $t),
+                    NP_JSON::Integer(x) => Some(*x as $t),
+                    NP_JSON::Unsigned(x) => Some(*x as $t),
+                    _ => None
+                }
+            }
+            fn to_json(self) -> NP_JSON { NP_JSON::Float(self as f64) }
+        }
+    };
+}
+
+tensor_int!(i8, NP_TypeKeys::Int8);
+tensor_int!(i16, NP_TypeKeys::Int16);
+tensor_int!(i32, NP_TypeKeys::Int32);
+tensor_int!(i64, NP_TypeKeys::Int64);
+tensor_int!(u8, NP_TypeKeys::Uint8);
+tensor_int!(u16, NP_TypeKeys::Uint16);
+tensor_int!(u32, NP_TypeKeys::Uint32);
+impl sealed::Sealed for u64 {}
+impl NP_Tensor_Item for u64 {
+    const DTYPE: NP_TypeKeys = NP_TypeKeys::Uint64;
+    fn from_le(bytes: &[u8]) -> Self {
+        let mut le = [0u8; 8];
+        le.copy_from_slice(bytes);
+        u64::from_le_bytes(le)
+    }
+    fn write_le(self, bytes: &mut Vec<u8>) { bytes.extend_from_slice(&self.to_le_bytes()); }
+    // negative numbers don't fit, they're refused instead of wrapping
+    fn from_json(json: &NP_JSON) -> Option<Self> { json.into_u64() }
+    fn to_json(self) -> NP_JSON { NP_JSON::from_u64(self) }
+}
+tensor_float!(f32, NP_TypeKeys::Float);
+tensor_float!(f64, NP_TypeKeys::Double);
+
+/// Get the data type for a `dtype` name in the schema
+pub fn dtype_from_str(name: &str) -> Option<NP_TypeKeys> {
+    match name {
+        "i8"  | "int8"   => Some(NP_TypeKeys::Int8),
+        "i16" | "int16"  => Some(NP_TypeKeys::Int16),
+        "i32" | "int32"  => Some(NP_TypeKeys::Int32),
+        "i64" | "int64"  => Some(NP_TypeKeys::Int64),
+        "u8"  | "uint8"  => Some(NP_TypeKeys::Uint8),
+        "u16" | "uint16" => Some(NP_TypeKeys::Uint16),
+        "u32" | "uint32" => Some(NP_TypeKeys::Uint32),
+        "u64" | "uint64" => Some(NP_TypeKeys::Uint64),
+        "f32" | "float"  => Some(NP_TypeKeys::Float),
+        "f64" | "double" => Some(NP_TypeKeys::Double),
+        _ => None
+    }
+}
+
+/// Get the `dtype` name of a tensor data type
+pub fn dtype_name(dtype: &NP_TypeKeys) -> &'static str {
+    match dtype {
+        NP_TypeKeys::Int8   => "i8",
+        NP_TypeKeys::Int16  => "i16",
+        NP_TypeKeys::Int32  => "i32",
+        NP_TypeKeys::Int64  => "i64",
+        NP_TypeKeys::Uint8  => "u8",
+        NP_TypeKeys::Uint16 => "u16",
+        NP_TypeKeys::Uint32 => "u32",
+        NP_TypeKeys::Uint64 => "u64",
+        NP_TypeKeys::Float  => "f32",
+        NP_TypeKeys::Double => "f64",
+        _ => ""
+    }
+}
+
+/// Size in bytes (and alignment) of a single value of a tensor data type
+pub fn dtype_size(dtype: &NP_TypeKeys) -> usize {
+    match dtype {
+        NP_TypeKeys::Int8  | NP_TypeKeys::Uint8 => 1,
+        NP_TypeKeys::Int16 | NP_TypeKeys::Uint16 => 2,
+        NP_TypeKeys::Int32 | NP_TypeKeys::Uint32 | NP_TypeKeys::Float => 4,
+        NP_TypeKeys::Int64 | NP_TypeKeys::Uint64 | NP_TypeKeys::Double => 8,
+        _ => 1
+    }
+}
+
+/// Holds the data of a tensor along with it's data type and shape.
+///
+/// Check out documentation [here](../tensor/index.html).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct NP_Tensor<'tensor> {
+    dtype: NP_TypeKeys,
+    shape: Vec<usize>,
+    data: Cow<'tensor, [u8]>
+}
+
+impl<'tensor> NP_Scalar for NP_Tensor<'tensor> {}
+
+impl<'tensor> NP_Tensor<'tensor> {
+
+    /// Create a new tensor from values in row major order, the number of values must match the shape.
+    ///
+    pub fn new<T: NP_Tensor_Item>(shape: &[usize], values: &[T]) -> Result<NP_Tensor<'static>, NP_Error> {
+        if shape.iter().product::<usize>() != values.len() {
+            return Err(NP_Error::new_static("Tensor shape doesn't match the number of values!"));
+        }
+
+        let mut data: Vec<u8> = Vec::with_capacity(values.len() * dtype_size(&T::DTYPE));
+        for value in values {
+            value.write_le(&mut data);
+        }
+
+        Ok(NP_Tensor { dtype: T::DTYPE, shape: shape.to_vec(), data: Cow::Owned(data) })
+    }
+
+    /// Tensor filled with zeros for a tensor schema
+    #[doc(hidden)]
+    pub fn zeros(schema: &NP_Parsed_Schema) -> Option<NP_Tensor<'static>> {
+        match schema {
+            NP_Parsed_Schema::Tensor { dtype, shape, .. } => {
+                let len = shape.iter().product::<usize>() * dtype_size(dtype);
+                Some(NP_Tensor { dtype: *dtype, shape: shape.clone(), data: Cow::Owned([0u8].repeat(len)) })
+            },
+            _ => None
+        }
+    }
+
+    /// Tensor for a tensor schema from little endian value bytes, returns `None` if the length doesn't match the schema
+    #[doc(hidden)]
+    pub fn from_le_bytes(schema: &NP_Parsed_Schema, bytes: Vec<u8>) -> Option<NP_Tensor<'static>> {
+        match schema {
+            NP_Parsed_Schema::Tensor { dtype, shape, .. } if shape.iter().product::<usize>() * dtype_size(dtype) == bytes.len() => {
+                Some(NP_Tensor { dtype: *dtype, shape: shape.clone(), data: Cow::Owned(bytes) })
+            },
+            _ => None
+        }
+    }
+
+    /// The data type of this tensor's values
+    pub fn dtype(&self) -> NP_TypeKeys {
+        self.dtype
+    }
+
+    /// The size of each dimension of this tensor
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// The number of values to step over to move one index in each dimension
+    pub fn strides(&self) -> Vec<usize> {
+        let mut strides: Vec<usize> = [1usize].repeat(self.shape.len());
+        for dim in (0..self.shape.len().saturating_sub(1)).rev() {
+            strides[dim] = strides[dim + 1] * self.shape[dim + 1];
+        }
+        strides
+    }
+
+    /// Total number of values in this tensor
+    pub fn len(&self) -> usize {
+        self.data.len() / dtype_size(&self.dtype)
+    }
+
+    /// Returns true if this tensor doesn't hold any values
+    pub fn is_empty(&self) -> bool {
+        self.data.len() == 0
+    }
+
+    /// The little endian bytes of this tensor's values
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Borrow the values of this tensor without copying them.
+    ///
+    /// Returns `None` if `T` doesn't match the data type, the machine is big endian or the data isn't aligned for `T`.
+    ///
+    pub fn as_slice<T: NP_Tensor_Item>(&self) -> Option<&[T]> {
+        if T::DTYPE != self.dtype || cfg!(target_endian = "big") {
+            return None;
+        }
+
+        let ptr = self.data.as_ptr();
+        if ptr.align_offset(core::mem::align_of::<T>()) != 0 {
+            return None;
+        }
+
+        // the data type matches and the bytes are aligned little endian values
+        Some(unsafe { core::slice::from_raw_parts(ptr as *const T, self.len()) })
+    }
+
+    /// Copy the values of this tensor into a vector, returns `None` if `T` doesn't match the data type.
+    ///
+    pub fn to_vec<T: NP_Tensor_Item>(&self) -> Option<Vec<T>> {
+        if T::DTYPE != self.dtype {
+            return None;
+        }
+
+        Some(self.data.chunks_exact(dtype_size(&self.dtype)).map(|bytes| T::from_le(bytes)).collect())
+    }
+
+    /// Get a single value by its index in each dimension
+    ///
+    pub fn get<T: NP_Tensor_Item>(&self, index: &[usize]) -> Option<T> {
+        if T::DTYPE != self.dtype || index.len() != self.shape.len() {
+            return None;
+        }
+
+        let mut offset = 0usize;
+        for ((idx, dim), stride) in index.iter().zip(self.shape.iter()).zip(self.strides().iter()) {
+            if idx >= dim {
+                return None;
+            }
+            offset += idx * stride;
+        }
+
+        let size = dtype_size(&self.dtype);
+        self.data.get((offset * size)..((offset + 1) * size)).map(|bytes| T::from_le(bytes))
+    }
+
+    fn values_to_json<T: NP_Tensor_Item>(&self) -> NP_JSON {
+        NP_JSON::Array(self.to_vec::<T>().unwrap_or_default().into_iter().map(|value| value.to_json()).collect())
+    }
+
+    /// Convert the values of this tensor into a flat JSON array
+    pub fn to_json_values(&self) -> NP_JSON {
+        match self.dtype {
+            NP_TypeKeys::Int8   => self.values_to_json::<i8>(),
+            NP_TypeKeys::Int16  => self.values_to_json::<i16>(),
+            NP_TypeKeys::Int32  => self.values_to_json::<i32>(),
+            NP_TypeKeys::Int64  => self.values_to_json::<i64>(),
+            NP_TypeKeys::Uint8  => self.values_to_json::<u8>(),
+            NP_TypeKeys::Uint16 => self.values_to_json::<u16>(),
+            NP_TypeKeys::Uint32 => self.values_to_json::<u32>(),
+            NP_TypeKeys::Uint64 => self.values_to_json::<u64>(),
+            NP_TypeKeys::Float  => self.values_to_json::<f32>(),
+            NP_TypeKeys::Double => self.values_to_json::<f64>(),
+            _ => NP_JSON::Null
+        }
+    }
+
+    /// Build a tensor for a tensor schema from a flat JSON array of values
+    ///
+    pub fn from_json_values(schema: &NP_Parsed_Schema, values: &[NP_JSON]) -> Result<NP_Tensor<'static>, NP_Error> {
+
+        fn convert<T: NP_Tensor_Item>(shape: &[usize], values: &[NP_JSON]) -> Result<NP_Tensor<'static>, NP_Error> {
+            let mut items: Vec<T> = Vec::with_capacity(values.len());
+            for value in values {
+                match T::from_json(value) {
+                    Some(x) => items.push(x),
+                    None => return Err(NP_Error::new_static("Tensor values must be numbers in range of the tensor dtype!"))
+                }
+            }
+            NP_Tensor::new(shape, &items)
+        }
+
+        match schema {
+            NP_Parsed_Schema::Tensor { dtype, shape, .. } => {
+                match dtype {
+                    NP_TypeKeys::Int8   => convert::<i8>(shape, values),
+                    NP_TypeKeys::Int16  => convert::<i16>(shape, values),
+                    NP_TypeKeys::Int32  => convert::<i32>(shape, values),
+                    NP_TypeKeys::Int64  => convert::<i64>(shape, values),
+                    NP_TypeKeys::Uint8  => convert::<u8>(shape, values),
+                    NP_TypeKeys::Uint16 => convert::<u16>(shape, values),
+                    NP_TypeKeys::Uint32 => convert::<u32>(shape, values),
+                    NP_TypeKeys::Uint64 => convert::<u64>(shape, values),
+                    NP_TypeKeys::Float  => convert::<f32>(shape, values),
+                    NP_TypeKeys::Double => convert::<f64>(shape, values),
+                    _ => Err(NP_Error::new_static("unreachable"))
+                }
+            },
+            _ => Err(NP_Error::new_static("unreachable"))
+        }
+    }
+}
+
+// the value address points to an offset byte, the data starts that many bytes later so it's aligned to the dtype size
+fn data_range(cursor: &NP_Cursor, memory: &NP_Memory) -> Option<(usize, usize)> {
+    let value_addr = cursor.get_value(memory).get_addr_value() as usize;
+
+    if value_addr == 0 {
+        return None;
+    }
+
+    match &memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::Tensor { dtype, shape, .. } => {
+            let offset = memory.get_1_byte(value_addr)? as usize;
+            // attack
+            if offset == 0 || offset > dtype_size(dtype) {
+                return None;
+            }
+            let start = value_addr + offset;
+            let end = start + (shape.iter().product::<usize>() * dtype_size(dtype));
+            if end > memory.read_bytes().len() {
+                return None;
+            }
+            Some((start, end))
+        },
+        _ => None
+    }
+}
+
+impl<'value> NP_Value<'value> for NP_Tensor<'value> {
+
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("tensor", NP_TypeKeys::Tensor) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("tensor", NP_TypeKeys::Tensor) }
+
+    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
+        let mut schema_json = JSMAP::new();
+        schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
+
+        match &schema[address] {
+            NP_Parsed_Schema::Tensor { dtype, shape, .. } => {
+                schema_json.insert("dtype".to_owned(), NP_JSON::String(dtype_name(dtype).to_string()));
+                schema_json.insert("shape".to_owned(), NP_JSON::Array(shape.iter().map(|dim| NP_JSON::Integer(*dim as i64)).collect()));
+            },
+            _ => { }
+        }
+
+        Ok(NP_JSON::Dictionary(schema_json))
+    }
+
+    fn schema_default(_schema: &'value NP_Parsed_Schema) -> Option<Self> {
+        None
+    }
+
+    fn set_value<'set>(cursor: NP_Cursor, memory: &'set NP_Memory, value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
+
+        let (dtype, shape) = match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Tensor { dtype, shape, .. } => (*dtype, shape),
+            _ => return Err(NP_Error::new_static("unreachable"))
+        };
+
+        if value.dtype != dtype {
+            return Err(NP_Error::new_static("Tensor dtype doesn't match the schema!"));
+        }
+
+        if &value.shape != shape {
+            return Err(NP_Error::new_static("Tensor shape doesn't match the schema!"));
+        }
+
+        let write_start = match data_range(&cursor, memory) {
+            Some((start, _end)) => start,
+            None => {
+                // offset byte, then padding so the data is aligned
+                let new_addr = memory.malloc_borrow(&[0u8])?;
+                let data_addr = memory.malloc_aligned(&value.data, dtype_size(&dtype))?;
+                memory.write_bytes()[new_addr] = (data_addr - new_addr) as u8;
+
+                cursor.get_value_mut(memory).set_addr_value(new_addr as u16);
+                return Ok(cursor);
+            }
+        };
+
+        let write_bytes = memory.write_bytes();
+        write_bytes[write_start..(write_start + value.data.len())].copy_from_slice(&value.data);
+
+        Ok(cursor)
+    }
+
+    fn into_value(cursor: &NP_Cursor, memory: &'value NP_Memory) -> Result<Option<Self>, NP_Error> where Self: Sized {
+
+        match data_range(cursor, memory) {
+            Some((start, end)) => {
+                match &memory.schema[cursor.schema_addr] {
+                    NP_Parsed_Schema::Tensor { dtype, shape, .. } => {
+                        Ok(Some(NP_Tensor { dtype: *dtype, shape: shape.clone(), data: Cow::Borrowed(&memory.read_bytes()[start..end]) }))
+                    },
+                    _ => Err(NP_Error::new_static("unreachable"))
+                }
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+        match Self::into_value(cursor, memory) {
+            Ok(Some(tensor)) => tensor.to_json_values(),
+            _ => NP_JSON::Null
+        }
+    }
+
+    fn get_size(cursor: &'value NP_Cursor, memory: &'value NP_Memory<'value>) -> Result<usize, NP_Error> {
+        match data_range(cursor, memory) {
+            Some((_start, end)) => Ok(end - (cursor.get_value(memory).get_addr_value() as usize)),
+            None => Ok(0)
+        }
+    }
+
+    fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
+
+        let dtype = match &json_schema["dtype"] {
+            NP_JSON::String(x) => match dtype_from_str(x.as_str()) {
+                Some(dtype) => dtype,
+                None => return Err(NP_Error::new_schema("Tensor dtype must be a number type like \"f32\" or \"u8\"!"))
+            },
+            _ => return Err(NP_Error::new_schema("Tensors require a \"dtype\" property!"))
+        };
+
+        let shape: Vec<usize> = match &json_schema["shape"] {
+            NP_JSON::Array(dims) if dims.len() > 0 && dims.len() <= 255 => {
+                let mut shape: Vec<usize> = Vec::with_capacity(dims.len());
+                for dim in dims {
+                    match dim {
+                        NP_JSON::Integer(x) if *x > 0 && *x <= u16::MAX as i64 => shape.push(*x as usize),
+                        _ => return Err(NP_Error::new_schema("Tensor dimensions must be numbers between 1 and 65,535!"))
+                    }
+                }
+                shape
+            },
+            _ => return Err(NP_Error::new_schema("Tensors require a \"shape\" array with 1 to 255 dimensions!"))
+        };
+
+        let mut data_size = dtype_size(&dtype);
+        for dim in shape.iter() {
+            data_size = data_size.saturating_mul(*dim);
+        }
+        if data_size > u16::MAX as usize {
+            return Err(NP_Error::new_schema("Tensor data can't be larger than 65,535 bytes!"));
+        }
+
+        let mut schema_data: Vec<u8> = Vec::new();
+        schema_data.push(NP_TypeKeys::Tensor as u8);
+        schema_data.push(dtype as u8);
+        schema_data.push(shape.len() as u8);
+        for dim in shape.iter() {
+            schema_data.extend_from_slice(&(*dim as u16).to_be_bytes());
+        }
+
+        schema.push(NP_Parsed_Schema::Tensor {
+            i: NP_TypeKeys::Tensor,
+            sortable: false,
+            dtype,
+            shape
+        });
+
+        Ok((false, schema_data, schema))
+    }
+
+    fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+        let dtype = NP_TypeKeys::from(bytes[address + 1]);
+        let rank = bytes[address + 2] as usize;
+
+        let shape: Vec<usize> = (0..rank).map(|dim| {
+            let dim_addr = address + 3 + (dim * 2);
+            u16::from_be_bytes([bytes[dim_addr], bytes[dim_addr + 1]]) as usize
+        }).collect();
+
+        schema.push(NP_Parsed_Schema::Tensor {
+            i: NP_TypeKeys::Tensor,
+            sortable: false,
+            dtype,
+            shape
+        });
+
+        (false, schema)
+    }
+}
+
+#[test]
+fn schema_parsing_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"tensor\",\"dtype\":\"f32\",\"shape\":[3,4]}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory2 = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, factory2.schema.to_json()?.stringify());
+
+    let schema = "{\"type\":\"tensor\",\"dtype\":\"uint16\",\"shape\":[8]}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!("{\"type\":\"tensor\",\"dtype\":\"u16\",\"shape\":[8]}", factory.schema.to_json()?.stringify());
+
+    assert!(crate::NP_Factory::new("{\"type\":\"tensor\",\"dtype\":\"string\",\"shape\":[8]}").is_err());
+    assert!(crate::NP_Factory::new("{\"type\":\"tensor\",\"dtype\":\"f32\",\"shape\":[]}").is_err());
+    assert!(crate::NP_Factory::new("{\"type\":\"tensor\",\"dtype\":\"f32\",\"shape\":[3,224,224]}").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["flag",   {"type": "u8"}],
+        ["matrix", {"type": "tensor", "dtype": "f64", "shape": [2, 2]}]
+    ]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["flag"], 1u8)?;
+    buffer.set(&["matrix"], NP_Tensor::new(&[2, 2], &[1.5f64, 2.5, 3.5, 4.5])?)?;
+
+    // data is aligned inside the buffer
+    let addr = buffer.get::<NP_Tensor>(&["matrix"])?.unwrap().as_bytes().as_ptr() as usize - buffer.read_bytes().as_ptr() as usize;
+    assert_eq!(addr % 8, 0);
+
+    // updates are done in place
+    let size = buffer.calc_bytes()?.current_buffer;
+    buffer.set(&["matrix"], NP_Tensor::new(&[2, 2], &[0f64, 1.0, 2.0, 3.0])?)?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer, size);
+    assert_eq!(buffer.get::<NP_Tensor>(&["matrix"])?.unwrap().get::<f64>(&[1, 1]), Some(3.0));
+
+    // wrong dtype or shape
+    assert!(buffer.set(&["matrix"], NP_Tensor::new(&[2, 2], &[0f32, 1.0, 2.0, 3.0])?).is_err());
+    assert!(buffer.set(&["matrix"], NP_Tensor::new(&[4], &[0f64, 1.0, 2.0, 3.0])?).is_err());
+    assert!(NP_Tensor::new(&[2, 2], &[0f64]).is_err());
+
+    assert_eq!(buffer.json_encode(&["matrix"])?.stringify(), "[0,1,2,3]");
+
+    buffer.compact(None)?;
+    let tensor = buffer.get::<NP_Tensor>(&["matrix"])?.unwrap();
+    assert_eq!(tensor.to_vec::<f64>(), Some([0f64, 1.0, 2.0, 3.0].to_vec()));
+    assert_eq!(tensor.to_vec::<f32>(), None);
+
+    buffer.del(&["matrix"])?;
+    assert_eq!(buffer.get::<NP_Tensor>(&["matrix"])?, None);
+
+    Ok(())
+}
+
+#[test]
+fn data_stays_aligned() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["name", {"type": "string"}],
+        ["u16",  {"type": "tensor", "dtype": "u16", "shape": [3]}],
+        ["f32",  {"type": "tensor", "dtype": "f32", "shape": [3]}],
+        ["u64",  {"type": "tensor", "dtype": "u64", "shape": [3]}]
+    ]}"#)?;
+
+    fn check(buffer: &crate::buffer::NP_Buffer) -> Result<(), NP_Error> {
+        for (col, align) in [("u16", 2usize), ("f32", 4), ("u64", 8)].iter() {
+            let tensor = buffer.get::<NP_Tensor>(&[col])?.unwrap();
+            let addr = tensor.as_bytes().as_ptr() as usize - buffer.read_bytes().as_ptr() as usize;
+            assert_eq!(addr % align, 0);
+        }
+        Ok(())
+    }
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["name"], "odd")?;
+    buffer.set(&["u16"], NP_Tensor::new(&[3], &[1u16, 2, 3])?)?;
+    buffer.set(&["name"], "an odd length")?;
+    buffer.set(&["f32"], NP_Tensor::new(&[3], &[1f32, 2.0, 3.0])?)?;
+    buffer.set(&["name"], "x")?;
+    buffer.set(&["u64"], NP_Tensor::new(&[3], &[1u64, 2, 3])?)?;
+    check(&buffer)?;
+
+    buffer.compact(None)?;
+    check(&buffer)?;
+
+    let buffer = factory.open_buffer(buffer.close());
+    check(&buffer)?;
+    assert_eq!(buffer.get::<NP_Tensor>(&["u64"])?.unwrap().to_vec::<u64>(), Some([1u64, 2, 3].to_vec()));
+
+    // u64 values keep every bit through JSON, negative values are refused instead of wrapping
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set_with_json(&["u64"], "[18446744073709551615, 0, 5]")?;
+    assert_eq!(buffer.get::<NP_Tensor>(&["u64"])?.unwrap().to_vec::<u64>(), Some([u64::MAX, 0, 5].to_vec()));
+    assert_eq!(buffer.json_encode(&["u64"])?.stringify(), "[18446744073709551615,0,5]");
+    assert!(buffer.set_with_json(&["u64"], "[-1, 0, 5]").is_err());
+    assert_eq!(buffer.get::<NP_Tensor>(&["u64"])?.unwrap().to_vec::<u64>(), Some([u64::MAX, 0, 5].to_vec()));
+
+    Ok(())
+}
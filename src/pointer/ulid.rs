@@ -15,7 +15,7 @@
 //! let ulid = NP_ULID::generate(1604965249484, 50);
 //! new_buffer.set(&[], &ulid)?;
 //! 
-//! assert_eq!("1EPQP4CEC3KANC3XYNG9YKAQ", new_buffer.get::<&NP_ULID>(&[])?.unwrap().to_string());
+//! assert_eq!("01EPQP4CEC93KANC3XYNG9YKAQ", new_buffer.get::<&NP_ULID>(&[])?.unwrap().to_string());
 //!
 //! # Ok::<(), NP_Error>(()) 
 //! ```
@@ -23,7 +23,7 @@
 
 use crate::{memory::NP_Memory, schema::{NP_Parsed_Schema}};
 use alloc::vec::Vec;
-use crate::utils::to_base32;
+use crate::utils::{to_base32, from_base32};
 use crate::json_flex::{JSMAP, NP_JSON};
 use crate::schema::{NP_TypeKeys};
 use crate::{pointer::NP_Value, error::NP_Error, utils::{Rand}};
@@ -110,6 +110,40 @@ impl NP_ULID {
         u64::from_be_bytes(time_bytes)
     }
 
+    /// Parse a ULID from its 26 character base32 string form.
+    /// 
+    pub fn from_string(value: &str) -> Option<NP_ULID> {
+
+        if value.len() != 26 || value.is_ascii() == false {
+            return None;
+        }
+
+        let time = from_base32(&value[0..10])?;
+        let rand = from_base32(&value[10..26])?;
+
+        // time is 48 bits
+        if time >= (1 << 48) {
+            return None;
+        }
+
+        let time_bytes = time.to_be_bytes();
+        let rand_bytes = rand.to_be_bytes();
+
+        let mut id: [u8; 16] = [0; 16];
+
+        for x in 0..id.len() {
+            if x < 6 {
+                id[x] = time_bytes[x + 10];
+            } else {
+                id[x] = rand_bytes[x];
+            }
+        }
+
+        Some(NP_ULID {
+            value: id
+        })
+    }
+
     /// Generates a stringified version of this ULID with base32.
     /// 
     pub fn to_string(&self) -> String {
@@ -148,8 +182,8 @@ impl Debug for NP_ULID {
 
 impl<'value> NP_Value<'value> for &NP_ULID {
 
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("ulid", NP_TypeKeys::Ulid) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("ulid", NP_TypeKeys::Ulid) }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("ulid", NP_TypeKeys::Ulid) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("ulid", NP_TypeKeys::Ulid) }
 
     fn schema_to_json(_schema: &Vec<NP_Parsed_Schema>, _address: usize)-> Result<NP_JSON, NP_Error> {
         let mut schema_json = JSMAP::new();
@@ -177,7 +211,7 @@ impl<'value> NP_Value<'value> for &NP_ULID {
         } else { // new value
 
             value_address = memory.malloc_borrow(&value.value)?;
-            c_value.set_addr_value(value_address as u16);
+            cursor.get_value_mut(memory).set_addr_value(value_address as u16);
         }                    
         
         Ok(cursor)
@@ -275,7 +309,8 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     let set_value = NP_ULID::generate(1606680515909, 212);
     buffer.set(&[], &set_value)?;
     assert_eq!(buffer.get::<&NP_ULID>(&[])?, Some(&(NP_ULID::generate(1606680515909, 212))));
-    assert_eq!(buffer.get::<&NP_ULID>(&[])?.unwrap().to_string(), "1ERASY5A5VKANC1CJGRZXYW8");
+    assert_eq!(buffer.get::<&NP_ULID>(&[])?.unwrap().to_string(), "01ERASY5A5KVKANC1CJGRZXYW8");
+    assert_eq!(NP_ULID::from_string(&set_value.to_string()), Some(set_value.clone()));
     buffer.del(&[])?;
     assert_eq!(buffer.get::<&NP_ULID>(&[])?, None);
 
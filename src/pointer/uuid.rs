@@ -2,6 +2,8 @@
 //! 
 //! `uuid` types are always represented with this struct.
 //! 
+//! With the `uuid` feature enabled values convert to and from `uuid::Uuid`.
+//! 
 //! ```
 //! use no_proto::error::NP_Error;
 //! use no_proto::NP_Factory;
@@ -21,7 +23,7 @@
 //! ```
 //! 
 
-use alloc::prelude::v1::Box;
+use alloc::boxed::Box;
 use crate::pointer::NP_Scalar;
 use crate::{memory::NP_Memory, schema::{NP_Parsed_Schema}};
 use alloc::vec::Vec;
@@ -96,6 +98,30 @@ impl NP_UUID {
         uuid
     }
 
+    /// Parse a UUID from its string form, dashes are optional.
+    /// 
+    pub fn from_string(value: &str) -> Option<Self> {
+
+        let mut uuid = NP_UUID {
+            value: [0; 16]
+        };
+
+        let mut digits = value.bytes().filter(|c| *c != b'-').map(|c| (c as char).to_digit(16));
+
+        for x in 0..uuid.value.len() {
+            let high = digits.next()??;
+            let low = digits.next()??;
+            uuid.value[x] = ((high << 4) | low) as u8;
+        }
+
+        // too many digits
+        if digits.next().is_some() {
+            return None;
+        }
+
+        Some(uuid)
+    }
+
     /// Generates a stringified version of the UUID.
     /// 
     pub fn to_string(&self) -> String {
@@ -114,6 +140,20 @@ impl NP_UUID {
     }
 }
 
+#[cfg(feature = "uuid")]
+impl From<::uuid::Uuid> for NP_UUID {
+    fn from(value: ::uuid::Uuid) -> Self {
+        NP_UUID { value: value.into_bytes() }
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<NP_UUID> for ::uuid::Uuid {
+    fn from(value: NP_UUID) -> Self {
+        ::uuid::Uuid::from_bytes(value.value)
+    }
+}
+
 impl Debug for NP_UUID {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.to_string())
@@ -128,8 +168,8 @@ impl Default for NP_UUID {
 
 impl<'value> NP_Value<'value> for &NP_UUID {
 
-    fn type_idx() -> (&'value str, NP_TypeKeys) { ("uuid", NP_TypeKeys::Uuid) }
-    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("uuid", NP_TypeKeys::Uuid) }
+    fn type_idx() -> (&'static str, NP_TypeKeys) { ("uuid", NP_TypeKeys::Uuid) }
+    fn self_type_idx(&self) -> (&'static str, NP_TypeKeys) { ("uuid", NP_TypeKeys::Uuid) }
 
     fn schema_to_json(_schema: &Vec<NP_Parsed_Schema>, _address: usize)-> Result<NP_JSON, NP_Error> {
         let mut schema_json = JSMAP::new();
@@ -156,7 +196,7 @@ impl<'value> NP_Value<'value> for &NP_UUID {
         } else { // new value
 
             value_address = memory.malloc_borrow(&value.value)?;
-            c_value.set_addr_value(value_address as u16);
+            cursor.get_value_mut(memory).set_addr_value(value_address as u16);
         }                    
         
         Ok(cursor)
@@ -257,6 +297,7 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     buffer.set(&[], &set_value)?;
     assert_eq!(buffer.get::<&NP_UUID>(&[])?, Some(&NP_UUID::generate(212)));
     assert_eq!(buffer.get::<&NP_UUID>(&[])?.unwrap().to_string(), "9EE6AAB0-2C94-41FE-FB88-42F73253F217");
+    assert_eq!(NP_UUID::from_string("9EE6AAB0-2C94-41FE-FB88-42F73253F217"), Some(set_value.clone()));
     buffer.del(&[])?;
     assert_eq!(buffer.get::<&NP_UUID>(&[])?, None);
 
@@ -264,4 +305,13 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn uuid_conversions_work() {
+    let value = ::uuid::Uuid::from_u128(0x67e5504410b1426f9247bb680e5fe0c8);
+    let np_uuid = NP_UUID::from(value);
+    assert_eq!(np_uuid.to_string(), "67E55044-10B1-426F-9247-BB680E5FE0C8");
+    assert_eq!(::uuid::Uuid::from(np_uuid), value);
+}
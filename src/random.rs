@@ -0,0 +1,616 @@
+//! Random valid buffers for property testing and fuzzing
+//!
+//! `NP_Factory::arbitrary_buffer` builds a buffer that matches the factory schema, with every value decided by a slice of entropy bytes.  The same bytes always produce the same buffer, and shorter or zeroed entropy produces smaller buffers, so test case shrinking works without any extra effort.
+//!
+//! The entropy slice plugs straight into the usual property testing tools:
+//! - With the `arbitrary` feature, `NP_Arbitrary_Buffer` implements `arbitrary::Arbitrary`.  Take it as the input of a `cargo fuzz` target and `build` it with the factory under test.
+//! - With the `proptest` feature, `buffers` is a strategy that generates buffers of a factory and shrinks them by shrinking their entropy.
+//! - Anything else can pass its own bytes to `NP_Factory::arbitrary_buffer`.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! let buffer = factory.arbitrary_buffer(&[1, 5, 12, 3, 99, 201, 7, 1, 1, 4, 8])?;
+//!
+//! // generated buffers are always valid for the schema
+//! let bytes = buffer.close();
+//! assert!(factory.open_checked_buffer(bytes).is_ok());
+//!
+//! // no entropy, no values
+//! let empty = factory.arbitrary_buffer(&[])?;
+//! assert_eq!(empty.json_encode(&[])?.stringify(), "null");
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! ## Fuzzing and proptest
+//! ```
+//! # #[cfg(feature = "arbitrary")] {
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::random::NP_Arbitrary_Buffer;
+//! use arbitrary::{Arbitrary, Unstructured};
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#).unwrap();
+//!
+//! // in a fuzz target this comes from `fuzz_target!(|input: NP_Arbitrary_Buffer| { ... })`
+//! let input = NP_Arbitrary_Buffer::arbitrary(&mut Unstructured::new(&[1, 9, 4, 200, 17, 3])).unwrap();
+//! let buffer = input.build(&factory).unwrap();
+//! assert!(factory.open_checked_buffer(buffer.close()).is_ok());
+//! # }
+//! ```
+//!
+//! ```
+//! # #[cfg(feature = "proptest")] {
+//! use no_proto::NP_Factory;
+//! use no_proto::random;
+//! use proptest::test_runner::TestRunner;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{"type": "map", "value": {"type": "u32"}}"#).unwrap();
+//!
+//! TestRunner::default().run(&random::buffers(&factory, 256), |buffer| {
+//!     assert!(factory.open_checked_buffer(buffer.close()).is_ok());
+//!     Ok(())
+//! }).unwrap();
+//! # }
+//! ```
+//!
+//! ## Samples
+//! `NP_Factory::generate_sample` builds a buffer with every value set to something plausible instead: short words for strings, small numbers, dates from the last few years, a few items in each collection.  Fixed sizes and option choices are respected, and values with a schema default sometimes use it.  Use samples for fixtures, demos and load testing.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",  {"type": "string"}],
+//!         ["color", {"type": "option", "choices": ["red", "green", "blue"]}],
+//!         ["tags",  {"type": "list", "of": {"type": "string", "size": 4}}]
+//!     ]
+//! }"#)?;
+//!
+//! let buffer = factory.generate_sample(42)?;
+//!
+//! assert!(buffer.get::<&str>(&["name"])?.is_some());
+//! assert!(buffer.length(&["tags"])?.unwrap() > 0);
+//! assert_eq!(buffer.get::<&str>(&["tags", "0"])?.unwrap().len(), 4);
+//!
+//! // same seed, same sample
+//! assert_eq!(factory.generate_sample(42)?.close(), buffer.close());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::pointer::{NP_Cursor, NP_Value};
+use crate::pointer::dec::NP_Dec;
+use crate::pointer::geo::NP_Geo;
+use crate::pointer::date::NP_Date;
+use crate::pointer::option::NP_Enum;
+use crate::pointer::uuid::NP_UUID;
+use crate::pointer::ulid::NP_ULID;
+use crate::pointer::tensor::{NP_Tensor, dtype_size};
+use crate::pointer::bigint::NP_BigInt;
+use crate::pointer::custom::NP_Custom;
+use crate::collection::{list::NP_List, log::NP_Log, map::NP_Map, table::NP_Table, tuple::NP_Tuple};
+use crate::buffer::ROOT_PTR_ADDR;
+use crate::memory::NP_Memory;
+use crate::schema::{NP_Parsed_Schema, NP_TypeKeys};
+use crate::error::NP_Error;
+use crate::utils::{opt_err, Rand};
+use crate::pointer::any::NP_Dyn;
+use crate::schema::NP_Schema;
+use crate::json_flex::NP_JSON;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+use crate::{NP_Factory, buffer::NP_Buffer};
+
+const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 ";
+
+// largest number of items generated for a single list or map
+const MAX_ITEMS: usize = 8;
+
+// largest string or bytes value generated for types without a fixed size
+const MAX_LEN: usize = 32;
+
+const WORDS: &[&str] = &["alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliet", "kilo", "lima", "mike", "november", "oscar", "papa"];
+
+// 2015-01-01 to 2025-01-01 in milliseconds
+const SAMPLE_DATES: (u64, u64) = (1_420_070_400_000, 1_735_689_600_000);
+
+/// Reads entropy bytes in order, returns zeros once the bytes run out
+struct NP_Entropy<'entropy> {
+    bytes: &'entropy [u8],
+    index: usize
+}
+
+impl<'entropy> NP_Entropy<'entropy> {
+
+    fn byte(&mut self) -> u8 {
+        let value = self.bytes.get(self.index).copied().unwrap_or(0);
+        self.index += 1;
+        value
+    }
+
+    fn flag(&mut self) -> bool {
+        self.byte() & 1 == 1
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_be_bytes([self.byte(), self.byte()])
+    }
+
+    fn u32(&mut self) -> u32 {
+        ((self.u16() as u32) << 16) | self.u16() as u32
+    }
+
+    fn u64(&mut self) -> u64 {
+        ((self.u32() as u64) << 32) | self.u32() as u64
+    }
+
+    // number in 0..max
+    fn below(&mut self, max: usize) -> usize {
+        if max == 0 { return 0 }
+        self.u16() as usize % max
+    }
+
+    fn string(&mut self, min_len: usize, max_len: usize) -> String {
+        let len = min_len + self.below(max_len - min_len + 1);
+        let mut value = String::with_capacity(len);
+        for _ in 0..len {
+            value.push(CHARS[self.below(CHARS.len())] as char);
+        }
+        value
+    }
+}
+
+/// Entropy for a random buffer, generated by fuzzers through `arbitrary::Arbitrary` (feature `arbitrary`).
+/// 
+/// The schema isn't known while the input is generated, so `build` turns the entropy into a buffer of any factory.  See the [random](index.html) module for an example.
+#[cfg(feature = "arbitrary")]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NP_Arbitrary_Buffer {
+    /// Bytes every value of the buffer is decided by, see `NP_Factory::arbitrary_buffer`
+    pub entropy: Vec<u8>
+}
+
+#[cfg(feature = "arbitrary")]
+impl NP_Arbitrary_Buffer {
+    /// Build the buffer for this factory, the same entropy always builds the same buffer
+    pub fn build<'buffer>(&self, factory: &'buffer NP_Factory) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        factory.arbitrary_buffer(&self.entropy)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for NP_Arbitrary_Buffer {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(NP_Arbitrary_Buffer { entropy: Vec::<u8>::arbitrary(u)? })
+    }
+
+    fn arbitrary_take_rest(u: arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(NP_Arbitrary_Buffer { entropy: u.take_rest().to_vec() })
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <Vec<u8> as arbitrary::Arbitrary>::size_hint(depth)
+    }
+}
+
+/// A proptest strategy for random buffers of this factory, built from up to `max_entropy` bytes of entropy (feature `proptest`).
+/// 
+/// Failing cases shrink toward shorter and smaller entropy, which means fewer and smaller values.  Entropy that builds a buffer past the max size is skipped.  See the [random](index.html) module for an example.
+#[cfg(feature = "proptest")]
+pub fn buffers(factory: &NP_Factory, max_entropy: usize) -> impl proptest::strategy::Strategy<Value = NP_Buffer<'_>> + '_ {
+    use proptest::strategy::Strategy;
+    proptest::collection::vec(proptest::num::u8::ANY, 0..=max_entropy)
+        .prop_filter_map("buffer doesn't fit", move |entropy| factory.arbitrary_buffer(&entropy).ok())
+}
+
+/// Fill an empty buffer with random values from the provided entropy
+pub fn generate(memory: &NP_Memory, entropy: &[u8]) -> Result<(), NP_Error> {
+    let mut entropy = NP_Entropy { bytes: entropy, index: 0 };
+
+    // an empty root is a valid buffer too
+    if entropy.flag() {
+        fill(NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), memory, &mut entropy)?;
+    }
+
+    Ok(())
+}
+
+fn fill(cursor: NP_Cursor, memory: &NP_Memory, entropy: &mut NP_Entropy) -> Result<(), NP_Error> {
+
+    match &memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::Table { columns, .. } => {
+            for (_, name, _) in columns.iter() {
+                if entropy.flag() {
+                    let column = opt_err(NP_Table::select(cursor.clone(), name, true, memory)?)?;
+                    fill(column, memory, entropy)?;
+                }
+            }
+        },
+        NP_Parsed_Schema::Tuple { values, sortable, .. } => {
+            for idx in 0..values.len() {
+                // sortable tuples always have every value set
+                if *sortable || entropy.flag() {
+                    let value = opt_err(NP_Tuple::select(cursor.clone(), idx, true, memory)?)?;
+                    fill(value, memory, entropy)?;
+                }
+            }
+        },
+        NP_Parsed_Schema::List { .. } => {
+            let mut index = 0usize;
+            for _ in 0..entropy.below(MAX_ITEMS + 1) {
+                // leave gaps between some items
+                index += entropy.below(3);
+                if index > 255 { break; }
+                let item = opt_err(opt_err(NP_List::select(cursor.clone(), index, true, memory)?)?.1)?;
+                fill(item, memory, entropy)?;
+                index += 1;
+            }
+            NP_List::restore_order(&cursor, memory)?;
+        },
+        NP_Parsed_Schema::Log { .. } => {
+            for _ in 0..entropy.below(MAX_ITEMS + 1) {
+                let (_, item) = NP_Log::append(&cursor, memory)?;
+                // empty log items are valid too
+                if entropy.flag() {
+                    fill(item, memory, entropy)?;
+                }
+            }
+        },
+        NP_Parsed_Schema::Map { .. } => {
+            for _ in 0..entropy.below(MAX_ITEMS + 1) {
+                let key = entropy.string(1, 8);
+                let item = opt_err(NP_Map::select(cursor.clone(), &key, true, memory)?)?;
+                fill(item, memory, entropy)?;
+            }
+        },
+        NP_Parsed_Schema::UTF8String { size, .. } => {
+            let max_len = if *size > 0 { *size as usize } else { MAX_LEN };
+            let value = entropy.string(0, max_len);
+            <&str>::set_value(cursor, memory, &value)?;
+        },
+        NP_Parsed_Schema::Bytes { size, .. } => {
+            let max_len = if *size > 0 { *size as usize } else { MAX_LEN };
+            let len = entropy.below(max_len + 1);
+            let value: Vec<u8> = (0..len).map(|_| entropy.byte()).collect();
+            <&[u8]>::set_value(cursor, memory, &value)?;
+        },
+        NP_Parsed_Schema::Int8    { .. } => { i8::set_value(cursor, memory, entropy.byte() as i8)?; },
+        NP_Parsed_Schema::Int16   { .. } => { i16::set_value(cursor, memory, entropy.u16() as i16)?; },
+        NP_Parsed_Schema::Int32   { .. } => { i32::set_value(cursor, memory, entropy.u32() as i32)?; },
+        NP_Parsed_Schema::Int64   { .. } => { i64::set_value(cursor, memory, entropy.u64() as i64)?; },
+        NP_Parsed_Schema::Uint8   { .. } => { u8::set_value(cursor, memory, entropy.byte())?; },
+        NP_Parsed_Schema::Uint16  { .. } => { u16::set_value(cursor, memory, entropy.u16())?; },
+        NP_Parsed_Schema::Uint32  { .. } => { u32::set_value(cursor, memory, entropy.u32())?; },
+        NP_Parsed_Schema::Uint64  { .. } => { u64::set_value(cursor, memory, entropy.u64())?; },
+        // floats are built from integers so NaN and infinity never show up
+        NP_Parsed_Schema::Float   { .. } => { f32::set_value(cursor, memory, entropy.u32() as i32 as f32 / 256f32)?; },
+        NP_Parsed_Schema::Double  { .. } => { f64::set_value(cursor, memory, entropy.u64() as i64 as f64 / 65536f64)?; },
+        NP_Parsed_Schema::Decimal { exp, .. } => { NP_Dec::set_value(cursor, memory, NP_Dec::new(entropy.u64() as i64, *exp))?; },
+        NP_Parsed_Schema::Boolean { .. } => { bool::set_value(cursor, memory, entropy.flag())?; },
+        NP_Parsed_Schema::Geo { size, .. } => {
+            let lat = (entropy.u32() % 180_000_000) as f64 / 1_000_000f64 - 90f64;
+            let lng = (entropy.u32() % 360_000_000) as f64 / 1_000_000f64 - 180f64;
+            NP_Geo::set_value(cursor, memory, NP_Geo::new(*size, lat, lng))?;
+        },
+        NP_Parsed_Schema::Date { .. } => { NP_Date::set_value(cursor, memory, NP_Date::new(entropy.u64()))?; },
+        NP_Parsed_Schema::Enum { choices, .. } => {
+            if choices.len() > 0 {
+                let choice = choices[entropy.below(choices.len())].clone();
+                NP_Enum::set_value(cursor, memory, choice)?;
+            }
+        },
+        NP_Parsed_Schema::Uuid { .. } => {
+            let mut uuid = NP_UUID { value: [0u8; 16] };
+            for x in 0..uuid.value.len() {
+                uuid.value[x] = entropy.byte();
+            }
+            // version 4
+            uuid.value[6] = 64 + (uuid.value[6] % 16);
+            <&NP_UUID>::set_value(cursor, memory, &uuid)?;
+        },
+        NP_Parsed_Schema::Ulid { .. } => {
+            let ulid = NP_ULID::generate(entropy.u64() % (1 << 48), entropy.u32());
+            <&NP_ULID>::set_value(cursor, memory, &ulid)?;
+        },
+        NP_Parsed_Schema::Tensor { dtype, shape, .. } => {
+            let len: usize = shape.iter().product();
+            let tensor = match dtype {
+                NP_TypeKeys::Float  => NP_Tensor::new(shape, &(0..len).map(|_| entropy.u32() as i32 as f32 / 256f32).collect::<Vec<f32>>())?,
+                NP_TypeKeys::Double => NP_Tensor::new(shape, &(0..len).map(|_| entropy.u64() as i64 as f64 / 256f64).collect::<Vec<f64>>())?,
+                _ => {
+                    let bytes: Vec<u8> = (0..(len * dtype_size(dtype))).map(|_| entropy.byte()).collect();
+                    opt_err(NP_Tensor::from_le_bytes(&memory.schema[cursor.schema_addr], bytes))?
+                }
+            };
+            NP_Tensor::set_value(cursor, memory, tensor)?;
+        },
+        NP_Parsed_Schema::BigInt { .. } => {
+            let magnitude: Vec<u8> = (0..entropy.below(MAX_LEN + 1)).map(|_| entropy.byte()).collect();
+            NP_BigInt::set_value(cursor, memory, NP_BigInt::new(entropy.byte() & 1 == 1, &magnitude))?;
+        },
+        NP_Parsed_Schema::Custom { .. } => {
+            let bytes: Vec<u8> = (0..entropy.below(MAX_LEN + 1)).map(|_| entropy.byte()).collect();
+            NP_Custom::set_value(cursor, memory, NP_Custom::new(&bytes))?;
+        },
+        NP_Parsed_Schema::Any { .. } | NP_Parsed_Schema::None => { }
+    }
+
+    Ok(())
+}
+
+/// Fill an empty buffer with plausible values picked by the seed
+pub fn generate_sample(memory: &NP_Memory, seed: u32) -> Result<(), NP_Error> {
+    let mut rand = Rand::new(seed);
+    sample(NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), memory, &mut rand)
+}
+
+// number in min..=max
+fn between(rand: &mut Rand, min: i64, max: i64) -> i64 {
+    min + (rand.rand() as u64 % (max - min + 1) as u64) as i64
+}
+
+fn words(rand: &mut Rand, count: usize) -> String {
+    let mut value = String::new();
+    for x in 0..count {
+        if x > 0 { value.push(' '); }
+        value.push_str(WORDS[rand.rand() as usize % WORDS.len()]);
+    }
+    value
+}
+
+fn sample(cursor: NP_Cursor, memory: &NP_Memory, rand: &mut Rand) -> Result<(), NP_Error> {
+
+    let schema = &memory.schema[cursor.schema_addr];
+
+    // values with a schema default use it some of the time
+    match schema {
+        NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::Tuple { .. } | NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Log { .. } | NP_Parsed_Schema::Map { .. } => { },
+        _ => {
+            if rand.rand() % 4 == 0 {
+                if let NP_JSON::Null = NP_Schema::_type_to_json(&memory.schema, cursor.schema_addr)?["default"] { } else {
+                    return NP_Cursor::set_schema_default(cursor, memory);
+                }
+            }
+        }
+    }
+
+    match schema {
+        NP_Parsed_Schema::Table { columns, .. } => {
+            for (_, name, _) in columns.iter() {
+                let column = opt_err(NP_Table::select(cursor.clone(), name, true, memory)?)?;
+                sample(column, memory, rand)?;
+            }
+        },
+        NP_Parsed_Schema::Tuple { values, .. } => {
+            for idx in 0..values.len() {
+                let value = opt_err(NP_Tuple::select(cursor.clone(), idx, true, memory)?)?;
+                sample(value, memory, rand)?;
+            }
+        },
+        NP_Parsed_Schema::List { .. } => {
+            for index in 0..(between(rand, 1, 4) as usize) {
+                let item = opt_err(opt_err(NP_List::select(cursor.clone(), index, true, memory)?)?.1)?;
+                sample(item, memory, rand)?;
+            }
+            NP_List::restore_order(&cursor, memory)?;
+        },
+        NP_Parsed_Schema::Log { .. } => {
+            for _ in 0..between(rand, 1, 4) {
+                let (_, item) = NP_Log::append(&cursor, memory)?;
+                sample(item, memory, rand)?;
+            }
+        },
+        NP_Parsed_Schema::Map { .. } => {
+            // keys are different words
+            let first = rand.rand() as usize;
+            for x in 0..(between(rand, 1, 4) as usize) {
+                let key = WORDS[(first + x) % WORDS.len()];
+                let item = opt_err(NP_Map::select(cursor.clone(), key, true, memory)?)?;
+                sample(item, memory, rand)?;
+            }
+        },
+        NP_Parsed_Schema::UTF8String { size, .. } => {
+            let count = between(rand, 1, 3) as usize;
+            let mut value = words(rand, count);
+            if *size > 0 {
+                // fill fixed size strings with words
+                while value.len() < *size as usize {
+                    value.push(' ');
+                    value.push_str(&words(rand, 1));
+                }
+                value.truncate(*size as usize);
+            }
+            <&str>::set_value(cursor, memory, &value)?;
+        },
+        NP_Parsed_Schema::Bytes { size, .. } => {
+            let len = if *size > 0 { *size as usize } else { between(rand, 1, 16) as usize };
+            let value: Vec<u8> = (0..len).map(|_| rand.rand() as u8).collect();
+            <&[u8]>::set_value(cursor, memory, &value)?;
+        },
+        NP_Parsed_Schema::Int8    { .. } => { i8::set_value(cursor, memory, between(rand, -100, 100) as i8)?; },
+        NP_Parsed_Schema::Int16   { .. } => { i16::set_value(cursor, memory, between(rand, -1000, 1000) as i16)?; },
+        NP_Parsed_Schema::Int32   { .. } => { i32::set_value(cursor, memory, between(rand, -1000, 1000) as i32)?; },
+        NP_Parsed_Schema::Int64   { .. } => { i64::set_value(cursor, memory, between(rand, -1000, 1000))?; },
+        NP_Parsed_Schema::Uint8   { .. } => { u8::set_value(cursor, memory, between(rand, 0, 100) as u8)?; },
+        NP_Parsed_Schema::Uint16  { .. } => { u16::set_value(cursor, memory, between(rand, 0, 1000) as u16)?; },
+        NP_Parsed_Schema::Uint32  { .. } => { u32::set_value(cursor, memory, between(rand, 0, 1000) as u32)?; },
+        NP_Parsed_Schema::Uint64  { .. } => { u64::set_value(cursor, memory, between(rand, 0, 1000) as u64)?; },
+        NP_Parsed_Schema::Float   { .. } => { f32::set_value(cursor, memory, between(rand, -10000, 10000) as f32 / 100f32)?; },
+        NP_Parsed_Schema::Double  { .. } => { f64::set_value(cursor, memory, between(rand, -10000, 10000) as f64 / 100f64)?; },
+        NP_Parsed_Schema::Decimal { exp, .. } => { NP_Dec::set_value(cursor, memory, NP_Dec::new(between(rand, -100000, 100000), *exp))?; },
+        NP_Parsed_Schema::Boolean { .. } => { bool::set_value(cursor, memory, rand.rand() % 2 == 0)?; },
+        NP_Parsed_Schema::Geo { size, .. } => {
+            let lat = between(rand, -90_000_000, 90_000_000) as f64 / 1_000_000f64;
+            let lng = between(rand, -180_000_000, 180_000_000) as f64 / 1_000_000f64;
+            NP_Geo::set_value(cursor, memory, NP_Geo::new(*size, lat, lng))?;
+        },
+        NP_Parsed_Schema::Date { .. } => {
+            NP_Date::set_value(cursor, memory, NP_Date::new(between(rand, SAMPLE_DATES.0 as i64, SAMPLE_DATES.1 as i64) as u64))?;
+        },
+        NP_Parsed_Schema::Enum { choices, .. } => {
+            if choices.len() > 0 {
+                let choice = choices[rand.rand() as usize % choices.len()].clone();
+                NP_Enum::set_value(cursor, memory, choice)?;
+            }
+        },
+        NP_Parsed_Schema::Uuid { .. } => {
+            <&NP_UUID>::set_value(cursor, memory, &NP_UUID::generate(rand.rand()))?;
+        },
+        NP_Parsed_Schema::Ulid { .. } => {
+            let time = between(rand, SAMPLE_DATES.0 as i64, SAMPLE_DATES.1 as i64) as u64;
+            <&NP_ULID>::set_value(cursor, memory, &NP_ULID::generate(time, rand.rand()))?;
+        },
+        NP_Parsed_Schema::Tensor { dtype, shape, .. } => {
+            let len: usize = shape.iter().product();
+            let tensor = match dtype {
+                NP_TypeKeys::Float  => NP_Tensor::new(shape, &(0..len).map(|_| between(rand, -100, 100) as f32 / 10f32).collect::<Vec<f32>>())?,
+                NP_TypeKeys::Double => NP_Tensor::new(shape, &(0..len).map(|_| between(rand, -100, 100) as f64 / 10f64).collect::<Vec<f64>>())?,
+                _ => {
+                    // small numbers in every integer type, little endian
+                    let size = dtype_size(dtype);
+                    let mut bytes: Vec<u8> = Vec::with_capacity(len * size);
+                    for _ in 0..len {
+                        bytes.push(between(rand, 0, 100) as u8);
+                        bytes.extend((1..size).map(|_| 0u8));
+                    }
+                    opt_err(NP_Tensor::from_le_bytes(&memory.schema[cursor.schema_addr], bytes))?
+                }
+            };
+            NP_Tensor::set_value(cursor, memory, tensor)?;
+        },
+        NP_Parsed_Schema::BigInt { .. } => {
+            NP_BigInt::set_value(cursor, memory, NP_BigInt::from(between(rand, -1_000_000_000, 1_000_000_000)))?;
+        },
+        // only the plugin knows what plausible bytes look like
+        NP_Parsed_Schema::Custom { .. } => { },
+        NP_Parsed_Schema::Any { .. } => {
+            let value = words(rand, 1);
+            let value = match rand.rand() % 3 {
+                0 => NP_Dyn::Str(&value),
+                1 => NP_Dyn::I64(between(rand, -1000, 1000)),
+                _ => NP_Dyn::Bool(rand.rand() % 2 == 0)
+            };
+            NP_Dyn::set_value(cursor, memory, value)?;
+        },
+        NP_Parsed_Schema::None => { }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn generated_buffers_are_valid() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["string", {"type": "string"}],
+        ["fixed",  {"type": "string", "size": 5}],
+        ["upper",  {"type": "string", "uppercase": true}],
+        ["bytes",  {"type": "bytes"}],
+        ["i64",    {"type": "i64"}],
+        ["f32",    {"type": "float"}],
+        ["dec",    {"type": "decimal", "exp": 2}],
+        ["bool",   {"type": "bool"}],
+        ["geo",    {"type": "geo8"}],
+        ["date",   {"type": "date"}],
+        ["option", {"type": "option", "choices": ["red", "blue"]}],
+        ["uuid",   {"type": "uuid"}],
+        ["ulid",   {"type": "ulid"}],
+        ["list",   {"type": "list", "of": {"type": "string"}}],
+        ["map",    {"type": "map", "value": {"type": "u8"}}],
+        ["tuple",  {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}],
+        ["log",    {"type": "log", "of": {"type": "i32"}}],
+        ["tensor", {"type": "tensor", "dtype": "f64", "shape": [2, 2]}],
+        ["bigint", {"type": "bigint"}]
+    ]}"#)?;
+
+    let mut rand = crate::utils::Rand::new(2846);
+
+    for _ in 0..500 {
+        let len = rand.gen_range(0, 512) as usize;
+        let entropy: Vec<u8> = (0..len).map(|_| rand.gen_range(0, 255) as u8).collect();
+
+        let buffer = factory.arbitrary_buffer(&entropy)?;
+        let json = buffer.json_encode(&[])?.stringify();
+
+        // same entropy, same buffer
+        assert_eq!(factory.arbitrary_buffer(&entropy)?.close(), buffer.read_bytes().to_vec());
+
+        let mut checked = factory.open_checked_buffer(buffer.close())?;
+        assert_eq!(checked.json_encode(&[])?.stringify(), json);
+
+        let size = checked.calc_bytes()?;
+        checked.compact(None)?;
+        assert_eq!(checked.calc_bytes()?.current_buffer, size.after_compaction);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn samples_are_valid() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["string", {"type": "string"}],
+        ["fixed",  {"type": "string", "size": 12}],
+        ["upper",  {"type": "string", "uppercase": true}],
+        ["bytes",  {"type": "bytes", "size": 3}],
+        ["i8",     {"type": "i8", "default": 7}],
+        ["u64",    {"type": "u64"}],
+        ["dec",    {"type": "decimal", "exp": 2}],
+        ["geo",    {"type": "geo16"}],
+        ["date",   {"type": "date"}],
+        ["option", {"type": "option", "choices": ["red", "blue"]}],
+        ["uuid",   {"type": "uuid"}],
+        ["ulid",   {"type": "ulid"}],
+        ["any",    {"type": "any"}],
+        ["list",   {"type": "list", "of": {"type": "string"}}],
+        ["map",    {"type": "map", "value": {"type": "u8"}}],
+        ["tuple",  {"type": "tuple", "values": [{"type": "u8"}, {"type": "string", "size": 4}], "sorted": true}],
+        ["log",    {"type": "log", "of": {"type": "i32"}}],
+        ["tensor", {"type": "tensor", "dtype": "i16", "shape": [2, 2]}],
+        ["bigint", {"type": "bigint"}]
+    ]}"#)?;
+
+    let mut used_default = false;
+
+    for seed in 0..200 {
+        let buffer = factory.generate_sample(seed)?;
+        let json = buffer.json_encode(&[])?.stringify();
+
+        // every value is set
+        let mut unset: Vec<String> = Vec::new();
+        factory.schema.walk(|path, _| {
+            if path.len() == 1 && buffer.json_encode(path).map(|x| x.stringify()) == Ok(String::from("null")) {
+                unset.push(path.join("."));
+            }
+        });
+        assert_eq!(unset, Vec::<String>::new());
+
+        assert_eq!(buffer.get::<&str>(&["fixed"])?.unwrap().len(), 12);
+        assert_eq!(buffer.get::<&str>(&["upper"])?.unwrap(), buffer.get::<&str>(&["upper"])?.unwrap().to_uppercase());
+        assert!(buffer.length(&["list"])?.unwrap() > 0);
+        used_default |= buffer.get::<i8>(&["i8"])? == Some(7);
+
+        let checked = factory.open_checked_buffer(buffer.close())?;
+        assert_eq!(checked.json_encode(&[])?.stringify(), json);
+        assert_eq!(factory.generate_sample(seed)?.json_encode(&[])?.stringify(), json);
+    }
+
+    assert!(used_default);
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! Low level access by address
+//!
+//! Inside the buffer every value is found by two addresses: where its schema is in `NP_Factory::schema.parsed` and where its pointer is in the buffer bytes.  An `NP_Raw_Addr` holds both, so custom traversals (like a query engine) can step through a buffer one level at a time without building string paths.
+//!
+//! Start from `NP_Buffer::raw_root`, step into collections with `raw_select` and read or write values with `raw_get` and `raw_set`.  Look up `schema_addr()` in the factory's parsed schema to see what kind of value an address holds.
+//!
+//! Addresses are checked every time they're used, an address from a buffer with a different schema returns an error instead of reading the wrong bytes.  Compacting a buffer moves its values, get new addresses after `compact`.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::schema::NP_Parsed_Schema;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["tags", "1"], "pilot")?;
+//!
+//! let root = new_buffer.raw_root();
+//! let tags = new_buffer.raw_select(root, "tags")?.unwrap();
+//! assert!(matches!(factory.schema.parsed[tags.schema_addr()], NP_Parsed_Schema::List { .. }));
+//!
+//! let second = new_buffer.raw_select(tags, "1")?.unwrap();
+//! assert_eq!(new_buffer.raw_get::<&str>(second)?, Some("pilot"));
+//!
+//! // values that don't exist yet can be made
+//! let name = new_buffer.raw_select_make(root, "name")?.unwrap();
+//! new_buffer.raw_set(name, "Jeb Kermin")?;
+//! assert_eq!(new_buffer.get::<&str>(&["name"])?, Some("Jeb Kermin"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::ROOT_PTR_ADDR;
+use crate::error::NP_Error;
+use crate::memory::NP_Memory;
+use crate::pointer::NP_Cursor;
+use crate::schema::{NP_Parsed_Schema, NP_Schema_Addr};
+
+/// The schema and buffer address of a value, see the [raw](index.html) module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NP_Raw_Addr {
+    schema_addr: NP_Schema_Addr,
+    buff_addr: usize,
+    // the collection holding this value decides how its pointer is laid out
+    parent_schema_addr: NP_Schema_Addr
+}
+
+impl NP_Raw_Addr {
+
+    pub(crate) fn from_cursor(cursor: NP_Cursor) -> Self {
+        NP_Raw_Addr { schema_addr: cursor.schema_addr, buff_addr: cursor.buff_addr, parent_schema_addr: cursor.parent_schema_addr }
+    }
+
+    /// Address of this value's schema in `NP_Factory::schema.parsed`
+    pub fn schema_addr(&self) -> NP_Schema_Addr {
+        self.schema_addr
+    }
+
+    /// Address of this value's pointer in the buffer
+    pub fn buff_addr(&self) -> usize {
+        self.buff_addr
+    }
+
+    /// Make sure the address fits the buffer and schema before using it
+    pub(crate) fn check(&self, memory: &NP_Memory) -> Result<NP_Cursor, NP_Error> {
+        let schema = memory.schema;
+        let invalid = || NP_Error::new_static("Raw address doesn't belong to this buffer!");
+
+        if self.schema_addr >= schema.len() || self.parent_schema_addr >= schema.len() {
+            return Err(invalid());
+        }
+
+        if self.buff_addr == ROOT_PTR_ADDR {
+            if self.schema_addr != 0 {
+                return Err(invalid());
+            }
+        } else {
+            if self.buff_addr + 2 > memory.read_bytes().len() {
+                return Err(invalid());
+            }
+
+            let is_child = match &schema[self.parent_schema_addr] {
+                NP_Parsed_Schema::Table { columns, .. } => columns.iter().any(|column| column.2 == self.schema_addr),
+                NP_Parsed_Schema::Tuple { values, .. } => values.contains(&self.schema_addr),
+                NP_Parsed_Schema::List { of, .. } => *of == self.schema_addr,
+                NP_Parsed_Schema::Log { of, .. } => *of == self.schema_addr,
+                NP_Parsed_Schema::Map { value, .. } => *value == self.schema_addr,
+                _ => false
+            };
+            if !is_child {
+                return Err(invalid());
+            }
+        }
+
+        Ok(NP_Cursor::new(self.buff_addr, self.schema_addr, self.parent_schema_addr))
+    }
+}
+
+#[test]
+fn raw_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "map", "value": {"type": "tuple", "values": [{"type": "u32"}, {"type": "string"}]}}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    let root = buffer.raw_root();
+    assert_eq!(root.buff_addr(), ROOT_PTR_ADDR);
+    assert_eq!(buffer.raw_select(root, "key")?, None);
+
+    let item = buffer.raw_select_make(root, "key")?.unwrap();
+    let count = buffer.raw_select_make(item, "0")?.unwrap();
+    assert!(buffer.raw_set(count, "wrong type").is_err());
+    buffer.raw_set(count, 12u32)?;
+    assert_eq!(buffer.get::<u32>(&["key", "0"])?, Some(12));
+    assert_eq!(buffer.raw_get::<u32>(count)?, Some(12));
+    assert_eq!(buffer.raw_select(item, "0")?, Some(count));
+    assert!(buffer.raw_select(count, "0")?.is_none());
+
+    // addresses from a buffer with a different schema are caught
+    let other = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u32"}}"#)?;
+    let mut other_buffer = other.empty_buffer(None);
+    let other_item = other_buffer.raw_select_make(other_buffer.raw_root(), "0")?.unwrap();
+    assert!(buffer.raw_get::<u32>(other_item).is_err());
+    assert!(buffer.raw_get::<u32>(NP_Raw_Addr { schema_addr: 2, buff_addr: 60_000, parent_schema_addr: 1 }).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,238 @@
+//! Buffers split into read and write halves
+//!
+//! `NP_Buffer::into_reader` turns a buffer into an `NP_Buffer_Reader`.  Readers only have methods that take `&self` and never change their bytes.  Cloning a reader shares the bytes instead of copying them.  Readers are `Send` and `Sync`, so many threads can read one buffer without a lock, through a shared reference or by each holding a clone.  An [`NP_Cell`](../cell/index.html) hands out readers of the latest version of a buffer to any thread.
+//!
+//! `NP_Buffer_Reader::into_writer` turns a reader back into an `NP_Buffer_Writer`, which has every method of `NP_Buffer`.  If other readers still share the bytes, the writer copies them before its first change, the other readers never see it.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb Kermin")?;
+//!
+//! let reader = new_buffer.into_reader();
+//! let other_reader = reader.clone();
+//! assert_eq!(reader.get::<&str>(&["name"])?, Some("Jeb Kermin"));
+//!
+//! // writers can change the buffer, other readers keep their bytes
+//! let mut writer = reader.into_writer();
+//! writer.set(&["age"], 30u8)?;
+//! assert_eq!(writer.get::<u8>(&["age"])?, Some(30));
+//! assert_eq!(other_reader.get::<u8>(&["age"])?, None);
+//!
+//! let reader = writer.into_reader();
+//! assert_eq!(reader.json_encode(&[])?.stringify(), r#"{"name":"Jeb Kermin","age":30}"#);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::{NP_Buffer, NP_Generic_Iterator, NP_ValueState};
+use crate::error::NP_Error;
+use crate::json_flex::NP_JSON;
+use crate::path::NP_PathHandle;
+use crate::pointer::{NP_Scalar, NP_Value, any::NP_Dyn};
+use crate::raw::NP_Raw_Addr;
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+
+/// The read half of a buffer, get one with `NP_Buffer::into_reader`.
+///
+/// See the [reader](index.html) module for an example.
+pub struct NP_Buffer_Reader<'buffer> {
+    // always holds frozen memory, which never writes to its bytes or flags
+    buffer: NP_Buffer<'buffer>
+}
+
+impl<'buffer> NP_Buffer_Reader<'buffer> {
+
+    #[doc(hidden)]
+    pub fn _new(buffer: NP_Buffer<'buffer>) -> Self {
+        NP_Buffer_Reader { buffer }
+    }
+
+    /// Turn this reader into a writer
+    pub fn into_writer(self) -> NP_Buffer_Writer<'buffer> {
+        NP_Buffer_Writer::_new(self.buffer.thawed())
+    }
+
+    /// Same as `NP_Buffer::get`
+    pub fn get<'get, X>(&'get self, path: &[&str]) -> Result<Option<X>, NP_Error> where X: 'get + NP_Value<'get> + NP_Scalar {
+        self.buffer.get(path)
+    }
+
+    /// Same as `NP_Buffer::get_state`
+    pub fn get_state<'get, X>(&'get self, path: &[&str]) -> Result<NP_ValueState<X>, NP_Error> where X: 'get + NP_Value<'get> + NP_Scalar {
+        self.buffer.get_state(path)
+    }
+
+    /// Same as `NP_Buffer::get_h`
+    pub fn get_h<'get, X>(&'get self, handle: &NP_PathHandle) -> Result<Option<X>, NP_Error> where X: 'get + NP_Value<'get> + NP_Scalar {
+        self.buffer.get_h(handle)
+    }
+
+    /// Same as `NP_Buffer::get_dyn`
+    pub fn get_dyn<'get>(&'get self, path: &[&str]) -> Result<Option<NP_Dyn<'get>>, NP_Error> {
+        self.buffer.get_dyn(path)
+    }
+
+    /// Same as `NP_Buffer::get_iter`
+    pub fn get_iter<'iter>(&'iter self, path: &'iter [&str]) -> Result<Option<NP_Generic_Iterator<'iter>>, NP_Error> {
+        self.buffer.get_iter(path)
+    }
+
+    /// Same as `NP_Buffer::length`
+    pub fn length(&self, path: &[&str]) -> Result<Option<usize>, NP_Error> {
+        self.buffer.length(path)
+    }
+
+    /// Same as `NP_Buffer::json_encode`
+    pub fn json_encode(&self, path: &[&str]) -> Result<NP_JSON, NP_Error> {
+        self.buffer.json_encode(path)
+    }
+
+    /// Same as `NP_Buffer::raw_root`
+    pub fn raw_root(&self) -> NP_Raw_Addr {
+        self.buffer.raw_root()
+    }
+
+    /// Same as `NP_Buffer::raw_select`
+    pub fn raw_select(&self, at: NP_Raw_Addr, key: &str) -> Result<Option<NP_Raw_Addr>, NP_Error> {
+        self.buffer.raw_select(at, key)
+    }
+
+    /// Same as `NP_Buffer::raw_get`
+    pub fn raw_get<'get, X>(&'get self, at: NP_Raw_Addr) -> Result<Option<X>, NP_Error> where X: 'get + NP_Value<'get> + NP_Scalar {
+        self.buffer.raw_get(at)
+    }
+
+    /// Read the bytes of the buffer
+    pub fn read_bytes(&self) -> &[u8] {
+        self.buffer.read_bytes()
+    }
+
+    /// Get a copy of the buffer's bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.buffer.read_bytes().to_vec()
+    }
+}
+
+// The memory of a reader is frozen.  Its bytes are only ever borrowed immutably, every write and every change to the memory's bookkeeping panics on frozen memory, and none of the methods above measure the buffer (the only thing that changes frozen memory).  Cloning only adds to the count of the `Arc` holding the bytes.
+unsafe impl<'buffer> Sync for NP_Buffer_Reader<'buffer> { }
+
+impl<'buffer> Clone for NP_Buffer_Reader<'buffer> {
+    fn clone(&self) -> Self {
+        NP_Buffer_Reader { buffer: self.buffer.frozen() }
+    }
+}
+
+/// The write half of a buffer, get one with `NP_Buffer::into_writer` or `NP_Buffer_Reader::into_writer`.
+///
+/// Writers have every method of `NP_Buffer`, see the [reader](index.html) module for an example.
+pub struct NP_Buffer_Writer<'buffer> {
+    buffer: NP_Buffer<'buffer>
+}
+
+impl<'buffer> NP_Buffer_Writer<'buffer> {
+
+    #[doc(hidden)]
+    pub fn _new(buffer: NP_Buffer<'buffer>) -> Self {
+        NP_Buffer_Writer { buffer }
+    }
+
+    /// Turn this writer into a reader
+    pub fn into_reader(self) -> NP_Buffer_Reader<'buffer> {
+        self.buffer.into_reader()
+    }
+
+    /// Turn this writer back into a plain buffer
+    pub fn into_buffer(self) -> NP_Buffer<'buffer> {
+        self.buffer
+    }
+}
+
+impl<'buffer> Deref for NP_Buffer_Writer<'buffer> {
+    type Target = NP_Buffer<'buffer>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+impl<'buffer> DerefMut for NP_Buffer_Writer<'buffer> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buffer
+    }
+}
+
+#[test]
+fn reader_works() -> Result<(), NP_Error> {
+    fn shared<T: Send + Sync>(_value: &T) { }
+
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    shared(&factory);
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["0"], "hello")?;
+    let reader = buffer.into_reader();
+    shared(&reader);
+    let first = reader.clone();
+
+    // the writer copies the shared bytes before changing them
+    let mut writer = reader.into_writer();
+    for i in 1..20 {
+        writer.set(&[alloc::format!("{}", i).as_str()], "world")?;
+    }
+    writer.set(&["0"], "there")?;
+    assert_eq!(first.json_encode(&[])?.stringify(), r#"["hello"]"#);
+    assert_eq!(first.get::<&str>(&["1"])?, None);
+
+    let second = writer.into_reader();
+    assert_eq!(second.get::<&str>(&["0"])?, Some("there"));
+    assert_eq!(second.length(&[])?, Some(20));
+    assert_eq!(second.get_iter(&[])?.unwrap().count(), 20);
+
+    // a reader nothing else shares writes in place
+    let mut last = second.into_writer();
+    last.compact(None)?;
+    assert_eq!(factory.open_buffer(last.into_buffer().close()).get::<&str>(&["19"])?, Some("world"));
+
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn reader_threads_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "map", "value": {"type": "u32"}}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    for i in 0..50u32 {
+        buffer.set(&[alloc::format!("k{}", i).as_str()], i)?;
+    }
+    let reader = buffer.into_reader();
+
+    // every thread reads through the same reference
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..4).map(|_| scope.spawn(|| -> Result<u32, NP_Error> {
+            let mut sum = 0;
+            for i in 0..50u32 {
+                sum += reader.get::<u32>(&[alloc::format!("k{}", i).as_str()])?.unwrap_or(0);
+            }
+            assert_eq!(reader.get_iter(&[])?.unwrap().count(), 50);
+            Ok(sum)
+        })).collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap()?, 1225);
+        }
+        Ok(())
+    })
+}
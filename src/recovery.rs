@@ -0,0 +1,738 @@
+//! Best effort recovery of corrupted buffers
+//!
+//! Buffers read from unreliable storage or networks can contain addresses that point outside the buffer, pointer chains that loop back on themselves or string data that isn't valid UTF8.
+//!
+//! `NP_Factory::open_lenient` walks every pointer in a buffer against the schema before handing the buffer back.  Each value that can't be read safely is detached from the buffer (so it reads as unset) and recorded in an `NP_Recovery_Report`.  Everything else in the buffer stays readable.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "hello")?;
+//! new_buffer.set(&["age"], 20u8)?;
+//! let mut bytes = new_buffer.close();
+//!
+//! // chop off the end of the buffer, the "age" value is now out of bounds
+//! bytes.pop();
+//!
+//! let (recovered, report) = factory.open_lenient(bytes);
+//! assert_eq!(report.is_clean(), false);
+//! assert_eq!(report.unreadable[0].path, vec!["age"]);
+//!
+//! // everything that survived is still there
+//! assert_eq!(recovered.get::<&str>(&["name"])?, Some("hello"));
+//! assert_eq!(recovered.get::<u8>(&["age"])?, None);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! `NP_Factory::open_verified` runs the same walk in strict mode for ingestion boundaries.  On top of the checks above it rejects unknown header flags, values that overlap the buffer header, list and log tails or counts that don't match their items, and booleans that aren't `0` or `1`.  Nothing is repaired, the first problem found is returned as an error.
+//!
+
+use crate::pointer::{NP_Cursor, NP_Value, any::NP_Dyn};
+use crate::buffer::ROOT_PTR_ADDR;
+use crate::memory::{NP_Memory, INLINE_TAG};
+use crate::fingerprint;
+use crate::schema::{NP_Parsed_Schema, NP_Schema_Addr};
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// A part of the buffer that couldn't be read
+#[derive(Debug, Clone, PartialEq)]
+pub struct NP_Unreadable {
+    /// Path to the value that was detached from the buffer
+    pub path: Vec<String>,
+    /// Why this value couldn't be read
+    pub reason: &'static str
+}
+
+/// List of every value that was detached from a buffer during recovery
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NP_Recovery_Report {
+    /// The detached values, in the order they were found
+    pub unreadable: Vec<NP_Unreadable>
+}
+
+impl NP_Recovery_Report {
+    /// True if no corruption was found
+    pub fn is_clean(&self) -> bool {
+        self.unreadable.len() == 0
+    }
+}
+
+/// Walk the whole buffer and detach everything that can't be read safely
+#[doc(hidden)]
+pub fn recover(memory: &NP_Memory) -> NP_Recovery_Report {
+    walk_buffer(memory, false)
+}
+
+/// Walk the whole buffer in strict mode, anything that would be repaired or detached is reported
+#[doc(hidden)]
+pub fn verify(memory: &NP_Memory) -> NP_Recovery_Report {
+    walk_buffer(memory, true)
+}
+
+fn walk_buffer(memory: &NP_Memory, strict: bool) -> NP_Recovery_Report {
+
+    let mut walker = NP_Recovery_Walker {
+        memory,
+        strict,
+        claimed: Vec::new(),
+        shared: Vec::new(),
+        path: Vec::new(),
+        report: NP_Recovery_Report::default()
+    };
+
+    walker.claimed.resize(memory.read_bytes().len(), false);
+
+    if strict {
+        if memory.has_unknown_flags() {
+            walker.report("Buffer has unknown header flags!");
+            return walker.report;
+        }
+        // root pointer, tombstone pointer, version and fingerprint
+        if let Some(reason) = walker.claim(0, fingerprint::address(memory) + fingerprint::size(memory)) {
+            walker.report(reason);
+            return walker.report;
+        }
+    } else {
+        // root pointer (and the reserved byte in front of it)
+        walker.claim(0, ROOT_PTR_ADDR + 2);
+    }
+
+    walker.walk(ROOT_PTR_ADDR, 0, 0);
+
+    walker.report
+}
+
+struct NP_Recovery_Walker<'walk> {
+    memory: &'walk NP_Memory<'walk>,
+    // report problems that lenient opening would quietly repair
+    strict: bool,
+    // every byte that belongs to a value we've already walked, no two values may share bytes
+    claimed: Vec<bool>,
+    // interned or deduplicated values are the only ones that can share bytes, these ranges have already been checked
+    // (start, end, checked as UTF8)
+    shared: Vec<(usize, usize, bool)>,
+    path: Vec<String>,
+    report: NP_Recovery_Report
+}
+
+impl<'walk> NP_Recovery_Walker<'walk> {
+
+    #[inline(always)]
+    fn read_addr(&self, address: usize) -> usize {
+        match self.memory.get_2_bytes(address) {
+            Some(x) => u16::from_be_bytes(*x) as usize,
+            None => 0
+        }
+    }
+
+    #[inline(always)]
+    fn write_addr(&self, address: usize, value: usize) {
+        let bytes = self.memory.write_bytes();
+        let value_bytes = (value as u16).to_be_bytes();
+        bytes[address] = value_bytes[0];
+        bytes[address + 1] = value_bytes[1];
+    }
+
+    #[inline(always)]
+    fn byte(&self, address: usize) -> usize {
+        self.memory.read_bytes()[address] as usize
+    }
+
+    /// Checks that `start..end` is inside the buffer and not used by anything else, then marks it as used.
+    fn claim(&mut self, start: usize, end: usize) -> Option<&'static str> {
+        if end > self.claimed.len() {
+            return Some("Value extends past end of buffer!");
+        }
+        if self.claimed[start..end].iter().any(|x| *x) {
+            return Some("Value overlaps another value!");
+        }
+        for x in &mut self.claimed[start..end] {
+            *x = true;
+        }
+        None
+    }
+
+    fn detach(&mut self, ptr_addr: usize, reason: &'static str) {
+        self.write_addr(ptr_addr, 0);
+        self.report(reason);
+    }
+
+    fn report(&mut self, reason: &'static str) {
+        self.report.unreadable.push(NP_Unreadable { path: self.path.clone(), reason });
+    }
+
+    // ptr_addr is the address of the pointer's value address, the caller guarantees the pointer itself is inside the buffer and claimed
+    fn walk(&mut self, ptr_addr: usize, schema_addr: NP_Schema_Addr, parent_schema_addr: NP_Schema_Addr) {
+
+        let memory = self.memory;
+
+        let addr = self.read_addr(ptr_addr);
+
+        // no value here
+        if addr == 0 {
+            return;
+        }
+
+        // one byte values stored in the pointer itself
+        if memory.is_inline(schema_addr) {
+            if self.strict && addr & 0xFF00 != INLINE_TAG as usize {
+                self.detach(ptr_addr, "Inline value has a bad tag!");
+                return;
+            }
+            match &memory.schema[schema_addr] {
+                NP_Parsed_Schema::Enum { choices, .. } if addr & 0xFF >= choices.len() => {
+                    self.detach(ptr_addr, "Option index is larger than number of choices!");
+                },
+                NP_Parsed_Schema::Boolean { .. } if self.strict && addr & 0xFF > 1 => {
+                    self.detach(ptr_addr, "Boolean is not 0 or 1!");
+                },
+                _ => { }
+            }
+            return;
+        }
+
+        if addr >= self.claimed.len() {
+            self.detach(ptr_addr, "Address is outside of buffer!");
+            return;
+        }
+
+        match &memory.schema[schema_addr] {
+            NP_Parsed_Schema::None => { },
+            NP_Parsed_Schema::Table { columns, .. } => {
+                let names: Vec<(String, NP_Schema_Addr)> = columns.iter().map(|col| (col.1.clone(), col.2)).collect();
+                self.walk_vtables(ptr_addr, schema_addr, names);
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                let names: Vec<(String, NP_Schema_Addr)> = values.iter().enumerate().map(|(idx, value)| (idx.to_string(), *value)).collect();
+                self.walk_vtables(ptr_addr, schema_addr, names);
+            },
+            NP_Parsed_Schema::List { of, .. } => {
+                // head & tail
+                if let Some(reason) = self.claim(addr, addr + 4) {
+                    self.detach(ptr_addr, reason);
+                    return;
+                }
+                self.walk_list(addr, schema_addr, *of);
+            },
+            NP_Parsed_Schema::Log { of, .. } => {
+                // head, tail & count
+                if let Some(reason) = self.claim(addr, addr + 6) {
+                    self.detach(ptr_addr, reason);
+                    return;
+                }
+                self.walk_log(addr, schema_addr, *of);
+            },
+            NP_Parsed_Schema::Map { value, .. } => {
+                self.walk_map(ptr_addr, schema_addr, *value);
+            },
+            _ => { // scalars
+                let cursor = NP_Cursor::new(ptr_addr, schema_addr, parent_schema_addr);
+                let value_size = match NP_Cursor::calc_size(&cursor, memory) {
+                    Ok(size) => size - cursor.get_value(memory).get_size(),
+                    Err(_) => {
+                        self.detach(ptr_addr, "Value size can't be calculated!");
+                        return;
+                    }
+                };
+
+                let shared = match &memory.schema[schema_addr] {
+                    NP_Parsed_Schema::UTF8String { intern, size, .. } => *intern || (*size == 0 && memory.has_shared()),
+                    NP_Parsed_Schema::Bytes { size, .. } => *size == 0 && memory.has_shared(),
+                    _ => false
+                };
+
+                let is_string = match &memory.schema[schema_addr] {
+                    NP_Parsed_Schema::UTF8String { .. } => true,
+                    _ => false
+                };
+
+                // a value we've already checked, strings sharing bytes with a bytes value still need a UTF8 check
+                if shared {
+                    if let Some(idx) = self.shared.iter().position(|x| x.0 == addr && x.1 == addr + value_size) {
+                        if is_string && self.shared[idx].2 == false {
+                            if core::str::from_utf8(&memory.read_bytes()[(addr + 2)..(addr + value_size)]).is_err() {
+                                self.detach(ptr_addr, "String is not valid UTF8!");
+                                return;
+                            }
+                            self.shared[idx].2 = true;
+                        }
+                        return;
+                    }
+                }
+
+                if let Some(reason) = self.claim(addr, addr + value_size) {
+                    self.detach(ptr_addr, reason);
+                    return;
+                }
+
+                match &memory.schema[schema_addr] {
+                    NP_Parsed_Schema::UTF8String { size, .. } => {
+                        let start = if *size > 0 { addr } else { addr + 2 };
+                        if core::str::from_utf8(&memory.read_bytes()[start..(addr + value_size)]).is_err() {
+                            self.detach(ptr_addr, "String is not valid UTF8!");
+                            return;
+                        }
+                    },
+                    NP_Parsed_Schema::Enum { choices, wide, .. } => {
+                        let index = if *wide { (self.byte(addr) << 8) | self.byte(addr + 1) } else { self.byte(addr) };
+                        if index >= choices.len() {
+                            self.detach(ptr_addr, "Option index is larger than number of choices!");
+                        }
+                    },
+                    NP_Parsed_Schema::Boolean { .. } if self.strict && self.byte(addr) > 1 => {
+                        self.detach(ptr_addr, "Boolean is not 0 or 1!");
+                    },
+                    NP_Parsed_Schema::Any { .. } => {
+                        if NP_Dyn::into_value(&cursor, memory).is_err() {
+                            self.detach(ptr_addr, "String is not valid UTF8!");
+                        }
+                    },
+                    _ => { }
+                }
+
+                if shared {
+                    self.shared.push((addr, addr + value_size, is_string));
+                }
+            }
+        }
+    }
+
+    fn walk_vtables(&mut self, ptr_addr: usize, schema_addr: NP_Schema_Addr, columns: Vec<(String, NP_Schema_Addr)>) {
+
+        let mut vtables = columns.len() / 4;
+        if columns.len() % 4 != 0 {
+            vtables += 1;
+        }
+
+        // the address field pointing to the current vtable
+        let mut link_addr = ptr_addr;
+
+        for v in 0..vtables {
+            let vtable_addr = self.read_addr(link_addr);
+
+            if vtable_addr == 0 {
+                return;
+            }
+
+            if let Some(reason) = self.claim(vtable_addr, vtable_addr + 10) {
+                self.path.push(columns[v * 4].0.clone());
+                self.detach(link_addr, reason);
+                self.path.pop();
+                return;
+            }
+
+            for slot in 0..4 {
+                let col = (v * 4) + slot;
+                if col >= columns.len() {
+                    break;
+                }
+                self.path.push(columns[col].0.clone());
+                self.walk(vtable_addr + (slot * 2), columns[col].1, schema_addr);
+                self.path.pop();
+            }
+
+            link_addr = vtable_addr + 8;
+        }
+
+        // the last vtable never links anywhere
+        if self.read_addr(link_addr) != 0 {
+            self.detach(link_addr, "Table has too many vtables!");
+        }
+    }
+
+    fn walk_log(&mut self, log_addr: usize, schema_addr: NP_Schema_Addr, of: NP_Schema_Addr) {
+
+        let tail_addr = self.read_addr(log_addr + 2);
+        let count = self.read_addr(log_addr + 4);
+
+        // the address field pointing to the current item
+        let mut link_addr = log_addr;
+        let mut last_item = 0usize;
+        let mut index = 0usize;
+
+        loop {
+            let item_addr = self.read_addr(link_addr);
+
+            if item_addr == 0 {
+                break;
+            }
+
+            let reason = match self.claim(item_addr, item_addr + 4) {
+                Some(reason) => Some(reason),
+                None if index >= count => Some("Log has more items than its count!"),
+                None => None
+            };
+
+            if let Some(reason) = reason {
+                self.path.push(index.to_string());
+                self.detach(link_addr, reason);
+                self.path.pop();
+                break;
+            }
+
+            self.path.push(index.to_string());
+            self.walk(item_addr, of, schema_addr);
+            self.path.pop();
+
+            index += 1;
+            last_item = item_addr;
+            link_addr = item_addr + 2;
+        }
+
+        if self.strict {
+            if tail_addr != last_item {
+                self.report("Log tail isn't its last item!");
+            } else if count != index {
+                self.report("Log count doesn't match its items!");
+            }
+            return;
+        }
+
+        if tail_addr != last_item {
+            self.write_addr(log_addr + 2, last_item);
+        }
+
+        if count != index {
+            self.write_addr(log_addr + 4, index);
+        }
+    }
+
+    fn walk_list(&mut self, list_addr: usize, schema_addr: NP_Schema_Addr, of: NP_Schema_Addr) {
+
+        let tail_addr = self.read_addr(list_addr + 2);
+
+        // the address field pointing to the current item
+        let mut link_addr = list_addr;
+        let mut last_item = 0usize;
+        let mut last_index: Option<usize> = None;
+
+        // wide lists have a u32 index after the item's addresses
+        let wide = matches!(self.memory.schema[schema_addr], NP_Parsed_Schema::List { wide: true, .. });
+        let item_size = if wide { 8 } else { 5 };
+        let read_index = |recovery: &Self, item_addr: usize| -> usize {
+            if wide {
+                (recovery.read_addr(item_addr + 4) << 16) | recovery.read_addr(item_addr + 6)
+            } else {
+                recovery.byte(item_addr + 4)
+            }
+        };
+
+        loop {
+            let item_addr = self.read_addr(link_addr);
+
+            if item_addr == 0 {
+                break;
+            }
+
+            let reason = match self.claim(item_addr, item_addr + item_size) {
+                Some(reason) => Some(reason),
+                None => match last_index {
+                    Some(x) if read_index(self, item_addr) <= x => Some("List items are out of order!"),
+                    _ => None
+                }
+            };
+
+            if let Some(reason) = reason {
+                self.path.push(match last_index { Some(x) => (x + 1).to_string(), None => String::from("0") });
+                self.detach(link_addr, reason);
+                self.path.pop();
+                break;
+            }
+
+            let index = read_index(self, item_addr);
+
+            self.path.push(index.to_string());
+            self.walk(item_addr, of, schema_addr);
+            self.path.pop();
+
+            last_index = Some(index);
+            last_item = item_addr;
+            link_addr = item_addr + 2;
+        }
+
+        if tail_addr != last_item {
+            if self.strict {
+                self.report("List tail isn't its last item!");
+                return;
+            }
+            self.write_addr(list_addr + 2, last_item);
+        }
+    }
+
+    fn walk_map(&mut self, ptr_addr: usize, schema_addr: NP_Schema_Addr, value: NP_Schema_Addr) {
+
+        // the address field pointing to the current item
+        let mut link_addr = ptr_addr;
+        let mut count = 0usize;
+
+        loop {
+            let item_addr = self.read_addr(link_addr);
+
+            if item_addr == 0 {
+                break;
+            }
+
+            let reason = if count >= 255 {
+                Some("Map has too many items!")
+            } else {
+                match self.claim(item_addr, item_addr + 6) {
+                    Some(reason) => Some(reason),
+                    None => {
+                        let key_addr = self.read_addr(item_addr + 4);
+                        if key_addr == 0 || key_addr >= self.claimed.len() {
+                            Some("Map key is outside of buffer!")
+                        } else {
+                            let key_len = self.byte(key_addr);
+                            match self.claim(key_addr, key_addr + 1 + key_len) {
+                                Some(reason) => Some(reason),
+                                None => {
+                                    if core::str::from_utf8(&self.memory.read_bytes()[(key_addr + 1)..(key_addr + 1 + key_len)]).is_err() {
+                                        Some("Map key is not valid UTF8!")
+                                    } else {
+                                        None
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            };
+
+            if let Some(reason) = reason {
+                self.detach(link_addr, reason);
+                break;
+            }
+
+            count += 1;
+
+            let key_addr = self.read_addr(item_addr + 4);
+            let key_len = self.byte(key_addr);
+            let key = String::from_utf8_lossy(&self.memory.read_bytes()[(key_addr + 1)..(key_addr + 1 + key_len)]).to_string();
+
+            self.path.push(key);
+            self.walk(item_addr, value, schema_addr);
+            self.path.pop();
+
+            link_addr = item_addr + 2;
+        }
+    }
+}
+
+#[test]
+fn lenient_open_works() -> Result<(), crate::error::NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [["tags", {"type": "list", "of": {"type": "string"}}], ["meta", {"type": "map", "value": {"type": "u32"}}]]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["tags", "0"], "hello")?;
+    buffer.set(&["tags", "1"], "world")?;
+    buffer.set(&["meta", "count"], 20u32)?;
+    let bytes = buffer.close();
+
+    // clean buffer
+    let (clean, report) = factory.open_lenient(bytes.clone());
+    assert!(report.is_clean());
+    assert_eq!(clean.close(), bytes);
+
+    // point the second list item back at the first one
+    let vtable = u16::from_be_bytes([bytes[1], bytes[2]]) as usize;
+    let list_addr = u16::from_be_bytes([bytes[vtable], bytes[vtable + 1]]) as usize;
+    let head = u16::from_be_bytes([bytes[list_addr], bytes[list_addr + 1]]) as usize;
+    let second = u16::from_be_bytes([bytes[head + 2], bytes[head + 3]]) as usize;
+    let mut looped = bytes.clone();
+    looped[second + 2] = bytes[list_addr];
+    looped[second + 3] = bytes[list_addr + 1];
+
+    let (recovered, report) = factory.open_lenient(looped);
+    assert_eq!(report.unreadable.len(), 1);
+    assert_eq!(report.unreadable[0].path, ["tags", "2"].to_vec());
+    assert_eq!(recovered.get::<&str>(&["tags", "1"])?, Some("world"));
+    assert_eq!(recovered.get::<u32>(&["meta", "count"])?, Some(20));
+    assert_eq!(recovered.get_iter(&["tags"])?.unwrap().count(), 2);
+
+    // root pointer outside of buffer
+    let (recovered, report) = factory.open_lenient([0u8, 200, 1].to_vec());
+    assert_eq!(report.is_clean(), false);
+    assert_eq!(recovered.get::<&str>(&["tags", "0"])?, None);
+
+    Ok(())
+}
+
+#[test]
+fn checked_open_survives_random_corruption() -> Result<(), crate::error::NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["string", {"type": "string"}],
+        ["fixed",  {"type": "string", "size": 5}],
+        ["bytes",  {"type": "bytes"}],
+        ["dec",    {"type": "decimal", "exp": 2}],
+        ["geo",    {"type": "geo8"}],
+        ["option", {"type": "option", "choices": ["red", "blue"]}],
+        ["list",   {"type": "list", "of": {"type": "string"}}],
+        ["map",    {"type": "map", "value": {"type": "u8"}}],
+        ["tuple",  {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}]
+    ]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["string"], "hello")?;
+    buffer.set(&["fixed"], "world")?;
+    buffer.set(&["bytes"], &[1u8, 2, 3] as &[u8])?;
+    buffer.set(&["option"], crate::pointer::option::NP_Enum::new("blue"))?;
+    buffer.set(&["list", "2"], "list")?;
+    buffer.set(&["list", "5"], "items")?;
+    buffer.set(&["map", "foo"], 2u8)?;
+    buffer.set(&["map", "bar"], 3u8)?;
+    buffer.set(&["tuple", "1"], "tuple")?;
+    let bytes = buffer.close();
+
+    let mut rand = crate::utils::Rand::new(2845);
+
+    for _ in 0..2000 {
+        let mut corrupt = bytes.clone();
+        for _ in 0..rand.gen_range(1, 4) {
+            let idx = rand.gen_range(0, corrupt.len() as i32 - 1) as usize;
+            corrupt[idx] = rand.gen_range(0, 255) as u8;
+        }
+        corrupt.truncate(rand.gen_range(1, corrupt.len() as i32) as usize);
+
+        if let Ok(mut checked) = factory.open_checked_buffer(corrupt.clone()) {
+            checked.json_encode(&[])?;
+            // overlapping values can make this fail, but never panic
+            let _ = checked.calc_bytes();
+            checked.set(&["list", "10"], "hello")?;
+            checked.compact(None)?;
+        }
+
+        let (mut lenient, _report) = factory.open_lenient(corrupt);
+        lenient.json_encode(&[])?;
+        // unset paths may still fail to resolve, they just can't panic
+        let _ = lenient.get::<&str>(&["list", "5"]);
+        let _ = lenient.get::<u8>(&["map", "foo"]);
+        lenient.compact(None)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn verified_open_works() -> Result<(), crate::error::NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [["flag", {"type": "bool"}], ["log", {"type": "log", "of": {"type": "u8"}}], ["tags", {"type": "list", "of": {"type": "string"}}]]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["flag"], true)?;
+    buffer.log_append(&["log"], 2u8)?;
+    buffer.log_append(&["log"], 3u8)?;
+    buffer.set(&["tags", "0"], "hello")?;
+    let bytes = buffer.close();
+
+    assert!(factory.open_verified(bytes.clone()).is_ok());
+
+    let vtable = u16::from_be_bytes([bytes[1], bytes[2]]) as usize;
+    let flag_addr = u16::from_be_bytes([bytes[vtable], bytes[vtable + 1]]) as usize;
+    let log_addr = u16::from_be_bytes([bytes[vtable + 2], bytes[vtable + 3]]) as usize;
+
+    // boolean that's neither true nor false
+    let mut bad_flag = bytes.clone();
+    bad_flag[flag_addr] = 7;
+    assert!(factory.open_checked_buffer(bad_flag.clone()).is_ok());
+    match factory.open_verified(bad_flag) {
+        Err(crate::error::NP_Error::CorruptBuffer { path, reason }) => {
+            assert_eq!(path, ["flag"].to_vec());
+            assert_eq!(reason, "Boolean is not 0 or 1!");
+        },
+        _ => panic!()
+    }
+
+    // log count larger than its items
+    let mut bad_count = bytes.clone();
+    bad_count[log_addr + 5] += 1;
+    assert!(factory.open_checked_buffer(bad_count.clone()).is_ok());
+    assert!(factory.open_verified(bad_count).is_err());
+
+    // flag from a newer version
+    let mut bad_header = bytes.clone();
+    bad_header[0] |= 0x80;
+    assert!(factory.open_verified(bad_header).is_err());
+
+    // inline values and fingerprints are part of a well formed buffer
+    let mut buffer = factory.open_buffer(bytes.clone());
+    buffer.inline_small_values()?;
+    buffer.embed_fingerprint()?;
+    let inline_bytes = buffer.close();
+    let verified = factory.open_verified(inline_bytes)?;
+    assert_eq!(verified.get::<bool>(&["flag"])?, Some(true));
+
+    let mut rand = crate::utils::Rand::new(2935);
+
+    for _ in 0..2000 {
+        let mut corrupt = bytes.clone();
+        for _ in 0..rand.gen_range(1, 4) {
+            let idx = rand.gen_range(0, corrupt.len() as i32 - 1) as usize;
+            corrupt[idx] = rand.gen_range(0, 255) as u8;
+        }
+
+        if let Ok(mut verified) = factory.open_verified(corrupt.clone()) {
+            assert!(factory.open_checked_buffer(corrupt).is_ok());
+            verified.json_encode(&[])?;
+            verified.compact(None)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cyclic_buffers_terminate() -> Result<(), crate::error::NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["tags", {"type": "list", "of": {"type": "string"}}],
+        ["meta", {"type": "map", "value": {"type": "u8"}}],
+        ["c",    {"type": "u8"}],
+        ["d",    {"type": "u8"}],
+        ["e",    {"type": "u8"}]
+    ]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["tags", "0"], "hello")?;
+    buffer.set(&["tags", "1"], "world")?;
+    buffer.set(&["meta", "foo"], 2u8)?;
+    buffer.set(&["meta", "bar"], 3u8)?;
+    buffer.set(&["e"], 4u8)?;
+    let bytes = buffer.close();
+
+    let vtable = u16::from_be_bytes([bytes[1], bytes[2]]) as usize;
+    let list_addr = u16::from_be_bytes([bytes[vtable], bytes[vtable + 1]]) as usize;
+    let first = u16::from_be_bytes([bytes[list_addr], bytes[list_addr + 1]]) as usize;
+    let second = u16::from_be_bytes([bytes[first + 2], bytes[first + 3]]) as usize;
+    let map_head = u16::from_be_bytes([bytes[vtable + 2], bytes[vtable + 3]]) as usize;
+
+    let mut looped = bytes.clone();
+    // second list item points back at the first
+    looped[second + 2] = bytes[list_addr];
+    looped[second + 3] = bytes[list_addr + 1];
+    // map head points at itself
+    looped[map_head + 2] = bytes[vtable + 2];
+    looped[map_head + 3] = bytes[vtable + 3];
+    // first vtable points at itself
+    looped[vtable + 8] = bytes[1];
+    looped[vtable + 9] = bytes[2];
+
+    // opened without any checks, every traversal still stops
+    let mut buffer = factory.open_buffer(looped);
+    assert_eq!(buffer.get_iter(&["tags"])?.unwrap().count(), 2);
+    assert!(buffer.get_iter(&["meta"])?.unwrap().count() <= buffer.read_bytes().len() / 6);
+    assert_eq!(buffer.get::<u8>(&["meta", "baz"])?, None);
+    buffer.json_encode(&[])?;
+    // items counted more than once can add up past the buffer length
+    let _ = buffer.calc_bytes();
+    buffer.compact(None)?;
+    assert_eq!(buffer.get::<&str>(&["tags", "1"])?, Some("world"));
+
+    Ok(())
+}
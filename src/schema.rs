@@ -23,7 +23,7 @@
 //!     // used by tuple to indicite bytewise sorting of children
 //!     sorted?: boolean;
 //!     
-//!     // used by list types
+//!     // used by list & log types
 //!     of?: NP_Schema
 //!     
 //!     // used by map types
@@ -38,8 +38,21 @@
 //!     // used by option/enum types
 //!     choices?: string[];
 //! 
+//!     // used by tensor types, the number type and size of each dimension
+//!     dtype?: string;
+//!     shape?: number[];
+//! 
 //!     // default value for this item
 //!     default?: any;
+//! 
+//!     // used by table columns, `false` marks the column as required for `buffer.validate()`
+//!     nullable?: boolean;
+//! 
+//!     // used by table columns, `true` leaves the column out of `buffer.json_encode_redacted()` and `buffer.compact_redacted()`
+//!     sensitive?: boolean;
+//! 
+//!     // used by table columns, how `NP_Buffer::crdt_merge` resolves conflicts
+//!     merge?: "lww" | "max" | "sum" | "set_union";
 //! }
 //! ```
 //! 
@@ -97,9 +110,12 @@
 //! |----------------------------------------|--------------------------------------------------------------------------|------------------|----------------|--------------------------------------------------------------------------|
 //! | [`table`](#table)                      | [`NP_Table`](../collection/table/struct.NP_Table.html)                   |𐄂                 | 2 bytes - ~4GB | Linked list with indexed keys that map against up to 255 named columns.  |
 //! | [`list`](#list)                        | [`NP_List`](../collection/list/struct.NP_List.html)                      |𐄂                 | 4 bytes - ~4GB | Linked list with integer indexed values and  up to 65,535 items.         |
+//! | [`sorted_list`](#sorted_list)          | [`NP_List`](../collection/list/struct.NP_List.html)                      |𐄂                 | 4 bytes - ~4GB | List of sortable values kept in order, up to 256 items.                  |
+//! | [`log`](#log)                          | [`NP_Log`](../collection/log/struct.NP_Log.html)                         |𐄂                 | 6 bytes - ~4GB | Append only sequence of values with up to 65,535 items.                  |
+//! | [`ring`](#ring)                        | [`NP_Log`](../collection/log/struct.NP_Log.html)                         |𐄂                 | 6 bytes - ~4GB | Log that keeps only its newest `capacity` items.                         |
 //! | [`map`](#map)                          | [`NP_Map`](../collection/map/struct.NP_Map.html)                         |𐄂                 | 2 bytes - ~4GB | Linked list with `Vec<u8>` keys.                                         |
 //! | [`tuple`](#tuple)                      | [`NP_Tuple`](../collection/tuple/struct.NP_Tuple.html)                   |✓ *               | 2 bytes - ~4GB | Static sized collection of specific values.                              |
-//! | [`any`](#any)                          | [`NP_Any`](../pointer/any/struct.NP_Any.html)                            |𐄂                 | 2 bytes - ~4GB | Generic type.                                                            |
+//! | [`any`](#any)                          | [`NP_Dyn`](../pointer/any/enum.NP_Dyn.html)                              |𐄂                 | 2 bytes - ~4GB | Any number, bool, string or bytes type, picked at runtime.              |
 //! | [`string`](#string)                    | [`String`](../pointer/string/index.html)                                 |✓ **              | 2 bytes - ~4GB | Utf-8 formatted string.                                                  |
 //! | [`bytes`](#bytes)                      | [`NP_Bytes`](../pointer/bytes/struct.NP_Bytes.html)                      |✓ **              | 2 bytes - ~4GB | Arbitrary bytes.                                                         |
 //! | [`int8`](#int8-int16-int32-int64)      | [`i8`](../pointer/numbers/index.html)                                    |✓                 | 1 byte         | -127 to 127                                                              |
@@ -112,7 +128,7 @@
 //! | [`uint64`](#uint8-uint16-uint32-uint64)| [`u64`](../pointer/numbers/index.html)                                   |✓                 | 8 bytes        | 0 - 18,446,744,073,709,551,616                                           |
 //! | [`float`](#float-double)               | [`f32`](../pointer/numbers/index.html)                                   |𐄂                 | 4 bytes        | -3.4e38 to 3.4e38                                                        |
 //! | [`double`](#float-double)              | [`f64`](../pointer/numbers/index.html)                                   |𐄂                 | 8 bytes        | -1.7e308 to 1.7e308                                                      |
-//! | [`option`](#option)                    | [`NP_Enum`](../pointer/option/struct.NP_Enum.html)                   |✓                 | 1 byte         | Up to 255 string based options in schema.                                |
+//! | [`option`](#option)                    | [`NP_Enum`](../pointer/option/struct.NP_Enum.html)                   |✓                 | 1 - 2 bytes    | Up to 254 string based options in schema, 65,534 with `wide`.            |
 //! | [`bool`](#bool)                        | [`bool`](../pointer/bool/index.html)                                     |✓                 | 1 byte         |                                                                          |
 //! | [`decimal`](#decimal)                  | [`NP_Dec`](../pointer/dec/struct.NP_Dec.html)                            |✓                 | 8 bytes        | Fixed point decimal number based on i64.                                 |
 //! | [`geo4`](#geo4-geo8-geo16)             | [`NP_Geo`](../pointer/geo/struct.NP_Geo.html)                            |✓                 | 4 bytes        | 1.1km resolution (city) geographic coordinate                            |
@@ -120,6 +136,9 @@
 //! | [`geo16`](#geo4-geo8-geo16)            | [`NP_Geo`](../pointer/geo/struct.NP_Geo.html)                            |✓                 | 16 bytes       | 110 microns resolution (grain of sand) geographic coordinate             |
 //! | [`ulid`](#ulid)                        | [`NP_ULID`](../pointer/ulid/struct.NP_ULID.html)                         |✓                 | 16 bytes       | 6 bytes for the timestamp, 10 bytes of randomness.                       |
 //! | [`uuid`](#uuid)                        | [`NP_UUID`](../pointer/uuid/struct.NP_UUID.html)                         |✓                 | 16 bytes       | v4 UUID, 2e37 possible UUIDs                                             |
+//! | [`bigint`](#bigint)                    | [`NP_BigInt`](../pointer/bigint/struct.NP_BigInt.html)                   |𐄂                 | 3+ bytes       | Arbitrary precision integer, sign and magnitude with a size prefix.     |
+//! | [custom types](#custom-types)       | [`NP_Custom`](../pointer/custom/struct.NP_Custom.html)                   |𐄂                 | 2+ bytes       | Scalar types from plugins, see `NP_Factory::new_with_plugins`.           |
+//! | [`tensor`](#tensor)                    | [`NP_Tensor`](../pointer/tensor/struct.NP_Tensor.html)                   |𐄂                 | 1 - 65,535 bytes | Fixed shape array of numbers, aligned for zero copy reads.           |
 //! | [`date`](#date)                        | [`NP_Date`](../pointer/date/struct.NP_Date.html)                         |✓                 | 8 bytes        | Good to store unix epoch (in milliseconds) until the year 584,866,263    |
 //!  
 //! - \* `sorting` must be set to `true` in the schema for this object to enable sorting.
@@ -161,7 +180,7 @@
 //! 
 //! - **Bytewise Sorting**: Unsupported
 //! - **Compaction**: Columns without values will be removed from the buffer durring compaction.  If a column never had a value set it's using *zero* space in the buffer.
-//! - **Schema Mutations**: The ordering of items in the `columns` property must always remain the same.  It's safe to add new columns to the bottom of the column list or rename columns, but never to remove columns.  Column types cannot be changed safely.  If you need to depreciate a column, set it's name to an empty string. 
+//! - **Schema Mutations**: The ordering of items in the `columns` property must always remain the same.  It's safe to add new columns to the bottom of the column list or rename columns, but never to remove columns.  Column types cannot be changed safely.  If you need to depreciate a column, set it's name to an empty string, `compact_pruned` drops the values of depreciated columns from existing buffers. 
 //! 
 //! Table schemas have a single required property called `columns`.  The `columns` property is an array of arrays that represent all possible columns in the table and their data types.  Any type can be used in columns, including other tables.
 //! 
@@ -169,10 +188,12 @@
 //! 
 //! If you need flexible column names use a `map` type instead.
 //! 
+//! Set `"wide": true` for tables with more than 255 columns or column names longer than 255 bytes, wide tables can have up to 65,535 of each.  Adding or removing `wide` changes the compiled schema but not the buffers, existing buffers can still be opened.
+//! 
 //! ```json
 //! {
 //!     "type": "table",
-//!     "columns": [ // can have between 1 and 255 columns
+//!     "columns": [ // can have between 1 and 255 columns, or 65,535 if "wide" is true
 //!         ["column name",  {"type": "data type for this column"}],
 //!         ["name",         {"type": "string"}],
 //!         ["tags",         {"type": "list", "of": { // nested list of strings
@@ -197,10 +218,14 @@
 //! - **Compaction**: Indexes that have had their value cleared will be removed from the buffer.  If a specific index never had a value, it occupies *zero* space.
 //! - **Schema Mutations**: None
 //! 
-//! Lists have a single required property in the schema, `of`.  The `of` property contains another schema for the type of data contained in the list.  Any type is supported, including another list.  Tables cannot have more than 255 columns, and the colum names cannot be longer than 255 UTF8 bytes.
+//! Lists have a single required property in the schema, `of`.  The `of` property contains another schema for the type of data contained in the list.  Any type is supported, including another list.
 //! 
 //! The more items you have in a list, the slower it will be to seek to values towards the end of the list or loop through the list.
 //! 
+//! List indexes go up to 255.  Set `"wide": true` to store each index in 4 bytes instead of 1, wide lists go up to index 4,294,967,295.  Changing `wide` is destructive.
+//! 
+//! In JSON wide lists are objects with the indexes as keys, like `{"5": "a", "70000": "b"}`, so the gaps between far apart indexes aren't written out as nulls.
+//! 
 //! ```json
 //! // a list of list of strings
 //! {
@@ -216,11 +241,91 @@
 //!     "type": "list",
 //!     "of": {"type": "int32"}
 //! }
+//! 
+//! // list of tables, stored column by column
+//! {
+//!     "type": "list",
+//!     "layout": "columnar",
+//!     "of": {"type": "table", "columns": [
+//!         ["time",  {"type": "u64"}],
+//!         ["value", {"type": "float"}]
+//!     ]}
+//! }
 //! ```
 //! 
+//! Lists of tables can set `"layout": "columnar"`.  Compaction then copies the values one column at a time, so every value of a column ends up next to the values of the same column in the other rows.  Scanning one column reads the buffer in order, and fixed size columns become contiguous arrays.  New values are still added to the end of the buffer until the next compaction.
+//! 
 //! More Details:
 //! - [Using NP_List data type](../collection/list/struct.NP_List.html)
 //! 
+//! ## sorted_list
+//! Sorted lists are lists that keep their items in bytewise order, so they can be searched like an index.
+//! 
+//! - **Bytewise Sorting**: Unsupported
+//! - **Compaction**: Items keep their order and indexes.
+//! - **Schema Mutations**: None
+//! 
+//! The `of` property works the same as it does for lists, but it must be a sortable type (see the table above, strings and bytes need a fixed `size`).  Items are added with `NP_Buffer::sorted_insert` and found with `NP_Buffer::sorted_contains` and `NP_Buffer::sorted_range`, both use a binary search.  Items can't be set by index, but they can be read and deleted by index like any list.  Sorted lists hold up to 256 items.
+//! 
+//! ```json
+//! {
+//!     "type": "sorted_list",
+//!     "of": {"type": "u64"}
+//! }
+//! ```
+//! 
+//! Sorted lists can also order their items by a value inside each item, `key` is the path to that value through tables (by column name) and tuples (by index).  The items themselves don't need to be sortable, only the value at the key.  A sorted list with a key works as a priority queue, `NP_Buffer::sorted_push` adds an item from JSON and `NP_Buffer::pop_min` removes and returns the item with the smallest key.  `"type": "priority_queue"` is accepted as another name for a sorted list, it's not a binary heap and has the same 256 item limit.
+//! 
+//! ```json
+//! {
+//!     "type": "priority_queue",
+//!     "key": ["priority"],
+//!     "of": {"type": "table", "columns": [
+//!         ["priority", {"type": "u32"}],
+//!         ["task",     {"type": "string"}]
+//!     ]}
+//! }
+//! ```
+//! 
+//! ## log
+//! Logs are append only sequences of items.  Every item has the same type and appending an item takes constant time no matter how long the log is.
+//! 
+//! - **Bytewise Sorting**: Unsupported
+//! - **Compaction**: Every item is kept, including items that have had their value cleared, so indexes never change.
+//! - **Schema Mutations**: None
+//! 
+//! Logs have a single required property in the schema, `of`, which works the same as it does for lists.  New items can only be added with `NP_Buffer::log_append`, existing items can be updated by their index.
+//! 
+//! ```json
+//! {
+//!     "type": "log",
+//!     "of": {"type": "string"}
+//! }
+//! ```
+//! 
+//! More Details:
+//! - [Using NP_Log data type](../collection/log/struct.NP_Log.html)
+//! 
+//! ## ring
+//! Rings are logs with a fixed `capacity`, once a ring is full each new item takes the place of the oldest item.  This makes them a good fit for "last N samples" telemetry, the ring never grows and never needs compaction to drop old items.
+//! 
+//! - **Bytewise Sorting**: Unsupported
+//! - **Compaction**: Only the newest `capacity` items are ever in the ring, they're kept in order.
+//! - **Schema Mutations**: None
+//! 
+//! The `of` property works the same as it does for logs, `capacity` can be from 1 to 65,535.  Items are added with `NP_Buffer::log_append`, index `0` is always the oldest item still in the ring.
+//! 
+//! ```json
+//! {
+//!     "type": "ring",
+//!     "capacity": 100,
+//!     "of": {"type": "f32"}
+//! }
+//! ```
+//! 
+//! More Details:
+//! - [Using rings](../collection/log/index.html#rings)
+//! 
 //! ## map
 //! A map is a dynamically sized list of items where each key is a Vec<u8>.  Every value of a map has the same type.
 //! 
@@ -242,8 +347,17 @@
 //!         "type": "string"
 //!     }
 //! }
+//! 
+//! // a map that only stores hashes of its keys
+//! {
+//!     "type": "map",
+//!     "keys": "hashed",
+//!     "value": {"type": "u32"}
+//! }
 //! ```
 //! 
+//! Maps can set `"keys": "hashed"` to store two 4 byte murmur3 hashes of each key (with different seeds) instead of the key itself, so every key takes 8 bytes no matter how long it is.  The original keys can't be read back: iterating or encoding the map to JSON gives the hashes written as `#` and 16 hex digits, and keys in that form (like the ones from `json_encode`) are used as the hash they spell out.  Keys that really start with `#` are escaped with a second one (`##tag` for the key `#tag`), any other key starting with a single `#` is an error.  Adding a key whose first hash matches a different key already in the map returns an error instead of overwriting that key's value, the second hash is how the map tells them apart.  Setting JSON with two keys that have the same hashes also returns an error.
+//! 
 //! More Details:
 //! - [Using NP_Map data type](../collection/map/struct.NP_Map.html)
 //! 
@@ -310,8 +424,22 @@
 //!     "type": "string",
 //!     "default": "Default string value"
 //! }
+//! // zero padded on the left, padding removed when read
+//! {
+//!     "type": "string",
+//!     "size": 10,
+//!     "pad": "left",
+//!     "pad_byte": 0,
+//!     "trim": true
+//! }
 //! ```
 //! 
+//! Fixed size strings are padded with spaces on the right by default.  Use `pad` (`"left"` or `"right"`) and `pad_byte` (any ascii byte) to change the padding, and `trim` to remove it when values are read.
+//! 
+//! Set `"collation": "unicode_ci"` on a fixed size string to sort it case insensitively.  A lowercased copy of the value is stored in front of it as the sort key, so each value takes twice the `size` in the buffer.
+//! 
+//! Strings without a `size` can set `"intern": true` to store identical values once, see [interning](../pointer/string/index.html#interning).
+//! 
 //! More Details:
 //! - [Using String data type](../pointer/string/index.html)
 //! 
@@ -359,6 +487,15 @@
 //! - **Compaction**: Updates are done in place, never use additional space.
 //! - **Schema Mutations**: None
 //! 
+//! Integers can be used as counters with `NP_Buffer::increment`.  By default an increment that doesn't fit returns an error, set `"overflow": "saturate"` to clamp the value at the type's minimum or maximum or `"overflow": "wrap"` to wrap around instead.  The same goes for numbers of other types that are converted by `NP_Buffer::set`, see [NP_Overflow](../pointer/numbers/enum.NP_Overflow.html).
+//! 
+//! ```json
+//! {
+//!     "type": "int64",
+//!     "overflow": "saturate"
+//! }
+//! ```
+//! 
 //! More Details:
 //! - [Using number data types](../pointer/numbers/index.html)
 //! 
@@ -378,6 +515,11 @@
 //!     "type": "uint8",
 //!     "default": 20
 //! }
+//! // counter that stops at 255 instead of erroring
+//! {
+//!     "type": "uint8",
+//!     "overflow": "saturate"
+//! }
 //! ```
 //! 
 //! More Details:
@@ -399,13 +541,20 @@
 //!     "type": "float",
 //!     "default": 20.283
 //! }
+//! // NaN and infinity are "NaN", "Infinity" and "-Infinity" in JSON instead of null
+//! {
+//!     "type": "double",
+//!     "non_finite": "string"
+//! }
 //! ```
 //! 
+//! `"non_finite": "error"` refuses to set NaN or infinity, see [NP_NonFinite](../pointer/numbers/enum.NP_NonFinite.html).
+//! 
 //! More Details:
 //! - [Using number data types](../pointer/numbers/index.html)
 //! 
 //! ## option
-//! Allows efficeint storage of a selection between a known collection of ordered strings.  The selection is stored as a single u8 byte, limiting the max number of choices to 255.  Also the choices themselves cannot be longer than 255 UTF8 bytes each.
+//! Allows efficeint storage of a selection between a known collection of ordered strings.  The selection is stored as a single u8 byte, limiting the max number of choices to 254, or as a u16 for `wide` options.  Also the choices themselves cannot be longer than 255 UTF8 bytes each.
 //! 
 //! - **Bytewise Sorting**: Supported
 //! - **Compaction**: Updates are done in place, never use additional space.
@@ -424,8 +573,21 @@
 //!     "choices": ["choice 1", "choice 2", "etc"],
 //!     "default": "etc"
 //! }
+//! // up to 65,534 choices, stored in two bytes
+//! {
+//!     "type": "option",
+//!     "wide": true,
+//!     "choices": ["choice 1", "choice 2", "etc"]
+//! }
+//! // choices with their own numbers, the rest count up from the one before
+//! {
+//!     "type": "option",
+//!     "choices": [["choice 1", 10], "choice 2", ["etc", -1]]
+//! }
 //! ```
 //! 
+//! Numbered choices can be set from integers, useful for enums from other systems where you don't control the numbers.  Changing `wide` is destructive.
+//! 
 //! More Details:
 //! - [Using NP_Enum data type](../pointer/option/struct.NP_Enum.html)
 //! 
@@ -557,8 +719,126 @@
 //! 
 //! More Details:
 //! - [Using NP_Date data type](../pointer/date/struct.NP_Date.html)
+//! 
+//! ## bigint
+//! Allows you to store integers of any size, well past the range of `i64` and `u64`.  Values are stored as a sign byte followed by the big endian magnitude, with a 2 byte size prefix.  In JSON big integers are always strings of decimal digits.
+//! 
+//! - **Bytewise Sorting**: Unsupported
+//! - **Compaction**: Updates that are equal or smaller than the previous value are done in place, larger updates use additional space.
+//! - **Schema Mutations**: None
+//! 
+//! ```json
+//! {
+//!     "type": "bigint",
+//!     "default": "-340282366920938463463374607431768211456"
+//! }
+//! ```
+//! 
+//! More Details:
+//! - [Using NP_BigInt data type](../pointer/bigint/struct.NP_BigInt.html)
+//! 
+//! ## custom types
+//! Plugins add their own scalar types to a factory, the `type` is the plugin's `type_name` and any other properties are read by the plugin.  Values are stored as bytes with a 2 byte size prefix, what the bytes mean is up to the plugin.
+//! 
+//! - **Bytewise Sorting**: Unsupported
+//! - **Compaction**: Updates that are equal or smaller than the previous value are done in place, larger updates use additional space.
+//! - **Schema Mutations**: None
+//! 
+//! ```json
+//! {
+//!     "type": "color"
+//! }
+//! ```
+//! 
+//! More Details:
+//! - [Writing plugins](../pointer/custom/index.html)
+//! 
+//! ## tensor
+//! Allows you to store a matrix or tensor of numbers with a fixed shape.  Values are stored in row major order as little endian numbers, aligned to the size of the number type.
+//! 
+//! - **Bytewise Sorting**: Unsupported
+//! - **Compaction**: Updates are done in place, never use additional space.
+//! - **Schema Mutations**: None
+//! 
+//! The `dtype` property is one of `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`, `f32` or `f64`.  The `shape` property holds the size of each dimension, the whole tensor must fit in 65,535 bytes.
+//! 
+//! ```json
+//! {
+//!     "type": "tensor",
+//!     "dtype": "f32",
+//!     "shape": [3, 16, 16]
+//! }
+//! // no default supported
+//! ```
+//! 
+//! More Details:
+//! - [Using NP_Tensor data type](../pointer/tensor/struct.NP_Tensor.html)
+//! 
+//! ## any
+//! Holds a value of any number, bool, string or bytes type.  The type is picked when the value is set and stored in the buffer in front of the value, so it can be different for every buffer and change every time the value is set.
+//! 
+//! - **Bytewise Sorting**: Unsupported
+//! - **Compaction**: Updates to a value of the same type and size are done in place, other updates use additional space.
+//! - **Schema Mutations**: None
+//! 
+//! In JSON integers become `i64` values, numbers with a decimal become `f64` values and arrays of numbers between 0 and 255 become bytes.
+//! 
+//! ```json
+//! {
+//!     "type": "any"
+//! }
+//! // no default supported
+//! ```
+//! 
+//! More Details:
+//! - [Using NP_Dyn data type](../pointer/any/enum.NP_Dyn.html)
 //!  
 //! 
+//! # Definitions
+//! Types that are used in more than one place can be declared once in a `definitions` object at the root of the schema, then used anywhere with `{"$use": "name"}`.  Definitions can use other definitions, they're copied into place when the schema is parsed so buffers are identical to ones made from the copy pasted schema.
+//! 
+//! Definitions can't use themselves, directly or through other definitions.  Compiled schemas are trees, so recursive types can't be represented.  Since every use is a full copy, a schema can hold at most 65,536 JSON values once its definitions are copied in, larger schemas are rejected before they're parsed.
+//! 
+//! Definitions are shared by every root when the schema has [named roots](../struct.NP_Factory.html#method.factory_of).
+//! 
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! 
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!     "definitions": {
+//!         "address": {
+//!             "type": "table",
+//!             "columns": [
+//!                 ["street", {"type": "string"}],
+//!                 ["city",   {"type": "string"}],
+//!                 ["point",  {"$use": "location"}]
+//!             ]
+//!         },
+//!         "location": {"type": "geo8"}
+//!     },
+//!     "type": "table",
+//!     "columns": [
+//!         ["shipping", {"$use": "address"}],
+//!         ["billing",  {"$use": "address"}]
+//!     ]
+//! }"#)?;
+//! 
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["billing", "city"], "Portland")?;
+//! assert_eq!(new_buffer.get::<&str>(&["billing", "city"])?, Some("Portland"));
+//! 
+//! // definitions that use themselves are rejected
+//! assert!(NP_Factory::new(r#"{
+//!     "definitions": {
+//!         "node": {"type": "list", "of": {"$use": "node"}}
+//!     },
+//!     "$use": "node"
+//! }"#).is_err());
+//! 
+//! # Ok::<(), NP_Error>(()) 
+//! ```
+//! 
 //! ## Next Step
 //! 
 //! Read about how to initialize a schema into a NoProto Factory.
@@ -566,21 +846,29 @@
 //! [Go to NP_Factory docs](../struct.NP_Factory.html)
 //! 
 
-use alloc::string::String;
+pub mod codegen;
+
+use alloc::string::{String, ToString};
 use core::{fmt::Debug};
-use crate::{hashmap::NP_HashMap, json_flex::NP_JSON, pointer::{string::NP_String, ulid::_NP_ULID, uuid::_NP_UUID}};
+use crate::{hashmap::NP_HashMap, json_flex::{NP_JSON, JSMAP}, pointer::{string::NP_String, ulid::_NP_ULID, uuid::_NP_UUID}};
 use crate::pointer::any::NP_Any;
 use crate::pointer::date::NP_Date;
 use crate::pointer::geo::NP_Geo;
-use crate::pointer::dec::NP_Dec;
+use crate::pointer::dec::{NP_Dec, NP_Rounding};
 use crate::collection::tuple::NP_Tuple;
 use crate::pointer::bytes::NP_Bytes;
-use crate::collection::{list::NP_List, table::NP_Table, map::NP_Map};
-use crate::pointer::{option::NP_Enum, NP_Value};
+use crate::collection::{list::NP_List, table::NP_Table, map::NP_Map, log::NP_Log};
+use crate::pointer::{option::NP_Enum, tensor::{self, NP_Tensor}, bigint::NP_BigInt, custom::{NP_Custom, NP_Plugin, NP_Plugin_Ref, NP_PLUGIN_KEYS}, numbers::{NP_Overflow, NP_NonFinite}, NP_Value};
 use crate::error::NP_Error;
+use crate::crdt::NP_Merge;
+use crate::infer::{self, NP_Infer_Options};
+use codegen::{NP_Sql_Dialect, NP_Sql_Options};
 use alloc::vec::Vec;
 use alloc::boxed::Box;
 
+// most JSON values a schema can hold after `$use` is expanded
+const MAX_SCHEMA_NODES: usize = 65_536;
+
 /// Simple enum to store the schema types
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
 #[repr(u8)]
@@ -610,12 +898,18 @@ pub enum NP_TypeKeys {
     Table = 21,
     Map = 22, 
     List = 23,
-    Tuple = 24
+    Tuple = 24,
+    Log = 25,
+    Tensor = 26,
+    BigInt = 27,
+    /// Every type key in `NP_PLUGIN_KEYS` is a plugin type
+    Custom = 64
 }
 
 impl From<u8> for NP_TypeKeys {
     fn from(value: u8) -> Self {
-        if value > 24 { return NP_TypeKeys::None; }
+        if NP_PLUGIN_KEYS.contains(&value) { return NP_TypeKeys::Custom; }
+        if value > 27 { return NP_TypeKeys::None; }
         unsafe { core::mem::transmute(value) }
     }
 }
@@ -649,6 +943,10 @@ impl NP_TypeKeys {
             NP_TypeKeys::Map =>        {    NP_Map::type_idx() }
             NP_TypeKeys::List =>       {   NP_List::type_idx() }
             NP_TypeKeys::Tuple =>      {  NP_Tuple::type_idx() }
+            NP_TypeKeys::Log =>        {    NP_Log::type_idx() }
+            NP_TypeKeys::Tensor =>     { NP_Tensor::type_idx() }
+            NP_TypeKeys::BigInt =>     { NP_BigInt::type_idx() }
+            NP_TypeKeys::Custom =>     { NP_Custom::type_idx() }
         }
     }
 }
@@ -672,36 +970,53 @@ impl From<u8> for String_Case {
     }
 }
 
+/// How fixed size strings are ordered when sorting buffers
+#[derive(Debug, Clone, Eq, PartialEq, Copy)]
+#[repr(u8)]
+#[allow(missing_docs)]
+pub enum String_Collation {
+    /// Raw utf-8 bytes
+    None = 0,
+    /// Case insensitive, a lowercased sort key is stored before the value
+    UnicodeCi = 1,
+}
+
 /// When a schema is parsed from JSON or Bytes, it is stored in this recursive type
 /// 
+/// This is the internal representation and may change between versions, use [NP_SchemaNode](struct.NP_SchemaNode.html) to inspect schemas.
+/// 
 #[allow(missing_docs)]
 #[derive(Debug)]
 pub enum NP_Parsed_Schema {
     None,
     Any        { sortable: bool, i:NP_TypeKeys },
-    UTF8String { sortable: bool, i:NP_TypeKeys, default: Option<String>, size: u16, case: String_Case },
+    UTF8String { sortable: bool, i:NP_TypeKeys, default: Option<String>, size: u16, case: String_Case, pad_byte: u8, pad_left: bool, trim: bool, collation: String_Collation, intern: bool },
     Bytes      { sortable: bool, i:NP_TypeKeys, default: Option<Vec<u8>>, size: u16 },
-    Int8       { sortable: bool, i:NP_TypeKeys, default: Option<i8> },
-    Int16      { sortable: bool, i:NP_TypeKeys, default: Option<i16> },
-    Int32      { sortable: bool, i:NP_TypeKeys, default: Option<i32> },
-    Int64      { sortable: bool, i:NP_TypeKeys, default: Option<i64> },
-    Uint8      { sortable: bool, i:NP_TypeKeys, default: Option<u8> },
-    Uint16     { sortable: bool, i:NP_TypeKeys, default: Option<u16> },
-    Uint32     { sortable: bool, i:NP_TypeKeys, default: Option<u32> },
-    Uint64     { sortable: bool, i:NP_TypeKeys, default: Option<u64> },
-    Float      { sortable: bool, i:NP_TypeKeys, default: Option<f32> },
-    Double     { sortable: bool, i:NP_TypeKeys, default: Option<f64> },
-    Decimal    { sortable: bool, i:NP_TypeKeys, default: Option<NP_Dec>, exp: u8 },
+    Int8       { sortable: bool, i:NP_TypeKeys, default: Option<i8>, overflow: NP_Overflow },
+    Int16      { sortable: bool, i:NP_TypeKeys, default: Option<i16>, overflow: NP_Overflow },
+    Int32      { sortable: bool, i:NP_TypeKeys, default: Option<i32>, overflow: NP_Overflow },
+    Int64      { sortable: bool, i:NP_TypeKeys, default: Option<i64>, overflow: NP_Overflow },
+    Uint8      { sortable: bool, i:NP_TypeKeys, default: Option<u8>, overflow: NP_Overflow },
+    Uint16     { sortable: bool, i:NP_TypeKeys, default: Option<u16>, overflow: NP_Overflow },
+    Uint32     { sortable: bool, i:NP_TypeKeys, default: Option<u32>, overflow: NP_Overflow },
+    Uint64     { sortable: bool, i:NP_TypeKeys, default: Option<u64>, overflow: NP_Overflow },
+    Float      { sortable: bool, i:NP_TypeKeys, default: Option<f32>, non_finite: NP_NonFinite },
+    Double     { sortable: bool, i:NP_TypeKeys, default: Option<f64>, non_finite: NP_NonFinite },
+    Decimal    { sortable: bool, i:NP_TypeKeys, default: Option<NP_Dec>, exp: u8, round: NP_Rounding },
     Boolean    { sortable: bool, i:NP_TypeKeys, default: Option<bool> },
     Geo        { sortable: bool, i:NP_TypeKeys, default: Option<NP_Geo>, size: u8 },
     Date       { sortable: bool, i:NP_TypeKeys, default: Option<NP_Date> },
-    Enum       { sortable: bool, i:NP_TypeKeys, default: Option<NP_Enum>, choices: Vec<NP_Enum> },
+    Enum       { sortable: bool, i:NP_TypeKeys, default: Option<NP_Enum>, choices: Vec<NP_Enum>, values: Vec<i64>, wide: bool },
     Uuid       { sortable: bool, i:NP_TypeKeys },
     Ulid       { sortable: bool, i:NP_TypeKeys },
-    Table      { sortable: bool, i:NP_TypeKeys, columns: Vec<(u8, String, NP_Schema_Addr)>, columns_mapped: NP_HashMap },
-    Map        { sortable: bool, i:NP_TypeKeys, value: NP_Schema_Addr}, 
-    List       { sortable: bool, i:NP_TypeKeys, of: NP_Schema_Addr },
-    Tuple      { sortable: bool, i:NP_TypeKeys, values: Vec<NP_Schema_Addr>}
+    Table      { sortable: bool, i:NP_TypeKeys, columns: Vec<(u16, String, NP_Schema_Addr)>, columns_mapped: NP_HashMap, required: Vec<bool>, sensitive: Vec<bool>, merge: Vec<Option<NP_Merge>>, wide: bool },
+    Map        { sortable: bool, i:NP_TypeKeys, value: NP_Schema_Addr, hashed: bool }, 
+    List       { sortable: bool, i:NP_TypeKeys, of: NP_Schema_Addr, columnar: bool, sorted: bool, key: Vec<String>, wide: bool },
+    Tuple      { sortable: bool, i:NP_TypeKeys, values: Vec<NP_Schema_Addr>},
+    Log        { sortable: bool, i:NP_TypeKeys, of: NP_Schema_Addr, capacity: usize },
+    Tensor     { sortable: bool, i:NP_TypeKeys, dtype: NP_TypeKeys, shape: Vec<usize> },
+    BigInt     { sortable: bool, i:NP_TypeKeys, default: Option<NP_BigInt> },
+    Custom     { sortable: bool, i:NP_TypeKeys, key: u8, name: String, data: Vec<u8>, plugin: Option<NP_Plugin_Ref> }
 }
 
 impl NP_Parsed_Schema {
@@ -734,6 +1049,10 @@ impl NP_Parsed_Schema {
             NP_Parsed_Schema::Map        { i, .. }     => { i }
             NP_Parsed_Schema::List       { i, .. }     => { i }
             NP_Parsed_Schema::Tuple      { i, .. }     => { i }
+            NP_Parsed_Schema::Log        { i, .. }     => { i }
+            NP_Parsed_Schema::Tensor     { i, .. }     => { i }
+            NP_Parsed_Schema::BigInt     { i, .. }     => { i }
+            NP_Parsed_Schema::Custom     { i, .. }     => { i }
         }
     }
 
@@ -765,6 +1084,20 @@ impl NP_Parsed_Schema {
             NP_Parsed_Schema::Map        { i, .. }     => { i.into_type_idx() }
             NP_Parsed_Schema::List       { i, .. }     => { i.into_type_idx() }
             NP_Parsed_Schema::Tuple      { i, .. }     => { i.into_type_idx() }
+            NP_Parsed_Schema::Log        { i, .. }     => { i.into_type_idx() }
+            NP_Parsed_Schema::Tensor     { i, .. }     => { i.into_type_idx() }
+            NP_Parsed_Schema::BigInt     { i, .. }     => { i.into_type_idx() }
+            NP_Parsed_Schema::Custom     { name, .. }  => { (name.as_str(), NP_TypeKeys::Custom) }
+        }
+    }
+
+    /// Name of the type of this schema for errors, it's the same as `get_type_data` but never borrows the schema
+    pub fn get_type_name(&self) -> &'static str {
+        match self {
+            NP_Parsed_Schema::None => "",
+            // schemas opened without their plugin only know the name as a string
+            NP_Parsed_Schema::Custom { plugin, .. } => plugin.as_ref().map(|plugin| plugin.0.type_name()).unwrap_or("custom"),
+            _ => self.get_type_key().into_type_idx().0
         }
     }
 
@@ -796,20 +1129,25 @@ impl NP_Parsed_Schema {
             NP_Parsed_Schema::Map        { sortable, .. }     => { *sortable }
             NP_Parsed_Schema::List       { sortable, .. }     => { *sortable }
             NP_Parsed_Schema::Tuple      { sortable, .. }     => { *sortable }
+            NP_Parsed_Schema::Log        { sortable, .. }     => { *sortable }
+            NP_Parsed_Schema::Tensor     { sortable, .. }     => { *sortable }
+            NP_Parsed_Schema::BigInt     { sortable, .. }     => { *sortable }
+            NP_Parsed_Schema::Custom     { sortable, .. }     => { *sortable }
         }
     }
 }
 
 
 
-/// New NP Schema
-#[doc(hidden)]
+/// Parsed schema owned by a factory, available at `factory.schema`
 #[derive(Debug)]
 pub struct NP_Schema {
     /// is this schema sortable?
     pub is_sortable: bool,
     /// recursive parsed schema
-    pub parsed: Vec<NP_Parsed_Schema>
+    pub parsed: Vec<NP_Parsed_Schema>,
+    // hash of the compiled schema, from `fingerprint::of`
+    pub(crate) fingerprint: u64
 }
 
 impl NP_Schema {
@@ -819,6 +1157,121 @@ impl NP_Schema {
         NP_Schema::_type_to_json(&self.parsed, 0)
     }
 
+    /// A stable 64 bit hash of this schema, see the [fingerprint](../fingerprint/index.html) module.
+    /// 
+    /// The hash is taken from the compiled schema once, when the factory is made.  Schemas that only differ in whitespace or property order compile to the same bytes, so they have the same fingerprint.
+    pub fn fingerprint(&self) -> Result<u64, NP_Error> {
+        Ok(self.fingerprint)
+    }
+
+    /// Get the root type of this schema
+    /// 
+    /// See [NP_SchemaNode](struct.NP_SchemaNode.html) for an example.
+    /// 
+    pub fn root(&self) -> NP_SchemaNode<'_> {
+        NP_SchemaNode { schema: &self.parsed, address: 0 }
+    }
+
+    /// Propose a schema that can hold the example JSON value.
+    /// 
+    /// Returns the schema as JSON so it can be reviewed and changed before it's used.  See the [infer](../infer/index.html) module for details and an example.
+    /// 
+    pub fn infer_from_json(example: &NP_JSON, options: NP_Infer_Options) -> Result<NP_JSON, NP_Error> {
+        infer::infer_from_json(example, options)
+    }
+
+    /// Generate SQL `CREATE TABLE` statements for this schema, the root must be a table.
+    /// 
+    /// See the [codegen](codegen/index.html) module for details and an example.
+    /// 
+    pub fn to_sql(&self, dialect: NP_Sql_Dialect, options: &NP_Sql_Options) -> Result<String, NP_Error> {
+        codegen::to_sql(self, dialect, options)
+    }
+
+    /// Generate GraphQL type definitions for this schema, the root must be a table.
+    /// 
+    /// See the [codegen](codegen/index.html) module for details and an example.
+    /// 
+    pub fn to_graphql(&self, type_name: &str) -> Result<String, NP_Error> {
+        codegen::to_graphql(self, type_name)
+    }
+
+    /// Generate Rust source with typed accessor structs for this schema, the root must be a table.
+    /// 
+    /// See the [codegen](codegen/index.html) module for details and an example.
+    /// 
+    pub fn to_rust(&self, type_name: &str) -> Result<String, NP_Error> {
+        codegen::to_rust(self, type_name)
+    }
+
+    /// Visit every type in this schema, depth first, parents before their children.
+    /// 
+    /// The callback gets the path to each type along with the type itself.  Table columns are named by column name and tuple values by their index, the same as buffer paths.  List items and map values can be at any index or key, so they're named `*`.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut found: Vec<String> = Vec::new();
+    /// factory.schema.walk(|path, schema| {
+    ///     found.push(format!("{}: {}", path.join("."), schema.type_name()));
+    /// });
+    /// 
+    /// assert_eq!(found, vec![": table", "name: string", "tags: list", "tags.*: string"]);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn walk<F>(&self, mut callback: F) where F: FnMut(&[&str], &NP_SchemaNode) {
+        let mut path: Vec<String> = Vec::new();
+        NP_Schema::_walk(&self.parsed, 0, &mut path, &mut callback);
+    }
+
+    /// Recursive function to visit each type in the schema
+    #[doc(hidden)]
+    pub fn _walk<F>(parsed_schema: &Vec<NP_Parsed_Schema>, address: usize, path: &mut Vec<String>, callback: &mut F) where F: FnMut(&[&str], &NP_SchemaNode) {
+        {
+            let path_refs: Vec<&str> = path.iter().map(|part| part.as_str()).collect();
+            callback(&path_refs, &NP_SchemaNode { schema: parsed_schema, address });
+        }
+
+        match &parsed_schema[address] {
+            NP_Parsed_Schema::Table { columns, .. } => {
+                for (_, name, column_addr) in columns.iter() {
+                    path.push(name.clone());
+                    NP_Schema::_walk(parsed_schema, *column_addr, path, callback);
+                    path.pop();
+                }
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                for (idx, value_addr) in values.iter().enumerate() {
+                    path.push(idx.to_string());
+                    NP_Schema::_walk(parsed_schema, *value_addr, path, callback);
+                    path.pop();
+                }
+            },
+            NP_Parsed_Schema::List { of, .. } | NP_Parsed_Schema::Log { of, .. } => {
+                path.push(String::from("*"));
+                NP_Schema::_walk(parsed_schema, *of, path, callback);
+                path.pop();
+            },
+            NP_Parsed_Schema::Map { value, .. } => {
+                path.push(String::from("*"));
+                NP_Schema::_walk(parsed_schema, *value, path, callback);
+                path.pop();
+            },
+            _ => { }
+        }
+    }
+
     /// Recursive function parse schema into JSON
     #[doc(hidden)]
     pub fn _type_to_json(parsed_schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<NP_JSON, NP_Error> {
@@ -847,6 +1300,10 @@ impl NP_Schema {
             NP_Parsed_Schema::Map        { .. }      => {    NP_Map::schema_to_json(parsed_schema, address) }
             NP_Parsed_Schema::List       { .. }      => {   NP_List::schema_to_json(parsed_schema, address) }
             NP_Parsed_Schema::Tuple      { .. }      => {  NP_Tuple::schema_to_json(parsed_schema, address) }
+            NP_Parsed_Schema::Log        { .. }      => {    NP_Log::schema_to_json(parsed_schema, address) }
+            NP_Parsed_Schema::Tensor     { .. }      => { NP_Tensor::schema_to_json(parsed_schema, address) }
+            NP_Parsed_Schema::BigInt     { .. }      => { NP_BigInt::schema_to_json(parsed_schema, address) }
+            NP_Parsed_Schema::Custom     { .. }      => { NP_Custom::schema_to_json(parsed_schema, address) }
             _ => { Ok(NP_JSON::Null) }
         }
     }
@@ -859,7 +1316,7 @@ impl NP_Schema {
                 Ok(x.clone())
             },
             _ => {
-                Err(NP_Error::new("Schemas must have a 'type' property!"))
+                Err(NP_Error::new_schema("Schemas must have a 'type' property!"))
             }
         }
     }
@@ -893,6 +1350,157 @@ impl NP_Schema {
             NP_TypeKeys::Map =>        {    NP_Map::from_bytes_to_schema(cache, address, bytes) }
             NP_TypeKeys::List =>       {   NP_List::from_bytes_to_schema(cache, address, bytes) }
             NP_TypeKeys::Tuple =>      {  NP_Tuple::from_bytes_to_schema(cache, address, bytes) }
+            NP_TypeKeys::Log =>        {    NP_Log::from_bytes_to_schema(cache, address, bytes) }
+            NP_TypeKeys::Tensor =>     { NP_Tensor::from_bytes_to_schema(cache, address, bytes) }
+            NP_TypeKeys::BigInt =>     { NP_BigInt::from_bytes_to_schema(cache, address, bytes) }
+            NP_TypeKeys::Custom =>     { NP_Custom::from_bytes_to_schema(cache, address, bytes) }
+        }
+    }
+
+    /// Replace every `{"$use": "name"}` in a JSON schema with a copy of the matching type from the schema's root `definitions` object.
+    /// 
+    /// `NP_Factory::new` does this before parsing, only call this if you're parsing JSON schemas yourself with `from_json`.
+    pub fn resolve_definitions(json_schema: Box<NP_JSON>) -> Result<Box<NP_JSON>, NP_Error> {
+
+        let mut json_schema = json_schema;
+
+        let definitions = match &mut *json_schema {
+            NP_JSON::Dictionary(root) => {
+                match root.values.iter().position(|(key, _)| key == "definitions") {
+                    Some(index) => root.values.remove(index).1,
+                    None => NP_JSON::Null
+                }
+            },
+            _ => NP_JSON::Null
+        };
+
+        let definitions = match definitions {
+            NP_JSON::Dictionary(map) => map,
+            NP_JSON::Null => JSMAP::new(),
+            _ => return Err(NP_Error::new_schema("Schema definitions must be an object!"))
+        };
+
+        let mut using: Vec<&str> = Vec::new();
+        let mut nodes = 0usize;
+        Ok(Box::new(Self::resolve_uses(&json_schema, &definitions, &mut using, &mut nodes)?))
+    }
+
+    /// Replace every type from a plugin in a JSON schema with the form the schema parser reads plugin types in.
+    /// 
+    /// `NP_Factory::new_with_plugins` does this before parsing, only call this if you're parsing JSON schemas yourself with `from_json`.  Use `attach_plugins` on the parsed schema afterwards.
+    pub fn resolve_plugins(json_schema: Box<NP_JSON>, plugins: &[&'static dyn NP_Plugin]) -> Result<Box<NP_JSON>, NP_Error> {
+        Self::check_plugins(plugins)?;
+        if plugins.is_empty() {
+            return Ok(json_schema);
+        }
+        Ok(Box::new(Self::resolve_plugin_types(&json_schema, plugins)?))
+    }
+
+    fn resolve_plugin_types(json: &NP_JSON, plugins: &[&'static dyn NP_Plugin]) -> Result<NP_JSON, NP_Error> {
+        match json {
+            NP_JSON::Dictionary(map) => {
+                if let Some(NP_JSON::String(type_name)) = map.get("type") {
+                    if let Some(plugin) = plugins.iter().find(|plugin| plugin.type_name() == type_name) {
+                        let mut resolved = JSMAP::new();
+                        resolved.insert(String::from("type"), NP_JSON::String(String::from("$plugin")));
+                        resolved.insert(String::from("key"), NP_JSON::Integer(plugin.type_key() as i64));
+                        resolved.insert(String::from("name"), NP_JSON::String(type_name.clone()));
+                        let data = plugin.schema_from_json(json)?;
+                        resolved.insert(String::from("data"), NP_JSON::Array(data.iter().map(|x| NP_JSON::Integer(*x as i64)).collect()));
+                        return Ok(NP_JSON::Dictionary(resolved));
+                    }
+                }
+
+                let mut resolved = JSMAP::new();
+                for (key, value) in &map.values {
+                    resolved.values.push((key.clone(), Self::resolve_plugin_types(value, plugins)?));
+                }
+                Ok(NP_JSON::Dictionary(resolved))
+            },
+            NP_JSON::Array(list) => {
+                let mut resolved = Vec::with_capacity(list.len());
+                for value in list {
+                    resolved.push(Self::resolve_plugin_types(value, plugins)?);
+                }
+                Ok(NP_JSON::Array(resolved))
+            },
+            _ => Ok(json.clone())
+        }
+    }
+
+    // plugins need type keys from the plugin range, and can't share names or keys
+    fn check_plugins(plugins: &[&'static dyn NP_Plugin]) -> Result<(), NP_Error> {
+        for (index, plugin) in plugins.iter().enumerate() {
+            if !NP_PLUGIN_KEYS.contains(&plugin.type_key()) {
+                return Err(NP_Error::new_schema(alloc::format!("The {:?} plugin needs a type key from 64 to 127!", plugin.type_name())));
+            }
+            if plugins[..index].iter().any(|other| other.type_key() == plugin.type_key() || other.type_name() == plugin.type_name()) {
+                return Err(NP_Error::new_schema(alloc::format!("The {:?} plugin has the same name or type key as another plugin!", plugin.type_name())));
+            }
+        }
+        Ok(())
+    }
+
+    /// Give every plugin type in a parsed schema its plugin, plugins are matched by type key.
+    /// 
+    /// Fails if a plugin type in the schema has no plugin, or the plugin with its key has a different name.
+    pub fn attach_plugins(parsed_schema: &mut [NP_Parsed_Schema], plugins: &[&'static dyn NP_Plugin]) -> Result<(), NP_Error> {
+        Self::check_plugins(plugins)?;
+        for schema in parsed_schema.iter_mut() {
+            if let NP_Parsed_Schema::Custom { key, name, plugin, .. } = schema {
+                match plugins.iter().find(|found| found.type_key() == *key) {
+                    Some(found) if found.type_name() == name => { *plugin = Some(NP_Plugin_Ref(*found)); },
+                    _ => return Err(NP_Error::new_schema(alloc::format!("No plugin for the {:?} type!", name)))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // `nodes` counts the JSON values written so far, definitions that use each other several times can grow exponentially
+    fn resolve_uses<'defs>(json: &NP_JSON, definitions: &'defs JSMAP, using: &mut Vec<&'defs str>, nodes: &mut usize) -> Result<NP_JSON, NP_Error> {
+        if let NP_JSON::Dictionary(map) = json {
+            if let Some(name) = map.get("$use") {
+                let name = match name {
+                    NP_JSON::String(name) => name,
+                    _ => return Err(NP_Error::new_schema("\"$use\" must be the name of a definition!"))
+                };
+                let (def_name, definition) = match definitions.values.iter().find(|(key, _)| key == name) {
+                    Some((def_name, definition)) => (def_name.as_str(), definition),
+                    None => return Err(NP_Error::new_schema(alloc::format!("Can't find a definition named {:?}!", name)))
+                };
+                if let Some(start) = using.iter().position(|x| *x == def_name) {
+                    let chain: Vec<String> = using[start..].iter().chain(core::iter::once(&def_name)).map(|x| alloc::format!("{:?}", x)).collect();
+                    return Err(NP_Error::new_schema(alloc::format!("Definition {:?} uses itself ({}), recursive definitions aren't supported!", def_name, chain.join(" -> "))));
+                }
+                using.push(def_name);
+                let resolved = Self::resolve_uses(definition, definitions, using, nodes)?;
+                using.pop();
+                return Ok(resolved);
+            }
+        }
+
+        *nodes += 1;
+        if *nodes > MAX_SCHEMA_NODES {
+            return Err(NP_Error::new_schema(alloc::format!("Schema is too large with every \"$use\" copied into place, it can hold up to {} JSON values!", MAX_SCHEMA_NODES)));
+        }
+
+        match json {
+            NP_JSON::Dictionary(map) => {
+                let mut resolved = JSMAP::new();
+                for (key, value) in &map.values {
+                    resolved.values.push((key.clone(), Self::resolve_uses(value, definitions, using, nodes)?));
+                }
+                Ok(NP_JSON::Dictionary(resolved))
+            },
+            NP_JSON::Array(list) => {
+                let mut resolved = Vec::with_capacity(list.len());
+                for value in list {
+                    resolved.push(Self::resolve_uses(value, definitions, using, nodes)?);
+                }
+                Ok(NP_JSON::Array(resolved))
+            },
+            _ => Ok(json.clone())
         }
     }
 
@@ -948,18 +1556,276 @@ impl NP_Schema {
                     "option"   => {   NP_Enum::from_json_to_schema(schema, &json_schema) },
                     "table"    => {  NP_Table::from_json_to_schema(schema, &json_schema) },
                     "list"     => {   NP_List::from_json_to_schema(schema, &json_schema) },
+                    "sorted_list" | "priority_queue" => { NP_List::from_json_to_schema(schema, json_schema) },
                     "map"      => {    NP_Map::from_json_to_schema(schema, &json_schema) },
                     "tuple"    => {  NP_Tuple::from_json_to_schema(schema, &json_schema) },
+                    "log"      => {    NP_Log::from_json_to_schema(schema, &json_schema) },
+                    "ring"     => {    NP_Log::from_json_to_schema(schema, json_schema) },
+                    "tensor"   => { NP_Tensor::from_json_to_schema(schema, &json_schema) },
+                    "bigint"   => { NP_BigInt::from_json_to_schema(schema, &json_schema) },
+                    "$plugin"  => { NP_Custom::from_json_to_schema(schema, json_schema) },
                     _ => {
                         let mut err_msg = String::from("Can't find a type that matches this schema! ");
                         err_msg.push_str(json_schema.stringify().as_str());
-                        return Err(NP_Error::new(err_msg.as_str()))
+                        return Err(NP_Error::new_schema(err_msg.as_str()))
                     }
                 }
             },
             _ => {
-                Err(NP_Error::new("Schemas must have a 'type' property!"))
+                Err(NP_Error::new_schema("Schemas must have a 'type' property!"))
             }
         }
     }
 }
+
+
+/// Read only view of a single type in a schema.
+/// 
+/// Schema nodes are the stable way to inspect a parsed schema, they don't change when the internal schema representation does.  Get the root node with `factory.schema.root()` or visit every node with `factory.schema.walk(..)`.
+/// 
+/// ```
+/// use no_proto::error::NP_Error;
+/// use no_proto::NP_Factory;
+/// use no_proto::schema::NP_TypeKeys;
+/// use no_proto::json_flex::NP_JSON;
+/// 
+/// let factory: NP_Factory = NP_Factory::new(r#"{
+///    "type": "table",
+///    "columns": [
+///         ["code",  {"type": "string", "size": 3, "default": "abc"}],
+///         ["color", {"type": "option", "choices": ["red", "blue"]}]
+///     ]
+/// }"#)?;
+/// 
+/// let root = factory.schema.root();
+/// assert_eq!(root.kind(), NP_TypeKeys::Table);
+/// 
+/// let children = root.children();
+/// assert_eq!(children[0].0, "code");
+/// assert_eq!(children[0].1.type_name(), "string");
+/// assert_eq!(children[0].1.fixed_size(), Some(3));
+/// assert_eq!(children[0].1.default()?.unwrap().stringify(), "\"abc\"");
+/// 
+/// let color = root.child("color").unwrap();
+/// assert_eq!(color.choices(), Some(vec!["red", "blue"]));
+/// 
+/// # Ok::<(), NP_Error>(()) 
+/// ```
+/// 
+#[derive(Debug, Clone, Copy)]
+pub struct NP_SchemaNode<'schema> {
+    schema: &'schema Vec<NP_Parsed_Schema>,
+    address: usize
+}
+
+impl<'schema> NP_SchemaNode<'schema> {
+
+    pub(crate) fn new(schema: &'schema Vec<NP_Parsed_Schema>, address: usize) -> Self {
+        NP_SchemaNode { schema, address }
+    }
+
+    /// The type of this node
+    pub fn kind(&self) -> NP_TypeKeys {
+        *self.schema[self.address].get_type_key()
+    }
+
+    /// The type name used in JSON schemas, like `string` or `table`
+    pub fn type_name(&self) -> &'schema str {
+        self.schema[self.address].get_type_data().0
+    }
+
+    /// Is this type stored in a bytewise sortable format?
+    pub fn is_sortable(&self) -> bool {
+        self.schema[self.address].is_sortable()
+    }
+
+    /// Child types of collections, named the same way as in `NP_Schema::walk`.  Scalar types have no children.
+    pub fn children(&self) -> Vec<(String, NP_SchemaNode<'schema>)> {
+        let node = |address: usize| NP_SchemaNode { schema: self.schema, address };
+        match &self.schema[self.address] {
+            NP_Parsed_Schema::Table { columns, .. } => columns.iter().map(|(_, name, addr)| (name.clone(), node(*addr))).collect(),
+            NP_Parsed_Schema::Tuple { values, .. } => values.iter().enumerate().map(|(idx, addr)| (idx.to_string(), node(*addr))).collect(),
+            NP_Parsed_Schema::List { of, .. } | NP_Parsed_Schema::Log { of, .. } => [(String::from("*"), node(*of))].to_vec(),
+            NP_Parsed_Schema::Map { value, .. } => [(String::from("*"), node(*value))].to_vec(),
+            _ => Vec::new()
+        }
+    }
+
+    /// Get a single child type by name
+    pub fn child(&self, name: &str) -> Option<NP_SchemaNode<'schema>> {
+        self.children().into_iter().find(|(child_name, _)| child_name == name).map(|(_, node)| node)
+    }
+
+    /// Number of bytes every value of this type takes up, if that's fixed
+    pub fn fixed_size(&self) -> Option<usize> {
+        match &self.schema[self.address] {
+            NP_Parsed_Schema::UTF8String { size, .. } => if *size > 0 { Some(*size as usize) } else { None },
+            NP_Parsed_Schema::Bytes      { size, .. } => if *size > 0 { Some(*size as usize) } else { None },
+            NP_Parsed_Schema::Int8       { .. } => Some(1),
+            NP_Parsed_Schema::Int16      { .. } => Some(2),
+            NP_Parsed_Schema::Int32      { .. } => Some(4),
+            NP_Parsed_Schema::Int64      { .. } => Some(8),
+            NP_Parsed_Schema::Uint8      { .. } => Some(1),
+            NP_Parsed_Schema::Uint16     { .. } => Some(2),
+            NP_Parsed_Schema::Uint32     { .. } => Some(4),
+            NP_Parsed_Schema::Uint64     { .. } => Some(8),
+            NP_Parsed_Schema::Float      { .. } => Some(4),
+            NP_Parsed_Schema::Double     { .. } => Some(8),
+            NP_Parsed_Schema::Decimal    { .. } => Some(8),
+            NP_Parsed_Schema::Boolean    { .. } => Some(1),
+            NP_Parsed_Schema::Geo        { size, .. } => Some(*size as usize),
+            NP_Parsed_Schema::Date       { .. } => Some(8),
+            NP_Parsed_Schema::Enum       { wide, .. } => Some(if *wide { 2 } else { 1 }),
+            NP_Parsed_Schema::Uuid       { .. } => Some(16),
+            NP_Parsed_Schema::Ulid       { .. } => Some(16),
+            NP_Parsed_Schema::Tensor     { dtype, shape, .. } => Some(shape.iter().product::<usize>() * tensor::dtype_size(dtype)),
+            _ => None
+        }
+    }
+
+    /// Number of decimal places for decimal types
+    pub fn exp(&self) -> Option<u8> {
+        match &self.schema[self.address] {
+            NP_Parsed_Schema::Decimal { exp, .. } => Some(*exp),
+            _ => None
+        }
+    }
+
+    /// The available choices for option types
+    pub fn choices(&self) -> Option<Vec<&'schema str>> {
+        match &self.schema[self.address] {
+            NP_Parsed_Schema::Enum { choices, .. } => Some(choices.iter().map(|choice| match choice {
+                NP_Enum::Some(value) => value.as_str(),
+                NP_Enum::None => ""
+            }).collect()),
+            _ => None
+        }
+    }
+
+    /// The number of each choice for option types, in the same order as `choices`
+    pub fn choice_values(&self) -> Option<Vec<i64>> {
+        match &self.schema[self.address] {
+            NP_Parsed_Schema::Enum { values, .. } => Some(values.clone()),
+            _ => None
+        }
+    }
+
+    /// The default value for this type as JSON, if the schema has one
+    pub fn default(&self) -> Result<Option<NP_JSON>, NP_Error> {
+        match &self.to_json()?["default"] {
+            NP_JSON::Null => Ok(None),
+            value => Ok(Some(value.clone()))
+        }
+    }
+
+    /// JSON schema for this type and all of its children
+    pub fn to_json(&self) -> Result<NP_JSON, NP_Error> {
+        NP_Schema::_type_to_json(self.schema, self.address)
+    }
+}
+
+
+#[test]
+fn walk_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["id",    {"type": "uuid"}],
+        ["meta",  {"type": "map", "value": {"type": "u32"}}],
+        ["point", {"type": "tuple", "values": [{"type": "f32"}, {"type": "f32"}]}]
+    ]}"#)?;
+
+    let mut found: Vec<(String, NP_TypeKeys)> = Vec::new();
+    factory.schema.walk(|path, schema| {
+        found.push((path.join("."), schema.kind()));
+    });
+
+    assert_eq!(found, [
+        (String::from(""), NP_TypeKeys::Table),
+        (String::from("id"), NP_TypeKeys::Uuid),
+        (String::from("meta"), NP_TypeKeys::Map),
+        (String::from("meta.*"), NP_TypeKeys::Uint32),
+        (String::from("point"), NP_TypeKeys::Tuple),
+        (String::from("point.0"), NP_TypeKeys::Float),
+        (String::from("point.1"), NP_TypeKeys::Float)
+    ].to_vec());
+
+    Ok(())
+}
+
+#[test]
+fn schema_node_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["price", {"type": "decimal", "exp": 2, "default": 10.25}],
+        ["tags",  {"type": "list", "of": {"type": "string"}}],
+        ["key",   {"type": "tuple", "sorted": true, "values": [{"type": "u16"}, {"type": "string", "size": 4}]}]
+    ]}"#)?;
+
+    let root = factory.schema.root();
+    assert_eq!(root.children().len(), 3);
+    assert_eq!(root.fixed_size(), None);
+    assert_eq!(root.child("nope").is_none(), true);
+
+    let price = root.child("price").unwrap();
+    assert_eq!(price.kind(), NP_TypeKeys::Decimal);
+    assert_eq!(price.exp(), Some(2));
+    assert_eq!(price.default()?.unwrap().stringify(), "10.25");
+
+    let tags = root.child("tags").unwrap();
+    assert!(tags.default()?.is_none());
+    assert_eq!(tags.child("*").unwrap().kind(), NP_TypeKeys::UTF8String);
+
+    let key = root.child("key").unwrap();
+    assert_eq!(key.is_sortable(), true);
+    assert_eq!(key.child("1").unwrap().fixed_size(), Some(4));
+    assert_eq!(key.to_json()?.stringify(), factory.schema.to_json()?["columns"][2][1].clone().stringify());
+
+    Ok(())
+}
+
+#[test]
+fn definitions_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{
+        "definitions": {
+            "money": {"type": "decimal", "exp": 2},
+            "line": {"type": "table", "columns": [["sku", {"type": "string"}], ["price", {"$use": "money"}]]}
+        },
+        "roots": {
+            "order": {"type": "table", "columns": [["lines", {"type": "list", "of": {"$use": "line"}}], ["total", {"$use": "money"}]]},
+            "refund": {"type": "table", "columns": [["line", {"$use": "line"}]]}
+        }
+    }"#)?;
+
+    let copied = crate::NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [["line", {"type": "table", "columns": [["sku", {"type": "string"}], ["price", {"type": "decimal", "exp": 2}]]}]]
+    }"#)?;
+
+    let refund = NP_Error::unwrap(factory.factory_of("refund"))?;
+    assert_eq!(refund.compile_schema(), copied.compile_schema());
+
+    assert!(crate::NP_Factory::new(r#"{"type": "list", "of": {"$use": "missing"}}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{
+        "definitions": {"a": {"type": "list", "of": {"$use": "b"}}, "b": {"type": "map", "value": {"$use": "a"}}},
+        "$use": "a"
+    }"#).is_err());
+    match crate::NP_Factory::new(r#"{
+        "definitions": {"a": {"type": "list", "of": {"$use": "b"}}, "b": {"type": "map", "value": {"$use": "a"}}},
+        "type": "table", "columns": [["x", {"$use": "b"}]]
+    }"#) {
+        Err(e) => assert_eq!(e.to_string(), "Definition \"b\" uses itself (\"b\" -> \"a\" -> \"b\"), recursive definitions aren't supported!"),
+        Ok(_) => panic!()
+    }
+
+    // each level uses the one below it 8 times, so the expanded schema would hold billions of values
+    let mut definitions = alloc::vec![String::from(r#""d0": {"type": "u8"}"#)];
+    for level in 1..12 {
+        let columns: Vec<String> = (0..8).map(|x| alloc::format!(r#"["c{}", {{"$use": "d{}"}}]"#, x, level - 1)).collect();
+        definitions.push(alloc::format!(r#""d{}": {{"type": "table", "columns": [{}]}}"#, level, columns.join(",")));
+    }
+    let schema = alloc::format!(r#"{{"definitions": {{{}}}, "$use": "d11"}}"#, definitions.join(","));
+    match crate::NP_Factory::new(&schema) {
+        Err(e) => assert!(e.to_string().contains("too large")),
+        Ok(_) => panic!()
+    }
+
+    Ok(())
+}
@@ -0,0 +1,962 @@
+//! Generate code from schemas
+//!
+//! `to_sql` writes `CREATE TABLE` statements for a schema with a table root, so data from NoProto buffers can be copied into a SQL database and queried there.  Postgres, SQLite and MySQL are supported.
+//!
+//! Scalar columns use the closest SQL type:
+//!
+//! | NoProto                    | Postgres                  | SQLite    | MySQL                     |
+//! |----------------------------|---------------------------|-----------|---------------------------|
+//! | string                     | `TEXT`, `VARCHAR(size)`   | `TEXT`    | `TEXT`, `VARCHAR(size)`   |
+//! | bytes                      | `BYTEA`                   | `BLOB`    | `BLOB`, `BINARY(size)`    |
+//! | i8, i16, u8                | `SMALLINT`                | `INTEGER` | `TINYINT`, `SMALLINT`, `TINYINT UNSIGNED` |
+//! | i32, u16                   | `INTEGER`                 | `INTEGER` | `INT`, `SMALLINT UNSIGNED` |
+//! | i64, u32                   | `BIGINT`                  | `INTEGER` | `BIGINT`, `INT UNSIGNED`  |
+//! | u64                        | `NUMERIC(20, 0)`          | `NUMERIC` | `BIGINT UNSIGNED`         |
+//! | float, double              | `REAL`, `DOUBLE PRECISION`| `REAL`    | `FLOAT`, `DOUBLE`         |
+//! | decimal                    | `NUMERIC(19, exp)`        | `NUMERIC` | `DECIMAL(19, exp)`        |
+//! | bool                       | `BOOLEAN`                 | `INTEGER` | `BOOLEAN`                 |
+//! | date                       | `TIMESTAMPTZ`             | `INTEGER` | `DATETIME(3)`             |
+//! | option                     | `TEXT` with a `CHECK`     | `TEXT` with a `CHECK` | `ENUM`        |
+//! | uuid, ulid                 | `UUID`, `CHAR(26)`        | `TEXT`    | `CHAR(36)`, `CHAR(26)`    |
+//! | bigint                     | `NUMERIC`                 | `TEXT`    | `DECIMAL(65, 0)`          |
+//! | geo, tensor, any           | `JSONB`                   | `TEXT`    | `JSON`                    |
+//!
+//! Required table columns are `NOT NULL`.  Nested collections (tables, tuples, lists, logs and maps) are written one of two ways, picked with `NP_Sql_Options::nested`:
+//!
+//! - `NP_Sql_Nested::Json` stores each collection in a JSON column, in the same format `json_encode` produces.
+//! - `NP_Sql_Nested::ChildTables` stores each collection in its own table named `<parent>_<column>`.  Every table gets a `key_column` primary key and child rows reference their parent row with a `<parent>_<key_column>` column.  List and log rows are numbered by an `idx` column and map rows by a `key` column.  Items that aren't tables or tuples are stored in a `value` column.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::schema::codegen::{NP_Sql_Dialect, NP_Sql_Nested, NP_Sql_Options};
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string", "nullable": false}],
+//!         ["age",  {"type": "u8"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! let options = NP_Sql_Options { table_name: String::from("users"), ..Default::default() };
+//! assert_eq!(factory.schema.to_sql(NP_Sql_Dialect::Postgres, &options)?, r#"CREATE TABLE "users" (
+//!     "name" TEXT NOT NULL,
+//!     "age" SMALLINT,
+//!     "tags" JSONB
+//! );
+//! "#);
+//!
+//! let options = NP_Sql_Options { table_name: String::from("users"), nested: NP_Sql_Nested::ChildTables, ..Default::default() };
+//! assert_eq!(factory.schema.to_sql(NP_Sql_Dialect::Postgres, &options)?, r#"CREATE TABLE "users" (
+//!     "id" BIGINT PRIMARY KEY,
+//!     "name" TEXT NOT NULL,
+//!     "age" SMALLINT
+//! );
+//!
+//! CREATE TABLE "users_tags" (
+//!     "id" BIGINT PRIMARY KEY,
+//!     "users_id" BIGINT NOT NULL REFERENCES "users" ("id"),
+//!     "idx" INTEGER NOT NULL,
+//!     "value" TEXT,
+//!     UNIQUE ("users_id", "idx")
+//! );
+//! "#);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! `to_graphql` writes GraphQL type definitions for a schema with a table root, they describe the same JSON `json_encode` produces.
+//!
+//! - Tables become object types.  The root type gets the name passed to `to_graphql`, nested tables are named after the path to them, so the `address` column of `User` is a `UserAddress`.
+//! - Options become enums named the same way, their choices must be valid GraphQL names.
+//! - Lists and logs become lists, items can be `null` since lists can have gaps.  Tensors are lists of their number type and bytes are `[Int!]`.
+//! - Numbers that fit in 32 bits are `Int`, larger integers and dates are a `Long` scalar.  Decimals and geo values use `Decimal` and `Geo` types with the same fields as their JSON.
+//! - Maps, tuples and `any` values have no GraphQL equivalent, they use a `JSON` scalar.
+//!
+//! Required table columns are non null.  Scalars and shared types are only declared if they're used.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",    {"type": "string", "nullable": false}],
+//!         ["joined",  {"type": "date"}],
+//!         ["color",   {"type": "option", "choices": ["red", "blue"]}],
+//!         ["friends", {"type": "list", "of": {"type": "table", "columns": [["name", {"type": "string"}]]}}]
+//!     ]
+//! }"#)?;
+//!
+//! assert_eq!(factory.schema.to_graphql("User")?, r#"type User {
+//!   name: String!
+//!   joined: Long
+//!   color: UserColor
+//!   friends: [UserFriends]
+//! }
+//!
+//! enum UserColor {
+//!   red
+//!   blue
+//! }
+//!
+//! type UserFriends {
+//!   name: String
+//! }
+//!
+//! scalar Long
+//! "#);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! `to_rust` writes Rust source with a typed accessor struct for every table in a schema, so field names are checked by the compiler without a proc macro.  Run it from a `build.rs` script and include the output in its own module:
+//!
+//! ```text
+//! // build.rs
+//! let factory = no_proto::NP_Factory::new(&std::fs::read_to_string("user.json")?)?;
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR")?).join("user.rs");
+//! std::fs::write(out, factory.schema.to_rust("User")?)?;
+//!
+//! // src/lib.rs
+//! mod user { include!(concat!(env!("OUT_DIR"), "/user.rs")); }
+//! ```
+//!
+//! Each struct wraps anything that borrows an `NP_Buffer`, an `&NP_Buffer` to read or an `&mut NP_Buffer` (or the buffer itself) to read and write.  `User::new(&mut buffer)` points at the root of the buffer.
+//!
+//! - Scalar columns get a getter named after the column along with `set_` and `del_` methods, using the same Rust types as `NP_Buffer::get` and `NP_Buffer::set`.
+//! - Table and tuple columns return the accessor struct for the nested type, `<column>_mut` returns one that can write.  Nested types are named after the path to them like `UserAddress`, tuple values are named `value_0`, `value_1` and so on.
+//! - List, log and map columns take an index or key, and get a `<column>_len` method.  Lists of scalars get `push_<column>` and logs `append_<column>`.
+//! - Collections directly inside other collections don't get accessors, use buffer paths for those.
+//!
+//! Column names that aren't Rust identifiers have other characters replaced by `_`, keywords get a trailing `_`.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! let code = factory.schema.to_rust("User")?;
+//! assert!(code.contains("pub struct User<'factory, B>"));
+//! assert!(code.contains("pub fn set_name(&mut self, value: &'factory str)"));
+//! assert!(code.contains("pub fn tags(&self, index: usize)"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::schema::{NP_Parsed_Schema, NP_Schema, NP_TypeKeys};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::format;
+
+/// SQL dialects supported by `to_sql`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NP_Sql_Dialect {
+    /// PostgreSQL
+    Postgres,
+    /// SQLite
+    Sqlite,
+    /// MySQL and MariaDB
+    MySql
+}
+
+/// How `to_sql` stores collections nested inside the root table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NP_Sql_Nested {
+    /// Store each nested collection in a JSON column
+    Json,
+    /// Store each nested collection in its own table that references its parent
+    ChildTables
+}
+
+/// Options for `to_sql`
+#[derive(Debug, Clone)]
+pub struct NP_Sql_Options {
+    /// Name of the table for the root of the schema, child tables are named after it
+    pub table_name: String,
+    /// How nested collections are stored
+    pub nested: NP_Sql_Nested,
+    /// Name of the primary key column added to every table when nested collections are stored in child tables
+    pub key_column: String
+}
+
+impl Default for NP_Sql_Options {
+    fn default() -> Self {
+        NP_Sql_Options { table_name: String::from("root"), nested: NP_Sql_Nested::Json, key_column: String::from("id") }
+    }
+}
+
+/// Generate `CREATE TABLE` statements for a schema, the root of the schema must be a table.
+///
+/// Statements are separated by a blank line, parent tables always come before their children.  See the [module docs](index.html) for an example.
+pub fn to_sql(schema: &NP_Schema, dialect: NP_Sql_Dialect, options: &NP_Sql_Options) -> Result<String, NP_Error> {
+    match schema.parsed.first() {
+        Some(NP_Parsed_Schema::Table { .. }) => { },
+        _ => return Err(NP_Error::new_static("SQL tables need a table root!"))
+    }
+
+    let mut generator = Generator { schema: &schema.parsed, dialect, options, names: Vec::new(), statements: Vec::new() };
+    generator.table(&options.table_name, None, 0)?;
+    Ok(generator.statements.join("\n"))
+}
+
+// how a child table is related to its parent
+#[derive(Clone, Copy)]
+enum Relation {
+    One,
+    Indexed,
+    Keyed
+}
+
+struct Generator<'gen> {
+    schema: &'gen [NP_Parsed_Schema],
+    dialect: NP_Sql_Dialect,
+    options: &'gen NP_Sql_Options,
+    names: Vec<String>,
+    statements: Vec<String>
+}
+
+impl<'gen> Generator<'gen> {
+
+    fn ident(&self, name: &str) -> String {
+        match self.dialect {
+            NP_Sql_Dialect::MySql => format!("`{}`", name.replace('`', "``")),
+            _ => format!("\"{}\"", name.replace('"', "\"\""))
+        }
+    }
+
+    fn json_type(&self) -> &'static str {
+        match self.dialect {
+            NP_Sql_Dialect::Postgres => "JSONB",
+            NP_Sql_Dialect::Sqlite => "TEXT",
+            NP_Sql_Dialect::MySql => "JSON"
+        }
+    }
+
+    fn key_type(&self) -> &'static str {
+        match self.dialect {
+            NP_Sql_Dialect::Sqlite => "INTEGER",
+            _ => "BIGINT"
+        }
+    }
+
+    // write the statement for one table, then the statements for its child tables
+    fn table(&mut self, name: &str, parent: Option<(&str, Relation)>, address: usize) -> Result<(), NP_Error> {
+        if self.names.iter().any(|existing| existing == name) {
+            return Err(NP_Error::new(String::from("Duplicate SQL table name: ") + name));
+        }
+        self.names.push(name.to_string());
+
+        let child_tables = self.options.nested == NP_Sql_Nested::ChildTables;
+        let key = self.options.key_column.clone();
+
+        let mut columns: Vec<(String, String)> = Vec::new();
+        let mut constraints: Vec<String> = Vec::new();
+        let mut children: Vec<(String, Relation, usize)> = Vec::new();
+
+        if child_tables {
+            columns.push((key.clone(), format!("{} PRIMARY KEY", self.key_type())));
+        }
+
+        if let Some((parent_name, relation)) = parent {
+            let parent_key = format!("{}_{}", parent_name, key);
+            columns.push((parent_key.clone(), format!("{} NOT NULL REFERENCES {} ({})", self.key_type(), self.ident(parent_name), self.ident(&key))));
+            let position = match relation {
+                Relation::One => None,
+                Relation::Indexed => Some(("idx", String::from("INTEGER NOT NULL"))),
+                Relation::Keyed => Some(("key", String::from(match self.dialect {
+                    NP_Sql_Dialect::MySql => "VARCHAR(255) NOT NULL",
+                    _ => "TEXT NOT NULL"
+                })))
+            };
+            match position {
+                Some((column, sql_type)) => {
+                    constraints.push(format!("UNIQUE ({}, {})", self.ident(&parent_key), self.ident(column)));
+                    columns.push((column.to_string(), sql_type));
+                },
+                None => constraints.push(format!("UNIQUE ({})", self.ident(&parent_key)))
+            }
+        }
+
+        // the columns of a table or tuple, or a single value column for anything else
+        let values: Vec<(String, usize, bool)> = match &self.schema[address] {
+            NP_Parsed_Schema::Table { columns, required, .. } => columns.iter().map(|(idx, column, addr)| {
+                (column.clone(), *addr, required.get(*idx as usize) == Some(&true))
+            }).collect(),
+            NP_Parsed_Schema::Tuple { values, .. } => values.iter().enumerate().map(|(idx, addr)| (idx.to_string(), *addr, false)).collect(),
+            _ => alloc::vec![(String::from("value"), address, false)]
+        };
+
+        for (column, addr, required) in values {
+            let relation = match &self.schema[addr] {
+                NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::Tuple { .. } => Some((Relation::One, addr)),
+                NP_Parsed_Schema::List { of, .. } | NP_Parsed_Schema::Log { of, .. } => Some((Relation::Indexed, *of)),
+                NP_Parsed_Schema::Map { value, .. } => Some((Relation::Keyed, *value)),
+                _ => None
+            };
+
+            match relation {
+                Some((relation, child_addr)) if child_tables => {
+                    children.push((format!("{}_{}", name, column), relation, child_addr));
+                },
+                _ => {
+                    if let Some(sql_type) = self.sql_type(&column, addr) {
+                        columns.push((column, if required { sql_type + " NOT NULL" } else { sql_type }));
+                    }
+                }
+            }
+        }
+
+        for (idx, (column, _)) in columns.iter().enumerate() {
+            if columns[..idx].iter().any(|(other, _)| other == column) {
+                return Err(NP_Error::new(format!("Duplicate SQL column name {} in table {}", column, name)));
+            }
+        }
+
+        let mut lines: Vec<String> = columns.iter().map(|(column, sql_type)| format!("    {} {}", self.ident(column), sql_type)).collect();
+        lines.extend(constraints.into_iter().map(|constraint| format!("    {}", constraint)));
+        self.statements.push(format!("CREATE TABLE {} (\n{}\n);\n", self.ident(name), lines.join(",\n")));
+
+        for (child_name, relation, child_addr) in children {
+            self.table(&child_name, Some((name, relation)), child_addr)?;
+        }
+
+        Ok(())
+    }
+
+    // SQL type for a column, `None` for columns that can't hold anything
+    fn sql_type(&self, column: &str, address: usize) -> Option<String> {
+        use NP_Sql_Dialect::*;
+
+        let dialect = self.dialect;
+        let pick = |postgres: &str, sqlite: &str, mysql: &str| -> Option<String> {
+            Some(String::from(match dialect { Postgres => postgres, Sqlite => sqlite, MySql => mysql }))
+        };
+
+        match &self.schema[address] {
+            NP_Parsed_Schema::None => None,
+            NP_Parsed_Schema::UTF8String { size, .. } if *size > 0 && dialect != Sqlite => Some(format!("VARCHAR({})", size)),
+            NP_Parsed_Schema::UTF8String { .. } => pick("TEXT", "TEXT", "TEXT"),
+            NP_Parsed_Schema::Bytes { size, .. } if *size > 0 && dialect == MySql => Some(format!("BINARY({})", size)),
+            NP_Parsed_Schema::Bytes { .. }      => pick("BYTEA", "BLOB", "BLOB"),
+            NP_Parsed_Schema::Int8 { .. }       => pick("SMALLINT", "INTEGER", "TINYINT"),
+            NP_Parsed_Schema::Int16 { .. }      => pick("SMALLINT", "INTEGER", "SMALLINT"),
+            NP_Parsed_Schema::Int32 { .. }      => pick("INTEGER", "INTEGER", "INT"),
+            NP_Parsed_Schema::Int64 { .. }      => pick("BIGINT", "INTEGER", "BIGINT"),
+            NP_Parsed_Schema::Uint8 { .. }      => pick("SMALLINT", "INTEGER", "TINYINT UNSIGNED"),
+            NP_Parsed_Schema::Uint16 { .. }     => pick("INTEGER", "INTEGER", "SMALLINT UNSIGNED"),
+            NP_Parsed_Schema::Uint32 { .. }     => pick("BIGINT", "INTEGER", "INT UNSIGNED"),
+            // SQLite integers are signed 64 bit
+            NP_Parsed_Schema::Uint64 { .. }     => pick("NUMERIC(20, 0)", "NUMERIC", "BIGINT UNSIGNED"),
+            NP_Parsed_Schema::Float { .. }      => pick("REAL", "REAL", "FLOAT"),
+            NP_Parsed_Schema::Double { .. }     => pick("DOUBLE PRECISION", "REAL", "DOUBLE"),
+            // i64 values have up to 19 digits
+            NP_Parsed_Schema::Decimal { exp, .. } => match dialect {
+                Postgres => Some(format!("NUMERIC({}, {})", core::cmp::max(19, *exp), exp)),
+                Sqlite => Some(String::from("NUMERIC")),
+                MySql => Some(format!("DECIMAL({}, {})", core::cmp::max(19, *exp), exp))
+            },
+            NP_Parsed_Schema::Boolean { .. }    => pick("BOOLEAN", "INTEGER", "BOOLEAN"),
+            NP_Parsed_Schema::Date { .. }       => pick("TIMESTAMPTZ", "INTEGER", "DATETIME(3)"),
+            NP_Parsed_Schema::Enum { choices, .. } => {
+                let choices: Vec<String> = choices.iter().map(|choice| format!("'{}'", choice.to_string().replace('\'', "''"))).collect();
+                match dialect {
+                    MySql => Some(format!("ENUM({})", choices.join(", "))),
+                    _ => Some(format!("TEXT CHECK ({} IN ({}))", self.ident(column), choices.join(", ")))
+                }
+            },
+            NP_Parsed_Schema::Uuid { .. }       => pick("UUID", "TEXT", "CHAR(36)"),
+            NP_Parsed_Schema::Ulid { .. }       => pick("CHAR(26)", "TEXT", "CHAR(26)"),
+            NP_Parsed_Schema::BigInt { .. }     => pick("NUMERIC", "TEXT", "DECIMAL(65, 0)"),
+            _ => Some(String::from(self.json_type()))
+        }
+    }
+}
+
+/// Generate GraphQL type definitions for a schema, the root of the schema must be a table.
+///
+/// `type_name` is the name of the root type, nested types are named after it.  See the [module docs](index.html) for an example.
+pub fn to_graphql(schema: &NP_Schema, type_name: &str) -> Result<String, NP_Error> {
+    match schema.parsed.first() {
+        Some(NP_Parsed_Schema::Table { .. }) => { },
+        _ => return Err(NP_Error::new_static("GraphQL types need a table root!"))
+    }
+
+    let mut generator = Sdl { schema: &schema.parsed, names: Vec::new(), definitions: Vec::new(), long: false, json: false, decimal: false, geo: false };
+    generator.object(type_name, 0)?;
+
+    if generator.decimal {
+        generator.define("Decimal", String::from("type Decimal {\n  num: Long!\n  exp: Int!\n}\n"))?;
+    }
+    if generator.geo {
+        generator.define("Geo", String::from("type Geo {\n  lat: Float!\n  lng: Float!\n}\n"))?;
+    }
+    if generator.long {
+        generator.define("Long", String::from("scalar Long\n"))?;
+    }
+    if generator.json {
+        generator.define("JSON", String::from("scalar JSON\n"))?;
+    }
+
+    Ok(generator.definitions.join("\n"))
+}
+
+// GraphQL names can't start with a digit and only use ASCII letters, digits and underscores
+fn graphql_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first == '_' || first.is_ascii_alphabetic() => chars.all(|c| c == '_' || c.is_ascii_alphanumeric()),
+        _ => false
+    }
+}
+
+// upper case the first letter of a column name for the types named after it
+fn pascal_case(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new()
+    }
+}
+
+struct Sdl<'gen> {
+    schema: &'gen [NP_Parsed_Schema],
+    names: Vec<String>,
+    definitions: Vec<String>,
+    long: bool,
+    json: bool,
+    decimal: bool,
+    geo: bool
+}
+
+impl<'gen> Sdl<'gen> {
+
+    // reserve a type name, returns where its definition goes so parents come before their children
+    fn reserve(&mut self, name: &str) -> Result<usize, NP_Error> {
+        if !graphql_name(name) {
+            return Err(NP_Error::new(String::from("Not a valid GraphQL name: ") + name));
+        }
+        if self.names.iter().any(|existing| existing == name) {
+            return Err(NP_Error::new(String::from("Duplicate GraphQL type name: ") + name));
+        }
+        self.names.push(name.to_string());
+        self.definitions.push(String::new());
+        Ok(self.definitions.len() - 1)
+    }
+
+    fn define(&mut self, name: &str, definition: String) -> Result<(), NP_Error> {
+        let slot = self.reserve(name)?;
+        self.definitions[slot] = definition;
+        Ok(())
+    }
+
+    fn object(&mut self, name: &str, address: usize) -> Result<(), NP_Error> {
+        let slot = self.reserve(name)?;
+
+        let mut fields: Vec<String> = Vec::new();
+        if let NP_Parsed_Schema::Table { columns, required, .. } = &self.schema[address] {
+            for (idx, column, addr) in columns.iter() {
+                if !graphql_name(column) {
+                    return Err(NP_Error::new(format!("Column {} of {} isn't a valid GraphQL name", column, name)));
+                }
+                if let Some(field_type) = self.field_type(&(String::from(name) + &pascal_case(column)), *addr)? {
+                    let non_null = if required.get(*idx as usize) == Some(&true) { "!" } else { "" };
+                    fields.push(format!("  {}: {}{}\n", column, field_type, non_null));
+                }
+            }
+        }
+
+        self.definitions[slot] = format!("type {} {{\n{}}}\n", name, fields.concat());
+        Ok(())
+    }
+
+    // GraphQL type for a value, `None` for values that can't hold anything
+    fn field_type(&mut self, name: &str, address: usize) -> Result<Option<String>, NP_Error> {
+        Ok(Some(match &self.schema[address] {
+            NP_Parsed_Schema::None => return Ok(None),
+            NP_Parsed_Schema::UTF8String { .. } | NP_Parsed_Schema::Uuid { .. } |
+            NP_Parsed_Schema::Ulid { .. } | NP_Parsed_Schema::BigInt { .. } => String::from("String"),
+            NP_Parsed_Schema::Bytes { .. } => String::from("[Int!]"),
+            NP_Parsed_Schema::Boolean { .. } => String::from("Boolean"),
+            NP_Parsed_Schema::Decimal { .. } => {
+                self.decimal = true;
+                self.long = true;
+                String::from("Decimal")
+            },
+            NP_Parsed_Schema::Geo { .. } => {
+                self.geo = true;
+                String::from("Geo")
+            },
+            NP_Parsed_Schema::Enum { choices, .. } => {
+                let slot = self.reserve(name)?;
+                let mut values = String::new();
+                for choice in choices.iter() {
+                    let choice = choice.to_string();
+                    // true, false and null are reserved
+                    if !graphql_name(&choice) || choice == "true" || choice == "false" || choice == "null" {
+                        return Err(NP_Error::new(format!("Option choice {} of {} isn't a valid GraphQL enum value", choice, name)));
+                    }
+                    values.push_str(&format!("  {}\n", choice));
+                }
+                self.definitions[slot] = format!("enum {} {{\n{}}}\n", name, values);
+                String::from(name)
+            },
+            NP_Parsed_Schema::Table { .. } => {
+                self.object(name, address)?;
+                String::from(name)
+            },
+            NP_Parsed_Schema::List { of, .. } | NP_Parsed_Schema::Log { of, .. } => match self.field_type(name, *of)? {
+                Some(item) => format!("[{}]", item),
+                None => return Ok(None)
+            },
+            NP_Parsed_Schema::Tensor { dtype, .. } => format!("[{}!]", self.number(dtype)),
+            NP_Parsed_Schema::Map { .. } | NP_Parsed_Schema::Tuple { .. } | NP_Parsed_Schema::Any { .. } => {
+                self.json = true;
+                String::from("JSON")
+            },
+            other => self.number(other.get_type_key())
+        }))
+    }
+
+    fn number(&mut self, key: &NP_TypeKeys) -> String {
+        String::from(match key {
+            NP_TypeKeys::Int8 | NP_TypeKeys::Int16 | NP_TypeKeys::Int32 | NP_TypeKeys::Uint8 | NP_TypeKeys::Uint16 => "Int",
+            NP_TypeKeys::Float | NP_TypeKeys::Double => "Float",
+            // GraphQL integers are 32 bit
+            _ => {
+                self.long = true;
+                "Long"
+            }
+        })
+    }
+}
+
+/// Generate Rust source with typed accessors for a schema, the root of the schema must be a table.
+///
+/// `type_name` is the name of the root struct, nested structs are named after it.  See the [module docs](index.html) for details.
+pub fn to_rust(schema: &NP_Schema, type_name: &str) -> Result<String, NP_Error> {
+    match schema.parsed.first() {
+        Some(NP_Parsed_Schema::Table { .. }) => { },
+        _ => return Err(NP_Error::new_static("Rust accessors need a table root!"))
+    }
+    if rust_ident(type_name) != type_name {
+        return Err(NP_Error::new(String::from("Not a valid Rust type name: ") + type_name));
+    }
+
+    let mut generator = Accessors { schema: &schema.parsed, names: Vec::new(), items: Vec::new() };
+    generator.object(type_name, 0, true)?;
+
+    let mut result = String::from(RUST_HEADER);
+    for item in generator.items {
+        result.push('\n');
+        result.push_str(&item);
+    }
+    Ok(result)
+}
+
+const RUST_HEADER: &str = "// Generated by no_proto from a schema, don't edit by hand.
+
+#[allow(dead_code)]
+fn np_path<'path>(prefix: &'path [::std::string::String], rest: &[&'path str]) -> ::std::vec::Vec<&'path str> {
+    prefix.iter().map(|part| part.as_str()).chain(rest.iter().copied()).collect()
+}
+
+#[allow(dead_code)]
+fn np_owned(prefix: &[::std::string::String], rest: &[&str]) -> ::std::vec::Vec<::std::string::String> {
+    prefix.iter().cloned().chain(rest.iter().map(|part| ::std::string::String::from(*part))).collect()
+}
+";
+
+const RUST_KEYWORDS: [&str; 51] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn", "else", "enum", "extern", "false", "final",
+    "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self", "Self",
+    "static", "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield"
+];
+
+// a Rust identifier as close to `name` as possible
+fn rust_ident(name: &str) -> String {
+    let mut ident: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if RUST_KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+// `userID` to `user_id`
+fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && previous.map(|p| p.is_ascii_lowercase() || p.is_ascii_digit()) == Some(true) {
+            result.push('_');
+        }
+        result.push(c.to_ascii_lowercase());
+        previous = Some(c);
+    }
+    result
+}
+
+// the `(get, set)` Rust types of a scalar, `None` for collections
+fn rust_types(schema: &NP_Parsed_Schema) -> Option<(&'static str, &'static str)> {
+    Some(match schema {
+        NP_Parsed_Schema::UTF8String { .. } => ("&str", "&'factory str"),
+        NP_Parsed_Schema::Bytes { .. }      => ("&[u8]", "&'factory [u8]"),
+        NP_Parsed_Schema::Int8 { .. }       => ("i8", "i8"),
+        NP_Parsed_Schema::Int16 { .. }      => ("i16", "i16"),
+        NP_Parsed_Schema::Int32 { .. }      => ("i32", "i32"),
+        NP_Parsed_Schema::Int64 { .. }      => ("i64", "i64"),
+        NP_Parsed_Schema::Uint8 { .. }      => ("u8", "u8"),
+        NP_Parsed_Schema::Uint16 { .. }     => ("u16", "u16"),
+        NP_Parsed_Schema::Uint32 { .. }     => ("u32", "u32"),
+        NP_Parsed_Schema::Uint64 { .. }     => ("u64", "u64"),
+        NP_Parsed_Schema::Float { .. }      => ("f32", "f32"),
+        NP_Parsed_Schema::Double { .. }     => ("f64", "f64"),
+        NP_Parsed_Schema::Boolean { .. }    => ("bool", "bool"),
+        NP_Parsed_Schema::Decimal { .. }    => ("::no_proto::pointer::dec::NP_Dec", "::no_proto::pointer::dec::NP_Dec"),
+        NP_Parsed_Schema::Geo { .. }        => ("::no_proto::pointer::geo::NP_Geo", "::no_proto::pointer::geo::NP_Geo"),
+        NP_Parsed_Schema::Date { .. }       => ("::no_proto::pointer::date::NP_Date", "::no_proto::pointer::date::NP_Date"),
+        NP_Parsed_Schema::Enum { .. }       => ("::no_proto::pointer::option::NP_Enum", "::no_proto::pointer::option::NP_Enum"),
+        NP_Parsed_Schema::Uuid { .. }       => ("&::no_proto::pointer::uuid::NP_UUID", "&'factory ::no_proto::pointer::uuid::NP_UUID"),
+        NP_Parsed_Schema::Ulid { .. }       => ("&::no_proto::pointer::ulid::NP_ULID", "&'factory ::no_proto::pointer::ulid::NP_ULID"),
+        NP_Parsed_Schema::BigInt { .. }     => ("::no_proto::pointer::bigint::NP_BigInt", "::no_proto::pointer::bigint::NP_BigInt"),
+        NP_Parsed_Schema::Tensor { .. }     => ("::no_proto::pointer::tensor::NP_Tensor<'_>", "::no_proto::pointer::tensor::NP_Tensor<'factory>"),
+        NP_Parsed_Schema::Any { .. }        => ("::no_proto::pointer::any::NP_Dyn<'_>", "::no_proto::pointer::any::NP_Dyn<'factory>"),
+        _ => return None
+    })
+}
+
+const NP_ERROR: &str = "::no_proto::error::NP_Error";
+const NP_BUFFER: &str = "::no_proto::buffer::NP_Buffer<'factory>";
+
+struct Accessors<'gen> {
+    schema: &'gen [NP_Parsed_Schema],
+    names: Vec<String>,
+    items: Vec<String>
+}
+
+impl<'gen> Accessors<'gen> {
+
+    fn object(&mut self, name: &str, address: usize, root: bool) -> Result<(), NP_Error> {
+        if self.names.iter().any(|existing| existing == name) {
+            return Err(NP_Error::new(String::from("Duplicate Rust type name: ") + name));
+        }
+        self.names.push(name.to_string());
+        self.items.push(String::new());
+        let slot = self.items.len() - 1;
+
+        // (path segment, method name, schema address)
+        let fields: Vec<(String, String, usize)> = match &self.schema[address] {
+            NP_Parsed_Schema::Table { columns, .. } => columns.iter().map(|(_, column, addr)| (column.clone(), rust_ident(&snake_case(column)), *addr)).collect(),
+            NP_Parsed_Schema::Tuple { values, .. } => values.iter().enumerate().map(|(idx, addr)| (idx.to_string(), format!("value_{}", idx), *addr)).collect(),
+            _ => Vec::new()
+        };
+
+        for (idx, (_, method, _)) in fields.iter().enumerate() {
+            if fields[..idx].iter().any(|(_, other, _)| other == method) {
+                return Err(NP_Error::new(format!("Duplicate Rust method name {} in {}", method, name)));
+            }
+        }
+
+        let mut read = String::new();
+        let mut write = String::new();
+
+        for (segment, method, addr) in fields {
+            let segment_lit = format!("{:?}", segment);
+            let path = format!("&np_path(&self.path, &[{}])", segment_lit);
+            let nested_name = String::from(name) + &segment.split(|c: char| !c.is_ascii_alphanumeric()).map(pascal_case).collect::<String>();
+
+            let collection = match &self.schema[addr] {
+                NP_Parsed_Schema::None => continue,
+                NP_Parsed_Schema::List { of, .. } => Some(("index: usize", "&index.to_string()", *of, Some(("push", "list_push", "Option<usize>")))),
+                NP_Parsed_Schema::Log { of, .. } => Some(("index: usize", "&index.to_string()", *of, Some(("append", "log_append", "Option<usize>")))),
+                NP_Parsed_Schema::Map { value, .. } => Some(("key: &str", "key", *value, None)),
+                _ => None
+            };
+
+            match collection {
+                None => match rust_types(&self.schema[addr]) {
+                    Some((get_type, set_type)) => {
+                        let (get, set) = if let NP_Parsed_Schema::Any { .. } = &self.schema[addr] { ("get_dyn", "set_dyn") } else { ("get", "set") };
+                        read.push_str(&format!("    /// Get `{segment}`\n    pub fn {method}(&self) -> Result<Option<{get_type}>, {err}> {{\n        self.buffer.borrow().{get}({path})\n    }}\n\n",
+                            segment = segment, method = method, get_type = get_type, err = NP_ERROR, get = get, path = path));
+                        write.push_str(&format!("    /// Set `{segment}`\n    pub fn set_{method}(&mut self, value: {set_type}) -> Result<bool, {err}> {{\n        self.buffer.borrow_mut().{set}({path}, value)\n    }}\n\n",
+                            segment = segment, method = method, set_type = set_type, err = NP_ERROR, set = set, path = path));
+                    },
+                    // tables and tuples
+                    None => {
+                        self.object(&nested_name, addr, false)?;
+                        read.push_str(&format!("    /// Get `{segment}`\n    pub fn {method}(&self) -> {nested}<'factory, &{buffer}> {{\n        {nested}::at(self.buffer.borrow(), np_owned(&self.path, &[{lit}]))\n    }}\n\n",
+                            segment = segment, method = method, nested = nested_name, buffer = NP_BUFFER, lit = segment_lit));
+                        write.push_str(&format!("    /// Get `{segment}` to update it\n    pub fn {method}_mut(&mut self) -> {nested}<'factory, &mut {buffer}> {{\n        {nested}::at(self.buffer.borrow_mut(), np_owned(&self.path, &[{lit}]))\n    }}\n\n",
+                            segment = segment, method = method, nested = nested_name, buffer = NP_BUFFER, lit = segment_lit));
+                    }
+                },
+                Some((arg, item, item_addr, append)) => {
+                    let item_path = format!("&np_path(&self.path, &[{}, {}])", segment_lit, item);
+                    read.push_str(&format!("    /// Get the number of items in `{segment}`\n    pub fn {method}_len(&self) -> Result<usize, {err}> {{\n        Ok(self.buffer.borrow().length({path})?.unwrap_or(0))\n    }}\n\n",
+                        segment = segment, method = method, err = NP_ERROR, path = path));
+
+                    match (&self.schema[item_addr], rust_types(&self.schema[item_addr])) {
+                        (NP_Parsed_Schema::None, _) => { },
+                        (_, Some((get_type, set_type))) => {
+                            let (get, set) = if let NP_Parsed_Schema::Any { .. } = &self.schema[item_addr] { ("get_dyn", "set_dyn") } else { ("get", "set") };
+                            read.push_str(&format!("    /// Get an item in `{segment}`\n    pub fn {method}(&self, {arg}) -> Result<Option<{get_type}>, {err}> {{\n        self.buffer.borrow().{get}({path})\n    }}\n\n",
+                                segment = segment, method = method, arg = arg, get_type = get_type, err = NP_ERROR, get = get, path = item_path));
+                            write.push_str(&format!("    /// Set an item in `{segment}`\n    pub fn set_{method}(&mut self, {arg}, value: {set_type}) -> Result<bool, {err}> {{\n        self.buffer.borrow_mut().{set}({path}, value)\n    }}\n\n",
+                                segment = segment, method = method, arg = arg, set_type = set_type, err = NP_ERROR, set = set, path = item_path));
+                            if let Some((prefix, call, result)) = append {
+                                write.push_str(&format!("    /// Add an item to the end of `{segment}`\n    pub fn {prefix}_{method}(&mut self, value: {set_type}) -> Result<{result}, {err}> {{\n        self.buffer.borrow_mut().{call}({path}, value)\n    }}\n\n",
+                                    segment = segment, prefix = prefix, method = method, set_type = set_type, result = result, err = NP_ERROR, call = call, path = path));
+                            }
+                        },
+                        (NP_Parsed_Schema::Table { .. }, None) | (NP_Parsed_Schema::Tuple { .. }, None) => {
+                            self.object(&nested_name, item_addr, false)?;
+                            read.push_str(&format!("    /// Get an item in `{segment}`\n    pub fn {method}(&self, {arg}) -> {nested}<'factory, &{buffer}> {{\n        {nested}::at(self.buffer.borrow(), np_owned(&self.path, &[{lit}, {item}]))\n    }}\n\n",
+                                segment = segment, method = method, arg = arg, nested = nested_name, buffer = NP_BUFFER, lit = segment_lit, item = item));
+                            write.push_str(&format!("    /// Get an item in `{segment}` to update it\n    pub fn {method}_mut(&mut self, {arg}) -> {nested}<'factory, &mut {buffer}> {{\n        {nested}::at(self.buffer.borrow_mut(), np_owned(&self.path, &[{lit}, {item}]))\n    }}\n\n",
+                                segment = segment, method = method, arg = arg, nested = nested_name, buffer = NP_BUFFER, lit = segment_lit, item = item));
+                        },
+                        // collections of collections only get a length
+                        _ => { }
+                    }
+                }
+            }
+
+            write.push_str(&format!("    /// Delete `{segment}`\n    pub fn del_{method}(&mut self) -> Result<bool, {err}> {{\n        self.buffer.borrow_mut().del({path})\n    }}\n\n",
+                segment = segment, method = method, err = NP_ERROR, path = path));
+        }
+
+        let constructor = if root {
+            format!("    /// Accessors for the root of `buffer`\n    pub fn new(buffer: B) -> Self {{\n        {name} {{ buffer, path: ::std::vec::Vec::new(), factory: ::core::marker::PhantomData }}\n    }}\n\n", name = name)
+        } else {
+            String::new()
+        };
+
+        self.items[slot] = format!("/// Typed accessors for `{name}` values in a buffer
+#[allow(dead_code)]
+pub struct {name}<'factory, B> {{
+    buffer: B,
+    path: ::std::vec::Vec<::std::string::String>,
+    // ties the lifetime of values read from the buffer to its factory
+    factory: ::core::marker::PhantomData<&'factory ()>
+}}
+
+#[allow(dead_code)]
+impl<'factory, B> {name}<'factory, B> {{
+{constructor}    fn at(buffer: B, path: ::std::vec::Vec<::std::string::String>) -> Self {{
+        {name} {{ buffer, path, factory: ::core::marker::PhantomData }}
+    }}
+
+    /// Give back the buffer
+    pub fn into_inner(self) -> B {{
+        self.buffer
+    }}
+}}
+
+#[allow(dead_code)]
+impl<'factory, B: ::core::borrow::Borrow<{buffer}>> {name}<'factory, B> {{
+{read}}}
+
+#[allow(dead_code)]
+impl<'factory, B: ::core::borrow::BorrowMut<{buffer}>> {name}<'factory, B> {{
+{write}}}
+", name = name, constructor = constructor, buffer = NP_BUFFER, read = read.trim_end_matches('\n').to_string() + "\n", write = write.trim_end_matches('\n').to_string() + "\n");
+
+        Ok(())
+    }
+}
+
+#[test]
+fn sql_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["code",   {"type": "string", "size": 8}],
+        ["u64",    {"type": "u64", "required": true}],
+        ["dec",    {"type": "decimal", "exp": 2}],
+        ["color",  {"type": "option", "choices": ["red", "o'range"]}],
+        ["geo",    {"type": "geo8"}],
+        ["info",   {"type": "table", "columns": [["a", {"type": "bool"}]]}],
+        ["pair",   {"type": "tuple", "values": [{"type": "uuid"}, {"type": "date"}]}],
+        ["scores", {"type": "map", "value": {"type": "list", "of": {"type": "i16"}}}]
+    ]}"#)?;
+
+    let options = NP_Sql_Options { table_name: String::from("t"), ..Default::default() };
+
+    assert_eq!(to_sql(&factory.schema, NP_Sql_Dialect::MySql, &options)?, "CREATE TABLE `t` (
+    `code` VARCHAR(8),
+    `u64` BIGINT UNSIGNED NOT NULL,
+    `dec` DECIMAL(19, 2),
+    `color` ENUM('red', 'o''range'),
+    `geo` JSON,
+    `info` JSON,
+    `pair` JSON,
+    `scores` JSON
+);
+");
+
+    let options = NP_Sql_Options { table_name: String::from("t"), nested: NP_Sql_Nested::ChildTables, key_column: String::from("rowid") };
+
+    assert_eq!(to_sql(&factory.schema, NP_Sql_Dialect::Sqlite, &options)?, r#"CREATE TABLE "t" (
+    "rowid" INTEGER PRIMARY KEY,
+    "code" TEXT,
+    "u64" NUMERIC NOT NULL,
+    "dec" NUMERIC,
+    "color" TEXT CHECK ("color" IN ('red', 'o''range')),
+    "geo" TEXT
+);
+
+CREATE TABLE "t_info" (
+    "rowid" INTEGER PRIMARY KEY,
+    "t_rowid" INTEGER NOT NULL REFERENCES "t" ("rowid"),
+    "a" INTEGER,
+    UNIQUE ("t_rowid")
+);
+
+CREATE TABLE "t_pair" (
+    "rowid" INTEGER PRIMARY KEY,
+    "t_rowid" INTEGER NOT NULL REFERENCES "t" ("rowid"),
+    "0" TEXT,
+    "1" INTEGER,
+    UNIQUE ("t_rowid")
+);
+
+CREATE TABLE "t_scores" (
+    "rowid" INTEGER PRIMARY KEY,
+    "t_rowid" INTEGER NOT NULL REFERENCES "t" ("rowid"),
+    "key" TEXT NOT NULL,
+    UNIQUE ("t_rowid", "key")
+);
+
+CREATE TABLE "t_scores_value" (
+    "rowid" INTEGER PRIMARY KEY,
+    "t_scores_rowid" INTEGER NOT NULL REFERENCES "t_scores" ("rowid"),
+    "idx" INTEGER NOT NULL,
+    "value" INTEGER,
+    UNIQUE ("t_scores_rowid", "idx")
+);
+"#);
+
+    // generated columns can't collide with schema columns
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [["id", {"type": "string"}]]}"#)?;
+    assert!(to_sql(&factory.schema, NP_Sql_Dialect::Postgres, &options).is_ok());
+    assert!(to_sql(&factory.schema, NP_Sql_Dialect::Postgres, &NP_Sql_Options { nested: NP_Sql_Nested::ChildTables, ..Default::default() }).is_err());
+
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    assert!(to_sql(&factory.schema, NP_Sql_Dialect::Postgres, &NP_Sql_Options::default()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn graphql_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["id",      {"type": "uuid", "required": true}],
+        ["small",   {"type": "u16"}],
+        ["big",     {"type": "u32"}],
+        ["bytes",   {"type": "bytes"}],
+        ["dec",     {"type": "decimal", "exp": 2}],
+        ["place",   {"type": "geo4"}],
+        ["grid",    {"type": "tensor", "dtype": "f32", "shape": [2, 2]}],
+        ["extra",   {"type": "map", "value": {"type": "string"}}],
+        ["nested",  {"type": "list", "of": {"type": "log", "of": {"type": "table", "columns": [["size", {"type": "option", "choices": ["S", "M"]}]]}}}]
+    ]}"#)?;
+
+    assert_eq!(to_graphql(&factory.schema, "Item")?, "type Item {
+  id: String!
+  small: Int
+  big: Long
+  bytes: [Int!]
+  dec: Decimal
+  place: Geo
+  grid: [Float!]
+  extra: JSON
+  nested: [[ItemNested]]
+}
+
+type ItemNested {
+  size: ItemNestedSize
+}
+
+enum ItemNestedSize {
+  S
+  M
+}
+
+type Decimal {
+  num: Long!
+  exp: Int!
+}
+
+type Geo {
+  lat: Float!
+  lng: Float!
+}
+
+scalar Long
+
+scalar JSON
+");
+
+    // names that can't be used in GraphQL are errors
+    assert!(to_graphql(&factory.schema, "2Item").is_err());
+    assert!(to_graphql(&factory.schema, "Geo").is_err());
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [["first name", {"type": "string"}]]}"#)?;
+    assert!(to_graphql(&factory.schema, "Item").is_err());
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [["a", {"type": "option", "choices": ["null"]}]]}"#)?;
+    assert!(to_graphql(&factory.schema, "Item").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn rust_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["userID",  {"type": "string"}],
+        ["type",    {"type": "uuid"}],
+        ["events",  {"type": "log", "of": {"type": "i32"}}],
+        ["rooms",   {"type": "map", "value": {"type": "table", "columns": [["size", {"type": "u8"}]]}}],
+        ["matrix",  {"type": "list", "of": {"type": "list", "of": {"type": "u8"}}}],
+        ["pair",    {"type": "tuple", "values": [{"type": "any"}, {"type": "bool"}]}]
+    ]}"#)?;
+
+    let code = to_rust(&factory.schema, "User")?;
+    for expected in [
+        "pub fn user_id(&self) -> Result<Option<&str>, ::no_proto::error::NP_Error> {\n        self.buffer.borrow().get(&np_path(&self.path, &[\"userID\"]))\n    }",
+        "pub fn set_type_(&mut self, value: &'factory ::no_proto::pointer::uuid::NP_UUID) -> Result<bool, ::no_proto::error::NP_Error> {",
+        "pub fn append_events(&mut self, value: i32) -> Result<Option<usize>, ::no_proto::error::NP_Error> {",
+        "pub fn events(&self, index: usize) -> Result<Option<i32>, ::no_proto::error::NP_Error> {",
+        "pub fn rooms_mut(&mut self, key: &str) -> UserRooms<'factory, &mut ::no_proto::buffer::NP_Buffer<'factory>> {",
+        "pub fn matrix_len(&self) -> Result<usize, ::no_proto::error::NP_Error> {",
+        "pub fn del_matrix(&mut self) -> Result<bool, ::no_proto::error::NP_Error> {",
+        "pub fn value_0(&self) -> Result<Option<::no_proto::pointer::any::NP_Dyn<'_>>, ::no_proto::error::NP_Error> {\n        self.buffer.borrow().get_dyn(&np_path(&self.path, &[\"0\"]))\n    }",
+        "pub struct UserRooms<'factory, B> {",
+        "pub struct UserPair<'factory, B> {"
+    ].iter() {
+        assert!(code.contains(expected), "missing {}", expected);
+    }
+    // lists of lists only get a length
+    assert!(!code.contains("pub fn matrix(&self"));
+    // only the root struct has a public constructor
+    assert_eq!(code.matches("pub fn new(").count(), 1);
+
+    assert!(to_rust(&factory.schema, "user type").is_err());
+    assert!(to_rust(&factory.schema, "Self").is_err());
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [["userID", {"type": "string"}], ["user_id", {"type": "string"}]]}"#)?;
+    assert!(to_rust(&factory.schema, "User").is_err());
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+//! Encrypted buffers, enabled with the `seal` feature
+//!
+//! `NP_Buffer::seal` encrypts a buffer with ChaCha20-Poly1305 (RFC 8439, from the `chacha20poly1305` crate) and `NP_Factory::open_sealed` decrypts it again, so buffers can be stored at rest without wrapping them in another format.
+//!
+//! Sealed buffers start with a short cleartext header:
+//!
+//! | Bytes       | Contents                                                           |
+//! |-------------|--------------------------------------------------------------------|
+//! | 0           | Envelope version, always `1`                                       |
+//! | 1 - 8       | Fingerprint of the schema the buffer was made with, big endian     |
+//! | 9 - 20      | Nonce                                                              |
+//! | 21 - end-16 | Encrypted buffer bytes                                             |
+//! | last 16     | Poly1305 tag                                                       |
+//!
+//! The fingerprint is the same one `NP_Schema::fingerprint` returns, see the [fingerprint](../fingerprint/index.html) module.
+//!
+//! The header is authenticated along with the buffer, changing any byte of a sealed buffer makes `open_sealed` return an error.  Opening a buffer with a factory for a different schema is an error before anything is decrypted.
+//!
+//! Keys are 32 bytes and nonces are 12 bytes.  **A nonce must never be used twice with the same key**, use a counter or 12 random bytes for every call to `seal`.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}]
+//!     ]
+//! }"#)?;
+//!
+//! let key = [7u8; 32];
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb Kermin")?;
+//! let sealed: Vec<u8> = new_buffer.seal(&key, &[0u8; 12])?;
+//!
+//! let opened = factory.open_sealed(&sealed, &key)?;
+//! assert_eq!(opened.get::<&str>(&["name"])?, Some("Jeb Kermin"));
+//!
+//! // the wrong key can't open it
+//! assert!(factory.open_sealed(&sealed, &[8u8; 32]).is_err());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use alloc::vec::Vec;
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce, Tag};
+use chacha20poly1305::aead::AeadInPlace;
+
+/// Envelope version written by `seal`
+pub const SEAL_VERSION: u8 = 1;
+
+// version, schema fingerprint, nonce
+const HEADER_LEN: usize = 21;
+const TAG_LEN: usize = 16;
+
+/// Encrypt buffer bytes into a sealed envelope
+pub(crate) fn seal(fingerprint: u64, bytes: &[u8], key: &[u8; 32], nonce: &[u8; 12]) -> Result<Vec<u8>, NP_Error> {
+    let mut sealed = Vec::with_capacity(HEADER_LEN + bytes.len() + TAG_LEN);
+    sealed.push(SEAL_VERSION);
+    sealed.extend_from_slice(&fingerprint.to_be_bytes());
+    sealed.extend_from_slice(nonce);
+    sealed.extend_from_slice(bytes);
+
+    // the header is the associated data, so it's authenticated but stays readable
+    let (header, body) = sealed.split_at_mut(HEADER_LEN);
+    let tag = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt_in_place_detached(Nonce::from_slice(nonce), header, body)
+        .map_err(|_| NP_Error::new_static("Buffer is too large to seal!"))?;
+    sealed.extend_from_slice(&tag);
+    Ok(sealed)
+}
+
+/// Check and decrypt a sealed envelope, returns the buffer bytes
+pub(crate) fn open(fingerprint: u64, sealed: &[u8], key: &[u8; 32]) -> Result<Vec<u8>, NP_Error> {
+    if sealed.len() < HEADER_LEN + TAG_LEN {
+        return Err(NP_Error::new_static("Sealed buffer is too short!"));
+    }
+    if sealed[0] != SEAL_VERSION {
+        return Err(NP_Error::new_static("Unknown sealed buffer version!"));
+    }
+    if sealed[1..9] != fingerprint.to_be_bytes() {
+        return Err(NP_Error::new_static("Sealed buffer was made with a different schema!"));
+    }
+
+    let (header, rest) = sealed.split_at(HEADER_LEN);
+    let (body, tag) = rest.split_at(rest.len() - TAG_LEN);
+
+    let mut bytes = body.to_vec();
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt_in_place_detached(Nonce::from_slice(&header[9..]), header, &mut bytes, Tag::from_slice(tag))
+        .map_err(|_| NP_Error::new_static("Sealed buffer can't be opened, the key is wrong or the bytes were changed!"))?;
+    Ok(bytes)
+}
+
+#[test]
+fn seal_works() -> Result<(), NP_Error> {
+    let key = [3u8; 32];
+    let nonce = [7, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47];
+
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["2"], "secret")?;
+    let sealed = buffer.seal(&key, &nonce)?;
+    assert_eq!(sealed[0], SEAL_VERSION);
+    assert_eq!(&sealed[1..9], &factory.schema.fingerprint()?.to_be_bytes());
+    assert_eq!(&sealed[9..21], &nonce);
+    assert!(!sealed.windows(6).any(|window| window == b"secret"));
+    assert_eq!(factory.open_sealed(&sealed, &key)?.get::<&str>(&["2"])?, Some("secret"));
+
+    // every byte is authenticated
+    for idx in 0..sealed.len() {
+        let mut changed = sealed.clone();
+        changed[idx] ^= 1;
+        assert!(factory.open_sealed(&changed, &key).is_err());
+    }
+    assert!(factory.open_sealed(&sealed[..36], &key).is_err());
+    assert!(factory.open_sealed(&sealed, &[4u8; 32]).is_err());
+
+    let other = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u8"}}"#)?;
+    assert_eq!(other.open_sealed(&sealed, &key).err(), Some(NP_Error::new_static("Sealed buffer was made with a different schema!")));
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Read only snapshots of buffers
+//!
+//! `NP_Buffer::freeze` returns an `NP_Snapshot`, the state of the buffer at that moment.  The snapshot shares the bytes written so far with the buffer instead of copying them, the buffer makes its own copy the next time it's used.  After that the buffer can keep changing while readers look at the snapshot, it never changes.  Reading a snapshot only borrows the shared bytes, it never copies them.
+//!
+//! Snapshots have every method of `NP_Buffer` that doesn't change the buffer.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb Kermin")?;
+//! new_buffer.set(&["age"], 30u8)?;
+//!
+//! let snapshot = new_buffer.freeze();
+//!
+//! // keep writing to the buffer
+//! new_buffer.set(&["name"], "Bill")?;
+//! new_buffer.del(&["age"])?;
+//!
+//! // the snapshot still has the values from when it was made
+//! assert_eq!(snapshot.get::<&str>(&["name"])?, Some("Jeb Kermin"));
+//! assert_eq!(snapshot.get::<u8>(&["age"])?, Some(30));
+//! assert_eq!(new_buffer.get::<&str>(&["name"])?, Some("Bill"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::NP_Buffer;
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+/// A buffer that can't be changed, get one with `NP_Buffer::freeze`.
+pub struct NP_Snapshot<'buffer> {
+    buffer: NP_Buffer<'buffer>
+}
+
+impl<'buffer> NP_Snapshot<'buffer> {
+
+    #[doc(hidden)]
+    pub fn _new(buffer: NP_Buffer<'buffer>) -> Self {
+        NP_Snapshot { buffer }
+    }
+
+    /// Get a copy of the snapshot's bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.buffer.read_bytes().to_vec()
+    }
+}
+
+impl<'buffer> Deref for NP_Snapshot<'buffer> {
+    type Target = NP_Buffer<'buffer>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+#[test]
+fn snapshot_works() -> Result<(), crate::error::NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["0"], "hello")?;
+    let first = buffer.freeze();
+    let second = buffer.freeze();
+
+    // the buffer grows and updates in place while the snapshots keep their bytes
+    for i in 1..20 {
+        buffer.set(&[alloc::format!("{}", i).as_str()], "world")?;
+    }
+    buffer.set(&["0"], "there")?;
+    let third = buffer.freeze();
+    buffer.compact(None)?;
+    buffer.set(&["0"], "again")?;
+
+    assert_eq!(first.json_encode(&[])?.stringify(), r#"["hello"]"#);
+    assert_eq!(first.read_bytes(), second.read_bytes());
+    // reading a snapshot never copies the shared bytes
+    assert_eq!(first.read_bytes().as_ptr(), second.read_bytes().as_ptr());
+    assert_eq!(third.get::<&str>(&["0"])?, Some("there"));
+    assert_eq!(third.get::<&str>(&["19"])?, Some("world"));
+    assert_eq!(buffer.get::<&str>(&["0"])?, Some("again"));
+
+    // dropped snapshots leave the others alone, the bytes still open as buffers
+    drop(second);
+    assert_eq!(first.get::<&str>(&["0"])?, Some("hello"));
+    assert_eq!(factory.open_buffer(third.to_bytes()).get::<&str>(&["0"])?, Some("there"));
+
+    Ok(())
+}
@@ -0,0 +1,203 @@
+//! Snapshot testing helpers, enabled with the `testing` feature
+//!
+//! A snapshot is a stable text form of a buffer: the schema's fingerprint followed by the buffer's JSON with sorted keys and one value per line.  Buffers with the same values always have the same snapshot no matter how their bytes are laid out, so snapshots can be checked into version control and compared against what an encoder produces.
+//!
+//! `assert_snapshot` compares a buffer against a snapshot file and panics with a line diff when they don't match.  Missing snapshot files are written instead of compared, set the `NP_UPDATE_SNAPSHOTS` environment variable to rewrite existing ones.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::testing::{snapshot, diff_snapshots};
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Billy")?;
+//! new_buffer.set(&["tags", "0"], "pilot")?;
+//!
+//! let expected = snapshot(&factory, &new_buffer)?;
+//! assert!(expected.ends_with("{\n  \"name\": \"Billy\",\n  \"tags\": [\n    \"pilot\"\n  ]\n}\n"));
+//!
+//! new_buffer.set(&["name"], "Bob")?;
+//! let actual = snapshot(&factory, &new_buffer)?;
+//!
+//! assert_eq!(diff_snapshots(&expected, &expected), None);
+//! assert!(diff_snapshots(&expected, &actual).unwrap().contains("-   \"name\": \"Billy\",\n+   \"name\": \"Bob\","));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+extern crate std;
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::json_flex::NP_JSON;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use std::path::Path;
+
+/// Environment variable that makes `assert_snapshot` rewrite snapshot files
+pub const UPDATE_SNAPSHOTS_VAR: &str = "NP_UPDATE_SNAPSHOTS";
+
+/// Get the snapshot of a buffer.
+pub fn snapshot(factory: &NP_Factory, buffer: &NP_Buffer) -> Result<String, NP_Error> {
+    // snapshots from different schemas never match
+    let mut result = format!("schema: {:016x}\n", factory.schema.fingerprint);
+    write_json(&buffer.json_encode(&[])?, 0, &mut result);
+    result.push('\n');
+    Ok(result)
+}
+
+// pretty print with sorted keys so the same values always print the same way
+fn write_json(json: &NP_JSON, depth: usize, result: &mut String) {
+    let indent = |result: &mut String, depth: usize| {
+        for _ in 0..depth { result.push_str("  "); }
+    };
+
+    match json {
+        NP_JSON::Dictionary(map) if !map.values.is_empty() => {
+            let mut keys: Vec<&(String, NP_JSON)> = map.values.iter().collect();
+            keys.sort_by(|a, b| a.0.cmp(&b.0));
+            result.push_str("{\n");
+            for (idx, (key, value)) in keys.into_iter().enumerate() {
+                if idx > 0 { result.push_str(",\n"); }
+                indent(result, depth + 1);
+                result.push_str(&NP_JSON::String(key.clone()).stringify());
+                result.push_str(": ");
+                write_json(value, depth + 1, result);
+            }
+            result.push('\n');
+            indent(result, depth);
+            result.push('}');
+        },
+        NP_JSON::Array(items) if !items.is_empty() => {
+            result.push_str("[\n");
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 { result.push_str(",\n"); }
+                indent(result, depth + 1);
+                write_json(item, depth + 1, result);
+            }
+            result.push('\n');
+            indent(result, depth);
+            result.push(']');
+        },
+        _ => result.push_str(&json.stringify())
+    }
+}
+
+/// Compare two snapshots line by line, returns `None` if they match or a readable diff if they don't.
+///
+/// Lines only in `expected` start with `-`, lines only in `actual` start with `+`.
+pub fn diff_snapshots(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+
+    let a: Vec<&str> = expected.lines().collect();
+    let b: Vec<&str> = actual.lines().collect();
+
+    // longest common subsequence of lines
+    let mut lcs = alloc::vec![alloc::vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { core::cmp::max(lcs[i + 1][j], lcs[i][j + 1]) };
+        }
+    }
+
+    let mut result = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            result.push_str("  ");
+            result.push_str(a[i]);
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            result.push_str("- ");
+            result.push_str(a[i]);
+            i += 1;
+        } else {
+            result.push_str("+ ");
+            result.push_str(b[j]);
+            j += 1;
+        }
+        result.push('\n');
+    }
+
+    Some(result)
+}
+
+/// Compare a buffer against the snapshot stored at `path`, panics with a diff if they don't match.
+///
+/// The snapshot is written to `path` if the file doesn't exist yet, or if the `NP_UPDATE_SNAPSHOTS` environment variable is set.
+pub fn assert_snapshot<P: AsRef<Path>>(path: P, factory: &NP_Factory, buffer: &NP_Buffer) {
+    let path = path.as_ref();
+
+    let actual = match snapshot(factory, buffer) {
+        Ok(x) => x,
+        Err(e) => panic!("Can't snapshot buffer: {}", e)
+    };
+
+    if std::env::var_os(UPDATE_SNAPSHOTS_VAR).is_some() || !path.exists() {
+        if let Some(dir) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                panic!("Can't create snapshot directory {}: {}", dir.display(), e);
+            }
+        }
+        if let Err(e) = std::fs::write(path, &actual) {
+            panic!("Can't write snapshot {}: {}", path.display(), e);
+        }
+        return;
+    }
+
+    let expected = match std::fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(e) => panic!("Can't read snapshot {}: {}", path.display(), e)
+    };
+
+    if let Some(diff) = diff_snapshots(&expected, &actual) {
+        panic!("Snapshot {} doesn't match, set {} to update it:\n{}", path.display(), UPDATE_SNAPSHOTS_VAR, diff);
+    }
+}
+
+#[test]
+fn snapshots_work() -> Result<(), NP_Error> {
+    let factory = NP_Factory::new(r#"{"type": "map", "value": {"type": "u8"}}"#)?;
+
+    // key order and layout don't change the snapshot
+    let mut a = factory.empty_buffer(None);
+    a.set(&["b"], 2u8)?;
+    a.set(&["a"], 1u8)?;
+    a.set(&["b"], 3u8)?;
+    let mut b = factory.empty_buffer(None);
+    b.set(&["a"], 1u8)?;
+    b.set(&["b"], 3u8)?;
+    assert_eq!(snapshot(&factory, &a)?, snapshot(&factory, &b)?);
+    assert!(snapshot(&factory, &a)?.ends_with("{\n  \"a\": 1,\n  \"b\": 3\n}\n"));
+
+    // different schemas, different snapshots
+    let other = NP_Factory::new(r#"{"type": "map", "value": {"type": "u16"}}"#)?;
+    let c = other.empty_buffer(None);
+    assert_ne!(factory.schema.fingerprint()?, other.schema.fingerprint()?);
+    assert_eq!(snapshot(&other, &c)?, format!("schema: {:016x}\nnull\n", other.schema.fingerprint()?));
+
+    let dir = std::env::temp_dir().join(format!("no_proto_snapshot_{}", std::process::id()));
+    let path = dir.join("map.snap");
+    assert_snapshot(&path, &factory, &a);
+    assert_snapshot(&path, &factory, &b);
+    b.set(&["c"], 4u8)?;
+    let changed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| assert_snapshot(&path, &factory, &b)));
+    std::fs::remove_dir_all(&dir).ok();
+    assert!(changed.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! Delete tracking for replicated buffers
+//!
+//! Once `NP_Buffer::track_deletes` is called, every `del` records a tombstone in the buffer: a hash of the deleted path and the time it was deleted.  Replicas merging buffers can then tell a value that was never set apart from one that was deleted, and keep whichever change happened last.
+//!
+//! Tombstones are stored in their own region of the buffer and are kept through compaction.  The clock isn't stored, call `track_deletes` again after opening a buffer to keep recording deletes.  Setting a value again doesn't remove its tombstone, compare the timestamps to decide which change wins.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::tombstone::{NP_Tombstone, path_hash};
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! // any function returning the current time works, like the seconds since the unix epoch
+//! new_buffer.track_deletes(|| 1_607_000_000)?;
+//!
+//! new_buffer.set(&["name"], "Jeb Kermin")?;
+//! new_buffer.del(&["name"])?;
+//!
+//! // deleted, and never set
+//! assert_eq!(new_buffer.tombstone(&["name"]), Some(1_607_000_000));
+//! assert_eq!(new_buffer.tombstone(&["age"]), None);
+//!
+//! // tombstones are saved with the buffer
+//! let opened = factory.open_buffer(new_buffer.close());
+//! assert_eq!(opened.tombstones(), vec![NP_Tombstone { path_hash: path_hash(&["name"]), timestamp: 1_607_000_000 }]);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! Tombstones are a linked list, the address of the first one is stored right after the root pointer (before the version, see the [version](../version/index.html) module).  Each tombstone is 14 bytes:
+//!
+//! | Bytes   | Contents                                   |
+//! |---------|--------------------------------------------|
+//! | 0 - 1   | Address of the next tombstone, 0 if none   |
+//! | 2 - 5   | Hash of the deleted path                   |
+//! | 6 - 13  | Timestamp from the clock                   |
+//!
+
+use crate::error::NP_Error;
+use crate::hashmap::{murmurhash3_x86_32, SEED};
+use crate::memory::NP_Memory;
+use alloc::vec::Vec;
+
+/// Address of the pointer to the first tombstone, right after the root pointer
+pub(crate) const HEAD_ADDR: usize = 3;
+
+const TOMBSTONE_LEN: usize = 14;
+
+/// A deleted path, returned from `NP_Buffer::tombstones`
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct NP_Tombstone {
+    /// Hash of the deleted path (from the root of the buffer), see `path_hash`
+    pub path_hash: u32,
+    /// When the path was deleted, from the clock passed to `track_deletes`
+    pub timestamp: u64
+}
+
+/// Hash a path from the root of a buffer the same way tombstones do
+pub fn path_hash(path: &[&str]) -> u32 {
+    hash_path(SEED, path)
+}
+
+// each key is hashed with the hash of the path before it as the seed, so the cursor can keep the hash of its own path
+pub(crate) fn hash_path(seed: u32, path: &[&str]) -> u32 {
+    path.iter().fold(seed, |hash, key| murmurhash3_x86_32(key.as_bytes(), hash))
+}
+
+fn read_u16(bytes: &[u8], address: usize) -> usize {
+    u16::from_be_bytes([bytes[address], bytes[address + 1]]) as usize
+}
+
+// addresses of every tombstone, stops at the first address that is out of bounds or already seen
+fn addresses(memory: &NP_Memory) -> Vec<usize> {
+    let bytes = memory.read_bytes();
+    let mut found: Vec<usize> = Vec::new();
+
+    if !memory.has_tombstones() || bytes.len() < HEAD_ADDR + 2 {
+        return found;
+    }
+
+    let mut address = read_u16(bytes, HEAD_ADDR);
+    while address != 0 && address + TOMBSTONE_LEN <= bytes.len() && !found.contains(&address) {
+        found.push(address);
+        address = read_u16(bytes, address);
+    }
+    found
+}
+
+/// Every tombstone in the buffer, newest first
+pub(crate) fn read(memory: &NP_Memory) -> Vec<NP_Tombstone> {
+    let bytes = memory.read_bytes();
+    addresses(memory).into_iter().map(|address| {
+        let mut hash = [0u8; 4];
+        hash.copy_from_slice(&bytes[(address + 2)..(address + 6)]);
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&bytes[(address + 6)..(address + 14)]);
+        NP_Tombstone { path_hash: u32::from_be_bytes(hash), timestamp: u64::from_be_bytes(timestamp) }
+    }).collect()
+}
+
+/// Bytes used by the tombstone region
+pub(crate) fn size(memory: &NP_Memory) -> usize {
+    if memory.has_tombstones() { 2 + addresses(memory).len() * TOMBSTONE_LEN } else { 0 }
+}
+
+/// Record a delete, updating the timestamp if the path already has a tombstone
+pub(crate) fn record(memory: &NP_Memory, path_hash: u32, timestamp: u64) -> Result<(), NP_Error> {
+    for address in addresses(memory) {
+        let bytes = memory.write_bytes();
+        if bytes[(address + 2)..(address + 6)] == path_hash.to_be_bytes() {
+            bytes[(address + 6)..(address + 14)].copy_from_slice(&timestamp.to_be_bytes());
+            return Ok(());
+        }
+    }
+
+    let mut tombstone: Vec<u8> = Vec::with_capacity(TOMBSTONE_LEN);
+    tombstone.extend_from_slice(&memory.read_bytes()[HEAD_ADDR..(HEAD_ADDR + 2)]);
+    tombstone.extend_from_slice(&path_hash.to_be_bytes());
+    tombstone.extend_from_slice(&timestamp.to_be_bytes());
+    let address = memory.malloc(tombstone)?;
+    memory.write_bytes()[HEAD_ADDR..(HEAD_ADDR + 2)].copy_from_slice(&(address as u16).to_be_bytes());
+    Ok(())
+}
+
+/// Copy every tombstone into a new buffer that already has its tombstone pointer, used by compaction
+pub(crate) fn copy(from: &NP_Memory, to: &NP_Memory) -> Result<(), NP_Error> {
+    for tombstone in read(from).into_iter().rev() {
+        record(to, tombstone.path_hash, tombstone.timestamp)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn tombstones_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "table", "columns": [["name", {"type": "string"}]]}}"#)?;
+
+    // tracking deletes on a buffer with values moves them to make room for the tombstones
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["0", "name"], "hello")?;
+    buffer.set(&["1", "name"], "world")?;
+    assert!(buffer.del(&["0", "name"])?);
+    assert_eq!(buffer.tombstones(), Vec::new());
+    buffer.track_deletes(|| 5)?;
+    assert_eq!(buffer.get::<&str>(&["1", "name"])?, Some("world"));
+
+    buffer.move_cursor(&["1"])?;
+    buffer.del(&["name"])?;
+    buffer.cursor_to_root();
+    assert_eq!(buffer.tombstone(&["1", "name"]), Some(5));
+    assert_eq!(buffer.tombstone(&["0", "name"]), None);
+
+    buffer.track_deletes(|| 9)?;
+    buffer.del(&["1", "name"])?;
+    buffer.del(&["0"])?;
+    assert_eq!(buffer.tombstones(), [NP_Tombstone { path_hash: path_hash(&["0"]), timestamp: 9 }, NP_Tombstone { path_hash: path_hash(&["1", "name"]), timestamp: 9 }].to_vec());
+
+    // compaction keeps tombstones
+    let before = buffer.tombstones();
+    buffer.compact(None)?;
+    assert_eq!(buffer.tombstones(), before);
+    assert_eq!(buffer.calc_bytes()?.wasted_bytes, 0);
+    assert_eq!(buffer.memory_stats()?.wasted_bytes, 0);
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+// Hooks for the `tracing` and `metrics` features.
+//
+// With the feature on buffers emit trace level spans and events under the `no_proto` target:
+// - `open` events with the size of every new or opened buffer
+// - `set` spans with the path being set, and `get` events with the path being read
+// - `grow` events with the old and new capacity whenever the underlying `Vec<u8>` has to grow, inside the span of the `set` that caused it
+// - `compact` spans around every compaction, and `compacted` events with the size before and after
+//
+// Without the feature the macros expand to nothing and their arguments are never evaluated.
+
+// a trace level event
+macro_rules! np_event {
+    ($($field:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(target: "no_proto", $($field)*);
+    };
+}
+
+// a trace level span, entered until the end of the enclosing block
+macro_rules! np_span {
+    ($($field:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!(target: "no_proto", $($field)*).entered();
+    };
+}
+
+// add one to a counter in the `metrics` module, compiled out without the `metrics` feature
+macro_rules! np_count {
+    ($counter:ident) => {
+        #[cfg(feature = "metrics")]
+        crate::metrics::$counter.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+    };
+}
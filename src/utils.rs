@@ -52,7 +52,7 @@ impl Rand {
 pub fn opt_err<T>(optin: Option<T>) -> Result<T, NP_Error> {
     match optin {
         Some(x) => Ok(x),
-        None => Err(NP_Error::new("No value found here!"))
+        None => Err(NP_Error::new_static("No value found here!"))
     }
 }
 
@@ -67,7 +67,7 @@ pub fn to_base32(num: u128, length: i32) -> String {
 
     let mut value = num;
     let i = length - 1;
-    for x in 0..i {
+    for x in 0..length {
         let modulus = value % 32; 
         result[(i - x) as usize] = base_values[modulus as usize];
         value = (value - modulus) / 32;
@@ -79,4 +79,26 @@ pub fn to_base32(num: u128, length: i32) -> String {
     }
 
     final_string
-}
\ No newline at end of file
+}
+
+pub fn from_base32(text: &str) -> Option<u128> {
+
+    let mut value = 0u128;
+
+    for c in text.bytes() {
+        let digit = match c.to_ascii_uppercase() {
+            b'0' | b'O' => 0,
+            b'1' | b'I' | b'L' => 1,
+            b'2'..=b'9' => c - b'0',
+            b'A'..=b'H' => c.to_ascii_uppercase() - b'A' + 10,
+            b'J' | b'K' => c.to_ascii_uppercase() - b'J' + 18,
+            b'M' | b'N' => c.to_ascii_uppercase() - b'M' + 20,
+            b'P'..=b'T' => c.to_ascii_uppercase() - b'P' + 22,
+            b'V'..=b'Z' => c.to_ascii_uppercase() - b'V' + 27,
+            _ => return None
+        };
+        value = value.checked_mul(32)?.checked_add(digit as u128)?;
+    }
+
+    Some(value)
+}
@@ -0,0 +1,191 @@
+//! Hybrid logical clocks for buffers
+//!
+//! Once `NP_Buffer::track_version` is called, every change to the buffer moves its version forward.  The version is a hybrid logical clock: the wall time from your clock, a logical counter for changes made during the same tick (or while the clock runs behind), and the id of the node that made the change.
+//!
+//! Versions only ever go up and compare in that order, so stores built on NoProto can use them to order replicas of a record or detect that a record changed since it was read.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::version::NP_Version;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! assert_eq!(new_buffer.version(), None);
+//!
+//! // any function returning the current time works, like milliseconds since the unix epoch
+//! new_buffer.track_version(7, || 1_607_000_000_000)?;
+//!
+//! new_buffer.set(&["name"], "Jeb Kermin")?;
+//! new_buffer.set(&["age"], 30u8)?;
+//! assert_eq!(new_buffer.version(), Some(NP_Version { wall: 1_607_000_000_000, logical: 1, node: 7 }));
+//!
+//! // the version is saved with the buffer
+//! let opened = factory.open_buffer(new_buffer.close());
+//! assert_eq!(opened.version(), Some(NP_Version { wall: 1_607_000_000_000, logical: 1, node: 7 }));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! The version is 16 bytes stored right after the root pointer, or after the tombstone pointer if the buffer has one:
+//!
+//! | Bytes   | Contents                |
+//! |---------|-------------------------|
+//! | 0 - 7   | Wall time               |
+//! | 8 - 11  | Logical counter         |
+//! | 12 - 15 | Node id                 |
+//!
+
+use crate::error::NP_Error;
+use crate::memory::NP_Memory;
+use crate::tombstone;
+
+pub(crate) const VERSION_LEN: usize = 16;
+
+/// A hybrid logical clock, returned from `NP_Buffer::version`
+///
+/// Versions are ordered by wall time, then logical counter, then node id.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Default)]
+pub struct NP_Version {
+    /// Largest time seen from the clock passed to `track_version`
+    pub wall: u64,
+    /// Changes made since the wall time last moved forward
+    pub logical: u32,
+    /// Id of the node passed to `track_version`
+    pub node: u32
+}
+
+impl NP_Version {
+
+    /// The version after a local change at time `now`.
+    pub fn tick(&self, now: u64) -> NP_Version {
+        if now > self.wall {
+            NP_Version { wall: now, logical: 0, node: self.node }
+        } else {
+            next(self.wall, self.logical, self.node)
+        }
+    }
+
+    /// The version after seeing a `remote` version at time `now`, it's larger than both versions.  The node id is kept from `self`.
+    pub fn receive(&self, remote: &NP_Version, now: u64) -> NP_Version {
+        let wall = core::cmp::max(now, core::cmp::max(self.wall, remote.wall));
+        if wall == self.wall && wall == remote.wall {
+            next(wall, core::cmp::max(self.logical, remote.logical), self.node)
+        } else if wall == self.wall {
+            next(wall, self.logical, self.node)
+        } else if wall == remote.wall {
+            next(wall, remote.logical, self.node)
+        } else {
+            NP_Version { wall, logical: 0, node: self.node }
+        }
+    }
+}
+
+// the logical counter running out moves the wall time forward instead
+fn next(wall: u64, logical: u32, node: u32) -> NP_Version {
+    match logical.checked_add(1) {
+        Some(logical) => NP_Version { wall, logical, node },
+        None => NP_Version { wall: wall.saturating_add(1), logical: 0, node }
+    }
+}
+
+// after the root pointer and the tombstone pointer
+fn address(memory: &NP_Memory) -> usize {
+    tombstone::HEAD_ADDR + if memory.has_tombstones() { 2 } else { 0 }
+}
+
+/// The version of the buffer, if it has one
+pub(crate) fn read(memory: &NP_Memory) -> Option<NP_Version> {
+    let address = address(memory);
+    let bytes = memory.read_bytes();
+    if !memory.has_version() || bytes.len() < address + VERSION_LEN {
+        return None;
+    }
+
+    let mut wall = [0u8; 8];
+    wall.copy_from_slice(&bytes[address..(address + 8)]);
+    let mut logical = [0u8; 4];
+    logical.copy_from_slice(&bytes[(address + 8)..(address + 12)]);
+    let mut node = [0u8; 4];
+    node.copy_from_slice(&bytes[(address + 12)..(address + 16)]);
+    Some(NP_Version { wall: u64::from_be_bytes(wall), logical: u32::from_be_bytes(logical), node: u32::from_be_bytes(node) })
+}
+
+/// Replace the version of a buffer that has one
+pub(crate) fn write(memory: &NP_Memory, version: &NP_Version) {
+    let address = address(memory);
+    if !memory.has_version() || memory.read_bytes().len() < address + VERSION_LEN {
+        return;
+    }
+
+    let bytes = memory.write_bytes();
+    bytes[address..(address + 8)].copy_from_slice(&version.wall.to_be_bytes());
+    bytes[(address + 8)..(address + 12)].copy_from_slice(&version.logical.to_be_bytes());
+    bytes[(address + 12)..(address + 16)].copy_from_slice(&version.node.to_be_bytes());
+}
+
+/// Bytes used by the version
+pub(crate) fn size(memory: &NP_Memory) -> usize {
+    if memory.has_version() { VERSION_LEN } else { 0 }
+}
+
+/// Copy the version into a new buffer that has only its root pointer (and tombstone pointer), used by compaction.
+pub(crate) fn copy(from: &NP_Memory, to: &NP_Memory) -> Result<(), NP_Error> {
+    to.start_version()?;
+    write(to, &read(from).unwrap_or_default());
+    Ok(())
+}
+
+#[test]
+fn version_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+
+    let version = NP_Version { wall: 10, logical: 2, node: 1 };
+    assert_eq!(version.tick(9), NP_Version { wall: 10, logical: 3, node: 1 });
+    assert_eq!(version.tick(11), NP_Version { wall: 11, logical: 0, node: 1 });
+    assert_eq!(NP_Version { wall: 10, logical: u32::MAX, node: 1 }.tick(10), NP_Version { wall: 11, logical: 0, node: 1 });
+    assert_eq!(version.receive(&NP_Version { wall: 10, logical: 5, node: 2 }, 3), NP_Version { wall: 10, logical: 6, node: 1 });
+    assert_eq!(version.receive(&NP_Version { wall: 12, logical: 5, node: 2 }, 3), NP_Version { wall: 12, logical: 6, node: 1 });
+    assert_eq!(version.receive(&NP_Version { wall: 12, logical: 5, node: 2 }, 20), NP_Version { wall: 20, logical: 0, node: 1 });
+    assert!(NP_Version { wall: 10, logical: 3, node: 0 } > version);
+
+    // tracking a buffer with values and tombstones moves them to make room
+    let mut buffer = factory.empty_buffer(None);
+    buffer.track_deletes(|| 4)?;
+    buffer.set(&["0"], "hello")?;
+    buffer.del(&["0"])?;
+    buffer.track_version(3, || 100)?;
+    assert_eq!(buffer.version(), Some(NP_Version { wall: 0, logical: 0, node: 3 }));
+    assert_eq!(buffer.tombstone(&["0"]), Some(4));
+
+    // failed changes leave the version alone
+    buffer.list_push(&[], "world")?;
+    assert!(buffer.set(&["1"], 2u8).is_err());
+    assert!(buffer.list_push(&[], 2u8).is_err());
+    assert_eq!(buffer.version(), Some(NP_Version { wall: 100, logical: 0, node: 3 }));
+
+    buffer.set_with_json(&["1"], r#""again""#)?;
+    buffer.compact(None)?;
+    assert_eq!(buffer.version(), Some(NP_Version { wall: 100, logical: 1, node: 3 }));
+    assert_eq!(buffer.tombstone(&["0"]), Some(4));
+    assert_eq!(buffer.get::<&str>(&["1"])?, Some("again"));
+    assert_eq!(buffer.calc_bytes()?.wasted_bytes, 0);
+    assert_eq!(buffer.memory_stats()?.wasted_bytes, 0);
+
+    // merged buffers get a version after both
+    let mut other = factory.empty_buffer(None);
+    other.track_version(9, || 150)?;
+    other.set(&["0"], "there")?;
+    let merged = crate::buffer::NP_Buffer::crdt_merge(&buffer, &other)?;
+    assert_eq!(merged.version(), Some(NP_Version { wall: 150, logical: 1, node: 3 }));
+    assert_eq!(merged.tombstone(&["0"]), Some(4));
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! Restricted views of buffers
+//!
+//! An `NP_FieldMask` is a list of paths checked against a schema.  `NP_Buffer::view` uses a mask to make an `NP_Buffer_View`, a handle that can only read and write values at or below those paths.  Hand views to plugins or scripting layers that should only see part of a record.
+//!
+//! Paths in a mask can use `*` in place of a list, log or map key to allow every item.  Using a path outside of the view returns `NP_Error::AccessDenied`, and `json_encode` leaves out everything the view can't see.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::view::NP_FieldMask;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",    {"type": "string"}],
+//!         ["salary",  {"type": "u32"}],
+//!         ["friends", {"type": "list", "of": {"type": "table", "columns": [
+//!             ["name",  {"type": "string"}],
+//!             ["phone", {"type": "string"}]
+//!         ]}}]
+//!     ]
+//! }"#)?;
+//!
+//! let mask = NP_FieldMask::new(&factory.schema, &[&["name"], &["friends", "*", "name"]])?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["salary"], 50_000u32)?;
+//! new_buffer.set(&["friends", "0", "phone"], "555-1234")?;
+//!
+//! let mut view = new_buffer.view(&mask);
+//! view.set(&["name"], "Jeb Kermin")?;
+//! view.set(&["friends", "0", "name"], "Bill")?;
+//!
+//! // everything else is off limits
+//! assert_eq!(view.get::<u32>(&["salary"]), Err(NP_Error::AccessDenied { path: vec!["salary".to_owned()] }));
+//! assert!(view.set(&["friends", "0", "phone"], "555-0000").is_err());
+//! assert!(view.del(&["friends"]).is_err());
+//!
+//! assert_eq!(view.json_encode(&[])?.stringify(), r#"{"name":"Jeb Kermin","friends":[{"name":"Bill"}]}"#);
+//!
+//! // the buffer itself still has every value
+//! assert_eq!(new_buffer.get::<u32>(&["salary"])?, Some(50_000));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::json_flex::NP_JSON;
+use crate::pointer::{NP_Scalar, NP_Value};
+use crate::mask::NP_Mask;
+use alloc::string::ToString;
+
+/// A list of paths a buffer view can access, use with `NP_Buffer::view`.  Field masks are `NP_Mask`s, see the [mask](../mask/index.html) module.
+pub type NP_FieldMask = NP_Mask;
+
+/// A buffer that can only be used at the paths of an `NP_FieldMask`, get one with `NP_Buffer::view`.
+///
+/// Paths are always relative to the root of the buffer.
+///
+pub struct NP_Buffer_View<'view, 'buffer> {
+    buffer: &'view mut NP_Buffer<'buffer>,
+    mask: &'view NP_FieldMask
+}
+
+impl<'view, 'buffer> NP_Buffer_View<'view, 'buffer> {
+
+    #[doc(hidden)]
+    pub fn _new(buffer: &'view mut NP_Buffer<'buffer>, mask: &'view NP_FieldMask) -> Self {
+        NP_Buffer_View { buffer, mask }
+    }
+
+    fn check(&self, path: &[&str]) -> Result<(), NP_Error> {
+        if self.mask.allows(path) {
+            Ok(())
+        } else {
+            Err(NP_Error::AccessDenied { path: path.iter().map(|key| key.to_string()).collect() })
+        }
+    }
+
+    /// If this view can access the value at `path`
+    pub fn allows(&self, path: &[&str]) -> bool {
+        self.mask.allows(path)
+    }
+
+    /// Get a value from the buffer, same as `NP_Buffer::get`
+    pub fn get<'get, X>(&'get self, path: &[&str]) -> Result<Option<X>, NP_Error> where X: 'get + NP_Value<'get> + NP_Scalar {
+        self.check(path)?;
+        self.buffer.get(path)
+    }
+
+    /// Set a value in the buffer, same as `NP_Buffer::set`
+    pub fn set<X>(&mut self, path: &[&str], value: X) -> Result<bool, NP_Error> where X: 'buffer + NP_Value<'buffer> + NP_Scalar {
+        self.check(path)?;
+        self.buffer.set(path, value)
+    }
+
+    /// Delete a value from the buffer, same as `NP_Buffer::del`
+    pub fn del(&mut self, path: &[&str]) -> Result<bool, NP_Error> {
+        self.check(path)?;
+        self.buffer.del(path)
+    }
+
+    /// Get the length of a collection or string, same as `NP_Buffer::length`
+    pub fn length(&self, path: &[&str]) -> Result<Option<usize>, NP_Error> {
+        self.check(path)?;
+        self.buffer.length(path)
+    }
+
+    /// Copy the value at `path` into JSON, leaving out everything this view can't access.
+    ///
+    /// Table columns and map keys outside the view are left out, list items outside the view are `null`.  Returns `NP_Error::AccessDenied` if nothing at or below `path` is in the view.
+    ///
+    pub fn json_encode(&self, path: &[&str]) -> Result<NP_JSON, NP_Error> {
+        match self.mask.prune(self.buffer.json_encode(path)?, path) {
+            Some(json) => Ok(json),
+            None => Err(NP_Error::AccessDenied { path: path.iter().map(|key| key.to_string()).collect() })
+        }
+    }
+}
+
+#[test]
+fn view_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["id",    {"type": "u32"}],
+        ["tags",  {"type": "map", "value": {"type": "tuple", "values": [{"type": "string"}, {"type": "u8"}]}}],
+        ["notes", {"type": "list", "of": {"type": "string"}}]
+    ]}"#)?;
+
+    let mask = NP_FieldMask::new(&factory.schema, &[&["tags", "*", "0"], &["notes", "1"]])?;
+    assert!(mask.allows(&["tags", "red", "0"]));
+    assert!(!mask.allows(&["tags", "red"]));
+    assert!(!mask.allows(&["notes", "0"]));
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["id"], 4u32)?;
+    buffer.set(&["tags", "red", "1"], 2u8)?;
+    buffer.set(&["notes", "0"], "hidden")?;
+    buffer.move_cursor(&["notes"])?;
+
+    let mut view = buffer.view(&mask);
+    view.set(&["tags", "red", "0"], "crimson")?;
+    view.set(&["notes", "1"], "shown")?;
+    assert_eq!(view.get::<&str>(&["notes", "1"])?, Some("shown"));
+    assert_eq!(view.length(&["notes"]), Err(NP_Error::AccessDenied { path: ["notes".to_string()].to_vec() }));
+    assert_eq!(view.json_encode(&[])?.stringify(), r#"{"tags":{"red":["crimson",null]},"notes":[null,"shown"]}"#);
+    assert_eq!(view.json_encode(&["notes"])?.stringify(), r#"[null,"shown"]"#);
+    assert!(view.json_encode(&["id"]).is_err());
+    assert!(view.del(&["notes", "1"])?);
+
+    assert_eq!(buffer.get::<&str>(&["notes", "0"])?, Some("hidden"));
+
+    Ok(())
+}
@@ -0,0 +1,265 @@
+//! Write-ahead logs for buffers, enabled with the `wal` feature
+//!
+//! An `NP_Wal` journals every change to a buffer into any `std::io::Write` (usually a file opened for appending) before making the change.  After a crash, `replay` applies the journal to the last saved copy of the buffer to get back every change that was made since.
+//!
+//! Changes are recorded as `NP_Patch` values, a path and the JSON value set there or a delete.  The log is flushed after every record, so once `set_with_json`, `set_json_value` or `del` returns the change survives a crash.  A record that was only partly written when the crash happened is ignored during replay along with anything after it.
+//!
+//! To checkpoint, save `buffer.close()` (or a copy of the bytes) and start a new, empty log.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::wal::{NP_Wal, replay};
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! // the last saved copy of the buffer
+//! let saved: Vec<u8> = factory.empty_buffer(None).close();
+//!
+//! let mut buffer = factory.open_buffer(saved.clone());
+//! let mut wal = NP_Wal::new(Vec::new());
+//! wal.set_with_json(&mut buffer, &["name"], r#""Jeb Kermin""#)?;
+//! wal.set_with_json(&mut buffer, &["age"], "30")?;
+//! wal.del(&mut buffer, &["age"])?;
+//! let log: Vec<u8> = wal.into_inner();
+//!
+//! // ... crash, then get the changes back from the saved copy and the log
+//! let mut recovered = factory.open_buffer(saved);
+//! assert_eq!(replay(&mut recovered, log.as_slice())?, 3);
+//! assert_eq!(recovered.get::<&str>(&["name"])?, Some("Jeb Kermin"));
+//! assert_eq!(recovered.get::<u8>(&["age"])?, None);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! Each record in the log is laid out like this, every number is big endian:
+//!
+//! | Bytes    | Contents                                                 |
+//! |----------|----------------------------------------------------------|
+//! | 0 - 3    | Length of the patch bytes (u32)                          |
+//! | 4 - 7    | Hash of the patch bytes (u32)                            |
+//! | 8        | `1` for a set, `2` for a delete                          |
+//! | 9 - 10   | Number of path segments (u16)                            |
+//! | ...      | Path segments, each a length (u16) and UTF8 bytes        |
+//! | ...      | Sets only, the value as JSON text                        |
+//!
+
+extern crate std;
+
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::hashmap::{murmurhash3_x86_32, SEED};
+use crate::json_flex::{json_decode, NP_JSON};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use std::io::{Read, Write};
+
+const SET: u8 = 1;
+const DELETE: u8 = 2;
+
+/// A change to a buffer, as stored in the log
+#[derive(Debug)]
+pub enum NP_Patch {
+    /// Set the value at the path, like `NP_Buffer::set_json_value`
+    Set {
+        /// Path to the value
+        path: Vec<String>,
+        /// The new value
+        value: NP_JSON
+    },
+    /// Delete the value at the path, like `NP_Buffer::del`
+    Delete {
+        /// Path to the value
+        path: Vec<String>
+    }
+}
+
+impl NP_Patch {
+
+    /// Apply this patch to a buffer
+    pub fn apply(&self, buffer: &mut NP_Buffer) -> Result<bool, NP_Error> {
+        match self {
+            NP_Patch::Set { path, value } => {
+                let path: Vec<&str> = path.iter().map(|segment| segment.as_str()).collect();
+                buffer.set_json_value(&path, value)
+            },
+            NP_Patch::Delete { path } => {
+                let path: Vec<&str> = path.iter().map(|segment| segment.as_str()).collect();
+                buffer.del(&path)
+            }
+        }
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, NP_Error> {
+        let (kind, path) = match self {
+            NP_Patch::Set { path, .. } => (SET, path),
+            NP_Patch::Delete { path } => (DELETE, path)
+        };
+        if path.len() > u16::MAX as usize || path.iter().any(|segment| segment.len() > u16::MAX as usize) {
+            return Err(NP_Error::new_static("Path is too long for the write-ahead log!"));
+        }
+
+        let mut bytes: Vec<u8> = alloc::vec![kind];
+        bytes.extend_from_slice(&(path.len() as u16).to_be_bytes());
+        for segment in path.iter() {
+            bytes.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(segment.as_bytes());
+        }
+        if let NP_Patch::Set { value, .. } = self {
+            bytes.extend_from_slice(value.stringify().as_bytes());
+        }
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, NP_Error> {
+        let corrupt = || NP_Error::new_static("Write-ahead log record is corrupt!");
+
+        let mut pos = 3usize;
+        let segments = u16::from_be_bytes([*bytes.get(1).ok_or_else(corrupt)?, *bytes.get(2).ok_or_else(corrupt)?]);
+        let mut path: Vec<String> = Vec::with_capacity(segments as usize);
+        for _ in 0..segments {
+            let len = bytes.get(pos..(pos + 2)).ok_or_else(corrupt)?;
+            let len = u16::from_be_bytes([len[0], len[1]]) as usize;
+            let segment = bytes.get((pos + 2)..(pos + 2 + len)).ok_or_else(corrupt)?;
+            path.push(core::str::from_utf8(segment).map_err(|_| corrupt())?.to_string());
+            pos += 2 + len;
+        }
+
+        match bytes[0] {
+            SET => {
+                let json = core::str::from_utf8(&bytes[pos..]).map_err(|_| corrupt())?;
+                Ok(NP_Patch::Set { path, value: *json_decode(String::from(json))? })
+            },
+            DELETE => Ok(NP_Patch::Delete { path }),
+            _ => Err(corrupt())
+        }
+    }
+}
+
+/// Journals changes to buffers into a log, see the [wal](index.html) module.
+#[derive(Debug)]
+pub struct NP_Wal<W: Write> {
+    log: W
+}
+
+impl<W: Write> NP_Wal<W> {
+
+    /// Start journaling into `log`, new records are written after anything already in it.
+    pub fn new(log: W) -> Self {
+        NP_Wal { log }
+    }
+
+    /// Journal a patch, then apply it to the buffer.
+    ///
+    /// If the log can't be written the buffer isn't changed.  Patches that fail to apply stay in the log, they fail the same way during replay.
+    ///
+    pub fn apply(&mut self, buffer: &mut NP_Buffer, patch: &NP_Patch) -> Result<bool, NP_Error> {
+        let bytes = patch.to_bytes()?;
+        if bytes.len() > u32::MAX as usize {
+            return Err(NP_Error::OutOfSpace);
+        }
+
+        let mut record: Vec<u8> = Vec::with_capacity(bytes.len() + 8);
+        record.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+        record.extend_from_slice(&murmurhash3_x86_32(&bytes, SEED).to_be_bytes());
+        record.extend_from_slice(&bytes);
+        self.log.write_all(&record).map_err(|err| NP_Error::new(err.to_string()))?;
+        self.log.flush().map_err(|err| NP_Error::new(err.to_string()))?;
+
+        patch.apply(buffer)
+    }
+
+    /// Journal and make the same change as `NP_Buffer::set_json_value`
+    pub fn set_json_value(&mut self, buffer: &mut NP_Buffer, path: &[&str], value: &NP_JSON) -> Result<bool, NP_Error> {
+        let path = path.iter().map(|segment| segment.to_string()).collect();
+        self.apply(buffer, &NP_Patch::Set { path, value: value.clone() })
+    }
+
+    /// Journal and make the same change as `NP_Buffer::set_with_json`, invalid JSON is never journaled.
+    pub fn set_with_json(&mut self, buffer: &mut NP_Buffer, path: &[&str], json: &str) -> Result<bool, NP_Error> {
+        let value = json_decode(String::from(json))?;
+        self.set_json_value(buffer, path, &value)
+    }
+
+    /// Journal and make the same change as `NP_Buffer::del`
+    pub fn del(&mut self, buffer: &mut NP_Buffer, path: &[&str]) -> Result<bool, NP_Error> {
+        let path = path.iter().map(|segment| segment.to_string()).collect();
+        self.apply(buffer, &NP_Patch::Delete { path })
+    }
+
+    /// Get the log back
+    pub fn into_inner(self) -> W {
+        self.log
+    }
+}
+
+/// Read every complete patch from a log, stopping at the first record that was only partly written or doesn't match its hash.
+pub fn read_log<R: Read>(mut log: R) -> Result<Vec<NP_Patch>, NP_Error> {
+    let mut bytes: Vec<u8> = Vec::new();
+    log.read_to_end(&mut bytes).map_err(|err| NP_Error::new(err.to_string()))?;
+
+    let mut patches: Vec<NP_Patch> = Vec::new();
+    let mut pos = 0usize;
+    while let Some(header) = bytes.get(pos..(pos + 8)) {
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let hash = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        match bytes.get((pos + 8)..(pos + 8 + len)) {
+            Some(record) if murmurhash3_x86_32(record, SEED) == hash => {
+                patches.push(NP_Patch::from_bytes(record)?);
+                pos += 8 + len;
+            },
+            _ => break
+        }
+    }
+    Ok(patches)
+}
+
+/// Apply every complete patch in a log to a buffer, returns how many patches were in the log.
+///
+/// Patches that fail to apply are skipped, they failed when they were first made too.
+///
+pub fn replay<R: Read>(buffer: &mut NP_Buffer, log: R) -> Result<usize, NP_Error> {
+    let patches = read_log(log)?;
+    for patch in patches.iter() {
+        let _ = patch.apply(buffer);
+    }
+    Ok(patches.len())
+}
+
+#[test]
+fn wal_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "map", "value": {"type": "list", "of": {"type": "u32"}}}"#)?;
+    let saved = factory.empty_buffer(None).close();
+
+    let mut buffer = factory.open_buffer(saved.clone());
+    let mut wal = NP_Wal::new(Vec::new());
+    wal.set_with_json(&mut buffer, &["a"], "[1, 2, 3]")?;
+    wal.set_with_json(&mut buffer, &["b", "1"], "7")?;
+    wal.del(&mut buffer, &["a", "1"])?;
+    assert!(wal.set_with_json(&mut buffer, &["c"], "[1,").is_err());
+    assert!(wal.set_with_json(&mut buffer, &["c", "0"], r#""text""#).is_err());
+    let log = wal.into_inner();
+
+    let mut replayed = factory.open_buffer(saved.clone());
+    assert_eq!(replay(&mut replayed, log.as_slice())?, 4);
+    assert_eq!(replayed.json_encode(&[])?.stringify(), buffer.json_encode(&[])?.stringify());
+    assert_eq!(replayed.json_encode(&[])?.stringify(), r#"{"c":[null],"b":[null,7],"a":[1,null,3]}"#);
+
+    // torn and corrupt records end the log
+    for cut in 1..9 {
+        let patches = read_log(&log[..(log.len() - cut)])?;
+        assert_eq!(patches.len(), 3);
+    }
+    let mut corrupt = log.clone();
+    corrupt[10] ^= 0xFF;
+    assert_eq!(read_log(corrupt.as_slice())?.len(), 0);
+    assert_eq!(read_log(&log[..0])?.len(), 0);
+
+    Ok(())
+}
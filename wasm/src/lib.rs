@@ -0,0 +1,136 @@
+//! WebAssembly bindings for NoProto
+//! 
+//! Build with `wasm-pack build` from this folder.  Values are passed in and out as JSON strings, and buffers are passed as `Uint8Array`.
+//! 
+//! ```js
+//! import { Factory } from "no_proto-wasm";
+//! 
+//! const factory = new Factory(`{
+//!     "type": "table",
+//!     "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }`);
+//! 
+//! const buffer = factory.empty_buffer();
+//! buffer.set(["name"], `"Jeb Kermin"`);
+//! buffer.set(["tags"], `["pilot"]`);
+//! 
+//! const bytes = buffer.close(); // Uint8Array
+//! 
+//! const opened = factory.open_buffer(bytes);
+//! console.log(opened.get(["name"])); // "Jeb Kermin"
+//! ```
+//! 
+
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use no_proto::NP_Factory;
+use no_proto::buffer::NP_Buffer;
+use no_proto::error::NP_Error;
+
+fn js_error(error: NP_Error) -> JsValue {
+    JsValue::from_str(&error.to_string())
+}
+
+fn js_path(path: &[JsValue]) -> Result<Vec<String>, JsValue> {
+    path.iter().map(|key| {
+        match key.as_string() {
+            Some(x) => Ok(x),
+            None => match key.as_f64() {
+                Some(x) => Ok((x as usize).to_string()),
+                None => Err(JsValue::from_str("Path keys must be strings or numbers!"))
+            }
+        }
+    }).collect()
+}
+
+/// Factory built from a JSON schema
+#[wasm_bindgen]
+pub struct Factory {
+    factory: Rc<NP_Factory>
+}
+
+#[wasm_bindgen]
+impl Factory {
+
+    /// Parse a JSON schema into a new factory
+    #[wasm_bindgen(constructor)]
+    pub fn new(schema: &str) -> Result<Factory, JsValue> {
+        Ok(Factory { factory: Rc::new(NP_Factory::new(schema).map_err(js_error)?) })
+    }
+
+    /// Create a new empty buffer
+    pub fn empty_buffer(&self) -> Buffer {
+        Buffer {
+            factory: Rc::clone(&self.factory),
+            bytes: self.factory.empty_buffer(None).close()
+        }
+    }
+
+    /// Open existing buffer bytes, the bytes are checked before they're used
+    pub fn open_buffer(&self, bytes: Vec<u8>) -> Result<Buffer, JsValue> {
+        let bytes = self.factory.open_checked_buffer(bytes).map_err(js_error)?.close();
+        Ok(Buffer {
+            factory: Rc::clone(&self.factory),
+            bytes
+        })
+    }
+}
+
+/// Buffer holding NoProto bytes for a factory
+#[wasm_bindgen]
+pub struct Buffer {
+    factory: Rc<NP_Factory>,
+    bytes: Vec<u8>
+}
+
+impl Buffer {
+    fn with_buffer<T, F>(&mut self, callback: F) -> Result<T, JsValue> where F: FnOnce(&mut NP_Buffer) -> Result<T, NP_Error> {
+        let mut buffer = self.factory.open_buffer(std::mem::take(&mut self.bytes));
+        let result = callback(&mut buffer);
+        self.bytes = buffer.close();
+        result.map_err(js_error)
+    }
+}
+
+#[wasm_bindgen]
+impl Buffer {
+
+    /// Set the value at `path` from a JSON string, returns false if the path doesn't exist
+    pub fn set(&mut self, path: Box<[JsValue]>, json: &str) -> Result<bool, JsValue> {
+        let path = js_path(&path)?;
+        let path: Vec<&str> = path.iter().map(|key| key.as_str()).collect();
+        self.with_buffer(|buffer| buffer.set_with_json(&path, json))
+    }
+
+    /// Get the value at `path` as a JSON string
+    pub fn get(&mut self, path: Box<[JsValue]>) -> Result<String, JsValue> {
+        let path = js_path(&path)?;
+        let path: Vec<&str> = path.iter().map(|key| key.as_str()).collect();
+        self.with_buffer(|buffer| Ok(buffer.json_encode(&path)?.stringify()))
+    }
+
+    /// Delete the value at `path`
+    pub fn del(&mut self, path: Box<[JsValue]>) -> Result<bool, JsValue> {
+        let path = js_path(&path)?;
+        let path: Vec<&str> = path.iter().map(|key| key.as_str()).collect();
+        self.with_buffer(|buffer| buffer.del(&path))
+    }
+
+    /// Remove unused space from the buffer
+    pub fn compact(&mut self) -> Result<(), JsValue> {
+        self.with_buffer(|buffer| buffer.compact(None))
+    }
+
+    /// Copy of the current buffer bytes
+    pub fn read_bytes(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    /// Consume the buffer and return it's bytes
+    pub fn close(self) -> Vec<u8> {
+        self.bytes
+    }
+}